@@ -132,6 +132,63 @@ impl std::fmt::Display for Value {
     }
 }
 
+/// The generic value type of the ICRC-3 standard.
+/// Unlike [`Value`], it has no `Nat64` variant: 64-bit naturals are represented as `Nat`,
+/// which does not change the hash of the value.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ICRC3Value {
+    Blob(ByteBuf),
+    Text(String),
+    Nat(Nat),
+    Int(Int),
+    Array(Vec<ICRC3Value>),
+    Map(BTreeMap<String, ICRC3Value>),
+}
+
+impl ICRC3Value {
+    /// Computes the representation-independent hash of a value.
+    pub fn hash(&self) -> Hash {
+        Value::from(self.clone()).hash()
+    }
+}
+
+impl From<Value> for ICRC3Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Blob(bytes) => ICRC3Value::Blob(bytes),
+            Value::Text(text) => ICRC3Value::Text(text),
+            Value::Nat(nat) => ICRC3Value::Nat(nat),
+            Value::Nat64(nat64) => ICRC3Value::Nat(Nat::from(nat64)),
+            Value::Int(int) => ICRC3Value::Int(int),
+            Value::Array(values) => {
+                ICRC3Value::Array(values.into_iter().map(ICRC3Value::from).collect())
+            }
+            Value::Map(map) => ICRC3Value::Map(
+                map.into_iter()
+                    .map(|(k, v)| (k, ICRC3Value::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<ICRC3Value> for Value {
+    fn from(value: ICRC3Value) -> Self {
+        match value {
+            ICRC3Value::Blob(bytes) => Value::Blob(bytes),
+            ICRC3Value::Text(text) => Value::Text(text),
+            ICRC3Value::Nat(nat) => Value::Nat(nat),
+            ICRC3Value::Int(int) => Value::Int(int),
+            ICRC3Value::Array(values) => {
+                Value::Array(values.into_iter().map(Value::from).collect())
+            }
+            ICRC3Value::Map(map) => {
+                Value::Map(map.into_iter().map(|(k, v)| (k, Value::from(v))).collect())
+            }
+        }
+    }
+}
+
 /// Encodes a 128-bit integer using unsigned LEB-128 encoding.
 /// Returns the index of the last valid byte in the buffer.
 fn leb128(buf: &mut [u8; INT128_BUF_SIZE], v: u128) -> usize {
@@ -221,3 +278,26 @@ fn test_sleb128() {
         assert_eq!(&buf[0..=i], b, "invalid encoding of integer {}", n);
     }
 }
+
+#[test]
+fn icrc3_value_should_have_the_same_hash_as_value() {
+    let value = Value::map(vec![
+        ("ts", Value::Nat64(1_677_770_607_672_807_382)),
+        ("fee", Value::Nat(Nat::from(10_000_u64))),
+        (
+            "tx",
+            Value::map(vec![
+                ("op", Value::text("xfer")),
+                ("amt", Value::Nat64(u64::MAX)),
+                ("from", Value::Array(vec![Value::blob(vec![1, 2, 3])])),
+            ]),
+        ),
+    ]);
+    let icrc3_value = ICRC3Value::from(value.clone());
+
+    assert_eq!(icrc3_value.hash(), value.hash());
+    assert_eq!(
+        ICRC3Value::from(Value::from(icrc3_value.clone())),
+        icrc3_value
+    );
+}
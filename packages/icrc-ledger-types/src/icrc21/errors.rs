@@ -0,0 +1,42 @@
+use candid::{CandidType, Deserialize, Nat};
+use std::fmt;
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ErrorInfo {
+    pub description: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Icrc21Error {
+    /// The canister does not produce consent messages for the requested call.
+    UnsupportedCanisterCall(ErrorInfo),
+    /// The canister could not produce a consent message for the call, e.g.,
+    /// because the argument was invalid.
+    ConsentMessageUnavailable(ErrorInfo),
+    /// The call requires a payment that the caller did not provide.
+    InsufficientPayment(ErrorInfo),
+    GenericError {
+        error_code: Nat,
+        description: String,
+    },
+}
+
+impl fmt::Display for Icrc21Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedCanisterCall(info) => {
+                write!(f, "unsupported canister call: {}", info.description)
+            }
+            Self::ConsentMessageUnavailable(info) => {
+                write!(f, "consent message unavailable: {}", info.description)
+            }
+            Self::InsufficientPayment(info) => {
+                write!(f, "insufficient payment: {}", info.description)
+            }
+            Self::GenericError {
+                error_code,
+                description,
+            } => write!(f, "error {}: {}", error_code, description),
+        }
+    }
+}
@@ -0,0 +1,471 @@
+use super::errors::{ErrorInfo, Icrc21Error};
+use super::requests::{ConsentMessageMetadata, ConsentMessageRequest, DisplayMessageType};
+use super::responses::{ConsentInfo, ConsentMessage, LineDisplayPage};
+use crate::icrc1::account::Account;
+use crate::icrc1::transfer::{Memo, TransferArg};
+use crate::icrc2::approve::ApproveArgs;
+use crate::icrc2::transfer_from::TransferFromArgs;
+use candid::{Decode, Nat, Principal};
+use std::fmt::Write;
+
+/// The only language in which consent messages are currently available.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A building block of a consent message.
+enum Section {
+    /// A paragraph of free text.
+    Text(String),
+    /// A labelled value, such as an amount or an account.
+    Field { label: String, value: String },
+}
+
+/// A consent message independent of the device it is displayed on.
+struct Message {
+    title: String,
+    sections: Vec<Section>,
+}
+
+impl Message {
+    fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            sections: vec![],
+        }
+    }
+
+    fn text(mut self, text: impl Into<String>) -> Self {
+        self.sections.push(Section::Text(text.into()));
+        self
+    }
+
+    fn field(mut self, label: impl Into<String>, value: impl Into<String>) -> Self {
+        self.sections.push(Section::Field {
+            label: label.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    fn render_generic_display(&self) -> String {
+        let mut message = format!("# {}", self.title);
+        for section in &self.sections {
+            match section {
+                Section::Text(text) => write!(message, "\n\n{}", text),
+                Section::Field { label, value } => {
+                    write!(message, "\n\n**{}:**\n`{}`", label, value)
+                }
+            }
+            .expect("writing to a string never fails");
+        }
+        message
+    }
+
+    fn render_line_display(
+        &self,
+        characters_per_line: u16,
+        lines_per_page: u16,
+    ) -> Result<Vec<LineDisplayPage>, Icrc21Error> {
+        if characters_per_line == 0 || lines_per_page == 0 {
+            return Err(Icrc21Error::ConsentMessageUnavailable(ErrorInfo {
+                description: format!(
+                    "cannot render a message on a display with {} characters per line and {} lines per page",
+                    characters_per_line, lines_per_page
+                ),
+            }));
+        }
+        let width = characters_per_line as usize;
+        let mut lines = wrap(&self.title, width);
+        for section in &self.sections {
+            match section {
+                Section::Text(text) => lines.extend(wrap(text, width)),
+                Section::Field { label, value } => {
+                    lines.extend(wrap(&format!("{}:", label), width));
+                    lines.extend(wrap(value, width));
+                }
+            }
+        }
+        Ok(lines
+            .chunks(lines_per_page as usize)
+            .map(|lines| LineDisplayPage {
+                lines: lines.to_vec(),
+            })
+            .collect())
+    }
+}
+
+/// Splits the text into lines of at most `width` characters, breaking at
+/// whitespace when possible and splitting words that do not fit on a line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let line_len = line.chars().count();
+        if line_len > 0 && line_len + 1 + word.len() <= width {
+            line.push(' ');
+            line.extend(word);
+            continue;
+        }
+        if line_len > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Formats a token amount given in the smallest units using the token's
+/// number of decimals, e.g., 150_000_000 with 8 decimals becomes "1.5".
+pub fn format_amount(amount: &Nat, decimals: u8) -> String {
+    let digits = amount.0.to_str_radix(10);
+    let decimals = decimals as usize;
+    let (integer, fraction) = if digits.len() > decimals {
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        (integer.to_string(), fraction.to_string())
+    } else {
+        (
+            "0".to_string(),
+            format!("{:0>width$}", digits, width = decimals),
+        )
+    };
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// Formats a timestamp in nanoseconds since the Unix epoch as a date and time
+/// in the time zone with the given offset relative to UTC.
+pub fn format_timestamp(timestamp_nanos: u64, utc_offset_minutes: Option<i16>) -> String {
+    let offset_minutes = utc_offset_minutes.unwrap_or(0) as i64;
+    let seconds = (timestamp_nanos / NANOS_PER_SECOND) as i64 + offset_minutes * 60;
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let seconds_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let offset = if offset_minutes == 0 {
+        String::new()
+    } else {
+        format!(
+            "{}{:02}:{:02}",
+            if offset_minutes < 0 { '-' } else { '+' },
+            offset_minutes.abs() / 60,
+            offset_minutes.abs() % 60
+        )
+    };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC{}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60,
+        offset
+    )
+}
+
+/// Converts a number of days since 1970-01-01 into a (year, month, day) date
+/// of the proleptic Gregorian calendar.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day)
+}
+
+fn format_memo(memo: &Memo) -> String {
+    match std::str::from_utf8(memo.0.as_slice()) {
+        Ok(text) if !text.chars().any(char::is_control) => text.to_string(),
+        _ => format!("0x{}", hex::encode(memo.0.as_slice())),
+    }
+}
+
+fn decode_arg<T: candid::CandidType + for<'de> candid::Deserialize<'de>>(
+    request: &ConsentMessageRequest,
+    type_name: &str,
+) -> Result<T, Icrc21Error> {
+    Decode!(request.arg.as_slice(), T).map_err(|e| {
+        Icrc21Error::UnsupportedCanisterCall(ErrorInfo {
+            description: format!("failed to decode the {} argument: {}", type_name, e),
+        })
+    })
+}
+
+/// Builds the consent message for a call to `icrc1_transfer`,
+/// `icrc2_approve` or `icrc2_transfer_from` on a ledger with the given fee,
+/// token symbol and number of decimals.
+///
+/// Returns `Icrc21Error::UnsupportedCanisterCall` if the request targets
+/// another method or if its argument cannot be decoded.
+pub fn build_icrc21_consent_info_for_icrc1_and_icrc2_endpoints(
+    consent_msg_request: ConsentMessageRequest,
+    caller_principal: Principal,
+    ledger_fee: Nat,
+    token_symbol: String,
+    decimals: u8,
+) -> Result<ConsentInfo, Icrc21Error> {
+    let metadata = ConsentMessageMetadata {
+        language: DEFAULT_LANGUAGE.to_string(),
+        utc_offset_minutes: consent_msg_request
+            .user_preferences
+            .metadata
+            .utc_offset_minutes,
+    };
+    let tokens = |amount: &Nat| format!("{} {}", format_amount(amount, decimals), token_symbol);
+
+    let message = match consent_msg_request.method.as_str() {
+        "icrc1_transfer" => {
+            let arg: TransferArg = decode_arg(&consent_msg_request, "TransferArg")?;
+            let from = Account {
+                owner: caller_principal,
+                subaccount: arg.from_subaccount,
+            };
+            let fee = arg.fee.unwrap_or_else(|| ledger_fee.clone());
+            let mut message = Message::new(format!("Send {}", token_symbol))
+                .text("You are approving a transfer of funds from your account.")
+                .field("From", from.to_string())
+                .field("Amount", tokens(&arg.amount))
+                .field("To", arg.to.to_string())
+                .field("Fee", tokens(&fee));
+            if let Some(memo) = &arg.memo {
+                message = message.field("Memo", format_memo(memo));
+            }
+            message
+        }
+        "icrc2_approve" => {
+            let arg: ApproveArgs = decode_arg(&consent_msg_request, "ApproveArgs")?;
+            let from = Account {
+                owner: caller_principal,
+                subaccount: arg.from_subaccount,
+            };
+            let fee = arg.fee.unwrap_or_else(|| ledger_fee.clone());
+            let mut message = Message::new("Approve spending")
+                .text("You are authorizing another account to withdraw funds from your account.")
+                .field("Your account", from.to_string())
+                .field("Requested allowance", tokens(&arg.amount))
+                .text(format!(
+                    "The allowance will be set to {} independently of any previous allowance.",
+                    tokens(&arg.amount)
+                ));
+            if let Some(expected_allowance) = &arg.expected_allowance {
+                message = message.field("Current allowance", tokens(expected_allowance));
+            }
+            let expiration = match arg.expires_at {
+                Some(expires_at) => format_timestamp(
+                    expires_at,
+                    consent_msg_request
+                        .user_preferences
+                        .metadata
+                        .utc_offset_minutes,
+                ),
+                None => "Never".to_string(),
+            };
+            message = message
+                .field("Expiration date", expiration)
+                .field("Approved spender", arg.spender.to_string())
+                .field("Approval fee", tokens(&fee));
+            if let Some(memo) = &arg.memo {
+                message = message.field("Memo", format_memo(memo));
+            }
+            message
+        }
+        "icrc2_transfer_from" => {
+            let arg: TransferFromArgs = decode_arg(&consent_msg_request, "TransferFromArgs")?;
+            let spender = Account {
+                owner: caller_principal,
+                subaccount: arg.spender_subaccount,
+            };
+            let fee = arg.fee.unwrap_or_else(|| ledger_fee.clone());
+            let mut message = Message::new("Transfer from a withdrawal account")
+                .text("You are transferring funds from an account that approved you as a spender.")
+                .field("Withdrawal account", arg.from.to_string())
+                .field("Spender account", spender.to_string())
+                .field("Amount", tokens(&arg.amount))
+                .field("To", arg.to.to_string())
+                .field("Fee paid by the withdrawal account", tokens(&fee));
+            if let Some(memo) = &arg.memo {
+                message = message.field("Memo", format_memo(memo));
+            }
+            message
+        }
+        method => {
+            return Err(Icrc21Error::UnsupportedCanisterCall(ErrorInfo {
+                description: format!("no consent message is available for method {}", method),
+            }))
+        }
+    };
+
+    let consent_message = match consent_msg_request.user_preferences.device_spec {
+        None | Some(DisplayMessageType::GenericDisplay) => {
+            ConsentMessage::GenericDisplayMessage(message.render_generic_display())
+        }
+        Some(DisplayMessageType::LineDisplay {
+            characters_per_line,
+            lines_per_page,
+        }) => ConsentMessage::LineDisplayMessage {
+            pages: message.render_line_display(characters_per_line, lines_per_page)?,
+        },
+    };
+
+    Ok(ConsentInfo {
+        consent_message,
+        metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icrc21::requests::ConsentMessageSpec;
+    use candid::Encode;
+    use serde_bytes::ByteBuf;
+
+    fn request(
+        method: &str,
+        arg: Vec<u8>,
+        device_spec: Option<DisplayMessageType>,
+    ) -> ConsentMessageRequest {
+        ConsentMessageRequest {
+            method: method.to_string(),
+            arg: ByteBuf::from(arg),
+            user_preferences: ConsentMessageSpec {
+                metadata: ConsentMessageMetadata {
+                    language: "en".to_string(),
+                    utc_offset_minutes: None,
+                },
+                device_spec,
+            },
+        }
+    }
+
+    fn build(request: ConsentMessageRequest) -> Result<ConsentInfo, Icrc21Error> {
+        build_icrc21_consent_info_for_icrc1_and_icrc2_endpoints(
+            request,
+            Principal::anonymous(),
+            Nat::from(10_000u64),
+            "ICP".to_string(),
+            8,
+        )
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(&Nat::from(0u64), 8), "0");
+        assert_eq!(format_amount(&Nat::from(10_000u64), 8), "0.0001");
+        assert_eq!(format_amount(&Nat::from(150_000_000u64), 8), "1.5");
+        assert_eq!(format_amount(&Nat::from(1_234_567_890u64), 8), "12.3456789");
+        assert_eq!(format_amount(&Nat::from(42u64), 0), "42");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0, None), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_timestamp(1_709_251_200 * NANOS_PER_SECOND, None),
+            "2024-03-01 00:00:00 UTC"
+        );
+        assert_eq!(
+            format_timestamp(1_709_251_200 * NANOS_PER_SECOND, Some(-90)),
+            "2024-02-29 22:30:00 UTC-01:30"
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("Send ICP now", 8), vec!["Send ICP", "now"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert!(wrap("", 4).is_empty());
+    }
+
+    #[test]
+    fn test_transfer_generic_display() {
+        let arg = TransferArg {
+            from_subaccount: None,
+            to: Account::from(Principal::management_canister()),
+            fee: None,
+            created_at_time: None,
+            memo: None,
+            amount: Nat::from(150_000_000u64),
+        };
+        let info = build(request("icrc1_transfer", Encode!(&arg).unwrap(), None)).unwrap();
+        assert_eq!(info.metadata.language, DEFAULT_LANGUAGE);
+        match info.consent_message {
+            ConsentMessage::GenericDisplayMessage(message) => {
+                assert!(message.starts_with("# Send ICP"));
+                assert!(message.contains("**Amount:**\n`1.5 ICP`"));
+                assert!(message.contains("**Fee:**\n`0.0001 ICP`"));
+                assert!(message.contains(&format!("`{}`", Principal::management_canister())));
+            }
+            other => panic!("unexpected consent message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_approve_line_display() {
+        let arg = ApproveArgs {
+            from_subaccount: None,
+            spender: Account::from(Principal::management_canister()),
+            amount: Nat::from(100_000_000u64),
+            expected_allowance: None,
+            expires_at: None,
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        };
+        let info = build(request(
+            "icrc2_approve",
+            Encode!(&arg).unwrap(),
+            Some(DisplayMessageType::LineDisplay {
+                characters_per_line: 20,
+                lines_per_page: 4,
+            }),
+        ))
+        .unwrap();
+        match info.consent_message {
+            ConsentMessage::LineDisplayMessage { pages } => {
+                assert!(!pages.is_empty());
+                for page in &pages {
+                    assert!(page.lines.len() <= 4);
+                    assert!(page.lines.iter().all(|line| line.chars().count() <= 20));
+                }
+                assert_eq!(pages[0].lines[0], "Approve spending");
+            }
+            other => panic!("unexpected consent message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unsupported_calls() {
+        assert!(matches!(
+            build(request("icrc1_balance_of", Encode!().unwrap(), None)),
+            Err(Icrc21Error::UnsupportedCanisterCall(_))
+        ));
+        assert!(matches!(
+            build(request("icrc1_transfer", vec![1, 2, 3], None)),
+            Err(Icrc21Error::UnsupportedCanisterCall(_))
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+use candid::{CandidType, Deserialize};
+use serde_bytes::ByteBuf;
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConsentMessageMetadata {
+    /// The language of the consent message, as a BCP-47 language tag.
+    pub language: String,
+    /// The offset of the user's time zone relative to UTC, in minutes.
+    pub utc_offset_minutes: Option<i16>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum DisplayMessageType {
+    /// The message is rendered by a device able to display Markdown.
+    GenericDisplay,
+    /// The message is rendered by a device with a fixed-size text display.
+    LineDisplay {
+        characters_per_line: u16,
+        lines_per_page: u16,
+    },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConsentMessageSpec {
+    pub metadata: ConsentMessageMetadata,
+    pub device_spec: Option<DisplayMessageType>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConsentMessageRequest {
+    /// The name of the method the user is about to call.
+    pub method: String,
+    /// The Candid-encoded argument of the call.
+    pub arg: ByteBuf,
+    pub user_preferences: ConsentMessageSpec,
+}
@@ -0,0 +1,23 @@
+use super::requests::ConsentMessageMetadata;
+use candid::{CandidType, Deserialize};
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LineDisplayPage {
+    pub lines: Vec<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ConsentMessage {
+    /// A Markdown message for devices with a generic display.
+    GenericDisplayMessage(String),
+    /// A plain text message split into pages of fixed-size lines.
+    LineDisplayMessage { pages: Vec<LineDisplayPage> },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConsentInfo {
+    pub consent_message: ConsentMessage,
+    /// The metadata of the returned message. The language may differ from the
+    /// requested one if the canister does not support it.
+    pub metadata: ConsentMessageMetadata,
+}
@@ -1,7 +1,7 @@
 use crate::icrc1::transfer::BlockIndex;
 
 use super::{
-    blocks::{BlockRange, GetBlocksRequest},
+    blocks::{BlockRange, GetBlocksArgs, GetBlocksRequest, GetBlocksResult},
     transactions::{GetTransactionsRequest, TransactionRange},
 };
 use candid::{CandidType, Deserialize, Nat, Principal};
@@ -91,3 +91,25 @@ pub struct ArchiveInfo {
 }
 pub type QueryBlockArchiveFn = QueryArchiveFn<GetBlocksRequest, BlockRange>;
 pub type QueryTxArchiveFn = QueryArchiveFn<GetTransactionsRequest, TransactionRange>;
+
+/// A function to fetch archived blocks in the ICRC-3 format.
+pub type ICRC3GetBlocksFn = QueryArchiveFn<GetBlocksArgs, GetBlocksResult>;
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GetArchivesArgs {
+    /// The last archive seen by the client.
+    /// The ledger returns the archives coming after this one if set,
+    /// otherwise it returns the first archives.
+    pub from: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ICRC3ArchiveInfo {
+    pub canister_id: Principal,
+    /// The index of the first block stored in the archive.
+    pub start: Nat,
+    /// The index of the last block stored in the archive.
+    pub end: Nat,
+}
+
+pub type GetArchivesResult = Vec<ICRC3ArchiveInfo>;
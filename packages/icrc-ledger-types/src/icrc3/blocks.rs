@@ -1,10 +1,14 @@
 use crate::icrc3::archive::ArchivedRange;
-use crate::icrc3::archive::QueryBlockArchiveFn;
-use crate::{icrc::generic_value::Value, icrc1::transfer::BlockIndex};
+use crate::icrc3::archive::{ICRC3GetBlocksFn, QueryBlockArchiveFn};
+use crate::{
+    icrc::generic_value::{ICRC3Value, Value},
+    icrc1::transfer::BlockIndex,
+};
 use candid::{CandidType, Deserialize, Nat};
 use serde_bytes::ByteBuf;
 
 pub type GenericBlock = Value;
+pub type ICRC3GenericBlock = ICRC3Value;
 
 #[derive(Debug, CandidType, Clone, Deserialize, PartialEq, Eq)]
 pub struct GetBlocksResponse {
@@ -52,3 +56,42 @@ pub struct DataCertificate {
     pub certificate: Option<serde_bytes::ByteBuf>,
     pub hash_tree: serde_bytes::ByteBuf,
 }
+
+/// The argument of `icrc3_get_blocks`: the list of block ranges to fetch.
+pub type GetBlocksArgs = Vec<GetBlocksRequest>;
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BlockWithId {
+    pub id: Nat,
+    pub block: ICRC3GenericBlock,
+}
+
+/// Block ranges that must be fetched from an archive using the given callback.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedBlocks {
+    pub args: GetBlocksArgs,
+    pub callback: ICRC3GetBlocksFn,
+}
+
+/// The result of `icrc3_get_blocks`.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GetBlocksResult {
+    /// The total number of blocks in the log.
+    pub log_length: Nat,
+    pub blocks: Vec<BlockWithId>,
+    pub archived_blocks: Vec<ArchivedBlocks>,
+}
+
+/// The certificate of the tip of the block log returned by `icrc3_get_tip_certificate`.
+/// The hash tree contains the labels `last_block_index` (LEB128-encoded) and `last_block_hash`.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ICRC3DataCertificate {
+    pub certificate: serde_bytes::ByteBuf,
+    pub hash_tree: serde_bytes::ByteBuf,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SupportedBlockType {
+    pub block_type: String,
+    pub url: String,
+}
@@ -4,7 +4,8 @@ use ic_btc_adapter::config::IncomingSource;
 use ic_btc_adapter::start_grpc_server;
 use ic_btc_adapter::AdapterState;
 use ic_btc_adapter::{
-    config::Config, BlockchainManagerRequest, BlockchainState, GetSuccessorsHandler,
+    config::Config, BlockFilterState, BlockchainManagerRequest, BlockchainState,
+    FilterManagerRequest, GetBlockFiltersHandler, GetSuccessorsHandler,
 };
 use ic_btc_adapter_client::setup_bitcoin_adapter_clients;
 use ic_btc_adapter_test_utils::generate_headers;
@@ -113,6 +114,15 @@ fn e2e(criterion: &mut Criterion) {
                     &MetricsRegistry::default(),
                 );
 
+                let (filter_manager_tx, _) = channel::<FilterManagerRequest>(10);
+                let block_filters_handler = GetBlockFiltersHandler::new(
+                    &config,
+                    Arc::new(Mutex::new(BlockFilterState::new(
+                        &MetricsRegistry::default(),
+                    ))),
+                    filter_manager_tx,
+                );
+
                 let adapter_state = AdapterState::new(config.idle_seconds);
 
                 let (transaction_manager_tx, _) = channel(100);
@@ -121,6 +131,7 @@ fn e2e(criterion: &mut Criterion) {
                     no_op_logger(),
                     adapter_state.clone(),
                     handler,
+                    block_filters_handler,
                     transaction_manager_tx,
                     &MetricsRegistry::default(),
                 );
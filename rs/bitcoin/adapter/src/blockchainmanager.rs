@@ -373,9 +373,9 @@ impl BlockchainManager {
             }
 
             match maybe_err {
-                Some(AddHeaderError::InvalidHeader(_, _)) => {
-                    return Err(ReceivedHeadersMessageError::ReceivedInvalidHeader)
-                }
+                Some(
+                    AddHeaderError::InvalidHeader(_, _) | AddHeaderError::InvalidPowHeader(_, _),
+                ) => return Err(ReceivedHeadersMessageError::ReceivedInvalidHeader),
                 Some(AddHeaderError::PrevHeaderNotCached(stop_hash)) => {
                     Some((blockchain_state.locator_hashes(), stop_hash))
                 }
@@ -1,7 +1,12 @@
 //! The module is responsible for keeping track of the blockchain state.
 //!
-use crate::{common::BlockHeight, config::Config, metrics::BlockchainStateMetrics};
-use bitcoin::{blockdata::constants::genesis_block, Block, BlockHash, BlockHeader, Network};
+use crate::{
+    chainprofile::{validate_pow_header, Checkpoint, ConsensusRules, PowHeaderError},
+    common::BlockHeight,
+    config::Config,
+    metrics::BlockchainStateMetrics,
+};
+use bitcoin::{Block, BlockHash, BlockHeader};
 use ic_btc_validation::{validate_header, HeaderStore, ValidateHeaderError};
 use ic_metrics::MetricsRegistry;
 use std::collections::HashMap;
@@ -65,6 +70,10 @@ pub enum AddHeaderError {
     /// (eg: not of the right format)
     #[error("Received an invalid block header: {0}")]
     InvalidHeader(BlockHash, ValidateHeaderError),
+    /// This variant is used when the input header violates the proof-of-work rules of the
+    /// chain profile.
+    #[error("Received a block header violating the proof-of-work rules: {0}")]
+    InvalidPowHeader(BlockHash, PowHeaderError),
     /// This variant is used when the predecessor of the input header is not part of header_cache.
     #[error("Received a block header where we do not have the previous header in the cache: {0}")]
     PrevHeaderNotCached(BlockHash),
@@ -97,8 +106,11 @@ pub struct BlockchainState {
     /// This field contains the known tips of the header cache.
     tips: Vec<Tip>,
 
-    /// Used to determine how validation should be handled with `validate_header`.
-    network: Network,
+    /// Used to determine how headers should be validated.
+    consensus: ConsensusRules,
+
+    /// The checkpoints that the headers must match if the chain is not a Bitcoin network.
+    checkpoints: Vec<Checkpoint>,
     metrics: BlockchainStateMetrics,
 }
 
@@ -106,7 +118,8 @@ impl BlockchainState {
     /// This function is used to create a new BlockChainState object.  
     pub fn new(config: &Config, metrics_registry: &MetricsRegistry) -> Self {
         // Create a header cache and inserting dummy header corresponding the `adapter_genesis_hash`.
-        let genesis_block_header = genesis_block(config.network).header;
+        let chain_profile = config.chain_profile();
+        let genesis_block_header = chain_profile.genesis_header;
         let header_cache = init_cache_with_genesis(genesis_block_header);
         let block_cache = HashMap::new();
         let tips = vec![Tip {
@@ -120,7 +133,8 @@ impl BlockchainState {
             header_cache,
             block_cache,
             tips,
-            network: config.network,
+            consensus: chain_profile.consensus,
+            checkpoints: chain_profile.checkpoints,
             metrics: BlockchainStateMetrics::new(metrics_registry),
         }
     }
@@ -179,8 +193,13 @@ impl BlockchainState {
             return Ok(AddHeaderResult::HeaderAlreadyExists(block_hash));
         }
 
-        if let Err(err) = validate_header(&self.network, self, &header) {
-            return Err(AddHeaderError::InvalidHeader(block_hash, err));
+        match &self.consensus {
+            ConsensusRules::Bitcoin(network) => validate_header(network, self, &header)
+                .map_err(|err| AddHeaderError::InvalidHeader(block_hash, err))?,
+            ConsensusRules::Pow(params) => {
+                validate_pow_header(params, &self.checkpoints, self, &header)
+                    .map_err(|err| AddHeaderError::InvalidPowHeader(block_hash, err))?
+            }
         }
 
         let parent = self
@@ -353,8 +372,15 @@ mod test {
     use ic_metrics::MetricsRegistry;
 
     use super::*;
-    use crate::{common::test_common::TestState, config::test::ConfigBuilder};
-    use ic_btc_adapter_test_utils::{block_1, block_2, generate_header, generate_headers};
+    use crate::{
+        chainprofile::{test::scrypt_test_chain, ChainProfile, PowAlgorithm, PowParams},
+        common::test_common::TestState,
+        config::test::ConfigBuilder,
+    };
+    use bitcoin::Network;
+    use ic_btc_adapter_test_utils::{
+        block_1, block_2, generate_header, generate_headers, headers_to_hashes,
+    };
     use std::collections::HashSet;
 
     #[test]
@@ -451,6 +477,191 @@ mod test {
         assert_eq!(tip.height, 2499);
     }
 
+    /// Tests whether or not the recorded mainnet headers satisfy the generic proof-of-work rules
+    /// when they are configured with the Bitcoin mainnet parameters.
+    #[test]
+    fn test_adding_mainnet_headers_with_pow_rules_successfully() {
+        let chain_profile = ChainProfile {
+            consensus: ConsensusRules::Pow(PowParams {
+                algorithm: PowAlgorithm::Sha256d,
+                pow_limit_bits: 0x1d00ffff,
+                target_spacing_seconds: 600,
+                adjustment_interval: 2016,
+                full_interval_retarget: false,
+                allow_min_difficulty_blocks: false,
+                no_retargeting: false,
+            }),
+            ..ChainProfile::bitcoin(Network::Bitcoin)
+        };
+        let config = ConfigBuilder::new()
+            .with_chain_profile(chain_profile)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+
+        let headers_json = include_str!("../test_data/first_2500_mainnet_headers.json");
+        let headers: Vec<BlockHeader> = serde_json::from_str(headers_json).unwrap();
+
+        let (added_headers, maybe_err) = state.add_headers(&headers);
+        assert!(
+            maybe_err.is_none(),
+            "Error when adding valid mainnet headers."
+        );
+        assert_eq!(added_headers.len(), 2499);
+        assert_eq!(state.get_active_chain_tip().height, 2499);
+    }
+
+    /// Tests whether or not the recorded testnet headers satisfy the generic proof-of-work rules,
+    /// including the minimum difficulty rule and the adjustment at height 2016, when they are
+    /// configured with the Bitcoin testnet parameters.
+    #[test]
+    fn test_adding_testnet_headers_with_pow_rules_successfully() {
+        let chain_profile = ChainProfile {
+            consensus: ConsensusRules::Pow(PowParams {
+                algorithm: PowAlgorithm::Sha256d,
+                pow_limit_bits: 0x1d00ffff,
+                target_spacing_seconds: 600,
+                adjustment_interval: 2016,
+                full_interval_retarget: false,
+                allow_min_difficulty_blocks: true,
+                no_retargeting: false,
+            }),
+            ..ChainProfile::bitcoin(Network::Testnet)
+        };
+        let config = ConfigBuilder::new()
+            .with_chain_profile(chain_profile)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+
+        let headers_json = include_str!("../test_data/first_2500_testnet_headers.json");
+        let headers: Vec<BlockHeader> = serde_json::from_str(headers_json).unwrap();
+
+        let (added_headers, maybe_err) = state.add_headers(&headers);
+        assert!(
+            maybe_err.is_none(),
+            "Error when adding valid testnet headers."
+        );
+        assert_eq!(added_headers.len(), 2499);
+        assert_eq!(state.get_active_chain_tip().height, 2499);
+    }
+
+    /// Tests whether or not the `BlockchainState::add_headers(...)` function rejects headers
+    /// that conflict with the checkpoints of the chain profile.
+    #[test]
+    fn test_adding_headers_conflicting_with_checkpoints() {
+        let headers_json = include_str!("../test_data/first_2500_mainnet_headers.json");
+        let headers: Vec<BlockHeader> = serde_json::from_str(headers_json).unwrap();
+        let chain_profile = |checkpoint_hash: BlockHash| ChainProfile {
+            consensus: ConsensusRules::Pow(PowParams {
+                algorithm: PowAlgorithm::Sha256d,
+                pow_limit_bits: 0x1d00ffff,
+                target_spacing_seconds: 600,
+                adjustment_interval: 2016,
+                full_interval_retarget: false,
+                allow_min_difficulty_blocks: false,
+                no_retargeting: false,
+            }),
+            checkpoints: vec![Checkpoint {
+                height: 2016,
+                hash: checkpoint_hash,
+            }],
+            ..ChainProfile::bitcoin(Network::Bitcoin)
+        };
+
+        let profile = chain_profile(headers[2016].block_hash());
+        assert!(!profile.is_beyond_last_checkpoint(2015));
+        assert!(profile.is_beyond_last_checkpoint(2016));
+        let config = ConfigBuilder::new().with_chain_profile(profile).build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let (added_headers, maybe_err) = state.add_headers(&headers);
+        assert!(maybe_err.is_none());
+        assert_eq!(added_headers.len(), 2499);
+
+        let config = ConfigBuilder::new()
+            .with_chain_profile(chain_profile(headers[2015].block_hash()))
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let (added_headers, maybe_err) = state.add_headers(&headers);
+        assert!(matches!(
+            maybe_err,
+            Some(AddHeaderError::InvalidPowHeader(
+                hash,
+                PowHeaderError::CheckpointMismatch { height: 2016, expected }
+            )) if hash == headers[2016].block_hash() && expected == headers[2015].block_hash()
+        ));
+        assert_eq!(added_headers.len(), 2015);
+        assert_eq!(state.get_active_chain_tip().height, 2015);
+    }
+
+    /// Tests whether or not the `BlockchainState::add_headers(...)` function can add the headers
+    /// of a chain with scrypt proof of work, including three difficulty adjustments.
+    #[test]
+    fn test_adding_scrypt_headers_successfully() {
+        let (chain_profile, headers) = scrypt_test_chain();
+        let config = ConfigBuilder::new()
+            .with_chain_profile(chain_profile)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        assert_eq!(state.genesis().block_hash(), headers[0].block_hash());
+
+        let (added_headers, maybe_err) = state.add_headers(&headers[1..]);
+        assert!(
+            maybe_err.is_none(),
+            "Error when adding valid scrypt headers."
+        );
+        assert_eq!(added_headers, headers_to_hashes(&headers[1..]));
+        assert_eq!(state.get_active_chain_tip().height, 24);
+    }
+
+    /// Tests whether or not the `BlockchainState::add_headers(...)` function rejects headers
+    /// that violate the proof-of-work rules of the chain profile.
+    #[test]
+    fn test_adding_scrypt_headers_violating_pow_rules() {
+        let (chain_profile, headers) = scrypt_test_chain();
+        let config = ConfigBuilder::new()
+            .with_chain_profile(chain_profile)
+            .build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let (_, maybe_err) = state.add_headers(&headers[1..8]);
+        assert!(maybe_err.is_none());
+
+        // The header at height 8 must carry the adjusted target.
+        let mut header = headers[8];
+        header.bits = headers[7].bits;
+        let (_, maybe_err) = state.add_headers(&[header]);
+        assert!(matches!(
+            maybe_err,
+            Some(AddHeaderError::InvalidPowHeader(hash, PowHeaderError::UnexpectedTarget { actual, expected }))
+                if hash == header.block_hash() && actual == headers[7].bits && expected == headers[8].bits
+        ));
+
+        let mut header = headers[8];
+        header.nonce += 1;
+        let (_, maybe_err) = state.add_headers(&[header]);
+        assert!(matches!(
+            maybe_err,
+            Some(AddHeaderError::InvalidPowHeader(
+                _,
+                PowHeaderError::InsufficientPow
+            ))
+        ));
+
+        let mut header = headers[8];
+        header.time = headers[0].time;
+        let (_, maybe_err) = state.add_headers(&[header]);
+        assert!(matches!(
+            maybe_err,
+            Some(AddHeaderError::InvalidPowHeader(
+                _,
+                PowHeaderError::HeaderIsOld { .. }
+            ))
+        ));
+
+        let (added_headers, maybe_err) = state.add_headers(&headers[8..]);
+        assert!(maybe_err.is_none());
+        assert_eq!(added_headers.len(), 17);
+        assert_eq!(state.get_active_chain_tip().height, 24);
+    }
+
     #[test]
     /// Tests whether or not the `BlockchainState::add_headers(...)` function can add headers that
     /// cause 2 forks in the chain. The state should be able to determine what is the active tip.
@@ -0,0 +1,623 @@
+//! The module is responsible for keeping track of the compact block filters (BIP157/158) of the
+//! active chain.
+//!
+//! The filter headers form a hash chain that commits to the filters of all blocks up to a given
+//! height. A filter header chain received from a peer is only accepted if it extends the verified
+//! chain, and a filter is only accepted if it matches the filter header of its block.
+use crate::{blockchainstate::BlockchainState, common::BlockHeight, metrics::BlockFilterMetrics};
+use bitcoin::{
+    consensus::Decodable,
+    hash_types::{FilterHash, FilterHeader},
+    network::message_filter::{CFHeaders, CFilter},
+    util::bip158::BlockFilter,
+    Block, BlockHash, VarInt,
+};
+use ic_metrics::MetricsRegistry;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// The type of the basic block filter defined in BIP158, the only filter type supported.
+pub const BASIC_FILTER_TYPE: u8 = 0;
+
+/// The errors that may occur when adding filter headers or filters to the state.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BlockFilterError {
+    /// This variant is used when a peer sends filters of a type other than the basic type.
+    #[error("Received filters of an unsupported type: {0}")]
+    UnsupportedFilterType(u8),
+    /// This variant is used when the block is not part of the active chain.
+    #[error("Received filter data for a block that is not in the active chain: {0}")]
+    UnknownBlock(BlockHash),
+    /// This variant is used when the filter headers do not directly follow the verified ones.
+    #[error("Received filter headers starting at height {0} that do not follow the verified filter headers")]
+    UnexpectedStartHeight(BlockHeight),
+    /// This variant is used when the filter headers do not connect to the verified ones.
+    #[error("Received filter headers that do not connect to the verified filter headers")]
+    PreviousFilterHeaderMismatch,
+    /// This variant is used when the filter header of the block has not been verified yet.
+    #[error("Received a filter for a block without a verified filter header: {0}")]
+    MissingFilterHeader(BlockHash),
+    /// This variant is used when the filter does not match the filter header of the block.
+    #[error("Received a filter that does not match the filter header of block {0}")]
+    FilterHeaderMismatch(BlockHash),
+}
+
+/// A verified block filter along with the filter header committing to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedBlockFilter {
+    /// The hash of the block the filter belongs to.
+    pub block_hash: BlockHash,
+    /// The basic filter of the block.
+    pub filter: BlockFilter,
+    /// The filter header of the block.
+    pub filter_header: FilterHeader,
+}
+
+/// This struct stores the filter header chain of the active chain and a cache of verified filters.
+#[derive(Debug)]
+pub struct BlockFilterState {
+    /// The block hashes of the active chain indexed by height, as last seen by the filter manager.
+    chain: Vec<BlockHash>,
+
+    /// The heights of the blocks in `chain`.
+    heights: HashMap<BlockHash, BlockHeight>,
+
+    /// The verified filter headers of the first blocks in `chain` indexed by height.
+    filter_headers: Vec<FilterHeader>,
+
+    /// This field stores the verified filters by block hash.
+    filter_cache: HashMap<BlockHash, BlockFilter>,
+
+    metrics: BlockFilterMetrics,
+}
+
+impl BlockFilterState {
+    /// This function is used to create a new BlockFilterState object.
+    pub fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            chain: vec![],
+            heights: HashMap::new(),
+            filter_headers: vec![],
+            filter_cache: HashMap::new(),
+            metrics: BlockFilterMetrics::new(metrics_registry),
+        }
+    }
+
+    /// Follows the active chain of the given blockchain state. The filter headers and filters of
+    /// the blocks that are no longer part of the active chain are removed.
+    pub fn update_chain(&mut self, blockchain: &BlockchainState) {
+        let mut new_hashes = vec![];
+        let mut fork_height = self.chain.len();
+        let mut block_hash = blockchain.get_active_chain_tip().header.block_hash();
+        while let Some(node) = blockchain.get_cached_header(&block_hash) {
+            if self.chain.get(node.height as usize) == Some(&block_hash) {
+                break;
+            }
+            new_hashes.push(block_hash);
+            fork_height = node.height as usize;
+            block_hash = node.header.prev_blockhash;
+        }
+        if new_hashes.is_empty() {
+            return;
+        }
+
+        for removed_hash in self.chain.drain(fork_height..) {
+            self.heights.remove(&removed_hash);
+            self.filter_cache.remove(&removed_hash);
+        }
+        self.filter_headers.truncate(fork_height);
+        for block_hash in new_hashes.into_iter().rev() {
+            self.heights
+                .insert(block_hash, self.chain.len() as BlockHeight);
+            self.chain.push(block_hash);
+        }
+        self.update_metrics();
+    }
+
+    /// Returns the height of the given block if it is part of the active chain.
+    pub fn get_height(&self, block_hash: &BlockHash) -> Option<BlockHeight> {
+        self.heights.get(block_hash).copied()
+    }
+
+    /// Returns the hash of the block at the given height of the active chain.
+    pub fn get_block_hash(&self, height: BlockHeight) -> Option<BlockHash> {
+        self.chain.get(height as usize).copied()
+    }
+
+    /// Returns the number of blocks of the active chain whose filter headers are verified.
+    pub fn filter_headers_len(&self) -> usize {
+        self.filter_headers.len()
+    }
+
+    /// Returns the range of the next filter headers to download as the height of the first
+    /// block and the hash of the last block. At most `max_headers` headers are requested.
+    pub fn next_filter_headers_range(
+        &self,
+        max_headers: usize,
+    ) -> Option<(BlockHeight, BlockHash)> {
+        let start = self.filter_headers.len();
+        if start >= self.chain.len() || max_headers == 0 {
+            return None;
+        }
+        let stop = (start + max_headers).min(self.chain.len()) - 1;
+        Some((start as BlockHeight, self.chain[stop]))
+    }
+
+    /// Verifies that the received filter headers extend the verified filter header chain and
+    /// adds them to the state. Returns the number of added filter headers.
+    pub fn add_filter_headers(&mut self, message: &CFHeaders) -> Result<usize, BlockFilterError> {
+        let start_height = self.check_filter_headers(message)?;
+        self.add_filter_hashes(start_height, &message.filter_hashes)
+    }
+
+    /// Verifies that the received filter headers extend the verified filter header chain without
+    /// adding them to the state. Returns the height of the first block of the filter headers.
+    pub fn check_filter_headers(
+        &self,
+        message: &CFHeaders,
+    ) -> Result<BlockHeight, BlockFilterError> {
+        if message.filter_type != BASIC_FILTER_TYPE {
+            return Err(BlockFilterError::UnsupportedFilterType(message.filter_type));
+        }
+        let stop_height = self
+            .get_height(&message.stop_hash)
+            .ok_or(BlockFilterError::UnknownBlock(message.stop_hash))?;
+        let start_height = (stop_height as usize + 1)
+            .checked_sub(message.filter_hashes.len())
+            .ok_or(BlockFilterError::UnexpectedStartHeight(0))?;
+        if start_height != self.filter_headers.len() {
+            return Err(BlockFilterError::UnexpectedStartHeight(
+                start_height as BlockHeight,
+            ));
+        }
+        if message.previous_filter_header != self.previous_filter_header(start_height) {
+            return Err(BlockFilterError::PreviousFilterHeaderMismatch);
+        }
+        Ok(start_height as BlockHeight)
+    }
+
+    /// Extends the verified filter header chain with the filter headers committing to the given
+    /// filter hashes of the blocks starting at the given height. Returns the number of added
+    /// filter headers.
+    pub fn add_filter_hashes(
+        &mut self,
+        start_height: BlockHeight,
+        filter_hashes: &[FilterHash],
+    ) -> Result<usize, BlockFilterError> {
+        let start_height = start_height as usize;
+        if start_height != self.filter_headers.len()
+            || start_height + filter_hashes.len() > self.chain.len()
+        {
+            return Err(BlockFilterError::UnexpectedStartHeight(
+                start_height as BlockHeight,
+            ));
+        }
+
+        let mut previous_filter_header = self.previous_filter_header(start_height);
+        for filter_hash in filter_hashes {
+            previous_filter_header = filter_hash.filter_header(&previous_filter_header);
+            self.filter_headers.push(previous_filter_header);
+        }
+        self.update_metrics();
+        Ok(filter_hashes.len())
+    }
+
+    /// Verifies that the received filter matches the filter header of its block and adds it to
+    /// the filter cache.
+    pub fn add_filter(&mut self, message: &CFilter) -> Result<(), BlockFilterError> {
+        if message.filter_type != BASIC_FILTER_TYPE {
+            return Err(BlockFilterError::UnsupportedFilterType(message.filter_type));
+        }
+        let height =
+            self.get_height(&message.block_hash)
+                .ok_or(BlockFilterError::UnknownBlock(message.block_hash))? as usize;
+        let filter_header = *self
+            .filter_headers
+            .get(height)
+            .ok_or(BlockFilterError::MissingFilterHeader(message.block_hash))?;
+
+        let filter = BlockFilter::new(&message.filter);
+        if filter.filter_header(&self.previous_filter_header(height)) != filter_header {
+            return Err(BlockFilterError::FilterHeaderMismatch(message.block_hash));
+        }
+        self.filter_cache.insert(message.block_hash, filter);
+        self.update_metrics();
+        Ok(())
+    }
+
+    /// Returns the filter of the given block if it has been downloaded and verified.
+    pub fn get_filter(&self, block_hash: &BlockHash) -> Option<VerifiedBlockFilter> {
+        let filter = self.filter_cache.get(block_hash)?;
+        let height = self.get_height(block_hash)?;
+        Some(VerifiedBlockFilter {
+            block_hash: *block_hash,
+            filter: filter.clone(),
+            filter_header: *self.filter_headers.get(height as usize)?,
+        })
+    }
+
+    /// Returns true if the filter of the given block has been downloaded and verified.
+    pub fn has_filter(&self, block_hash: &BlockHash) -> bool {
+        self.filter_cache.contains_key(block_hash)
+    }
+
+    /// Removes the filters of the given blocks from the filter cache.
+    pub fn prune_filters(&mut self, block_hashes: &[BlockHash]) {
+        for block_hash in block_hashes {
+            self.filter_cache.remove(block_hash);
+        }
+        self.update_metrics();
+    }
+
+    /// Removes the filters of the blocks below the given height from the filter cache.
+    pub fn prune_filters_below_height(&mut self, height: BlockHeight) {
+        let heights = &self.heights;
+        self.filter_cache
+            .retain(|block_hash, _| heights.get(block_hash).map_or(false, |h| *h >= height));
+        self.update_metrics();
+    }
+
+    /// Used when the adapter is shutdown and no longer requires holding on to filters.
+    pub fn clear_filters(&mut self) {
+        self.filter_cache = HashMap::new();
+        self.update_metrics();
+    }
+
+    /// Returns the filter header preceding the block at the given height.
+    fn previous_filter_header(&self, height: usize) -> FilterHeader {
+        match height.checked_sub(1) {
+            Some(previous_height) => self.filter_headers[previous_height],
+            None => FilterHeader::default(),
+        }
+    }
+
+    fn update_metrics(&self) {
+        self.metrics
+            .filter_headers
+            .set(self.filter_headers.len() as i64);
+        self.metrics
+            .filter_cache_elements
+            .set(self.filter_cache.len() as i64);
+    }
+}
+
+/// Returns false if the given basic filter provably does not belong to the given block.
+///
+/// The basic filter of a block contains the output scripts of the block and the scripts of the
+/// outputs spent by the block. As the spent output scripts are not known without the UTXO set,
+/// the filter is only checked to contain all output scripts and no more elements than the
+/// output scripts and the spent outputs of the block can account for.
+///
+/// The spent output scripts are not verified: a filter that omits them and contains other
+/// elements instead is considered consistent with the block.
+pub fn is_filter_consistent_with_block(filter: &BlockFilter, block: &Block) -> bool {
+    let output_scripts: HashSet<&[u8]> = block
+        .txdata
+        .iter()
+        .flat_map(|tx| tx.output.iter())
+        .map(|output| &output.script_pubkey)
+        .filter(|script| !script.is_empty() && !script.is_op_return())
+        .map(|script| script.as_bytes())
+        .collect();
+    let spent_outputs: usize = block.txdata.iter().skip(1).map(|tx| tx.input.len()).sum();
+    let num_elements = match VarInt::consensus_decode(filter.content.as_slice()) {
+        Ok(VarInt(num_elements)) => num_elements as usize,
+        Err(_) => return false,
+    };
+    num_elements >= output_scripts.len()
+        && num_elements <= output_scripts.len() + spent_outputs
+        && filter
+            .match_all(&block.block_hash(), &mut output_scripts.into_iter())
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::config::test::ConfigBuilder;
+    use bitcoin::{
+        hashes::{hex::FromHex, Hash},
+        util::bip158::BlockFilterWriter,
+        BlockHeader, Network, Script,
+    };
+    use ic_btc_adapter_test_utils::{block_1, block_2, generate_headers};
+
+    /// Returns an arbitrary filter for the block at the given height.
+    pub fn filter_content(height: usize) -> Vec<u8> {
+        vec![1, height as u8, (height >> 8) as u8]
+    }
+
+    /// Builds the `cfheaders` message for the given blocks of the chain starting at `start_height`.
+    pub fn cfheaders_message(chain: &[BlockHash], start_height: usize, count: usize) -> CFHeaders {
+        let filters: Vec<Vec<u8>> = (0..start_height + count).map(filter_content).collect();
+        cfheaders_message_with_filters(chain, &filters, start_height, count)
+    }
+
+    /// Builds the `cfheaders` message committing to the given filters, indexed by height, for the
+    /// given blocks of the chain starting at `start_height`.
+    pub fn cfheaders_message_with_filters(
+        chain: &[BlockHash],
+        filters: &[Vec<u8>],
+        start_height: usize,
+        count: usize,
+    ) -> CFHeaders {
+        let mut previous_filter_header = FilterHeader::default();
+        for filter in &filters[..start_height] {
+            previous_filter_header =
+                BlockFilter::new(filter).filter_header(&previous_filter_header);
+        }
+        CFHeaders {
+            filter_type: BASIC_FILTER_TYPE,
+            stop_hash: chain[start_height + count - 1],
+            previous_filter_header,
+            filter_hashes: filters[start_height..start_height + count]
+                .iter()
+                .map(|filter| FilterHash::hash(filter))
+                .collect(),
+        }
+    }
+
+    /// Computes the basic filter of a block that only spends outputs with the given script.
+    pub fn basic_filter(block: &Block, spent_script: &Script) -> BlockFilter {
+        BlockFilter::new_script_filter(block, |_| Ok(spent_script.clone())).unwrap()
+    }
+
+    /// Builds the `cfilter` message for the block at the given height of the chain.
+    pub fn cfilter_message(chain: &[BlockHash], height: usize) -> CFilter {
+        CFilter {
+            filter_type: BASIC_FILTER_TYPE,
+            block_hash: chain[height],
+            filter: filter_content(height),
+        }
+    }
+
+    /// Creates a regtest blockchain state with the given number of blocks following the genesis
+    /// block. Returns the state and the hashes of the active chain including the genesis block.
+    pub fn regtest_blockchain(len: u32) -> (BlockchainState, Vec<BlockHeader>) {
+        let config = ConfigBuilder::new().with_network(Network::Regtest).build();
+        let mut blockchain = BlockchainState::new(&config, &MetricsRegistry::default());
+        let genesis = *blockchain.genesis();
+        let headers = generate_headers(genesis.block_hash(), genesis.time, len, &[]);
+        let (_, maybe_err) = blockchain.add_headers(&headers);
+        assert!(
+            maybe_err.is_none(),
+            "Failed to add headers: {:?}",
+            maybe_err
+        );
+        (
+            blockchain,
+            std::iter::once(genesis).chain(headers).collect(),
+        )
+    }
+
+    fn hashes(headers: &[BlockHeader]) -> Vec<BlockHash> {
+        headers.iter().map(|header| header.block_hash()).collect()
+    }
+
+    /// Tests that the filter of the testnet genesis block from the BIP158 test vectors is verified.
+    #[test]
+    fn test_testnet_genesis_filter() {
+        let config = ConfigBuilder::new().with_network(Network::Testnet).build();
+        let blockchain = BlockchainState::new(&config, &MetricsRegistry::default());
+        let genesis_hash = blockchain.genesis().block_hash();
+        let filter = Vec::from_hex("019dfca8").unwrap();
+        let expected_filter_header = FilterHeader::from_hex(
+            "21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750",
+        )
+        .unwrap();
+
+        let mut state = BlockFilterState::new(&MetricsRegistry::default());
+        state.update_chain(&blockchain);
+        assert_eq!(
+            state.next_filter_headers_range(2_000),
+            Some((0, genesis_hash))
+        );
+
+        let added = state
+            .add_filter_headers(&CFHeaders {
+                filter_type: BASIC_FILTER_TYPE,
+                stop_hash: genesis_hash,
+                previous_filter_header: FilterHeader::default(),
+                filter_hashes: vec![FilterHash::hash(&filter)],
+            })
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(state.next_filter_headers_range(2_000), None);
+
+        state
+            .add_filter(&CFilter {
+                filter_type: BASIC_FILTER_TYPE,
+                block_hash: genesis_hash,
+                filter: filter.clone(),
+            })
+            .unwrap();
+        assert_eq!(
+            state.get_filter(&genesis_hash),
+            Some(VerifiedBlockFilter {
+                block_hash: genesis_hash,
+                filter: BlockFilter::new(&filter),
+                filter_header: expected_filter_header,
+            })
+        );
+    }
+
+    /// Tests that filter headers are only accepted in order and if they connect to the verified ones.
+    #[test]
+    fn test_add_filter_headers() {
+        let (blockchain, headers) = regtest_blockchain(10);
+        let chain = hashes(&headers);
+        let mut state = BlockFilterState::new(&MetricsRegistry::default());
+        state.update_chain(&blockchain);
+        assert_eq!(state.next_filter_headers_range(4), Some((0, chain[3])));
+
+        // The filter headers need to start right after the verified ones.
+        assert_eq!(
+            state.add_filter_headers(&cfheaders_message(&chain, 1, 3)),
+            Err(BlockFilterError::UnexpectedStartHeight(1))
+        );
+        assert_eq!(
+            state.add_filter_headers(&cfheaders_message(&chain, 0, 4)),
+            Ok(4)
+        );
+        assert_eq!(state.next_filter_headers_range(4), Some((4, chain[7])));
+
+        // The filter headers need to connect to the verified ones.
+        let mut message = cfheaders_message(&chain, 4, 7);
+        message.previous_filter_header = FilterHeader::default();
+        assert_eq!(
+            state.add_filter_headers(&message),
+            Err(BlockFilterError::PreviousFilterHeaderMismatch)
+        );
+
+        let mut message = cfheaders_message(&chain, 4, 7);
+        message.filter_type = 1;
+        assert_eq!(
+            state.add_filter_headers(&message),
+            Err(BlockFilterError::UnsupportedFilterType(1))
+        );
+
+        assert_eq!(
+            state.add_filter_headers(&cfheaders_message(&chain, 4, 7)),
+            Ok(7)
+        );
+        assert_eq!(state.filter_headers_len(), 11);
+        assert_eq!(state.next_filter_headers_range(4), None);
+    }
+
+    /// Tests that filters are only accepted if they match the verified filter headers.
+    #[test]
+    fn test_add_filter() {
+        let (blockchain, headers) = regtest_blockchain(5);
+        let chain = hashes(&headers);
+        let mut state = BlockFilterState::new(&MetricsRegistry::default());
+        state.update_chain(&blockchain);
+        state
+            .add_filter_headers(&cfheaders_message(&chain, 0, 3))
+            .unwrap();
+
+        assert_eq!(
+            state.add_filter(&cfilter_message(&chain, 3)),
+            Err(BlockFilterError::MissingFilterHeader(chain[3]))
+        );
+        let mut message = cfilter_message(&chain, 2);
+        message.filter = filter_content(1);
+        assert_eq!(
+            state.add_filter(&message),
+            Err(BlockFilterError::FilterHeaderMismatch(chain[2]))
+        );
+        assert!(!state.has_filter(&chain[2]));
+
+        assert_eq!(state.add_filter(&cfilter_message(&chain, 2)), Ok(()));
+        let filter = state.get_filter(&chain[2]).unwrap();
+        assert_eq!(filter.filter, BlockFilter::new(&filter_content(2)));
+
+        state.prune_filters(&[chain[2]]);
+        assert!(!state.has_filter(&chain[2]));
+    }
+
+    /// Tests that the filter headers and filters of blocks that are no longer part of the active
+    /// chain are removed.
+    #[test]
+    fn test_update_chain_after_reorg() {
+        let (mut blockchain, headers) = regtest_blockchain(4);
+        let chain = hashes(&headers);
+        let mut state = BlockFilterState::new(&MetricsRegistry::default());
+        state.update_chain(&blockchain);
+        state
+            .add_filter_headers(&cfheaders_message(&chain, 0, 5))
+            .unwrap();
+        for height in 0..5 {
+            state.add_filter(&cfilter_message(&chain, height)).unwrap();
+        }
+
+        // Fork off after block 2 with a longer chain.
+        let fork = generate_headers(chain[2], headers[2].time, 3, &chain);
+        blockchain.add_headers(&fork);
+        assert_eq!(
+            blockchain.get_active_chain_tip().header.block_hash(),
+            fork[2].block_hash()
+        );
+        state.update_chain(&blockchain);
+
+        assert_eq!(state.filter_headers_len(), 3);
+        assert_eq!(state.get_height(&chain[3]), None);
+        assert!(!state.has_filter(&chain[3]));
+        assert!(!state.has_filter(&chain[4]));
+        assert!(state.has_filter(&chain[2]));
+        assert_eq!(state.get_block_hash(3), Some(fork[0].block_hash()));
+        assert_eq!(state.get_block_hash(5), Some(fork[2].block_hash()));
+        assert_eq!(
+            state.next_filter_headers_range(2_000),
+            Some((3, fork[2].block_hash()))
+        );
+
+        state.prune_filters_below_height(2);
+        assert!(!state.has_filter(&chain[1]));
+        assert!(state.has_filter(&chain[2]));
+    }
+
+    /// Tests that filter hashes are only added right after the verified filter headers and for
+    /// blocks of the active chain.
+    #[test]
+    fn test_add_filter_hashes() {
+        let (blockchain, headers) = regtest_blockchain(3);
+        let chain = hashes(&headers);
+        let mut state = BlockFilterState::new(&MetricsRegistry::default());
+        state.update_chain(&blockchain);
+
+        let message = cfheaders_message(&chain, 0, 4);
+        assert_eq!(state.check_filter_headers(&message), Ok(0));
+        assert_eq!(state.filter_headers_len(), 0);
+
+        assert_eq!(
+            state.add_filter_hashes(1, &message.filter_hashes[1..2]),
+            Err(BlockFilterError::UnexpectedStartHeight(1))
+        );
+        assert_eq!(
+            state.add_filter_hashes(0, &message.filter_hashes[..2]),
+            Ok(2)
+        );
+        assert_eq!(
+            state.add_filter_hashes(2, &[message.filter_hashes[2]; 3]),
+            Err(BlockFilterError::UnexpectedStartHeight(2))
+        );
+        assert_eq!(
+            state.add_filter_hashes(2, &message.filter_hashes[2..]),
+            Ok(2)
+        );
+
+        // The filter headers are the same as if they were added from the message.
+        let mut other_state = BlockFilterState::new(&MetricsRegistry::default());
+        other_state.update_chain(&blockchain);
+        other_state.add_filter_headers(&message).unwrap();
+        assert_eq!(state.filter_headers, other_state.filter_headers);
+    }
+
+    /// Tests that filters are checked against the output scripts of their blocks.
+    #[test]
+    fn test_is_filter_consistent_with_block() {
+        let block = block_1();
+        let filter = basic_filter(&block, &Script::new());
+        assert!(is_filter_consistent_with_block(&filter, &block));
+
+        // The filter of another block does not contain the output script.
+        let other_filter = basic_filter(&block_2(), &Script::new());
+        assert!(!is_filter_consistent_with_block(&other_filter, &block));
+
+        // The block does not spend any outputs that could account for an additional element.
+        let mut content = vec![];
+        let mut writer = BlockFilterWriter::new(&mut content, &block);
+        writer.add_output_scripts();
+        writer.add_element(&[1, 2, 3]);
+        writer.finish().unwrap();
+        assert!(!is_filter_consistent_with_block(
+            &BlockFilter::new(&content),
+            &block
+        ));
+
+        assert!(!is_filter_consistent_with_block(
+            &BlockFilter::new(&[]),
+            &block
+        ));
+    }
+}
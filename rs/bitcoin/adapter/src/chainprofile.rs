@@ -0,0 +1,628 @@
+//! The module contains the parameters of the blockchains that the adapter can follow.
+//!
+//! The built-in profiles of the Bitcoin networks delegate header validation to
+//! `ic_btc_validation`. Other chains derived from Bitcoin are described with
+//! their own proof-of-work parameters and checkpoints and validated by
+//! [validate_pow_header].
+//!
+//! Only Litecoin-style chains are supported besides Bitcoin: chains whose
+//! headers are plain Bitcoin headers and whose difficulty is adjusted once per
+//! interval of blocks. Dogecoin is not supported, as its merge-mined headers
+//! carry an AuxPoW proof that the adapter does not decode, and its DigiShield
+//! rules adjust the difficulty on every block.
+use crate::common::BlockHeight;
+use bitcoin::{
+    blockdata::constants::genesis_block,
+    hashes::{hex::FromHex, hmac, sha256, Hash, HashEngine},
+    util::uint::Uint256,
+    BlockHash, BlockHeader, Network, TxMerkleNode,
+};
+use ic_btc_validation::{is_beyond_last_checkpoint, HeaderStore};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The number of previous headers whose median timestamp a new header must exceed.
+const MEDIAN_TIME_SPAN: usize = 11;
+
+/// The scrypt cost parameter of the Litecoin-style proof of work.
+const SCRYPT_N: usize = 1024;
+
+/// The hash function used to compute the proof of work of a header.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum PowAlgorithm {
+    /// The double SHA-256 of the header, i.e., the block hash (Bitcoin).
+    Sha256d,
+    /// scrypt of the header with N = 1024, r = 1 and p = 1 (Litecoin).
+    Scrypt,
+}
+
+/// The proof-of-work and difficulty adjustment rules of a chain.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PowParams {
+    /// The hash function used to compute the proof of work.
+    pub algorithm: PowAlgorithm,
+    /// The easiest allowed target in the compact representation.
+    pub pow_limit_bits: u32,
+    /// The expected number of seconds between two blocks.
+    pub target_spacing_seconds: u32,
+    /// The number of blocks between two difficulty adjustments.
+    pub adjustment_interval: u32,
+    /// When set, the time span of an adjustment is measured over the full
+    /// interval instead of the last `adjustment_interval - 1` blocks (Litecoin).
+    #[serde(default)]
+    pub full_interval_retarget: bool,
+    /// When set, a header that is more than twice the target spacing younger
+    /// than its parent may use the easiest target (testnet rule).
+    #[serde(default)]
+    pub allow_min_difficulty_blocks: bool,
+    /// When set, the difficulty never changes (regtest rule).
+    #[serde(default)]
+    pub no_retargeting: bool,
+}
+
+impl PowParams {
+    fn pow_limit(&self) -> Uint256 {
+        BlockHeader::u256_from_compact_target(self.pow_limit_bits)
+    }
+
+    fn target_timespan(&self) -> u32 {
+        self.target_spacing_seconds * self.adjustment_interval
+    }
+}
+
+/// The rules that the headers of a chain must satisfy.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ConsensusRules {
+    /// The headers are validated with the rules of the given Bitcoin network.
+    Bitcoin(Network),
+    /// The headers are validated with the given proof-of-work rules.
+    ///
+    /// Chains that merge-mine their blocks (AuxPoW, e.g., Dogecoin after
+    /// height 371,337) or that adjust the difficulty on every block (e.g.,
+    /// Dogecoin's DigiShield) are not supported, see [ChainProfile::validate].
+    Pow(PowParams),
+}
+
+/// A block that the chain is known to contain.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The height of the block.
+    pub height: BlockHeight,
+    /// The hash of the block.
+    pub hash: BlockHash,
+}
+
+/// The parameters of a blockchain that the adapter follows.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ChainProfile {
+    /// A human-readable name of the chain.
+    pub name: String,
+    /// The magic value that prefixes all network messages of the chain.
+    pub magic: u32,
+    /// The header of the first block of the chain.
+    pub genesis_header: BlockHeader,
+    /// The rules used to validate the headers.
+    pub consensus: ConsensusRules,
+    /// The blocks that the chain is known to contain. Headers that conflict
+    /// with a checkpoint are rejected, and the adapter does not serve blocks
+    /// until its header chain is past the last checkpoint. The profiles of the
+    /// Bitcoin networks use the checkpoints of `ic_btc_validation` instead.
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>,
+    /// The port that the nodes of the chain listen on by default.
+    pub default_port: u16,
+    /// The DNS seeds used when the configuration does not provide any.
+    #[serde(default)]
+    pub dns_seeds: Vec<String>,
+    /// The number of addresses needed to be maintained in the address book.
+    pub min_addresses: usize,
+    /// The maximum number of addresses that can be stored in the address book.
+    pub max_addresses: usize,
+}
+
+impl ChainProfile {
+    /// Returns the profile of the given Bitcoin network.
+    pub fn bitcoin(network: Network) -> Self {
+        let (default_port, min_addresses, max_addresses) = match network {
+            Network::Bitcoin => (8333, 500, 2000),
+            Network::Testnet => (18333, 100, 1000),
+            Network::Signet | Network::Regtest => (8333, 1, 1),
+        };
+        Self {
+            name: network.to_string(),
+            magic: network.magic(),
+            genesis_header: genesis_block(network).header,
+            consensus: ConsensusRules::Bitcoin(network),
+            checkpoints: vec![],
+            default_port,
+            dns_seeds: vec![],
+            min_addresses,
+            max_addresses,
+        }
+    }
+
+    /// Returns the profile of the Litecoin mainnet.
+    pub fn litecoin() -> Self {
+        Self {
+            name: "litecoin".to_string(),
+            magic: 0xdbb6c0fb,
+            genesis_header: BlockHeader {
+                version: 1,
+                prev_blockhash: BlockHash::default(),
+                merkle_root: TxMerkleNode::from_hex(
+                    "97ddfbbae6be97fd6cdf3e7ca13232a3afff2353e29badfab7f73011edd4ced9",
+                )
+                .expect("valid merkle root"),
+                time: 1317972665,
+                bits: 0x1e0ffff0,
+                nonce: 2084524493,
+            },
+            consensus: ConsensusRules::Pow(PowParams {
+                algorithm: PowAlgorithm::Scrypt,
+                pow_limit_bits: 0x1e0fffff,
+                target_spacing_seconds: 150,
+                adjustment_interval: 2016,
+                full_interval_retarget: true,
+                allow_min_difficulty_blocks: false,
+                no_retargeting: false,
+            }),
+            // The first checkpoints of Litecoin Core.
+            checkpoints: vec![
+                Checkpoint {
+                    height: 1500,
+                    hash: BlockHash::from_hex(
+                        "841a2965955dd288cfa707a755d05a54e45f8bd476835ec9af4402a2b59a2967",
+                    )
+                    .expect("valid block hash"),
+                },
+                Checkpoint {
+                    height: 4032,
+                    hash: BlockHash::from_hex(
+                        "9ce90e427198fc0ef05e5905ce3503725b80e26afd35a987965fd7e3d9cf0846",
+                    )
+                    .expect("valid block hash"),
+                },
+            ],
+            default_port: 9333,
+            dns_seeds: vec![
+                "seed-a.litecoin.loshan.co.uk".to_string(),
+                "dnsseed.thrasher.io".to_string(),
+                "dnsseed.litecointools.com".to_string(),
+                "dnsseed.litecoinpool.org".to_string(),
+            ],
+            min_addresses: 500,
+            max_addresses: 2000,
+        }
+    }
+
+    /// Checks that the adapter can follow the chain. Profiles whose
+    /// difficulty is adjusted on every block, such as the DigiShield rules of
+    /// Dogecoin, are rejected, as they would be validated with the rules of
+    /// Bitcoin.
+    pub fn validate(&self) -> Result<(), String> {
+        if let ConsensusRules::Pow(params) = &self.consensus {
+            if params.adjustment_interval < 2 && !params.no_retargeting {
+                return Err(format!(
+                    "The chain {} adjusts the difficulty on every block, which is not supported",
+                    self.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the Bitcoin network whose rules the chain follows, if any.
+    pub fn bitcoin_network(&self) -> Option<Network> {
+        match self.consensus {
+            ConsensusRules::Bitcoin(network) => Some(network),
+            ConsensusRules::Pow(_) => None,
+        }
+    }
+
+    /// Returns true if a header chain with the given height is past the last
+    /// checkpoint of the chain.
+    pub fn is_beyond_last_checkpoint(&self, height: BlockHeight) -> bool {
+        match self.consensus {
+            ConsensusRules::Bitcoin(network) => is_beyond_last_checkpoint(&network, height),
+            ConsensusRules::Pow(_) => self
+                .checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint.height)
+                .max()
+                .map_or(true, |last| last <= height),
+        }
+    }
+}
+
+/// The reasons why a header violates the proof-of-work rules of a chain.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PowHeaderError {
+    /// The previous header is not known.
+    #[error("The previous header {0} is not known")]
+    PrevHeaderNotFound(BlockHash),
+    /// The chain has a different block at the height of the header.
+    #[error("The header at height {height} does not match the checkpoint {expected}")]
+    CheckpointMismatch {
+        /// The height of the header.
+        height: BlockHeight,
+        /// The hash of the checkpoint at that height.
+        expected: BlockHash,
+    },
+    /// The timestamp is not after the median timestamp of the previous headers.
+    #[error("The header timestamp {time} is not after the median time past {median_time_past}")]
+    HeaderIsOld {
+        /// The timestamp of the header.
+        time: u32,
+        /// The median timestamp of the previous headers.
+        median_time_past: u32,
+    },
+    /// The target of the header differs from the target required by the chain.
+    #[error("The header target {actual:#x} does not match the required target {expected:#x}")]
+    UnexpectedTarget {
+        /// The target of the header.
+        actual: u32,
+        /// The target required by the chain.
+        expected: u32,
+    },
+    /// The proof-of-work hash of the header is above its target.
+    #[error("The header proof of work does not meet its target")]
+    InsufficientPow,
+}
+
+/// Validates a header against the proof-of-work rules and the checkpoints of a chain.
+pub fn validate_pow_header(
+    params: &PowParams,
+    checkpoints: &[Checkpoint],
+    store: &impl HeaderStore,
+    header: &BlockHeader,
+) -> Result<(), PowHeaderError> {
+    let (prev_header, prev_height) = store
+        .get_header(&header.prev_blockhash)
+        .ok_or(PowHeaderError::PrevHeaderNotFound(header.prev_blockhash))?;
+
+    let height = prev_height + 1;
+    if let Some(checkpoint) = checkpoints.iter().find(|c| c.height == height) {
+        if checkpoint.hash != header.block_hash() {
+            return Err(PowHeaderError::CheckpointMismatch {
+                height,
+                expected: checkpoint.hash,
+            });
+        }
+    }
+
+    let median_time_past = median_time_past(store, prev_header);
+    if header.time <= median_time_past {
+        return Err(PowHeaderError::HeaderIsOld {
+            time: header.time,
+            median_time_past,
+        });
+    }
+
+    let expected = next_required_bits(params, store, &prev_header, prev_height, header.time);
+    if header.bits != expected {
+        return Err(PowHeaderError::UnexpectedTarget {
+            actual: header.bits,
+            expected,
+        });
+    }
+
+    if pow_hash(params.algorithm, header) > header.target() {
+        return Err(PowHeaderError::InsufficientPow);
+    }
+    Ok(())
+}
+
+/// Returns the median timestamp of the given header and its ancestors.
+fn median_time_past(store: &impl HeaderStore, mut header: BlockHeader) -> u32 {
+    let mut times = vec![header.time];
+    while times.len() < MEDIAN_TIME_SPAN {
+        match store.get_header(&header.prev_blockhash) {
+            Some((prev_header, _)) => {
+                times.push(prev_header.time);
+                header = prev_header;
+            }
+            None => break,
+        }
+    }
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// Returns the target, in the compact representation, that the successor of
+/// `prev_header` must have.
+fn next_required_bits(
+    params: &PowParams,
+    store: &impl HeaderStore,
+    prev_header: &BlockHeader,
+    prev_height: BlockHeight,
+    time: u32,
+) -> u32 {
+    if params.no_retargeting {
+        return prev_header.bits;
+    }
+
+    let height = prev_height + 1;
+    if height % params.adjustment_interval != 0 {
+        if !params.allow_min_difficulty_blocks {
+            return prev_header.bits;
+        }
+        if time > prev_header.time + 2 * params.target_spacing_seconds {
+            return params.pow_limit_bits;
+        }
+        // Use the target of the last header that was not mined with the easiest target.
+        let mut header = *prev_header;
+        let mut header_height = prev_height;
+        while header_height % params.adjustment_interval != 0
+            && header.bits == params.pow_limit_bits
+        {
+            match store.get_header(&header.prev_blockhash) {
+                Some((parent, parent_height)) => {
+                    header = parent;
+                    header_height = parent_height;
+                }
+                None => break,
+            }
+        }
+        return header.bits;
+    }
+
+    // The first adjustment cannot go back further than the genesis header.
+    let blocks_back = if params.full_interval_retarget && height != params.adjustment_interval {
+        params.adjustment_interval
+    } else {
+        params.adjustment_interval - 1
+    };
+    let mut first_header = *prev_header;
+    for _ in 0..blocks_back {
+        match store.get_header(&first_header.prev_blockhash) {
+            Some((parent, _)) => first_header = parent,
+            None => break,
+        }
+    }
+
+    let target_timespan = params.target_timespan();
+    let actual_timespan = prev_header
+        .time
+        .saturating_sub(first_header.time)
+        .clamp(target_timespan / 4, target_timespan * 4);
+
+    let pow_limit = params.pow_limit();
+    // Halve the target before multiplying it if it could overflow.
+    let shift = usize::from(prev_header.target().bits() > pow_limit.bits() - 1);
+    let target = (prev_header.target() >> shift).mul_u32(actual_timespan)
+        / Uint256::from_u64(target_timespan as u64).expect("u64 fits into Uint256");
+    let target = target << shift;
+
+    BlockHeader::compact_target_from_u256(&target.min(pow_limit))
+}
+
+/// Computes the proof-of-work hash of the header as a number that must not
+/// exceed the header's target.
+fn pow_hash(algorithm: PowAlgorithm, header: &BlockHeader) -> Uint256 {
+    let mut hash = match algorithm {
+        PowAlgorithm::Sha256d => header.block_hash().into_inner(),
+        PowAlgorithm::Scrypt => {
+            let encoded = bitcoin::consensus::serialize(header);
+            let mut output = [0u8; 32];
+            scrypt(&encoded, &encoded, SCRYPT_N, &mut output);
+            output
+        }
+    };
+    // Hashes are interpreted as little-endian numbers.
+    hash.reverse();
+    Uint256::from_be_bytes(hash)
+}
+
+/// Computes scrypt with the cost parameter `n` and the parameters r = 1 and
+/// p = 1 (RFC 7914).
+fn scrypt(password: &[u8], salt: &[u8], n: usize, output: &mut [u8]) {
+    let mut block = [0u8; 128];
+    pbkdf2_hmac_sha256(password, salt, &mut block);
+
+    let mut x = [0u32; 32];
+    for (word, chunk) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    let mut v = vec![[0u32; 32]; n];
+    for entry in v.iter_mut() {
+        *entry = x;
+        block_mix(&mut x);
+    }
+    for _ in 0..n {
+        let j = x[16] as usize & (n - 1);
+        for (word, other) in x.iter_mut().zip(v[j].iter()) {
+            *word ^= other;
+        }
+        block_mix(&mut x);
+    }
+
+    for (chunk, word) in block.chunks_exact_mut(4).zip(x.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    pbkdf2_hmac_sha256(password, &block, output);
+}
+
+/// Computes PBKDF2 with HMAC-SHA256 and a single iteration.
+fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], output: &mut [u8]) {
+    for (i, chunk) in output.chunks_mut(32).enumerate() {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(password);
+        engine.input(salt);
+        engine.input(&(i as u32 + 1).to_be_bytes());
+        let mac = hmac::Hmac::<sha256::Hash>::from_engine(engine);
+        chunk.copy_from_slice(&mac[..chunk.len()]);
+    }
+}
+
+/// The scrypt BlockMix function with r = 1.
+fn block_mix(x: &mut [u32; 32]) {
+    let (b0, b1) = x.split_at_mut(16);
+    for (word, other) in b0.iter_mut().zip(b1.iter()) {
+        *word ^= other;
+    }
+    salsa20_8(b0);
+    for (word, other) in b1.iter_mut().zip(b0.iter()) {
+        *word ^= other;
+    }
+    salsa20_8(b1);
+}
+
+/// The Salsa20/8 core function.
+fn salsa20_8(block: &mut [u32]) {
+    let mut x = [0u32; 16];
+    x.copy_from_slice(block);
+    for _ in 0..4 {
+        for (a, b, c, d) in [
+            (4, 0, 12, 7),
+            (8, 4, 0, 9),
+            (12, 8, 4, 13),
+            (0, 12, 8, 18),
+            (9, 5, 1, 7),
+            (13, 9, 5, 9),
+            (1, 13, 9, 13),
+            (5, 1, 13, 18),
+            (14, 10, 6, 7),
+            (2, 14, 10, 9),
+            (6, 2, 14, 13),
+            (10, 6, 2, 18),
+            (3, 15, 11, 7),
+            (7, 3, 15, 9),
+            (11, 7, 3, 13),
+            (15, 11, 7, 18),
+            (1, 0, 3, 7),
+            (2, 1, 0, 9),
+            (3, 2, 1, 13),
+            (0, 3, 2, 18),
+            (6, 5, 4, 7),
+            (7, 6, 5, 9),
+            (4, 7, 6, 13),
+            (5, 4, 7, 18),
+            (11, 10, 9, 7),
+            (8, 11, 10, 9),
+            (9, 8, 11, 13),
+            (10, 9, 8, 18),
+            (12, 15, 14, 7),
+            (13, 12, 15, 9),
+            (14, 13, 12, 13),
+            (15, 14, 13, 18),
+        ] {
+            x[a] ^= x[b].wrapping_add(x[c]).rotate_left(d);
+        }
+    }
+    for (word, mixed) in block.iter_mut().zip(x.iter()) {
+        *word = word.wrapping_add(*mixed);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    /// Returns the profile of a test chain with scrypt proof of work and a
+    /// difficulty adjustment every 8 blocks, along with its first 25 headers.
+    /// The difficulty is adjusted at heights 8, 16 and 24.
+    pub fn scrypt_test_chain() -> (ChainProfile, Vec<BlockHeader>) {
+        let headers_json = include_str!("../test_data/scrypt_test_chain_headers.json");
+        let headers: Vec<BlockHeader> = serde_json::from_str(headers_json).unwrap();
+        let profile = ChainProfile {
+            name: "scrypt-test".to_string(),
+            magic: 0xdab5bffa,
+            genesis_header: headers[0],
+            consensus: ConsensusRules::Pow(PowParams {
+                algorithm: PowAlgorithm::Scrypt,
+                pow_limit_bits: 0x1f0fffff,
+                target_spacing_seconds: 60,
+                adjustment_interval: 8,
+                full_interval_retarget: true,
+                allow_min_difficulty_blocks: false,
+                no_retargeting: false,
+            }),
+            checkpoints: vec![],
+            default_port: 19444,
+            dns_seeds: vec![],
+            min_addresses: 1,
+            max_addresses: 1,
+        };
+        (profile, headers)
+    }
+
+    /// Checks the implementation against the first test vector of RFC 7914.
+    #[test]
+    fn test_scrypt_rfc_7914_vector() {
+        let mut output = [0u8; 64];
+        scrypt(b"", b"", 16, &mut output);
+        assert_eq!(
+            hex::encode(output),
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+             fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+        );
+    }
+
+    #[test]
+    fn test_litecoin_genesis_header() {
+        let profile = ChainProfile::litecoin();
+        assert_eq!(
+            profile.genesis_header.block_hash().to_string(),
+            "12a765e31ffd4059bada1e25190f6e98c99d9714d334efa41a195a7e7e04bfe2"
+        );
+        assert!(
+            pow_hash(PowAlgorithm::Scrypt, &profile.genesis_header)
+                <= profile.genesis_header.target()
+        );
+        // The block hash of the genesis header does not meet the target.
+        assert!(
+            pow_hash(PowAlgorithm::Sha256d, &profile.genesis_header)
+                > profile.genesis_header.target()
+        );
+    }
+
+    #[test]
+    fn test_bitcoin_profiles_match_the_network_parameters() {
+        for network in [
+            Network::Bitcoin,
+            Network::Testnet,
+            Network::Signet,
+            Network::Regtest,
+        ] {
+            let profile = ChainProfile::bitcoin(network);
+            assert_eq!(profile.magic, network.magic());
+            assert_eq!(profile.genesis_header, genesis_block(network).header);
+            assert_eq!(profile.bitcoin_network(), Some(network));
+        }
+        assert_eq!(ChainProfile::litecoin().bitcoin_network(), None);
+    }
+
+    #[test]
+    fn test_litecoin_is_beyond_last_checkpoint() {
+        let profile = ChainProfile::litecoin();
+        assert!(!profile.is_beyond_last_checkpoint(0));
+        assert!(!profile.is_beyond_last_checkpoint(4031));
+        assert!(profile.is_beyond_last_checkpoint(4032));
+
+        // Chains without checkpoints are never gated.
+        let (profile, _) = scrypt_test_chain();
+        assert!(profile.is_beyond_last_checkpoint(0));
+    }
+
+    #[test]
+    fn test_validate_rejects_per_block_retargeting() {
+        let (profile, _) = scrypt_test_chain();
+        assert_eq!(profile.validate(), Ok(()));
+        assert_eq!(ChainProfile::litecoin().validate(), Ok(()));
+
+        let mut profile = profile;
+        if let ConsensusRules::Pow(params) = &mut profile.consensus {
+            params.adjustment_interval = 1;
+        }
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn test_chain_profile_serde_roundtrip() {
+        let (profile, _) = scrypt_test_chain();
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ChainProfile>(&json).unwrap(),
+            profile
+        );
+    }
+}
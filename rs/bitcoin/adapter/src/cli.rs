@@ -44,6 +44,10 @@ impl Cli {
                 ));
             }
         }
+
+        if let Some(chain_profile) = &config.chain_profile {
+            chain_profile.validate().map_err(CliError::Validation)?;
+        }
         Ok(config)
     }
 }
@@ -51,7 +55,7 @@ impl Cli {
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use crate::config::IncomingSource;
+    use crate::{chainprofile::ChainProfile, config::IncomingSource};
     use bitcoin::Network;
     use std::io::Write;
     use std::path::PathBuf;
@@ -91,6 +95,50 @@ pub mod test {
         "ipv6_only": true    
     }"#;
 
+    const LITECOIN_CONFIG: &str = r#"{
+        "network": "bitcoin",
+        "chain_profile": {
+            "name": "litecoin",
+            "magic": 3686187259,
+            "genesis_header": {
+                "version": 1,
+                "prev_blockhash": "0000000000000000000000000000000000000000000000000000000000000000",
+                "merkle_root": "97ddfbbae6be97fd6cdf3e7ca13232a3afff2353e29badfab7f73011edd4ced9",
+                "time": 1317972665,
+                "bits": 504365040,
+                "nonce": 2084524493
+            },
+            "consensus": {
+                "Pow": {
+                    "algorithm": "Scrypt",
+                    "pow_limit_bits": 504365055,
+                    "target_spacing_seconds": 150,
+                    "adjustment_interval": 2016,
+                    "full_interval_retarget": true
+                }
+            },
+            "checkpoints": [
+                {
+                    "height": 1500,
+                    "hash": "841a2965955dd288cfa707a755d05a54e45f8bd476835ec9af4402a2b59a2967"
+                },
+                {
+                    "height": 4032,
+                    "hash": "9ce90e427198fc0ef05e5905ce3503725b80e26afd35a987965fd7e3d9cf0846"
+                }
+            ],
+            "default_port": 9333,
+            "dns_seeds": [
+                "seed-a.litecoin.loshan.co.uk",
+                "dnsseed.thrasher.io",
+                "dnsseed.litecointools.com",
+                "dnsseed.litecoinpool.org"
+            ],
+            "min_addresses": 500,
+            "max_addresses": 2000
+        }
+    }"#;
+
     const TESTNET_BAD_SOCKS_CONFIG: &str = r#"{
         "network": "testnet",
         "socks_proxy": "socks5.notaproxy.com"        
@@ -175,4 +223,18 @@ pub mod test {
             IncomingSource::Path(PathBuf::from("/tmp/ic-btc-adapter.socket"))
         );
     }
+
+    #[test]
+    fn test_cli_get_config_good_chain_profile_json() {
+        let mut tmpfile = NamedTempFile::new().expect("Failed to create tmp file");
+        writeln!(tmpfile, "{}", LITECOIN_CONFIG).expect("Failed to write to tmp file");
+        let cli = Cli {
+            config: tmpfile.path().to_owned(),
+        };
+        let result = cli.get_config();
+        let config = result.unwrap();
+        assert_eq!(config.chain_profile(), ChainProfile::litecoin());
+        assert_eq!(config.network_port(), 9333);
+        assert_eq!(config.dns_seeds().len(), 4);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::chainprofile::ChainProfile;
 use bitcoin::Network;
 use ic_config::logger::Config as LoggerConfig;
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,10 @@ pub enum IncomingSource {
 pub struct Config {
     /// The type of Bitcoin network we plan to communicate to (e.g. Mainnet, Testnet, etc.).
     pub network: Network,
+    /// The parameters of the chain to follow. When set, the adapter follows this chain
+    /// instead of the Bitcoin `network`.
+    #[serde(default)]
+    pub chain_profile: Option<ChainProfile>,
     /// A list of DNS seeds for address discovery.
     #[serde(default)]
     pub dns_seeds: Vec<String>,
@@ -46,6 +51,10 @@ pub struct Config {
     /// Specifies which unix domain socket should be used for serving incoming requests.
     #[serde(default)]
     pub incoming_source: IncomingSource,
+    /// When this field is set to `true`, the adapter downloads the compact block filters
+    /// (BIP157/158) of the active chain and serves them via the `GetBlockFilters` RPC.
+    #[serde(default)]
+    pub block_filters: bool,
 }
 
 /// Set the default idle seconds to one hour.
@@ -54,12 +63,24 @@ fn default_idle_seconds() -> u64 {
 }
 
 impl Config {
-    /// This function returns the port to use based on the Bitcoin network provided.
+    /// This function returns the parameters of the chain that the adapter follows.
+    pub fn chain_profile(&self) -> ChainProfile {
+        self.chain_profile
+            .clone()
+            .unwrap_or_else(|| ChainProfile::bitcoin(self.network))
+    }
+
+    /// This function returns the port to use based on the chain provided.
     pub fn network_port(&self) -> u16 {
-        match self.network {
-            Network::Bitcoin => 8333,
-            Network::Testnet => 18333,
-            _ => 8333,
+        self.chain_profile().default_port
+    }
+
+    /// This function returns the DNS seeds to use, falling back to the seeds of the chain profile.
+    pub fn dns_seeds(&self) -> Vec<String> {
+        if self.dns_seeds.is_empty() {
+            self.chain_profile().dns_seeds
+        } else {
+            self.dns_seeds.clone()
         }
     }
 }
@@ -69,12 +90,14 @@ impl Default for Config {
         Self {
             dns_seeds: Default::default(),
             network: Network::Bitcoin,
+            chain_profile: None,
             socks_proxy: Default::default(),
             nodes: vec![],
             idle_seconds: default_idle_seconds(),
             ipv6_only: false,
             logger: LoggerConfig::default(),
             incoming_source: Default::default(),
+            block_filters: false,
         }
     }
 }
@@ -111,6 +134,16 @@ pub mod test {
             self
         }
 
+        pub fn with_chain_profile(mut self, chain_profile: ChainProfile) -> Self {
+            self.config.chain_profile = Some(chain_profile);
+            self
+        }
+
+        pub fn with_block_filters(mut self, block_filters: bool) -> Self {
+            self.config.block_filters = block_filters;
+            self
+        }
+
         pub fn with_ipv6_only(mut self, ipv6_only: bool) -> Self {
             self.config.ipv6_only = ipv6_only;
             self
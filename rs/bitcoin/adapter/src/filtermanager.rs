@@ -0,0 +1,1367 @@
+//! The module is responsible for downloading the compact block filter headers and filters
+//! (BIP157/158) of the active chain from the peers that serve them.
+//!
+//! The same filter headers are requested from several peers and only the filter headers the
+//! peers agree on are added. When the peers disagree on the filter of a block, the block is
+//! downloaded and the peers whose filter headers commit to a filter that does not belong to the
+//! block are disconnected. Filters are checked against the verified filter headers.
+use crate::{
+    blockfilterstate::{
+        is_filter_consistent_with_block, BlockFilterError, BlockFilterState, BASIC_FILTER_TYPE,
+    },
+    common::BlockHeight,
+    config::Config,
+    BlockchainState, Channel, Command, ProcessBitcoinNetworkMessageError,
+};
+use bitcoin::{
+    hash_types::FilterHash,
+    hashes::Hash,
+    network::{
+        constants::ServiceFlags,
+        message::NetworkMessage,
+        message_blockdata::Inventory,
+        message_filter::{CFHeaders, CFilter, GetCFHeaders, GetCFilters},
+    },
+    util::bip158::BlockFilter,
+    Block, BlockHash,
+};
+use hashlink::{LinkedHashMap, LinkedHashSet};
+use ic_logger::{debug, trace, warn, ReplicaLogger};
+use rand::seq::SliceRandom;
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::Arc,
+    time::Instant,
+};
+use tokio::sync::Mutex;
+
+/// The maximum number of filter headers in a `cfheaders` message.
+/// https://github.com/bitcoin/bips/blob/master/bip-0157.mediawiki#getcfheaders
+const MAX_FILTER_HEADERS_PER_REQUEST: usize = 2_000;
+
+/// The maximum number of peers the same filter headers are requested from.
+/// https://github.com/bitcoin/bips/blob/master/bip-0157.mediawiki#client-operation
+const MAX_FILTER_HEADER_PEERS: usize = 3;
+
+/// The maximum number of filters requested with a single `getcfilters` message.
+/// BIP157 allows up to 1000 filters per request.
+const MAX_FILTERS_PER_REQUEST: usize = 100;
+
+/// The maximum number of filters that have been requested but not received yet.
+const MAX_FILTERS_IN_FLIGHT: usize = 1_000;
+
+/// This constant is the maximum number of seconds to wait until we get a response to the
+/// `getcfheaders`, `getcfilters` or `getdata` requests sent by us.
+const FILTER_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// This constant is the maximum number of seconds to wait until the block and the filters
+/// needed to resolve a disagreement about filter headers are received.
+const FILTER_HEADER_CONFLICT_TIMEOUT_SECS: u64 = 120;
+
+/// The number of disagreements about filter headers that could not be resolved a peer may take
+/// part in before it is disconnected.
+const MAX_UNRESOLVED_FILTER_HEADER_CONFLICTS: usize = 3;
+
+/// This struct stores the information related to a `getcfheaders` or `getcfilters` request.
+#[derive(Debug)]
+struct FilterRequestInfo {
+    /// This field stores the socket address of the Bitcoin node to which the request was sent.
+    socket: SocketAddr,
+    /// This field contains the time at which the request was sent.
+    sent_at: Instant,
+}
+
+impl FilterRequestInfo {
+    fn new(socket: SocketAddr) -> Self {
+        Self {
+            socket,
+            sent_at: Instant::now(),
+        }
+    }
+
+    fn has_timed_out(&self) -> bool {
+        self.sent_at.elapsed().as_secs() >= FILTER_REQUEST_TIMEOUT_SECS
+    }
+}
+
+/// This struct stores a disagreement between peers about the filter of a block. The filter
+/// headers of the peers agree up to the previous block.
+#[derive(Debug)]
+struct FilterHeaderConflict {
+    /// This field contains the height of the block.
+    height: BlockHeight,
+    /// This field contains the hash of the block.
+    block_hash: BlockHash,
+    /// This field contains the filters of the block the peers' filter headers commit to.
+    candidates: Vec<FilterCandidate>,
+    /// This field contains the block once it is received. The block is requested by the filter
+    /// manager itself, as the blocks requested by the blockchain manager are dropped once they
+    /// are below the anchor, which the block usually is.
+    block: Option<Block>,
+    /// Records the outstanding `getdata` request for the block.
+    getdata_request: Option<FilterRequestInfo>,
+    /// This field contains the time at which the disagreement was detected.
+    detected_at: Instant,
+}
+
+/// This struct stores a filter of a block along with the peers whose filter headers commit to it.
+#[derive(Debug)]
+struct FilterCandidate {
+    /// This field contains the hash of the filter.
+    filter_hash: FilterHash,
+    /// This field contains the peers whose filter headers commit to the filter. The filter is
+    /// requested from the first peer.
+    peers: Vec<SocketAddr>,
+    /// This field contains the filter once it is received and matches `filter_hash`.
+    filter: Option<BlockFilter>,
+}
+
+/// The FilterManager struct handles interactions that involve compact block filters.
+pub struct FilterManager {
+    /// This field is used to follow the active chain.
+    blockchain: Arc<Mutex<BlockchainState>>,
+
+    /// This field stores the verified filter headers and filters.
+    filter_state: Arc<Mutex<BlockFilterState>>,
+
+    /// When this field is set to `false`, no filter headers or filters are downloaded.
+    enabled: bool,
+
+    /// This field contains the peers that advertised serving compact block filters.
+    filter_peers: HashSet<SocketAddr>,
+
+    /// Records the outstanding `getcfheaders` requests. The same filter headers are requested
+    /// from several peers and the next filter headers are only requested once all of them
+    /// responded or timed out, as the filter headers need to be verified in order.
+    getcfheaders_requests: Vec<FilterRequestInfo>,
+
+    /// Stores the `cfheaders` messages received in response to the outstanding `getcfheaders`
+    /// requests by the peers that sent them.
+    cfheaders_responses: HashMap<SocketAddr, CFHeaders>,
+
+    /// Records the disagreement between peers about the filter headers that is being resolved.
+    /// No filter headers are requested while this field is set.
+    filter_header_conflict: Option<FilterHeaderConflict>,
+
+    /// Counts the disagreements about filter headers that could not be resolved by the peers
+    /// that took part in them. Filter headers are requested from the peers with the fewest
+    /// unresolved disagreements first.
+    unresolved_conflicts: HashMap<SocketAddr, usize>,
+
+    /// Records the outstanding `getcfilters` requests by the block hashes of the requested filters.
+    /// An entry is removed when the corresponding `cfilter` message is received.
+    getcfilters_requests: LinkedHashMap<BlockHash, FilterRequestInfo>,
+
+    /// This queue stores the block hashes whose filters are yet to be downloaded.
+    ///
+    /// A block hash is added when the `GetBlockFilters` request is processed and removed when a
+    /// `getcfilters` message requesting its filter is sent.
+    filter_sync_queue: LinkedHashSet<BlockHash>,
+
+    logger: ReplicaLogger,
+}
+
+impl FilterManager {
+    /// Creates a new FilterManager that downloads the filters of the active chain of the given
+    /// blockchain state into the given filter state.
+    pub fn new(
+        config: &Config,
+        blockchain: Arc<Mutex<BlockchainState>>,
+        filter_state: Arc<Mutex<BlockFilterState>>,
+        logger: ReplicaLogger,
+    ) -> Self {
+        Self {
+            blockchain,
+            filter_state,
+            enabled: config.block_filters,
+            filter_peers: HashSet::new(),
+            getcfheaders_requests: vec![],
+            cfheaders_responses: HashMap::new(),
+            filter_header_conflict: None,
+            unresolved_conflicts: HashMap::new(),
+            getcfilters_requests: LinkedHashMap::new(),
+            filter_sync_queue: LinkedHashSet::new(),
+            logger,
+        }
+    }
+
+    /// This method is used when the adapter is no longer receiving RPC calls from the replica.
+    /// Clears the filter cache, the filter peers, the filters to be synced and the outstanding
+    /// requests. The verified filter headers are kept.
+    pub async fn make_idle(&mut self) {
+        self.filter_peers.clear();
+        self.getcfheaders_requests.clear();
+        self.cfheaders_responses.clear();
+        self.filter_header_conflict = None;
+        self.unresolved_conflicts.clear();
+        self.getcfilters_requests.clear();
+        self.filter_sync_queue.clear();
+        self.filter_state.lock().await.clear_filters();
+    }
+
+    /// Adds the given block hashes to the filter sync queue unless their filters are already
+    /// downloaded or requested.
+    pub async fn enqueue_filters_to_download(&mut self, block_hashes: Vec<BlockHash>) {
+        if !self.enabled {
+            return;
+        }
+        let filter_state = self.filter_state.lock().await;
+        for block_hash in block_hashes {
+            if !filter_state.has_filter(&block_hash)
+                && !self.getcfilters_requests.contains_key(&block_hash)
+            {
+                self.filter_sync_queue.insert(block_hash);
+            }
+        }
+    }
+
+    /// Removes the filters of the given blocks and of the blocks below the anchor from the
+    /// filter cache.
+    pub async fn prune_filters(
+        &mut self,
+        anchor: BlockHash,
+        processed_block_hashes: Vec<BlockHash>,
+    ) {
+        let mut filter_state = self.filter_state.lock().await;
+        filter_state.prune_filters(&processed_block_hashes);
+        if let Some(anchor_height) = filter_state.get_height(&anchor) {
+            filter_state.prune_filters_below_height(anchor_height);
+        }
+    }
+
+    /// Returns `true` if the given message contains the block requested in order to resolve a
+    /// disagreement about filter headers. The blockchain manager does not know about this block.
+    pub fn is_requested_block(&self, message: &NetworkMessage) -> bool {
+        match message {
+            NetworkMessage::Block(block) => self.is_block_requested(&block.block_hash()),
+            _ => false,
+        }
+    }
+
+    fn is_block_requested(&self, block_hash: &BlockHash) -> bool {
+        self.filter_header_conflict
+            .as_ref()
+            .map_or(false, |conflict| {
+                conflict.block.is_none() && conflict.block_hash == *block_hash
+            })
+    }
+
+    /// Follows the active chain, retries timed-out requests, verifies the received filter headers
+    /// and sends `getcfheaders` and `getcfilters` requests to the peers serving compact block
+    /// filters.
+    pub async fn tick(&mut self, channel: &mut impl Channel) {
+        if !self.enabled {
+            return;
+        }
+
+        // The states are cloned as the guards are held while the requests are updated.
+        let (blockchain, filter_state) = (self.blockchain.clone(), self.filter_state.clone());
+        let blockchain = blockchain.lock().await;
+        let mut filter_state = filter_state.lock().await;
+        filter_state.update_chain(&blockchain);
+
+        self.getcfheaders_requests
+            .retain(|request| !request.has_timed_out());
+        let timed_out_block_hashes: Vec<BlockHash> = self
+            .getcfilters_requests
+            .iter()
+            .filter(|(_, request)| request.has_timed_out())
+            .map(|(block_hash, _)| *block_hash)
+            .collect();
+        for block_hash in timed_out_block_hashes {
+            self.getcfilters_requests.remove(&block_hash);
+            self.filter_sync_queue.insert(block_hash);
+        }
+
+        if self.getcfheaders_requests.is_empty() {
+            self.process_cfheaders_responses(channel, &blockchain, &mut filter_state);
+        }
+        self.resolve_filter_header_conflict(channel, &blockchain, &mut filter_state);
+        drop(blockchain);
+
+        let mut peers: Vec<SocketAddr> = channel
+            .available_connections()
+            .into_iter()
+            .filter(|addr| self.filter_peers.contains(addr))
+            .collect();
+        if peers.is_empty() {
+            return;
+        }
+        let mut rng = rand::thread_rng();
+        peers.shuffle(&mut rng);
+        peers.sort_by_key(|addr| {
+            self.unresolved_conflicts
+                .get(addr)
+                .copied()
+                .unwrap_or_default()
+        });
+
+        if self.getcfheaders_requests.is_empty() && self.filter_header_conflict.is_none() {
+            if let Some((start_height, stop_hash)) =
+                filter_state.next_filter_headers_range(MAX_FILTER_HEADERS_PER_REQUEST)
+            {
+                for peer in peers.iter().take(MAX_FILTER_HEADER_PEERS) {
+                    trace!(
+                        self.logger,
+                        "Sending getcfheaders to {} starting at height {}",
+                        peer,
+                        start_height
+                    );
+                    channel
+                        .send(Command {
+                            address: Some(*peer),
+                            message: NetworkMessage::GetCFHeaders(GetCFHeaders {
+                                filter_type: BASIC_FILTER_TYPE,
+                                start_height,
+                                stop_hash,
+                            }),
+                        })
+                        .ok();
+                    self.getcfheaders_requests
+                        .push(FilterRequestInfo::new(*peer));
+                }
+            }
+        }
+
+        // Collect the filters that can be verified once they are received.
+        let capacity = MAX_FILTERS_IN_FLIGHT.saturating_sub(self.getcfilters_requests.len());
+        let mut ready: Vec<(BlockHeight, BlockHash)> = vec![];
+        let mut deferred = vec![];
+        while ready.len() < capacity {
+            let block_hash = match self.filter_sync_queue.pop_front() {
+                Some(block_hash) => block_hash,
+                None => break,
+            };
+            if filter_state.has_filter(&block_hash)
+                || self.getcfilters_requests.contains_key(&block_hash)
+            {
+                continue;
+            }
+            match filter_state.get_height(&block_hash) {
+                Some(height) if (height as usize) < filter_state.filter_headers_len() => {
+                    ready.push((height, block_hash));
+                }
+                // The filter header of the block is not verified yet.
+                Some(_) => deferred.push(block_hash),
+                // The block is no longer part of the active chain.
+                None => {}
+            }
+        }
+        for block_hash in deferred {
+            self.filter_sync_queue.insert(block_hash);
+        }
+
+        // Request the filters of consecutive blocks together, spreading the requests over the peers.
+        ready.sort_unstable();
+        let mut peers = peers.iter().cycle();
+        for batch in consecutive_batches(&ready) {
+            let (peer, (start_height, _), (_, stop_hash)) =
+                match (peers.next(), batch.first(), batch.last()) {
+                    (Some(peer), Some(first), Some(last)) => (*peer, *first, *last),
+                    _ => break,
+                };
+            trace!(
+                self.logger,
+                "Sending getcfilters to {} for {} blocks starting at height {}",
+                peer,
+                batch.len(),
+                start_height
+            );
+            channel
+                .send(Command {
+                    address: Some(peer),
+                    message: NetworkMessage::GetCFilters(GetCFilters {
+                        filter_type: BASIC_FILTER_TYPE,
+                        start_height,
+                        stop_hash,
+                    }),
+                })
+                .ok();
+            for (_, block_hash) in batch {
+                self.getcfilters_requests
+                    .insert(*block_hash, FilterRequestInfo::new(peer));
+            }
+        }
+    }
+
+    /// This method compares the `cfheaders` messages received from the peers once all of them
+    /// responded or timed out. The filter headers all peers agree on are added. If the peers
+    /// disagree on the filter of the next block, the block and the filters the peers committed
+    /// to are requested in order to resolve the disagreement.
+    fn process_cfheaders_responses(
+        &mut self,
+        channel: &mut impl Channel,
+        blockchain: &BlockchainState,
+        filter_state: &mut BlockFilterState,
+    ) {
+        let mut responses = vec![];
+        for (addr, message) in self.cfheaders_responses.drain() {
+            match filter_state.check_filter_headers(&message) {
+                Ok(_) => responses.push((addr, message)),
+                // The filter headers are ignored without disconnecting the peer as the verified
+                // filter headers may have been sent by a single peer.
+                Err(err) => debug!(self.logger, "Ignoring cfheaders from {}: {}", addr, err),
+            }
+        }
+        let first = match responses.first() {
+            Some((_, message)) => message.filter_hashes.clone(),
+            None => return,
+        };
+
+        let start_height = filter_state.filter_headers_len() as BlockHeight;
+        let agreed = responses
+            .iter()
+            .map(|(_, message)| {
+                first
+                    .iter()
+                    .zip(message.filter_hashes.iter())
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            })
+            .min()
+            .unwrap_or_default();
+        match filter_state.add_filter_hashes(start_height, &first[..agreed]) {
+            Ok(added) => trace!(
+                self.logger,
+                "Added {} filter headers from {} peers",
+                added,
+                responses.len()
+            ),
+            Err(err) => {
+                debug!(self.logger, "Unable to add filter headers: {}", err);
+                return;
+            }
+        }
+
+        // Group the peers by the filter of the first block they disagree on.
+        let mut candidates: Vec<FilterCandidate> = vec![];
+        for (addr, message) in &responses {
+            let filter_hash = match message.filter_hashes.get(agreed) {
+                Some(filter_hash) => *filter_hash,
+                None => continue,
+            };
+            match candidates
+                .iter_mut()
+                .find(|candidate| candidate.filter_hash == filter_hash)
+            {
+                Some(candidate) => candidate.peers.push(*addr),
+                None => candidates.push(FilterCandidate {
+                    filter_hash,
+                    peers: vec![*addr],
+                    filter: None,
+                }),
+            }
+        }
+        if candidates.len() < 2 {
+            return;
+        }
+
+        let height = start_height + agreed as BlockHeight;
+        let block_hash = match filter_state.get_block_hash(height) {
+            Some(block_hash) => block_hash,
+            None => return,
+        };
+        warn!(
+            self.logger,
+            "Peers disagree on the filter of block {} at height {}", block_hash, height
+        );
+        for candidate in &candidates {
+            channel
+                .send(Command {
+                    address: Some(candidate.peers[0]),
+                    message: NetworkMessage::GetCFilters(GetCFilters {
+                        filter_type: BASIC_FILTER_TYPE,
+                        start_height: height,
+                        stop_hash: block_hash,
+                    }),
+                })
+                .ok();
+        }
+        self.filter_header_conflict = Some(FilterHeaderConflict {
+            height,
+            block_hash,
+            candidates,
+            block: blockchain.get_block(&block_hash).cloned(),
+            getdata_request: None,
+            detected_at: Instant::now(),
+        });
+    }
+
+    /// This method resolves the disagreement about filter headers once the block and the filters
+    /// the peers committed to are received. The peers whose filter headers commit to a filter
+    /// that does not belong to the block are disconnected. The filter header of the block is
+    /// added if exactly one of the filters may belong to the block.
+    fn resolve_filter_header_conflict(
+        &mut self,
+        channel: &mut impl Channel,
+        blockchain: &BlockchainState,
+        filter_state: &mut BlockFilterState,
+    ) {
+        let conflict = match &mut self.filter_header_conflict {
+            Some(conflict) => conflict,
+            None => return,
+        };
+        // The disagreement is no longer relevant if the active chain changed.
+        if filter_state.get_block_hash(conflict.height) != Some(conflict.block_hash)
+            || filter_state.filter_headers_len() != conflict.height as usize
+        {
+            self.filter_header_conflict = None;
+            return;
+        }
+        if conflict.block.is_none() {
+            conflict.block = blockchain.get_block(&conflict.block_hash).cloned();
+        }
+        if conflict.block.is_none()
+            && conflict
+                .getdata_request
+                .as_ref()
+                .map_or(true, FilterRequestInfo::has_timed_out)
+        {
+            // Any peer may serve the block as it is checked against its header.
+            let mut peers = channel.available_connections();
+            peers.shuffle(&mut rand::thread_rng());
+            if let Some(peer) = peers.first() {
+                trace!(
+                    self.logger,
+                    "Sending getdata to {} for block {}",
+                    peer,
+                    conflict.block_hash
+                );
+                channel
+                    .send(Command {
+                        address: Some(*peer),
+                        message: NetworkMessage::GetData(vec![Inventory::Block(
+                            conflict.block_hash,
+                        )]),
+                    })
+                    .ok();
+                conflict.getdata_request = Some(FilterRequestInfo::new(*peer));
+            }
+        }
+
+        let timed_out =
+            conflict.detected_at.elapsed().as_secs() >= FILTER_HEADER_CONFLICT_TIMEOUT_SECS;
+        let all_received = conflict
+            .candidates
+            .iter()
+            .all(|candidate| candidate.filter.is_some());
+        if conflict.block.is_none() || !(all_received || timed_out) {
+            if timed_out {
+                debug!(
+                    self.logger,
+                    "Timed out resolving the filter of block {}", conflict.block_hash
+                );
+                let peers: Vec<SocketAddr> = conflict
+                    .candidates
+                    .iter()
+                    .flat_map(|candidate| candidate.peers.iter().copied())
+                    .collect();
+                self.filter_header_conflict = None;
+                self.record_unresolved_conflict(channel, &peers);
+            }
+            return;
+        }
+
+        let conflict = match self.filter_header_conflict.take() {
+            Some(conflict) => conflict,
+            None => return,
+        };
+        let block = match &conflict.block {
+            Some(block) => block,
+            None => return,
+        };
+        let mut consistent = vec![];
+        // The peers whose filter headers could neither be verified nor refuted.
+        let mut unresolved = vec![];
+        for candidate in &conflict.candidates {
+            match &candidate.filter {
+                Some(filter) if is_filter_consistent_with_block(filter, block) => {
+                    consistent.push(candidate);
+                }
+                Some(_) => {
+                    for peer in &candidate.peers {
+                        warn!(
+                            self.logger,
+                            "Disconnecting {} as its filter headers commit to an invalid filter of block {}",
+                            peer,
+                            conflict.block_hash
+                        );
+                        self.filter_peers.remove(peer);
+                        channel.discard(peer);
+                    }
+                }
+                // The filter was not received in time.
+                None => unresolved.extend(candidate.peers.iter().copied()),
+            }
+        }
+        if let [candidate] = consistent[..] {
+            filter_state
+                .add_filter_hashes(conflict.height, &[candidate.filter_hash])
+                .ok();
+        } else {
+            // As the spent output scripts are not checked, a filter omitting them may pass the
+            // check, so the peers of several consistent filters cannot be told apart.
+            for candidate in consistent {
+                unresolved.extend(candidate.peers.iter().copied());
+            }
+        }
+        self.record_unresolved_conflict(channel, &unresolved);
+    }
+
+    /// Records that the given peers took part in a disagreement about filter headers that could
+    /// not be resolved. Peers that took part in too many of them are disconnected, so that the
+    /// filter headers are requested from other peers and the sync can make progress even if a
+    /// dishonest peer keeps committing to filters that cannot be refuted.
+    fn record_unresolved_conflict(&mut self, channel: &mut impl Channel, peers: &[SocketAddr]) {
+        for peer in peers {
+            let count = self.unresolved_conflicts.entry(*peer).or_default();
+            *count += 1;
+            if *count >= MAX_UNRESOLVED_FILTER_HEADER_CONFLICTS {
+                warn!(
+                    self.logger,
+                    "Disconnecting {} as it took part in {} unresolved disagreements about filter headers",
+                    peer,
+                    count
+                );
+                self.unresolved_conflicts.remove(peer);
+                self.filter_peers.remove(peer);
+                channel.discard(peer);
+            }
+        }
+    }
+
+    /// This method processes a `block` message received in response to the `getdata` request sent
+    /// to resolve a disagreement about filter headers. Other blocks are ignored.
+    fn received_block_message(&mut self, addr: &SocketAddr, block: &Block) {
+        if !self.is_block_requested(&block.block_hash()) {
+            return;
+        }
+        // The header of the block is part of the active chain, so the block is genuine if its
+        // transactions match the header.
+        if !block.check_merkle_root() {
+            warn!(
+                self.logger,
+                "Received block {} from {} that does not match its header",
+                block.block_hash(),
+                addr
+            );
+            return;
+        }
+        if let Some(conflict) = self.filter_header_conflict.as_mut() {
+            conflict.block = Some(block.clone());
+            conflict.getdata_request = None;
+        }
+    }
+
+    /// This method processes a `cfheaders` message received in response to our `getcfheaders` request.
+    fn received_cfheaders_message(
+        &mut self,
+        addr: &SocketAddr,
+        message: &CFHeaders,
+    ) -> Result<(), ProcessBitcoinNetworkMessageError> {
+        let index = match self
+            .getcfheaders_requests
+            .iter()
+            .position(|request| request.socket == *addr)
+        {
+            Some(index) => index,
+            // Unsolicited filter headers are ignored.
+            None => return Ok(()),
+        };
+        self.getcfheaders_requests.remove(index);
+
+        if message.filter_type != BASIC_FILTER_TYPE {
+            warn!(
+                self.logger,
+                "Received invalid cfheaders from {}: {}",
+                addr,
+                BlockFilterError::UnsupportedFilterType(message.filter_type)
+            );
+            self.filter_peers.remove(addr);
+            return Err(ProcessBitcoinNetworkMessageError::InvalidMessage);
+        }
+        self.cfheaders_responses.insert(*addr, message.clone());
+        Ok(())
+    }
+
+    /// This method processes a `cfilter` message received in response to a `getcfilters` request
+    /// sent to resolve a disagreement about filter headers. Returns `None` if the message is not
+    /// such a response.
+    fn received_conflicting_cfilter_message(
+        &mut self,
+        addr: &SocketAddr,
+        message: &CFilter,
+    ) -> Option<Result<(), ProcessBitcoinNetworkMessageError>> {
+        let candidate = self
+            .filter_header_conflict
+            .as_mut()
+            .filter(|conflict| conflict.block_hash == message.block_hash)?
+            .candidates
+            .iter_mut()
+            .find(|candidate| candidate.peers[0] == *addr && candidate.filter.is_none())?;
+
+        if message.filter_type != BASIC_FILTER_TYPE
+            || FilterHash::hash(&message.filter) != candidate.filter_hash
+        {
+            warn!(
+                self.logger,
+                "Received a cfilter from {} that does not match its filter headers", addr
+            );
+            self.filter_peers.remove(addr);
+            return Some(Err(ProcessBitcoinNetworkMessageError::InvalidMessage));
+        }
+        candidate.filter = Some(BlockFilter::new(&message.filter));
+        Some(Ok(()))
+    }
+
+    /// This method processes a `cfilter` message received in response to our `getcfilters` request.
+    async fn received_cfilter_message(
+        &mut self,
+        addr: &SocketAddr,
+        message: &CFilter,
+    ) -> Result<(), ProcessBitcoinNetworkMessageError> {
+        if let Some(result) = self.received_conflicting_cfilter_message(addr, message) {
+            return result;
+        }
+        match self.getcfilters_requests.get(&message.block_hash) {
+            Some(request) if request.socket == *addr => {}
+            // Unsolicited filters are ignored.
+            _ => return Ok(()),
+        }
+        self.getcfilters_requests.remove(&message.block_hash);
+
+        match self.filter_state.lock().await.add_filter(message) {
+            Ok(()) => Ok(()),
+            Err(err @ BlockFilterError::UnsupportedFilterType(_)) => {
+                warn!(
+                    self.logger,
+                    "Received invalid cfilter from {}: {}", addr, err
+                );
+                self.filter_sync_queue.insert(message.block_hash);
+                self.filter_peers.remove(addr);
+                Err(ProcessBitcoinNetworkMessageError::InvalidMessage)
+            }
+            Err(err @ BlockFilterError::FilterHeaderMismatch(_)) => {
+                // The peer is not disconnected as its filter may be valid if the verified filter
+                // header was sent by a single peer. Another peer may serve the filter.
+                warn!(
+                    self.logger,
+                    "Received invalid cfilter from {}: {}", addr, err
+                );
+                self.filter_sync_queue.insert(message.block_hash);
+                Ok(())
+            }
+            Err(err) => {
+                // The active chain changed since the request was sent.
+                debug!(self.logger, "Ignoring cfilter from {}: {}", addr, err);
+                Ok(())
+            }
+        }
+    }
+
+    /// This method is used to process messages from Bitcoin peers that are relevant for
+    /// downloading compact block filters.
+    pub async fn process_bitcoin_network_message(
+        &mut self,
+        addr: SocketAddr,
+        message: &NetworkMessage,
+    ) -> Result<(), ProcessBitcoinNetworkMessageError> {
+        match message {
+            NetworkMessage::Version(version_message)
+                if version_message.services.has(ServiceFlags::COMPACT_FILTERS) =>
+            {
+                self.filter_peers.insert(addr);
+                Ok(())
+            }
+            NetworkMessage::CFHeaders(cfheaders) => {
+                self.received_cfheaders_message(&addr, cfheaders)
+            }
+            NetworkMessage::CFilter(cfilter) => self.received_cfilter_message(&addr, cfilter).await,
+            NetworkMessage::Block(block) => {
+                self.received_block_message(&addr, block);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Splits the given blocks, sorted by height, into batches of consecutive blocks that can be
+/// requested with a single `getcfilters` message.
+fn consecutive_batches(blocks: &[(BlockHeight, BlockHash)]) -> Vec<&[(BlockHeight, BlockHash)]> {
+    let mut batches = vec![];
+    let mut start = 0;
+    for i in 1..=blocks.len() {
+        let ends_batch = i == blocks.len()
+            || i - start == MAX_FILTERS_PER_REQUEST
+            || blocks[i].0 != blocks[i - 1].0 + 1;
+        if ends_batch {
+            batches.push(&blocks[start..i]);
+            start = i;
+        }
+    }
+    batches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        blockfilterstate::test::{
+            basic_filter, cfheaders_message, cfheaders_message_with_filters, cfilter_message,
+            filter_content, regtest_blockchain,
+        },
+        common::test_common::TestChannel,
+        config::test::ConfigBuilder,
+    };
+    use bitcoin::{
+        hash_types::FilterHeader,
+        network::{address::Address, message_network::VersionMessage},
+        OutPoint, Script, Transaction, TxIn, Witness,
+    };
+    use ic_btc_adapter_test_utils::{block_1, block_2, generate_header, generate_headers};
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_metrics::MetricsRegistry;
+    use std::str::FromStr;
+
+    fn version_message(addr: &SocketAddr, services: ServiceFlags) -> NetworkMessage {
+        NetworkMessage::Version(VersionMessage::new(
+            services,
+            0,
+            Address::new(addr, ServiceFlags::NONE),
+            Address::new(addr, services),
+            1,
+            String::from("test"),
+            0,
+        ))
+    }
+
+    /// Creates a filter manager following a regtest chain with the given number of blocks
+    /// following the genesis block. Returns the manager and the hashes of the active chain.
+    fn setup(len: u32) -> (FilterManager, Vec<BlockHash>) {
+        let (blockchain, headers) = regtest_blockchain(len);
+        let chain = headers.iter().map(|header| header.block_hash()).collect();
+        (filter_manager(blockchain), chain)
+    }
+
+    /// Creates a filter manager following the active chain of the given blockchain state.
+    fn filter_manager(blockchain: BlockchainState) -> FilterManager {
+        let config = ConfigBuilder::new()
+            .with_network(bitcoin::Network::Regtest)
+            .with_block_filters(true)
+            .build();
+        FilterManager::new(
+            &config,
+            Arc::new(Mutex::new(blockchain)),
+            Arc::new(Mutex::new(BlockFilterState::new(
+                &MetricsRegistry::default(),
+            ))),
+            no_op_logger(),
+        )
+    }
+
+    /// Announces that the given peers serve compact block filters.
+    async fn add_filter_peers(manager: &mut FilterManager, addrs: &[SocketAddr]) {
+        for addr in addrs {
+            manager
+                .process_bitcoin_network_message(
+                    *addr,
+                    &version_message(addr, ServiceFlags::COMPACT_FILTERS),
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Returns the peers the pending commands are sent to, ensuring that all commands contain
+    /// the given message.
+    fn pop_commands(channel: &mut TestChannel, message: &NetworkMessage) -> HashSet<SocketAddr> {
+        let mut addrs = HashSet::new();
+        while let Some(command) = channel.pop_front() {
+            assert_eq!(&command.message, message);
+            addrs.insert(command.address.unwrap());
+        }
+        addrs
+    }
+
+    /// Tests that the filter headers and filters are only requested from peers serving them.
+    #[tokio::test]
+    async fn test_requests_filters_from_filter_peers() {
+        let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let other_addr = SocketAddr::from_str("127.0.0.1:8334").expect("bad address format");
+        let (mut manager, chain) = setup(9);
+        let mut channel = TestChannel::new(vec![addr, other_addr]);
+
+        manager.tick(&mut channel).await;
+        assert_eq!(channel.command_count(), 0);
+
+        manager
+            .process_bitcoin_network_message(
+                other_addr,
+                &version_message(&other_addr, ServiceFlags::NETWORK),
+            )
+            .await
+            .unwrap();
+        manager
+            .process_bitcoin_network_message(
+                addr,
+                &version_message(&addr, ServiceFlags::NETWORK | ServiceFlags::COMPACT_FILTERS),
+            )
+            .await
+            .unwrap();
+        manager.tick(&mut channel).await;
+        assert_eq!(
+            channel.pop_front(),
+            Some(Command {
+                address: Some(addr),
+                message: NetworkMessage::GetCFHeaders(GetCFHeaders {
+                    filter_type: BASIC_FILTER_TYPE,
+                    start_height: 0,
+                    stop_hash: chain[9],
+                }),
+            })
+        );
+
+        // Filters can only be requested once their filter headers are verified.
+        manager
+            .enqueue_filters_to_download(chain[2..6].to_vec())
+            .await;
+        manager.tick(&mut channel).await;
+        assert_eq!(channel.command_count(), 0);
+
+        manager
+            .process_bitcoin_network_message(
+                addr,
+                &NetworkMessage::CFHeaders(cfheaders_message(&chain, 0, 10)),
+            )
+            .await
+            .unwrap();
+        manager.tick(&mut channel).await;
+        assert_eq!(
+            channel.pop_front(),
+            Some(Command {
+                address: Some(addr),
+                message: NetworkMessage::GetCFilters(GetCFilters {
+                    filter_type: BASIC_FILTER_TYPE,
+                    start_height: 2,
+                    stop_hash: chain[5],
+                }),
+            })
+        );
+        assert_eq!(channel.command_count(), 0);
+
+        for height in 2..6 {
+            manager
+                .process_bitcoin_network_message(
+                    addr,
+                    &NetworkMessage::CFilter(cfilter_message(&chain, height)),
+                )
+                .await
+                .unwrap();
+        }
+        let filter_state = manager.filter_state.lock().await;
+        assert!((2..6).all(|height| filter_state.has_filter(&chain[height])));
+    }
+
+    /// Tests that a peer sending a filter that does not match its filter header is not disconnected
+    /// and that the filter is requested again.
+    #[tokio::test]
+    async fn test_invalid_filter_is_requested_again() {
+        let addr = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let (mut manager, chain) = setup(4);
+        let mut channel = TestChannel::new(vec![addr]);
+        add_filter_peers(&mut manager, &[addr]).await;
+        manager.tick(&mut channel).await;
+        manager
+            .process_bitcoin_network_message(
+                addr,
+                &NetworkMessage::CFHeaders(cfheaders_message(&chain, 0, 5)),
+            )
+            .await
+            .unwrap();
+        manager.enqueue_filters_to_download(vec![chain[3]]).await;
+        manager.tick(&mut channel).await;
+        assert_eq!(channel.command_count(), 2);
+
+        // Unsolicited filters are ignored.
+        let mut message = cfilter_message(&chain, 4);
+        message.filter = vec![];
+        assert!(manager
+            .process_bitcoin_network_message(addr, &NetworkMessage::CFilter(message))
+            .await
+            .is_ok());
+
+        let mut message = cfilter_message(&chain, 3);
+        message.filter = vec![];
+        assert!(manager
+            .process_bitcoin_network_message(addr, &NetworkMessage::CFilter(message))
+            .await
+            .is_ok());
+        assert!(manager.filter_peers.contains(&addr));
+        assert!(manager.filter_sync_queue.contains(&chain[3]));
+        assert!(!manager.filter_state.lock().await.has_filter(&chain[3]));
+
+        // A filter of an unsupported type is a protocol violation.
+        manager.tick(&mut channel).await;
+        let mut message = cfilter_message(&chain, 3);
+        message.filter_type = 1;
+        assert!(matches!(
+            manager
+                .process_bitcoin_network_message(addr, &NetworkMessage::CFilter(message))
+                .await,
+            Err(ProcessBitcoinNetworkMessageError::InvalidMessage)
+        ));
+        assert!(!manager.filter_peers.contains(&addr));
+    }
+
+    /// Tests that the same filter headers are requested from several peers and that only the
+    /// filter headers they agree on are added.
+    #[tokio::test]
+    async fn test_filter_headers_are_compared_across_peers() {
+        let addrs: Vec<SocketAddr> = (0..4)
+            .map(|i| SocketAddr::from_str(&format!("127.0.0.1:{}", 8333 + i)).unwrap())
+            .collect();
+        let (mut manager, chain) = setup(9);
+        let mut channel = TestChannel::new(addrs.clone());
+        add_filter_peers(&mut manager, &addrs).await;
+
+        manager.tick(&mut channel).await;
+        let request = NetworkMessage::GetCFHeaders(GetCFHeaders {
+            filter_type: BASIC_FILTER_TYPE,
+            start_height: 0,
+            stop_hash: chain[9],
+        });
+        let peers: Vec<SocketAddr> = pop_commands(&mut channel, &request).into_iter().collect();
+        assert_eq!(peers.len(), MAX_FILTER_HEADER_PEERS);
+
+        // The first peer sends fewer filter headers, the second sends filter headers that do not
+        // connect to the verified ones and the third does not respond. Filter headers sent again
+        // are ignored.
+        let mut not_connecting = cfheaders_message(&chain, 0, 10);
+        not_connecting.previous_filter_header = FilterHeader::hash(&[1]);
+        for (peer, message) in [
+            (peers[0], cfheaders_message(&chain, 0, 6)),
+            (peers[1], not_connecting),
+            (peers[0], cfheaders_message(&chain, 0, 10)),
+        ] {
+            manager
+                .process_bitcoin_network_message(peer, &NetworkMessage::CFHeaders(message))
+                .await
+                .unwrap();
+        }
+        manager.tick(&mut channel).await;
+        assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 0);
+        assert_eq!(channel.command_count(), 0);
+
+        // Time out the request sent to the third peer.
+        assert_eq!(manager.getcfheaders_requests.len(), 1);
+        manager.getcfheaders_requests.clear();
+        manager.tick(&mut channel).await;
+        assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 6);
+        assert!(addrs.iter().all(|addr| manager.filter_peers.contains(addr)));
+        let request = NetworkMessage::GetCFHeaders(GetCFHeaders {
+            filter_type: BASIC_FILTER_TYPE,
+            start_height: 6,
+            stop_hash: chain[9],
+        });
+        assert_eq!(
+            pop_commands(&mut channel, &request).len(),
+            MAX_FILTER_HEADER_PEERS
+        );
+    }
+
+    /// Creates a regtest chain of 5 blocks following the genesis block, where the block at height 3
+    /// contains the given transactions. Returns the blockchain state, the hashes of the active
+    /// chain and the block at height 3.
+    fn blockchain_with_block(txdata: Vec<Transaction>) -> (BlockchainState, Vec<BlockHash>, Block) {
+        let (mut blockchain, mut headers) = regtest_blockchain(2);
+        let previous = headers[2];
+        let mut block = Block {
+            header: generate_header(previous.block_hash(), previous.time, 0),
+            txdata,
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        while block.header.validate_pow(&block.header.target()).is_err() {
+            block.header.nonce += 1;
+        }
+        headers.push(block.header);
+        headers.extend(generate_headers(
+            block.block_hash(),
+            block.header.time,
+            2,
+            &[],
+        ));
+        assert!(blockchain.add_headers(&headers[3..]).1.is_none());
+        let chain = headers.iter().map(|header| header.block_hash()).collect();
+        (blockchain, chain, block)
+    }
+
+    /// Tests that a disagreement about the filter of a block is resolved by downloading the block
+    /// and that only the peers committing to a filter that does not belong to the block are
+    /// disconnected. The block is below the anchor, so the blockchain manager would not download
+    /// it.
+    #[tokio::test]
+    async fn test_filter_header_conflict_is_resolved_with_block() {
+        let honest = [
+            SocketAddr::from_str("127.0.0.1:8333").expect("bad address format"),
+            SocketAddr::from_str("127.0.0.1:8334").expect("bad address format"),
+        ];
+        let dishonest = SocketAddr::from_str("127.0.0.1:8335").expect("bad address format");
+
+        // The block at height 3 contains the coinbase transaction of the first mainnet block.
+        let (blockchain, chain, block) = blockchain_with_block(block_1().txdata);
+
+        let mut filters: Vec<Vec<u8>> = (0..6).map(filter_content).collect();
+        filters[3] = basic_filter(&block, &Script::new()).content;
+        let mut dishonest_filters = filters.clone();
+        dishonest_filters[3] = basic_filter(&block_2(), &Script::new()).content;
+
+        let mut manager = filter_manager(blockchain);
+        let mut channel = TestChannel::new(vec![honest[0], honest[1], dishonest]);
+        add_filter_peers(&mut manager, &[honest[0], honest[1], dishonest]).await;
+        manager.tick(&mut channel).await;
+        assert_eq!(channel.command_count(), 3);
+        channel.pop_front();
+        channel.pop_front();
+        channel.pop_front();
+        for (peer, filters) in [
+            (honest[0], &filters),
+            (honest[1], &filters),
+            (dishonest, &dishonest_filters),
+        ] {
+            manager
+                .process_bitcoin_network_message(
+                    peer,
+                    &NetworkMessage::CFHeaders(cfheaders_message_with_filters(
+                        &chain, filters, 0, 6,
+                    )),
+                )
+                .await
+                .unwrap();
+        }
+
+        // The filter headers the peers agree on are added and the block and the filters of the
+        // block the peers disagree on are requested.
+        manager.tick(&mut channel).await;
+        assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 3);
+        let getdata = channel.pop_back().unwrap();
+        assert_eq!(
+            getdata.message,
+            NetworkMessage::GetData(vec![Inventory::Block(chain[3])])
+        );
+        let request = NetworkMessage::GetCFilters(GetCFilters {
+            filter_type: BASIC_FILTER_TYPE,
+            start_height: 3,
+            stop_hash: chain[3],
+        });
+        let peers = pop_commands(&mut channel, &request);
+        assert_eq!(peers.len(), 2);
+        assert!(peers.contains(&dishonest));
+
+        for peer in peers {
+            let filter = if peer == dishonest {
+                dishonest_filters[3].clone()
+            } else {
+                filters[3].clone()
+            };
+            manager
+                .process_bitcoin_network_message(
+                    peer,
+                    &NetworkMessage::CFilter(CFilter {
+                        filter_type: BASIC_FILTER_TYPE,
+                        block_hash: chain[3],
+                        filter,
+                    }),
+                )
+                .await
+                .unwrap();
+        }
+
+        // The disagreement is resolved once the block is downloaded, even though the anchor is
+        // above the block.
+        manager.tick(&mut channel).await;
+        assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 3);
+        assert_eq!(channel.command_count(), 0);
+
+        manager.blockchain.lock().await.prune_blocks_below_height(5);
+        assert!(manager.is_requested_block(&NetworkMessage::Block(block.clone())));
+
+        // A block whose transactions do not match its header is ignored.
+        let mut forged = block.clone();
+        forged.txdata = block_2().txdata;
+        manager
+            .process_bitcoin_network_message(
+                getdata.address.unwrap(),
+                &NetworkMessage::Block(forged),
+            )
+            .await
+            .unwrap();
+        assert!(manager.is_requested_block(&NetworkMessage::Block(block.clone())));
+        manager
+            .process_bitcoin_network_message(
+                getdata.address.unwrap(),
+                &NetworkMessage::Block(block.clone()),
+            )
+            .await
+            .unwrap();
+        assert!(!manager.is_requested_block(&NetworkMessage::Block(block)));
+        manager.tick(&mut channel).await;
+        assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 4);
+        assert!(channel.has_discarded_address(&dishonest));
+        assert!(!manager.filter_peers.contains(&dishonest));
+        assert!(honest
+            .iter()
+            .all(|addr| manager.filter_peers.contains(addr)));
+        assert!(honest
+            .iter()
+            .all(|addr| !channel.has_discarded_address(addr)));
+
+        let request = NetworkMessage::GetCFHeaders(GetCFHeaders {
+            filter_type: BASIC_FILTER_TYPE,
+            start_height: 4,
+            stop_hash: chain[5],
+        });
+        assert_eq!(
+            pop_commands(&mut channel, &request),
+            honest.iter().copied().collect()
+        );
+    }
+
+    /// Tests that peers taking part in disagreements that cannot be resolved, as the filters they
+    /// commit to only differ in the spent output scripts, are eventually disconnected.
+    #[tokio::test]
+    async fn test_peers_are_disconnected_after_unresolved_conflicts() {
+        let addrs = [
+            SocketAddr::from_str("127.0.0.1:8333").expect("bad address format"),
+            SocketAddr::from_str("127.0.0.1:8334").expect("bad address format"),
+        ];
+
+        // The block at height 3 contains a transaction spending an output.
+        let coinbase = block_1().txdata.remove(0);
+        let spending = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(coinbase.txid(), 0),
+                script_sig: Script::new(),
+                sequence: u32::MAX,
+                witness: Witness::default(),
+            }],
+            output: coinbase.output.clone(),
+        };
+        let (blockchain, chain, block) = blockchain_with_block(vec![coinbase, spending]);
+
+        let mut filters: Vec<Vec<u8>> = (0..6).map(filter_content).collect();
+        filters[3] = basic_filter(&block, &Script::from(vec![0x51])).content;
+        let mut other_filters = filters.clone();
+        other_filters[3] = basic_filter(&block, &Script::from(vec![0x52])).content;
+
+        let mut manager = filter_manager(blockchain);
+        let mut channel = TestChannel::new(addrs.to_vec());
+        add_filter_peers(&mut manager, &addrs).await;
+        manager.tick(&mut channel).await;
+
+        for round in 1..=MAX_UNRESOLVED_FILTER_HEADER_CONFLICTS {
+            assert!(addrs.iter().all(|addr| manager.filter_peers.contains(addr)));
+            while channel.pop_front().is_some() {}
+            let start_height = manager.filter_state.lock().await.filter_headers_len();
+            for (peer, filters) in [(addrs[0], &filters), (addrs[1], &other_filters)] {
+                manager
+                    .process_bitcoin_network_message(
+                        peer,
+                        &NetworkMessage::CFHeaders(cfheaders_message_with_filters(
+                            &chain,
+                            filters,
+                            start_height,
+                            6 - start_height,
+                        )),
+                    )
+                    .await
+                    .unwrap();
+            }
+            manager.tick(&mut channel).await;
+            for (peer, filters) in [(addrs[0], &filters), (addrs[1], &other_filters)] {
+                manager
+                    .process_bitcoin_network_message(
+                        peer,
+                        &NetworkMessage::CFilter(CFilter {
+                            filter_type: BASIC_FILTER_TYPE,
+                            block_hash: chain[3],
+                            filter: filters[3].clone(),
+                        }),
+                    )
+                    .await
+                    .unwrap();
+            }
+            manager
+                .process_bitcoin_network_message(addrs[0], &NetworkMessage::Block(block.clone()))
+                .await
+                .unwrap();
+
+            // Both filters are consistent with the block, so none of them is added.
+            manager.tick(&mut channel).await;
+            assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 3);
+            assert_eq!(
+                addrs.iter().all(|addr| channel.has_discarded_address(addr)),
+                round == MAX_UNRESOLVED_FILTER_HEADER_CONFLICTS
+            );
+        }
+        assert!(addrs
+            .iter()
+            .all(|addr| !manager.filter_peers.contains(addr)));
+    }
+
+    /// Tests that a peer sending a filter that does not match its own filter headers while
+    /// resolving a disagreement is disconnected.
+    #[tokio::test]
+    async fn test_conflicting_filter_must_match_filter_headers() {
+        let addrs = [
+            SocketAddr::from_str("127.0.0.1:8333").expect("bad address format"),
+            SocketAddr::from_str("127.0.0.1:8334").expect("bad address format"),
+        ];
+        let (mut manager, chain) = setup(4);
+        let mut channel = TestChannel::new(addrs.to_vec());
+        add_filter_peers(&mut manager, &addrs).await;
+        manager.tick(&mut channel).await;
+        channel.pop_front();
+        channel.pop_front();
+
+        let filters: Vec<Vec<u8>> = (0..5).map(filter_content).collect();
+        let mut other_filters = filters.clone();
+        other_filters[2] = vec![];
+        for (peer, filters) in [(addrs[0], &filters), (addrs[1], &other_filters)] {
+            manager
+                .process_bitcoin_network_message(
+                    peer,
+                    &NetworkMessage::CFHeaders(cfheaders_message_with_filters(
+                        &chain, filters, 0, 5,
+                    )),
+                )
+                .await
+                .unwrap();
+        }
+        manager.tick(&mut channel).await;
+        assert_eq!(manager.filter_state.lock().await.filter_headers_len(), 2);
+        // The filters of both candidates and the block are requested.
+        assert_eq!(channel.command_count(), 3);
+
+        assert!(matches!(
+            manager
+                .process_bitcoin_network_message(
+                    addrs[1],
+                    &NetworkMessage::CFilter(cfilter_message(&chain, 2)),
+                )
+                .await,
+            Err(ProcessBitcoinNetworkMessageError::InvalidMessage)
+        ));
+        assert!(!manager.filter_peers.contains(&addrs[1]));
+        assert!(manager
+            .process_bitcoin_network_message(
+                addrs[0],
+                &NetworkMessage::CFilter(cfilter_message(&chain, 2)),
+            )
+            .await
+            .is_ok());
+    }
+}
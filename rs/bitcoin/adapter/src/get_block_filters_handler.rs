@@ -0,0 +1,281 @@
+use std::{collections::HashSet, sync::Arc};
+
+use bitcoin::BlockHash;
+use tokio::sync::{mpsc::Sender, Mutex};
+use tonic::{Code, Status};
+
+use crate::{
+    blockfilterstate::{BlockFilterState, VerifiedBlockFilter},
+    common::BlockHeight,
+    config::Config,
+    FilterManagerRequest,
+};
+
+// Max size of the `GetBlockFiltersResponse` message. Matches the maximum size of the
+// `GetSuccessorsResponse` message.
+const MAX_RESPONSE_SIZE: usize = 2_000_000;
+
+// Max number of next block hashes that can be returned in the `GetBlockFiltersResponse`.
+const MAX_NEXT_BLOCK_HASHES_LENGTH: usize = 100;
+
+// Max number of filters that can be returned in the `GetBlockFiltersResponse`.
+const MAX_FILTERS_LENGTH: usize = 1_000;
+
+const BLOCK_HASH_SIZE: usize = 32;
+
+// The size of a filter in the response in addition to its content: the block hash and the filter header.
+const FILTER_OVERHEAD_SIZE: usize = 2 * BLOCK_HASH_SIZE;
+
+// The maximum number of bytes the `next` field in a response can take.
+const MAX_NEXT_BYTES: usize = MAX_NEXT_BLOCK_HASHES_LENGTH * BLOCK_HASH_SIZE;
+
+// The maximum number of bytes the `filters` in a response can take.
+// NOTE: This is a soft limit, and is only honored if there's > 1 filters already in the response.
+const MAX_FILTERS_BYTES: usize = MAX_RESPONSE_SIZE - MAX_NEXT_BYTES;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetBlockFiltersRequest {
+    /// Hash of the most recent stable block in the Bitcoin canister.
+    pub anchor: BlockHash,
+    /// Most recent block hashes whose filters have been processed by the canister.
+    pub processed_block_hashes: Vec<BlockHash>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetBlockFiltersResponse {
+    /// Verified filters of the blocks following the anchor on the active chain.
+    pub filters: Vec<VerifiedBlockFilter>,
+    /// Hashes of the next blocks whose filters may be sent in upcoming responses.
+    pub next: Vec<BlockHash>,
+}
+
+/// Contains the functionality to respond to GetBlockFiltersRequests via the RPC
+/// server.
+pub struct GetBlockFiltersHandler {
+    state: Arc<Mutex<BlockFilterState>>,
+    filter_manager_tx: Sender<FilterManagerRequest>,
+    enabled: bool,
+}
+
+impl GetBlockFiltersHandler {
+    /// Creates a GetBlockFiltersHandler to be used to access the block filter state
+    /// inside of the adapter when a `GetBlockFiltersRequest` is received.
+    pub fn new(
+        config: &Config,
+        state: Arc<Mutex<BlockFilterState>>,
+        filter_manager_tx: Sender<FilterManagerRequest>,
+    ) -> Self {
+        Self {
+            state,
+            filter_manager_tx,
+            enabled: config.block_filters,
+        }
+    }
+
+    /// Handles a request for block filters. The response contains the verified filters of the
+    /// blocks following the anchor on the active chain, in order of height and up to the first
+    /// block whose filter is not downloaded yet, as well as the hashes of the next blocks.
+    /// If the channel is full, EnqueueFiltersToDownload and PruneFilters will not be executed.
+    pub async fn get_block_filters(
+        &self,
+        request: GetBlockFiltersRequest,
+    ) -> Result<GetBlockFiltersResponse, Status> {
+        if !self.enabled {
+            return Err(Status::new(
+                Code::Unavailable,
+                "Block filters are disabled in the adapter",
+            ));
+        }
+
+        let response = {
+            let state = self.state.lock().await;
+            let anchor_height = state.get_height(&request.anchor).ok_or_else(|| {
+                Status::new(Code::NotFound, "Anchor is not part of the active chain")
+            })?;
+
+            // The filters following the anchor can only be verified once their filter headers are.
+            if state.filter_headers_len() <= anchor_height as usize {
+                return Err(Status::new(
+                    Code::Unavailable,
+                    "Filter headers not yet synced past the anchor",
+                ));
+            }
+
+            get_successor_filters(&state, anchor_height, &request.processed_block_hashes)
+        };
+
+        if !response.next.is_empty() {
+            // TODO: better handling of full channel as the receivers are never closed.
+            self.filter_manager_tx
+                .try_send(FilterManagerRequest::EnqueueFiltersToDownload(
+                    response.next.clone(),
+                ))
+                .ok();
+        }
+        // TODO: better handling of full channel as the receivers are never closed.
+        self.filter_manager_tx
+            .try_send(FilterManagerRequest::PruneFilters(
+                request.anchor,
+                request.processed_block_hashes,
+            ))
+            .ok();
+
+        Ok(response)
+    }
+}
+
+// Walks the active chain starting after the anchor and returns as many consecutive filters as
+// fit in the `MAX_FILTERS_BYTES` limit, with a minimum of one filter. The hashes of the following
+// blocks are returned as the next blocks.
+fn get_successor_filters(
+    state: &BlockFilterState,
+    anchor_height: BlockHeight,
+    processed_block_hashes: &[BlockHash],
+) -> GetBlockFiltersResponse {
+    let seen: HashSet<BlockHash> = processed_block_hashes.iter().copied().collect();
+
+    let mut filters = vec![];
+    let mut next = vec![];
+    let mut response_filters_size: usize = 0;
+    let mut collecting_filters = true;
+    let mut height = anchor_height + 1;
+    while let Some(block_hash) = state.get_block_hash(height) {
+        height += 1;
+        if seen.contains(&block_hash) {
+            continue;
+        }
+
+        if collecting_filters {
+            if let Some(filter) = state.get_filter(&block_hash) {
+                let filter_size = filter.filter.content.len() + FILTER_OVERHEAD_SIZE;
+                if response_filters_size == 0
+                    || (response_filters_size + filter_size <= MAX_FILTERS_BYTES
+                        && filters.len() < MAX_FILTERS_LENGTH)
+                {
+                    filters.push(filter);
+                    response_filters_size += filter_size;
+                    continue;
+                }
+            }
+            // The filters need to be returned in order, so no filters can be returned after a
+            // missing one.
+            collecting_filters = false;
+        }
+
+        if next.len() >= MAX_NEXT_BLOCK_HASHES_LENGTH {
+            break;
+        }
+        next.push(block_hash);
+    }
+
+    GetBlockFiltersResponse { filters, next }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use bitcoin::Network;
+    use ic_metrics::MetricsRegistry;
+    use tokio::sync::mpsc::channel;
+
+    use crate::{
+        blockfilterstate::test::{cfheaders_message, cfilter_message, regtest_blockchain},
+        config::test::ConfigBuilder,
+    };
+
+    /// Creates a handler whose filter state follows a regtest chain with the given number of
+    /// blocks following the genesis block. Returns the handler and the hashes of the active chain.
+    fn setup(
+        len: u32,
+        block_filters: bool,
+    ) -> (
+        GetBlockFiltersHandler,
+        tokio::sync::mpsc::Receiver<FilterManagerRequest>,
+        Vec<BlockHash>,
+    ) {
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_block_filters(block_filters)
+            .build();
+        let (blockchain, headers) = regtest_blockchain(len);
+        let mut state = BlockFilterState::new(&MetricsRegistry::default());
+        state.update_chain(&blockchain);
+        let (filter_manager_tx, filter_manager_rx) = channel(10);
+        let handler =
+            GetBlockFiltersHandler::new(&config, Arc::new(Mutex::new(state)), filter_manager_tx);
+        let chain = headers.iter().map(|header| header.block_hash()).collect();
+        (handler, filter_manager_rx, chain)
+    }
+
+    /// This test ensures that the filters following the anchor are returned in order up to the
+    /// first missing filter, and that the missing filters are enqueued.
+    #[tokio::test]
+    async fn test_get_block_filters() {
+        let (handler, mut filter_manager_rx, chain) = setup(6, true);
+        {
+            let mut state = handler.state.lock().await;
+            state
+                .add_filter_headers(&cfheaders_message(&chain, 0, 7))
+                .unwrap();
+            for height in [1, 2, 3, 5] {
+                state.add_filter(&cfilter_message(&chain, height)).unwrap();
+            }
+        }
+
+        let response = handler
+            .get_block_filters(GetBlockFiltersRequest {
+                anchor: chain[0],
+                processed_block_hashes: vec![chain[1]],
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response
+                .filters
+                .iter()
+                .map(|filter| filter.block_hash)
+                .collect::<Vec<_>>(),
+            vec![chain[2], chain[3]]
+        );
+        assert_eq!(response.next, vec![chain[4], chain[5], chain[6]]);
+        assert!(matches!(
+            filter_manager_rx.try_recv(),
+            Ok(FilterManagerRequest::EnqueueFiltersToDownload(block_hashes)) if block_hashes == response.next
+        ));
+        assert!(matches!(
+            filter_manager_rx.try_recv(),
+            Ok(FilterManagerRequest::PruneFilters(anchor, processed)) if anchor == chain[0] && processed == vec![chain[1]]
+        ));
+    }
+
+    /// This test ensures that no filters are returned until the filter headers are synced past
+    /// the anchor, or when the block filters are disabled.
+    #[tokio::test]
+    async fn test_get_block_filters_unavailable() {
+        let (handler, _filter_manager_rx, chain) = setup(3, true);
+        let request = GetBlockFiltersRequest {
+            anchor: chain[1],
+            processed_block_hashes: vec![],
+        };
+        let err = handler
+            .get_block_filters(request.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+
+        let err = handler
+            .get_block_filters(GetBlockFiltersRequest {
+                anchor: BlockHash::default(),
+                processed_block_hashes: vec![],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let (handler, _filter_manager_rx, _) = setup(3, false);
+        let err = handler.get_block_filters(request).await.unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+    }
+}
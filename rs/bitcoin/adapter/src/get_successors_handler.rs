@@ -4,14 +4,13 @@ use std::{
 };
 
 use bitcoin::{Block, BlockHash, BlockHeader, Network};
-use ic_btc_validation::is_beyond_last_checkpoint;
 use ic_metrics::MetricsRegistry;
 use tokio::sync::{mpsc::Sender, Mutex};
 use tonic::{Code, Status};
 
 use crate::{
-    common::BlockHeight, config::Config, metrics::GetSuccessorMetrics, BlockchainManagerRequest,
-    BlockchainState,
+    chainprofile::ChainProfile, common::BlockHeight, config::Config, metrics::GetSuccessorMetrics,
+    BlockchainManagerRequest, BlockchainState,
 };
 
 // Max size of the `GetSuccessorsResponse` message.
@@ -62,7 +61,8 @@ pub struct GetSuccessorsResponse {
 pub struct GetSuccessorsHandler {
     state: Arc<Mutex<BlockchainState>>,
     blockchain_manager_tx: Sender<BlockchainManagerRequest>,
-    network: Network,
+    /// The profile of the chain whose checkpoints and limits apply.
+    chain_profile: ChainProfile,
     metrics: GetSuccessorMetrics,
 }
 
@@ -78,7 +78,7 @@ impl GetSuccessorsHandler {
         Self {
             state,
             blockchain_manager_tx,
-            network: config.network,
+            chain_profile: config.chain_profile(),
             metrics: GetSuccessorMetrics::new(metrics_registry),
         }
     }
@@ -105,14 +105,23 @@ impl GetSuccessorsHandler {
 
             // Wait with downloading blocks until we synced the header chain above the last checkpoint
             // to make sure we are following the correct chain.
-            if !is_beyond_last_checkpoint(&self.network, state.get_active_chain_tip().height) {
+            if !self
+                .chain_profile
+                .is_beyond_last_checkpoint(state.get_active_chain_tip().height)
+            {
                 return Err(Status::new(
                     Code::Unavailable,
                     "Header chain not yet synced past last checkpoint",
                 ));
             }
 
-            let allow_multiple_blocks = are_multiple_blocks_allowed(self.network, anchor_height);
+            // Chains other than Bitcoin have no known limits on the number of blocks per response.
+            let allow_multiple_blocks = self
+                .chain_profile
+                .bitcoin_network()
+                .map_or(true, |network| {
+                    are_multiple_blocks_allowed(network, anchor_height)
+                });
             let blocks = get_successor_blocks(
                 &state,
                 &request.anchor,
@@ -263,6 +272,7 @@ mod test {
     use ic_metrics::MetricsRegistry;
     use tokio::sync::{mpsc::channel, Mutex};
 
+    use crate::chainprofile::{test::scrypt_test_chain, Checkpoint};
     use crate::config::test::ConfigBuilder;
     use ic_btc_adapter_test_utils::{
         generate_headers, generate_large_block_blockchain, headers_to_hashes,
@@ -422,6 +432,49 @@ mod test {
         assert_eq!(response.err().unwrap().code(), Code::Unavailable);
     }
 
+    #[tokio::test]
+    async fn test_get_successors_wait_header_sync_chain_profile_checkpoints() {
+        let (mut chain_profile, headers) = scrypt_test_chain();
+        chain_profile.checkpoints = vec![Checkpoint {
+            height: 16,
+            hash: headers[16].block_hash(),
+        }];
+        let config = ConfigBuilder::new()
+            .with_chain_profile(chain_profile)
+            .build();
+        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let genesis_hash = blockchain_state.genesis().block_hash();
+        let (blockchain_manager_tx, _blockchain_manager_rx) =
+            channel::<BlockchainManagerRequest>(10);
+        let handler = GetSuccessorsHandler::new(
+            &config,
+            Arc::new(Mutex::new(blockchain_state)),
+            blockchain_manager_tx,
+            &MetricsRegistry::default(),
+        );
+        let request = || GetSuccessorsRequest {
+            anchor: genesis_hash,
+            processed_block_hashes: vec![],
+        };
+
+        {
+            let mut blockchain = handler.state.lock().await;
+            let (_, maybe_err) = blockchain.add_headers(&headers[1..16]);
+            assert!(maybe_err.is_none());
+        }
+        // The header chain is not past the checkpoint at height 16 yet.
+        let response = handler.get_successors(request()).await;
+        assert_eq!(response.err().unwrap().code(), Code::Unavailable);
+
+        {
+            let mut blockchain = handler.state.lock().await;
+            let (_, maybe_err) = blockchain.add_headers(&headers[16..]);
+            assert!(maybe_err.is_none());
+        }
+        let response = handler.get_successors(request()).await;
+        assert!(response.is_ok());
+    }
+
     #[tokio::test]
     async fn test_get_successors_wait_header_sync_regtest() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
@@ -21,6 +21,11 @@ mod addressbook;
 mod blockchainmanager;
 /// This module contains the data structure for storing the current state of the Bitcoin ledger
 mod blockchainstate;
+/// This module contains the data structure for storing the verified compact block filters
+/// (BIP157/158) of the active chain.
+mod blockfilterstate;
+/// This module contains the parameters of the chains that the adapter can follow.
+pub mod chainprofile;
 /// This module contains command line arguments parser.
 pub mod cli;
 /// This module contains constants and types that are shared by many modules.
@@ -34,6 +39,10 @@ mod connection;
 /// This module contains code that is used to manage multiple connections to
 /// BTC nodes.
 mod connectionmanager;
+/// This module contains the method for downloading compact block filters, sending
+/// "getcfheaders", "getcfilters" messages to Bitcoin peers and processing the
+/// "cfheaders", "cfilter" messages received from Bitcoin peers.
+mod filtermanager;
 mod metrics;
 /// The module is responsible for awaiting messages from bitcoin peers and dispaching them
 /// to the correct component.
@@ -49,9 +58,15 @@ mod transaction_store;
 // malicious fork can be prioritized by a DFS, thus potentially ignoring honest forks).
 mod get_successors_handler;
 
+// This module contains code that is used to return verified compact block filters to the Bitcoin
+// canister. The filters are returned in the order of the active chain.
+mod get_block_filters_handler;
+
 pub use blockchainmanager::BlockchainManager;
 pub use blockchainstate::BlockchainState;
+pub use blockfilterstate::BlockFilterState;
 use common::BlockHeight;
+pub use get_block_filters_handler::GetBlockFiltersHandler;
 pub use get_successors_handler::GetSuccessorsHandler;
 pub use router::start_main_event_loop;
 pub use rpc_server::start_grpc_server;
@@ -140,6 +155,15 @@ pub enum BlockchainManagerRequest {
     PruneBlocks(BlockHash, Vec<BlockHash>),
 }
 
+/// Commands sent back to the router in order to download or prune compact block filters.
+#[derive(Debug)]
+pub enum FilterManagerRequest {
+    /// Inform the adapter to enqueue the filters of the following blocks into the syncing queue.
+    EnqueueFiltersToDownload(Vec<BlockHash>),
+    /// Inform the adapter to prune the filters of the following block hashes from the cache.
+    PruneFilters(BlockHash, Vec<BlockHash>),
+}
+
 /// The transaction manager is owned by a single thread which listens on a channel
 /// for TransactionManagerRequest messages and executes the corresponding method.
 #[derive(Debug)]
@@ -210,6 +234,11 @@ pub fn start_grpc_server_and_router(
         metrics_registry,
     );
 
+    let (filter_manager_tx, filter_manager_rx) = channel(100);
+    let block_filter_state = Arc::new(Mutex::new(BlockFilterState::new(metrics_registry)));
+    let get_block_filters_handler =
+        GetBlockFiltersHandler::new(config, block_filter_state.clone(), filter_manager_tx);
+
     let (transaction_manager_tx, transaction_manager_rx) = channel(100);
 
     start_grpc_server(
@@ -217,6 +246,7 @@ pub fn start_grpc_server_and_router(
         logger.clone(),
         adapter_state.clone(),
         get_successors_handler,
+        get_block_filters_handler,
         transaction_manager_tx,
         metrics_registry,
     );
@@ -225,9 +255,11 @@ pub fn start_grpc_server_and_router(
         config,
         logger,
         blockchain_state,
+        block_filter_state,
         transaction_manager_rx,
         adapter_state,
         blockchain_manager_rx,
+        filter_manager_rx,
         metrics_registry,
     );
 }
@@ -4,6 +4,7 @@ use ic_metrics::{
 };
 use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge};
 
+pub(crate) const LABEL_GET_BLOCK_FILTERS: &str = "get_block_filters";
 pub(crate) const LABEL_GET_SUCCESSOR: &str = "get_successor";
 pub(crate) const LABEL_REQUEST_TYPE: &str = "type";
 pub(crate) const LABEL_SEND_TRANSACTION: &str = "send_transaction";
@@ -117,6 +118,27 @@ impl BlockchainStateMetrics {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct BlockFilterMetrics {
+    pub filter_headers: IntGauge,
+    pub filter_cache_elements: IntGauge,
+}
+
+impl BlockFilterMetrics {
+    pub fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            filter_headers: metrics_registry.int_gauge(
+                "filter_headers",
+                "Number of verified compact block filter headers.",
+            ),
+            filter_cache_elements: metrics_registry.int_gauge(
+                "filter_cache_elements",
+                "Number of compact block filters currently stored in the filter cache.",
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TransactionMetrics {
     pub txn_ops: IntCounterVec,
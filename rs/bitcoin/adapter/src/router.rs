@@ -1,11 +1,12 @@
 //! The module is responsible for awaiting messages from bitcoin peers and dispaching them
 //! to the correct component.
 use crate::{
-    blockchainmanager::BlockchainManager, common::DEFAULT_CHANNEL_BUFFER_SIZE, config::Config,
-    connectionmanager::ConnectionManager, metrics::RouterMetrics, stream::handle_stream,
+    blockchainmanager::BlockchainManager, blockfilterstate::BlockFilterState,
+    common::DEFAULT_CHANNEL_BUFFER_SIZE, config::Config, connectionmanager::ConnectionManager,
+    filtermanager::FilterManager, metrics::RouterMetrics, stream::handle_stream,
     transaction_store::TransactionStore, AdapterState, BlockchainManagerRequest, BlockchainState,
-    Channel, ProcessBitcoinNetworkMessage, ProcessBitcoinNetworkMessageError, ProcessEvent,
-    TransactionManagerRequest,
+    Channel, FilterManagerRequest, ProcessBitcoinNetworkMessage, ProcessBitcoinNetworkMessageError,
+    ProcessEvent, TransactionManagerRequest,
 };
 use bitcoin::network::message::NetworkMessage;
 use ic_logger::ReplicaLogger;
@@ -30,9 +31,11 @@ pub fn start_main_event_loop(
     config: &Config,
     logger: ReplicaLogger,
     blockchain_state: Arc<Mutex<BlockchainState>>,
+    block_filter_state: Arc<Mutex<BlockFilterState>>,
     mut transaction_manager_rx: Receiver<TransactionManagerRequest>,
     adapter_state: AdapterState,
     mut blockchain_manager_rx: Receiver<BlockchainManagerRequest>,
+    mut filter_manager_rx: Receiver<FilterManagerRequest>,
     metrics_registry: &MetricsRegistry,
 ) {
     let (network_message_sender, mut network_message_receiver) =
@@ -40,6 +43,12 @@ pub fn start_main_event_loop(
 
     let router_metrics = RouterMetrics::new(metrics_registry);
 
+    let mut filter_manager = FilterManager::new(
+        config,
+        blockchain_state.clone(),
+        block_filter_state,
+        logger.clone(),
+    );
     let mut blockchain_manager =
         BlockchainManager::new(blockchain_state, logger.clone(), router_metrics.clone());
     let mut transaction_manager = TransactionStore::new(logger.clone(), metrics_registry);
@@ -57,6 +66,7 @@ pub fn start_main_event_loop(
             if adapter_state.is_idle() {
                 connection_manager.make_idle();
                 blockchain_manager.make_idle().await;
+                filter_manager.make_idle().await;
                 // TODO: instead of sleeping here add some async synchronization.
                 sleep(sleep_idle_interval).await;
                 continue;
@@ -83,7 +93,14 @@ pub fn start_main_event_loop(
                         connection_manager.discard(&address);
                     }
 
+                    // The block requested by the filter manager is unknown to the blockchain manager.
+                    let is_filter_block = filter_manager.is_requested_block(&message);
                     if let Err(ProcessBitcoinNetworkMessageError::InvalidMessage) = blockchain_manager.process_bitcoin_network_message(&mut connection_manager, address, &message).await {
+                        if !is_filter_block {
+                            connection_manager.discard(&address);
+                        }
+                    }
+                    if let Err(ProcessBitcoinNetworkMessageError::InvalidMessage) = filter_manager.process_bitcoin_network_message(address, &message).await {
                         connection_manager.discard(&address);
                     }
                     if let Err(ProcessBitcoinNetworkMessageError::InvalidMessage) = transaction_manager.process_bitcoin_network_message(&mut connection_manager, address, &message) {
@@ -101,6 +118,17 @@ pub fn start_main_event_loop(
                         }
                     };
                 }
+                result = filter_manager_rx.recv() => {
+                    let command = result.expect("Receiving should not fail because the sender part of the channel is never closed.");
+                    match command {
+                        FilterManagerRequest::EnqueueFiltersToDownload(block_hashes) => {
+                            filter_manager.enqueue_filters_to_download(block_hashes).await;
+                        }
+                        FilterManagerRequest::PruneFilters(anchor, processed_block_hashes) => {
+                            filter_manager.prune_filters(anchor, processed_block_hashes).await;
+                        }
+                    };
+                }
                 transaction_manager_request = transaction_manager_rx.recv() => {
                     match transaction_manager_request.unwrap() {
                         TransactionManagerRequest::SendTransaction(transaction) => transaction_manager.enqueue_transaction(&transaction),
@@ -112,6 +140,7 @@ pub fn start_main_event_loop(
                     connection_manager.tick(blockchain_manager.get_height().await, handle_stream);
                     blockchain_manager
                         .tick(&mut connection_manager).await;
+                    filter_manager.tick(&mut connection_manager).await;
                     transaction_manager.advertise_txids(&mut connection_manager);
                 }
             };
@@ -1,13 +1,17 @@
 use crate::{
     config::{Config, IncomingSource},
+    get_block_filters_handler::{GetBlockFiltersRequest, GetBlockFiltersResponse},
     get_successors_handler::{GetSuccessorsRequest, GetSuccessorsResponse},
-    metrics::{ServiceMetrics, LABEL_GET_SUCCESSOR, LABEL_SEND_TRANSACTION},
-    AdapterState, GetSuccessorsHandler, TransactionManagerRequest,
+    metrics::{
+        ServiceMetrics, LABEL_GET_BLOCK_FILTERS, LABEL_GET_SUCCESSOR, LABEL_SEND_TRANSACTION,
+    },
+    AdapterState, GetBlockFiltersHandler, GetSuccessorsHandler, TransactionManagerRequest,
 };
 use bitcoin::{consensus::Encodable, hashes::Hash, BlockHash};
 use ic_async_utils::{incoming_from_first_systemd_socket, incoming_from_path};
 use ic_btc_service::{
     btc_service_server::{BtcService, BtcServiceServer},
+    BtcServiceBlockFilter, BtcServiceGetBlockFiltersRequest, BtcServiceGetBlockFiltersResponse,
     BtcServiceGetSuccessorsRequest, BtcServiceGetSuccessorsResponse,
     BtcServiceSendTransactionRequest, BtcServiceSendTransactionResponse,
 };
@@ -20,6 +24,7 @@ use tonic::{transport::Server, Request, Response, Status};
 struct BtcServiceImpl {
     adapter_state: AdapterState,
     get_successors_handler: GetSuccessorsHandler,
+    get_block_filters_handler: GetBlockFiltersHandler,
     transaction_manager_tx: Sender<TransactionManagerRequest>,
     logger: ReplicaLogger,
     metrics: ServiceMetrics,
@@ -72,6 +77,49 @@ impl TryFrom<GetSuccessorsResponse> for BtcServiceGetSuccessorsResponse {
     }
 }
 
+impl TryFrom<BtcServiceGetBlockFiltersRequest> for GetBlockFiltersRequest {
+    type Error = Status;
+
+    fn try_from(request: BtcServiceGetBlockFiltersRequest) -> Result<Self, Self::Error> {
+        let anchor = BlockHash::from_slice(request.anchor.as_slice())
+            .map_err(|_| Status::unknown("Failed to parse anchor hash!"))?;
+
+        let processed_block_hashes = request
+            .processed_block_hashes
+            .iter()
+            .map(|hash| {
+                BlockHash::from_slice(hash.as_slice())
+                    .map_err(|_| Status::unknown("Failed to read processed_block_hashes!"))
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        Ok(GetBlockFiltersRequest {
+            anchor,
+            processed_block_hashes,
+        })
+    }
+}
+
+impl From<GetBlockFiltersResponse> for BtcServiceGetBlockFiltersResponse {
+    fn from(response: GetBlockFiltersResponse) -> Self {
+        let filters = response
+            .filters
+            .into_iter()
+            .map(|filter| BtcServiceBlockFilter {
+                block_hash: filter.block_hash[..].to_vec(),
+                filter: filter.filter.content,
+                filter_header: filter.filter_header[..].to_vec(),
+            })
+            .collect();
+        let next = response
+            .next
+            .iter()
+            .map(|block_hash| block_hash[..].to_vec())
+            .collect();
+        BtcServiceGetBlockFiltersResponse { filters, next }
+    }
+}
+
 #[tonic::async_trait]
 impl BtcService for BtcServiceImpl {
     async fn get_successors(
@@ -118,6 +166,32 @@ impl BtcService for BtcServiceImpl {
             );
         Ok(Response::new(BtcServiceSendTransactionResponse {}))
     }
+
+    async fn get_block_filters(
+        &self,
+        request: Request<BtcServiceGetBlockFiltersRequest>,
+    ) -> Result<Response<BtcServiceGetBlockFiltersResponse>, Status> {
+        let _timer = self
+            .metrics
+            .request_duration
+            .with_label_values(&[LABEL_GET_BLOCK_FILTERS])
+            .start_timer();
+        self.adapter_state.received_now();
+        let inner = request.into_inner();
+        debug!(self.logger, "Received GetBlockFiltersRequest: {:?}", inner);
+        let request = inner.try_into()?;
+
+        let response = BtcServiceGetBlockFiltersResponse::from(
+            self.get_block_filters_handler
+                .get_block_filters(request)
+                .await?,
+        );
+        debug!(
+            self.logger,
+            "Sending GetBlockFiltersResponse: {:?}", response
+        );
+        Ok(Response::new(response))
+    }
 }
 
 /// Spawns in a separate Tokio task the BTC adapter gRPC service.
@@ -126,12 +200,14 @@ pub fn start_grpc_server(
     logger: ReplicaLogger,
     adapter_state: AdapterState,
     get_successors_handler: GetSuccessorsHandler,
+    get_block_filters_handler: GetBlockFiltersHandler,
     transaction_manager_tx: Sender<TransactionManagerRequest>,
     metrics_registry: &MetricsRegistry,
 ) {
     let btc_adapter_impl = BtcServiceImpl {
         adapter_state,
         get_successors_handler,
+        get_block_filters_handler,
         transaction_manager_tx,
         logger,
         metrics: ServiceMetrics::new(metrics_registry),
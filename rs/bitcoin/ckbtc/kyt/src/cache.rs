@@ -0,0 +1,89 @@
+use std::collections::{BTreeMap, VecDeque};
+
+/// A bounded cache of check results with a fixed time-to-live.
+///
+/// The cache evicts the oldest entries first when it reaches its capacity.
+pub struct Cache<K, V> {
+    entries: BTreeMap<K, (u64, V)>,
+    /// Keys in the insertion order, paired with their insertion timestamps.
+    insertion_order: VecDeque<(u64, K)>,
+    capacity: usize,
+    ttl_nanos: u64,
+}
+
+impl<K: Ord + Clone, V: Clone> Cache<K, V> {
+    pub fn new(capacity: usize, ttl_nanos: u64) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            insertion_order: VecDeque::new(),
+            capacity,
+            ttl_nanos,
+        }
+    }
+
+    /// Returns the value cached for the key if the entry did not expire yet.
+    pub fn get(&self, key: &K, now: u64) -> Option<V> {
+        self.entries
+            .get(key)
+            .filter(|(inserted_at, _)| now.saturating_sub(*inserted_at) < self.ttl_nanos)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Caches the value for the key, evicting expired entries and the oldest
+    /// entries if the cache is full.
+    pub fn insert(&mut self, key: K, value: V, now: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        while let Some((inserted_at, oldest_key)) = self.insertion_order.front() {
+            let expired = now.saturating_sub(*inserted_at) >= self.ttl_nanos;
+            let has_room = self.entries.len() < self.capacity || self.entries.contains_key(&key);
+            if !expired && has_room {
+                break;
+            }
+            // Entries overwritten by a later insert have a newer timestamp, so
+            // we remove the entry only if it belongs to this queue item.
+            if self.entries.get(oldest_key).map(|(ts, _)| ts) == Some(inserted_at) {
+                self.entries.remove(oldest_key);
+            }
+            self.insertion_order.pop_front();
+        }
+        if let Some((inserted_at, _)) = self.entries.insert(key.clone(), (now, value)) {
+            // Drop the stale queue item so that it does not evict the new entry.
+            self.insertion_order
+                .retain(|(ts, k)| !(*ts == inserted_at && k == &key));
+        }
+        self.insertion_order.push_back((now, key));
+    }
+
+    /// Returns the number of cached entries, including the expired ones.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[test]
+fn test_cache_expiration() {
+    let mut cache = Cache::new(10, 100);
+    cache.insert(1, "a", 0);
+    assert_eq!(cache.get(&1, 99), Some("a"));
+    assert_eq!(cache.get(&1, 100), None);
+
+    cache.insert(2, "b", 150);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get(&2, 200), Some("b"));
+}
+
+#[test]
+fn test_cache_evicts_oldest_entries() {
+    let mut cache = Cache::new(2, 1_000);
+    cache.insert(1, "a", 0);
+    cache.insert(2, "b", 1);
+    cache.insert(1, "c", 2);
+    cache.insert(3, "d", 3);
+
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(&1, 4), Some("c"));
+    assert_eq!(cache.get(&2, 4), None);
+    assert_eq!(cache.get(&3, 4), Some("d"));
+}
@@ -8,6 +8,7 @@ use candid::Principal;
 pub struct DashboardTemplate {
     pub minter_id: Principal,
     pub maintainers: Vec<Principal>,
+    pub providers: Vec<Principal>,
     pub events: Vec<Event>,
     pub mode: KytMode,
     pub last_api_key_update_date: String,
@@ -11,6 +11,9 @@ pub enum KytMode {
     RejectAll,
     /// In this mode, the canister will call Chainalysis API for each request.
     Normal,
+    /// In this mode, the canister will not make any HTTP calls and will check
+    /// all requests using a deterministic mock provider.
+    Mock(MockProviderConfig),
 }
 
 impl fmt::Display for KytMode {
@@ -19,10 +22,24 @@ impl fmt::Display for KytMode {
             KytMode::AcceptAll => write!(f, "AcceptAll"),
             KytMode::RejectAll => write!(f, "RejectAll"),
             KytMode::Normal => write!(f, "Normal"),
+            KytMode::Mock(_) => write!(f, "Mock"),
         }
     }
 }
 
+/// The configuration of the built-in mock provider.
+///
+/// The mock provider reports a severe alert for every UTXO of the listed
+/// transactions and for every withdrawal to the listed addresses, and no
+/// alerts for all other requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct MockProviderConfig {
+    /// The transactions whose outputs the mock provider considers tainted.
+    pub tainted_txids: Vec<[u8; 32]>,
+    /// The Bitcoin addresses that the mock provider considers tainted.
+    pub tainted_addresses: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub struct InitArg {
     /// The principal of the minter canister.
@@ -85,6 +102,18 @@ pub struct FetchAlertsResponse {
     pub external_id: String,
     pub alerts: Vec<Alert>,
     pub provider: Principal,
+    /// Some(true) if the canister served the response from its result cache
+    /// without calling the provider. The provider did not perform a new check
+    /// in that case, so the caller should not charge the check fee again.
+    /// None if the canister predates the result cache.
+    pub cached: Option<bool>,
+}
+
+impl FetchAlertsResponse {
+    /// Returns true if the response comes from the result cache.
+    pub fn is_cached(&self) -> bool {
+        self.cached == Some(true)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
@@ -11,14 +11,17 @@ use ic_ckbtc_kyt::{
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory as VM};
 use ic_stable_structures::storable::Storable;
 use ic_stable_structures::{DefaultMemoryImpl, RestrictedMemory as RM, StableCell, StableLog};
+use providers::Provider;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap;
 use std::fmt;
 
+mod cache;
 mod dashboard;
 mod json_rpc;
+mod providers;
 
 /// The max number of times we poll a summary method before giving up.
 /// The Chainalysis docs says that the processing should take up to 30 seconds:
@@ -30,6 +33,12 @@ mod json_rpc;
 /// See: https://docs.chainalysis.com/api/kyt/guides/#workflows-polling-the-summary-endpoints
 const MAX_SUMMARY_POLLS: usize = 10;
 
+/// The maximum number of UTXO check results we keep in the result cache.
+const MAX_CACHED_RESULTS: usize = 10_000;
+
+/// The time after which we check the same UTXO again.
+const CACHED_RESULT_TTL_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
 /// The number of Wasm pages to use for the canister metadata.
 const METADATA_PAGES: u64 = 16;
 const EVENT_INDEX_ID: MemoryId = MemoryId::new(0);
@@ -152,6 +161,17 @@ impl Event {
         }
     }
 
+    /// Returns true if the event is a check event served from the result cache.
+    pub fn cached(&self) -> bool {
+        match &self.kind {
+            EventKind::UtxoCheck { cached, .. } => *cached,
+            EventKind::AddressCheck { cached, .. } => *cached,
+            EventKind::ApiKeyUpdate => false,
+            EventKind::ApiKeySet { .. } => false,
+            EventKind::ApiKeyExpired { .. } => false,
+        }
+    }
+
     /// Returns false if the event is a check event and it had alerts.
     pub fn ok(&self) -> bool {
         match &self.kind {
@@ -183,6 +203,11 @@ pub enum EventKind {
 
         #[serde(rename = "alerts")]
         alerts: Vec<Alert>,
+
+        /// True if the result comes from the result cache.
+        #[serde(rename = "cached")]
+        #[serde(default)]
+        cached: bool,
     },
     #[serde(rename = "address_check")]
     AddressCheck {
@@ -204,6 +229,12 @@ pub enum EventKind {
 
         #[serde(rename = "alerts")]
         alerts: Vec<Alert>,
+
+        /// True if the result came from the result cache. Withdrawal checks
+        /// are no longer cached, so this is only set in older events.
+        #[serde(rename = "cached")]
+        #[serde(default)]
+        cached: bool,
     },
     #[serde(rename = "api_key_update")]
     ApiKeyUpdate,
@@ -256,16 +287,53 @@ thread_local! {
 
     static UTXO_CHECKS_COUNT: Cell<u64> = Cell::default();
     static ADDRESS_CHECKS_COUNT: Cell<u64> = Cell::default();
+    static CACHE_HITS_COUNT: Cell<u64> = Cell::default();
+    static PROVIDER_FAILURES_COUNT: Cell<u64> = Cell::default();
+
+    /// The results of recent UTXO checks. Withdrawal checks are not cached,
+    /// since the provider must register every withdrawal attempt.
+    static UTXO_CHECK_CACHE: RefCell<cache::Cache<([u8; 32], u32), FetchAlertsResponse>> =
+        RefCell::new(cache::Cache::new(MAX_CACHED_RESULTS, CACHED_RESULT_TTL_NANOS));
 
     /// The provider we used for the last KYT call.
     static LAST_USED_PROVIDER: Cell<Option<Principal>> = Cell::default();
 }
 
-fn pick_api_key() -> Result<(Principal, String), Error> {
-    CONFIG_CELL.with(|cfg_cell| {
-        let cfg_value = cfg_cell.borrow();
-        let cfg = cfg_value.get();
-        pick_api_key_from(&cfg.api_keys)
+/// Returns the providers to use for the next check in the order in which we
+/// should try them.
+fn pick_providers() -> Result<Vec<Provider>, Error> {
+    let (mode, api_keys) = CONFIG_CELL.with(|cell| {
+        let cfg = cell.borrow();
+        (cfg.get().mode.clone(), cfg.get().api_keys.clone())
+    });
+    Ok(match mode {
+        // The mock provider does not need an API key, the canister itself
+        // plays the role of the provider.
+        KytMode::Mock(config) => vec![Provider::Mock {
+            id: ic_cdk::id(),
+            config,
+        }],
+        KytMode::AcceptAll => vec![Provider::AcceptAll {
+            id: pick_api_key_from(&api_keys)?.0,
+        }],
+        KytMode::RejectAll => vec![Provider::RejectAll {
+            id: pick_api_key_from(&api_keys)?.0,
+        }],
+        KytMode::Normal => {
+            let (first, api_key) = pick_api_key_from(&api_keys)?;
+            // Fall back to the other providers in the rotation order.
+            let fallbacks = api_keys
+                .range(first..)
+                .skip(1)
+                .chain(api_keys.range(..first))
+                .map(|(id, api_key)| Provider::Chainalysis {
+                    id: *id,
+                    api_key: api_key.clone(),
+                });
+            std::iter::once(Provider::Chainalysis { id: first, api_key })
+                .chain(fallbacks)
+                .collect()
+        }
     })
 }
 
@@ -298,10 +366,6 @@ fn pick_api_key_from(api_keys: &BTreeMap<Principal, String>) -> Result<(Principa
     })
 }
 
-fn kyt_mode() -> KytMode {
-    CONFIG_CELL.with(|cell| cell.borrow().get().mode.clone())
-}
-
 fn modify_config(f: impl FnOnce(Config) -> Config) {
     CONFIG_CELL.with(|cell| {
         let config = cell.borrow().get().0.clone();
@@ -453,34 +517,35 @@ async fn get_utxo_alerts(
 #[update(guard = "caller_is_minter")]
 #[candid_method(update)]
 async fn fetch_utxo_alerts(request: DepositRequest) -> Result<FetchAlertsResponse, Error> {
-    loop {
-        let (provider, api_key) = pick_api_key()?;
-        let (external_id, alerts) = match kyt_mode() {
-            KytMode::Normal => match get_utxo_alerts(api_key, request.clone()).await {
-                Ok(result) => result,
-                Err(KytCheckError::TimedOut(msg)) => {
-                    return Err(Error::TemporarilyUnavailable(msg))
-                }
-                Err(KytCheckError::RpcError(err)) => {
-                    if err.is_access_denied_error() {
-                        expire_key(provider);
-                        // Try again with a different provider.
-                        continue;
-                    } else {
-                        return Err(Error::TemporarilyUnavailable(err.to_string()));
-                    }
-                }
-            },
-            KytMode::AcceptAll => (ic_cdk::api::time().to_string(), vec![]),
-            KytMode::RejectAll => (
-                ic_cdk::api::time().to_string(),
-                vec![Alert {
-                    level: AlertLevel::Severe,
-                    category: None,
-                    service: None,
-                    exposure_type: ExposureType::Direct,
-                }],
-            ),
+    let cache_key = (request.txid, request.vout);
+    if let Some(response) =
+        UTXO_CHECK_CACHE.with(|c| c.borrow().get(&cache_key, ic_cdk::api::time()))
+    {
+        CACHE_HITS_COUNT.with(|c| c.set(c.get() + 1));
+        record_event(EventKind::UtxoCheck {
+            txid: request.txid,
+            vout: request.vout,
+            caller: Some(request.caller),
+            alerts: response.alerts.clone(),
+            external_id: response.external_id.clone(),
+            cached: true,
+        });
+        return Ok(FetchAlertsResponse {
+            cached: Some(true),
+            ..response
+        });
+    }
+
+    let mut last_error = None;
+    for provider in pick_providers()? {
+        let (external_id, alerts) = match provider.utxo_alerts(request.clone()).await {
+            Ok(result) => result,
+            Err(err) => {
+                on_provider_failure(&provider, &err);
+                last_error = Some(err);
+                // Try again with a different provider.
+                continue;
+            }
         };
 
         UTXO_CHECKS_COUNT.with(|c| c.set(c.get() + 1));
@@ -491,12 +556,38 @@ async fn fetch_utxo_alerts(request: DepositRequest) -> Result<FetchAlertsRespons
             caller: Some(request.caller),
             alerts: alerts.clone(),
             external_id: external_id.clone(),
+            cached: false,
         });
-        return Ok(FetchAlertsResponse {
+        let response = FetchAlertsResponse {
             external_id,
             alerts,
-            provider,
+            provider: provider.id(),
+            cached: Some(false),
+        };
+        UTXO_CHECK_CACHE.with(|c| {
+            c.borrow_mut()
+                .insert(cache_key, response.clone(), ic_cdk::api::time())
         });
+        return Ok(response);
+    }
+    Err(all_providers_failed(last_error))
+}
+
+/// Handles a failed call to a provider before we fall back to the next one.
+fn on_provider_failure(provider: &Provider, err: &KytCheckError) {
+    PROVIDER_FAILURES_COUNT.with(|c| c.set(c.get() + 1));
+    if let KytCheckError::RpcError(e) = err {
+        if e.is_access_denied_error() {
+            expire_key(provider.id());
+        }
+    }
+}
+
+fn all_providers_failed(last_error: Option<KytCheckError>) -> Error {
+    match last_error {
+        Some(KytCheckError::TimedOut(msg)) => Error::TemporarilyUnavailable(msg),
+        Some(KytCheckError::RpcError(e)) => Error::TemporarilyUnavailable(e.to_string()),
+        None => Error::TemporarilyUnavailable("No valid API keys".to_string()),
     }
 }
 
@@ -528,35 +619,16 @@ async fn get_withdrawal_alerts(
 async fn fetch_withdrawal_alerts(
     withdrawal: WithdrawalAttempt,
 ) -> Result<FetchAlertsResponse, Error> {
-    loop {
-        let (provider, api_key) = pick_api_key()?;
-
-        let (external_id, alerts) = match kyt_mode() {
-            KytMode::Normal => match get_withdrawal_alerts(api_key, withdrawal.clone()).await {
-                Ok(result) => result,
-                Err(KytCheckError::TimedOut(msg)) => {
-                    return Err(Error::TemporarilyUnavailable(msg))
-                }
-                Err(KytCheckError::RpcError(e)) => {
-                    if e.is_access_denied_error() {
-                        expire_key(provider);
-                        // Try again with a different provider.
-                        continue;
-                    } else {
-                        return Err(Error::TemporarilyUnavailable(e.to_string()));
-                    }
-                }
-            },
-            KytMode::AcceptAll => (ic_cdk::api::time().to_string(), vec![]),
-            KytMode::RejectAll => (
-                ic_cdk::api::time().to_string(),
-                vec![Alert {
-                    level: AlertLevel::Severe,
-                    service: None,
-                    category: None,
-                    exposure_type: ExposureType::Direct,
-                }],
-            ),
+    let mut last_error = None;
+    for provider in pick_providers()? {
+        let (external_id, alerts) = match provider.withdrawal_alerts(withdrawal.clone()).await {
+            Ok(result) => result,
+            Err(err) => {
+                on_provider_failure(&provider, &err);
+                last_error = Some(err);
+                // Try again with a different provider.
+                continue;
+            }
         };
 
         ADDRESS_CHECKS_COUNT.with(|c| c.set(c.get() + 1));
@@ -564,17 +636,20 @@ async fn fetch_withdrawal_alerts(
         record_event(EventKind::AddressCheck {
             caller: Some(withdrawal.caller),
             withdrawal_id: withdrawal.id,
-            address: withdrawal.address,
+            address: withdrawal.address.clone(),
             amount: withdrawal.amount,
             alerts: alerts.clone(),
             external_id: external_id.clone(),
+            cached: false,
         });
         return Ok(FetchAlertsResponse {
             external_id,
             alerts,
-            provider,
+            provider: provider.id(),
+            cached: Some(false),
         });
     }
+    Err(all_providers_failed(last_error))
 }
 
 #[query]
@@ -663,6 +738,34 @@ fn http_request(req: http::HttpRequest) -> http::HttpResponse {
             )
             .unwrap();
 
+        writer
+            .encode_counter(
+                "ckbtc_kyt_cache_hits_total",
+                CACHE_HITS_COUNT.with(|c| c.get() as f64),
+                "The number of KYT requests served from the result cache since the last canister upgrade.",
+            )
+            .unwrap();
+
+        writer
+            .encode_counter(
+                "ckbtc_kyt_provider_failures_total",
+                PROVIDER_FAILURES_COUNT.with(|c| c.get() as f64),
+                "The number of failed provider calls since the last canister upgrade.",
+            )
+            .unwrap();
+
+        writer
+            .gauge_vec(
+                "ckbtc_kyt_cached_results",
+                "The number of check results in the result caches.",
+            )
+            .unwrap()
+            .value(
+                &[("type", "utxo_check")],
+                UTXO_CHECK_CACHE.with(|c| c.borrow().len() as f64),
+            )
+            .unwrap();
+
         http::HttpResponseBuilder::ok()
             .header("Content-Type", "text/plain; version=0.0.4")
             .with_body_and_content_length(writer.into_inner())
@@ -680,6 +783,7 @@ fn http_request(req: http::HttpRequest) -> http::HttpResponse {
         let dashboard = dashboard::DashboardTemplate {
             minter_id: config.minter_id,
             maintainers: config.maintainers,
+            providers: config.api_keys.keys().cloned().collect(),
             events,
             last_api_key_update_date: format_timestamp(
                 config.last_api_key_update.unwrap_or_default(),
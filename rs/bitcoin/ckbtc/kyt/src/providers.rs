@@ -0,0 +1,148 @@
+use crate::{json_rpc, DisplayTxid, KytCheckError};
+use candid::Principal;
+use ic_ckbtc_kyt::{
+    Alert, AlertLevel, DepositRequest, ExposureType, MockProviderConfig, WithdrawalAttempt,
+};
+
+/// A backend that checks Bitcoin transfers and reports alerts.
+pub enum Provider {
+    /// Checks transfers using the Chainalysis KYT API.
+    Chainalysis { id: Principal, api_key: String },
+    /// Reports no alerts for all requests.
+    AcceptAll { id: Principal },
+    /// Reports a severe alert for all requests.
+    RejectAll { id: Principal },
+    /// Reports alerts according to a static configuration without making any
+    /// HTTP calls.
+    Mock {
+        id: Principal,
+        config: MockProviderConfig,
+    },
+}
+
+impl Provider {
+    /// Returns the principal that the minter should pay for the checks
+    /// performed by this provider.
+    pub fn id(&self) -> Principal {
+        match self {
+            Provider::Chainalysis { id, .. }
+            | Provider::AcceptAll { id }
+            | Provider::RejectAll { id }
+            | Provider::Mock { id, .. } => *id,
+        }
+    }
+
+    pub async fn utxo_alerts(
+        &self,
+        request: DepositRequest,
+    ) -> Result<(json_rpc::ExternalId, Vec<Alert>), KytCheckError> {
+        match self {
+            Provider::Chainalysis { api_key, .. } => {
+                crate::get_utxo_alerts(api_key.clone(), request).await
+            }
+            Provider::AcceptAll { .. } => Ok((ic_cdk::api::time().to_string(), vec![])),
+            Provider::RejectAll { .. } => {
+                Ok((ic_cdk::api::time().to_string(), vec![severe_alert()]))
+            }
+            Provider::Mock { config, .. } => Ok(mock_utxo_alerts(config, &request)),
+        }
+    }
+
+    pub async fn withdrawal_alerts(
+        &self,
+        withdrawal: WithdrawalAttempt,
+    ) -> Result<(json_rpc::ExternalId, Vec<Alert>), KytCheckError> {
+        match self {
+            Provider::Chainalysis { api_key, .. } => {
+                crate::get_withdrawal_alerts(api_key.clone(), withdrawal).await
+            }
+            Provider::AcceptAll { .. } => Ok((ic_cdk::api::time().to_string(), vec![])),
+            Provider::RejectAll { .. } => {
+                Ok((ic_cdk::api::time().to_string(), vec![severe_alert()]))
+            }
+            Provider::Mock { config, .. } => Ok(mock_withdrawal_alerts(config, &withdrawal)),
+        }
+    }
+}
+
+fn severe_alert() -> Alert {
+    Alert {
+        level: AlertLevel::Severe,
+        category: None,
+        service: None,
+        exposure_type: ExposureType::Direct,
+    }
+}
+
+fn mock_alert() -> Alert {
+    Alert {
+        category: Some("mock".to_string()),
+        ..severe_alert()
+    }
+}
+
+fn mock_utxo_alerts(
+    config: &MockProviderConfig,
+    request: &DepositRequest,
+) -> (json_rpc::ExternalId, Vec<Alert>) {
+    let external_id = format!("mock:{}:{}", DisplayTxid(&request.txid), request.vout);
+    if config.tainted_txids.contains(&request.txid) {
+        (external_id, vec![mock_alert()])
+    } else {
+        (external_id, vec![])
+    }
+}
+
+fn mock_withdrawal_alerts(
+    config: &MockProviderConfig,
+    withdrawal: &WithdrawalAttempt,
+) -> (json_rpc::ExternalId, Vec<Alert>) {
+    let external_id = format!("mock:{}", withdrawal.id);
+    if config.tainted_addresses.contains(&withdrawal.address) {
+        (external_id, vec![mock_alert()])
+    } else {
+        (external_id, vec![])
+    }
+}
+
+#[test]
+fn test_mock_provider_is_deterministic() {
+    let config = MockProviderConfig {
+        tainted_txids: vec![[1; 32]],
+        tainted_addresses: vec!["bc1qtainted".to_string()],
+    };
+    let deposit = |txid| DepositRequest {
+        caller: Principal::anonymous(),
+        txid,
+        vout: 2,
+    };
+
+    let (external_id, alerts) = mock_utxo_alerts(&config, &deposit([1; 32]));
+    assert_eq!(
+        external_id,
+        format!("mock:{}:2", "01".repeat(32)),
+        "unexpected external id"
+    );
+    assert_eq!(alerts, vec![mock_alert()]);
+    assert_eq!(
+        mock_utxo_alerts(&config, &deposit([1; 32])),
+        mock_utxo_alerts(&config, &deposit([1; 32]))
+    );
+    assert_eq!(mock_utxo_alerts(&config, &deposit([2; 32])).1, vec![]);
+
+    let withdrawal = |address: &str| WithdrawalAttempt {
+        caller: Principal::anonymous(),
+        id: "42".to_string(),
+        amount: 100_000,
+        address: address.to_string(),
+        timestamp_nanos: 0,
+    };
+    assert_eq!(
+        mock_withdrawal_alerts(&config, &withdrawal("bc1qtainted")),
+        ("mock:42".to_string(), vec![mock_alert()])
+    );
+    assert_eq!(
+        mock_withdrawal_alerts(&config, &withdrawal("bc1qclean")),
+        ("mock:42".to_string(), vec![])
+    );
+}
@@ -2,11 +2,11 @@ use assert_matches::assert_matches;
 use candid::{Decode, Encode, Principal};
 use ic_ckbtc_kyt::{
     Alert, AlertLevel, DepositRequest, Error as KytError, ExposureType, FetchAlertsResponse,
-    InitArg, KytMode, LifecycleArg, SetApiKeyArg,
+    InitArg, KytMode, LifecycleArg, MockProviderConfig, SetApiKeyArg, WithdrawalAttempt,
 };
 use ic_state_machine_tests::{
-    CanisterHttpRequestContext, CanisterHttpResponsePayload, Cycles, IngressState, IngressStatus,
-    PayloadBuilder, StateMachine, WasmResult,
+    CanisterHttpRequestContext, CanisterHttpResponsePayload, CanisterId, Cycles, IngressState,
+    IngressStatus, PayloadBuilder, StateMachine, WasmResult,
 };
 use ic_test_utilities_load_wasm::load_wasm;
 
@@ -56,6 +56,33 @@ fn kyt_wasm() -> Vec<u8> {
     )
 }
 
+fn install_kyt(
+    env: &StateMachine,
+    minter_id: Principal,
+    maintainers: Vec<Principal>,
+    mode: KytMode,
+) -> CanisterId {
+    env.install_canister_with_cycles(
+        kyt_wasm(),
+        Encode!(&LifecycleArg::InitArg(InitArg {
+            minter_id,
+            maintainers,
+            mode,
+        }))
+        .unwrap(),
+        None,
+        Cycles::from(100_000_000_000_000u64),
+    )
+    .expect("failed to install the KYT canister")
+}
+
+fn decode_alerts_response(result: WasmResult) -> Result<FetchAlertsResponse, KytError> {
+    match result {
+        WasmResult::Reply(bytes) => Decode!(&bytes, Result<FetchAlertsResponse, KytError>).unwrap(),
+        WasmResult::Reject(msg) => panic!("unexpected reject: {}", msg),
+    }
+}
+
 #[test]
 fn test_key_recovery() {
     let env = StateMachine::new();
@@ -193,9 +220,172 @@ fn test_key_recovery() {
                         service: Some("S".to_string()),
                         exposure_type: ExposureType::Direct,
                     }],
+                    cached: Some(false),
                 })
             );
         }
         WasmResult::Reject(msg) => panic!("unexpected reject: {}", msg),
     }
 }
+
+#[test]
+fn test_mock_provider() {
+    let env = StateMachine::new();
+    let minter_id = Principal::anonymous();
+
+    let kyt = install_kyt(
+        &env,
+        minter_id,
+        vec![],
+        KytMode::Mock(MockProviderConfig {
+            tainted_txids: vec![[1; 32]],
+            tainted_addresses: vec!["bc1qtainted".to_string()],
+        }),
+    );
+
+    let fetch_utxo_alerts = |txid: [u8; 32]| {
+        let result = env
+            .execute_ingress_as(
+                minter_id.into(),
+                kyt,
+                "fetch_utxo_alerts",
+                Encode!(&DepositRequest {
+                    caller: minter_id,
+                    txid,
+                    vout: 0
+                })
+                .unwrap(),
+            )
+            .expect("failed to fetch UTXO alerts");
+        decode_alerts_response(result).expect("the mock provider failed")
+    };
+
+    let tainted = fetch_utxo_alerts([1; 32]);
+    assert_eq!(tainted.provider, kyt.get().0);
+    assert_eq!(tainted.alerts.len(), 1);
+    assert_eq!(tainted.alerts[0].level, AlertLevel::Severe);
+    // The mock provider must not make any HTTP calls.
+    assert!(env.canister_http_request_contexts().is_empty());
+
+    let clean = fetch_utxo_alerts([2; 32]);
+    assert_eq!(clean.alerts, vec![]);
+    assert!(!clean.is_cached());
+    // Repeated checks return the cached result.
+    assert_eq!(
+        fetch_utxo_alerts([2; 32]),
+        FetchAlertsResponse {
+            cached: Some(true),
+            ..clean
+        }
+    );
+
+    let fetch_withdrawal_alerts = |address: &str, amount: u64| {
+        let result = env
+            .execute_ingress_as(
+                minter_id.into(),
+                kyt,
+                "fetch_withdrawal_alerts",
+                Encode!(&WithdrawalAttempt {
+                    caller: minter_id,
+                    id: "1".to_string(),
+                    amount,
+                    address: address.to_string(),
+                    timestamp_nanos: 0,
+                })
+                .unwrap(),
+            )
+            .expect("failed to fetch withdrawal alerts");
+        decode_alerts_response(result).expect("the mock provider failed")
+    };
+
+    assert_eq!(
+        fetch_withdrawal_alerts("bc1qtainted", 100_000).alerts.len(),
+        1
+    );
+    assert_eq!(fetch_withdrawal_alerts("bc1qclean", 100_000).alerts, vec![]);
+    // Every withdrawal attempt is checked again.
+    assert!(!fetch_withdrawal_alerts("bc1qclean", 100_000).is_cached());
+    assert!(env.canister_http_request_contexts().is_empty());
+}
+
+#[test]
+fn test_provider_fallback() {
+    let env = StateMachine::new();
+    let p1 = Principal::management_canister();
+    let p2 = Principal::anonymous();
+    let minter_id = Principal::anonymous();
+
+    let kyt = install_kyt(&env, minter_id, vec![p1, p2], KytMode::Normal);
+
+    for (maintainer, api_key) in [(p1, "Key1"), (p2, "Key2")] {
+        env.execute_ingress_as(
+            maintainer.into(),
+            kyt,
+            "set_api_key",
+            Encode!(&SetApiKeyArg {
+                api_key: api_key.to_string()
+            })
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
+    let call_id = env.send_ingress(
+        minter_id.into(),
+        kyt,
+        "fetch_utxo_alerts",
+        Encode!(&DepositRequest {
+            caller: minter_id,
+            txid: [0; 32],
+            vout: 0
+        })
+        .unwrap(),
+    );
+
+    env.tick();
+
+    handle_http_call("transfer with a failing provider", &env, |req| {
+        assert_has_header(req, "Token", "Key1");
+        CanisterHttpResponsePayload {
+            status: 500,
+            headers: vec![],
+            body: br#"{"status": 500, "message": "Internal Server Error"}"#.to_vec(),
+        }
+    });
+
+    tick_until_next_request(&env);
+
+    handle_http_call("retry transfer with the fallback provider", &env, |req| {
+        assert_has_header(req, "Token", "Key2");
+        CanisterHttpResponsePayload {
+            status: 200,
+            headers: vec![],
+            body: br#"{"externalId": "12356-abcde", "updatedAt": "2023-03-02T15:23:27+00:00", "transferReference":"0000000000000000000000000000000000000000000000000000000000000000:0"}"#.to_vec(),
+        }
+    });
+
+    tick_until_next_request(&env);
+
+    handle_http_call("fetch alerts", &env, |req| {
+        assert_has_header(req, "Token", "Key2");
+        CanisterHttpResponsePayload {
+            status: 200,
+            headers: vec![],
+            body: br#"{"alerts": []}"#.to_vec(),
+        }
+    });
+
+    let result = env
+        .await_ingress(call_id, /*max_ticks=*/ MAX_TICKS)
+        .expect("the fetch request didn't finish");
+
+    assert_eq!(
+        decode_alerts_response(result),
+        Ok(FetchAlertsResponse {
+            external_id: "12356-abcde".to_string(),
+            provider: p2,
+            alerts: vec![],
+            cached: Some(false),
+        })
+    );
+}
@@ -284,6 +284,14 @@ pub fn build_metadata() -> String {
                         <th>Total BTC managed</th>
                         <td>{}</td>
                     </tr>
+                    <tr>
+                        <th>Minter fee balance</th>
+                        <td>{}</td>
+                    </tr>
+                    <tr>
+                        <th>UTXO consolidation threshold</th>
+                        <td>{}</td>
+                    </tr>
                 </tbody>
             </table>",
             s.btc_network,
@@ -300,7 +308,9 @@ pub fn build_metadata() -> String {
                 .unwrap_or_else(|| "N/A".to_string()),
             DisplayAmount(s.kyt_fee),
             DisplayAmount(s.retrieve_btc_min_amount),
-            DisplayAmount(get_total_btc_managed())
+            DisplayAmount(get_total_btc_managed()),
+            DisplayAmount(s.minter_fee_balance),
+            crate::UTXO_CONSOLIDATION_THRESHOLD,
         )
     })
 }
@@ -363,6 +373,9 @@ pub fn build_submitted_transactions() -> String {
                         .unwrap();
 
                         write!(buf, "<td rowspan='{}'>", rowspan).unwrap();
+                        if tx.requests.is_empty() {
+                            write!(buf, "UTXO consolidation").unwrap();
+                        }
                         for req in &tx.requests {
                             write!(
                                 buf,
@@ -58,6 +58,17 @@ pub const MIN_RELAY_FEE_PER_VBYTE: MillisatoshiPerByte = 1_000;
 /// The minimum time the minter should wait before replacing a stuck transaction.
 pub const MIN_RESUBMISSION_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
 
+/// The number of available UTXOs above which the minter starts consolidating
+/// them into larger outputs.
+pub const UTXO_CONSOLIDATION_THRESHOLD: usize = 1_000;
+
+/// The maximum number of UTXOs that a single consolidation transaction spends.
+pub const MAX_INPUTS_PER_CONSOLIDATION: usize = 100;
+
+/// The maximum median network fee (in millisatoshi per vbyte) at which the
+/// minter still consolidates UTXOs.
+pub const MAX_CONSOLIDATION_FEE_PER_VBYTE: MillisatoshiPerByte = 10_000;
+
 /// The maximum memo size of a transaction on the ckBTC ledger.
 /// The ckBTC minter requires at least 69 bytes, we choose 80
 /// to have some room for future modifications.
@@ -404,6 +415,158 @@ async fn submit_pending_requests() {
     }
 }
 
+/// Consolidates the smallest minter UTXOs into a single output if the minter
+/// owns too many UTXOs and the network fees are low. The minter pays the
+/// Bitcoin fee from its fee balance.
+async fn consolidate_utxos() {
+    let maybe_fee_per_vbyte = state::read_state(|s| {
+        if s.available_utxos.len() <= UTXO_CONSOLIDATION_THRESHOLD || s.has_pending_consolidation()
+        {
+            return None;
+        }
+        // We rely on the fee percentiles from the latest fee refresh.
+        match (s.last_fee_per_vbyte.get(25), s.last_fee_per_vbyte.get(50)) {
+            (Some(low_fee), Some(median_fee)) if *median_fee <= MAX_CONSOLIDATION_FEE_PER_VBYTE => {
+                Some((*low_fee).max(MIN_RELAY_FEE_PER_VBYTE))
+            }
+            _ => {
+                log!(
+                    P1,
+                    "[consolidate_utxos]: postponing consolidation of {} UTXOs until fees go down",
+                    s.available_utxos.len()
+                );
+                None
+            }
+        }
+    });
+    let fee_per_vbyte = match maybe_fee_per_vbyte {
+        Some(fee) => fee,
+        None => return,
+    };
+
+    let main_account = Account {
+        owner: ic_cdk::id(),
+        subaccount: None,
+    };
+
+    let ecdsa_public_key = updates::get_btc_address::init_ecdsa_public_key().await;
+    let main_address = address::account_to_bitcoin_address(&ecdsa_public_key, &main_account);
+
+    let maybe_sign_request = state::mutate_state(|s| {
+        let mut utxos =
+            select_consolidation_utxos(&mut s.available_utxos, MAX_INPUTS_PER_CONSOLIDATION);
+
+        match build_consolidation_transaction(&mut utxos, main_address, fee_per_vbyte) {
+            Ok((unsigned_tx, change_output, used_utxos)) => {
+                let fee = used_utxos.iter().map(|u| u.value).sum::<u64>() - change_output.value;
+                if fee > s.minter_fee_balance {
+                    log!(P0,
+                        "[consolidate_utxos]: the consolidation fee {} exceeds the minter fee balance {}",
+                        tx::DisplayAmount(fee),
+                        tx::DisplayAmount(s.minter_fee_balance),
+                    );
+                    s.available_utxos.extend(used_utxos);
+                    return None;
+                }
+
+                Some(SignTxRequest {
+                    key_name: s.ecdsa_key_name.clone(),
+                    ecdsa_public_key,
+                    change_output,
+                    outpoint_account: filter_output_accounts(s, &unsigned_tx),
+                    network: s.btc_network,
+                    unsigned_tx,
+                    requests: vec![],
+                    utxos: used_utxos,
+                })
+            }
+            Err(err) => {
+                log!(
+                    P0,
+                    "[consolidate_utxos]: failed to build a consolidation transaction for {} UTXOs: {:?}",
+                    utxos.len(),
+                    err
+                );
+                s.available_utxos.append(&mut utxos);
+                None
+            }
+        }
+    });
+
+    let req = match maybe_sign_request {
+        Some(req) => req,
+        None => return,
+    };
+
+    log!(
+        P1,
+        "[consolidate_utxos]: signing a consolidation transaction: {}",
+        hex::encode(tx::encode_into(&req.unsigned_tx, Vec::new()))
+    );
+
+    // This guard ensures that we return the UTXOs back to the state if
+    // signing or sending the transaction fails or panics.
+    let utxos_guard = guard(req.utxos, |utxos| {
+        undo_sign_request(vec![], utxos);
+    });
+
+    let txid = req.unsigned_tx.txid();
+
+    let signed_tx = match sign_transaction(
+        req.key_name,
+        &req.ecdsa_public_key,
+        &req.outpoint_account,
+        req.unsigned_tx,
+    )
+    .await
+    {
+        Ok(tx) => tx,
+        Err(err) => {
+            log!(
+                P0,
+                "[consolidate_utxos]: failed to sign a BTC transaction: {}",
+                err
+            );
+            return;
+        }
+    };
+
+    match management::send_transaction(&signed_tx, req.network).await {
+        Ok(()) => {
+            log!(
+                P0,
+                "[consolidate_utxos]: sent consolidation transaction {} spending {} UTXOs",
+                &txid,
+                utxos_guard.len(),
+            );
+
+            // Defuse the guard because we sent the transaction successfully.
+            let used_utxos = ScopeGuard::into_inner(utxos_guard);
+
+            state::mutate_state(|s| {
+                state::audit::sent_transaction(
+                    s,
+                    state::SubmittedBtcTransaction {
+                        requests: vec![],
+                        txid,
+                        used_utxos,
+                        change_output: Some(req.change_output),
+                        submitted_at: ic_cdk::api::time(),
+                        fee_per_vbyte: Some(fee_per_vbyte),
+                    },
+                );
+            });
+        }
+        Err(err) => {
+            log!(
+                P0,
+                "[consolidate_utxos]: failed to send a bitcoin transaction: {}",
+                err
+            );
+        }
+    }
+}
+
 fn finalization_time_estimate(min_confirmations: u32, network: Network) -> Duration {
     Duration::from_nanos(
         min_confirmations as u64
@@ -616,18 +779,20 @@ async fn finalize_requests() {
             None => fee_per_vbyte,
         };
 
-        let outputs = submitted_tx
-            .requests
-            .iter()
-            .map(|req| (req.address.clone(), req.amount))
-            .collect();
+        let maybe_tx = if submitted_tx.requests.is_empty() {
+            // A transaction without requests consolidates the minter UTXOs.
+            build_consolidation_transaction(&mut utxos, main_address.clone(), tx_fee_per_vbyte)
+        } else {
+            let outputs = submitted_tx
+                .requests
+                .iter()
+                .map(|req| (req.address.clone(), req.amount))
+                .collect();
 
-        let (unsigned_tx, change_output, used_utxos) = match build_unsigned_transaction(
-            &mut utxos,
-            outputs,
-            main_address.clone(),
-            tx_fee_per_vbyte,
-        ) {
+            build_unsigned_transaction(&mut utxos, outputs, main_address.clone(), tx_fee_per_vbyte)
+        };
+
+        let (unsigned_tx, change_output, used_utxos) = match maybe_tx {
             Ok(tx) => tx,
             // If it's impossible to build a new transaction, the fees probably became too high.
             // Let's ignore this transaction and wait for fees to go down.
@@ -642,6 +807,28 @@ async fn finalize_requests() {
             }
         };
 
+        if submitted_tx.requests.is_empty() {
+            // The minter pays the fee increase of a consolidation transaction from its fee
+            // balance.
+            let fee_increase = submitted_tx
+                .change_output
+                .as_ref()
+                .map(|out| out.value)
+                .unwrap_or_default()
+                .saturating_sub(change_output.value);
+            let minter_fee_balance = state::read_state(|s| s.minter_fee_balance);
+            if fee_increase > minter_fee_balance {
+                log!(
+                    P1,
+                    "[finalize_requests]: cannot afford fee increase {} for consolidation transaction {} (minter fee balance: {})",
+                    tx::DisplayAmount(fee_increase),
+                    &submitted_tx.txid,
+                    tx::DisplayAmount(minter_fee_balance),
+                );
+                continue;
+            }
+        }
+
         let outpoint_account = state::read_state(|s| filter_output_accounts(s, &unsigned_tx));
 
         assert!(
@@ -845,6 +1032,18 @@ pub fn fake_sign(unsigned_tx: &tx::UnsignedTransaction) -> tx::SignedTransaction
     }
 }
 
+/// Having a sequence number lower than (0xffffffff - 1) signals the use of replacement by fee.
+/// It allows us to increase the fee of a transaction already sent to the mempool.
+/// The rbf option is used in `resubmit_retrieve_btc`.
+/// https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
+const SEQUENCE_RBF_ENABLED: u32 = 0xfffffffd;
+
+/// The default dustRelayFee is 3 sat/vB,
+/// which translates to a dust threshold of 546 satoshi for P2PKH outputs.
+/// The threshold for other types is lower,
+/// so we simply use 546 satoshi as the minimum amount per output.
+const MIN_OUTPUT_AMOUNT: u64 = 546;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum BuildTxError {
     /// The minter does not have enough UTXOs to make the transfer
@@ -915,12 +1114,6 @@ pub fn build_unsigned_transaction(
 ) -> Result<(tx::UnsignedTransaction, state::ChangeOutput, Vec<Utxo>), BuildTxError> {
     assert!(!outputs.is_empty());
 
-    /// Having a sequence number lower than (0xffffffff - 1) signals the use of replacement by fee.
-    /// It allows us to increase the fee of a transaction already sent to the mempool.
-    /// The rbf option is used in `resubmit_retrieve_btc`.
-    /// https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
-    const SEQUENCE_RBF_ENABLED: u32 = 0xfffffffd;
-
     let amount = outputs.iter().map(|(_, amount)| amount).sum::<u64>();
 
     let input_utxos = greedy(amount, minter_utxos);
@@ -989,12 +1182,6 @@ pub fn build_unsigned_transaction(
     }
 
     let fee_shares = distribute(fee + minter_fee, outputs.len() as u64);
-    // The default dustRelayFee is 3 sat/vB,
-    // which translates to a dust threshold of 546 satoshi for P2PKH outputs.
-    // The threshold for other types is lower,
-    // so we simply use 546 satoshi as the minimum amount per output.
-    const MIN_OUTPUT_AMOUNT: u64 = 546;
-
     for (output, fee_share) in unsigned_tx.outputs.iter_mut().zip(fee_shares.iter()) {
         if output.address != main_address {
             if output.value <= *fee_share + MIN_OUTPUT_AMOUNT {
@@ -1019,6 +1206,81 @@ pub fn build_unsigned_transaction(
     ))
 }
 
+/// Builds a transaction that spends all the specified minter UTXOs and sends
+/// their total value minus the Bitcoin fee back to the minter main address.
+///
+/// The minter pays the Bitcoin fee from its fee balance, so the transaction
+/// has a single output, the change output.
+///
+/// # Panics
+///
+/// This function panics if the `minter_utxos` set is empty as it indicates a
+/// bug in the caller's code.
+///
+/// # Error case properties
+///
+/// * In case of errors, the function does not modify the inputs.
+/// ```text
+/// result.is_err() => minter_utxos' == minter_utxos
+/// ```
+pub fn build_consolidation_transaction(
+    minter_utxos: &mut BTreeSet<Utxo>,
+    main_address: BitcoinAddress,
+    fee_per_vbyte: u64,
+) -> Result<(tx::UnsignedTransaction, state::ChangeOutput, Vec<Utxo>), BuildTxError> {
+    assert!(!minter_utxos.is_empty());
+
+    let inputs_value = minter_utxos.iter().map(|u| u.value).sum::<u64>();
+
+    let mut unsigned_tx = tx::UnsignedTransaction {
+        inputs: minter_utxos
+            .iter()
+            .map(|utxo| tx::UnsignedInput {
+                previous_output: utxo.outpoint.clone(),
+                value: utxo.value,
+                sequence: SEQUENCE_RBF_ENABLED,
+            })
+            .collect(),
+        outputs: vec![tx::TxOut {
+            address: main_address,
+            value: inputs_value,
+        }],
+        lock_time: 0,
+    };
+
+    let tx_vsize = fake_sign(&unsigned_tx).vsize();
+    let fee = (tx_vsize as u64 * fee_per_vbyte) / 1000;
+
+    if inputs_value <= fee + MIN_OUTPUT_AMOUNT {
+        return Err(BuildTxError::AmountTooLow);
+    }
+
+    let change_output = state::ChangeOutput {
+        vout: 0,
+        value: inputs_value - fee,
+    };
+    unsigned_tx.outputs[0].value = change_output.value;
+
+    let used_utxos = std::mem::take(minter_utxos).into_iter().collect();
+
+    Ok((unsigned_tx, change_output, used_utxos))
+}
+
+/// Removes up to `max_count` UTXOs with the lowest values from the available
+/// set and returns them.
+fn select_consolidation_utxos(
+    available_utxos: &mut BTreeSet<Utxo>,
+    max_count: usize,
+) -> BTreeSet<Utxo> {
+    let mut by_value: Vec<Utxo> = available_utxos.iter().cloned().collect();
+    by_value.sort_by_key(|u| u.value);
+    by_value.truncate(max_count);
+    for utxo in by_value.iter() {
+        available_utxos.remove(utxo);
+    }
+    by_value.into_iter().collect()
+}
+
 /// Distributes an amount across the specified number of shares as fairly as
 /// possible.
 ///
@@ -1152,6 +1414,24 @@ pub fn timer() {
                 schedule_after(FEE_ESTIMATE_DELAY, TaskType::RefreshFeePercentiles);
             });
         }
+        TaskType::ConsolidateUtxos => {
+            ic_cdk::spawn(async {
+                const CONSOLIDATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+                let _enqueue_followup_guard = guard((), |_| {
+                    schedule_after(CONSOLIDATION_INTERVAL, TaskType::ConsolidateUtxos)
+                });
+
+                // Consolidation competes with retrieve_btc requests for the
+                // available UTXOs, so it shares the guard with the main logic.
+                let _guard = match crate::guard::TimerLogicGuard::new() {
+                    Some(guard) => guard,
+                    None => return,
+                };
+
+                consolidate_utxos().await;
+            });
+        }
         TaskType::DistributeKytFee => {
             ic_cdk::spawn(async {
                 let _guard = match crate::guard::DistributeKytFeeGuard::new() {
@@ -128,6 +128,12 @@ pub fn encode_metrics(
         "Total number of UTXOs the minter can use for retrieve_btc requests.",
     )?;
 
+    metrics.encode_gauge(
+        "ckbtc_minter_fee_balance",
+        state::read_state(|s| s.minter_fee_balance) as f64,
+        "The amount of BTC (in satoshi) the minter collected as fees and can spend on UTXO consolidation.",
+    )?;
+
     metrics
         .counter_vec(
             "ckbtc_minter_get_utxos_calls",
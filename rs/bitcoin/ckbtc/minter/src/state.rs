@@ -331,6 +331,11 @@ pub struct CkBtcMinterState {
     /// Map from burn block index to amount to reimburse because of
     /// KYT fees.
     pub reimbursement_map: BTreeMap<u64, ReimburseDepositTask>,
+
+    /// The amount of BTC (in satoshi) that the minter collected as fees on
+    /// retrieve_btc transactions and can spend on its own transactions, such
+    /// as UTXO consolidation.
+    pub minter_fee_balance: u64,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, Serialize)]
@@ -748,6 +753,7 @@ impl CkBtcMinterState {
         }
 
         let new_txid = tx.txid;
+        let (new_inflow, new_outflow) = fee_balance_flows(&tx);
         let pos = self
             .submitted_transactions
             .iter()
@@ -758,6 +764,12 @@ impl CkBtcMinterState {
         // tx points to the old transaction now.
         debug_assert_eq!(&tx.txid, old_txid);
 
+        // Revert the fee balance change of the old transaction before applying
+        // the change of the replacement.
+        let (old_inflow, old_outflow) = fee_balance_flows(&tx);
+        self.update_fee_balance((old_outflow, old_inflow));
+        self.update_fee_balance((new_inflow, new_outflow));
+
         self.stuck_transactions.push(tx);
         self.replacement_txid.insert(*old_txid, new_txid);
         self.rev_replacement_txid.insert(new_txid, *old_txid);
@@ -845,9 +857,23 @@ impl CkBtcMinterState {
             assert!(!self.has_pending_request(req.block_index));
             self.requests_in_flight.remove(&req.block_index);
         }
+        self.update_fee_balance(fee_balance_flows(&tx));
         self.submitted_transactions.push(tx);
     }
 
+    /// Returns true if the minter has a submitted UTXO consolidation
+    /// transaction that did not receive enough confirmations yet.
+    pub fn has_pending_consolidation(&self) -> bool {
+        self.submitted_transactions
+            .iter()
+            .any(|tx| tx.requests.is_empty())
+    }
+
+    /// Adds `inflow` to the minter fee balance and subtracts `outflow` from it.
+    fn update_fee_balance(&mut self, (inflow, outflow): (u64, u64)) {
+        self.minter_fee_balance = (self.minter_fee_balance + inflow).saturating_sub(outflow);
+    }
+
     /// Marks the specified retrieve_btc request as finalized.
     ///
     /// # Panics
@@ -1035,6 +1061,12 @@ impl CkBtcMinterState {
             "kyt_principal does not match"
         );
 
+        ensure_eq!(
+            self.minter_fee_balance,
+            other.minter_fee_balance,
+            "minter_fee_balance does not match"
+        );
+
         let my_txs = as_sorted_vec(self.submitted_transactions.iter().cloned(), |tx| tx.txid);
         let other_txs = as_sorted_vec(other.submitted_transactions.iter().cloned(), |tx| tx.txid);
         ensure_eq!(my_txs, other_txs, "submitted_transactions do not match");
@@ -1074,6 +1106,23 @@ impl CkBtcMinterState {
     }
 }
 
+/// Returns the amounts that the specified transaction adds to and removes from
+/// the minter fee balance.
+///
+/// The change output of a retrieve_btc transaction holds the change plus the
+/// minter fee, so the balance grows by `change + requested - inputs`. A UTXO
+/// consolidation transaction has no requests, so the balance decreases by the
+/// Bitcoin fee `inputs - change`.
+fn fee_balance_flows(tx: &SubmittedBtcTransaction) -> (u64, u64) {
+    match &tx.change_output {
+        Some(change_output) => (
+            change_output.value + tx.requests.iter().map(|req| req.amount).sum::<u64>(),
+            tx.used_utxos.iter().map(|utxo| utxo.value).sum::<u64>(),
+        ),
+        None => (0, 0),
+    }
+}
+
 fn as_sorted_vec<T, K: Ord>(values: impl Iterator<Item = T>, key: impl Fn(&T) -> K) -> Vec<T> {
     let mut v: Vec<_> = values.collect();
     v.sort_by_key(key);
@@ -1121,6 +1170,7 @@ impl From<InitArgs> for CkBtcMinterState {
             ignored_utxos: Default::default(),
             quarantined_utxos: Default::default(),
             reimbursement_map: Default::default(),
+            minter_fee_balance: 0,
         }
     }
 }
@@ -1,7 +1,8 @@
 use crate::MINTER_FEE_CONSTANT;
 use crate::{
-    address::BitcoinAddress, build_unsigned_transaction, estimate_fee, fake_sign, greedy,
-    signature::EncodedSignature, tx, BuildTxError,
+    address::BitcoinAddress, build_consolidation_transaction, build_unsigned_transaction,
+    estimate_fee, fake_sign, greedy, select_consolidation_utxos, signature::EncodedSignature, tx,
+    BuildTxError,
 };
 use crate::{
     lifecycle::init::InitArgs,
@@ -297,6 +298,156 @@ fn test_no_dust_outputs() {
     assert_eq!(available_utxos.len(), 1);
 }
 
+#[test]
+fn test_build_consolidation_transaction() {
+    let mut available_utxos: BTreeSet<Utxo> = (1..=20u64)
+        .map(|v| dummy_utxo_from_value(v * 10_000))
+        .collect();
+
+    let mut utxos = select_consolidation_utxos(&mut available_utxos, 5);
+    assert_eq!(
+        utxos.iter().map(|u| u.value).collect::<BTreeSet<_>>(),
+        (1..=5u64).map(|v| v * 10_000).collect::<BTreeSet<_>>()
+    );
+    assert_eq!(available_utxos.len(), 15);
+
+    let minter_addr = BitcoinAddress::P2wpkhV0([0; 20]);
+    let fee_per_vbyte = 2000;
+
+    let (tx, change_output, used_utxos) =
+        build_consolidation_transaction(&mut utxos, minter_addr.clone(), fee_per_vbyte)
+            .expect("failed to build a consolidation transaction");
+
+    assert!(utxos.is_empty());
+    assert_eq!(used_utxos.len(), 5);
+    assert_eq!(tx.inputs.len(), 5);
+
+    let inputs_value = used_utxos.iter().map(|u| u.value).sum::<u64>();
+    let fee = fake_sign(&tx).vsize() as u64 * fee_per_vbyte / 1000;
+    assert_eq!(
+        &tx.outputs,
+        &[tx::TxOut {
+            address: minter_addr,
+            value: inputs_value - fee,
+        }]
+    );
+    assert_eq!(
+        change_output,
+        ChangeOutput {
+            vout: 0,
+            value: inputs_value - fee,
+        }
+    );
+}
+
+#[test]
+fn test_consolidation_amount_too_low() {
+    let mut utxos: BTreeSet<Utxo> = (1..=3u64).map(dummy_utxo_from_value).collect();
+    let original_utxos = utxos.clone();
+
+    assert_eq!(
+        build_consolidation_transaction(&mut utxos, BitcoinAddress::P2wpkhV0([0; 20]), 1000),
+        Err(BuildTxError::AmountTooLow)
+    );
+    assert_eq!(utxos, original_utxos);
+}
+
+#[test]
+fn test_minter_fee_balance() {
+    let mut state = CkBtcMinterState::from(InitArgs {
+        btc_network: Network::Regtest.into(),
+        ecdsa_key_name: "".to_string(),
+        retrieve_btc_min_amount: 5_000u64,
+        ledger_id: CanisterId::from_u64(42),
+        max_time_in_queue_nanos: 0,
+        min_confirmations: None,
+        mode: Mode::GeneralAvailability,
+        kyt_fee: None,
+        kyt_principal: None,
+    });
+    let account = Account {
+        owner: Principal::management_canister(),
+        subaccount: None,
+    };
+    state.add_utxos(
+        account,
+        (1..=10u64)
+            .map(|v| dummy_utxo_from_value(v * 100_000))
+            .collect(),
+    );
+
+    let minter_addr = BitcoinAddress::P2wpkhV0([0; 20]);
+    let request = RetrieveBtcRequest {
+        amount: 250_000,
+        address: BitcoinAddress::P2wpkhV0([1; 20]),
+        block_index: 1,
+        received_at: 0,
+        kyt_provider: None,
+    };
+    let (tx, change_output, used_utxos) = build_unsigned_transaction(
+        &mut state.available_utxos,
+        vec![(request.address.clone(), request.amount)],
+        minter_addr.clone(),
+        2000,
+    )
+    .expect("failed to build a transaction");
+    let minter_fee = crate::MINTER_FEE_PER_INPUT * tx.inputs.len() as u64
+        + crate::MINTER_FEE_PER_OUTPUT * tx.outputs.len() as u64
+        + crate::MINTER_FEE_CONSTANT;
+
+    state.push_submitted_transaction(SubmittedBtcTransaction {
+        requests: vec![request],
+        txid: tx.txid(),
+        used_utxos,
+        submitted_at: 0,
+        change_output: Some(change_output),
+        fee_per_vbyte: Some(2000),
+    });
+    assert_eq!(state.minter_fee_balance, minter_fee);
+    assert!(!state.has_pending_consolidation());
+
+    let mut utxos = select_consolidation_utxos(&mut state.available_utxos, 2);
+    let (tx, change_output, used_utxos) =
+        build_consolidation_transaction(&mut utxos, minter_addr.clone(), 1000)
+            .expect("failed to build a consolidation transaction");
+    let fee = used_utxos.iter().map(|u| u.value).sum::<u64>() - change_output.value;
+    assert!(fee < minter_fee);
+    let consolidation_txid = tx.txid();
+
+    state.push_submitted_transaction(SubmittedBtcTransaction {
+        requests: vec![],
+        txid: consolidation_txid,
+        used_utxos: used_utxos.clone(),
+        submitted_at: 0,
+        change_output: Some(change_output),
+        fee_per_vbyte: Some(1000),
+    });
+    assert_eq!(state.minter_fee_balance, minter_fee - fee);
+    assert!(state.has_pending_consolidation());
+
+    // Replacing the consolidation transaction charges only the fee difference.
+    let (tx, change_output, _) = build_consolidation_transaction(
+        &mut used_utxos.iter().cloned().collect(),
+        minter_addr,
+        1500,
+    )
+    .expect("failed to build a consolidation transaction");
+    let new_fee = used_utxos.iter().map(|u| u.value).sum::<u64>() - change_output.value;
+    state.replace_transaction(
+        &consolidation_txid,
+        SubmittedBtcTransaction {
+            requests: vec![],
+            txid: tx.txid(),
+            used_utxos,
+            submitted_at: 0,
+            change_output: Some(change_output),
+            fee_per_vbyte: Some(1500),
+        },
+    );
+    assert_eq!(state.minter_fee_balance, minter_fee - new_fee);
+    state.check_invariants().expect("violated invariants");
+}
+
 #[test]
 fn blocklist_is_sorted() {
     use crate::blocklist::BTC_ADDRESS_BLOCKLIST;
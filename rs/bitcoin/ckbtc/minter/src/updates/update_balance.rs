@@ -273,6 +273,10 @@ async fn kyt_check_utxo(
         return Ok((uuid, status, api_key_owner));
     }
 
+    // We charge the KYT fee even if the KYT canister serves the result from its
+    // cache: we call the KYT canister only for UTXOs that we have not recorded
+    // as checked, so a cached result means that the provider performed the
+    // check but we failed to record it, and the provider was not paid for it.
     match fetch_utxo_alerts(kyt_principal, caller, utxo)
         .await
         .map_err(|call_err| {
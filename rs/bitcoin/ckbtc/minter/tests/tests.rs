@@ -1532,6 +1532,64 @@ fn test_ledger_memo() {
     assert_eq!(decoded_data, MintMemo::Kyt);
 }
 
+#[test]
+fn test_repeated_withdrawals_are_charged_kyt_fee() {
+    let ckbtc = CkBtcSetup::new();
+
+    let deposit_value = 100_000_000;
+    let utxo = Utxo {
+        height: 0,
+        outpoint: OutPoint {
+            txid: range_to_txid(1..=32),
+            vout: 1,
+        },
+        value: deposit_value,
+    };
+
+    let user = Principal::from(ckbtc.caller);
+    ckbtc.deposit_utxo(user, utxo);
+    assert_eq!(ckbtc.balance_of(user), Nat::from(deposit_value - KYT_FEE));
+
+    let withdrawal_amount = 30_000_000;
+    let withdrawal_account = ckbtc.withdrawal_account(user.into());
+    ckbtc.transfer(user, withdrawal_account, 2 * withdrawal_amount);
+
+    ckbtc
+        .retrieve_btc(WITHDRAWAL_ADDRESS.to_string(), withdrawal_amount)
+        .expect("retrieve_btc failed");
+    // The KYT canister registers every withdrawal attempt with the provider,
+    // so the minter charges the KYT fee for the identical second withdrawal.
+    let RetrieveBtcOk { block_index } = ckbtc
+        .retrieve_btc(WITHDRAWAL_ADDRESS.to_string(), withdrawal_amount)
+        .expect("retrieve_btc failed");
+
+    let res = ckbtc.get_transactions(GetTransactionsRequest {
+        start: block_index.into(),
+        length: 1.into(),
+    });
+    let memo = res.transactions[0].burn.clone().unwrap().memo.unwrap();
+    use ic_ckbtc_minter::memo::{BurnMemo, Status};
+    assert_eq!(
+        minicbor::decode::<BurnMemo>(&memo.0).expect("failed to decode memo"),
+        BurnMemo::Convert {
+            address: Some(WITHDRAWAL_ADDRESS),
+            kyt_fee: Some(KYT_FEE),
+            status: Some(Status::Accepted),
+        }
+    );
+
+    ckbtc
+        .env
+        .execute_ingress(ckbtc.minter_id, "distribute_kyt_fee", Encode!().unwrap())
+        .expect("failed to transfer funds");
+
+    // The provider gets paid for the deposit and for both withdrawals.
+    assert_eq!(
+        ckbtc.balance_of(Principal::from(ckbtc.kyt_provider)),
+        Nat::from(3 * KYT_FEE)
+    );
+}
+
 #[test]
 fn test_filter_logs() {
     let ckbtc = CkBtcSetup::new();
@@ -15,9 +15,7 @@ use ic_embedders::{
     wasm_utils::WasmImportsDetails, CompilationCache, CompilationResult, WasmExecutionInput,
 };
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
-#[cfg(target_os = "linux")]
-use ic_logger::warn;
-use ic_logger::{error, info, ReplicaLogger};
+use ic_logger::{error, info, warn, ReplicaLogger};
 use ic_metrics::buckets::decimal_buckets_with_zero;
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::canister_state::execution_state::{
@@ -27,7 +25,7 @@ use ic_replicated_state::{EmbedderCache, ExecutionState, ExportedFunctions, Memo
 use ic_types::ingress::WasmResult;
 use ic_types::methods::{FuncRef, WasmMethod};
 use ic_types::{CanisterId, NumInstructions};
-use ic_wasm_types::CanisterModule;
+use ic_wasm_types::{CanisterModule, WasmEngineError};
 #[cfg(target_os = "linux")]
 use prometheus::IntGauge;
 use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec};
@@ -66,6 +64,8 @@ const EMBEDDER_CACHE_HIT_SANDBOX_EVICTED: &str = "embedder_cache_hit_sandbox_evi
 const EMBEDDER_CACHE_HIT_COMPILATION_ERROR: &str = "embedder_cache_hit_compilation_error";
 const COMPILATION_CACHE_HIT: &str = "compilation_cache_hit";
 const COMPILATION_CACHE_HIT_COMPILATION_ERROR: &str = "compilation_cache_hit_compilation_error";
+const COMPILATION_CACHE_HIT_DESERIALIZATION_ERROR: &str =
+    "compilation_cache_hit_deserialization_error";
 const CACHE_MISS: &str = "cache_miss";
 
 struct SandboxedExecutionMetrics {
@@ -822,16 +822,90 @@ impl WasmExecutor for SandboxedExecutionController {
         // and compiles the wasm binary (or looks it up in the cache).
         // Then, through RPC, operations are sent to the sandbox, passing along
         // also serialized versions of the needed objects (e.g., the page allocator through the pagemap)
-        let wasm_id = WasmId::new();
+        let mut wasm_id = WasmId::new();
         let wasm_page_map = PageMap::new(Arc::clone(&self.fd_factory));
         let next_wasm_memory_id = MemoryId::new();
 
         let stable_memory_page_map = PageMap::new(Arc::clone(&self.fd_factory));
 
+        // A module persisted by the on-disk tier of the compilation cache may
+        // not be accepted by the sandbox, e.g. if it was written by a replica
+        // with a different Wasmtime. Such a module is dropped from the cache
+        // and the Wasm is compiled again.
+        let cached = match compilation_cache.get(&wasm_binary.binary) {
+            None => {
+                self.metrics.inc_cache_lookup(CACHE_MISS);
+                None
+            }
+            Some(Err(err)) => {
+                self.metrics
+                    .inc_cache_lookup(COMPILATION_CACHE_HIT_COMPILATION_ERROR);
+                return Err(err);
+            }
+            Some(Ok(serialized_module)) => {
+                self.metrics.inc_cache_lookup(COMPILATION_CACHE_HIT);
+                let _deserialization_timer = self
+                    .metrics
+                    .sandboxed_execution_replica_create_exe_state_wait_deserialize_duration
+                    .start_timer();
+                sandbox_process.history.record(format!(
+                    "CreateExecutionStateSerialized(wasm_id={}, next_wasm_memory_id={})",
+                    wasm_id, next_wasm_memory_id
+                ));
+                let sandbox_result = sandbox_process
+                    .sandbox_service
+                    .create_execution_state_serialized(
+                        protocol::sbxsvc::CreateExecutionStateSerializedRequest {
+                            wasm_id,
+                            serialized_module: Arc::clone(&serialized_module),
+                            wasm_page_map: wasm_page_map.serialize(),
+                            next_wasm_memory_id,
+                            canister_id,
+                            stable_memory_page_map: stable_memory_page_map.serialize(),
+                        },
+                    )
+                    .sync()
+                    .unwrap()
+                    .0;
+                match sandbox_result {
+                    Ok(sandbox_result) => {
+                        self.metrics
+                            .sandboxed_execution_sandbox_create_exe_state_deserialize_total_duration
+                            .observe(sandbox_result.total_sandbox_time.as_secs_f64());
+                        self.metrics
+                            .sandboxed_execution_sandbox_create_exe_state_deserialize_duration
+                            .observe(sandbox_result.deserialization_time.as_secs_f64());
+                        Some((
+                            sandbox_result.wasm_memory_modifications,
+                            sandbox_result.exported_globals,
+                            serialized_module,
+                            None,
+                        ))
+                    }
+                    Err(err) if is_deserialization_error(&err) => {
+                        warn!(
+                            self.logger,
+                            "Recompiling the Wasm of canister {}: {}", canister_id, err
+                        );
+                        discard_cached_module(
+                            &sandbox_process,
+                            wasm_id,
+                            &wasm_binary,
+                            &compilation_cache,
+                            &self.metrics,
+                        );
+                        wasm_id = WasmId::new();
+                        None
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        };
+
         let (memory_modifications, exported_globals, serialized_module, compilation_result) =
-            match compilation_cache.get(&wasm_binary.binary) {
+            match cached {
+                Some(cached) => cached,
                 None => {
-                    self.metrics.inc_cache_lookup(CACHE_MISS);
                     let _compilation_timer = self
                         .metrics
                         .sandboxed_execution_replica_create_exe_state_wait_compile_duration
@@ -871,49 +945,6 @@ impl WasmExecutor for SandboxedExecutionController {
                         }
                     }
                 }
-                Some(Err(err)) => {
-                    self.metrics
-                        .inc_cache_lookup(COMPILATION_CACHE_HIT_COMPILATION_ERROR);
-                    return Err(err);
-                }
-                Some(Ok(serialized_module)) => {
-                    self.metrics.inc_cache_lookup(COMPILATION_CACHE_HIT);
-                    let _deserialization_timer = self
-                        .metrics
-                        .sandboxed_execution_replica_create_exe_state_wait_deserialize_duration
-                        .start_timer();
-                    sandbox_process.history.record(format!(
-                        "CreateExecutionStateSerialized(wasm_id={}, next_wasm_memory_id={})",
-                        wasm_id, next_wasm_memory_id
-                    ));
-                    let sandbox_result = sandbox_process
-                        .sandbox_service
-                        .create_execution_state_serialized(
-                            protocol::sbxsvc::CreateExecutionStateSerializedRequest {
-                                wasm_id,
-                                serialized_module: Arc::clone(&serialized_module),
-                                wasm_page_map: wasm_page_map.serialize(),
-                                next_wasm_memory_id,
-                                canister_id,
-                                stable_memory_page_map: stable_memory_page_map.serialize(),
-                            },
-                        )
-                        .sync()
-                        .unwrap()
-                        .0?;
-                    self.metrics
-                        .sandboxed_execution_sandbox_create_exe_state_deserialize_total_duration
-                        .observe(sandbox_result.total_sandbox_time.as_secs_f64());
-                    self.metrics
-                        .sandboxed_execution_sandbox_create_exe_state_deserialize_duration
-                        .observe(sandbox_result.deserialization_time.as_secs_f64());
-                    (
-                        sandbox_result.wasm_memory_modifications,
-                        sandbox_result.exported_globals,
-                        serialized_module,
-                        None,
-                    )
-                }
             };
         let _finish_timer = self
             .metrics
@@ -1461,60 +1492,112 @@ fn open_wasm(
         }
     }
 
-    let wasm_id = WasmId::new();
     match compilation_cache.get(&wasm_binary.binary) {
-        None => {
-            metrics.inc_cache_lookup(CACHE_MISS);
+        None => metrics.inc_cache_lookup(CACHE_MISS),
+        Some(Err(err)) => {
+            metrics.inc_cache_lookup(COMPILATION_CACHE_HIT_COMPILATION_ERROR);
+            cache_errored_wasm(&mut embedder_cache, err.clone());
+            return Err(err);
+        }
+        Some(Ok(serialized_module)) => {
+            metrics.inc_cache_lookup(COMPILATION_CACHE_HIT);
+            let wasm_id = WasmId::new();
             sandbox_process
                 .history
-                .record(format!("OpenWasm(wasm_id={})", wasm_id));
+                .record(format!("OpenWasmSerialized(wasm_id={})", wasm_id));
+            // Wait for the sandbox to deserialize the module, so that a module
+            // it rejects, e.g. one persisted by a replica with a different
+            // Wasmtime, can be compiled again below.
             match sandbox_process
                 .sandbox_service
-                .open_wasm(protocol::sbxsvc::OpenWasmRequest {
+                .open_wasm_serialized(protocol::sbxsvc::OpenWasmSerializedRequest {
                     wasm_id,
-                    wasm_src: wasm_binary.binary.as_slice().to_vec(),
+                    serialized_module: Arc::clone(&serialized_module.bytes),
                 })
                 .sync()
                 .unwrap()
                 .0
             {
-                Ok((compilation_result, serialized_module)) => {
-                    cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
+                Ok(()) => {
                     observe_metrics(metrics, &serialized_module.imports_details);
-                    compilation_cache.insert(&wasm_binary.binary, Ok(Arc::new(serialized_module)));
-                    Ok((wasm_id, Some(compilation_result)))
+                    cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
+                    return Ok((wasm_id, None));
+                }
+                Err(err) if is_deserialization_error(&err) => {
+                    discard_cached_module(
+                        sandbox_process,
+                        wasm_id,
+                        wasm_binary,
+                        &compilation_cache,
+                        metrics,
+                    );
                 }
                 Err(err) => {
-                    compilation_cache.insert(&wasm_binary.binary, Err(err.clone()));
                     cache_errored_wasm(&mut embedder_cache, err.clone());
-                    Err(err)
+                    return Err(err);
                 }
             }
         }
-        Some(Err(err)) => {
-            metrics.inc_cache_lookup(COMPILATION_CACHE_HIT_COMPILATION_ERROR);
+    }
+
+    let wasm_id = WasmId::new();
+    sandbox_process
+        .history
+        .record(format!("OpenWasm(wasm_id={})", wasm_id));
+    match sandbox_process
+        .sandbox_service
+        .open_wasm(protocol::sbxsvc::OpenWasmRequest {
+            wasm_id,
+            wasm_src: wasm_binary.binary.as_slice().to_vec(),
+        })
+        .sync()
+        .unwrap()
+        .0
+    {
+        Ok((compilation_result, serialized_module)) => {
+            cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
+            observe_metrics(metrics, &serialized_module.imports_details);
+            compilation_cache.insert(&wasm_binary.binary, Ok(Arc::new(serialized_module)));
+            Ok((wasm_id, Some(compilation_result)))
+        }
+        Err(err) => {
+            compilation_cache.insert(&wasm_binary.binary, Err(err.clone()));
             cache_errored_wasm(&mut embedder_cache, err.clone());
             Err(err)
         }
-        Some(Ok(serialized_module)) => {
-            metrics.inc_cache_lookup(COMPILATION_CACHE_HIT);
-            observe_metrics(metrics, &serialized_module.imports_details);
-            sandbox_process
-                .history
-                .record(format!("OpenWasmSerialized(wasm_id={})", wasm_id));
-            sandbox_process
-                .sandbox_service
-                .open_wasm_serialized(protocol::sbxsvc::OpenWasmSerializedRequest {
-                    wasm_id,
-                    serialized_module: Arc::clone(&serialized_module.bytes),
-                })
-                .on_completion(|_| ());
-            cache_opened_wasm(&mut embedder_cache, sandbox_process, wasm_id);
-            Ok((wasm_id, None))
-        }
     }
 }
 
+/// Returns true if the sandbox failed to deserialize a module of the
+/// compilation cache.
+fn is_deserialization_error(err: &HypervisorError) -> bool {
+    matches!(
+        err,
+        HypervisorError::WasmEngineError(WasmEngineError::FailedToDeserializeModule(_))
+    )
+}
+
+/// Closes the wasm that the sandbox failed to open from a module of the
+/// compilation cache and removes the module from the cache, so that the wasm
+/// is compiled again.
+fn discard_cached_module(
+    sandbox_process: &Arc<SandboxProcess>,
+    wasm_id: WasmId,
+    wasm_binary: &WasmBinary,
+    compilation_cache: &CompilationCache,
+    metrics: &SandboxedExecutionMetrics,
+) {
+    metrics.inc_cache_lookup(COMPILATION_CACHE_HIT_DESERIALIZATION_ERROR);
+    sandbox_process
+        .history
+        .record(format!("CloseWasm(wasm_id={})", wasm_id));
+    sandbox_process
+        .sandbox_service
+        .close_wasm(protocol::sbxsvc::CloseWasmRequest { wasm_id })
+        .on_completion(|_| {});
+    compilation_cache.remove(&wasm_binary.binary);
+}
+
 // Returns the id of the remote memory after making sure that the remote memory
 // is in sync with the local memory.
 fn open_remote_memory(
@@ -1735,7 +1818,9 @@ mod tests {
     use ic_config::{
         execution_environment::MAX_COMPILATION_CACHE_SIZE, logger::Config as LoggerConfig,
     };
+    use ic_embedders::{SerializedModule, SerializedModuleBytes};
     use ic_logger::{new_replica_logger, replica_logger::no_op_logger};
+    use ic_replicated_state::canister_state::execution_state::WasmMetadata;
     use ic_test_utilities::types::ids::canister_test_id;
     use libc::kill;
     use slog::{o, Drain};
@@ -1825,4 +1910,50 @@ mod tests {
             canister_id, sandbox_pid
         )));
     }
+
+    #[test]
+    fn cached_module_rejected_by_sandbox_is_recompiled() {
+        use ic_replicated_state::page_map::TestPageAllocatorFileDescriptorImpl;
+        let controller = SandboxedExecutionController::new(
+            no_op_logger(),
+            &MetricsRegistry::new(),
+            &EmbeddersConfig::default(),
+            Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+        )
+        .unwrap();
+
+        let wat = "(module)";
+        let canister_module = CanisterModule::new(wat::parse_str(wat).unwrap());
+        let compilation_cache = Arc::new(CompilationCache::new(MAX_COMPILATION_CACHE_SIZE));
+        // Bytes that the sandbox fails to deserialize, as if the module had
+        // been persisted by a replica with a different Wasmtime.
+        compilation_cache.insert(
+            &canister_module,
+            Ok(Arc::new(SerializedModule {
+                bytes: Arc::new(SerializedModuleBytes::empty()),
+                exported_functions: Default::default(),
+                data_segments: Default::default(),
+                wasm_metadata: WasmMetadata::default(),
+                compilation_cost: NumInstructions::new(0),
+                imports_details: Default::default(),
+            })),
+        );
+
+        let (_, _, compilation_result) = controller
+            .create_execution_state(
+                canister_module.clone(),
+                PathBuf::new(),
+                canister_test_id(0),
+                Arc::clone(&compilation_cache),
+            )
+            .unwrap();
+        assert!(compilation_result.is_some());
+        assert!(!compilation_cache
+            .get(&canister_module)
+            .unwrap()
+            .unwrap()
+            .bytes
+            .as_slice()
+            .is_empty());
+    }
 }
@@ -125,7 +125,13 @@ pub const SAMPLE_CONFIG: &str = r#"
         // - EXAMPLE: csp_vault_type: { unix_socket: { logic: "/some/path/to/socket", metrics: "/some/path/to/another_socket" } },
         //   CspVault is run as a separate process, which can be reached via a Unix socket.
         //   It also has an optional Unix socket for exporting metrics.
+        // - EXAMPLE: csp_vault_type: { tls: { address: "10.0.0.1:4444", server_name: "csp-vault", ca_cert: "/some/path/to/ca.der", cert: "/some/path/to/client.der", key: "/some/path/to/client_key.der" } },
+        //   CspVault is run on another host, which can be reached via TCP with mutually
+        //   authenticated TLS.
         csp_vault_type: { unix_socket: { logic: "/some/path/to/socket", metrics: "/some/path/to/another_socket" } },
+        // The directory holding the key-encryption keys of the secret key stores.
+        // If not set, the secret key stores are not encrypted at rest.
+        // - EXAMPLE: secret_key_store_encryption_key_dir: "/some/path/to/key_encryption_keys",
     },
     // ========================================
     // Configuration of the message scheduling.
@@ -6,6 +6,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::net::SocketAddr;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
@@ -39,6 +40,25 @@ pub enum CspVaultType {
         logic: PathBuf,
         metrics: Option<PathBuf>,
     },
+    /// The vault runs on another host and is reached over TCP with mutually
+    /// authenticated TLS. The server must present a certificate for
+    /// `server_name` issued by the CA with certificate `ca_cert`, and the
+    /// client authenticates with `cert` and `key`. All files are DER-encoded,
+    /// and `key` is a PKCS#8 private key.
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "(any::<std::net::SocketAddrV4>(), any::<String>(), any::<String>(), any::<String>(), any::<String>())\
+              .prop_map(|(address, server_name, ca_cert, cert, key)| CspVaultType::Tls{address: SocketAddr::V4(address), server_name, ca_cert: PathBuf::from(ca_cert), cert: PathBuf::from(cert), key: PathBuf::from(key)})"
+        )
+    )]
+    Tls {
+        address: SocketAddr,
+        server_name: String,
+        ca_cert: PathBuf,
+        cert: PathBuf,
+        key: PathBuf,
+    },
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -60,6 +80,17 @@ pub struct CryptoConfig {
     )]
     pub crypto_root: PathBuf,
     pub csp_vault_type: CspVaultType,
+    /// Directory holding the key-encryption keys with which the secret key
+    /// stores are encrypted at rest. If `None`, the secret key stores are not
+    /// encrypted. The stores must be migrated with `ic-crypto-sks-encryption`
+    /// before this is set.
+    #[cfg_attr(
+        test,
+        proptest(
+            strategy = "proptest::option::of(any::<String>().prop_map(|x| PathBuf::from(x)))"
+        )
+    )]
+    pub secret_key_store_encryption_key_dir: Option<PathBuf>,
 }
 
 impl Default for CryptoConfig {
@@ -67,6 +98,7 @@ impl Default for CryptoConfig {
         Self {
             crypto_root: PathBuf::from(CRYPTO_ROOT_DEFAULT_PATH),
             csp_vault_type: CspVaultType::InReplica,
+            secret_key_store_encryption_key_dir: None,
         }
     }
 }
@@ -77,6 +109,7 @@ impl CryptoConfig {
         Self {
             crypto_root,
             csp_vault_type: CspVaultType::InReplica,
+            secret_key_store_encryption_key_dir: None,
         }
     }
 
@@ -94,6 +127,7 @@ impl CryptoConfig {
                 logic: logic_socket_path,
                 metrics: metrics_socket_path,
             },
+            secret_key_store_encryption_key_dir: None,
         }
     }
 
@@ -201,6 +235,20 @@ mod tests {
         CryptoConfig::run_with_temp_config(|config| serde_test(config));
     }
 
+    #[test]
+    fn tls_vault_config_serializes_and_deserializes() {
+        CryptoConfig::run_with_temp_config(|mut config| {
+            config.csp_vault_type = CspVaultType::Tls {
+                address: "10.0.0.1:4444".parse().unwrap(),
+                server_name: "csp-vault".to_string(),
+                ca_cert: PathBuf::from("/some/path/to/ca.der"),
+                cert: PathBuf::from("/some/path/to/client.der"),
+                key: PathBuf::from("/some/path/to/client_key.der"),
+            };
+            serde_test(config)
+        });
+    }
+
     proptest! {
         #[allow(dead_code)]
         // #[test]
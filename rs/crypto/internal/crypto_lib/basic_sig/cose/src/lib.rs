@@ -1,5 +1,6 @@
 use ic_crypto_internal_basic_sig_der_utils::PkixAlgorithmIdentifier;
 use ic_crypto_internal_basic_sig_ecdsa_secp256r1::der_encoding_from_xy_coordinates as p256_from_coordinates;
+use ic_crypto_internal_basic_sig_ed25519 as ed25519;
 use ic_crypto_internal_basic_sig_rsa_pkcs1::RsaPublicKey;
 use ic_types::crypto::{AlgorithmId, CryptoError, CryptoResult};
 use simple_asn1::oid;
@@ -19,6 +20,7 @@ type CborMap = std::collections::BTreeMap<serde_cbor::Value, serde_cbor::Value>;
 enum CosePublicKey {
     EcdsaP256Sha256(Vec<u8>),
     RsaPkcs1v15Sha256(Vec<u8>),
+    Ed25519(Vec<u8>),
 }
 
 // see https://tools.ietf.org/html/rfc8152 section 8.1
@@ -45,6 +47,15 @@ const COSE_KTY_RSA: serde_cbor::Value = serde_cbor::Value::Integer(3);
 const COSE_PARAM_RSA_N: serde_cbor::Value = serde_cbor::Value::Integer(-1);
 const COSE_PARAM_RSA_E: serde_cbor::Value = serde_cbor::Value::Integer(-2);
 
+// https://datatracker.ietf.org/doc/html/rfc8152#section-13.2
+const COSE_KTY_OKP: serde_cbor::Value = serde_cbor::Value::Integer(1);
+const COSE_PARAM_OKP_CRV: serde_cbor::Value = serde_cbor::Value::Integer(-1);
+const COSE_PARAM_OKP_X: serde_cbor::Value = serde_cbor::Value::Integer(-2);
+
+// https://datatracker.ietf.org/doc/html/rfc8152#section-8.2
+const COSE_ALG_EDDSA: serde_cbor::Value = serde_cbor::Value::Integer(-8);
+const COSE_OKP_CRV_ED25519: serde_cbor::Value = serde_cbor::Value::Integer(6);
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 /// An error that occurred while parsing the COSE key
 enum CosePublicKeyParseError {
@@ -86,6 +97,8 @@ impl CosePublicKey {
                 Self::parse_ecdsa_p256(&fields)
             } else if *kty == COSE_KTY_RSA && *alg == COSE_ALG_RS256 {
                 Self::parse_rsa_pkcs1_sha256(&fields)
+            } else if *kty == COSE_KTY_OKP && *alg == COSE_ALG_EDDSA {
+                Self::parse_ed25519(&fields)
             } else {
                 // Some other algorithm
                 Err(CosePublicKeyParseError::AlgorithmNotSupported)
@@ -186,11 +199,48 @@ impl CosePublicKey {
         }
     }
 
+    /// Parse a COSE EdDSA key
+    fn parse_ed25519(fields: &CborMap) -> Result<Self, CosePublicKeyParseError> {
+        Self::verify_key_ops(fields)?;
+
+        let crv =
+            fields
+                .get(&COSE_PARAM_OKP_CRV)
+                .ok_or(CosePublicKeyParseError::MalformedPublicKey(
+                    AlgorithmId::Ed25519,
+                ))?;
+
+        if *crv != COSE_OKP_CRV_ED25519 {
+            // Some EdDSA we don't support, e.g. Ed448
+            return Err(CosePublicKeyParseError::AlgorithmNotSupported);
+        }
+
+        let x =
+            fields
+                .get(&COSE_PARAM_OKP_X)
+                .ok_or(CosePublicKeyParseError::MalformedPublicKey(
+                    AlgorithmId::Ed25519,
+                ))?;
+
+        match x {
+            serde_cbor::Value::Bytes(x) => {
+                let key = ed25519::types::PublicKeyBytes::try_from(x.clone()).map_err(|_| {
+                    CosePublicKeyParseError::MalformedPublicKey(AlgorithmId::Ed25519)
+                })?;
+                Ok(Self::Ed25519(ed25519::public_key_to_der(key)))
+            }
+            _ => Err(CosePublicKeyParseError::MalformedPublicKey(
+                AlgorithmId::Ed25519,
+            )),
+        }
+    }
+
     /// Return the algorithm ID associated with this public key
     fn algorithm_id(&self) -> AlgorithmId {
         match self {
             Self::EcdsaP256Sha256(_) => AlgorithmId::EcdsaP256,
             Self::RsaPkcs1v15Sha256(_) => AlgorithmId::RsaSha256,
+            Self::Ed25519(_) => AlgorithmId::Ed25519,
         }
     }
 
@@ -199,6 +249,7 @@ impl CosePublicKey {
         match self {
             Self::EcdsaP256Sha256(der) => der.to_vec(),
             Self::RsaPkcs1v15Sha256(der) => der.to_vec(),
+            Self::Ed25519(der) => der.to_vec(),
         }
     }
 }
@@ -66,6 +66,62 @@ fn should_correctly_parse_webauthn_cose_encoded_pk() {
     let _pk = parse_cose_public_key(&pk_cose).unwrap();
 }
 
+#[test]
+fn should_correctly_parse_cose_encoded_ed25519_pk() {
+    // The public key of test 1 in RFC 8032, section 7.1
+    let ed25519_cose = hex::decode(
+        "a4010103272006215820d75a980182b10ab7d54bfef3c964073a0ee172f3daa62325af021a68f707511a",
+    )
+    .unwrap();
+
+    let (algorithm_id, pk_der) = parse_cose_public_key(&ed25519_cose).unwrap();
+
+    assert_eq!(algorithm_id, AlgorithmId::Ed25519);
+    assert_eq!(
+        hex::encode(pk_der),
+        "302a300506032b6570032100d75a980182b10ab7d54bfef3c964073a0ee172f3daa62325af021a68f707511a"
+    );
+}
+
+#[test]
+fn should_reject_cose_encoded_eddsa_pk_with_unsupported_curve() {
+    // Same as above, but with crv = 7 (Ed448)
+    let ed448_cose = hex::decode(
+        "a4010103272007215820d75a980182b10ab7d54bfef3c964073a0ee172f3daa62325af021a68f707511a",
+    )
+    .unwrap();
+
+    let result = parse_cose_public_key(&ed448_cose);
+
+    assert_eq!(
+        result,
+        Err(CryptoError::AlgorithmNotSupported {
+            algorithm: AlgorithmId::Placeholder,
+            reason: "Algorithm not supported in COSE parser".to_string()
+        })
+    );
+}
+
+#[test]
+fn should_reject_cose_encoded_ed25519_pk_of_wrong_length() {
+    // The key bytes are truncated to 31 bytes
+    let bad_ed25519_cose = hex::decode(
+        "a401010327200621581fd75a980182b10ab7d54bfef3c964073a0ee172f3daa62325af021a68f707511",
+    )
+    .unwrap();
+
+    let result = parse_cose_public_key(&bad_ed25519_cose);
+
+    assert_eq!(
+        result,
+        Err(CryptoError::MalformedPublicKey {
+            algorithm: AlgorithmId::Ed25519,
+            key_bytes: Some(bad_ed25519_cose),
+            internal_error: "Failed to parse COSE public key".to_string()
+        })
+    );
+}
+
 #[test]
 fn should_fail_parsing_a_corrupted_cose_encoded_pk() {
     let mut pk_cose = hex::decode(ECDSA_P256_PK_2_COSE_HEX).unwrap();
@@ -20,4 +20,23 @@ pub struct SecretKeyStore {
     #[prost(map = "string, message", tag = "3")]
     pub key_id_to_secret_key_v1:
         ::std::collections::HashMap<::prost::alloc::string::String, SecretKeyV1>,
+    /// If set, the secret keys are not stored in `key_id_to_secret_key_v1` but
+    /// in encrypted form.
+    #[prost(message, optional, tag = "4")]
+    pub encrypted_secret_key_store: ::core::option::Option<EncryptedSecretKeyStore>,
+}
+/// A `SecretKeyStore` encrypted with a key-encryption key.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EncryptedSecretKeyStore {
+    /// Identifier of the key-encryption key, as given by the key provider.
+    #[prost(string, tag = "1")]
+    pub key_encryption_key_id: ::prost::alloc::string::String,
+    /// Nonce used for the encryption with ChaCha20-Poly1305.
+    #[prost(bytes = "vec", tag = "2")]
+    pub nonce: ::prost::alloc::vec::Vec<u8>,
+    /// ChaCha20-Poly1305 encryption of a serialized `SecretKeyStore` in which
+    /// `encrypted_secret_key_store` is not set.
+    #[prost(bytes = "vec", tag = "3")]
+    pub ciphertext: ::prost::alloc::vec::Vec<u8>,
 }
@@ -22,8 +22,8 @@ pub mod vault;
 
 pub use crate::vault::api::TlsHandshakeCspVault;
 pub use crate::vault::local_csp_vault::LocalCspVault;
-pub use crate::vault::remote_csp_vault::run_csp_vault_server;
-use crate::vault::remote_csp_vault::RemoteCspVault;
+use crate::vault::remote_csp_vault::{remote_vault_tls_client_config, RemoteCspVault};
+pub use crate::vault::remote_csp_vault::{run_csp_vault_server, run_csp_vault_server_with_tls};
 
 use crate::api::{
     CspIDkgProtocol, CspKeyGenerator, CspPublicAndSecretKeyStoreChecker, CspPublicKeyStore,
@@ -43,9 +43,11 @@ use ic_logger::{info, new_logger, replica_logger::no_op_logger, ReplicaLogger};
 use ic_types::crypto::CurrentNodePublicKeys;
 use key_id::KeyId;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
+use tokio_rustls::rustls::ClientConfig;
 
 #[cfg(test)]
 mod tests;
@@ -152,13 +154,14 @@ impl<T> CspRwLock<T> {
 impl Csp {
     /// Creates a production-grade crypto service provider.
     ///
-    /// If the `config`'s vault type is `UnixSocket`, a `tokio_runtime_handle`
-    /// must be provided, which is then used for the `async`hronous
-    /// communication with the vault via RPC.
+    /// If the `config`'s vault type is `UnixSocket` or `Tls`, a
+    /// `tokio_runtime_handle` must be provided, which is then used for the
+    /// `async`hronous communication with the vault via RPC.
     ///
     /// # Panics
-    /// Panics if the `config`'s vault type is `UnixSocket` and
-    /// `tokio_runtime_handle` is `None`.
+    /// Panics if the `config`'s vault type is `UnixSocket` or `Tls` and
+    /// `tokio_runtime_handle` is `None`, or if the TLS certificates and key
+    /// of a `Tls` vault cannot be read.
     pub fn new(
         config: &CryptoConfig,
         tokio_runtime_handle: Option<tokio::runtime::Handle>,
@@ -178,6 +181,30 @@ impl Csp {
                 logger,
                 metrics,
             ),
+            CspVaultType::Tls {
+                address,
+                server_name,
+                ca_cert,
+                cert,
+                key,
+            } => {
+                let read = |path: &PathBuf| {
+                    std::fs::read(path)
+                        .unwrap_or_else(|e| panic!("Could not read {:?}: {}", path, e))
+                };
+                let tls_config =
+                    remote_vault_tls_client_config(&read(ca_cert), read(cert), read(key))
+                        .unwrap_or_else(|e| panic!("Invalid TLS configuration of CspVault: {}", e));
+                Self::new_with_tls_vault(
+                    *address,
+                    server_name.clone(),
+                    tls_config,
+                    tokio_runtime_handle.expect("missing tokio runtime handle"),
+                    config,
+                    logger,
+                    metrics,
+                )
+            }
         }
     }
 
@@ -192,7 +219,7 @@ impl Csp {
             "Proceeding with an in-replica csp_vault, CryptoConfig: {:?}", config
         );
         let csp_vault =
-            LocalCspVault::new_in_dir(&config.crypto_root, metrics.clone(), new_logger!(&logger));
+            LocalCspVault::new_from_config(config, metrics.clone(), new_logger!(&logger));
         Csp::builder(csp_vault, logger, metrics).build()
     }
 
@@ -233,6 +260,34 @@ impl Csp {
         });
         Csp::builder(csp_vault, logger, metrics).build()
     }
+
+    fn new_with_tls_vault(
+        server_address: SocketAddr,
+        server_name: String,
+        tls_config: Arc<ClientConfig>,
+        rt_handle: tokio::runtime::Handle,
+        config: &CryptoConfig,
+        logger: Option<ReplicaLogger>,
+        metrics: Arc<CryptoMetrics>,
+    ) -> Self {
+        let logger = logger.unwrap_or_else(no_op_logger);
+        info!(
+            logger,
+            "Proceeding with a remote csp_vault over TLS, CryptoConfig: {:?}", config
+        );
+        let csp_vault =
+            RemoteCspVault::builder_with_tls(server_address, server_name, tls_config, rt_handle)
+                .with_logger(new_logger!(&logger))
+                .with_metrics(metrics.clone())
+                .build()
+                .unwrap_or_else(|e| {
+                    panic!(
+                        "Could not connect to CspVault at {}: {:?}",
+                        server_address, e
+                    )
+                });
+        Csp::builder(csp_vault, logger, metrics).build()
+    }
 }
 
 impl CspPublicKeyStore for Csp {
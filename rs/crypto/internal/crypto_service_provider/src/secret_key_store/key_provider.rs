@@ -0,0 +1,156 @@
+//! Providers of key-encryption keys for secret key stores encrypted at rest
+use ic_config::crypto::CryptoConfig;
+use ic_crypto_secrets_containers::SecretArray;
+use std::fmt;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Size in bytes of a key-encryption key.
+pub const KEY_ENCRYPTION_KEY_SIZE: usize = 32;
+
+/// A key used to encrypt a secret key store.
+pub type KeyEncryptionKey = SecretArray<KEY_ENCRYPTION_KEY_SIZE>;
+
+/// A source of key-encryption keys, e.g. a file or a hardware security module.
+///
+/// Each key is identified by a string. A secret key store is always encrypted
+/// with the provider's current key and records the identifier of that key, so
+/// that it can still be decrypted after the current key was rotated, as long
+/// as the provider still knows the previous key.
+pub trait KeyProvider: Send + Sync {
+    /// Returns the identifier of the key that should be used for encryption.
+    fn current_key_id(&self) -> Result<String, KeyProviderError>;
+
+    /// Returns the key with the given identifier.
+    fn key(&self, key_id: &str) -> Result<KeyEncryptionKey, KeyProviderError>;
+}
+
+/// Errors that can occur while retrieving a key-encryption key
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyProviderError {
+    KeyNotFound(String),
+    InvalidKeyId(String),
+    InternalError(String),
+}
+
+impl std::error::Error for KeyProviderError {}
+
+impl fmt::Display for KeyProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyProviderError::KeyNotFound(key_id) => {
+                write!(f, "Key-encryption key with ID {} not found", key_id)
+            }
+            KeyProviderError::InvalidKeyId(key_id) => {
+                write!(f, "Invalid key-encryption key ID {}", key_id)
+            }
+            KeyProviderError::InternalError(e) => {
+                write!(f, "Internal error retrieving key-encryption key: {}", e)
+            }
+        }
+    }
+}
+
+/// Returns the provider of the key-encryption keys with which the secret key
+/// stores are encrypted according to `config`, or `None` if they are not
+/// encrypted.
+pub fn key_provider_from_config(config: &CryptoConfig) -> Option<Arc<dyn KeyProvider>> {
+    config
+        .secret_key_store_encryption_key_dir
+        .as_ref()
+        .map(|dir| Arc::new(FileKeyProvider::new(dir)) as Arc<dyn KeyProvider>)
+}
+
+/// A key provider that stores keys as files in a directory.
+///
+/// The key with identifier `id` is stored in the file `<id>.kek`, and the
+/// identifier of the current key in the file `current`. Since the keys are
+/// stored in the clear, the directory must be protected separately, e.g. by
+/// being on a volume that is encrypted or only mounted while the node runs.
+pub struct FileKeyProvider {
+    dir: PathBuf,
+}
+
+impl FileKeyProvider {
+    const CURRENT_KEY_ID_FILE_NAME: &'static str = "current";
+
+    /// Creates a provider for the keys stored in `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Stores `key` under the identifier `key_id`.
+    pub fn add_key(
+        &self,
+        key_id: &str,
+        key: &[u8; KEY_ENCRYPTION_KEY_SIZE],
+    ) -> Result<(), KeyProviderError> {
+        let key_file = self.key_file(key_id)?;
+        ic_utils::fs::write_atomically(&key_file, |f| f.write_all(key))
+            .map_err(|e| KeyProviderError::InternalError(e.to_string()))
+    }
+
+    /// Removes the key with identifier `key_id`, e.g. once no secret key store
+    /// is encrypted with it anymore.
+    pub fn remove_key(&self, key_id: &str) -> Result<(), KeyProviderError> {
+        let key_file = self.key_file(key_id)?;
+        ic_utils::fs::remove_file(key_file).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => KeyProviderError::KeyNotFound(key_id.to_string()),
+            _ => KeyProviderError::InternalError(e.to_string()),
+        })
+    }
+
+    /// Makes the key with identifier `key_id` the one used for encryption.
+    pub fn set_current_key_id(&self, key_id: &str) -> Result<(), KeyProviderError> {
+        self.key_file(key_id)?;
+        ic_utils::fs::write_atomically(self.dir.join(Self::CURRENT_KEY_ID_FILE_NAME), |f| {
+            f.write_all(key_id.as_bytes())
+        })
+        .map_err(|e| KeyProviderError::InternalError(e.to_string()))
+    }
+
+    fn key_file(&self, key_id: &str) -> Result<PathBuf, KeyProviderError> {
+        let is_valid = !key_id.is_empty()
+            && key_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_valid {
+            return Err(KeyProviderError::InvalidKeyId(key_id.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.kek", key_id)))
+    }
+}
+
+impl KeyProvider for FileKeyProvider {
+    fn current_key_id(&self) -> Result<String, KeyProviderError> {
+        match fs::read_to_string(self.dir.join(Self::CURRENT_KEY_ID_FILE_NAME)) {
+            Ok(key_id) => Ok(key_id.trim().to_string()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(KeyProviderError::InternalError(
+                "no current key-encryption key is set".to_string(),
+            )),
+            Err(e) => Err(KeyProviderError::InternalError(e.to_string())),
+        }
+    }
+
+    fn key(&self, key_id: &str) -> Result<KeyEncryptionKey, KeyProviderError> {
+        let mut bytes = match fs::read(self.key_file(key_id)?) {
+            Ok(bytes) => zeroize::Zeroizing::new(bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(KeyProviderError::KeyNotFound(key_id.to_string()))
+            }
+            Err(e) => return Err(KeyProviderError::InternalError(e.to_string())),
+        };
+        let key: &mut [u8; KEY_ENCRYPTION_KEY_SIZE] =
+            bytes.as_mut_slice().try_into().map_err(|_| {
+                KeyProviderError::InternalError(format!(
+                    "key-encryption key with ID {} has wrong length",
+                    key_id
+                ))
+            })?;
+        Ok(KeyEncryptionKey::new_and_zeroize_argument(key))
+    }
+}
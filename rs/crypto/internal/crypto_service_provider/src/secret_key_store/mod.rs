@@ -7,6 +7,7 @@ pub use scope::Scope;
 use std::fmt;
 
 // Implementations
+pub mod key_provider;
 pub mod proto_store;
 
 #[cfg(test)]
@@ -2,10 +2,13 @@
 #![allow(clippy::unwrap_used)]
 use crate::canister_threshold::IDKG_MEGA_SCOPE;
 use crate::key_id::KeyId;
+use crate::secret_key_store::key_provider::KeyProvider;
 use crate::secret_key_store::{
     Scope, SecretKeyStore, SecretKeyStoreInsertionError, SecretKeyStoreWriteError,
 };
 use crate::types::CspSecretKey;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
 use hex::{FromHex, ToHex};
 use ic_config::crypto::CryptoConfig;
 use ic_crypto_internal_threshold_sig_bls12381::ni_dkg::groth20_bls12_381::types::convert_keyset_to_keyset_with_pop;
@@ -13,6 +16,8 @@ use ic_crypto_internal_threshold_sig_bls12381::ni_dkg::types::CspFsEncryptionKey
 use ic_logger::{debug, info, replica_logger::no_op_logger, warn, ReplicaLogger};
 use parking_lot::RwLock;
 use prost::Message;
+use rand::rngs::OsRng;
+use rand::RngCore;
 use std::borrow::BorrowMut;
 use std::collections::HashMap;
 use std::fs;
@@ -20,11 +25,18 @@ use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use zeroize::Zeroizing;
 
 #[cfg(test)]
 mod tests;
 
 const CURRENT_SKS_VERSION: u32 = 3;
+/// The version of a secret key store whose keys are encrypted at rest. The
+/// encrypted store itself is of version [`CURRENT_SKS_VERSION`]. Binaries that
+/// predate encryption do not support this version, so they fail loudly when
+/// reading an encrypted store instead of reading it as a store without keys.
+const ENCRYPTED_SKS_VERSION: u32 = 4;
+const ENCRYPTION_NONCE_SIZE: usize = 12;
 
 fn key_id_from_hex(key_id_hex: &str) -> KeyId {
     KeyId::from_hex(key_id_hex).unwrap_or_else(|_| panic!("Error parsing hex KeyId {}", key_id_hex))
@@ -40,10 +52,14 @@ type SecretKeys = HashMap<KeyId, (CspSecretKey, Option<Scope>)>;
 
 /// A secret key store that persists data to the filesystem, using protobufs for
 /// serialization
+///
+/// If the store is opened with a [`KeyProvider`], the data is encrypted at rest
+/// with the provider's current key-encryption key.
 pub struct ProtoSecretKeyStore {
     proto_file: PathBuf,
     old_proto_file_to_zeroize: PathBuf,
     keys: Arc<RwLock<SecretKeys>>,
+    key_provider: Option<Arc<dyn KeyProvider>>,
     logger: ReplicaLogger,
 }
 
@@ -61,6 +77,68 @@ impl ProtoSecretKeyStore {
     ///  - If the crypto root directory does not have the required permissions
     ///  - If the secret key store file is not a POSIX regular file
     pub fn open(dir: &Path, file_name: &str, logger: Option<ReplicaLogger>) -> Self {
+        Self::open_internal(dir, file_name, logger, None, false)
+    }
+
+    /// Creates a `ProtoSecretKeyStore` instance backed by a file that is encrypted with the
+    /// key-encryption keys of `key_provider`. See [`ProtoSecretKeyStore::open`] for the
+    /// requirements on `dir` and `file_name`.
+    ///
+    /// # Panics
+    ///  - In the cases listed for [`ProtoSecretKeyStore::open`]
+    ///  - If the secret key store exists but is not encrypted, in which case it must first be
+    ///    migrated with [`ProtoSecretKeyStore::migrate_to_encrypted`]
+    ///  - If the secret key store cannot be decrypted with the keys of `key_provider`
+    pub fn open_encrypted(
+        dir: &Path,
+        file_name: &str,
+        logger: Option<ReplicaLogger>,
+        key_provider: Arc<dyn KeyProvider>,
+    ) -> Self {
+        Self::open_internal(dir, file_name, logger, Some(key_provider), false)
+    }
+
+    /// Encrypts an existing secret key store with the current key-encryption key of
+    /// `key_provider`, so that it can afterwards be opened with
+    /// [`ProtoSecretKeyStore::open_encrypted`]. The unencrypted secret key store file is
+    /// atomically replaced and then zeroized. Calling this on a store that is already
+    /// encrypted re-encrypts it with the current key-encryption key.
+    ///
+    /// # Panics
+    ///  - In the cases listed for [`ProtoSecretKeyStore::open`]
+    ///  - If the secret key store is encrypted and cannot be decrypted with the keys of
+    ///    `key_provider`
+    pub fn migrate_to_encrypted(
+        dir: &Path,
+        file_name: &str,
+        logger: Option<ReplicaLogger>,
+        key_provider: Arc<dyn KeyProvider>,
+    ) -> Result<(), SecretKeyStoreWriteError> {
+        let sks = Self::open_internal(dir, file_name, logger, Some(key_provider), true);
+        sks.rewrite_secret_keys_to_disk()
+    }
+
+    /// Re-encrypts the secret key store with the current key-encryption key of the store's
+    /// key provider, e.g. after the key was rotated. The secret key store file is replaced
+    /// atomically, so the store can at all times be decrypted with either the previous or the
+    /// current key-encryption key.
+    ///
+    /// # Panics
+    ///  - If the store was not opened with a key provider
+    pub fn reencrypt_with_current_key(&self) -> Result<(), SecretKeyStoreWriteError> {
+        if self.key_provider.is_none() {
+            panic!("secret key store is not encrypted");
+        }
+        self.rewrite_secret_keys_to_disk()
+    }
+
+    fn open_internal(
+        dir: &Path,
+        file_name: &str,
+        logger: Option<ReplicaLogger>,
+        key_provider: Option<Arc<dyn KeyProvider>>,
+        allow_unencrypted: bool,
+    ) -> Self {
         CryptoConfig::check_dir_has_required_permissions(dir)
             .expect("wrong crypto root permissions");
         let proto_file = dir.join(file_name);
@@ -75,21 +153,61 @@ impl ProtoSecretKeyStore {
             }
         }
         let old_proto_file_to_zeroize = dir.join(format!("{}.old", file_name));
-        let secret_keys = match Self::read_sks_data_from_disk(&proto_file) {
-            Some(sks_proto) => sks_proto,
-            None => SecretKeys::new(),
+        let (secret_keys, key_encryption_key_id) = match Self::read_sks_data_from_disk(
+            &proto_file,
+            key_provider.as_deref(),
+            allow_unencrypted,
+        ) {
+            Some(sks_data) => sks_data,
+            None => (SecretKeys::new(), None),
         };
         let logger = logger.unwrap_or_else(no_op_logger);
         let sks = ProtoSecretKeyStore {
             proto_file,
             old_proto_file_to_zeroize,
             keys: Arc::new(RwLock::new(secret_keys)),
+            key_provider,
             logger,
         };
         sks.clean_up_old_sks();
+        if let Some(key_encryption_key_id) = key_encryption_key_id {
+            sks.reencrypt_if_key_was_rotated(&key_encryption_key_id);
+        }
         sks
     }
 
+    /// Re-encrypts the store if it is encrypted with a key-encryption key that is no longer
+    /// the current one, so that the previous key can be retired once all stores were opened.
+    /// Failures are only logged, since the store can still be decrypted with the previous key.
+    fn reencrypt_if_key_was_rotated(&self, key_encryption_key_id: &str) {
+        let key_provider = match &self.key_provider {
+            Some(key_provider) => key_provider,
+            None => return,
+        };
+        match key_provider.current_key_id() {
+            Ok(current_key_id) if current_key_id != key_encryption_key_id => {
+                info!(
+                    self.logger,
+                    "Re-encrypting secret key store {:?} with key-encryption key {} instead of {}",
+                    self.proto_file,
+                    current_key_id,
+                    key_encryption_key_id
+                );
+                if let Err(e) = self.rewrite_secret_keys_to_disk() {
+                    warn!(
+                        self.logger,
+                        "Failed to re-encrypt secret key store {:?}: {}", self.proto_file, e
+                    );
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                self.logger,
+                "Failed to retrieve the current key-encryption key ID: {}", e
+            ),
+        }
+    }
+
     /// Returns the path to the protobuf file storing the keys.
     pub fn proto_file_path(&self) -> &Path {
         self.proto_file.as_path()
@@ -179,7 +297,10 @@ impl ProtoSecretKeyStore {
         &self,
         secret_keys: &SecretKeys,
     ) -> Result<(), SecretKeyStoreWriteError> {
-        let sks_proto = ProtoSecretKeyStore::secret_keys_to_sks_proto(secret_keys)?;
+        let mut sks_proto = ProtoSecretKeyStore::secret_keys_to_sks_proto(secret_keys)?;
+        if let Some(key_provider) = &self.key_provider {
+            sks_proto = Self::encrypt_sks_proto(&sks_proto, key_provider.as_ref())?;
+        }
         match self.proto_file.try_exists() {
             Ok(exists) => {
                 if exists {
@@ -226,14 +347,42 @@ impl ProtoSecretKeyStore {
         Ok(())
     }
 
-    fn read_sks_data_from_disk(sks_data_file: &Path) -> Option<SecretKeys> {
+    fn rewrite_secret_keys_to_disk(&self) -> Result<(), SecretKeyStoreWriteError> {
+        with_write_lock(&self.keys, |keys| self.write_secret_keys_to_disk(keys))
+    }
+
+    fn read_sks_data_from_disk(
+        sks_data_file: &Path,
+        key_provider: Option<&dyn KeyProvider>,
+        allow_unencrypted: bool,
+    ) -> Option<(SecretKeys, Option<String>)> {
         match fs::read(sks_data_file) {
             Ok(data) => {
-                let sks_pb = pb::SecretKeyStore::decode(&*data).unwrap_or_else(
+                let mut sks_pb = pb::SecretKeyStore::decode(&*data).unwrap_or_else(
                     |_ignored_so_that_no_data_is_leaked| panic!("error parsing SKS protobuf data"),
                 );
+                let mut key_encryption_key_id = None;
+                match (&sks_pb.encrypted_secret_key_store, key_provider) {
+                    (Some(encrypted_sks), Some(key_provider)) => {
+                        if sks_pb.version != ENCRYPTED_SKS_VERSION {
+                            panic!(
+                                "Unsupported encrypted SecretKeyStore-proto version: {}",
+                                sks_pb.version
+                            )
+                        }
+                        key_encryption_key_id = Some(encrypted_sks.key_encryption_key_id.clone());
+                        sks_pb = Self::decrypt_sks_proto(encrypted_sks, key_provider);
+                    }
+                    (Some(_), None) => {
+                        panic!("secret key store is encrypted, but no key provider was given")
+                    }
+                    (None, Some(_)) if !allow_unencrypted => {
+                        panic!("secret key store is not encrypted and must be migrated first")
+                    }
+                    (None, _) => {}
+                }
                 let keys = ProtoSecretKeyStore::migrate_to_current_version(sks_pb);
-                Some(keys)
+                Some((keys, key_encryption_key_id))
             }
             Err(err) => {
                 if err.kind() == ErrorKind::NotFound {
@@ -245,6 +394,83 @@ impl ProtoSecretKeyStore {
         }
     }
 
+    fn encrypt_sks_proto(
+        sks_proto: &pb::SecretKeyStore,
+        key_provider: &dyn KeyProvider,
+    ) -> Result<pb::SecretKeyStore, SecretKeyStoreWriteError> {
+        let key_encryption_key_id = key_provider.current_key_id().map_err(|e| {
+            SecretKeyStoreWriteError::TransientError(format!(
+                "Error retrieving current key-encryption key ID: {}",
+                e
+            ))
+        })?;
+        let key_encryption_key = key_provider.key(&key_encryption_key_id).map_err(|e| {
+            SecretKeyStoreWriteError::TransientError(format!(
+                "Error retrieving key-encryption key: {}",
+                e
+            ))
+        })?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key_encryption_key.expose_secret()));
+        let mut nonce = [0u8; ENCRYPTION_NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let plaintext = Zeroizing::new(sks_proto.encode_to_vec());
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: key_encryption_key_id.as_bytes(),
+                },
+            )
+            .map_err(|_ignored_so_that_no_data_is_leaked| {
+                SecretKeyStoreWriteError::SerializationError(
+                    "Error encrypting secret key store".to_string(),
+                )
+            })?;
+        Ok(pb::SecretKeyStore {
+            version: ENCRYPTED_SKS_VERSION,
+            encrypted_secret_key_store: Some(pb::EncryptedSecretKeyStore {
+                key_encryption_key_id,
+                nonce: nonce.to_vec(),
+                ciphertext,
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn decrypt_sks_proto(
+        encrypted_sks: &pb::EncryptedSecretKeyStore,
+        key_provider: &dyn KeyProvider,
+    ) -> pb::SecretKeyStore {
+        let key_encryption_key = key_provider
+            .key(&encrypted_sks.key_encryption_key_id)
+            .unwrap_or_else(|e| panic!("Error retrieving key-encryption key: {}", e));
+        if encrypted_sks.nonce.len() != ENCRYPTION_NONCE_SIZE {
+            panic!("Invalid nonce length in encrypted SKS");
+        }
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key_encryption_key.expose_secret()));
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(
+                    Nonce::from_slice(&encrypted_sks.nonce),
+                    Payload {
+                        msg: &encrypted_sks.ciphertext,
+                        aad: encrypted_sks.key_encryption_key_id.as_bytes(),
+                    },
+                )
+                .unwrap_or_else(|_| panic!("error decrypting SKS data")),
+        );
+        let sks_pb = pb::SecretKeyStore::decode(plaintext.as_slice()).unwrap_or_else(
+            |_ignored_so_that_no_data_is_leaked| {
+                panic!("error parsing decrypted SKS protobuf data")
+            },
+        );
+        if sks_pb.encrypted_secret_key_store.is_some() {
+            panic!("decrypted SKS is encrypted again");
+        }
+        sks_pb
+    }
+
     fn migrate_to_current_version(sks_proto: pb::SecretKeyStore) -> SecretKeys {
         match sks_proto.version {
             CURRENT_SKS_VERSION => ProtoSecretKeyStore::sks_proto_to_secret_keys(&sks_proto),
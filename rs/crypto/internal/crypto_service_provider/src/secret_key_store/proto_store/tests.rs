@@ -1096,6 +1096,346 @@ mod insert_or_replace {
         ]
     }
 }
+mod encryption {
+    use super::*;
+    use crate::secret_key_store::key_provider::{FileKeyProvider, KeyProvider, KeyProviderError};
+    use rand::{CryptoRng, Rng};
+
+    const SKS_FILE_NAME: &str = "sks_data.pb";
+
+    #[test]
+    fn should_retrieve_inserted_key_after_reopening_encrypted_store() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let key_id = make_key_id(rng);
+        let key = make_secret_key(rng);
+        {
+            let mut sks = ProtoSecretKeyStore::open_encrypted(
+                sks_dir.path(),
+                SKS_FILE_NAME,
+                None,
+                key_provider.clone(),
+            );
+            assert!(sks.insert(key_id, key.clone(), None).is_ok());
+        }
+
+        let sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+
+        assert_eq!(sks.get(&key_id), Some(key));
+    }
+
+    #[test]
+    fn should_not_store_keys_unencrypted() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        assert!(sks
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+
+        let sks_pb = read_sks_proto(&sks_dir);
+
+        assert!(sks_pb.key_id_to_secret_key_v1.is_empty());
+        assert_matches!(
+            sks_pb.encrypted_secret_key_store,
+            Some(encrypted) if encrypted.key_encryption_key_id == "kek-1"
+        );
+    }
+
+    #[test]
+    fn should_store_encrypted_keys_with_version_unsupported_by_older_binaries() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        assert!(sks
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+
+        let version = read_sks_proto(&sks_dir).version;
+
+        assert_eq!(version, ENCRYPTED_SKS_VERSION);
+        assert!(version > CURRENT_SKS_VERSION);
+    }
+
+    #[test]
+    fn should_panic_when_opening_encrypted_store_with_unencrypted_version() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks = ProtoSecretKeyStore::open_encrypted(
+            sks_dir.path(),
+            SKS_FILE_NAME,
+            None,
+            key_provider.clone(),
+        );
+        assert!(sks
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+        drop(sks);
+        let mut sks_pb = read_sks_proto(&sks_dir);
+        sks_pb.version = CURRENT_SKS_VERSION;
+        fs::write(sks_dir.path().join(SKS_FILE_NAME), sks_pb.encode_to_vec())
+            .expect("error writing SKS");
+
+        let panic_msg = catch_unwind(AssertUnwindSafe(|| {
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        }));
+        assert_eq!(
+            format!(
+                "Unsupported encrypted SecretKeyStore-proto version: {}",
+                CURRENT_SKS_VERSION
+            ),
+            *panic_msg.unwrap_err().downcast_ref::<String>().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_panic_when_opening_encrypted_store_without_key_provider() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        assert!(sks
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+        drop(sks);
+
+        let panic_msg = catch_unwind(AssertUnwindSafe(|| {
+            ProtoSecretKeyStore::open(sks_dir.path(), SKS_FILE_NAME, None);
+        }));
+        assert_eq!(
+            "secret key store is encrypted, but no key provider was given",
+            *panic_msg.unwrap_err().downcast_ref::<&str>().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_not_leak_any_data_when_decrypting_with_wrong_key() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks = ProtoSecretKeyStore::open_encrypted(
+            sks_dir.path(),
+            SKS_FILE_NAME,
+            None,
+            key_provider.clone(),
+        );
+        assert!(sks
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+        drop(sks);
+        FileKeyProvider::new(kek_dir.path())
+            .add_key("kek-1", &rng.gen())
+            .expect("failed to replace key");
+
+        let panic_msg = catch_unwind(AssertUnwindSafe(|| {
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        }));
+        assert_eq!(
+            "error decrypting SKS data",
+            *panic_msg.unwrap_err().downcast_ref::<&str>().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_panic_when_opening_unencrypted_store_with_key_provider() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks = ProtoSecretKeyStore::open(sks_dir.path(), SKS_FILE_NAME, None);
+        assert!(sks
+            .insert(make_key_id(rng), make_secret_key(rng), None)
+            .is_ok());
+        drop(sks);
+
+        let panic_msg = catch_unwind(AssertUnwindSafe(|| {
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        }));
+        assert_eq!(
+            "secret key store is not encrypted and must be migrated first",
+            *panic_msg.unwrap_err().downcast_ref::<&str>().unwrap()
+        );
+    }
+
+    #[test]
+    fn should_migrate_unencrypted_store() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, _kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let key_id = make_key_id(rng);
+        let key = make_secret_key(rng);
+        let mut sks = ProtoSecretKeyStore::open(sks_dir.path(), SKS_FILE_NAME, None);
+        assert!(sks
+            .insert(key_id, key.clone(), Some(IDKG_MEGA_SCOPE))
+            .is_ok());
+        drop(sks);
+
+        assert_eq!(
+            ProtoSecretKeyStore::migrate_to_encrypted(
+                sks_dir.path(),
+                SKS_FILE_NAME,
+                None,
+                key_provider.clone()
+            ),
+            Ok(())
+        );
+
+        assert!(read_sks_proto(&sks_dir).key_id_to_secret_key_v1.is_empty());
+        assert!(!sks_dir
+            .path()
+            .join(format!("{}.old", SKS_FILE_NAME))
+            .exists());
+        let sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        assert_eq!(sks.get(&key_id), Some(key));
+        assert!(!sks.retain_would_modify_keystore(|_, _| true, IDKG_MEGA_SCOPE));
+        assert!(sks.retain_would_modify_keystore(|_, _| false, IDKG_MEGA_SCOPE));
+    }
+
+    #[test]
+    fn should_reencrypt_with_rotated_key() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let file_key_provider = FileKeyProvider::new(kek_dir.path());
+        let key_id = make_key_id(rng);
+        let key = make_secret_key(rng);
+        let mut sks = ProtoSecretKeyStore::open_encrypted(
+            sks_dir.path(),
+            SKS_FILE_NAME,
+            None,
+            key_provider.clone(),
+        );
+        assert!(sks.insert(key_id, key.clone(), None).is_ok());
+
+        file_key_provider
+            .add_key("kek-2", &rng.gen())
+            .expect("failed to add key");
+        file_key_provider
+            .set_current_key_id("kek-2")
+            .expect("failed to set current key");
+        assert_eq!(sks.reencrypt_with_current_key(), Ok(()));
+        drop(sks);
+        file_key_provider
+            .remove_key("kek-1")
+            .expect("failed to remove key");
+
+        assert_matches!(
+            read_sks_proto(&sks_dir).encrypted_secret_key_store,
+            Some(encrypted) if encrypted.key_encryption_key_id == "kek-2"
+        );
+        let sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        assert_eq!(sks.get(&key_id), Some(key));
+    }
+
+    #[test]
+    fn should_reencrypt_with_rotated_key_when_opening_store() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let file_key_provider = FileKeyProvider::new(kek_dir.path());
+        let key_id = make_key_id(rng);
+        let key = make_secret_key(rng);
+        let mut sks = ProtoSecretKeyStore::open_encrypted(
+            sks_dir.path(),
+            SKS_FILE_NAME,
+            None,
+            key_provider.clone(),
+        );
+        assert!(sks.insert(key_id, key.clone(), None).is_ok());
+        drop(sks);
+
+        file_key_provider
+            .add_key("kek-2", &rng.gen())
+            .expect("failed to add key");
+        file_key_provider
+            .set_current_key_id("kek-2")
+            .expect("failed to set current key");
+        let sks = ProtoSecretKeyStore::open_encrypted(
+            sks_dir.path(),
+            SKS_FILE_NAME,
+            None,
+            key_provider.clone(),
+        );
+        assert_eq!(sks.get(&key_id), Some(key.clone()));
+        drop(sks);
+        file_key_provider
+            .remove_key("kek-1")
+            .expect("failed to remove key");
+
+        assert_matches!(
+            read_sks_proto(&sks_dir).encrypted_secret_key_store,
+            Some(encrypted) if encrypted.key_encryption_key_id == "kek-2"
+        );
+        let sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        assert_eq!(sks.get(&key_id), Some(key));
+    }
+
+    #[test]
+    fn should_fail_to_write_if_current_key_is_missing() {
+        let rng = &mut reproducible_rng();
+        let (sks_dir, kek_dir, key_provider) = sks_dir_and_key_provider_with_key("kek-1", rng);
+        let mut sks =
+            ProtoSecretKeyStore::open_encrypted(sks_dir.path(), SKS_FILE_NAME, None, key_provider);
+        FileKeyProvider::new(kek_dir.path())
+            .remove_key("kek-1")
+            .expect("failed to remove key");
+
+        assert_matches!(
+            sks.insert(make_key_id(rng), make_secret_key(rng), None),
+            Err(SecretKeyStoreInsertionError::TransientError(_))
+        );
+    }
+
+    #[test]
+    fn file_key_provider_should_reject_invalid_key_ids() {
+        let kek_dir = mk_temp_dir_with_permissions(0o700);
+        let key_provider = FileKeyProvider::new(kek_dir.path());
+
+        for key_id in ["", "../kek", "kek/1", "kek.1"] {
+            assert_eq!(
+                key_provider.add_key(key_id, &[0; 32]),
+                Err(KeyProviderError::InvalidKeyId(key_id.to_string()))
+            );
+        }
+        assert_eq!(
+            key_provider.key("kek-1").map(|_| ()),
+            Err(KeyProviderError::KeyNotFound("kek-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn file_key_provider_should_ignore_surrounding_whitespace_in_current_key_id() {
+        let kek_dir = mk_temp_dir_with_permissions(0o700);
+        fs::write(kek_dir.path().join("current"), "kek-1\n").expect("failed to write key ID");
+
+        assert_eq!(
+            FileKeyProvider::new(kek_dir.path()).current_key_id(),
+            Ok("kek-1".to_string())
+        );
+    }
+
+    fn sks_dir_and_key_provider_with_key<R: Rng + CryptoRng>(
+        key_id: &str,
+        rng: &mut R,
+    ) -> (TempDir, TempDir, Arc<dyn KeyProvider>) {
+        let sks_dir = mk_temp_dir_with_permissions(0o700);
+        let kek_dir = mk_temp_dir_with_permissions(0o700);
+        let key_provider = FileKeyProvider::new(kek_dir.path());
+        key_provider
+            .add_key(key_id, &rng.gen())
+            .expect("failed to add key");
+        key_provider
+            .set_current_key_id(key_id)
+            .expect("failed to set current key");
+        (sks_dir, kek_dir, Arc::new(key_provider))
+    }
+
+    fn read_sks_proto(sks_dir: &TempDir) -> pb::SecretKeyStore {
+        let data = fs::read(sks_dir.path().join(SKS_FILE_NAME)).expect("failed to read SKS");
+        pb::SecretKeyStore::decode(&*data).expect("failed to decode SKS")
+    }
+}
+
 fn copy_file_to_dir(source_file: &Path, target_dir: &Path) {
     let filename = source_file.file_name().expect("expected file name");
     let target_file = target_dir.join(filename);
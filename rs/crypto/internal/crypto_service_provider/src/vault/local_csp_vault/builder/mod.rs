@@ -1,6 +1,12 @@
 use super::*;
+use crate::secret_key_store::key_provider::KeyProvider;
+use crate::secret_key_store::SecretKeyStoreWriteError;
 use rand::rngs::OsRng;
 
+const SKS_DATA_FILENAME: &str = "sks_data.pb";
+const PUBLIC_KEY_STORE_DATA_FILENAME: &str = "public_keys.pb";
+const CANISTER_SKS_DATA_FILENAME: &str = "canister_sks_data.pb";
+
 pub struct LocalCspVaultBuilder<R, S, C, P> {
     csprng: Box<dyn FnOnce() -> R>,
     node_secret_key_store: Box<dyn FnOnce() -> S>,
@@ -37,17 +43,31 @@ impl ProdLocalCspVault {
         logger: ReplicaLogger,
     ) -> LocalCspVaultBuilder<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore, ProtoPublicKeyStore>
     {
-        const SKS_DATA_FILENAME: &str = "sks_data.pb";
-        const PUBLIC_KEY_STORE_DATA_FILENAME: &str = "public_keys.pb";
-        const CANISTER_SKS_DATA_FILENAME: &str = "canister_sks_data.pb";
+        Self::builder_in_dir_with_key_provider(key_store_dir, None, metrics, logger)
+    }
 
-        let node_secret_key_store =
-            ProtoSecretKeyStore::open(key_store_dir, SKS_DATA_FILENAME, Some(new_logger!(logger)));
-        let canister_secret_key_store = ProtoSecretKeyStore::open(
-            key_store_dir,
-            CANISTER_SKS_DATA_FILENAME,
-            Some(new_logger!(logger)),
-        );
+    /// Like [`Self::builder_in_dir`], but if a `key_provider` is given, the
+    /// secret key stores are encrypted at rest with its key-encryption keys.
+    /// The secret key stores must have been migrated with
+    /// [`Self::encrypt_secret_key_stores_in_dir`] beforehand.
+    pub fn builder_in_dir_with_key_provider(
+        key_store_dir: &Path,
+        key_provider: Option<Arc<dyn KeyProvider>>,
+        metrics: Arc<CryptoMetrics>,
+        logger: ReplicaLogger,
+    ) -> LocalCspVaultBuilder<OsRng, ProtoSecretKeyStore, ProtoSecretKeyStore, ProtoPublicKeyStore>
+    {
+        let open_secret_key_store = |file_name| match &key_provider {
+            Some(key_provider) => ProtoSecretKeyStore::open_encrypted(
+                key_store_dir,
+                file_name,
+                Some(new_logger!(logger)),
+                Arc::clone(key_provider),
+            ),
+            None => ProtoSecretKeyStore::open(key_store_dir, file_name, Some(new_logger!(logger))),
+        };
+        let node_secret_key_store = open_secret_key_store(SKS_DATA_FILENAME);
+        let canister_secret_key_store = open_secret_key_store(CANISTER_SKS_DATA_FILENAME);
         let public_key_store = ProtoPublicKeyStore::open(
             key_store_dir,
             PUBLIC_KEY_STORE_DATA_FILENAME,
@@ -62,6 +82,29 @@ impl ProdLocalCspVault {
             logger,
         )
     }
+
+    /// Encrypts the node and canister secret key stores in `key_store_dir`
+    /// with the current key-encryption key of `key_provider`. Stores that are
+    /// already encrypted are re-encrypted with the current key, so this is
+    /// also used after the key was rotated.
+    ///
+    /// # Panics
+    /// In the cases listed for [`ProtoSecretKeyStore::migrate_to_encrypted`].
+    pub fn encrypt_secret_key_stores_in_dir(
+        key_store_dir: &Path,
+        key_provider: Arc<dyn KeyProvider>,
+        logger: &ReplicaLogger,
+    ) -> Result<(), SecretKeyStoreWriteError> {
+        for file_name in [SKS_DATA_FILENAME, CANISTER_SKS_DATA_FILENAME] {
+            ProtoSecretKeyStore::migrate_to_encrypted(
+                key_store_dir,
+                file_name,
+                Some(new_logger!(logger)),
+                Arc::clone(&key_provider),
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl<R, S, C, P> LocalCspVaultBuilder<R, S, C, P>
@@ -15,9 +15,11 @@ mod tls;
 
 use crate::public_key_store::proto_pubkey_store::ProtoPublicKeyStore;
 use crate::public_key_store::PublicKeyStore;
+use crate::secret_key_store::key_provider::key_provider_from_config;
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
 use crate::secret_key_store::SecretKeyStore;
 use crate::CspRwLock;
+use ic_config::crypto::CryptoConfig;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_utils_time::CurrentSystemTimeSource;
@@ -123,6 +125,22 @@ impl ProdLocalCspVault {
     ) -> Self {
         ProdLocalCspVault::builder_in_dir(key_store_dir, metrics, logger).build()
     }
+
+    /// Creates a vault whose key stores are in the crypto root of `config`,
+    /// with the secret key stores encrypted at rest if `config` says so.
+    pub fn new_from_config(
+        config: &CryptoConfig,
+        metrics: Arc<CryptoMetrics>,
+        logger: ReplicaLogger,
+    ) -> Self {
+        ProdLocalCspVault::builder_in_dir_with_key_provider(
+            &config.crypto_root,
+            key_provider_from_config(config),
+            metrics,
+            logger,
+        )
+        .build()
+    }
 }
 
 impl<R: Rng + CryptoRng, S: SecretKeyStore, C: SecretKeyStore, P: PublicKeyStore>
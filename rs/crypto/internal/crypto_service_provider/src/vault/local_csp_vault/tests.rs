@@ -1,10 +1,12 @@
 //! Tests for Local CSP vault
 
 use crate::public_key_store::proto_pubkey_store::ProtoPublicKeyStore;
+use crate::secret_key_store::key_provider::FileKeyProvider;
 use crate::secret_key_store::test_utils::{make_key_id, make_secret_key};
 use crate::secret_key_store::SecretKeyStore;
-use crate::vault::local_csp_vault::ProtoSecretKeyStore;
+use crate::vault::local_csp_vault::{ProdLocalCspVault, ProtoSecretKeyStore};
 use crate::LocalCspVault;
+use ic_config::crypto::CryptoConfig;
 use ic_crypto_internal_csp_test_utils::files::mk_temp_dir_with_permissions;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_test_utils_reproducible_rng::reproducible_rng;
@@ -165,3 +167,48 @@ fn should_insert_keys_in_canister_sks() {
     // Key should be in the canister secret key store after insertion
     assert!(vault.canister_sks_read_lock().contains(&key_id));
 }
+
+#[test]
+fn should_open_key_stores_in_dir_after_encrypting_them() {
+    let rng = &mut reproducible_rng();
+    let (mut config, _crypto_root) = CryptoConfig::new_in_temp_dir();
+    let kek_dir = mk_temp_dir_with_permissions(0o700);
+    let key_provider = FileKeyProvider::new(kek_dir.path());
+    key_provider
+        .add_key("kek-1", &[42; 32])
+        .expect("failed to add key");
+    key_provider
+        .set_current_key_id("kek-1")
+        .expect("failed to set current key");
+    let (node_key_id, canister_key_id) = (make_key_id(rng), make_key_id(rng));
+    {
+        let vault = LocalCspVault::new_in_dir(
+            &config.crypto_root,
+            Arc::new(CryptoMetrics::none()),
+            no_op_logger(),
+        );
+        assert!(vault
+            .sks_write_lock()
+            .insert(node_key_id, make_secret_key(rng), None)
+            .is_ok());
+        assert!(vault
+            .canister_sks_write_lock()
+            .insert(canister_key_id, make_secret_key(rng), None)
+            .is_ok());
+    }
+
+    assert_eq!(
+        ProdLocalCspVault::encrypt_secret_key_stores_in_dir(
+            &config.crypto_root,
+            Arc::new(key_provider),
+            &no_op_logger()
+        ),
+        Ok(())
+    );
+
+    config.secret_key_store_encryption_key_dir = Some(kek_dir.path().to_path_buf());
+    let vault =
+        LocalCspVault::new_from_config(&config, Arc::new(CryptoMetrics::none()), no_op_logger());
+    assert!(vault.sks_read_lock().contains(&node_key_id));
+    assert!(vault.canister_sks_read_lock().contains(&canister_key_id));
+}
@@ -5,6 +5,7 @@ use crate::vault::api::{
     CspMultiSignatureKeygenError, CspPublicKeyStoreError, CspSecretKeyStoreContainsError,
     CspTlsKeygenError, CspTlsSignError, PksAndSksContainsErrors, ValidatePksAndSksError,
 };
+use ic_config::crypto::CryptoConfig;
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors;
 use ic_crypto_internal_threshold_sig_ecdsa::{
@@ -18,7 +19,7 @@ use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::{
     CspNiDkgDealing, CspNiDkgTranscript, Epoch,
 };
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
-use ic_logger::ReplicaLogger;
+use ic_logger::{debug, info, new_logger, warn, ReplicaLogger};
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError, IDkgRetainKeysError,
     IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
@@ -31,11 +32,15 @@ use ic_types::crypto::{AlgorithmId, CurrentNodePublicKeys};
 use ic_types::{NodeId, NodeIndex, NumberOfNodes, Randomness};
 use serde_bytes::ByteBuf;
 use std::collections::{BTreeMap, BTreeSet};
-use std::path::Path;
-use tokio::net::UnixListener;
+use std::time::Duration;
+use stubborn_io::strategies::ExpBackoffStrategy;
+use stubborn_io::ReconnectOptions;
+use tokio::net::{TcpListener, UnixListener};
+use tokio_rustls::rustls::ServerConfig;
 
 const FOUR_GIGA_BYTES: usize = 4 * 1024 * 1024 * 1024;
 mod codec;
+mod robust_tls_socket;
 mod robust_unix_socket;
 mod tarpc_csp_vault_client;
 mod tarpc_csp_vault_server;
@@ -43,8 +48,11 @@ mod tarpc_csp_vault_server;
 use crate::key_id::KeyId;
 pub use crate::vault::local_csp_vault::ProdLocalCspVault;
 use crate::ExternalPublicKeys;
-use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_crypto_internal_logmon::metrics::{CryptoMetrics, ServiceType, VaultConnectionEvent};
 use ic_crypto_node_key_validation::ValidNodePublicKeys;
+pub use robust_tls_socket::{
+    remote_vault_tls_client_config, remote_vault_tls_server_config, RemoteCspVaultTlsConfigError,
+};
 use std::sync::Arc;
 pub use tarpc_csp_vault_client::{RemoteCspVault, RemoteCspVaultBuilder};
 pub use tarpc_csp_vault_server::{TarpcCspVaultServerImpl, TarpcCspVaultServerImplBuilder};
@@ -235,18 +243,34 @@ pub trait TarpcCspVault {
 }
 
 pub async fn run_csp_vault_server(
-    sks_dir: &Path,
+    config: &CryptoConfig,
     listener: UnixListener,
     logger: ReplicaLogger,
     metrics: CryptoMetrics,
 ) {
-    let server = TarpcCspVaultServerImpl::builder(sks_dir)
+    let server = TarpcCspVaultServerImpl::builder_from_config(config)
         .with_logger(logger)
         .with_metrics(Arc::new(metrics))
         .build(listener);
     server.run().await
 }
 
+/// Runs a CSP vault server that accepts connections on the TCP `listener`
+/// from clients that authenticate with TLS according to `tls_config`.
+pub async fn run_csp_vault_server_with_tls(
+    config: &CryptoConfig,
+    listener: TcpListener,
+    tls_config: Arc<ServerConfig>,
+    logger: ReplicaLogger,
+    metrics: CryptoMetrics,
+) {
+    let server = TarpcCspVaultServerImpl::builder_from_config(config)
+        .with_logger(logger)
+        .with_metrics(Arc::new(metrics))
+        .build_with_tls(listener, tls_config);
+    server.run().await
+}
+
 pub fn remote_vault_codec_builder() -> Builder {
     let mut codec_builder = LengthDelimitedCodec::builder();
     codec_builder
@@ -254,3 +278,59 @@ pub fn remote_vault_codec_builder() -> Builder {
         .max_frame_length(FOUR_GIGA_BYTES);
     codec_builder
 }
+
+/// Options for transparently re-establishing a lost connection from a remote
+/// CSP vault client to the server at `server_address`.
+fn reconnect_options(
+    server_address: String,
+    logger: &ReplicaLogger,
+    metrics: &Arc<CryptoMetrics>,
+) -> ReconnectOptions {
+    const MINIMUM_DELAY: Duration = Duration::from_millis(100);
+    const MAXIMUM_DELAY: Duration = Duration::from_secs(1);
+    const EXPONENTIAL_BACKOFF_FACTOR: f64 = 2.0;
+    const JITTER_AMOUNT: f64 = 0.05;
+    ReconnectOptions::new()
+        .with_on_disconnect_callback({
+            let logger = new_logger!(logger);
+            let metrics = Arc::clone(metrics);
+            let server_address = server_address.clone();
+            move || {
+                warn!(
+                    logger,
+                    "Detected disconnection from {}. Attempting to reconnect...", server_address
+                );
+                metrics.observe_vault_connection_event(
+                    ServiceType::Client,
+                    VaultConnectionEvent::Disconnected,
+                );
+            }
+        })
+        .with_on_connect_callback({
+            let logger = new_logger!(logger);
+            let metrics = Arc::clone(metrics);
+            let server_address = server_address.clone();
+            move || {
+                debug!(logger, "Successfully (re-)connected to {}", server_address);
+                metrics.observe_vault_connection_event(
+                    ServiceType::Client,
+                    VaultConnectionEvent::Connected,
+                );
+            }
+        })
+        .with_on_connect_fail_callback({
+            let logger = new_logger!(logger);
+            let metrics = Arc::clone(metrics);
+            move || {
+                info!(logger, "Failed to reconnect to {}", server_address);
+                metrics.observe_vault_connection_event(
+                    ServiceType::Client,
+                    VaultConnectionEvent::ConnectFailed,
+                );
+            }
+        })
+        .with_retries_generator(|| {
+            ExpBackoffStrategy::new(MINIMUM_DELAY, EXPONENTIAL_BACKOFF_FACTOR, JITTER_AMOUNT)
+                .with_max(MAXIMUM_DELAY)
+        })
+}
@@ -0,0 +1,180 @@
+use super::reconnect_options;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_logger::{debug, ReplicaLogger};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use stubborn_io::tokio::{StubbornIo, UnderlyingIo};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{
+    version::TLS13, Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName,
+};
+use tokio_rustls::TlsConnector;
+
+/// Maximum duration of establishing a TCP connection and performing the TLS
+/// handshake, both on the client and on the server side.
+pub(super) const TLS_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The TLS server a remote CSP vault client connects to.
+#[derive(Clone)]
+pub struct TlsServerEndpoint {
+    pub address: SocketAddr,
+    pub server_name: ServerName,
+    pub config: Arc<ClientConfig>,
+}
+
+pub struct RobustTlsStream(TlsStream<TcpStream>);
+
+impl AsyncRead for RobustTlsStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for RobustTlsStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, Error>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl UnderlyingIo<(TlsServerEndpoint, ReplicaLogger)> for RobustTlsStream {
+    fn establish(
+        (endpoint, logger): (TlsServerEndpoint, ReplicaLogger),
+    ) -> Pin<Box<dyn Future<Output = io::Result<Self>> + Send>> {
+        Box::pin(async move {
+            debug!(logger, "Trying to (re-)connect to {}", endpoint.address);
+            let connect = async {
+                let stream = TcpStream::connect(endpoint.address).await?;
+                stream.set_nodelay(true)?;
+                TlsConnector::from(endpoint.config)
+                    .connect(endpoint.server_name, stream)
+                    .await
+            };
+            let stream = tokio::time::timeout(TLS_CONNECT_TIMEOUT, connect)
+                .await
+                .map_err(|_| {
+                    Error::new(
+                        ErrorKind::TimedOut,
+                        format!("connecting to {} timed out", endpoint.address),
+                    )
+                })??;
+            Ok(RobustTlsStream(stream))
+        })
+    }
+}
+
+pub type RobustTlsSocket = StubbornIo<RobustTlsStream, (TlsServerEndpoint, ReplicaLogger)>;
+
+pub async fn connect(
+    endpoint: TlsServerEndpoint,
+    logger: ReplicaLogger,
+    metrics: Arc<CryptoMetrics>,
+) -> io::Result<RobustTlsSocket> {
+    let options = reconnect_options(endpoint.address.to_string(), &logger, &metrics);
+    RobustTlsSocket::connect_with_options((endpoint, logger), options).await
+}
+
+/// Errors that can occur while creating the TLS configuration of a remote CSP
+/// vault client or server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoteCspVaultTlsConfigError {
+    MalformedCaCertificate(String),
+    MalformedCertificateOrKey(String),
+}
+
+impl std::error::Error for RemoteCspVaultTlsConfigError {}
+
+impl fmt::Display for RemoteCspVaultTlsConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteCspVaultTlsConfigError::MalformedCaCertificate(e) => {
+                write!(f, "Malformed CA certificate: {}", e)
+            }
+            RemoteCspVaultTlsConfigError::MalformedCertificateOrKey(e) => {
+                write!(f, "Malformed certificate or private key: {}", e)
+            }
+        }
+    }
+}
+
+/// Creates the TLS configuration of a remote CSP vault server.
+///
+/// The server authenticates with `server_cert_der` and `server_key_der` (a
+/// PKCS#8 private key) and only accepts clients that present a certificate
+/// issued by the CA with certificate `ca_cert_der`. Only TLS 1.3 is supported.
+pub fn remote_vault_tls_server_config(
+    ca_cert_der: &[u8],
+    server_cert_der: Vec<u8>,
+    server_key_der: Vec<u8>,
+) -> Result<Arc<ServerConfig>, RemoteCspVaultTlsConfigError> {
+    let client_cert_verifier = AllowAnyAuthenticatedClient::new(root_cert_store(ca_cert_der)?);
+    let config = ServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[&TLS13])
+        .expect("the default cipher suites support TLS 1.3")
+        .with_client_cert_verifier(Arc::new(client_cert_verifier))
+        .with_single_cert(
+            vec![Certificate(server_cert_der)],
+            PrivateKey(server_key_der),
+        )
+        .map_err(|e| RemoteCspVaultTlsConfigError::MalformedCertificateOrKey(e.to_string()))?;
+    Ok(Arc::new(config))
+}
+
+/// Creates the TLS configuration of a remote CSP vault client.
+///
+/// The client authenticates with `client_cert_der` and `client_key_der` (a
+/// PKCS#8 private key) and only accepts servers that present a certificate
+/// issued by the CA with certificate `ca_cert_der`. Only TLS 1.3 is supported.
+pub fn remote_vault_tls_client_config(
+    ca_cert_der: &[u8],
+    client_cert_der: Vec<u8>,
+    client_key_der: Vec<u8>,
+) -> Result<Arc<ClientConfig>, RemoteCspVaultTlsConfigError> {
+    let config = ClientConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[&TLS13])
+        .expect("the default cipher suites support TLS 1.3")
+        .with_root_certificates(root_cert_store(ca_cert_der)?)
+        .with_client_auth_cert(
+            vec![Certificate(client_cert_der)],
+            PrivateKey(client_key_der),
+        )
+        .map_err(|e| RemoteCspVaultTlsConfigError::MalformedCertificateOrKey(e.to_string()))?;
+    Ok(Arc::new(config))
+}
+
+fn root_cert_store(ca_cert_der: &[u8]) -> Result<RootCertStore, RemoteCspVaultTlsConfigError> {
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(&Certificate(ca_cert_der.to_vec()))
+        .map_err(|e| RemoteCspVaultTlsConfigError::MalformedCaCertificate(e.to_string()))?;
+    Ok(roots)
+}
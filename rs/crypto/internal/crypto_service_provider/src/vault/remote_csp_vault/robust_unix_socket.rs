@@ -1,14 +1,14 @@
-use ic_logger::{debug, info, new_logger, warn, ReplicaLogger};
+use super::reconnect_options;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_logger::{debug, ReplicaLogger};
 use std::future::Future;
 use std::io;
 use std::io::Error;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
-use std::time::Duration;
-use stubborn_io::strategies::ExpBackoffStrategy;
 use stubborn_io::tokio::{StubbornIo, UnderlyingIo};
-use stubborn_io::ReconnectOptions;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::UnixStream;
 
@@ -56,41 +56,11 @@ impl UnderlyingIo<(PathBuf, ReplicaLogger)> for RobustUnixStream {
 
 pub type RobustUnixSocket = StubbornIo<RobustUnixStream, (PathBuf, ReplicaLogger)>;
 
-pub async fn connect(socket_path: PathBuf, logger: ReplicaLogger) -> io::Result<RobustUnixSocket> {
-    const MINIMUM_DELAY: Duration = Duration::from_millis(100);
-    const MAXIMUM_DELAY: Duration = Duration::from_secs(1);
-    const EXPONENTIAL_BACKOFF_FACTOR: f64 = 2.0;
-    const JITTER_AMOUNT: f64 = 0.05;
-    let options = ReconnectOptions::new()
-        .with_on_disconnect_callback({
-            let logger = new_logger!(logger);
-            let socket_path = socket_path.clone();
-            move || {
-                warn!(
-                    logger,
-                    "Detected disconnection from socket {:?}. Attempting to reconnect...",
-                    socket_path
-                );
-            }
-        })
-        .with_on_connect_callback({
-            let logger = new_logger!(logger);
-            let socket_path = socket_path.clone();
-            move || {
-                debug!(
-                    logger,
-                    "Successfully (re-)connected to socket {:?}", socket_path
-                )
-            }
-        })
-        .with_on_connect_fail_callback({
-            let logger = new_logger!(logger);
-            let socket_path = socket_path.clone();
-            move || info!(logger, "Failed to reconnect to socket {:?}", socket_path)
-        })
-        .with_retries_generator(|| {
-            ExpBackoffStrategy::new(MINIMUM_DELAY, EXPONENTIAL_BACKOFF_FACTOR, JITTER_AMOUNT)
-                .with_max(MAXIMUM_DELAY)
-        });
+pub async fn connect(
+    socket_path: PathBuf,
+    logger: ReplicaLogger,
+    metrics: Arc<CryptoMetrics>,
+) -> io::Result<RobustUnixSocket> {
+    let options = reconnect_options(format!("{:?}", socket_path), &logger, &metrics);
     RobustUnixSocket::connect_with_options((socket_path, logger), options).await
 }
@@ -11,8 +11,10 @@ use crate::vault::api::{
     ThresholdSignatureCspVault, ValidatePksAndSksError,
 };
 use crate::vault::remote_csp_vault::codec::{Bincode, CspVaultObserver, ObservableCodec};
+use crate::vault::remote_csp_vault::robust_tls_socket::TlsServerEndpoint;
 use crate::vault::remote_csp_vault::{
-    remote_vault_codec_builder, robust_unix_socket, TarpcCspVaultClient, FOUR_GIGA_BYTES,
+    remote_vault_codec_builder, robust_tls_socket, robust_unix_socket, TarpcCspVaultClient,
+    FOUR_GIGA_BYTES,
 };
 use crate::{ExternalPublicKeys, TlsHandshakeCspVault};
 use core::future::Future;
@@ -48,10 +50,13 @@ use ic_types::{NodeId, NumberOfNodes, Randomness};
 use serde::{Deserialize, Serialize};
 use serde_bytes::ByteBuf;
 use std::collections::{BTreeMap, BTreeSet};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tarpc::serde_transport;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::{ClientConfig, ServerName};
 
 #[cfg(test)]
 use ic_config::logger::Config as LoggerConfig;
@@ -120,10 +125,43 @@ impl RemoteCspVault {
     ) -> RemoteCspVaultBuilder {
         RemoteCspVaultBuilder::new(socket_path, rt_handle)
     }
+
+    /// Creates a builder for a `RemoteCspVault`-object that communicates
+    /// with a server at `server_address` via mutually authenticated TLS, see
+    /// [`RemoteCspVaultBuilder::new_with_tls`].
+    pub fn builder_with_tls(
+        server_address: SocketAddr,
+        server_name: String,
+        tls_config: Arc<ClientConfig>,
+        rt_handle: tokio::runtime::Handle,
+    ) -> RemoteCspVaultBuilder {
+        RemoteCspVaultBuilder::new_with_tls(server_address, server_name, tls_config, rt_handle)
+    }
+}
+
+/// The transport over which a `RemoteCspVault` talks to the server.
+enum RemoteCspVaultTransport {
+    UnixSocket(PathBuf),
+    Tls {
+        server_address: SocketAddr,
+        server_name: String,
+        config: Arc<ClientConfig>,
+    },
+}
+
+impl RemoteCspVaultTransport {
+    fn server_address(&self) -> String {
+        match self {
+            RemoteCspVaultTransport::UnixSocket(socket_path) => {
+                socket_path.to_string_lossy().to_string()
+            }
+            RemoteCspVaultTransport::Tls { server_address, .. } => server_address.to_string(),
+        }
+    }
 }
 
 pub struct RemoteCspVaultBuilder {
-    socket_path: PathBuf,
+    transport: RemoteCspVaultTransport,
     rt_handle: tokio::runtime::Handle,
     max_frame_length: usize,
     rpc_timeout: Duration,
@@ -136,8 +174,34 @@ pub struct RemoteCspVaultBuilder {
 
 impl RemoteCspVaultBuilder {
     pub fn new(socket_path: PathBuf, rt_handle: tokio::runtime::Handle) -> Self {
+        Self::new_internal(RemoteCspVaultTransport::UnixSocket(socket_path), rt_handle)
+    }
+
+    /// Creates a builder for a client that connects to the server at
+    /// `server_address` over TCP and authenticates the server as
+    /// `server_name` with TLS, using `tls_config` (see
+    /// [`remote_vault_tls_client_config`](super::remote_vault_tls_client_config)).
+    ///
+    /// If the connection is lost, it is transparently re-established.
+    pub fn new_with_tls(
+        server_address: SocketAddr,
+        server_name: String,
+        tls_config: Arc<ClientConfig>,
+        rt_handle: tokio::runtime::Handle,
+    ) -> Self {
+        Self::new_internal(
+            RemoteCspVaultTransport::Tls {
+                server_address,
+                server_name,
+                config: tls_config,
+            },
+            rt_handle,
+        )
+    }
+
+    fn new_internal(transport: RemoteCspVaultTransport, rt_handle: tokio::runtime::Handle) -> Self {
         RemoteCspVaultBuilder {
-            socket_path,
+            transport,
             rt_handle,
             max_frame_length: FOUR_GIGA_BYTES,
             rpc_timeout: DEFAULT_RPC_TIMEOUT,
@@ -190,28 +254,43 @@ impl RemoteCspVaultBuilder {
     }
 
     pub fn build(self) -> Result<RemoteCspVault, RemoteCspVaultError> {
-        let conn = self
-            .rt_handle
-            .block_on(robust_unix_socket::connect(
-                self.socket_path.clone(),
-                new_logger!(&self.logger),
-            ))
-            .map_err(|e| RemoteCspVaultError::TransportError {
-                server_address: self.socket_path.to_string_lossy().to_string(),
-                message: e.to_string(),
-            })?;
-        let transport = serde_transport::new(
-            remote_vault_codec_builder()
-                .max_frame_length(self.max_frame_length)
-                .new_framed(conn),
-            ObservableCodec::new(
-                Bincode::default(),
-                CspVaultObserver::new(new_logger!(&self.logger), Arc::clone(&self.metrics)),
-            ),
-        );
-        let client = {
-            let _enter_guard = self.rt_handle.enter();
-            TarpcCspVaultClient::new(Default::default(), transport).spawn()
+        let transport_error = |message: String| RemoteCspVaultError::TransportError {
+            server_address: self.transport.server_address(),
+            message,
+        };
+        let client = match &self.transport {
+            RemoteCspVaultTransport::UnixSocket(socket_path) => {
+                let conn = self
+                    .rt_handle
+                    .block_on(robust_unix_socket::connect(
+                        socket_path.clone(),
+                        new_logger!(&self.logger),
+                        Arc::clone(&self.metrics),
+                    ))
+                    .map_err(|e| transport_error(e.to_string()))?;
+                self.spawn_client(conn)
+            }
+            RemoteCspVaultTransport::Tls {
+                server_address,
+                server_name,
+                config,
+            } => {
+                let endpoint = TlsServerEndpoint {
+                    address: *server_address,
+                    server_name: ServerName::try_from(server_name.as_str())
+                        .map_err(|e| transport_error(e.to_string()))?,
+                    config: Arc::clone(config),
+                };
+                let conn = self
+                    .rt_handle
+                    .block_on(robust_tls_socket::connect(
+                        endpoint,
+                        new_logger!(&self.logger),
+                        Arc::clone(&self.metrics),
+                    ))
+                    .map_err(|e| transport_error(e.to_string()))?;
+                self.spawn_client(conn)
+            }
         };
         debug!(self.logger, "Instantiated remote CSP vault client");
         Ok(RemoteCspVault {
@@ -229,6 +308,23 @@ impl RemoteCspVaultBuilder {
     pub fn build_expecting_ok(self) -> RemoteCspVault {
         self.build().expect("error building RemoteCspVault")
     }
+
+    fn spawn_client<S>(&self, conn: S) -> TarpcCspVaultClient
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let transport = serde_transport::new(
+            remote_vault_codec_builder()
+                .max_frame_length(self.max_frame_length)
+                .new_framed(conn),
+            ObservableCodec::new(
+                Bincode::default(),
+                CspVaultObserver::new(new_logger!(&self.logger), Arc::clone(&self.metrics)),
+            ),
+        );
+        let _enter_guard = self.rt_handle.enter();
+        TarpcCspVaultClient::new(Default::default(), transport).spawn()
+    }
 }
 
 fn deadline_from_now(timeout: Duration) -> SystemTime {
@@ -8,10 +8,12 @@ use crate::vault::api::{
 };
 use crate::vault::api::{CspPublicKeyStoreError, CspVault};
 use crate::vault::local_csp_vault::{LocalCspVault, ProdLocalCspVault};
+use crate::vault::remote_csp_vault::robust_tls_socket::TLS_CONNECT_TIMEOUT;
 use crate::vault::remote_csp_vault::{remote_vault_codec_builder, TarpcCspVault};
 use crate::vault::remote_csp_vault::{PksAndSksContainsErrors, FOUR_GIGA_BYTES};
 use crate::ExternalPublicKeys;
-use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_config::crypto::CryptoConfig;
+use ic_crypto_internal_logmon::metrics::{CryptoMetrics, ServiceType, VaultConnectionEvent};
 use ic_crypto_internal_seed::Seed;
 use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::{
     CspDkgCreateFsKeyError, CspDkgCreateReshareDealingError, CspDkgLoadPrivateKeyError,
@@ -31,7 +33,7 @@ use ic_crypto_internal_types::NodeIndex;
 use ic_crypto_node_key_validation::ValidNodePublicKeys;
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_logger::replica_logger::no_op_logger;
-use ic_logger::{info, new_logger, warn, ReplicaLogger};
+use ic_logger::{debug, info, new_logger, warn, ReplicaLogger};
 use ic_types::crypto::canister_threshold_sig::error::{
     IDkgCreateDealingError, IDkgLoadTranscriptError, IDkgOpenTranscriptError, IDkgRetainKeysError,
     IDkgVerifyDealingPrivateError, ThresholdEcdsaSignShareError,
@@ -44,21 +46,27 @@ use ic_types::crypto::{AlgorithmId, CurrentNodePublicKeys};
 use ic_types::{NodeId, NumberOfNodes, Randomness};
 use serde_bytes::ByteBuf;
 use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
 use std::path::Path;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use tarpc::server::BaseChannel;
 #[allow(unused_imports)]
 use tarpc::server::Serve;
 use tarpc::{context, serde_transport, server::Channel};
 use threadpool::ThreadPool;
-use tokio::net::UnixListener;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
 
 use super::codec::{Bincode, CspVaultObserver, ObservableCodec};
 
 /// Crypto service provider (CSP) vault server based on the tarpc RPC framework.
 pub struct TarpcCspVaultServerImpl<C: CspVault> {
     local_csp_vault: Arc<C>,
-    listener: UnixListener,
+    listener: VaultListener,
     thread_pool: ThreadPool,
     max_frame_length: usize,
     metrics: Arc<CryptoMetrics>,
@@ -66,6 +74,17 @@ pub struct TarpcCspVaultServerImpl<C: CspVault> {
     logger: ReplicaLogger,
 }
 
+/// The transport over which the CSP vault server accepts connections.
+enum VaultListener {
+    UnixSocket(UnixListener),
+    /// Clients connect over TCP and must authenticate with TLS as required
+    /// by the `TlsAcceptor`'s configuration.
+    Tls {
+        listener: TcpListener,
+        acceptor: TlsAcceptor,
+    },
+}
+
 /// A worker of the tarpc CSP vault server responsible for a single service request.
 ///
 /// For each service request (i.e., remote procedure call), a new worker is
@@ -527,6 +546,21 @@ impl TarpcCspVaultServerImplBuilder<ProdLocalCspVault> {
         });
         Self::new_internal(local_csp_vault_factory)
     }
+
+    /// Creates a builder for a server whose vault uses the key stores in the
+    /// crypto root of `config`, with the secret key stores encrypted at rest
+    /// if `config` says so.
+    pub fn new_from_config(config: &CryptoConfig) -> Self {
+        let config = config.clone();
+        let local_csp_vault_factory = Box::new(move |logger: &ReplicaLogger, metrics| {
+            Arc::new(LocalCspVault::new_from_config(
+                &config,
+                metrics,
+                new_logger!(logger),
+            ))
+        });
+        Self::new_internal(local_csp_vault_factory)
+    }
 }
 
 impl<C: 'static + Send + Sync> TarpcCspVaultServerImplBuilder<C> {
@@ -567,6 +601,24 @@ impl<C> TarpcCspVaultServerImplBuilder<C> {
 
 impl<C: CspVault> TarpcCspVaultServerImplBuilder<C> {
     pub fn build(&self, listener: UnixListener) -> TarpcCspVaultServerImpl<C> {
+        self.build_internal(VaultListener::UnixSocket(listener))
+    }
+
+    /// Builds a server that accepts connections on the TCP `listener` from
+    /// clients that authenticate with TLS according to `tls_config` (see
+    /// [`remote_vault_tls_server_config`](super::remote_vault_tls_server_config)).
+    pub fn build_with_tls(
+        &self,
+        listener: TcpListener,
+        tls_config: Arc<ServerConfig>,
+    ) -> TarpcCspVaultServerImpl<C> {
+        self.build_internal(VaultListener::Tls {
+            listener,
+            acceptor: TlsAcceptor::from(tls_config),
+        })
+    }
+
+    fn build_internal(&self, listener: VaultListener) -> TarpcCspVaultServerImpl<C> {
         info!(&self.logger, "Starting new RPC CSP vault server");
         let local_csp_vault: Arc<C> =
             (self.local_csp_vault_factory)(&self.logger, Arc::clone(&self.metrics));
@@ -585,6 +637,12 @@ impl TarpcCspVaultServerImpl<ProdLocalCspVault> {
     pub fn builder(key_store_dir: &Path) -> TarpcCspVaultServerImplBuilder<ProdLocalCspVault> {
         TarpcCspVaultServerImplBuilder::new(key_store_dir)
     }
+
+    pub fn builder_from_config(
+        config: &CryptoConfig,
+    ) -> TarpcCspVaultServerImplBuilder<ProdLocalCspVault> {
+        TarpcCspVaultServerImplBuilder::new_from_config(config)
+    }
 }
 
 impl<C: CspVault + 'static> TarpcCspVaultServerImpl<C> {
@@ -599,26 +657,104 @@ impl<C: CspVault + 'static> TarpcCspVaultServerImpl<C> {
 
 impl<C: CspVault + 'static> TarpcCspVaultServerImpl<C> {
     pub async fn run(self) {
+        // Listen for connections; spawns one `tokio` task per client.
+        match &self.listener {
+            VaultListener::UnixSocket(listener) => loop {
+                let (conn, _addr) = listener.accept().await.unwrap_or_else(|e| {
+                    panic!(
+                        "Error listening at socket {:?}: {}",
+                        listener.local_addr(),
+                        e
+                    )
+                });
+                tokio::spawn((self.connection_server())(conn));
+            },
+            VaultListener::Tls { listener, acceptor } => {
+                const MINIMUM_ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(10);
+                const MAXIMUM_ACCEPT_ERROR_DELAY: Duration = Duration::from_secs(1);
+                let mut accept_error_delay = MINIMUM_ACCEPT_ERROR_DELAY;
+                loop {
+                    let (conn, addr) = match listener.accept().await {
+                        Ok(accepted) => {
+                            accept_error_delay = MINIMUM_ACCEPT_ERROR_DELAY;
+                            accepted
+                        }
+                        Err(e) => {
+                            // Errors such as running out of file descriptors are
+                            // transient for TCP listeners, so keep listening, but
+                            // back off exponentially so that a persistent error
+                            // does not make the loop spin.
+                            warn!(
+                                self.logger,
+                                "Error accepting connection at {:?}: {}. Retrying in {:?}",
+                                listener.local_addr(),
+                                e,
+                                accept_error_delay
+                            );
+                            tokio::time::sleep(accept_error_delay).await;
+                            accept_error_delay =
+                                (accept_error_delay * 2).min(MAXIMUM_ACCEPT_ERROR_DELAY);
+                            continue;
+                        }
+                    };
+                    let acceptor = acceptor.clone();
+                    let logger = new_logger!(&self.logger);
+                    let metrics = Arc::clone(&self.metrics);
+                    let serve = self.connection_server();
+                    // The handshake happens in the spawned task so that a slow
+                    // client cannot block other clients from connecting.
+                    tokio::spawn(async move {
+                        let handshake = async {
+                            conn.set_nodelay(true)?;
+                            acceptor.accept(conn).await
+                        };
+                        match tokio::time::timeout(TLS_CONNECT_TIMEOUT, handshake).await {
+                            Ok(Ok(tls_stream)) => {
+                                debug!(logger, "Accepted TLS connection from {}", addr);
+                                metrics.observe_vault_connection_event(
+                                    ServiceType::Server,
+                                    VaultConnectionEvent::Connected,
+                                );
+                                serve(tls_stream).await;
+                            }
+                            Ok(Err(e)) => {
+                                warn!(logger, "TLS handshake with {} failed: {}", addr, e);
+                                metrics.observe_vault_connection_event(
+                                    ServiceType::Server,
+                                    VaultConnectionEvent::TlsHandshakeFailed,
+                                );
+                            }
+                            Err(_) => {
+                                warn!(logger, "TLS handshake with {} timed out", addr);
+                                metrics.observe_vault_connection_event(
+                                    ServiceType::Server,
+                                    VaultConnectionEvent::TlsHandshakeFailed,
+                                );
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns a function that serves the requests of a client over an
+    /// established connection, so that it can be moved into a `tokio` task.
+    fn connection_server<S>(&self) -> impl FnOnce(S) -> Pin<Box<dyn Future<Output = ()> + Send>>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
         // Wrap data in telegrams with a length header.
         let mut codec_builder = remote_vault_codec_builder();
         codec_builder.max_frame_length(self.max_frame_length);
-
-        // Listen for connections; spawns one `tokio` task per client.
-        loop {
-            let (conn, _addr) = self.listener.accept().await.unwrap_or_else(|e| {
-                panic!(
-                    "Error listening at socket {:?}: {}",
-                    &self.listener.local_addr(),
-                    e
-                )
-            });
-            let local_csp_vault = Arc::clone(&self.local_csp_vault);
-            let thread_pool_handle = self.thread_pool.clone(); // creates a pool handle similar to Arc
-            let codec = ObservableCodec::new(
-                Bincode::default(),
-                CspVaultObserver::new(new_logger!(&self.logger), Arc::clone(&self.metrics)),
-            );
-            tokio::spawn(async move {
+        let local_csp_vault = Arc::clone(&self.local_csp_vault);
+        let thread_pool_handle = self.thread_pool.clone(); // creates a pool handle similar to Arc
+        let codec = ObservableCodec::new(
+            Bincode::default(),
+            CspVaultObserver::new(new_logger!(&self.logger), Arc::clone(&self.metrics)),
+        );
+        move |conn: S| -> Pin<Box<dyn Future<Output = ()> + Send>> {
+            Box::pin(async move {
                 let framed = codec_builder.new_framed(conn);
                 let transport = serde_transport::new(framed, codec);
                 let worker = TarpcCspVaultServerWorker {
@@ -628,7 +764,7 @@ impl<C: CspVault + 'static> TarpcCspVaultServerImpl<C> {
                 let channel_executor =
                     BaseChannel::with_defaults(transport).execute(worker.serve());
                 channel_executor.await;
-            });
+            })
         }
     }
 }
@@ -437,3 +437,179 @@ mod single_call_bincode {
         assert_eq!(bytes_tokio_bincode, bytes_our_bincode);
     }
 }
+
+mod tls {
+    use super::*;
+    use crate::api::CspPublicKeyStore;
+    use crate::vault::api::CspBasicSignatureKeygenError;
+    use crate::vault::remote_csp_vault::tarpc_csp_vault_client::RemoteCspVaultError;
+    use crate::vault::remote_csp_vault::{
+        remote_vault_tls_client_config, remote_vault_tls_server_config, RemoteCspVaultBuilder,
+    };
+    use crate::Csp;
+    use ic_config::crypto::{CryptoConfig, CspVaultType};
+    use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+    use std::net::SocketAddr;
+    use tokio::net::TcpListener;
+
+    const SERVER_NAME: &str = "csp-vault.example";
+
+    struct TestCa(Certificate);
+
+    impl TestCa {
+        fn new() -> Self {
+            let mut params = CertificateParams::new(vec![]);
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            Self(Certificate::from_params(params).unwrap())
+        }
+
+        fn cert_der(&self) -> Vec<u8> {
+            self.0.serialize_der().unwrap()
+        }
+
+        /// Returns a DER-encoded certificate for `name` issued by this CA,
+        /// together with the corresponding DER-encoded private key.
+        fn issue(&self, name: &str) -> (Vec<u8>, Vec<u8>) {
+            let cert =
+                Certificate::from_params(CertificateParams::new(vec![name.to_string()])).unwrap();
+            (
+                cert.serialize_der_with_signer(&self.0).unwrap(),
+                cert.serialize_private_key_der(),
+            )
+        }
+    }
+
+    fn start_tls_server(
+        rt_handle: &tokio::runtime::Handle,
+        server_ca: &TestCa,
+        client_ca: &TestCa,
+    ) -> SocketAddr {
+        let (cert, key) = server_ca.issue(SERVER_NAME);
+        let tls_config = remote_vault_tls_server_config(&client_ca.cert_der(), cert, key).unwrap();
+        let listener = rt_handle
+            .block_on(TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let server_address = listener.local_addr().unwrap();
+        let local_csp_vault = LocalCspVault::builder_for_test().build_into_arc();
+        let server = TarpcCspVaultServerImpl::builder_for_test(local_csp_vault)
+            .build_with_tls(listener, tls_config);
+        rt_handle.spawn(server.run());
+        server_address
+    }
+
+    fn tls_client_builder(
+        rt_handle: &tokio::runtime::Handle,
+        server_address: SocketAddr,
+        server_ca: &TestCa,
+        client_ca: &TestCa,
+    ) -> RemoteCspVaultBuilder {
+        let (cert, key) = client_ca.issue("replica.example");
+        let tls_config = remote_vault_tls_client_config(&server_ca.cert_der(), cert, key).unwrap();
+        RemoteCspVault::builder_with_tls(
+            server_address,
+            SERVER_NAME.to_string(),
+            tls_config,
+            rt_handle.clone(),
+        )
+    }
+
+    #[test]
+    fn should_call_vault_over_mutually_authenticated_tls() {
+        let tokio_rt = new_tokio_runtime();
+        let ca = TestCa::new();
+        let server_address = start_tls_server(tokio_rt.handle(), &ca, &ca);
+
+        let csp_vault = tls_client_builder(tokio_rt.handle(), server_address, &ca, &ca)
+            .build()
+            .expect("Could not create RemoteCspVault");
+
+        assert!(csp_vault.gen_node_signing_key_pair().is_ok());
+    }
+
+    #[test]
+    fn should_fail_to_connect_to_server_with_untrusted_certificate() {
+        let tokio_rt = new_tokio_runtime();
+        let ca = TestCa::new();
+        let untrusted_ca = TestCa::new();
+        let server_address = start_tls_server(tokio_rt.handle(), &untrusted_ca, &ca);
+
+        let result = tls_client_builder(tokio_rt.handle(), server_address, &ca, &ca).build();
+
+        assert_matches!(result.err(), Some(RemoteCspVaultError::TransportError { server_address: address, .. })
+            if address == server_address.to_string()
+        );
+    }
+
+    #[test]
+    fn should_reject_client_with_untrusted_certificate() {
+        let tokio_rt = new_tokio_runtime();
+        let ca = TestCa::new();
+        let untrusted_ca = TestCa::new();
+        let server_address = start_tls_server(tokio_rt.handle(), &ca, &ca);
+
+        // With TLS 1.3 the server verifies the client certificate only after
+        // the client considers the handshake complete, so the connection is
+        // established but no request gets through.
+        let csp_vault = tls_client_builder(tokio_rt.handle(), server_address, &ca, &untrusted_ca)
+            .with_rpc_timeout(Duration::from_secs(1))
+            .build()
+            .expect("Could not create RemoteCspVault");
+
+        assert_matches!(
+            csp_vault.gen_node_signing_key_pair(),
+            Err(CspBasicSignatureKeygenError::TransientInternalError { .. })
+        );
+    }
+
+    #[test]
+    fn should_create_csp_with_tls_vault_from_config() {
+        let tokio_rt = new_tokio_runtime();
+        let ca = TestCa::new();
+        let server_address = start_tls_server(tokio_rt.handle(), &ca, &ca);
+        let (mut config, temp_dir) = CryptoConfig::new_in_temp_dir();
+        let (cert, key) = ca.issue("replica.example");
+        let write = |file_name: &str, contents: &[u8]| {
+            let path = temp_dir.path().join(file_name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+        config.csp_vault_type = CspVaultType::Tls {
+            address: server_address,
+            server_name: SERVER_NAME.to_string(),
+            ca_cert: write("ca.der", &ca.cert_der()),
+            cert: write("client.der", &cert),
+            key: write("client_key.der", &key),
+        };
+
+        let csp = Csp::new(
+            &config,
+            Some(tokio_rt.handle().clone()),
+            None,
+            Arc::new(CryptoMetrics::none()),
+        );
+
+        assert_eq!(csp.idkg_dealing_encryption_pubkeys_count(), Ok(0));
+    }
+
+    #[test]
+    fn should_fail_with_transport_error_for_invalid_server_name() {
+        let tokio_rt = new_tokio_runtime();
+        let ca = TestCa::new();
+        let (cert, key) = ca.issue("replica.example");
+        let tls_config = remote_vault_tls_client_config(&ca.cert_der(), cert, key).unwrap();
+
+        let result = RemoteCspVault::builder_with_tls(
+            "127.0.0.1:1".parse().unwrap(),
+            "not a valid name!".to_string(),
+            tls_config,
+            tokio_rt.handle().clone(),
+        )
+        .build();
+
+        assert_matches!(
+            result.err(),
+            Some(RemoteCspVaultError::TransportError { .. })
+        );
+    }
+}
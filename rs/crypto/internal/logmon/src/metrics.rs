@@ -254,6 +254,21 @@ impl CryptoMetrics {
         }
     }
 
+    /// Observes a change of the state of a connection between the CSP vault
+    /// client and server.
+    pub fn observe_vault_connection_event(
+        &self,
+        service_type: ServiceType,
+        event: VaultConnectionEvent,
+    ) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .crypto_vault_connection_events_total
+                .with_label_values(&[&format!("{}", service_type), &format!("{}", event)])
+                .inc();
+        }
+    }
+
     /// Observes the cache statistics for the verification of threshold BLS12-381 signatures.
     pub fn observe_bls12_381_sig_cache_stats(&self, size: usize, hits: u64, misses: u64) {
         if let Some(metrics) = &self.metrics {
@@ -399,6 +414,20 @@ pub enum MessageType {
     Response,
 }
 
+#[derive(Copy, Clone, Debug, EnumIter, Eq, strum_macros::Display, PartialOrd, Ord, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(test, derive(IntoStaticStr))]
+pub enum VaultConnectionEvent {
+    /// A connection was (re-)established.
+    Connected,
+    /// An established connection was lost.
+    Disconnected,
+    /// An attempt to (re-)establish a connection failed.
+    ConnectFailed,
+    /// The TLS handshake of a connection failed or timed out.
+    TlsHandshakeFailed,
+}
+
 /// Keeps track of the number of node keys. This information is collected and provided to the
 /// metrics component. The type of keys for which the key counts are tracked are the following:
 ///  - `pk_registry`: The number of node public keys (and TLS x.509 certificates) stored
@@ -553,6 +582,11 @@ struct Metrics {
     /// The 'result' label indicates if the result of the operation was an `Ok(_)`
     pub crypto_vault_message_serialization_duration_seconds: HistogramVec,
 
+    /// Counter vector for changes of the state of connections between the CSP vault client and server.
+    /// The 'service_type' label indicates whether the observation is made by the `client` or `server`
+    /// The 'event' label indicates the kind of change, e.g., `connected` or `disconnected`.
+    pub crypto_vault_connection_events_total: IntCounterVec,
+
     /// Metrics for the cache of successfully verified BLS12-381 threshold signatures.
     pub crypto_bls12_381_sig_cache_metrics: bls12_381_sig_cache::Metrics,
 
@@ -614,6 +648,17 @@ impl Metrics {
         for result in KeyRotationResult::iter() {
             rotation_results.with_label_values(&[&format!("{}", result)]);
         }
+        let vault_connection_events = r.int_counter_vec(
+            "crypto_vault_connection_events_total",
+            "Changes of the state of connections between the crypto vault client and server",
+            &["service_type", "event"],
+        );
+        for service_type in ServiceType::iter() {
+            for event in VaultConnectionEvent::iter() {
+                vault_connection_events
+                    .with_label_values(&[&format!("{}", service_type), &format!("{}", event)]);
+            }
+        }
         Self {
             crypto_lock_acquisition_duration_seconds: r.histogram_vec(
                 "crypto_lock_acquisition_duration_seconds",
@@ -677,6 +722,7 @@ impl Metrics {
                 vec![0.000_001, 0.000_01, 0.000_1, 0.001, 0.01, 0.1, 1.0, 10.0],
                 &["service_type", "message_type", "domain", "method_name"],
             ),
+            crypto_vault_connection_events_total: vault_connection_events,
             crypto_bls12_381_sig_cache_metrics: bls12_381_sig_cache::Metrics {
                 cache_size: r.int_gauge(
                     "crypto_bls12_381_sig_cache_size",
@@ -2,9 +2,11 @@ use clap::Parser;
 use ic_adapter_metrics_server::start_metrics_grpc;
 use ic_async_utils::incoming_from_nth_systemd_socket;
 use ic_config::{Config, ConfigSource};
+use ic_crypto_internal_csp::vault::remote_csp_vault::remote_vault_tls_server_config;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_logger::{info, new_replica_logger_from_config};
 use ic_metrics::MetricsRegistry;
+use std::net::SocketAddr;
 use std::os::unix::io::FromRawFd;
 use std::path::PathBuf;
 
@@ -17,13 +19,34 @@ const IC_CRYPTO_CSP_SOCKET_FILENAME: &str = "ic-crypto-csp.socket";
     name = "Remote CspVault server",
     version = "0.1",
     author = "Internet Computer Developers",
-    about = "NOTE: This binary is intended to be started as socket-activated \
-               systemd service with a single socket named ic-crypto-csp.socket"
+    about = "NOTE: Unless --tls-listen-address is given, this binary is intended \
+               to be started as socket-activated systemd service with a single \
+               socket named ic-crypto-csp.socket"
 )]
 struct Opts {
     /// Sets the replica configuration file
     #[clap(long = "replica-config-file", parse(from_os_str))]
     config: PathBuf,
+
+    /// Listens for clients on this TCP address with mutually authenticated
+    /// TLS instead of on the systemd socket
+    #[clap(
+        long = "tls-listen-address",
+        requires_all = &["tls-ca-cert", "tls-cert", "tls-key"]
+    )]
+    tls_listen_address: Option<SocketAddr>,
+
+    /// DER-encoded certificate of the CA that issued the client certificates
+    #[clap(long = "tls-ca-cert", parse(from_os_str))]
+    tls_ca_cert: Option<PathBuf>,
+
+    /// DER-encoded certificate with which the server authenticates
+    #[clap(long = "tls-cert", parse(from_os_str))]
+    tls_cert: Option<PathBuf>,
+
+    /// DER-encoded PKCS#8 private key of the server certificate
+    #[clap(long = "tls-key", parse(from_os_str))]
+    tls_key: Option<PathBuf>,
 }
 
 fn main() {
@@ -38,6 +61,11 @@ fn main() {
 
     let sks_dir = ic_config.crypto.crypto_root.as_path();
 
+    if let Some(tls_listen_address) = opts.tls_listen_address {
+        run_with_tls(rt, &opts, tls_listen_address, &ic_config);
+        return;
+    }
+
     ensure_n_named_systemd_sockets(2);
     let systemd_socket_listener = listener_from_first_systemd_socket(rt.handle().clone());
 
@@ -80,13 +108,60 @@ fn main() {
     }
 
     rt.block_on(ic_crypto_internal_csp::run_csp_vault_server(
-        sks_dir,
+        &ic_config.crypto,
         systemd_socket_listener,
         logger,
         metrics,
     ));
 }
 
+/// Runs the server on a TCP socket with mutually authenticated TLS. Since the
+/// replica is on another host, the metrics are not exported through the
+/// replica's metrics adapter.
+fn run_with_tls(
+    rt: tokio::runtime::Runtime,
+    opts: &Opts,
+    tls_listen_address: SocketAddr,
+    ic_config: &Config,
+) {
+    let read = |path: &Option<PathBuf>| {
+        let path = path.as_ref().expect("required by --tls-listen-address");
+        std::fs::read(path).unwrap_or_else(|e| panic!("failed to read {:?}: {}", path, e))
+    };
+    let tls_config = remote_vault_tls_server_config(
+        &read(&opts.tls_ca_cert),
+        read(&opts.tls_cert),
+        read(&opts.tls_key),
+    )
+    .unwrap_or_else(|e| panic!("invalid TLS configuration: {}", e));
+    let listener = rt
+        .block_on(tokio::net::TcpListener::bind(tls_listen_address))
+        .unwrap_or_else(|e| panic!("failed to listen on {}: {}", tls_listen_address, e));
+
+    // The `AsyncGuard` must be kept in scope for asynchronously logged messages to appear in the logs.
+    let (logger, _async_log_guard) = new_replica_logger_from_config(&ic_config.csp_vault_logger);
+
+    info!(logger;
+        crypto.method_name => "main",
+        crypto.description => format!(
+            "Starting CspVault server listening with TLS at '{}', with SKS-data in '{}' ...",
+            tls_listen_address,
+            ic_config.crypto.crypto_root.display()
+        )
+    );
+
+    abort_on_panic();
+    let metrics = CryptoMetrics::new(Some(&MetricsRegistry::global()));
+
+    rt.block_on(ic_crypto_internal_csp::run_csp_vault_server_with_tls(
+        &ic_config.crypto,
+        listener,
+        tls_config,
+        logger,
+        metrics,
+    ));
+}
+
 /// Aborts the whole program with a core dump if a single thread panics.
 pub fn abort_on_panic() {
     let default_hook = std::panic::take_hook();
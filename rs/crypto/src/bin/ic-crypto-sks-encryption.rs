@@ -0,0 +1,60 @@
+use clap::Parser;
+use ic_config::{Config, ConfigSource};
+use ic_crypto_internal_csp::secret_key_store::key_provider::key_provider_from_config;
+use ic_crypto_internal_csp::vault::local_csp_vault::ProdLocalCspVault;
+use ic_logger::{info, new_replica_logger_from_config};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[clap(
+    name = "Secret key store encryption",
+    version = "0.1",
+    author = "Internet Computer Developers",
+    about = "Encrypts the secret key stores in the crypto root with the current \
+               key-encryption key, or re-encrypts them after the key was rotated. \
+               NOTE: The CspVault server and the replica must not be running."
+)]
+struct Opts {
+    /// Sets the replica configuration file
+    #[clap(long = "replica-config-file", parse(from_os_str))]
+    config: PathBuf,
+}
+
+fn main() {
+    let opts = Opts::parse();
+    let ic_config = get_ic_config(opts.config);
+    let crypto_config = &ic_config.crypto;
+
+    let key_provider = key_provider_from_config(crypto_config).unwrap_or_else(|| {
+        eprintln!("secret_key_store_encryption_key_dir is not set in the crypto config");
+        std::process::exit(1);
+    });
+
+    // The `AsyncGuard` must be kept in scope for asynchronously logged messages to appear in the logs.
+    let (logger, _async_log_guard) = new_replica_logger_from_config(&ic_config.csp_vault_logger);
+    info!(
+        logger,
+        "Encrypting secret key stores in '{}'",
+        crypto_config.crypto_root.display()
+    );
+
+    if let Err(e) = ProdLocalCspVault::encrypt_secret_key_stores_in_dir(
+        &crypto_config.crypto_root,
+        key_provider,
+        &logger,
+    ) {
+        eprintln!("failed to encrypt secret key stores: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn get_ic_config(replica_config_file: PathBuf) -> Config {
+    let tmpdir = tempfile::Builder::new()
+        .prefix("ic_config")
+        .tempdir()
+        .expect("failed to create temporary directory for replica config")
+        .path()
+        .to_path_buf();
+
+    Config::load_with_tmpdir(ConfigSource::File(replica_config_file), tmpdir)
+}
@@ -0,0 +1,549 @@
+//! Verification of the authentication part of an HTTP request envelope
+//!
+//! The replica validates requests with the same code (see the `ic-validator`
+//! crate), so services outside of the Internet Computer authenticate IC users
+//! exactly like the replica does. See the
+//! [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication).
+use crate::sign_utils::{
+    ecdsa_p256_signature_from_der_bytes, rsa_signature_from_bytes, user_public_key_from_bytes,
+    KeyBytesContentType,
+};
+use ic_crypto_interfaces_sig_verification::{BasicSigVerifierByPublicKey, CanisterSigVerifier};
+use ic_types::crypto::threshold_sig::{IcRootOfTrust, RootOfTrustProvider};
+use ic_types::crypto::{
+    AlgorithmId, BasicSig, BasicSigOf, CanisterSig, CanisterSigOf, CryptoError, CryptoResult,
+    Signable, UserPublicKey,
+};
+use ic_types::messages::{
+    Delegation, SignedDelegation, UserSignature, WebAuthnEnvelope, WebAuthnSignature,
+};
+use ic_types::{CanisterId, PrincipalId, Time, UserId};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+/// Maximum number of delegations allowed in an envelope.
+/// Envelopes having more delegations will be declared invalid without further verifying whether
+/// the delegation chain is correctly signed.
+/// **Note**: this limit is currently more generous than the one in the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication),
+/// which specifies a maximum of 4 delegations, in order to prevent potentially breaking already deployed applications
+/// since this limit was before (wrongly) not enforced.
+/// This limit will be tightened up once the number of delegations can be observed (via metrics or logs),
+/// see CRP-1961.
+pub const MAXIMUM_NUMBER_OF_DELEGATIONS: usize = 20;
+
+/// Maximum number of targets (collection of `CanisterId`s) that can be specified in a
+/// single delegation. Envelopes having a single delegation with more targets will be declared
+/// invalid without any further verification.
+/// **Note**: this limit part of the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication)
+/// and so changing this value might be breaking or result in a deviation from the specification.
+pub const MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION: usize = 1_000;
+
+/// The signature verification that [`verify_envelope_with`] needs for an
+/// envelope authenticating a message of type `M`.
+///
+/// Implemented by the crypto component, see `IngressSigVerifier`, and by
+/// [`StandaloneSigVerifier`].
+pub trait EnvelopeSigVerifier<M: Signable>:
+    BasicSigVerifierByPublicKey<M>
+    + BasicSigVerifierByPublicKey<Delegation>
+    + BasicSigVerifierByPublicKey<WebAuthnEnvelope>
+    + CanisterSigVerifier<M>
+    + CanisterSigVerifier<Delegation>
+{
+}
+
+impl<M: Signable, T> EnvelopeSigVerifier<M> for T where
+    T: BasicSigVerifierByPublicKey<M>
+        + BasicSigVerifierByPublicKey<Delegation>
+        + BasicSigVerifierByPublicKey<WebAuthnEnvelope>
+        + CanisterSigVerifier<M>
+        + CanisterSigVerifier<Delegation>
+        + ?Sized
+{
+}
+
+/// Verifies signatures with the functions of this crate, without any
+/// registry access or metrics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StandaloneSigVerifier;
+
+impl<T: Signable> BasicSigVerifierByPublicKey<T> for StandaloneSigVerifier {
+    fn verify_basic_sig_by_public_key(
+        &self,
+        signature: &BasicSigOf<T>,
+        signed_bytes: &T,
+        public_key: &UserPublicKey,
+    ) -> CryptoResult<()> {
+        crate::verify_basic_sig_by_public_key(
+            public_key.algorithm_id,
+            &signed_bytes.as_signed_bytes(),
+            &signature.get_ref().0,
+            &public_key.key,
+        )
+    }
+}
+
+impl<T: Signable> CanisterSigVerifier<T> for StandaloneSigVerifier {
+    fn verify_canister_sig(
+        &self,
+        signature: &CanisterSigOf<T>,
+        signed_bytes: &T,
+        public_key: &UserPublicKey,
+        root_of_trust: &IcRootOfTrust,
+    ) -> CryptoResult<()> {
+        if public_key.algorithm_id != AlgorithmId::IcCanisterSignature {
+            return Err(CryptoError::AlgorithmNotSupported {
+                algorithm: public_key.algorithm_id,
+                reason: format!("Expected {:?}", AlgorithmId::IcCanisterSignature),
+            });
+        }
+        crate::verify_canister_sig(
+            &signed_bytes.as_signed_bytes(),
+            &signature.get_ref().0,
+            &public_key.key,
+            root_of_trust,
+        )
+    }
+}
+
+/// Provides the root of trust given to [`verify_envelope`], if any.
+struct GivenRootOfTrust<'a>(Option<&'a IcRootOfTrust>);
+
+impl RootOfTrustProvider for GivenRootOfTrust<'_> {
+    type Error = String;
+
+    fn root_of_trust(&self) -> Result<IcRootOfTrust, Self::Error> {
+        self.0
+            .copied()
+            .ok_or_else(|| "no root of trust given to verify canister signatures".to_string())
+    }
+}
+
+/// The canister IDs that a verified envelope is allowed to target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DelegationTargets {
+    /// None of the delegations restricts the targets.
+    All,
+    /// The canister IDs that are common to all delegations.
+    Some(BTreeSet<CanisterId>),
+}
+
+impl DelegationTargets {
+    pub fn contains(&self, canister_id: &CanisterId) -> bool {
+        match self {
+            DelegationTargets::All => true,
+            DelegationTargets::Some(set) => set.contains(canister_id),
+        }
+    }
+
+    fn intersect(self, other: Self) -> Self {
+        match (self, other) {
+            (DelegationTargets::All, other) => other,
+            (me, DelegationTargets::All) => me,
+            (DelegationTargets::Some(set1), DelegationTargets::Some(set2)) => {
+                DelegationTargets::Some(set1.intersection(&set2).cloned().collect())
+            }
+        }
+    }
+}
+
+/// Error in verifying an envelope with [`verify_envelope`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvelopeVerificationError {
+    InvalidDelegationExpiry(String),
+    UserIdDoesNotMatchPublicKey(UserId, Vec<u8>),
+    InvalidSignature(AuthenticationError),
+    InvalidDelegation(AuthenticationError),
+    MissingSignature(UserId),
+    AnonymousSignatureNotAllowed,
+}
+
+impl fmt::Display for EnvelopeVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeVerificationError::InvalidDelegationExpiry(msg) => write!(f, "{}", msg),
+            EnvelopeVerificationError::UserIdDoesNotMatchPublicKey(user_id, pubkey) => write!(
+                f,
+                "The user id {} does not match the public key {}",
+                user_id,
+                hex::encode(pubkey)
+            ),
+            EnvelopeVerificationError::InvalidSignature(err) => {
+                write!(f, "Invalid signature: {}", err)
+            }
+            EnvelopeVerificationError::InvalidDelegation(err) => {
+                write!(f, "Invalid delegation: {}", err)
+            }
+            EnvelopeVerificationError::MissingSignature(user_id) => {
+                write!(f, "Missing signature from user: {}", user_id)
+            }
+            EnvelopeVerificationError::AnonymousSignatureNotAllowed => {
+                write!(f, "Signature is not allowed for the anonymous user")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeVerificationError {}
+
+/// Error in verifying a signature or a delegation of an envelope.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthenticationError {
+    InvalidBasicSignature(CryptoError),
+    InvalidCanisterSignature(String),
+    InvalidPublicKey(CryptoError),
+    WebAuthnError(String),
+    DelegationTargetError(String),
+    DelegationTooLongError { length: usize, maximum: usize },
+    DelegationContainsCyclesError { public_key: Vec<u8> },
+}
+
+impl fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthenticationError::InvalidBasicSignature(err) => {
+                write!(f, "Invalid basic signature: {}", err)
+            }
+            AuthenticationError::InvalidCanisterSignature(err) => {
+                write!(f, "Invalid canister signature: {}", err)
+            }
+            AuthenticationError::InvalidPublicKey(err) => write!(f, "Invalid public key: {}", err),
+            AuthenticationError::WebAuthnError(msg) => write!(f, "{}", msg),
+            AuthenticationError::DelegationTargetError(msg) => write!(f, "{}", msg),
+            AuthenticationError::DelegationTooLongError { length, maximum } => write!(
+                f,
+                "Chain of delegations is too long: got {} delegations, but at most {} are allowed",
+                length, maximum
+            ),
+            AuthenticationError::DelegationContainsCyclesError { public_key } => write!(
+                f,
+                "Chain of delegations contains at least one cycle: first repeating public key encountered {}",
+                hex::encode(public_key)
+            ),
+        }
+    }
+}
+
+/// Verifies the authentication part of an envelope sent by `sender`.
+///
+/// If valid, returns the canister IDs that are common to all delegations.
+///
+/// The envelope is valid iff
+/// * the sender is anonymous and there is no `signature`, or
+/// * the sender is not anonymous and
+///     * `sender` is the self-authenticating ID of `signature.signer_pubkey`,
+///     * there are at most [`MAXIMUM_NUMBER_OF_DELEGATIONS`] delegations,
+///       each with at most [`MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION`] targets,
+///     * none of the delegations has expired relative to `current_time`,
+///     * the delegations form a chain rooted at `signature.signer_pubkey`
+///       that is correctly signed and does not contain any cycle, and
+///     * `signature.signature` is a valid signature on `message` by the
+///       last public key of the chain.
+///
+/// Ed25519, ECDSA P-256, ECDSA secp256k1, canister signatures and WebAuthn
+/// (ECDSA P-256, RSA PKCS #1 v1.5 and Ed25519) are supported. RSA signatures
+/// are only accepted on delegations or within WebAuthn. Canister signatures
+/// are verified with respect to `root_of_trust`, and are rejected if none is
+/// given.
+///
+/// Note that it is up to the caller to check the expiry of the message
+/// itself and whether the targeted canister is among the returned targets.
+pub fn verify_envelope<S: Signable>(
+    sender: &UserId,
+    message: &S,
+    signature: Option<&UserSignature>,
+    current_time: Time,
+    root_of_trust: Option<&IcRootOfTrust>,
+) -> Result<DelegationTargets, EnvelopeVerificationError> {
+    verify_envelope_with(
+        &StandaloneSigVerifier,
+        sender,
+        message,
+        signature,
+        current_time,
+        &GivenRootOfTrust(root_of_trust),
+    )
+}
+
+/// Verifies the authentication part of an envelope like [`verify_envelope`],
+/// but with the signature verification of `verifier`.
+///
+/// The `root_of_trust_provider` is only queried if a canister signature is
+/// involved.
+pub fn verify_envelope_with<V, S, R>(
+    verifier: &V,
+    sender: &UserId,
+    message: &S,
+    signature: Option<&UserSignature>,
+    current_time: Time,
+    root_of_trust_provider: &R,
+) -> Result<DelegationTargets, EnvelopeVerificationError>
+where
+    V: EnvelopeSigVerifier<S> + ?Sized,
+    S: Signable,
+    R: RootOfTrustProvider,
+    R::Error: fmt::Display,
+{
+    let signature = match signature {
+        None if sender.get().is_anonymous() => return Ok(DelegationTargets::All),
+        None => return Err(EnvelopeVerificationError::MissingSignature(*sender)),
+        Some(_) if sender.get().is_anonymous() => {
+            return Err(EnvelopeVerificationError::AnonymousSignatureNotAllowed)
+        }
+        Some(signature) => signature,
+    };
+    if sender.get_ref() != &PrincipalId::new_self_authenticating(&signature.signer_pubkey) {
+        return Err(EnvelopeVerificationError::UserIdDoesNotMatchPublicKey(
+            *sender,
+            signature.signer_pubkey.clone(),
+        ));
+    }
+
+    let delegations = signature.sender_delegation.as_deref().unwrap_or_default();
+    if delegations.len() > MAXIMUM_NUMBER_OF_DELEGATIONS {
+        return Err(EnvelopeVerificationError::InvalidDelegation(
+            AuthenticationError::DelegationTooLongError {
+                length: delegations.len(),
+                maximum: MAXIMUM_NUMBER_OF_DELEGATIONS,
+            },
+        ));
+    }
+    for delegation in delegations {
+        let expiry = delegation.delegation().expiration();
+        if expiry < current_time {
+            return Err(EnvelopeVerificationError::InvalidDelegationExpiry(format!(
+                "Specified sender delegation has expired:\n\
+                 Provided expiry:    {}\n\
+                 Local replica time: {}",
+                expiry, current_time,
+            )));
+        }
+    }
+
+    let (pubkey, targets) = verify_delegations(
+        verifier,
+        delegations,
+        &signature.signer_pubkey,
+        root_of_trust_provider,
+    )?;
+
+    let (pk, pk_type) = user_public_key_from_bytes(pubkey)
+        .map_err(AuthenticationError::InvalidPublicKey)
+        .map_err(EnvelopeVerificationError::InvalidSignature)?;
+    if pk_type == KeyBytesContentType::RsaSha256PublicKeyDer {
+        return Err(EnvelopeVerificationError::InvalidSignature(
+            AuthenticationError::InvalidBasicSignature(CryptoError::AlgorithmNotSupported {
+                algorithm: AlgorithmId::RsaSha256,
+                reason: "RSA signatures are not allowed except in webauthn context".to_owned(),
+            }),
+        ));
+    }
+    verify_signature(
+        verifier,
+        message,
+        &signature.signature,
+        &pk,
+        pk_type,
+        root_of_trust_provider,
+    )
+    .map_err(EnvelopeVerificationError::InvalidSignature)?;
+
+    Ok(targets)
+}
+
+// Verifies a chain of delegations rooted at `sender_pubkey`.
+//
+// If the delegations are valid, returns the public key that is expected to
+// sign the message as well as the canister IDs it is valid for.
+fn verify_delegations<'a, V, R>(
+    verifier: &V,
+    delegations: &'a [SignedDelegation],
+    sender_pubkey: &'a [u8],
+    root_of_trust_provider: &R,
+) -> Result<(&'a [u8], DelegationTargets), EnvelopeVerificationError>
+where
+    V: BasicSigVerifierByPublicKey<Delegation>
+        + BasicSigVerifierByPublicKey<WebAuthnEnvelope>
+        + CanisterSigVerifier<Delegation>
+        + ?Sized,
+    R: RootOfTrustProvider,
+    R::Error: fmt::Display,
+{
+    let mut observed_public_keys = HashSet::with_capacity(delegations.len() + 1);
+    observed_public_keys.insert(sender_pubkey);
+    for delegation in delegations {
+        let public_key = delegation.delegation().pubkey();
+        if !observed_public_keys.insert(public_key) {
+            return Err(EnvelopeVerificationError::InvalidDelegation(
+                AuthenticationError::DelegationContainsCyclesError {
+                    public_key: public_key.clone(),
+                },
+            ));
+        }
+    }
+    for delegation in delegations {
+        match delegation.delegation().number_of_targets() {
+            Some(number_of_targets)
+                if number_of_targets > MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION =>
+            {
+                return Err(EnvelopeVerificationError::InvalidDelegation(
+                    AuthenticationError::DelegationTargetError(format!(
+                        "expected at most {} targets per delegation, but got {}",
+                        MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION, number_of_targets
+                    )),
+                ));
+            }
+            _ => (),
+        }
+    }
+
+    let mut pubkey = sender_pubkey;
+    let mut targets = DelegationTargets::All;
+    for signed_delegation in delegations {
+        let delegation = signed_delegation.delegation();
+        let new_targets = verify_delegation(
+            verifier,
+            delegation,
+            signed_delegation.signature(),
+            pubkey,
+            root_of_trust_provider,
+        )
+        .map_err(EnvelopeVerificationError::InvalidDelegation)?;
+        targets = targets.intersect(new_targets);
+        pubkey = delegation.pubkey().as_slice();
+    }
+    Ok((pubkey, targets))
+}
+
+fn verify_delegation<V, R>(
+    verifier: &V,
+    delegation: &Delegation,
+    signature: &[u8],
+    pubkey: &[u8],
+    root_of_trust_provider: &R,
+) -> Result<DelegationTargets, AuthenticationError>
+where
+    V: BasicSigVerifierByPublicKey<Delegation>
+        + BasicSigVerifierByPublicKey<WebAuthnEnvelope>
+        + CanisterSigVerifier<Delegation>
+        + ?Sized,
+    R: RootOfTrustProvider,
+    R::Error: fmt::Display,
+{
+    let (pk, pk_type) =
+        user_public_key_from_bytes(pubkey).map_err(AuthenticationError::InvalidPublicKey)?;
+    verify_signature(
+        verifier,
+        delegation,
+        signature,
+        &pk,
+        pk_type,
+        root_of_trust_provider,
+    )?;
+
+    match delegation
+        .targets()
+        .map_err(AuthenticationError::DelegationTargetError)?
+    {
+        None => Ok(DelegationTargets::All),
+        Some(targets) => Ok(DelegationTargets::Some(targets)),
+    }
+}
+
+fn verify_signature<V, S, R>(
+    verifier: &V,
+    message: &S,
+    signature: &[u8],
+    pk: &UserPublicKey,
+    pk_type: KeyBytesContentType,
+    root_of_trust_provider: &R,
+) -> Result<(), AuthenticationError>
+where
+    V: BasicSigVerifierByPublicKey<S>
+        + BasicSigVerifierByPublicKey<WebAuthnEnvelope>
+        + CanisterSigVerifier<S>
+        + ?Sized,
+    S: Signable,
+    R: RootOfTrustProvider,
+    R::Error: fmt::Display,
+{
+    match pk_type {
+        KeyBytesContentType::EcdsaP256PublicKeyDerWrappedCose
+        | KeyBytesContentType::RsaSha256PublicKeyDerWrappedCose
+        | KeyBytesContentType::Ed25519PublicKeyDerWrappedCose => {
+            let webauthn_sig = WebAuthnSignature::try_from(signature)
+                .map_err(AuthenticationError::WebAuthnError)?;
+            verify_webauthn_sig(verifier, &webauthn_sig, message, pk)
+                .map_err(AuthenticationError::WebAuthnError)
+        }
+        KeyBytesContentType::Ed25519PublicKeyDer
+        | KeyBytesContentType::EcdsaP256PublicKeyDer
+        | KeyBytesContentType::EcdsaSecp256k1PublicKeyDer
+        | KeyBytesContentType::RsaSha256PublicKeyDer => {
+            let basic_sig = BasicSigOf::from(BasicSig(signature.to_vec()));
+            verifier
+                .verify_basic_sig_by_public_key(&basic_sig, message, pk)
+                .map_err(AuthenticationError::InvalidBasicSignature)
+        }
+        KeyBytesContentType::IcCanisterSignatureAlgPublicKeyDer => {
+            let canister_sig = CanisterSigOf::from(CanisterSig(signature.to_vec()));
+            let root_of_trust = root_of_trust_provider
+                .root_of_trust()
+                .map_err(|e| AuthenticationError::InvalidCanisterSignature(e.to_string()))?;
+            verifier
+                .verify_canister_sig(&canister_sig, message, pk, &root_of_trust)
+                .map_err(|e| AuthenticationError::InvalidCanisterSignature(e.to_string()))
+        }
+    }
+}
+
+/// Verifies that `webauthn_sig` is a valid WebAuthn signature by `pk` whose
+/// challenge is `message`.
+pub fn verify_webauthn_sig<V, S>(
+    verifier: &V,
+    webauthn_sig: &WebAuthnSignature,
+    message: &S,
+    pk: &UserPublicKey,
+) -> Result<(), String>
+where
+    V: BasicSigVerifierByPublicKey<WebAuthnEnvelope> + ?Sized,
+    S: Signable,
+{
+    let basic_sig = match pk.algorithm_id {
+        // ECDSA signatures are DER wrapped, whereas RSA and EdDSA signatures are not,
+        // see https://www.w3.org/TR/webauthn-2/#sctn-signature-attestation-types
+        AlgorithmId::EcdsaP256 => ecdsa_p256_signature_from_der_bytes(&webauthn_sig.signature().0)
+            .map_err(|e| format!("Failed to parse EcdsaP256 signature: {}", e))?,
+        AlgorithmId::RsaSha256 => rsa_signature_from_bytes(&webauthn_sig.signature()),
+        AlgorithmId::Ed25519 => BasicSig(webauthn_sig.signature().0.clone()),
+        algorithm_id => {
+            return Err(format!(
+                "Only ECDSA on curve P-256, RSA PKCS #1 v1.5 and Ed25519 are supported for WebAuthn, given: {:?}",
+                algorithm_id
+            ))
+        }
+    };
+    let envelope = WebAuthnEnvelope::try_from(webauthn_sig)
+        .map_err(|err| format!("WebAuthn envelope creation failed: {}", err))?;
+
+    verifier
+        .verify_basic_sig_by_public_key(&BasicSigOf::from(basic_sig.clone()), &envelope, pk)
+        .map_err(|e| {
+            format!(
+                "Verifying signature failed. signature: {:?}; envelope: {:?}; public_key: {}. Error: {}",
+                basic_sig, envelope, pk, e
+            )
+        })?;
+
+    // The challenge in the webauthn envelope must match signed bytes.
+    let signed_bytes = message.as_signed_bytes();
+    if envelope.challenge() != signed_bytes {
+        Err(format!(
+            "Challenge in webauthn is {:?} while it is expected to be {:?}",
+            envelope.challenge(),
+            signed_bytes,
+        ))
+    } else {
+        Ok(())
+    }
+}
@@ -17,6 +17,7 @@ pub enum KeyBytesContentType {
     RsaSha256PublicKeyDer,
     EcdsaP256PublicKeyDerWrappedCose,
     RsaSha256PublicKeyDerWrappedCose,
+    Ed25519PublicKeyDerWrappedCose,
     IcCanisterSignatureAlgPublicKeyDer,
 }
 
@@ -24,6 +25,7 @@ fn cose_key_bytes_content_type(alg_id: AlgorithmId) -> Option<KeyBytesContentTyp
     match alg_id {
         AlgorithmId::EcdsaP256 => Some(KeyBytesContentType::EcdsaP256PublicKeyDerWrappedCose),
         AlgorithmId::RsaSha256 => Some(KeyBytesContentType::RsaSha256PublicKeyDerWrappedCose),
+        AlgorithmId::Ed25519 => Some(KeyBytesContentType::Ed25519PublicKeyDerWrappedCose),
         _ => None,
     }
 }
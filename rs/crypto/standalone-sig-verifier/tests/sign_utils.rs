@@ -75,6 +75,24 @@ fn should_fail_parse_raw_ed25519_pk() {
     assert!(result.is_err());
 }
 
+#[test]
+fn should_correctly_parse_der_wrapped_cose_ed25519_pk() {
+    let pk_raw = hex::decode(test_data::ED25519_PK_1_RFC8032_HEX).unwrap();
+    let mut pk_cose = hex::decode("a4010103272006215820").unwrap();
+    pk_cose.extend_from_slice(&pk_raw);
+    let pk_der =
+        subject_public_key_info_der(oid!(1, 3, 6, 1, 4, 1, 56387, 1, 1), &pk_cose).unwrap();
+
+    let (pk, bytes_type) = user_public_key_from_bytes(&pk_der).unwrap();
+
+    assert_eq!(pk.algorithm_id, AlgorithmId::Ed25519);
+    assert_eq!(pk.key, pk_raw);
+    assert_eq!(
+        bytes_type,
+        KeyBytesContentType::Ed25519PublicKeyDerWrappedCose
+    );
+}
+
 #[test]
 fn should_correctly_parse_der_encoded_openssl_ecdsa_p256_pk() {
     let rng = &mut ReproducibleRng::new();
@@ -0,0 +1,448 @@
+#![allow(clippy::unwrap_used)]
+use assert_matches::assert_matches;
+use ic_crypto_internal_basic_sig_der_utils::subject_public_key_info_der;
+use ic_crypto_internal_basic_sig_ed25519 as ed25519;
+use ic_crypto_standalone_sig_verifier::{
+    ed25519_public_key_to_der, verify_envelope, AuthenticationError, DelegationTargets,
+    EnvelopeVerificationError, MAXIMUM_NUMBER_OF_DELEGATIONS,
+};
+use ic_crypto_test_utils_reproducible_rng::ReproducibleRng;
+use ic_types::crypto::Signable;
+use ic_types::messages::{
+    Blob, Delegation, MessageId, SignedDelegation, UserSignature, WebAuthnSignature,
+};
+use ic_types::{CanisterId, PrincipalId, Time, UserId};
+use simple_asn1::oid;
+use std::collections::BTreeSet;
+
+const CURRENT_TIME: Time = Time::from_nanos_since_unix_epoch(1_000_000);
+const DELEGATION_EXPIRY: Time = Time::from_nanos_since_unix_epoch(2_000_000);
+
+#[test]
+fn should_accept_anonymous_sender_without_signature() {
+    let result = verify_envelope(
+        &UserId::from(PrincipalId::new_anonymous()),
+        &message_id(),
+        None,
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(result, Ok(DelegationTargets::All));
+}
+
+#[test]
+fn should_reject_anonymous_sender_with_signature() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+    let signature = UserSignature {
+        signature: key.sign(&message_id()),
+        signer_pubkey: key.public_key_der(),
+        sender_delegation: None,
+    };
+
+    let result = verify_envelope(
+        &UserId::from(PrincipalId::new_anonymous()),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(
+        result,
+        Err(EnvelopeVerificationError::AnonymousSignatureNotAllowed)
+    );
+}
+
+#[test]
+fn should_reject_missing_signature() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+
+    let result = verify_envelope(&key.user_id(), &message_id(), None, CURRENT_TIME, None);
+
+    assert_eq!(
+        result,
+        Err(EnvelopeVerificationError::MissingSignature(key.user_id()))
+    );
+}
+
+#[test]
+fn should_accept_valid_signature_without_delegations() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+    let signature = UserSignature {
+        signature: key.sign(&message_id()),
+        signer_pubkey: key.public_key_der(),
+        sender_delegation: None,
+    };
+
+    let result = verify_envelope(
+        &key.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(result, Ok(DelegationTargets::All));
+}
+
+#[test]
+fn should_reject_sender_that_does_not_match_public_key() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+    let other_key = Ed25519Key::new(rng);
+    let signature = UserSignature {
+        signature: key.sign(&message_id()),
+        signer_pubkey: key.public_key_der(),
+        sender_delegation: None,
+    };
+
+    let result = verify_envelope(
+        &other_key.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(
+        result,
+        Err(EnvelopeVerificationError::UserIdDoesNotMatchPublicKey(
+            other_key.user_id(),
+            key.public_key_der()
+        ))
+    );
+}
+
+#[test]
+fn should_reject_invalid_signature() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+    let other_key = Ed25519Key::new(rng);
+    let signature = UserSignature {
+        signature: other_key.sign(&message_id()),
+        signer_pubkey: key.public_key_der(),
+        sender_delegation: None,
+    };
+
+    let result = verify_envelope(
+        &key.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_matches!(
+        result,
+        Err(EnvelopeVerificationError::InvalidSignature(
+            AuthenticationError::InvalidBasicSignature(_)
+        ))
+    );
+}
+
+#[test]
+fn should_return_intersection_of_delegation_targets() {
+    let rng = &mut ReproducibleRng::new();
+    let [sender, session_1, session_2] = [(); 3].map(|_| Ed25519Key::new(rng));
+    let delegations = vec![
+        sender.delegate_to(
+            &session_1,
+            Some(vec![CanisterId::from_u64(1), CanisterId::from_u64(2)]),
+        ),
+        session_1.delegate_to(
+            &session_2,
+            Some(vec![CanisterId::from_u64(2), CanisterId::from_u64(3)]),
+        ),
+    ];
+    let signature = UserSignature {
+        signature: session_2.sign(&message_id()),
+        signer_pubkey: sender.public_key_der(),
+        sender_delegation: Some(delegations),
+    };
+
+    let result = verify_envelope(
+        &sender.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(
+        result,
+        Ok(DelegationTargets::Some(BTreeSet::from([
+            CanisterId::from_u64(2)
+        ])))
+    );
+}
+
+#[test]
+fn should_reject_signature_by_sender_when_delegating() {
+    let rng = &mut ReproducibleRng::new();
+    let [sender, session] = [(); 2].map(|_| Ed25519Key::new(rng));
+    let signature = UserSignature {
+        signature: sender.sign(&message_id()),
+        signer_pubkey: sender.public_key_der(),
+        sender_delegation: Some(vec![sender.delegate_to(&session, None)]),
+    };
+
+    let result = verify_envelope(
+        &sender.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_matches!(
+        result,
+        Err(EnvelopeVerificationError::InvalidSignature(
+            AuthenticationError::InvalidBasicSignature(_)
+        ))
+    );
+}
+
+#[test]
+fn should_reject_expired_delegation() {
+    let rng = &mut ReproducibleRng::new();
+    let [sender, session] = [(); 2].map(|_| Ed25519Key::new(rng));
+    let signature = UserSignature {
+        signature: session.sign(&message_id()),
+        signer_pubkey: sender.public_key_der(),
+        sender_delegation: Some(vec![sender.delegate_to(&session, None)]),
+    };
+
+    let result = verify_envelope(
+        &sender.user_id(),
+        &message_id(),
+        Some(&signature),
+        Time::from_nanos_since_unix_epoch(DELEGATION_EXPIRY.as_nanos_since_unix_epoch() + 1),
+        None,
+    );
+
+    assert_matches!(
+        result,
+        Err(EnvelopeVerificationError::InvalidDelegationExpiry(_))
+    );
+}
+
+#[test]
+fn should_reject_delegation_with_invalid_signature() {
+    let rng = &mut ReproducibleRng::new();
+    let [sender, session, other] = [(); 3].map(|_| Ed25519Key::new(rng));
+    let signature = UserSignature {
+        signature: session.sign(&message_id()),
+        signer_pubkey: sender.public_key_der(),
+        sender_delegation: Some(vec![other.delegate_to(&session, None)]),
+    };
+
+    let result = verify_envelope(
+        &sender.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_matches!(
+        result,
+        Err(EnvelopeVerificationError::InvalidDelegation(
+            AuthenticationError::InvalidBasicSignature(_)
+        ))
+    );
+}
+
+#[test]
+fn should_reject_delegations_with_cycle() {
+    let rng = &mut ReproducibleRng::new();
+    let [sender, session] = [(); 2].map(|_| Ed25519Key::new(rng));
+    let signature = UserSignature {
+        signature: sender.sign(&message_id()),
+        signer_pubkey: sender.public_key_der(),
+        sender_delegation: Some(vec![
+            sender.delegate_to(&session, None),
+            session.delegate_to(&sender, None),
+        ]),
+    };
+
+    let result = verify_envelope(
+        &sender.user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(
+        result,
+        Err(EnvelopeVerificationError::InvalidDelegation(
+            AuthenticationError::DelegationContainsCyclesError {
+                public_key: sender.public_key_der()
+            }
+        ))
+    );
+}
+
+#[test]
+fn should_reject_too_many_delegations() {
+    let rng = &mut ReproducibleRng::new();
+    let keys: Vec<_> = (0..=MAXIMUM_NUMBER_OF_DELEGATIONS + 1)
+        .map(|_| Ed25519Key::new(rng))
+        .collect();
+    let delegations: Vec<_> = keys
+        .windows(2)
+        .map(|pair| pair[0].delegate_to(&pair[1], None))
+        .collect();
+    let signature = UserSignature {
+        signature: keys.last().unwrap().sign(&message_id()),
+        signer_pubkey: keys[0].public_key_der(),
+        sender_delegation: Some(delegations),
+    };
+
+    let result = verify_envelope(
+        &keys[0].user_id(),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(
+        result,
+        Err(EnvelopeVerificationError::InvalidDelegation(
+            AuthenticationError::DelegationTooLongError {
+                length: MAXIMUM_NUMBER_OF_DELEGATIONS + 1,
+                maximum: MAXIMUM_NUMBER_OF_DELEGATIONS,
+            }
+        ))
+    );
+}
+
+#[test]
+fn should_accept_webauthn_signature_with_cose_ed25519_key() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+    let signature = UserSignature {
+        signature: key.sign_webauthn(&message_id().as_signed_bytes()),
+        signer_pubkey: key.public_key_cose_der(),
+        sender_delegation: None,
+    };
+
+    let result = verify_envelope(
+        &UserId::from(PrincipalId::new_self_authenticating(
+            &key.public_key_cose_der(),
+        )),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_eq!(result, Ok(DelegationTargets::All));
+}
+
+#[test]
+fn should_reject_webauthn_signature_on_wrong_challenge() {
+    let rng = &mut ReproducibleRng::new();
+    let key = Ed25519Key::new(rng);
+    let signature = UserSignature {
+        signature: key.sign_webauthn(b"some other challenge"),
+        signer_pubkey: key.public_key_cose_der(),
+        sender_delegation: None,
+    };
+
+    let result = verify_envelope(
+        &UserId::from(PrincipalId::new_self_authenticating(
+            &key.public_key_cose_der(),
+        )),
+        &message_id(),
+        Some(&signature),
+        CURRENT_TIME,
+        None,
+    );
+
+    assert_matches!(
+        result,
+        Err(EnvelopeVerificationError::InvalidSignature(
+            AuthenticationError::WebAuthnError(msg)
+        )) if msg.starts_with("Challenge in webauthn is")
+    );
+}
+
+fn message_id() -> MessageId {
+    MessageId::from([42; 32])
+}
+
+struct Ed25519Key {
+    sk: ed25519::types::SecretKeyBytes,
+    pk: ed25519::types::PublicKeyBytes,
+}
+
+impl Ed25519Key {
+    fn new(rng: &mut ReproducibleRng) -> Self {
+        let (sk, pk) = ed25519::keypair_from_rng(rng);
+        Self { sk, pk }
+    }
+
+    fn public_key_der(&self) -> Vec<u8> {
+        ed25519_public_key_to_der(self.pk.0.to_vec()).unwrap()
+    }
+
+    fn public_key_cose_der(&self) -> Vec<u8> {
+        // COSE map with kty = OKP, alg = EdDSA, crv = Ed25519 and x = <key>
+        let mut pk_cose = hex::decode("a4010103272006215820").unwrap();
+        pk_cose.extend_from_slice(&self.pk.0);
+        subject_public_key_info_der(oid!(1, 3, 6, 1, 4, 1, 56387, 1, 1), &pk_cose).unwrap()
+    }
+
+    fn user_id(&self) -> UserId {
+        UserId::from(PrincipalId::new_self_authenticating(&self.public_key_der()))
+    }
+
+    fn sign<S: Signable>(&self, message: &S) -> Vec<u8> {
+        ed25519::sign(&message.as_signed_bytes(), &self.sk)
+            .unwrap()
+            .0
+            .to_vec()
+    }
+
+    fn sign_webauthn(&self, challenge: &[u8]) -> Vec<u8> {
+        use ic_crypto_sha2::Sha256;
+
+        let authenticator_data = vec![0; 37];
+        let client_data_json = format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://example.org"}}"#,
+            base64::encode_config(challenge, base64::URL_SAFE_NO_PAD)
+        )
+        .into_bytes();
+        let mut signed_bytes = authenticator_data.clone();
+        signed_bytes.extend_from_slice(&Sha256::hash(&client_data_json));
+        let signature = ed25519::sign(&signed_bytes, &self.sk).unwrap().0.to_vec();
+
+        serde_cbor::to_vec(&WebAuthnSignature::new(
+            Blob(authenticator_data),
+            Blob(client_data_json),
+            Blob(signature),
+        ))
+        .unwrap()
+    }
+
+    fn delegate_to(
+        &self,
+        other: &Ed25519Key,
+        targets: Option<Vec<CanisterId>>,
+    ) -> SignedDelegation {
+        let delegation = match targets {
+            None => Delegation::new(other.public_key_der(), DELEGATION_EXPIRY),
+            Some(targets) => {
+                Delegation::new_with_targets(other.public_key_der(), DELEGATION_EXPIRY, targets)
+            }
+        };
+        let signature = self.sign(&delegation);
+        SignedDelegation::new(delegation, signature)
+    }
+}
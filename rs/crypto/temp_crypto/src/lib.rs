@@ -1030,6 +1030,8 @@ impl EcdsaSubnetConfig {
                 max_instructions_per_install_code: 200_000_000_000,
                 features: None,
                 max_number_of_canisters: 0,
+                max_total_memory_bytes: 0,
+                max_reserved_compute_allocation: 0,
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: Some(EcdsaConfig {
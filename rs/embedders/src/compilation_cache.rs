@@ -1,21 +1,245 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fs,
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::SystemTime,
+};
 
-use crate::SerializedModule;
-use ic_interfaces::execution_environment::HypervisorResult;
-use ic_types::NumBytes;
+use crate::{SerializedModule, WasmtimeEmbedder};
+use ic_config::{
+    embedders::{Config as EmbeddersConfig, FeatureFlags, MeteringType},
+    flag_status::FlagStatus,
+};
+use ic_crypto_sha2::Sha256;
+use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
+use ic_logger::{warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_registry_subnet_type::SubnetType;
+use ic_types::{CountBytes, NumBytes};
 use ic_utils_lru_cache::LruCache;
-use ic_wasm_types::{CanisterModule, WasmHash};
+use ic_wasm_types::{CanisterModule, WasmEngineError, WasmHash};
+use prometheus::{IntCounter, IntCounterVec, IntGauge};
+
+#[cfg(test)]
+mod compilation_cache_tests;
+
+/// Identifies the output of compilation for a given Wasm and configuration.
+/// Must be bumped whenever a change to the embedder (e.g. to instrumentation)
+/// changes the `SerializedModule` produced for the same input, so that stale
+/// entries of the on-disk cache are not reused after an upgrade.
+const COMPILATION_CACHE_FORMAT_VERSION: u32 = 1;
+
+/// The length of the SHA-256 of its content that prefixes every file of the
+/// on-disk cache.
+const CHECKSUM_LENGTH: usize = 32;
+
+/// The maximum number of modules waiting to be persisted by the background
+/// writer. While the queue is full, newly compiled modules are only cached in
+/// memory.
+const DISK_WRITE_QUEUE_LENGTH: usize = 100;
+
+const TIER_MEMORY: &str = "memory";
+const TIER_DISK: &str = "disk";
+
+/// Returns a fingerprint of everything besides the Wasm itself that
+/// determines the `SerializedModule` produced by compilation: the format
+/// version above, the version and configuration of Wasmtime and the
+/// embedders configuration.
+///
+/// Modules persisted by an on-disk cache are only reused by a cache created
+/// with the same fingerprint.
+pub fn compilation_cache_version(embedder_config: &EmbeddersConfig) -> HypervisorResult<String> {
+    let engine = wasmtime::Engine::new(&WasmtimeEmbedder::wasmtime_execution_config(
+        embedder_config,
+    ))
+    .map_err(|_| HypervisorError::WasmEngineError(WasmEngineError::FailedToInitializeEngine))?;
+
+    let mut hasher = Sha256::new();
+    hasher.write(&COMPILATION_CACHE_FORMAT_VERSION.to_le_bytes());
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    hasher.write(&encode_embedders_config(embedder_config));
+    Ok(to_hex(&hasher.finish()))
+}
+
+/// Encodes the fields of the embedders configuration that can affect the
+/// result of compilation (validation limits, instrumentation and costs) in a
+/// fixed order, with integers as 8 little-endian bytes and enums as explicit
+/// tags. The configuration is destructured exhaustively, so that adding a
+/// field requires deciding whether it belongs in the encoding.
+fn encode_embedders_config(config: &EmbeddersConfig) -> Vec<u8> {
+    let EmbeddersConfig {
+        max_wasm_stack_size,
+        query_execution_threads_per_canister: _,
+        max_globals,
+        max_functions,
+        max_custom_sections,
+        max_custom_sections_size,
+        max_number_exported_functions,
+        max_sum_exported_function_name_lengths,
+        cost_to_compile_wasm_instruction,
+        num_rayon_compilation_threads: _,
+        feature_flags:
+            FeatureFlags {
+                rate_limiting_of_debug_prints: _,
+                write_barrier,
+                wasm_native_stable_memory,
+            },
+        metering_type,
+        stable_memory_dirty_page_limit,
+        stable_memory_accessed_page_limit,
+        min_sandbox_count: _,
+        max_sandbox_count: _,
+        max_sandbox_idle_time: _,
+        subnet_type,
+        dirty_page_overhead,
+        trace_execution: _,
+    } = config;
+
+    let mut bytes = vec![];
+    for value in [
+        *max_wasm_stack_size as u64,
+        *max_globals as u64,
+        *max_functions as u64,
+        *max_custom_sections as u64,
+        max_custom_sections_size.get(),
+        *max_number_exported_functions as u64,
+        *max_sum_exported_function_name_lengths as u64,
+        cost_to_compile_wasm_instruction.get(),
+        stable_memory_dirty_page_limit.get(),
+        stable_memory_accessed_page_limit.get(),
+        dirty_page_overhead.get(),
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    for flag in [write_barrier, wasm_native_stable_memory] {
+        bytes.push(match flag {
+            FlagStatus::Disabled => 0,
+            FlagStatus::Enabled => 1,
+        });
+    }
+    bytes.push(match metering_type {
+        MeteringType::Old => 0,
+        MeteringType::New => 1,
+        MeteringType::None => 2,
+    });
+    bytes.push(match subnet_type {
+        SubnetType::Application => 0,
+        SubnetType::System => 1,
+        SubnetType::VerifiedApplication => 2,
+    });
+    bytes
+}
+
+#[derive(Clone)]
+struct CompilationCacheMetrics {
+    hits: IntCounterVec,
+    misses: IntCounter,
+    disk_errors: IntCounterVec,
+    disk_writes_dropped: IntCounter,
+    disk_size: IntGauge,
+}
+
+impl CompilationCacheMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            hits: metrics_registry.int_counter_vec(
+                "execution_compilation_cache_hits_total",
+                "The number of lookups in the compilation cache that found a module, by tier",
+                &["tier"],
+            ),
+            misses: metrics_registry.int_counter(
+                "execution_compilation_cache_misses_total",
+                "The number of lookups in the compilation cache that found no module",
+            ),
+            disk_errors: metrics_registry.int_counter_vec(
+                "execution_compilation_cache_disk_errors_total",
+                "The number of failed operations on the on-disk compilation cache, by operation",
+                &["operation"],
+            ),
+            disk_writes_dropped: metrics_registry.int_counter(
+                "execution_compilation_cache_disk_writes_dropped_total",
+                "The number of modules not persisted because the queue of the disk writer was full",
+            ),
+            disk_size: metrics_registry.int_gauge(
+                "execution_compilation_cache_disk_size_bytes",
+                "The total size of the modules in the on-disk compilation cache",
+            ),
+        }
+    }
+}
 
 /// Stores the serialized modules of wasm code that has already been compiled so
 /// that it can be used again without recompiling.
+///
+/// Optionally, successfully compiled modules are also persisted on disk, so
+/// that they survive restarts and upgrades of the replica. Modules are written
+/// by a background thread, so that inserting a module never waits for the
+/// disk. Both tiers are used by in-process and by sandboxed execution, which
+/// send modules found in the cache to the sandbox processes instead of
+/// compiling the Wasm there.
 pub struct CompilationCache {
     cache: Mutex<LruCache<WasmHash, HypervisorResult<Arc<SerializedModule>>>>,
+    disk_cache: Option<Arc<DiskCache>>,
+    disk_writer: Option<DiskWriter>,
+    metrics: CompilationCacheMetrics,
+}
+
+/// A request to the background thread that persists modules.
+enum DiskWrite {
+    Module(WasmHash, Arc<SerializedModule>),
+    /// Acknowledged once all the modules queued before are persisted.
+    Flush(SyncSender<()>),
+}
+
+struct DiskWriter {
+    sender: SyncSender<DiskWrite>,
+    thread: JoinHandle<()>,
 }
 
 impl CompilationCache {
     pub fn new(capacity: NumBytes) -> Self {
         Self {
             cache: Mutex::new(LruCache::new(capacity)),
+            disk_cache: None,
+            disk_writer: None,
+            metrics: CompilationCacheMetrics::new(&MetricsRegistry::new()),
+        }
+    }
+
+    /// Creates a cache that additionally persists up to `disk_capacity` bytes
+    /// of compiled modules in `dir`. Only modules that were persisted with
+    /// the same `version` (see `compilation_cache_version`) are reused.
+    pub fn new_with_disk_cache(
+        capacity: NumBytes,
+        dir: PathBuf,
+        disk_capacity: NumBytes,
+        version: String,
+        metrics_registry: &MetricsRegistry,
+        log: ReplicaLogger,
+    ) -> Self {
+        let metrics = CompilationCacheMetrics::new(metrics_registry);
+        let disk_cache = Arc::new(DiskCache::open(dir, disk_capacity, version, log));
+        metrics.disk_size.set(disk_cache.size() as i64);
+
+        let (sender, receiver) = sync_channel(DISK_WRITE_QUEUE_LENGTH);
+        let writer_disk_cache = Arc::clone(&disk_cache);
+        let writer_metrics = metrics.clone();
+        let thread = std::thread::Builder::new()
+            .name("CompilationCacheWriter".to_string())
+            .spawn(move || run_disk_writer(&writer_disk_cache, receiver, &writer_metrics))
+            .expect("Failed to spawn the compilation cache writer thread");
+
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            disk_cache: Some(disk_cache),
+            disk_writer: Some(DiskWriter { sender, thread }),
+            metrics,
         }
     }
 
@@ -24,25 +248,360 @@ impl CompilationCache {
         canister_module: &CanisterModule,
         serialized_module: HypervisorResult<Arc<SerializedModule>>,
     ) {
+        let wasm_hash = WasmHash::from(canister_module);
+        // Compilation errors are cheap to reproduce, so only successfully
+        // compiled modules are persisted.
+        if let (Some(disk_writer), Ok(serialized_module)) = (&self.disk_writer, &serialized_module)
+        {
+            let write = DiskWrite::Module(wasm_hash.clone(), Arc::clone(serialized_module));
+            if disk_writer.sender.try_send(write).is_err() {
+                self.metrics.disk_writes_dropped.inc();
+            }
+        }
         self.cache
             .lock()
             .unwrap()
-            .push(WasmHash::from(canister_module), serialized_module);
+            .push(wasm_hash, serialized_module);
     }
 
     pub fn get(
         &self,
         canister_module: &CanisterModule,
     ) -> Option<HypervisorResult<Arc<SerializedModule>>> {
-        self.cache
+        let wasm_hash = WasmHash::from(canister_module);
+        if let Some(result) = self
+            .cache
             .lock()
             .unwrap()
-            .get(&WasmHash::from(canister_module))
+            .get(&wasm_hash)
             .map(|o| o.as_ref().map(Arc::clone).map_err(|e| e.clone()))
+        {
+            self.metrics.hits.with_label_values(&[TIER_MEMORY]).inc();
+            return Some(result);
+        }
+
+        if let Some(disk_cache) = &self.disk_cache {
+            match disk_cache.get(&wasm_hash) {
+                Ok(Some(serialized_module)) => {
+                    self.metrics.hits.with_label_values(&[TIER_DISK]).inc();
+                    let serialized_module = Arc::new(serialized_module);
+                    self.cache
+                        .lock()
+                        .unwrap()
+                        .push(wasm_hash, Ok(Arc::clone(&serialized_module)));
+                    return Some(Ok(serialized_module));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    self.metrics.disk_errors.with_label_values(&["read"]).inc();
+                    warn!(
+                        disk_cache.log,
+                        "Discarding persisted module {}: {}",
+                        to_hex(&wasm_hash.to_slice()),
+                        err
+                    );
+                    self.metrics.disk_size.set(disk_cache.size() as i64);
+                }
+            }
+        }
+
+        self.metrics.misses.inc();
+        None
+    }
+
+    /// Removes the module compiled from `canister_module` from both tiers,
+    /// e.g. because a module persisted by a previous replica version could not
+    /// be deserialized, so that the next lookup misses and it is recompiled.
+    pub fn remove(&self, canister_module: &CanisterModule) {
+        let wasm_hash = WasmHash::from(canister_module);
+        self.cache.lock().unwrap().pop(&wasm_hash);
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.remove(&wasm_hash);
+            self.metrics.disk_size.set(disk_cache.size() as i64);
+        }
+    }
+
+    /// Waits until all the modules queued so far are persisted.
+    fn flush_disk_writes(&self) {
+        if let Some(disk_writer) = &self.disk_writer {
+            let (ack_sender, ack_receiver) = sync_channel(1);
+            if disk_writer
+                .sender
+                .send(DiskWrite::Flush(ack_sender))
+                .is_ok()
+            {
+                ack_receiver.recv().ok();
+            }
+        }
     }
 
     #[doc(hidden)]
     pub fn clear_for_testing(&self) {
-        self.cache.lock().unwrap().clear()
+        self.flush_disk_writes();
+        self.cache.lock().unwrap().clear();
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.clear();
+        }
+    }
+}
+
+impl Drop for CompilationCache {
+    /// Persists the modules that are still queued before returning, so that a
+    /// cache opened afterwards on the same directory finds them.
+    fn drop(&mut self) {
+        if let Some(DiskWriter { sender, thread }) = self.disk_writer.take() {
+            drop(sender);
+            thread.join().ok();
+        }
+    }
+}
+
+/// Persists the modules received on `receiver` until the sender is dropped.
+fn run_disk_writer(
+    disk_cache: &DiskCache,
+    receiver: Receiver<DiskWrite>,
+    metrics: &CompilationCacheMetrics,
+) {
+    for write in receiver {
+        match write {
+            DiskWrite::Module(wasm_hash, serialized_module) => {
+                if let Err(err) = disk_cache.insert(&wasm_hash, &serialized_module) {
+                    metrics.disk_errors.with_label_values(&["write"]).inc();
+                    warn!(
+                        disk_cache.log,
+                        "Failed to persist compiled module {}: {}",
+                        to_hex(&wasm_hash.to_slice()),
+                        err
+                    );
+                }
+                metrics.disk_size.set(disk_cache.size() as i64);
+            }
+            DiskWrite::Flush(ack) => {
+                ack.send(()).ok();
+            }
+        }
+    }
+}
+
+/// The size of a file of the on-disk cache.
+struct FileSize(usize);
+
+impl CountBytes for FileSize {
+    fn count_bytes(&self) -> usize {
+        self.0
+    }
+}
+
+/// Persists serialized modules in a directory, one file per module. The name
+/// of each file consists of the hash of the Wasm and the version of the cache
+/// and its content of the SHA-256 of the serialized module followed by the
+/// serialized module itself.
+struct DiskCache {
+    dir: PathBuf,
+    version: String,
+    /// The files in `dir`, in the order in which they were last used. Across
+    /// restarts, the order is approximated by the time of the last write.
+    index: Mutex<LruCache<WasmHash, FileSize>>,
+    /// Used to give concurrent writes distinct temporary files.
+    next_tmp_file_id: AtomicU64,
+    log: ReplicaLogger,
+}
+
+impl DiskCache {
+    /// Opens the cache in `dir`, creating the directory if needed. Files
+    /// written with a different version are removed and, if the remaining
+    /// ones exceed `capacity`, the least recently modified are evicted.
+    fn open(dir: PathBuf, capacity: NumBytes, version: String, log: ReplicaLogger) -> Self {
+        let cache = Self {
+            dir,
+            version,
+            index: Mutex::new(LruCache::new(capacity)),
+            next_tmp_file_id: AtomicU64::new(0),
+            log,
+        };
+        if let Err(err) = fs::create_dir_all(&cache.dir) {
+            warn!(
+                cache.log,
+                "Failed to create compilation cache directory {}: {}",
+                cache.dir.display(),
+                err
+            );
+            return cache;
+        }
+
+        let mut entries: Vec<(SystemTime, WasmHash, usize)> = vec![];
+        let read_dir = match fs::read_dir(&cache.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                warn!(
+                    cache.log,
+                    "Failed to list compilation cache directory {}: {}",
+                    cache.dir.display(),
+                    err
+                );
+                return cache;
+            }
+        };
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let wasm_hash = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| cache.parse_file_name(name));
+            let metadata = entry.metadata();
+            match (wasm_hash, metadata) {
+                (Some(wasm_hash), Ok(metadata)) if metadata.is_file() => {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    entries.push((modified, wasm_hash, metadata.len() as usize));
+                }
+                // Leftovers of other versions or of interrupted writes.
+                _ => remove_file(&path, &cache.log),
+            }
+        }
+
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        let mut index = cache.index.lock().unwrap();
+        for (_, wasm_hash, size) in entries {
+            let evicted = index.push(wasm_hash, FileSize(size));
+            cache.remove_evicted(evicted);
+        }
+        drop(index);
+        cache
+    }
+
+    fn size(&self) -> usize {
+        self.index.lock().unwrap().count_bytes()
+    }
+
+    fn path(&self, wasm_hash: &WasmHash) -> PathBuf {
+        self.dir.join(format!(
+            "{}-{}",
+            to_hex(&wasm_hash.to_slice()),
+            self.version
+        ))
+    }
+
+    fn parse_file_name(&self, name: &str) -> Option<WasmHash> {
+        let (hash, version) = name.split_once('-')?;
+        if version != self.version {
+            return None;
+        }
+        let hash: [u8; 32] = from_hex(hash)?.try_into().ok()?;
+        Some(WasmHash::from(hash))
+    }
+
+    /// Returns the module persisted for `wasm_hash`, if any. Files that can't
+    /// be read or don't pass the integrity check are removed.
+    fn get(&self, wasm_hash: &WasmHash) -> Result<Option<SerializedModule>, String> {
+        if self.index.lock().unwrap().get(wasm_hash).is_none() {
+            return Ok(None);
+        }
+        let path = self.path(wasm_hash);
+        let result = read_module(&path);
+        if result.is_err() {
+            self.index.lock().unwrap().pop(wasm_hash);
+            remove_file(&path, &self.log);
+        }
+        result.map(Some)
+    }
+
+    /// Persists `serialized_module` and evicts the least recently used
+    /// modules if the capacity is exceeded.
+    fn insert(
+        &self,
+        wasm_hash: &WasmHash,
+        serialized_module: &SerializedModule,
+    ) -> Result<(), String> {
+        let path = self.path(wasm_hash);
+        let tmp_path = path.with_extension(format!(
+            "tmp{}",
+            self.next_tmp_file_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let size = write_module(&path, &tmp_path, serialized_module)?;
+
+        let mut index = self.index.lock().unwrap();
+        // The file was overwritten, so it must not be removed as a replaced
+        // entry.
+        index.pop(wasm_hash);
+        let evicted = index.push(wasm_hash.clone(), FileSize(size));
+        drop(index);
+        self.remove_evicted(evicted);
+        Ok(())
+    }
+
+    fn remove(&self, wasm_hash: &WasmHash) {
+        if self.index.lock().unwrap().pop(wasm_hash).is_some() {
+            remove_file(&self.path(wasm_hash), &self.log);
+        }
+    }
+
+    fn remove_evicted(&self, evicted: Vec<(WasmHash, FileSize)>) {
+        for (wasm_hash, _) in evicted {
+            remove_file(&self.path(&wasm_hash), &self.log);
+        }
+    }
+
+    fn clear(&self) {
+        let mut index = self.index.lock().unwrap();
+        if let Ok(read_dir) = fs::read_dir(&self.dir) {
+            for entry in read_dir.flatten() {
+                remove_file(&entry.path(), &self.log);
+            }
+        }
+        index.clear();
+    }
+}
+
+fn read_module(path: &Path) -> Result<SerializedModule, String> {
+    let content = fs::read(path).map_err(|err| format!("failed to read: {}", err))?;
+    if content.len() < CHECKSUM_LENGTH {
+        return Err("file is truncated".to_string());
+    }
+    let (checksum, serialized) = content.split_at(CHECKSUM_LENGTH);
+    if Sha256::hash(serialized) != checksum {
+        return Err("checksum mismatch".to_string());
+    }
+    bincode::deserialize(serialized).map_err(|err| format!("failed to deserialize: {}", err))
+}
+
+/// Writes `serialized_module` to `path` atomically, via `tmp_path`, and
+/// returns the size of the file.
+fn write_module(
+    path: &Path,
+    tmp_path: &Path,
+    serialized_module: &SerializedModule,
+) -> Result<usize, String> {
+    let serialized = bincode::serialize(serialized_module)
+        .map_err(|err| format!("failed to serialize: {}", err))?;
+    let mut content = Vec::with_capacity(CHECKSUM_LENGTH + serialized.len());
+    content.extend_from_slice(&Sha256::hash(&serialized));
+    content.extend_from_slice(&serialized);
+
+    // Temporary files are not recognized as cache entries, so a partial write
+    // is never read and is cleaned up on the next start.
+    fs::write(tmp_path, &content).map_err(|err| format!("failed to write: {}", err))?;
+    fs::rename(tmp_path, path).map_err(|err| format!("failed to rename: {}", err))?;
+    Ok(content.len())
+}
+
+fn remove_file(path: &Path, log: &ReplicaLogger) {
+    if let Err(err) = fs::remove_file(path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            warn!(log, "Failed to remove {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
     }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
 }
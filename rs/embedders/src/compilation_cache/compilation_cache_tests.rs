@@ -0,0 +1,178 @@
+use super::*;
+use crate::SerializedModuleBytes;
+use ic_logger::replica_logger::no_op_logger;
+use ic_replicated_state::canister_state::execution_state::WasmMetadata;
+use ic_types::NumInstructions;
+
+const CAPACITY: NumBytes = NumBytes::new(1 << 30);
+const VERSION: &str = "version";
+
+fn serialized_module(compilation_cost: u64) -> Arc<SerializedModule> {
+    Arc::new(SerializedModule {
+        bytes: Arc::new(SerializedModuleBytes::empty()),
+        exported_functions: Default::default(),
+        data_segments: Default::default(),
+        wasm_metadata: WasmMetadata::default(),
+        compilation_cost: NumInstructions::new(compilation_cost),
+        imports_details: Default::default(),
+    })
+}
+
+fn cache_with_disk(dir: &Path, disk_capacity: NumBytes, version: &str) -> CompilationCache {
+    CompilationCache::new_with_disk_cache(
+        CAPACITY,
+        dir.to_path_buf(),
+        disk_capacity,
+        version.to_string(),
+        &MetricsRegistry::new(),
+        no_op_logger(),
+    )
+}
+
+fn cached_compilation_cost(cache: &CompilationCache, module: &CanisterModule) -> Option<u64> {
+    cache
+        .get(module)
+        .map(|result| result.unwrap().compilation_cost.get())
+}
+
+fn files_in(dir: &Path) -> usize {
+    fs::read_dir(dir).unwrap().count()
+}
+
+#[test]
+fn modules_survive_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let module = CanisterModule::new(vec![1, 2, 3]);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    assert_eq!(cached_compilation_cost(&cache, &module), None);
+    cache.insert(&module, Ok(serialized_module(42)));
+    drop(cache);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    assert_eq!(cached_compilation_cost(&cache, &module), Some(42));
+}
+
+#[test]
+fn modules_of_other_versions_are_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let module = CanisterModule::new(vec![1, 2, 3]);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    cache.insert(&module, Ok(serialized_module(42)));
+    drop(cache);
+    assert_eq!(files_in(dir.path()), 1);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, "other_version");
+    assert_eq!(files_in(dir.path()), 0);
+    assert_eq!(cached_compilation_cost(&cache, &module), None);
+}
+
+#[test]
+fn corrupted_modules_are_discarded() {
+    let dir = tempfile::tempdir().unwrap();
+    let module = CanisterModule::new(vec![1, 2, 3]);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    cache.insert(&module, Ok(serialized_module(42)));
+    drop(cache);
+
+    let path = fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let mut content = fs::read(&path).unwrap();
+    *content.last_mut().unwrap() ^= 1;
+    fs::write(&path, content).unwrap();
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    assert_eq!(cached_compilation_cost(&cache, &module), None);
+    assert_eq!(files_in(dir.path()), 0);
+}
+
+#[test]
+fn removed_modules_are_removed_from_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let module = CanisterModule::new(vec![1, 2, 3]);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    cache.insert(&module, Ok(serialized_module(42)));
+    cache.flush_disk_writes();
+    assert_eq!(files_in(dir.path()), 1);
+
+    cache.remove(&module);
+    assert_eq!(cached_compilation_cost(&cache, &module), None);
+    assert_eq!(files_in(dir.path()), 0);
+}
+
+#[test]
+fn compilation_errors_are_not_persisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let module = CanisterModule::new(vec![1, 2, 3]);
+
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    cache.insert(
+        &module,
+        Err(HypervisorError::WasmEngineError(
+            WasmEngineError::FailedToInitializeEngine,
+        )),
+    );
+    assert!(cache.get(&module).unwrap().is_err());
+    cache.flush_disk_writes();
+    assert_eq!(files_in(dir.path()), 0);
+}
+
+#[test]
+fn least_recently_used_modules_are_evicted_from_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let modules: Vec<_> = (0..3).map(|i| CanisterModule::new(vec![i])).collect();
+
+    // Measure the size of a single file to fit exactly two of them.
+    let cache = cache_with_disk(dir.path(), CAPACITY, VERSION);
+    cache.insert(&modules[0], Ok(serialized_module(0)));
+    cache.flush_disk_writes();
+    let file_size = fs::read_dir(dir.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .metadata()
+        .unwrap()
+        .len();
+    cache.clear_for_testing();
+    drop(cache);
+
+    let cache = cache_with_disk(dir.path(), NumBytes::new(2 * file_size), VERSION);
+    for (i, module) in modules.iter().enumerate() {
+        cache.insert(module, Ok(serialized_module(i as u64)));
+    }
+    cache.flush_disk_writes();
+    assert_eq!(files_in(dir.path()), 2);
+    drop(cache);
+
+    let cache = cache_with_disk(dir.path(), NumBytes::new(2 * file_size), VERSION);
+    assert_eq!(cached_compilation_cost(&cache, &modules[0]), None);
+    assert_eq!(cached_compilation_cost(&cache, &modules[1]), Some(1));
+    assert_eq!(cached_compilation_cost(&cache, &modules[2]), Some(2));
+}
+
+#[test]
+fn config_encoding_ignores_runtime_only_settings() {
+    let config = EmbeddersConfig::default();
+    let mut runtime_only_change = config.clone();
+    runtime_only_change.num_rayon_compilation_threads += 1;
+    runtime_only_change.max_sandbox_count += 1;
+    assert_eq!(
+        encode_embedders_config(&config),
+        encode_embedders_config(&runtime_only_change)
+    );
+
+    let mut compilation_change = config.clone();
+    compilation_change.max_functions += 1;
+    assert_ne!(
+        encode_embedders_config(&config),
+        encode_embedders_config(&compilation_change)
+    );
+}
@@ -28,7 +28,7 @@ use ic_replicated_state::{EmbedderCache, ExecutionState};
 use ic_sys::{page_bytes_from_ptr, PageBytes, PageIndex, PAGE_SIZE};
 use ic_system_api::{ExecutionParameters, ModificationTracking, SystemApiImpl};
 use ic_types::{CanisterId, NumBytes, NumInstructions};
-use ic_wasm_types::{BinaryEncodedWasm, CanisterModule};
+use ic_wasm_types::{BinaryEncodedWasm, CanisterModule, WasmEngineError};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
@@ -377,42 +377,57 @@ impl WasmExecutorImpl {
         } else {
             match compilation_cache.get(&wasm_binary.binary) {
                 Some(Ok(serialized_module)) => {
-                    let instance_pre = self
+                    match self
                         .wasm_embedder
-                        .deserialize_module_and_pre_instantiate(&serialized_module.bytes);
-                    let cache = EmbedderCache::new(instance_pre.clone());
-                    *guard = Some(cache.clone());
-                    match instance_pre {
-                        Ok(_) => Ok(CacheLookup {
-                            cache,
-                            serialized_module: Some(serialized_module),
-                            compilation_result: None,
-                        }),
-                        Err(err) => Err(err),
+                        .deserialize_module_and_pre_instantiate(&serialized_module.bytes)
+                    {
+                        // A module persisted by a replica with a different
+                        // Wasmtime is compiled again below.
+                        Err(HypervisorError::WasmEngineError(
+                            WasmEngineError::FailedToDeserializeModule(err),
+                        )) => {
+                            warn!(
+                                self.log,
+                                "Recompiling a Wasm whose cached module could not be deserialized: {}",
+                                err
+                            );
+                            compilation_cache.remove(&wasm_binary.binary);
+                        }
+                        instance_pre => {
+                            let cache = EmbedderCache::new(instance_pre.clone());
+                            *guard = Some(cache.clone());
+                            return match instance_pre {
+                                Ok(_) => Ok(CacheLookup {
+                                    cache,
+                                    serialized_module: Some(serialized_module),
+                                    compilation_result: None,
+                                }),
+                                Err(err) => Err(err),
+                            };
+                        }
                     }
                 }
                 Some(Err(err)) => {
                     let cache: HypervisorResult<Module> = Err(err.clone());
                     *guard = Some(EmbedderCache::new(cache));
-                    Err(err)
-                }
-                None => {
-                    use std::borrow::Cow;
-                    let decoded_wasm: Cow<'_, BinaryEncodedWasm> =
-                        Cow::Owned(decode_wasm(wasm_binary.binary.to_shared_vec())?);
-                    let (cache, result) = compile(&self.wasm_embedder, decoded_wasm.as_ref());
-                    *guard = Some(cache.clone());
-                    let (compilation_result, serialized_module) = result?;
-                    let serialized_module = Arc::new(serialized_module);
-                    compilation_cache
-                        .insert(&wasm_binary.binary, Ok(Arc::clone(&serialized_module)));
-                    Ok(CacheLookup {
-                        cache,
-                        serialized_module: Some(serialized_module),
-                        compilation_result: Some(compilation_result),
-                    })
+                    return Err(err);
                 }
+                None => {}
             }
+
+            use std::borrow::Cow;
+            let decoded_wasm: Cow<'_, BinaryEncodedWasm> =
+                Cow::Owned(decode_wasm(wasm_binary.binary.to_shared_vec())?);
+            let (cache, result) = compile(&self.wasm_embedder, decoded_wasm.as_ref());
+            *guard = Some(cache.clone());
+            let (compilation_result, serialized_module) = result?;
+            let serialized_module = Arc::new(serialized_module);
+            compilation_cache.insert(&wasm_binary.binary, Ok(Arc::clone(&serialized_module)));
+            Ok(CacheLookup {
+                cache,
+                serialized_module: Some(serialized_module),
+                compilation_result: Some(compilation_result),
+            })
         }
     }
 
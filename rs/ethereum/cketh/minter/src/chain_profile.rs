@@ -0,0 +1,143 @@
+//! Chain-specific parameters of the EVM network on which the minter operates.
+use crate::address::Address;
+use crate::endpoints::CandidFeeModel;
+use crate::eth_rpc::{BlockSpec, BlockTag};
+use crate::lifecycle::EthereumNetwork;
+use crate::numeric::{BlockNumber, Wei, WeiPerGas};
+use std::fmt::{Display, Formatter};
+
+#[cfg(test)]
+mod tests;
+
+/// Parameterizes the minter by the chain backing the ck-tokens,
+/// so that the same code can run against Ethereum and its layer 2 rollups.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainProfile {
+    pub network: EthereumNetwork,
+    pub finality: Finality,
+    pub fee_model: FeeModel,
+    pub helper_contract_address: Option<Address>,
+}
+
+impl ChainProfile {
+    pub fn chain_id(&self) -> u64 {
+        self.network.chain_id()
+    }
+}
+
+/// Determines when a block is considered final, i.e., when the deposits it contains can be minted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Finality {
+    /// The block is final once it's not higher than the block with the given tag.
+    BlockTag(BlockTag),
+    /// The block is final once it's buried under the given number of blocks.
+    /// Useful for chains that do not support the `safe` and `finalized` block tags.
+    ConfirmationDepth(u64),
+}
+
+impl Finality {
+    /// The block to query to retrieve the latest block that is possibly final.
+    pub fn block_to_query(&self) -> BlockSpec {
+        match self {
+            Finality::BlockTag(tag) => BlockSpec::Tag(*tag),
+            Finality::ConfirmationDepth(_) => BlockSpec::Tag(BlockTag::Latest),
+        }
+    }
+
+    /// Returns the latest final block number given the number of the block returned
+    /// when querying [`Finality::block_to_query`].
+    pub fn last_final_block_number(&self, queried_block_number: BlockNumber) -> BlockNumber {
+        match self {
+            Finality::BlockTag(_) => queried_block_number,
+            Finality::ConfirmationDepth(depth) => queried_block_number
+                .checked_sub(BlockNumber::from(*depth))
+                .unwrap_or(BlockNumber::ZERO),
+        }
+    }
+}
+
+impl Display for Finality {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Finality::BlockTag(tag) => write!(f, "{tag} block"),
+            Finality::ConfirmationDepth(depth) => write!(f, "{depth} confirmations"),
+        }
+    }
+}
+
+/// Determines how the fees of transactions issued by the minter are estimated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeModel {
+    /// EIP-1559 fee estimation of Ethereum: the max priority fee per gas is the median of
+    /// the priority fees paid in recent blocks, but at least 1.5 gwei.
+    Eip1559,
+    /// EIP-1559 fee estimation with a fixed max priority fee per gas,
+    /// e.g. for rollups where the sequencer orders transactions first-come first-served.
+    Eip1559FixedPriorityFee { max_priority_fee_per_gas: WeiPerGas },
+    /// Fee estimation of OP Stack rollups (e.g. OP Mainnet and Base): EIP-1559 with a fixed
+    /// max priority fee per gas, plus a fee for posting the transaction data on L1.
+    /// The L1 data fee is deducted from the sender's balance but not bounded by the transaction,
+    /// so `max_l1_data_fee` is charged to the user on top of the gas fee.
+    OpStack {
+        max_priority_fee_per_gas: WeiPerGas,
+        max_l1_data_fee: Wei,
+    },
+}
+
+impl FeeModel {
+    /// The amount reserved for the L1 data fee of a transaction, which is zero
+    /// unless the chain charges such a fee.
+    pub fn max_l1_data_fee(&self) -> Wei {
+        match self {
+            FeeModel::Eip1559 | FeeModel::Eip1559FixedPriorityFee { .. } => Wei::ZERO,
+            FeeModel::OpStack {
+                max_l1_data_fee, ..
+            } => *max_l1_data_fee,
+        }
+    }
+}
+
+impl TryFrom<CandidFeeModel> for FeeModel {
+    type Error = String;
+
+    fn try_from(value: CandidFeeModel) -> Result<Self, Self::Error> {
+        match value {
+            CandidFeeModel::Eip1559 => Ok(FeeModel::Eip1559),
+            CandidFeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas,
+            } => Ok(FeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas: WeiPerGas::try_from(max_priority_fee_per_gas)?,
+            }),
+            CandidFeeModel::OpStack {
+                max_priority_fee_per_gas,
+                max_l1_data_fee,
+            } => Ok(FeeModel::OpStack {
+                max_priority_fee_per_gas: WeiPerGas::try_from(max_priority_fee_per_gas)?,
+                max_l1_data_fee: Wei::try_from(max_l1_data_fee)?,
+            }),
+        }
+    }
+}
+
+impl Display for FeeModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeeModel::Eip1559 => write!(f, "EIP-1559"),
+            FeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas,
+            } => write!(
+                f,
+                "EIP-1559 with fixed max priority fee of {} Wei per gas",
+                max_priority_fee_per_gas
+            ),
+            FeeModel::OpStack {
+                max_priority_fee_per_gas,
+                max_l1_data_fee,
+            } => write!(
+                f,
+                "OP Stack with fixed max priority fee of {} Wei per gas and max L1 data fee of {} Wei",
+                max_priority_fee_per_gas, max_l1_data_fee
+            ),
+        }
+    }
+}
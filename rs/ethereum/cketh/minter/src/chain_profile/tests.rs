@@ -0,0 +1,175 @@
+mod finality {
+    use crate::chain_profile::Finality;
+    use crate::eth_rpc::{BlockSpec, BlockTag};
+    use crate::numeric::BlockNumber;
+
+    #[test]
+    fn should_query_block_with_tag() {
+        for tag in [BlockTag::Latest, BlockTag::Safe, BlockTag::Finalized] {
+            let finality = Finality::BlockTag(tag);
+
+            assert_eq!(finality.block_to_query(), BlockSpec::Tag(tag));
+            assert_eq!(
+                finality.last_final_block_number(BlockNumber::new(1_000)),
+                BlockNumber::new(1_000)
+            );
+        }
+    }
+
+    #[test]
+    fn should_query_latest_block_and_subtract_confirmation_depth() {
+        let finality = Finality::ConfirmationDepth(64);
+
+        assert_eq!(finality.block_to_query(), BlockSpec::Tag(BlockTag::Latest));
+        assert_eq!(
+            finality.last_final_block_number(BlockNumber::new(1_000)),
+            BlockNumber::new(936)
+        );
+        assert_eq!(
+            finality.last_final_block_number(BlockNumber::new(64)),
+            BlockNumber::ZERO
+        );
+        assert_eq!(
+            finality.last_final_block_number(BlockNumber::new(10)),
+            BlockNumber::ZERO
+        );
+    }
+}
+
+mod fee_model {
+    use crate::chain_profile::FeeModel;
+    use crate::endpoints::CandidFeeModel;
+    use crate::numeric::{Wei, WeiPerGas};
+    use candid::Nat;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn should_convert_from_candid_fee_model() {
+        assert_eq!(
+            FeeModel::try_from(CandidFeeModel::Eip1559),
+            Ok(FeeModel::Eip1559)
+        );
+        assert_eq!(
+            FeeModel::try_from(CandidFeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas: Nat::from(1_000_000_u64),
+            }),
+            Ok(FeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000)
+            })
+        );
+        assert_eq!(
+            FeeModel::try_from(CandidFeeModel::OpStack {
+                max_priority_fee_per_gas: Nat::from(1_000_000_u64),
+                max_l1_data_fee: Nat::from(100_000_000_000_000_u64),
+            }),
+            Ok(FeeModel::OpStack {
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000),
+                max_l1_data_fee: Wei::new(100_000_000_000_000),
+            })
+        );
+    }
+
+    #[test]
+    fn should_reserve_l1_data_fee_only_with_op_stack_fee_model() {
+        assert_eq!(FeeModel::Eip1559.max_l1_data_fee(), Wei::ZERO);
+        assert_eq!(
+            FeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000)
+            }
+            .max_l1_data_fee(),
+            Wei::ZERO
+        );
+        assert_eq!(
+            FeeModel::OpStack {
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000),
+                max_l1_data_fee: Wei::new(100_000_000_000_000),
+            }
+            .max_l1_data_fee(),
+            Wei::new(100_000_000_000_000)
+        );
+    }
+
+    #[test]
+    fn should_fail_to_convert_fee_model_when_priority_fee_too_large() {
+        assert!(FeeModel::try_from(CandidFeeModel::Eip1559FixedPriorityFee {
+            max_priority_fee_per_gas: Nat(
+                BigUint::from_bytes_be(&ethnum::u256::MAX.to_be_bytes()) + 1_u8
+            ),
+        })
+        .is_err());
+    }
+}
+
+mod ethereum_network {
+    use crate::chain_profile::FeeModel;
+    use crate::lifecycle::EthereumNetwork;
+    use crate::numeric::{GasAmount, Wei};
+
+    const ALL_NETWORKS: [EthereumNetwork; 8] = [
+        EthereumNetwork::Mainnet,
+        EthereumNetwork::Sepolia,
+        EthereumNetwork::Optimism,
+        EthereumNetwork::OptimismSepolia,
+        EthereumNetwork::Base,
+        EthereumNetwork::BaseSepolia,
+        EthereumNetwork::ArbitrumOne,
+        EthereumNetwork::ArbitrumSepolia,
+    ];
+
+    #[test]
+    fn should_convert_chain_id_back_and_forth() {
+        for network in ALL_NETWORKS {
+            assert_eq!(EthereumNetwork::try_from(network.chain_id()), Ok(network));
+        }
+        assert!(EthereumNetwork::try_from(0).is_err());
+    }
+
+    #[test]
+    fn should_use_eip1559_fee_model_only_on_layer1() {
+        for network in ALL_NETWORKS {
+            assert_eq!(
+                network.default_fee_model() == FeeModel::Eip1559,
+                !network.is_layer2(),
+                "unexpected default fee model for {network}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_reserve_l1_data_fee_only_on_op_stack_rollups() {
+        for network in ALL_NETWORKS {
+            let is_op_stack = matches!(
+                network,
+                EthereumNetwork::Optimism
+                    | EthereumNetwork::OptimismSepolia
+                    | EthereumNetwork::Base
+                    | EthereumNetwork::BaseSepolia
+            );
+            assert_eq!(
+                network.default_fee_model().max_l1_data_fee() > Wei::ZERO,
+                is_op_stack,
+                "unexpected L1 data fee reserve for {network}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_use_larger_gas_limits_on_arbitrum() {
+        for network in ALL_NETWORKS {
+            let is_arbitrum = matches!(
+                network,
+                EthereumNetwork::ArbitrumOne | EthereumNetwork::ArbitrumSepolia
+            );
+            assert_eq!(
+                network.eth_transfer_gas_limit() > GasAmount::new(21_000),
+                is_arbitrum,
+                "unexpected ETH transfer gas limit for {network}"
+            );
+            assert_eq!(
+                network.erc20_transfer_gas_limit() > GasAmount::new(65_000),
+                is_arbitrum,
+                "unexpected ERC-20 transfer gas limit for {network}"
+            );
+        }
+    }
+}
@@ -4,9 +4,11 @@ mod tests;
 use askama::Template;
 use candid::Principal;
 use ic_cketh_minter::address::Address;
+use ic_cketh_minter::chain_profile::{FeeModel, Finality};
 use ic_cketh_minter::endpoints::{EthTransaction, RetrieveEthStatus};
 use ic_cketh_minter::eth_logs::{EventSource, ReceivedEthEvent};
 use ic_cketh_minter::eth_rpc::Hash;
+use ic_cketh_minter::eth_rpc_client::providers::CustomProvider;
 use ic_cketh_minter::eth_rpc_client::responses::TransactionStatus;
 use ic_cketh_minter::lifecycle::EthereumNetwork;
 use ic_cketh_minter::numeric::{BlockNumber, LedgerBurnIndex, TransactionNonce, Wei};
@@ -32,10 +34,18 @@ pub struct DashboardFinalizedTransaction {
     pub status: TransactionStatus,
 }
 
+pub struct DashboardRpcProviderError {
+    pub provider: String,
+    pub method: String,
+    pub count: u64,
+}
+
 #[derive(Template)]
 #[template(path = "dashboard.html")]
 pub struct DashboardTemplate {
     pub ethereum_network: EthereumNetwork,
+    pub finality: Finality,
+    pub fee_model: FeeModel,
     pub ecdsa_key_name: String,
     pub minter_address: String,
     pub contract_address: String,
@@ -53,6 +63,8 @@ pub struct DashboardTemplate {
     pub finalized_transactions: Vec<DashboardFinalizedTransaction>,
     pub reimbursed_transactions: Vec<Reimbursed>,
     pub eth_balance: EthBalance,
+    pub rpc_providers: Vec<CustomProvider>,
+    pub rpc_provider_errors: Vec<DashboardRpcProviderError>,
 }
 
 impl DashboardTemplate {
@@ -113,8 +125,20 @@ impl DashboardTemplate {
         reimbursed_transactions
             .sort_unstable_by_key(|reimbursed_tx| std::cmp::Reverse(reimbursed_tx.withdrawal_id));
 
+        let rpc_provider_errors = state
+            .rpc_provider_errors
+            .iter()
+            .map(|((provider, method), count)| DashboardRpcProviderError {
+                provider: provider.clone(),
+                method: method.clone(),
+                count: *count,
+            })
+            .collect();
+
         DashboardTemplate {
             ethereum_network: state.ethereum_network,
+            finality: state.finality,
+            fee_model: state.fee_model,
             ecdsa_key_name: state.ecdsa_key_name.clone(),
             minter_address: state
                 .minter_address()
@@ -137,6 +161,8 @@ impl DashboardTemplate {
             finalized_transactions,
             reimbursed_transactions,
             eth_balance: state.eth_balance.clone(),
+            rpc_providers: state.rpc_providers.clone(),
+            rpc_provider_errors,
         }
     }
 }
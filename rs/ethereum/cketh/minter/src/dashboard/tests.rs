@@ -32,6 +32,8 @@ fn should_display_metadata() {
 
     DashboardAssert::assert_that(dashboard)
         .has_ethereum_network("Ethereum Testnet Sepolia")
+        .has_finality("latest block")
+        .has_fee_model("EIP-1559")
         .has_minter_address("0x1789F79e95324A47c5Fd6693071188e82E9a3558")
         .has_contract_address("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34")
         .has_ledger_canister_id("apia6-jaaaa-aaaar-qabma-cai")
@@ -235,6 +237,24 @@ fn should_display_rejected_deposits() {
         );
 }
 
+#[test]
+fn should_display_rpc_provider_errors() {
+    DashboardAssert::assert_that(initial_dashboard())
+        .has_no_elements_matching("#rpc-provider-errors");
+
+    let dashboard = {
+        let mut state = initial_state();
+        state.record_rpc_provider_error("ankr", "eth_getLogs");
+        state.record_rpc_provider_error("ankr", "eth_getLogs");
+        state.record_rpc_provider_error("publicnode", "eth_feeHistory");
+        DashboardTemplate::from_state(&state)
+    };
+
+    DashboardAssert::assert_that(dashboard)
+        .has_rpc_provider_errors(1, &vec!["ankr", "eth_getLogs", "2"])
+        .has_rpc_provider_errors(2, &vec!["publicnode", "eth_feeHistory", "1"]);
+}
+
 #[test]
 fn should_display_withdrawal_requests_sorted_by_decreasing_ledger_burn_index() {
     DashboardAssert::assert_that(initial_dashboard())
@@ -584,6 +604,9 @@ fn initial_state() -> State {
         minimum_withdrawal_amount: Wei::TWO.into(),
         next_transaction_nonce: TransactionNonce::ZERO.into(),
         last_scraped_block_number: candid::Nat::from(3_956_206),
+        rpc_providers: None,
+        confirmation_depth: None,
+        fee_model: None,
     })
     .expect("valid init args")
 }
@@ -670,6 +693,7 @@ fn withdrawal_flow(
         gas_used: signed_tx.transaction().gas_limit,
         status: tx_status,
         transaction_hash: signed_tx.hash(),
+        l1_fee: None,
     };
     (withdrawal_request, transaction, signed_tx, tx_receipt)
 }
@@ -755,6 +779,14 @@ mod assertions {
             )
         }
 
+        pub fn has_finality(&self, expected_finality: &str) -> &Self {
+            self.has_string_value("#finality > td", expected_finality, "wrong finality")
+        }
+
+        pub fn has_fee_model(&self, expected_fee_model: &str) -> &Self {
+            self.has_string_value("#fee-model > td", expected_fee_model, "wrong fee model")
+        }
+
         pub fn has_minter_address(&self, expected_address: &str) -> &Self {
             self.has_string_value(
                 "#minter-address > td",
@@ -847,6 +879,14 @@ mod assertions {
             )
         }
 
+        pub fn has_rpc_provider_errors(&self, row_index: u8, expected_value: &Vec<&str>) -> &Self {
+            self.has_table_row_string_value(
+                &format!("#rpc-provider-errors + table > tbody > tr:nth-child({row_index})"),
+                expected_value,
+                "rpc-provider-errors",
+            )
+        }
+
         pub fn has_withdrawal_requests(&self, row_index: u8, expected_value: &Vec<&str>) -> &Self {
             self.has_table_row_string_value(
                 &format!("#withdrawal-requests + table > tbody > tr:nth-child({row_index})"),
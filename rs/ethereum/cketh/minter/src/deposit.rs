@@ -1,6 +1,5 @@
 use crate::address::Address;
-use crate::eth_logs::{report_transaction_error, ReceivedEthEventError};
-use crate::eth_rpc::BlockSpec;
+use crate::eth_logs::{report_transaction_error, ReceivedEthEventError, ReceivedEvent};
 use crate::eth_rpc_client::EthRpcClient;
 use crate::guard::TimerGuard;
 use crate::logs::{DEBUG, INFO};
@@ -13,7 +12,7 @@ use num_traits::ToPrimitive;
 use std::cmp::{min, Ordering};
 use std::time::Duration;
 
-async fn mint_cketh() {
+async fn mint() {
     use icrc_ledger_client_cdk::{CdkRuntime, ICRC1Client};
     use icrc_ledger_types::icrc1::transfer::TransferArg;
 
@@ -74,19 +73,108 @@ async fn mint_cketh() {
         );
     }
 
+    let (tokens, erc20_events) =
+        read_state(|s| (s.ckerc20_tokens.clone(), s.erc20_events_to_mint.clone()));
+    for (event_source, event) in erc20_events {
+        let token = tokens
+            .get(&event.erc20_contract_address)
+            .unwrap_or_else(|| {
+                panic!("BUG: accepted deposit {event:?} of an unsupported ERC-20 token")
+            });
+        let ledger_canister_id = token.ckerc20_ledger_id;
+        let client = ICRC1Client {
+            runtime: CdkRuntime,
+            ledger_canister_id,
+        };
+        let block_index = match client
+            .transfer(TransferArg {
+                from_subaccount: None,
+                to: event.principal.into(),
+                fee: None,
+                created_at_time: None,
+                memo: None,
+                amount: candid::Nat::from(event.value),
+            })
+            .await
+        {
+            Ok(Ok(block_index)) => block_index.0.to_u64().expect("nat does not fit into u64"),
+            Ok(Err(err)) => {
+                log!(
+                    INFO,
+                    "Failed to mint {}: {event:?} {err}",
+                    token.ckerc20_token_symbol
+                );
+                error_count += 1;
+                continue;
+            }
+            Err(err) => {
+                log!(
+                    INFO,
+                    "Failed to send a message to the ledger ({ledger_canister_id}): {err:?}"
+                );
+                error_count += 1;
+                continue;
+            }
+        };
+        mutate_state(|s| {
+            process_event(
+                s,
+                EventType::MintedCkErc20 {
+                    event_source,
+                    mint_block_index: LedgerMintIndex::new(block_index),
+                    ckerc20_token_symbol: token.ckerc20_token_symbol.clone(),
+                    erc20_contract_address: event.erc20_contract_address,
+                },
+            )
+        });
+        log!(
+            INFO,
+            "Minted {} {} to {} in block {block_index}",
+            event.value,
+            token.ckerc20_token_symbol,
+            event.principal
+        );
+    }
+
     if error_count > 0 {
         log!(
             INFO,
             "Failed to mint {error_count} events, rescheduling the minting"
         );
-        ic_cdk_timers::set_timer(crate::MINT_RETRY_DELAY, || ic_cdk::spawn(mint_cketh()));
+        ic_cdk_timers::set_timer(crate::MINT_RETRY_DELAY, || ic_cdk::spawn(mint()));
+    }
+}
+
+/// The helper smart contracts whose logs are scraped by the minter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LogScraping {
+    /// Deposits of ETH.
+    Eth,
+    /// Deposits of ERC-20 tokens.
+    Erc20,
+}
+
+impl LogScraping {
+    fn last_scraped_block_number(&self, state: &State) -> BlockNumber {
+        match self {
+            LogScraping::Eth => state.last_scraped_block_number,
+            LogScraping::Erc20 => state.last_erc20_scraped_block_number,
+        }
+    }
+
+    fn set_last_scraped_block_number(&self, state: &mut State, block_number: BlockNumber) {
+        match self {
+            LogScraping::Eth => state.last_scraped_block_number = block_number,
+            LogScraping::Erc20 => state.last_erc20_scraped_block_number = block_number,
+        }
     }
 }
 
 /// Scraps Ethereum logs between `from` and `min(from + MAX_BLOCK_SPREAD, to)` since certain RPC providers
 /// require that the number of blocks queried is no greater than MAX_BLOCK_SPREAD.
 /// Returns the last block number that was scraped (which is `min(from + MAX_BLOCK_SPREAD, to)`).
-async fn scrap_eth_logs_range_inclusive(
+async fn scrap_logs_range_inclusive(
+    scraping: LogScraping,
     contract_address: Address,
     from: BlockNumber,
     to: BlockNumber,
@@ -102,60 +190,54 @@ async fn scrap_eth_logs_range_inclusive(
             let last_scraped_block_number = min(max_to, to);
             log!(
                 DEBUG,
-                "Scrapping ETH logs from block {:?} to block {:?}...",
+                "Scrapping {scraping:?} logs from block {:?} to block {:?}...",
                 from,
                 last_scraped_block_number
             );
 
-            let (transaction_events, errors) = match crate::eth_logs::last_received_eth_events(
-                contract_address,
-                from,
-                last_scraped_block_number,
-            )
-            .await
+            let result: Result<(Vec<ReceivedEvent>, Vec<ReceivedEthEventError>), _> = match scraping
             {
+                LogScraping::Eth => crate::eth_logs::last_received_eth_events(
+                    contract_address,
+                    from,
+                    last_scraped_block_number,
+                )
+                .await
+                .map(|(events, errors)| {
+                    (
+                        events.into_iter().map(ReceivedEvent::from).collect(),
+                        errors,
+                    )
+                }),
+                LogScraping::Erc20 => crate::eth_logs::last_received_erc20_events(
+                    contract_address,
+                    from,
+                    last_scraped_block_number,
+                )
+                .await
+                .map(|(events, errors)| {
+                    (
+                        events.into_iter().map(ReceivedEvent::from).collect(),
+                        errors,
+                    )
+                }),
+            };
+            let (transaction_events, errors) = match result {
                 Ok((events, errors)) => (events, errors),
                 Err(e) => {
                     log!(
                         INFO,
-                        "Failed to get ETH logs from block {from} to block {last_scraped_block_number}: {e:?}",
+                        "Failed to get {scraping:?} logs from block {from} to block {last_scraped_block_number}: {e:?}",
                     );
                     return from;
                 }
             };
             let has_new_events = !transaction_events.is_empty();
             for event in transaction_events {
-                log!(
-                    INFO,
-                    "Received event {event:?}; will mint {} wei to {}",
-                    event.value,
-                    event.principal
-                );
-                if crate::blocklist::is_blocked(event.from_address) {
-                    log!(
-                        INFO,
-                        "Received event from a blocked address: {} for {} WEI",
-                        event.from_address,
-                        event.value,
-                    );
-                    mutate_state(|s| {
-                        process_event(
-                            s,
-                            EventType::InvalidDeposit {
-                                event_source: crate::eth_logs::EventSource {
-                                    transaction_hash: event.transaction_hash,
-                                    log_index: event.log_index,
-                                },
-                                reason: format!("blocked address {}", event.from_address),
-                            },
-                        )
-                    });
-                } else {
-                    mutate_state(|s| process_event(s, EventType::AcceptedDeposit(event)));
-                }
+                process_received_event(event);
             }
             if has_new_events {
-                ic_cdk_timers::set_timer(Duration::from_secs(0), || ic_cdk::spawn(mint_cketh()));
+                ic_cdk_timers::set_timer(Duration::from_secs(0), || ic_cdk::spawn(mint()));
             }
             for error in errors {
                 if let ReceivedEthEventError::InvalidEventSource { source, error } = &error {
@@ -171,13 +253,13 @@ async fn scrap_eth_logs_range_inclusive(
                 }
                 report_transaction_error(error);
             }
-            mutate_state(|s| s.last_scraped_block_number = last_scraped_block_number);
+            mutate_state(|s| scraping.set_last_scraped_block_number(s, last_scraped_block_number));
             last_scraped_block_number
         }
         Ordering::Equal => {
             log!(
                 DEBUG,
-                "[scrap_eth_logs] Skipping scrapping ETH logs: no new blocks",
+                "[scrap_eth_logs] Skipping scrapping {scraping:?} logs: no new blocks",
             );
             to
         }
@@ -190,21 +272,72 @@ async fn scrap_eth_logs_range_inclusive(
     }
 }
 
+fn process_received_event(event: ReceivedEvent) {
+    log!(INFO, "Received event {event:?}");
+    let event_source = event.source();
+    let from_address = event.from_address();
+    if crate::blocklist::is_blocked(from_address) {
+        log!(
+            INFO,
+            "Received event from a blocked address: {from_address}: {event:?}",
+        );
+        mutate_state(|s| {
+            process_event(
+                s,
+                EventType::InvalidDeposit {
+                    event_source,
+                    reason: format!("blocked address {from_address}"),
+                },
+            )
+        });
+        return;
+    }
+    match event {
+        ReceivedEvent::Eth(event) => {
+            mutate_state(|s| process_event(s, EventType::AcceptedDeposit(event)));
+        }
+        ReceivedEvent::Erc20(event) => {
+            let erc20_contract_address = event.erc20_contract_address;
+            if read_state(|s| s.ckerc20_tokens.contains_key(&erc20_contract_address)) {
+                mutate_state(|s| process_event(s, EventType::AcceptedErc20Deposit(event)));
+            } else {
+                log!(
+                    INFO,
+                    "Received event for an unsupported ERC-20 token {erc20_contract_address}: {event:?}",
+                );
+                mutate_state(|s| {
+                    process_event(
+                        s,
+                        EventType::InvalidDeposit {
+                            event_source,
+                            reason: format!("unsupported ERC-20 token {erc20_contract_address}"),
+                        },
+                    )
+                });
+            }
+        }
+    }
+}
+
 pub async fn scrap_eth_logs() {
     let _guard = match TimerGuard::new(TaskType::ScrapEthLogs) {
         Ok(guard) => guard,
         Err(_) => return,
     };
-    let contract_address = match read_state(|s| s.ethereum_contract_address) {
-        Some(address) => address,
-        None => {
-            log!(
-                DEBUG,
-                "[scrap_eth_logs]: skipping scrapping ETH logs: no contract address"
-            );
-            return;
-        }
-    };
+    let (eth_contract_address, erc20_helper_contract_address) = read_state(|s| {
+        (
+            s.ethereum_contract_address,
+            s.erc20_helper_contract_address
+                .filter(|_| !s.ckerc20_tokens.is_empty()),
+        )
+    });
+    if eth_contract_address.is_none() && erc20_helper_contract_address.is_none() {
+        log!(
+            DEBUG,
+            "[scrap_eth_logs]: skipping scrapping ETH logs: no contract address"
+        );
+        return;
+    }
     let last_block_number = match update_last_observed_block_number().await {
         Some(block_number) => block_number,
         None => {
@@ -215,13 +348,27 @@ pub async fn scrap_eth_logs() {
             return;
         }
     };
-    let mut last_scraped_block_number = read_state(|s| s.last_scraped_block_number);
+    if let Some(contract_address) = eth_contract_address {
+        scrap_logs_until(LogScraping::Eth, contract_address, last_block_number).await;
+    }
+    if let Some(contract_address) = erc20_helper_contract_address {
+        scrap_logs_until(LogScraping::Erc20, contract_address, last_block_number).await;
+    }
+}
+
+async fn scrap_logs_until(
+    scraping: LogScraping,
+    contract_address: Address,
+    last_block_number: BlockNumber,
+) {
+    let mut last_scraped_block_number = read_state(|s| scraping.last_scraped_block_number(s));
 
     while last_scraped_block_number < last_block_number {
         let next_block_to_query = last_scraped_block_number
             .checked_increment()
             .unwrap_or(BlockNumber::MAX);
-        last_scraped_block_number = scrap_eth_logs_range_inclusive(
+        last_scraped_block_number = scrap_logs_range_inclusive(
+            scraping,
             contract_address,
             next_block_to_query,
             last_block_number,
@@ -231,20 +378,20 @@ pub async fn scrap_eth_logs() {
 }
 
 pub async fn update_last_observed_block_number() -> Option<BlockNumber> {
-    let block_height = read_state(State::ethereum_block_height);
+    let finality = read_state(State::finality);
     match read_state(EthRpcClient::from_state)
-        .eth_get_block_by_number(BlockSpec::Tag(block_height))
+        .eth_get_block_by_number(finality.block_to_query())
         .await
     {
         Ok(latest_block) => {
-            let block_number = Some(latest_block.number);
+            let block_number = Some(finality.last_final_block_number(latest_block.number));
             mutate_state(|s| s.last_observed_block_number = block_number);
             block_number
         }
         Err(e) => {
             log!(
                 INFO,
-                "Failed to get the latest {block_height} block number: {e:?}"
+                "Failed to get the latest block number with finality {finality}: {e:?}"
             );
             read_state(|s| s.last_observed_block_number)
         }
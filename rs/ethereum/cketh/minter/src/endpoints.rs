@@ -1,6 +1,6 @@
 use crate::state::transactions::EthWithdrawalRequest;
 use crate::tx::{SignedEip1559TransactionRequest, TransactionPrice};
-use candid::{CandidType, Deserialize, Nat};
+use candid::{CandidType, Deserialize, Nat, Principal};
 use icrc_ledger_types::icrc2::transfer_from::TransferFromError;
 use minicbor::{Decode, Encode};
 use std::fmt::{Display, Formatter};
@@ -60,6 +60,28 @@ pub enum CandidBlockTag {
     Finalized,
 }
 
+#[derive(CandidType, Debug, Deserialize, Clone, Encode, Decode, PartialEq, Eq)]
+pub enum CandidFeeModel {
+    /// EIP-1559 fee estimation of Ethereum, with a minimum max priority fee of 1.5 gwei.
+    #[n(0)]
+    Eip1559,
+    /// EIP-1559 fee estimation with a fixed max priority fee per gas.
+    #[n(1)]
+    Eip1559FixedPriorityFee {
+        #[cbor(n(0), with = "crate::cbor::nat")]
+        max_priority_fee_per_gas: Nat,
+    },
+    /// Fee estimation of OP Stack rollups: EIP-1559 with a fixed max priority fee per gas,
+    /// plus at most `max_l1_data_fee` Wei for posting the transaction data on L1.
+    #[n(2)]
+    OpStack {
+        #[cbor(n(0), with = "crate::cbor::nat")]
+        max_priority_fee_per_gas: Nat,
+        #[cbor(n(1), with = "crate::cbor::nat")]
+        max_l1_data_fee: Nat,
+    },
+}
+
 impl From<EthWithdrawalRequest> for RetrieveEthRequest {
     fn from(value: EthWithdrawalRequest) -> Self {
         Self {
@@ -165,6 +187,138 @@ impl From<TransferFromError> for WithdrawalError {
     }
 }
 
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AddCkErc20Token {
+    pub chain_id: Nat,
+    pub address: String,
+    pub ckerc20_token_symbol: String,
+    pub ckerc20_ledger_id: Principal,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CkErc20Token {
+    pub ckerc20_token_symbol: String,
+    pub erc20_contract_address: String,
+    pub ledger_canister_id: Principal,
+}
+
+impl From<crate::erc20::CkErc20Token> for CkErc20Token {
+    fn from(value: crate::erc20::CkErc20Token) -> Self {
+        Self {
+            ckerc20_token_symbol: value.ckerc20_token_symbol,
+            erc20_contract_address: value.erc20_contract_address.to_string(),
+            ledger_canister_id: value.ckerc20_ledger_id,
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct WithdrawErc20Arg {
+    pub amount: Nat,
+    pub ckerc20_ledger_id: Principal,
+    pub recipient: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct RetrieveErc20Request {
+    pub cketh_block_index: Nat,
+    pub ckerc20_block_index: Nat,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+pub enum WithdrawErc20Error {
+    TokenNotSupported {
+        supported_tokens: Vec<CkErc20Token>,
+    },
+    RecipientAddressBlocked {
+        address: String,
+    },
+    CkEthLedgerError {
+        error: LedgerError,
+    },
+    CkErc20LedgerError {
+        cketh_block_index: Nat,
+        error: LedgerError,
+    },
+    TemporarilyUnavailable(String),
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq)]
+pub enum LedgerError {
+    InsufficientFunds {
+        balance: Nat,
+        failed_burn_amount: Nat,
+        token_symbol: String,
+        ledger_id: Principal,
+    },
+    AmountTooLow {
+        minimum_burn_amount: Nat,
+        failed_burn_amount: Nat,
+        token_symbol: String,
+        ledger_id: Principal,
+    },
+    InsufficientAllowance {
+        allowance: Nat,
+        failed_burn_amount: Nat,
+        token_symbol: String,
+        ledger_id: Principal,
+    },
+    TemporarilyUnavailable(String),
+}
+
+impl LedgerError {
+    /// Converts the error returned by the ledger `ledger_id` when burning
+    /// `failed_burn_amount` tokens with symbol `token_symbol`.
+    pub fn from_transfer_from_error(
+        transfer_from_error: TransferFromError,
+        failed_burn_amount: Nat,
+        token_symbol: String,
+        ledger_id: Principal,
+    ) -> Self {
+        match transfer_from_error {
+            TransferFromError::BadFee { expected_fee } => {
+                panic!("bug: bad fee, expected fee: {expected_fee}")
+            }
+            TransferFromError::BadBurn { min_burn_amount } => Self::AmountTooLow {
+                minimum_burn_amount: min_burn_amount,
+                failed_burn_amount,
+                token_symbol,
+                ledger_id,
+            },
+            TransferFromError::InsufficientFunds { balance } => Self::InsufficientFunds {
+                balance,
+                failed_burn_amount,
+                token_symbol,
+                ledger_id,
+            },
+            TransferFromError::InsufficientAllowance { allowance } => {
+                Self::InsufficientAllowance {
+                    allowance,
+                    failed_burn_amount,
+                    token_symbol,
+                    ledger_id,
+                }
+            }
+            TransferFromError::TooOld => panic!("bug: transfer too old"),
+            TransferFromError::CreatedInFuture { ledger_time } => {
+                panic!("bug: created in future, ledger time: {ledger_time}")
+            }
+            TransferFromError::Duplicate { duplicate_of } => {
+                panic!("bug: duplicate transfer of: {duplicate_of}")
+            }
+            TransferFromError::TemporarilyUnavailable => Self::TemporarilyUnavailable(format!(
+                "{token_symbol} ledger ({ledger_id}) temporarily unavailable, try again"
+            )),
+            TransferFromError::GenericError {
+                error_code,
+                message,
+            } => Self::TemporarilyUnavailable(format!(
+                "{token_symbol} ledger ({ledger_id}) unreachable, error code: {error_code}, with message: {message}"
+            )),
+        }
+    }
+}
+
 pub mod events {
     use crate::lifecycle::init::InitArg;
     use crate::lifecycle::upgrade::UpgradeArg;
@@ -281,5 +435,54 @@ pub mod events {
             withdrawal_id: Nat,
             reimbursed_amount: Nat,
         },
+        AcceptedErc20Deposit {
+            transaction_hash: String,
+            block_number: Nat,
+            log_index: Nat,
+            from_address: String,
+            value: Nat,
+            principal: Principal,
+            erc20_contract_address: String,
+        },
+        MintedCkErc20 {
+            event_source: EventSource,
+            mint_block_index: Nat,
+            ckerc20_token_symbol: String,
+            erc20_contract_address: String,
+        },
+        SyncedErc20ToBlock {
+            block_number: Nat,
+        },
+        AddedCkErc20Token {
+            chain_id: Nat,
+            address: String,
+            ckerc20_token_symbol: String,
+            ckerc20_ledger_id: Principal,
+        },
+        AcceptedErc20WithdrawalRequest {
+            max_transaction_fee: Nat,
+            withdrawal_amount: Nat,
+            erc20_contract_address: String,
+            destination: String,
+            cketh_ledger_burn_index: Nat,
+            ckerc20_ledger_id: Principal,
+            ckerc20_ledger_burn_index: Nat,
+            from: Principal,
+            from_subaccount: Option<[u8; 32]>,
+        },
+        FailedErc20WithdrawalRequest {
+            withdrawal_id: Nat,
+            reimbursed_amount: Nat,
+            to: Principal,
+            to_subaccount: Option<[u8; 32]>,
+        },
+        ReimbursedErc20Withdrawal {
+            withdrawal_id: Nat,
+            burn_in_block: Nat,
+            reimbursed_in_block: Nat,
+            ledger_id: Principal,
+            reimbursed_amount: Nat,
+            transaction_hash: String,
+        },
     }
 }
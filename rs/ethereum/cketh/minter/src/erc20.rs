@@ -0,0 +1,103 @@
+//! Module dealing with ckERC20 tokens, i.e., ERC-20 tokens on Ethereum
+//! that are represented on the IC by an ICRC-1 ledger controlled by the minter.
+
+#[cfg(test)]
+mod tests;
+
+use crate::address::Address;
+use crate::endpoints::AddCkErc20Token;
+use crate::lifecycle::EthereumNetwork;
+use crate::numeric::Erc20Value;
+use candid::Principal;
+use hex_literal::hex;
+use minicbor::{Decode, Encode};
+use num_traits::ToPrimitive;
+use std::fmt;
+use std::str::FromStr;
+
+/// Selector of the ERC-20 function `transfer(address,uint256)`,
+/// i.e. the first 4 bytes of the Keccak-256 hash of the function signature.
+pub const ERC20_TRANSFER_FUNCTION_SELECTOR: [u8; 4] = hex!("a9059cbb");
+
+/// Maximum length of the symbol of a ckERC20 token, e.g. `ckUSDC`.
+pub const MAX_CKERC20_TOKEN_SYMBOL_LENGTH: usize = 20;
+
+/// An ERC-20 token supported by the minter together with its ckERC20 ledger.
+#[derive(Clone, PartialEq, Eq, Encode, Decode)]
+pub struct CkErc20Token {
+    #[n(0)]
+    pub erc20_ethereum_network: EthereumNetwork,
+    #[n(1)]
+    pub erc20_contract_address: Address,
+    #[n(2)]
+    pub ckerc20_token_symbol: String,
+    #[cbor(n(3), with = "crate::cbor::principal")]
+    pub ckerc20_ledger_id: Principal,
+}
+
+impl fmt::Debug for CkErc20Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CkErc20Token")
+            .field("erc20_ethereum_network", &self.erc20_ethereum_network)
+            .field("erc20_contract_address", &self.erc20_contract_address)
+            .field("ckerc20_token_symbol", &self.ckerc20_token_symbol)
+            .field(
+                "ckerc20_ledger_id",
+                &format_args!("{}", self.ckerc20_ledger_id),
+            )
+            .finish()
+    }
+}
+
+impl TryFrom<AddCkErc20Token> for CkErc20Token {
+    type Error = String;
+
+    fn try_from(value: AddCkErc20Token) -> Result<Self, Self::Error> {
+        let chain_id = value
+            .chain_id
+            .0
+            .to_u64()
+            .ok_or_else(|| format!("chain ID {} does not fit into u64", value.chain_id))?;
+        let erc20_ethereum_network = EthereumNetwork::try_from(chain_id)?;
+        let erc20_contract_address = Address::from_str(&value.address)?;
+        if erc20_contract_address == Address::ZERO {
+            return Err("ERC-20 contract address cannot be the zero address".to_string());
+        }
+        validate_ckerc20_token_symbol(&value.ckerc20_token_symbol)?;
+        if value.ckerc20_ledger_id == Principal::anonymous() {
+            return Err("ckERC20 ledger ID cannot be the anonymous principal".to_string());
+        }
+        Ok(Self {
+            erc20_ethereum_network,
+            erc20_contract_address,
+            ckerc20_token_symbol: value.ckerc20_token_symbol,
+            ckerc20_ledger_id: value.ckerc20_ledger_id,
+        })
+    }
+}
+
+fn validate_ckerc20_token_symbol(symbol: &str) -> Result<(), String> {
+    if symbol.is_empty() || symbol.len() > MAX_CKERC20_TOKEN_SYMBOL_LENGTH {
+        return Err(format!(
+            "ckERC20 token symbol must have between 1 and {MAX_CKERC20_TOKEN_SYMBOL_LENGTH} characters, got {}",
+            symbol.len()
+        ));
+    }
+    if !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!(
+            "ckERC20 token symbol must be ASCII alphanumeric, got {symbol}"
+        ));
+    }
+    Ok(())
+}
+
+/// ABI-encodes a call to the ERC-20 function `transfer(address,uint256)`
+/// sending `value` tokens to `to`.
+pub fn encode_erc20_transfer_data(to: &Address, value: Erc20Value) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + 32 + 32);
+    data.extend_from_slice(&ERC20_TRANSFER_FUNCTION_SELECTOR);
+    data.extend_from_slice(&[0_u8; 12]);
+    data.extend_from_slice(to.as_ref());
+    data.extend_from_slice(&value.to_be_bytes());
+    data
+}
@@ -0,0 +1,105 @@
+mod encode_erc20_transfer_data {
+    use crate::address::Address;
+    use crate::erc20::encode_erc20_transfer_data;
+    use crate::numeric::Erc20Value;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_encode_transfer_call() {
+        let to = Address::from_str("0xdd2851cdd40ae6536831558dd46db62fac7a844d").unwrap();
+
+        let data = encode_erc20_transfer_data(&to, Erc20Value::new(1_000_000));
+
+        assert_eq!(
+            hex::encode(data),
+            "a9059cbb\
+             000000000000000000000000dd2851cdd40ae6536831558dd46db62fac7a844d\
+             00000000000000000000000000000000000000000000000000000000000f4240"
+        );
+    }
+
+    #[test]
+    fn should_have_correct_function_selector() {
+        use crate::erc20::ERC20_TRANSFER_FUNCTION_SELECTOR;
+        use ic_crypto_sha3::Keccak256;
+
+        let hash = Keccak256::hash("transfer(address,uint256)");
+        assert_eq!(hash[..4], ERC20_TRANSFER_FUNCTION_SELECTOR);
+    }
+}
+
+mod ckerc20_token {
+    use crate::address::Address;
+    use crate::endpoints::AddCkErc20Token;
+    use crate::erc20::CkErc20Token;
+    use crate::lifecycle::EthereumNetwork;
+    use assert_matches::assert_matches;
+    use candid::{Nat, Principal};
+    use std::str::FromStr;
+
+    #[test]
+    fn should_convert_valid_arg() {
+        assert_eq!(
+            CkErc20Token::try_from(valid_arg()),
+            Ok(CkErc20Token {
+                erc20_ethereum_network: EthereumNetwork::Sepolia,
+                erc20_contract_address: Address::from_str(
+                    "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238"
+                )
+                .unwrap(),
+                ckerc20_token_symbol: "ckSepoliaUSDC".to_string(),
+                ckerc20_ledger_id: Principal::from_text("mxzaz-hqaaa-aaaar-qaada-cai").unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_fail_when_arg_invalid() {
+        assert_matches!(
+            CkErc20Token::try_from(AddCkErc20Token {
+                chain_id: Nat::from(5_u8),
+                ..valid_arg()
+            }),
+            Err(e) if e.contains("chain ID")
+        );
+        assert_matches!(
+            CkErc20Token::try_from(AddCkErc20Token {
+                address: "0x0000000000000000000000000000000000000000".to_string(),
+                ..valid_arg()
+            }),
+            Err(e) if e.contains("zero address")
+        );
+        assert_matches!(
+            CkErc20Token::try_from(AddCkErc20Token {
+                address: "1c7d4b196cb0c7b01d743fbc6116a902379c7238".to_string(),
+                ..valid_arg()
+            }),
+            Err(_)
+        );
+        for invalid_symbol in ["", "ckUSDC!", "ck USDC", "ckTokenWithAVeryLongSymbol"] {
+            assert_matches!(
+                CkErc20Token::try_from(AddCkErc20Token {
+                    ckerc20_token_symbol: invalid_symbol.to_string(),
+                    ..valid_arg()
+                }),
+                Err(e) if e.contains("symbol")
+            );
+        }
+        assert_matches!(
+            CkErc20Token::try_from(AddCkErc20Token {
+                ckerc20_ledger_id: Principal::anonymous(),
+                ..valid_arg()
+            }),
+            Err(e) if e.contains("anonymous")
+        );
+    }
+
+    fn valid_arg() -> AddCkErc20Token {
+        AddCkErc20Token {
+            chain_id: Nat::from(11155111_u64),
+            address: "0x1c7d4b196cb0c7b01d743fbc6116a902379c7238".to_string(),
+            ckerc20_token_symbol: "ckSepoliaUSDC".to_string(),
+            ckerc20_ledger_id: Principal::from_text("mxzaz-hqaaa-aaaar-qaada-cai").unwrap(),
+        }
+    }
+}
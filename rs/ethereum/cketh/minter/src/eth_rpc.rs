@@ -577,9 +577,18 @@ impl<T: HttpResponsePayload> HttpResponsePayload for Option<T> {}
 
 impl HttpResponsePayload for TransactionCount {}
 
+/// Endpoint of a JSON-RPC provider.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RpcEndpoint {
+    /// URL to call, which may contain an API key and must therefore never be logged.
+    pub url: String,
+    /// URL of the provider without API key.
+    pub public_url: String,
+}
+
 /// Calls a JSON-RPC method on an Ethereum node at the specified URL.
 pub async fn call<I, O>(
-    url: impl Into<String>,
+    endpoint: RpcEndpoint,
     method: impl Into<String>,
     params: I,
     mut response_size_estimate: ResponseSizeEstimate,
@@ -595,7 +604,7 @@ where
         method: eth_method.clone(),
         id: 1,
     };
-    let url = url.into();
+    let RpcEndpoint { url, public_url } = endpoint;
 
     loop {
         rpc_request.id = mutate_state(State::next_request_id);
@@ -603,7 +612,7 @@ where
         log!(
             TRACE_HTTP,
             "Calling url: {}, with payload: {payload}",
-            url.clone()
+            public_url
         );
 
         let effective_size_estimate = response_size_estimate.get() + HEADER_SIZE_LIMIT;
@@ -666,7 +675,7 @@ where
             TRACE_HTTP,
             "Got response: {} from url: {} with status: {}",
             String::from_utf8_lossy(&response.body),
-            url,
+            public_url,
             response.status
         );
 
@@ -3,46 +3,142 @@ use crate::eth_rpc::{
     Hash, HttpOutcallError, HttpOutcallResult, HttpResponsePayload, JsonRpcResult, LogEntry,
     ResponseSizeEstimate, SendRawTransactionResult,
 };
-use crate::eth_rpc_client::providers::{RpcNodeProvider, MAINNET_PROVIDERS, SEPOLIA_PROVIDERS};
+use crate::eth_rpc_client::providers::{
+    ApiKeys, RpcNodeProvider, MAINNET_PROVIDERS, SEPOLIA_PROVIDERS,
+};
 use crate::eth_rpc_client::requests::GetTransactionCountParams;
 use crate::eth_rpc_client::responses::TransactionReceipt;
 use crate::lifecycle::EthereumNetwork;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::TransactionCount;
-use crate::state::State;
+use crate::state::{mutate_state, State};
+use candid::{CandidType, Deserialize};
 use ic_canister_log::log;
+use minicbor::{Decode, Encode};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 
-mod providers;
+pub mod providers;
 pub mod requests;
 pub mod responses;
 
 #[cfg(test)]
 mod tests;
 
+/// JSON-RPC methods whose responses from the different providers are reduced to a single one.
+#[derive(
+    CandidType, Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Encode, Decode,
+)]
+#[cbor(index_only)]
+pub enum RpcMethod {
+    #[n(0)]
+    EthGetLogs,
+    #[n(1)]
+    EthGetBlockByNumber,
+    #[n(2)]
+    EthGetTransactionReceipt,
+    #[n(3)]
+    EthFeeHistory,
+}
+
+impl RpcMethod {
+    pub fn default_reduction_strategy(&self) -> ReductionStrategy {
+        match self {
+            RpcMethod::EthGetLogs
+            | RpcMethod::EthGetBlockByNumber
+            | RpcMethod::EthGetTransactionReceipt => ReductionStrategy::Equality,
+            RpcMethod::EthFeeHistory => ReductionStrategy::StrictMajority,
+        }
+    }
+
+    /// Whether the method supports the given reduction strategy.
+    /// Taking the minimum of deposit logs or transaction receipts is meaningless and could lead to
+    /// skipping deposits, so only methods with a meaningful ordering support [`ReductionStrategy::MinByKey`].
+    pub fn supports(&self, strategy: ReductionStrategy) -> bool {
+        match (self, strategy) {
+            (_, ReductionStrategy::Equality) | (_, ReductionStrategy::StrictMajority) => true,
+            (RpcMethod::EthGetBlockByNumber, ReductionStrategy::MinByKey)
+            | (RpcMethod::EthFeeHistory, ReductionStrategy::MinByKey) => true,
+            (RpcMethod::EthGetLogs, ReductionStrategy::MinByKey)
+            | (RpcMethod::EthGetTransactionReceipt, ReductionStrategy::MinByKey) => false,
+        }
+    }
+}
+
+/// Strategy used to reduce the responses of the different providers to a single one.
+#[derive(
+    CandidType, Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Encode, Decode,
+)]
+#[cbor(index_only)]
+pub enum ReductionStrategy {
+    /// All providers must return the same response.
+    #[n(0)]
+    Equality,
+    /// Strictly more than half of the providers must return the same response.
+    #[n(1)]
+    StrictMajority,
+    /// Take the response with the smallest key, e.g. the lowest block number.
+    #[n(2)]
+    MinByKey,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct EthRpcClient {
     chain: EthereumNetwork,
+    custom_providers: Vec<RpcNodeProvider>,
+    api_keys: ApiKeys,
+    reduction_strategies: BTreeMap<RpcMethod, ReductionStrategy>,
 }
 
 impl EthRpcClient {
-    const fn new(chain: EthereumNetwork) -> Self {
-        Self { chain }
+    fn new(chain: EthereumNetwork) -> Self {
+        Self {
+            chain,
+            custom_providers: vec![],
+            api_keys: ApiKeys::default(),
+            reduction_strategies: BTreeMap::new(),
+        }
     }
 
-    pub const fn from_state(state: &State) -> Self {
-        Self::new(state.ethereum_network())
+    pub fn from_state(state: &State) -> Self {
+        Self {
+            custom_providers: state
+                .rpc_providers
+                .iter()
+                .cloned()
+                .map(RpcNodeProvider::Custom)
+                .collect(),
+            api_keys: crate::storage::rpc_api_keys(),
+            reduction_strategies: state.rpc_reduction_strategies.clone(),
+            ..Self::new(state.ethereum_network())
+        }
     }
 
     fn providers(&self) -> &[RpcNodeProvider] {
+        if !self.custom_providers.is_empty() {
+            return &self.custom_providers;
+        }
         match self.chain {
             EthereumNetwork::Mainnet => &MAINNET_PROVIDERS,
             EthereumNetwork::Sepolia => &SEPOLIA_PROVIDERS,
+            // Layer 2 networks require custom providers, which is enforced when validating the state.
+            EthereumNetwork::Optimism
+            | EthereumNetwork::OptimismSepolia
+            | EthereumNetwork::Base
+            | EthereumNetwork::BaseSepolia
+            | EthereumNetwork::ArbitrumOne
+            | EthereumNetwork::ArbitrumSepolia => &[],
         }
     }
 
+    fn reduction_strategy(&self, method: RpcMethod) -> ReductionStrategy {
+        self.reduction_strategies
+            .get(&method)
+            .copied()
+            .unwrap_or_else(|| method.default_reduction_strategy())
+    }
+
     /// Query all providers in sequence until one returns an ok result
     /// (which could still be a JsonRpcResult::Error).
     /// If none of the providers return an ok result, return the last error.
@@ -58,6 +154,7 @@ impl EthRpcClient {
         I: Serialize + Clone,
         O: DeserializeOwned + HttpResponsePayload + Debug,
     {
+        let method: String = method.into();
         let mut last_result: Option<HttpOutcallResult<JsonRpcResult<O>>> = None;
         for provider in self.providers() {
             log!(
@@ -66,12 +163,13 @@ impl EthRpcClient {
                 provider
             );
             let result = eth_rpc::call(
-                provider.url().to_string(),
+                provider.endpoint(&self.api_keys),
                 method.clone(),
                 params.clone(),
                 response_size_estimate,
             )
             .await;
+            record_provider_error(provider, &method, &result);
             match result {
                 Ok(JsonRpcResult::Result(value)) => return Ok(JsonRpcResult::Result(value)),
                 Ok(json_rpc_error @ JsonRpcResult::Error { .. }) => {
@@ -105,13 +203,14 @@ impl EthRpcClient {
         I: Serialize + Clone,
         O: DeserializeOwned + HttpResponsePayload,
     {
+        let method: String = method.into();
         let providers = self.providers();
         let results = {
             let mut fut = Vec::with_capacity(providers.len());
             for provider in providers {
                 log!(DEBUG, "[parallel_call]: will call provider: {:?}", provider);
                 fut.push(eth_rpc::call(
-                    provider.url().to_string(),
+                    provider.endpoint(&self.api_keys),
                     method.clone(),
                     params.clone(),
                     response_size_estimate,
@@ -119,6 +218,9 @@ impl EthRpcClient {
             }
             futures::future::join_all(fut).await
         };
+        for (provider, result) in providers.iter().zip(results.iter()) {
+            record_provider_error(provider, &method, result);
+        }
         MultiCallResults::from_non_empty_iter(providers.iter().cloned().zip(results.into_iter()))
     }
 
@@ -130,7 +232,11 @@ impl EthRpcClient {
         let results: MultiCallResults<Vec<LogEntry>> = self
             .parallel_call("eth_getLogs", vec![params], ResponseSizeEstimate::new(100))
             .await;
-        results.reduce_with_equality()
+        results.reduce(self.reduction_strategy(RpcMethod::EthGetLogs), |logs| {
+            logs.iter()
+                .map(|log| (log.block_number, log.log_index))
+                .collect::<Vec<_>>()
+        })
     }
 
     pub async fn eth_get_block_by_number(
@@ -149,7 +255,10 @@ impl EthRpcClient {
                 ResponseSizeEstimate::new(6 * 1024),
             )
             .await;
-        results.reduce_with_equality()
+        results.reduce(
+            self.reduction_strategy(RpcMethod::EthGetBlockByNumber),
+            |block| block.number,
+        )
     }
 
     pub async fn eth_get_transaction_receipt(
@@ -163,7 +272,14 @@ impl EthRpcClient {
                 ResponseSizeEstimate::new(700),
             )
             .await;
-        results.reduce_with_equality()
+        results.reduce(
+            self.reduction_strategy(RpcMethod::EthGetTransactionReceipt),
+            |receipt| {
+                receipt
+                    .as_ref()
+                    .map(|receipt| (receipt.block_number, receipt.block_hash))
+            },
+        )
     }
 
     pub async fn eth_fee_history(
@@ -174,7 +290,10 @@ impl EthRpcClient {
         let results: MultiCallResults<FeeHistory> = self
             .parallel_call("eth_feeHistory", params, ResponseSizeEstimate::new(512))
             .await;
-        results.reduce_with_strict_majority_by_key(|fee_history| fee_history.oldest_block)
+        results.reduce(
+            self.reduction_strategy(RpcMethod::EthFeeHistory),
+            |fee_history| fee_history.oldest_block,
+        )
     }
 
     pub async fn eth_send_raw_transaction(
@@ -204,6 +323,16 @@ impl EthRpcClient {
     }
 }
 
+fn record_provider_error<O>(
+    provider: &RpcNodeProvider,
+    method: &str,
+    result: &HttpOutcallResult<JsonRpcResult<O>>,
+) {
+    if !matches!(result, Ok(JsonRpcResult::Result(_))) {
+        mutate_state(|s| s.record_rpc_provider_error(provider.name(), method));
+    }
+}
+
 /// Aggregates responses of different providers to the same query.
 /// Guaranteed to be non-empty.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -282,6 +411,20 @@ pub enum MultiCallError<T> {
 }
 
 impl<T: Debug + PartialEq> MultiCallResults<T> {
+    /// Reduces the results with the given strategy.
+    /// The key extractor is used by [`ReductionStrategy::StrictMajority`] and [`ReductionStrategy::MinByKey`].
+    pub fn reduce<F: Fn(&T) -> K, K: Ord>(
+        self,
+        strategy: ReductionStrategy,
+        extractor: F,
+    ) -> Result<T, MultiCallError<T>> {
+        match strategy {
+            ReductionStrategy::Equality => self.reduce_with_equality(),
+            ReductionStrategy::StrictMajority => self.reduce_with_strict_majority_by_key(extractor),
+            ReductionStrategy::MinByKey => self.reduce_with_min_by_key(extractor),
+        }
+    }
+
     pub fn reduce_with_equality(self) -> Result<T, MultiCallError<T>> {
         let mut results = self.all_ok()?.into_iter();
         let (base_node_provider, base_result) = results
@@ -1,3 +1,12 @@
+use crate::eth_rpc::RpcEndpoint;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Placeholder in the URL of a custom provider that is replaced by the provider's API key
+/// when making the HTTP outcall.
+pub const API_KEY_PLACEHOLDER: &str = "{API_KEY}";
+
 pub(crate) const MAINNET_PROVIDERS: [RpcNodeProvider; 3] = [
     RpcNodeProvider::Ethereum(EthereumProvider::Ankr),
     RpcNodeProvider::Ethereum(EthereumProvider::BlockPi),
@@ -10,19 +19,77 @@ pub(crate) const SEPOLIA_PROVIDERS: [RpcNodeProvider; 3] = [
     RpcNodeProvider::Sepolia(SepoliaProvider::PublicNode),
 ];
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub(crate) enum RpcNodeProvider {
     Ethereum(EthereumProvider),
     Sepolia(SepoliaProvider),
+    Custom(CustomProvider),
 }
 
 impl RpcNodeProvider {
+    /// Public URL of the provider, which never contains an API key.
     pub(crate) fn url(&self) -> &str {
         match self {
             Self::Ethereum(provider) => provider.ethereum_mainnet_endpoint_url(),
             Self::Sepolia(provider) => provider.ethereum_sepolia_endpoint_url(),
+            Self::Custom(provider) => &provider.url,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Self::Ethereum(provider) => provider.name(),
+            Self::Sepolia(provider) => provider.name(),
+            Self::Custom(provider) => &provider.name,
         }
     }
+
+    /// Endpoint to call, where the API key of the provider, if any, is substituted in the URL.
+    pub(crate) fn endpoint(&self, api_keys: &ApiKeys) -> RpcEndpoint {
+        let public_url = self.url().to_string();
+        let url = match (self, api_keys.get(self.name())) {
+            (Self::Custom(_), Some(api_key)) => public_url.replace(API_KEY_PLACEHOLDER, api_key),
+            _ => public_url.clone(),
+        };
+        RpcEndpoint { url, public_url }
+    }
+}
+
+/// JSON-RPC provider configured via the upgrade argument.
+#[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+pub struct CustomProvider {
+    pub name: String,
+    /// URL of the provider, where the API key (if any) is replaced by [`API_KEY_PLACEHOLDER`].
+    pub url: String,
+}
+
+/// API keys of the custom providers, indexed by provider name.
+/// The keys are secret and are therefore never displayed.
+#[derive(Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct ApiKeys(#[n(0)] BTreeMap<String, String>);
+
+impl ApiKeys {
+    pub fn get(&self, provider_name: &str) -> Option<&str> {
+        self.0.get(provider_name).map(String::as_str)
+    }
+
+    pub fn contains(&self, provider_name: &str) -> bool {
+        self.0.contains_key(provider_name)
+    }
+}
+
+impl FromIterator<(String, String)> for ApiKeys {
+    fn from_iter<T: IntoIterator<Item = (String, String)>>(iter: T) -> Self {
+        Self(BTreeMap::from_iter(iter))
+    }
+}
+
+impl fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.keys().map(|name| (name, "<redacted>")))
+            .finish()
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
@@ -43,6 +110,14 @@ impl EthereumProvider {
             EthereumProvider::PublicNode => "https://ethereum.publicnode.com",
         }
     }
+
+    fn name(&self) -> &str {
+        match self {
+            EthereumProvider::Ankr => "ankr",
+            EthereumProvider::BlockPi => "blockpi",
+            EthereumProvider::PublicNode => "publicnode",
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
@@ -63,4 +138,12 @@ impl SepoliaProvider {
             SepoliaProvider::PublicNode => "https://ethereum-sepolia.publicnode.com",
         }
     }
+
+    fn name(&self) -> &str {
+        match self {
+            SepoliaProvider::Ankr => "ankr",
+            SepoliaProvider::BlockPi => "blockpi",
+            SepoliaProvider::PublicNode => "publicnode",
+        }
+    }
 }
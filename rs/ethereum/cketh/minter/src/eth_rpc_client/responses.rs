@@ -30,12 +30,19 @@ pub struct TransactionReceipt {
     /// The hash of the transaction
     #[n(5)]
     pub transaction_hash: Hash,
+
+    /// The fee paid for posting the transaction data on L1, which OP Stack rollups
+    /// charge in addition to the gas.
+    #[n(6)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_fee: Option<Wei>,
 }
 
 impl TransactionReceipt {
     pub fn effective_transaction_fee(&self) -> Wei {
         self.effective_gas_price
             .transaction_cost(self.gas_used)
+            .and_then(|gas_fee| gas_fee.checked_add(self.l1_fee.unwrap_or(Wei::ZERO)))
             .expect("ERROR: overflow during transaction fee calculation")
     }
 }
@@ -1,7 +1,11 @@
 mod eth_rpc_client {
-    use crate::eth_rpc_client::providers::{EthereumProvider, RpcNodeProvider, SepoliaProvider};
-    use crate::eth_rpc_client::EthRpcClient;
+    use crate::eth_rpc::RpcEndpoint;
+    use crate::eth_rpc_client::providers::{
+        ApiKeys, CustomProvider, EthereumProvider, RpcNodeProvider, SepoliaProvider,
+    };
+    use crate::eth_rpc_client::{EthRpcClient, ReductionStrategy, RpcMethod};
     use crate::lifecycle::EthereumNetwork;
+    use maplit::btreemap;
 
     #[test]
     fn should_retrieve_sepolia_providers_in_stable_order() {
@@ -34,6 +38,66 @@ mod eth_rpc_client {
             ]
         );
     }
+
+    #[test]
+    fn should_prefer_custom_providers() {
+        let alchemy = RpcNodeProvider::Custom(CustomProvider {
+            name: "alchemy".to_string(),
+            url: "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}".to_string(),
+        });
+        let client = EthRpcClient {
+            custom_providers: vec![alchemy.clone()],
+            ..EthRpcClient::new(EthereumNetwork::Mainnet)
+        };
+
+        assert_eq!(client.providers(), &[alchemy]);
+    }
+
+    #[test]
+    fn should_substitute_api_key_only_in_called_url() {
+        let api_keys = ApiKeys::from_iter(vec![("alchemy".to_string(), "secret".to_string())]);
+        let alchemy = RpcNodeProvider::Custom(CustomProvider {
+            name: "alchemy".to_string(),
+            url: "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}".to_string(),
+        });
+
+        let endpoint = alchemy.endpoint(&api_keys);
+
+        assert_eq!(
+            endpoint.url,
+            "https://eth-mainnet.g.alchemy.com/v2/secret".to_string()
+        );
+        assert_eq!(
+            endpoint.public_url,
+            "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}".to_string()
+        );
+        assert!(!format!("{:?} {:?}", alchemy, api_keys).contains("secret"));
+        let RpcEndpoint { url, public_url } =
+            RpcNodeProvider::Ethereum(EthereumProvider::Ankr).endpoint(&api_keys);
+        assert_eq!(url, "https://rpc.ankr.com/eth".to_string());
+        assert_eq!(public_url, url);
+    }
+
+    #[test]
+    fn should_use_default_reduction_strategy_unless_overridden() {
+        let client = EthRpcClient {
+            reduction_strategies: btreemap! {RpcMethod::EthGetBlockByNumber => ReductionStrategy::MinByKey},
+            ..EthRpcClient::new(EthereumNetwork::Mainnet)
+        };
+
+        assert_eq!(
+            client.reduction_strategy(RpcMethod::EthGetBlockByNumber),
+            ReductionStrategy::MinByKey
+        );
+        assert_eq!(
+            client.reduction_strategy(RpcMethod::EthGetLogs),
+            ReductionStrategy::Equality
+        );
+        assert_eq!(
+            client.reduction_strategy(RpcMethod::EthFeeHistory),
+            ReductionStrategy::StrictMajority
+        );
+    }
 }
 
 mod multi_call_results {
@@ -435,7 +499,7 @@ mod multi_call_results {
 mod eth_get_transaction_receipt {
     use crate::eth_rpc::Hash;
     use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
-    use crate::numeric::{BlockNumber, GasAmount, WeiPerGas};
+    use crate::numeric::{BlockNumber, GasAmount, Wei, WeiPerGas};
     use assert_matches::assert_matches;
     use proptest::proptest;
     use std::str::FromStr;
@@ -476,10 +540,33 @@ mod eth_get_transaction_receipt {
                     "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d"
                 )
                 .unwrap(),
+                l1_fee: None,
             }
         )
     }
 
+    #[test]
+    fn should_include_l1_fee_of_op_stack_receipt_in_effective_transaction_fee() {
+        const RECEIPT: &str = r#"{
+        "transactionHash": "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",
+        "blockHash": "0x82005d2f17b251900968f01b0ed482cb49b7e1d797342bc504904d442b64dbe4",
+        "blockNumber": "0x4132ec",
+        "effectiveGasPrice": "0x3e8",
+        "gasUsed": "0x5208",
+        "l1Fee": "0x2386f26fc10000",
+        "l1GasUsed": "0x640",
+        "status": "0x01"
+    }"#;
+
+        let receipt: TransactionReceipt = serde_json::from_str(RECEIPT).unwrap();
+
+        assert_eq!(receipt.l1_fee, Some(Wei::new(10_000_000_000_000_000)));
+        assert_eq!(
+            receipt.effective_transaction_fee(),
+            Wei::new(10_000_000_000_000_000 + 1_000 * 21_000)
+        );
+    }
+
     #[test]
     fn should_deserialize_transaction_status() {
         let status: TransactionStatus = serde_json::from_str("\"0x01\"").unwrap();
@@ -1,9 +1,11 @@
 pub mod address;
 pub mod blocklist;
 mod cbor;
+pub mod chain_profile;
 pub mod checked_amount;
 pub mod deposit;
 pub mod endpoints;
+pub mod erc20;
 pub mod eth_logs;
 pub mod eth_rpc;
 pub mod eth_rpc_client;
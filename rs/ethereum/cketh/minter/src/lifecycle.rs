@@ -1,6 +1,8 @@
 //! Module dealing with the lifecycle methods of the ckETH Minter.
+use crate::chain_profile::FeeModel;
 use crate::lifecycle::init::InitArg;
 use crate::lifecycle::upgrade::UpgradeArg;
+use crate::numeric::{GasAmount, Wei, WeiPerGas};
 use candid::{CandidType, Deserialize};
 use minicbor::{Decode, Encode};
 use std::fmt::{Display, Formatter};
@@ -28,6 +30,18 @@ pub enum EthereumNetwork {
     #[n(11155111)]
     #[default]
     Sepolia,
+    #[n(10)]
+    Optimism,
+    #[n(11155420)]
+    OptimismSepolia,
+    #[n(8453)]
+    Base,
+    #[n(84532)]
+    BaseSepolia,
+    #[n(42161)]
+    ArbitrumOne,
+    #[n(421614)]
+    ArbitrumSepolia,
 }
 
 impl EthereumNetwork {
@@ -35,6 +49,113 @@ impl EthereumNetwork {
         match self {
             EthereumNetwork::Mainnet => 1,
             EthereumNetwork::Sepolia => 11155111,
+            EthereumNetwork::Optimism => 10,
+            EthereumNetwork::OptimismSepolia => 11155420,
+            EthereumNetwork::Base => 8453,
+            EthereumNetwork::BaseSepolia => 84532,
+            EthereumNetwork::ArbitrumOne => 42161,
+            EthereumNetwork::ArbitrumSepolia => 421614,
+        }
+    }
+
+    /// Whether the network is a layer 2 rollup settling on Ethereum.
+    pub fn is_layer2(&self) -> bool {
+        match self {
+            EthereumNetwork::Mainnet | EthereumNetwork::Sepolia => false,
+            EthereumNetwork::Optimism
+            | EthereumNetwork::OptimismSepolia
+            | EthereumNetwork::Base
+            | EthereumNetwork::BaseSepolia
+            | EthereumNetwork::ArbitrumOne
+            | EthereumNetwork::ArbitrumSepolia => true,
+        }
+    }
+
+    /// Default fee model of the network.
+    /// Rollup sequencers order transactions first-come first-served,
+    /// so that a tiny priority fee is enough for a transaction to be included.
+    pub fn default_fee_model(&self) -> FeeModel {
+        match self {
+            EthereumNetwork::Mainnet | EthereumNetwork::Sepolia => FeeModel::Eip1559,
+            EthereumNetwork::Optimism
+            | EthereumNetwork::OptimismSepolia
+            | EthereumNetwork::Base
+            | EthereumNetwork::BaseSepolia => FeeModel::OpStack {
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000),
+                // 0.0001 ETH, well above the L1 data fee of a transfer even when blobs are congested.
+                max_l1_data_fee: Wei::new(100_000_000_000_000),
+            },
+            // Arbitrum ignores the priority fee.
+            EthereumNetwork::ArbitrumOne | EthereumNetwork::ArbitrumSepolia => {
+                FeeModel::Eip1559FixedPriorityFee {
+                    max_priority_fee_per_gas: WeiPerGas::ZERO,
+                }
+            }
+        }
+    }
+
+    /// Gas limit of a transaction transferring ETH to an externally owned account.
+    /// Arbitrum charges the L1 calldata cost as additional L2 gas, which depends on the L1 base fee,
+    /// so that the limit must be much larger than the 21_000 gas of the execution alone.
+    pub fn eth_transfer_gas_limit(&self) -> GasAmount {
+        match self {
+            EthereumNetwork::Mainnet
+            | EthereumNetwork::Sepolia
+            | EthereumNetwork::Optimism
+            | EthereumNetwork::OptimismSepolia
+            | EthereumNetwork::Base
+            | EthereumNetwork::BaseSepolia => GasAmount::new(21_000),
+            EthereumNetwork::ArbitrumOne | EthereumNetwork::ArbitrumSepolia => {
+                GasAmount::new(1_000_000)
+            }
+        }
+    }
+
+    /// Gas limit of a transaction calling `transfer` on an ERC-20 contract.
+    /// See [`EthereumNetwork::eth_transfer_gas_limit`] regarding Arbitrum.
+    pub fn erc20_transfer_gas_limit(&self) -> GasAmount {
+        match self {
+            EthereumNetwork::Mainnet
+            | EthereumNetwork::Sepolia
+            | EthereumNetwork::Optimism
+            | EthereumNetwork::OptimismSepolia
+            | EthereumNetwork::Base
+            | EthereumNetwork::BaseSepolia => GasAmount::new(65_000),
+            EthereumNetwork::ArbitrumOne | EthereumNetwork::ArbitrumSepolia => {
+                GasAmount::new(1_500_000)
+            }
+        }
+    }
+
+    /// Base URL of the block explorer of the network.
+    pub fn block_explorer_url(&self) -> &str {
+        match self {
+            EthereumNetwork::Mainnet => "https://etherscan.io",
+            EthereumNetwork::Sepolia => "https://sepolia.etherscan.io",
+            EthereumNetwork::Optimism => "https://optimistic.etherscan.io",
+            EthereumNetwork::OptimismSepolia => "https://sepolia-optimism.etherscan.io",
+            EthereumNetwork::Base => "https://basescan.org",
+            EthereumNetwork::BaseSepolia => "https://sepolia.basescan.org",
+            EthereumNetwork::ArbitrumOne => "https://arbiscan.io",
+            EthereumNetwork::ArbitrumSepolia => "https://sepolia.arbiscan.io",
+        }
+    }
+}
+
+impl TryFrom<u64> for EthereumNetwork {
+    type Error = String;
+
+    fn try_from(chain_id: u64) -> Result<Self, Self::Error> {
+        match chain_id {
+            1 => Ok(EthereumNetwork::Mainnet),
+            11155111 => Ok(EthereumNetwork::Sepolia),
+            10 => Ok(EthereumNetwork::Optimism),
+            11155420 => Ok(EthereumNetwork::OptimismSepolia),
+            8453 => Ok(EthereumNetwork::Base),
+            84532 => Ok(EthereumNetwork::BaseSepolia),
+            42161 => Ok(EthereumNetwork::ArbitrumOne),
+            421614 => Ok(EthereumNetwork::ArbitrumSepolia),
+            _ => Err(format!("unsupported Ethereum chain ID {chain_id}")),
         }
     }
 }
@@ -44,6 +165,12 @@ impl Display for EthereumNetwork {
         match self {
             EthereumNetwork::Mainnet => write!(f, "Ethereum Mainnet"),
             EthereumNetwork::Sepolia => write!(f, "Ethereum Testnet Sepolia"),
+            EthereumNetwork::Optimism => write!(f, "OP Mainnet"),
+            EthereumNetwork::OptimismSepolia => write!(f, "OP Sepolia Testnet"),
+            EthereumNetwork::Base => write!(f, "Base"),
+            EthereumNetwork::BaseSepolia => write!(f, "Base Sepolia Testnet"),
+            EthereumNetwork::ArbitrumOne => write!(f, "Arbitrum One"),
+            EthereumNetwork::ArbitrumSepolia => write!(f, "Arbitrum Sepolia"),
         }
     }
 }
@@ -1,10 +1,13 @@
 use crate::address::Address;
-use crate::endpoints::CandidBlockTag;
+use crate::chain_profile::FeeModel;
+use crate::endpoints::{CandidBlockTag, CandidFeeModel};
 use crate::eth_rpc::BlockTag;
+use crate::eth_rpc_client::providers::ApiKeys;
+use crate::lifecycle::upgrade::{take_api_keys, RpcProvider};
 use crate::lifecycle::EthereumNetwork;
 use crate::numeric::{BlockNumber, TransactionNonce, Wei};
 use crate::state::transactions::EthTransactions;
-use crate::state::{InvalidStateError, State};
+use crate::state::{finality, parse_rpc_providers, InvalidStateError, State};
 use candid::types::number::Nat;
 use candid::types::principal::Principal;
 use candid::{CandidType, Deserialize};
@@ -28,6 +31,22 @@ pub struct InitArg {
     pub next_transaction_nonce: Nat,
     #[cbor(n(8), with = "crate::cbor::nat")]
     pub last_scraped_block_number: Nat,
+    #[n(9)]
+    pub rpc_providers: Option<Vec<RpcProvider>>,
+    #[n(10)]
+    pub confirmation_depth: Option<u64>,
+    #[n(11)]
+    pub fee_model: Option<CandidFeeModel>,
+}
+
+impl InitArg {
+    /// Removes the API keys of the JSON-RPC providers from the init argument,
+    /// so that they are not recorded in the (public) event log.
+    pub fn take_rpc_api_keys(&mut self) -> Option<ApiKeys> {
+        self.rpc_providers
+            .as_mut()
+            .map(|providers| take_api_keys(providers))
+    }
 }
 
 impl TryFrom<InitArg> for State {
@@ -42,6 +61,9 @@ impl TryFrom<InitArg> for State {
             minimum_withdrawal_amount,
             next_transaction_nonce,
             last_scraped_block_number,
+            rpc_providers,
+            confirmation_depth,
+            fee_model,
         }: InitArg,
     ) -> Result<Self, Self::Error> {
         use std::str::FromStr;
@@ -69,15 +91,35 @@ impl TryFrom<InitArg> for State {
                         "ERROR: last_scraped_block_number is at maximum value".to_string(),
                     )
                 })?;
+        let rpc_providers = rpc_providers
+            .map(parse_rpc_providers)
+            .transpose()?
+            .unwrap_or_default();
+        let finality = finality(BlockTag::from(ethereum_block_height), confirmation_depth)?;
+        let fee_model = fee_model
+            .map(FeeModel::try_from)
+            .transpose()
+            .map_err(|e| InvalidStateError::InvalidFeeModel(format!("ERROR: {}", e)))?
+            .unwrap_or_else(|| ethereum_network.default_fee_model());
         let state = Self {
             ethereum_network,
             ecdsa_key_name,
             ethereum_contract_address,
             retrieve_eth_principals: Default::default(),
             eth_transactions: EthTransactions::new(initial_nonce),
+            ledger_suite_orchestrator_id: None,
+            erc20_helper_contract_address: None,
+            ckerc20_tokens: Default::default(),
+            last_erc20_scraped_block_number: last_scraped_block_number,
+            erc20_events_to_mint: Default::default(),
+            minted_erc20_events: Default::default(),
+            erc20_balances: Default::default(),
+            rpc_providers,
+            rpc_reduction_strategies: Default::default(),
             ledger_id,
             minimum_withdrawal_amount,
-            ethereum_block_height: BlockTag::from(ethereum_block_height),
+            finality,
+            fee_model,
             first_scraped_block_number,
             last_scraped_block_number,
             last_observed_block_number: None,
@@ -88,6 +130,7 @@ impl TryFrom<InitArg> for State {
             eth_balance: Default::default(),
             active_tasks: Default::default(),
             http_request_counter: 0,
+            rpc_provider_errors: Default::default(),
         };
         state.validate_config()?;
         Ok(state)
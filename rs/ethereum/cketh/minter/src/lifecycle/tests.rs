@@ -1,5 +1,10 @@
 mod init {
+    use crate::chain_profile::{FeeModel, Finality};
+    use crate::endpoints::CandidFeeModel;
+    use crate::eth_rpc::BlockTag;
     use crate::lifecycle::init::InitArg;
+    use crate::lifecycle::upgrade::RpcProvider;
+    use crate::lifecycle::EthereumNetwork;
     use crate::numeric::{TransactionNonce, Wei};
     use crate::state::{InvalidStateError, State};
     use assert_matches::assert_matches;
@@ -69,6 +74,34 @@ mod init {
             }),
             Err(InvalidStateError::InvalidLastScrapedBlockNumber(_))
         );
+
+        assert_matches!(
+            State::try_from(InitArg {
+                confirmation_depth: Some(0),
+                ..valid_init_arg()
+            }),
+            Err(InvalidStateError::InvalidConfirmationDepth(_))
+        );
+
+        assert_matches!(
+            State::try_from(InitArg {
+                fee_model: Some(CandidFeeModel::Eip1559FixedPriorityFee {
+                    max_priority_fee_per_gas: Nat(BigUint::from_bytes_be(
+                        &ethnum::u256::MAX.to_be_bytes(),
+                    ) + 1_u8),
+                }),
+                ..valid_init_arg()
+            }),
+            Err(InvalidStateError::InvalidFeeModel(_))
+        );
+
+        assert_matches!(
+            State::try_from(InitArg {
+                ethereum_network: EthereumNetwork::Base,
+                ..valid_init_arg()
+            }),
+            Err(InvalidStateError::InvalidRpcProviders(_))
+        );
     }
 
     #[test]
@@ -86,6 +119,32 @@ mod init {
             state.eth_transactions.next_transaction_nonce(),
             TransactionNonce::ZERO
         );
+        assert_eq!(state.finality, Finality::BlockTag(BlockTag::Latest));
+        assert_eq!(state.fee_model, FeeModel::Eip1559);
+    }
+
+    #[test]
+    fn should_succeed_on_layer2() {
+        let init_arg = InitArg {
+            ethereum_network: EthereumNetwork::Optimism,
+            rpc_providers: Some(vec![RpcProvider {
+                name: "alchemy".to_string(),
+                url: "https://opt-mainnet.g.alchemy.com/v2/{API_KEY}".to_string(),
+                api_key: None,
+            }]),
+            confirmation_depth: Some(64),
+            ..valid_init_arg()
+        };
+
+        let state = State::try_from(init_arg).expect("valid init args");
+
+        assert_eq!(state.ethereum_network, EthereumNetwork::Optimism);
+        assert_eq!(state.chain_profile().chain_id(), 10);
+        assert_eq!(state.finality, Finality::ConfirmationDepth(64));
+        assert_eq!(
+            state.fee_model,
+            EthereumNetwork::Optimism.default_fee_model()
+        );
     }
 
     fn valid_init_arg() -> InitArg {
@@ -99,6 +158,9 @@ mod init {
             minimum_withdrawal_amount: Wei::TWO.into(),
             next_transaction_nonce: TransactionNonce::ZERO.into(),
             last_scraped_block_number: Default::default(),
+            rpc_providers: None,
+            confirmation_depth: None,
+            fee_model: None,
         }
     }
 }
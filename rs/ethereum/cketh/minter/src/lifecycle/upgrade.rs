@@ -1,11 +1,15 @@
-use crate::endpoints::CandidBlockTag;
+use crate::endpoints::{CandidBlockTag, CandidFeeModel};
+use crate::eth_rpc_client::providers::{ApiKeys, API_KEY_PLACEHOLDER};
+use crate::eth_rpc_client::{ReductionStrategy, RpcMethod};
 use crate::logs::INFO;
 use crate::state::audit::{process_event, replay_events, EventType};
 use crate::state::mutate_state;
 use crate::state::STATE;
-use candid::{CandidType, Deserialize, Nat};
+use crate::storage;
+use candid::{CandidType, Deserialize, Nat, Principal};
 use ic_canister_log::log;
 use minicbor::{Decode, Encode};
+use std::fmt;
 
 #[derive(CandidType, Deserialize, Clone, Debug, Default, Encode, Decode, PartialEq, Eq)]
 pub struct UpgradeArg {
@@ -17,6 +21,77 @@ pub struct UpgradeArg {
     pub ethereum_contract_address: Option<String>,
     #[n(3)]
     pub ethereum_block_height: Option<CandidBlockTag>,
+    #[n(4)]
+    pub erc20_helper_contract_address: Option<String>,
+    #[cbor(n(5), with = "crate::cbor::nat::option")]
+    pub last_erc20_scraped_block_number: Option<Nat>,
+    #[cbor(n(6), with = "crate::cbor::principal::option")]
+    pub ledger_suite_orchestrator_id: Option<Principal>,
+    #[n(7)]
+    pub rpc_providers: Option<Vec<RpcProvider>>,
+    #[n(8)]
+    pub rpc_reduction_strategies: Option<Vec<RpcReductionStrategy>>,
+    #[n(9)]
+    pub confirmation_depth: Option<u64>,
+    #[n(10)]
+    pub fee_model: Option<CandidFeeModel>,
+}
+
+impl UpgradeArg {
+    /// Removes the API keys of the JSON-RPC providers from the upgrade argument,
+    /// so that they are not recorded in the (public) event log.
+    /// Returns `None` if the upgrade argument does not change the JSON-RPC providers.
+    pub fn take_rpc_api_keys(&mut self) -> Option<ApiKeys> {
+        self.rpc_providers
+            .as_mut()
+            .map(|providers| take_api_keys(providers))
+    }
+}
+
+/// Removes the API keys from the given providers.
+pub fn take_api_keys(providers: &mut [RpcProvider]) -> ApiKeys {
+    providers
+        .iter_mut()
+        .filter_map(|provider| {
+            provider
+                .api_key
+                .take()
+                .map(|api_key| (provider.name.clone(), api_key))
+        })
+        .collect()
+}
+
+/// JSON-RPC provider used by the minter to interact with the Ethereum network.
+#[derive(CandidType, Deserialize, Clone, Encode, Decode, PartialEq, Eq)]
+pub struct RpcProvider {
+    /// Unique name of the provider, e.g. used to label metrics.
+    #[n(0)]
+    pub name: String,
+    /// HTTPS URL of the provider.
+    /// If the provider requires an API key, the URL must contain the placeholder `{API_KEY}`.
+    #[n(1)]
+    pub url: String,
+    /// Secret API key of the provider, which is never recorded in the event log.
+    #[n(2)]
+    pub api_key: Option<String>,
+}
+
+impl fmt::Debug for RpcProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcProvider")
+            .field("name", &self.name)
+            .field("url", &self.url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct RpcReductionStrategy {
+    #[n(0)]
+    pub method: RpcMethod,
+    #[n(1)]
+    pub strategy: ReductionStrategy,
 }
 
 pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
@@ -25,8 +100,15 @@ pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
     STATE.with(|cell| {
         *cell.borrow_mut() = Some(replay_events());
     });
-    if let Some(args) = upgrade_args {
-        mutate_state(|s| process_event(s, EventType::Upgrade(args)))
+    if let Some(mut args) = upgrade_args {
+        let api_keys = args.take_rpc_api_keys();
+        if let (Some(providers), Some(api_keys)) = (&args.rpc_providers, &api_keys) {
+            validate_api_keys(providers, api_keys);
+        }
+        mutate_state(|s| process_event(s, EventType::Upgrade(args)));
+        if let Some(api_keys) = api_keys {
+            storage::set_rpc_api_keys(api_keys);
+        }
     }
 
     let end = ic_cdk::api::instruction_counter();
@@ -37,3 +119,27 @@ pub fn post_upgrade(upgrade_args: Option<UpgradeArg>) {
         end - start
     );
 }
+
+/// Ensures that an API key is given exactly for the providers whose URL contains the placeholder `{API_KEY}`.
+///
+/// # Panics
+///
+/// If the URL of a provider contains the placeholder `{API_KEY}` but no API key was given, or vice versa.
+pub fn validate_api_keys(providers: &[RpcProvider], api_keys: &ApiKeys) {
+    for provider in providers {
+        match (
+            provider.url.contains(API_KEY_PLACEHOLDER),
+            api_keys.contains(&provider.name),
+        ) {
+            (true, false) => panic!(
+                "ERROR: URL of provider '{}' contains {API_KEY_PLACEHOLDER} but no API key was given",
+                provider.name
+            ),
+            (false, true) => panic!(
+                "ERROR: API key given for provider '{}' but its URL does not contain {API_KEY_PLACEHOLDER}",
+                provider.name
+            ),
+            _ => {}
+        }
+    }
+}
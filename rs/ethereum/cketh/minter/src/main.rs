@@ -1,4 +1,4 @@
-use candid::{candid_method, Nat};
+use candid::{candid_method, Nat, Principal};
 use ic_canister_log::log;
 use ic_canisters_http_types::{HttpRequest, HttpResponse, HttpResponseBuilder};
 use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query, update};
@@ -8,17 +8,23 @@ use ic_cketh_minter::endpoints::events::{
     Event as CandidEvent, EventSource as CandidEventSource, GetEventsArg, GetEventsResult,
 };
 use ic_cketh_minter::endpoints::{
-    Eip1559TransactionPrice, RetrieveEthRequest, RetrieveEthStatus, WithdrawalArg, WithdrawalError,
+    AddCkErc20Token, Eip1559TransactionPrice, LedgerError, RetrieveErc20Request,
+    RetrieveEthRequest, RetrieveEthStatus, WithdrawErc20Arg, WithdrawErc20Error, WithdrawalArg,
+    WithdrawalError,
 };
-use ic_cketh_minter::eth_logs::{EventSource, ReceivedEthEvent};
+use ic_cketh_minter::erc20::CkErc20Token;
+use ic_cketh_minter::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent};
 use ic_cketh_minter::guard::retrieve_eth_guard;
+use ic_cketh_minter::lifecycle::upgrade::validate_api_keys;
 use ic_cketh_minter::lifecycle::MinterArg;
 use ic_cketh_minter::logs::{DEBUG, INFO};
-use ic_cketh_minter::numeric::{LedgerBurnIndex, Wei};
+use ic_cketh_minter::numeric::{Erc20Value, LedgerBurnIndex, Wei};
 use ic_cketh_minter::state::audit::{process_event, Event, EventType};
-use ic_cketh_minter::state::transactions::{EthWithdrawalRequest, Reimbursed};
+use ic_cketh_minter::state::transactions::{
+    Erc20Reimbursed, Erc20WithdrawalRequest, EthWithdrawalRequest, Reimbursed, ReimbursementRequest,
+};
 use ic_cketh_minter::state::{lazy_call_ecdsa_public_key, mutate_state, read_state, State, STATE};
-use ic_cketh_minter::tx::estimate_transaction_price;
+use ic_cketh_minter::tx::{estimate_erc20_transaction_price, estimate_transaction_price};
 use ic_cketh_minter::withdraw::{
     eth_fee_history, process_reimbursement, process_retrieve_eth_requests,
 };
@@ -67,13 +73,20 @@ fn setup_timers() {
 #[candid_method(init)]
 fn init(arg: MinterArg) {
     match arg {
-        MinterArg::InitArg(init_arg) => {
+        MinterArg::InitArg(mut init_arg) => {
+            let api_keys = init_arg.take_rpc_api_keys();
+            if let (Some(providers), Some(api_keys)) = (&init_arg.rpc_providers, &api_keys) {
+                validate_api_keys(providers, api_keys);
+            }
             log!(INFO, "[init]: initialized minter with arg: {:?}", init_arg);
             STATE.with(|cell| {
                 storage::record_event(EventType::Init(init_arg.clone()));
                 *cell.borrow_mut() =
                     Some(State::try_from(init_arg).expect("BUG: failed to initialize minter"))
             });
+            if let Some(api_keys) = api_keys {
+                storage::set_rpc_api_keys(api_keys);
+            }
         }
         MinterArg::UpgradeArg(_) => {
             ic_cdk::trap("cannot init canister state with upgrade args");
@@ -87,6 +100,11 @@ fn emit_preupgrade_events() {
         storage::record_event(EventType::SyncedToBlock {
             block_number: s.last_scraped_block_number,
         });
+        if s.erc20_helper_contract_address.is_some() {
+            storage::record_event(EventType::SyncedErc20ToBlock {
+                block_number: s.last_erc20_scraped_block_number,
+            });
+        }
     });
 }
 
@@ -132,6 +150,8 @@ async fn eip_1559_transaction_price() -> Eip1559TransactionPrice {
         &eth_fee_history()
             .await
             .expect("ERROR: failed to retrieve fee history"),
+        read_state(State::fee_model),
+        read_state(State::ethereum_network),
     );
     Eip1559TransactionPrice::from(transaction_price)
 }
@@ -232,6 +252,217 @@ async fn withdraw_eth(
     }
 }
 
+/// Burns `amount` tokens of the ledger `ledger_id` from the caller's account.
+async fn burn_from(
+    caller: Principal,
+    ledger_id: Principal,
+    amount: Nat,
+    token_symbol: &str,
+) -> Result<LedgerBurnIndex, LedgerError> {
+    let client = ICRC1Client {
+        runtime: CdkRuntime,
+        ledger_canister_id: ledger_id,
+    };
+    log!(
+        INFO,
+        "[burn_from]: burning {amount} {token_symbol} from {caller}"
+    );
+    match client
+        .transfer_from(TransferFromArgs {
+            spender_subaccount: None,
+            from: caller.into(),
+            to: ic_cdk::id().into(),
+            amount: amount.clone(),
+            fee: None,
+            memo: None,
+            created_at_time: None,
+        })
+        .await
+    {
+        Ok(Ok(block_index)) => Ok(LedgerBurnIndex::new(
+            block_index.0.to_u64().expect("nat does not fit into u64"),
+        )),
+        Ok(Err(error)) => {
+            log!(
+                DEBUG,
+                "[burn_from]: failed to transfer_from from the {token_symbol} ledger with error: {error:?}"
+            );
+            Err(LedgerError::from_transfer_from_error(
+                error,
+                amount,
+                token_symbol.to_string(),
+                ledger_id,
+            ))
+        }
+        Err((error_code, message)) => {
+            log!(
+                DEBUG,
+                "[burn_from]: failed to call the {token_symbol} ledger with error_code: {error_code:?} and message: {message}",
+            );
+            Err(LedgerError::TemporarilyUnavailable(format!(
+                "failed to call the {token_symbol} ledger ({ledger_id}) with error_code: {error_code:?} and message: {message}"
+            )))
+        }
+    }
+}
+
+#[update]
+#[candid_method(update)]
+async fn withdraw_erc20(
+    WithdrawErc20Arg {
+        amount,
+        ckerc20_ledger_id,
+        recipient,
+    }: WithdrawErc20Arg,
+) -> Result<RetrieveErc20Request, WithdrawErc20Error> {
+    let caller = validate_caller_not_anonymous();
+    let _guard = retrieve_eth_guard(caller).unwrap_or_else(|e| {
+        ic_cdk::trap(&format!(
+            "Failed retrieving guard for principal {}: {:?}",
+            caller, e
+        ))
+    });
+
+    let destination = Address::from_str(&recipient)
+        .and_then(|a| validate_address_as_destination(a).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("invalid recipient address: {:?}", e)));
+
+    if ic_cketh_minter::blocklist::is_blocked(destination) {
+        return Err(WithdrawErc20Error::RecipientAddressBlocked {
+            address: destination.to_string(),
+        });
+    }
+
+    let withdrawal_amount = Erc20Value::try_from(amount.clone())
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("invalid withdrawal amount: {:?}", e)));
+
+    let ckerc20_token = match read_state(|s| {
+        s.find_ck_erc20_token_by_ledger_id(&ckerc20_ledger_id)
+            .cloned()
+    }) {
+        Some(token) => token,
+        None => {
+            return Err(WithdrawErc20Error::TokenNotSupported {
+                supported_tokens: read_state(|s| {
+                    s.ckerc20_tokens
+                        .values()
+                        .cloned()
+                        .map(ic_cketh_minter::endpoints::CkErc20Token::from)
+                        .collect()
+                }),
+            })
+        }
+    };
+
+    let max_transaction_fee = match eth_fee_history().await {
+        Ok(fee_history) => {
+            let (fee_model, ethereum_network) = read_state(|s| (s.fee_model, s.ethereum_network));
+            estimate_erc20_transaction_price(&fee_history, fee_model, ethereum_network)
+                .max_transaction_fee()
+                .checked_add(fee_model.max_l1_data_fee())
+                .expect("BUG: max transaction fee always fits into U256")
+        }
+        Err(e) => {
+            log!(
+                INFO,
+                "[withdraw_erc20]: failed to retrieve fee history: {e:?}"
+            );
+            return Err(WithdrawErc20Error::TemporarilyUnavailable(
+                "failed to retrieve the current transaction fees".to_string(),
+            ));
+        }
+    };
+
+    let cketh_ledger_id = read_state(|s| s.ledger_id);
+    let cketh_ledger_burn_index = burn_from(
+        caller,
+        cketh_ledger_id,
+        Nat::from(max_transaction_fee),
+        "ckETH",
+    )
+    .await
+    .map_err(|error| WithdrawErc20Error::CkEthLedgerError { error })?;
+
+    match burn_from(
+        caller,
+        ckerc20_ledger_id,
+        amount,
+        &ckerc20_token.ckerc20_token_symbol,
+    )
+    .await
+    {
+        Ok(ckerc20_ledger_burn_index) => {
+            let withdrawal_request = Erc20WithdrawalRequest {
+                max_transaction_fee,
+                withdrawal_amount,
+                destination,
+                cketh_ledger_burn_index,
+                erc20_contract_address: ckerc20_token.erc20_contract_address,
+                ckerc20_ledger_id,
+                ckerc20_ledger_burn_index,
+                from: caller,
+                from_subaccount: None,
+            };
+            log!(
+                INFO,
+                "[withdraw_erc20]: queuing withdrawal request {:?}",
+                withdrawal_request,
+            );
+            mutate_state(|s| {
+                process_event(
+                    s,
+                    EventType::AcceptedErc20WithdrawalRequest(withdrawal_request),
+                );
+            });
+            Ok(RetrieveErc20Request {
+                cketh_block_index: Nat::from(cketh_ledger_burn_index.get()),
+                ckerc20_block_index: Nat::from(ckerc20_ledger_burn_index.get()),
+            })
+        }
+        Err(error) => {
+            let reimbursement_request = ReimbursementRequest {
+                withdrawal_id: cketh_ledger_burn_index,
+                reimbursed_amount: max_transaction_fee,
+                to: caller,
+                to_subaccount: None,
+            };
+            log!(
+                INFO,
+                "[withdraw_erc20]: failed to burn {}, will reimburse the transaction fees {:?}",
+                ckerc20_token.ckerc20_token_symbol,
+                reimbursement_request,
+            );
+            mutate_state(|s| {
+                process_event(
+                    s,
+                    EventType::FailedErc20WithdrawalRequest(reimbursement_request),
+                );
+            });
+            Err(WithdrawErc20Error::CkErc20LedgerError {
+                cketh_block_index: Nat::from(cketh_ledger_burn_index.get()),
+                error,
+            })
+        }
+    }
+}
+
+#[update]
+#[candid_method(update)]
+fn add_ckerc20_token(erc20_token: AddCkErc20Token) {
+    let orchestrator_id = read_state(|s| s.ledger_suite_orchestrator_id)
+        .unwrap_or_else(|| ic_cdk::trap("ERROR: ERC-20 feature is not activated"));
+    if orchestrator_id != ic_cdk::caller() {
+        ic_cdk::trap(&format!(
+            "ERROR: only the orchestrator {} can add ERC-20 tokens",
+            orchestrator_id
+        ));
+    }
+    let ckerc20_token = CkErc20Token::try_from(erc20_token)
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("ERROR: {}", e)));
+    log!(INFO, "[add_ckerc20_token]: adding {:?}", ckerc20_token);
+    mutate_state(|s| process_event(s, EventType::AddedCkErc20Token(ckerc20_token)));
+}
+
 #[update]
 #[candid_method(update)]
 async fn retrieve_eth_status(block_index: u64) -> RetrieveEthStatus {
@@ -415,6 +646,95 @@ fn get_events(arg: GetEventsArg) -> GetEventsResult {
                     reimbursed_in_block: reimbursed_in_block.get().into(),
                     reimbursed_amount: reimbursed_amount.into(),
                 },
+                EventType::AcceptedErc20Deposit(ReceivedErc20Event {
+                    transaction_hash,
+                    block_number,
+                    log_index,
+                    from_address,
+                    value,
+                    principal,
+                    erc20_contract_address,
+                }) => EP::AcceptedErc20Deposit {
+                    transaction_hash: transaction_hash.to_string(),
+                    block_number: block_number.into(),
+                    log_index: log_index.into(),
+                    from_address: from_address.to_string(),
+                    value: value.into(),
+                    principal,
+                    erc20_contract_address: erc20_contract_address.to_string(),
+                },
+                EventType::MintedCkErc20 {
+                    event_source,
+                    mint_block_index,
+                    ckerc20_token_symbol,
+                    erc20_contract_address,
+                } => EP::MintedCkErc20 {
+                    event_source: map_event_source(event_source),
+                    mint_block_index: mint_block_index.get().into(),
+                    ckerc20_token_symbol,
+                    erc20_contract_address: erc20_contract_address.to_string(),
+                },
+                EventType::SyncedErc20ToBlock { block_number } => EP::SyncedErc20ToBlock {
+                    block_number: block_number.into(),
+                },
+                EventType::AddedCkErc20Token(CkErc20Token {
+                    erc20_ethereum_network,
+                    erc20_contract_address,
+                    ckerc20_token_symbol,
+                    ckerc20_ledger_id,
+                }) => EP::AddedCkErc20Token {
+                    chain_id: erc20_ethereum_network.chain_id().into(),
+                    address: erc20_contract_address.to_string(),
+                    ckerc20_token_symbol,
+                    ckerc20_ledger_id,
+                },
+                EventType::AcceptedErc20WithdrawalRequest(Erc20WithdrawalRequest {
+                    max_transaction_fee,
+                    withdrawal_amount,
+                    destination,
+                    cketh_ledger_burn_index,
+                    erc20_contract_address,
+                    ckerc20_ledger_id,
+                    ckerc20_ledger_burn_index,
+                    from,
+                    from_subaccount,
+                }) => EP::AcceptedErc20WithdrawalRequest {
+                    max_transaction_fee: max_transaction_fee.into(),
+                    withdrawal_amount: withdrawal_amount.into(),
+                    erc20_contract_address: erc20_contract_address.to_string(),
+                    destination: destination.to_string(),
+                    cketh_ledger_burn_index: cketh_ledger_burn_index.get().into(),
+                    ckerc20_ledger_id,
+                    ckerc20_ledger_burn_index: ckerc20_ledger_burn_index.get().into(),
+                    from,
+                    from_subaccount: from_subaccount.map(|s| s.0),
+                },
+                EventType::FailedErc20WithdrawalRequest(ReimbursementRequest {
+                    withdrawal_id,
+                    reimbursed_amount,
+                    to,
+                    to_subaccount,
+                }) => EP::FailedErc20WithdrawalRequest {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    reimbursed_amount: reimbursed_amount.into(),
+                    to,
+                    to_subaccount: to_subaccount.map(|s| s.0),
+                },
+                EventType::ReimbursedErc20Withdrawal(Erc20Reimbursed {
+                    withdrawal_id,
+                    burn_in_block,
+                    reimbursed_in_block,
+                    ledger_id,
+                    reimbursed_amount,
+                    transaction_hash,
+                }) => EP::ReimbursedErc20Withdrawal {
+                    withdrawal_id: withdrawal_id.get().into(),
+                    burn_in_block: burn_in_block.get().into(),
+                    reimbursed_in_block: reimbursed_in_block.get().into(),
+                    ledger_id,
+                    reimbursed_amount: reimbursed_amount.into(),
+                    transaction_hash: transaction_hash.to_string(),
+                },
             },
         }
     }
@@ -488,6 +808,17 @@ fn http_request(req: HttpRequest) -> HttpResponse {
                     "Total amount of unspent fees across all finalized transaction ckETH -> ETH",
                 )?;
 
+                let mut rpc_errors = w.counter_vec(
+                    "cketh_minter_rpc_provider_errors",
+                    "Number of failed JSON-RPC calls since the last upgrade, labeled by provider and method.",
+                )?;
+                for ((provider, method), count) in &s.rpc_provider_errors {
+                    rpc_errors = rpc_errors.value(
+                        &[("provider", provider.as_str()), ("method", method.as_str())],
+                        *count as f64,
+                    )?;
+                }
+
                 Ok(())
             })
         }
@@ -9,6 +9,10 @@ use phantom_newtype::Id;
 pub enum WeiTag {}
 pub type Wei = CheckedAmountOf<WeiTag>;
 
+pub enum Erc20Tag {}
+/// Amount of ERC-20 tokens in the smallest unit of the token.
+pub type Erc20Value = CheckedAmountOf<Erc20Tag>;
+
 pub enum WeiPerGasUnit {}
 pub type WeiPerGas = CheckedAmountOf<WeiPerGasUnit>;
 
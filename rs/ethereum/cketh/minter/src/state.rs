@@ -1,11 +1,17 @@
 use crate::address::Address;
-use crate::eth_logs::{EventSource, ReceivedEthEvent};
+use crate::chain_profile::{ChainProfile, FeeModel, Finality};
+use crate::erc20::CkErc20Token;
+use crate::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent};
 use crate::eth_rpc::BlockTag;
+use crate::eth_rpc_client::providers::CustomProvider;
 use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
-use crate::lifecycle::upgrade::UpgradeArg;
+use crate::eth_rpc_client::{ReductionStrategy, RpcMethod};
+use crate::lifecycle::upgrade::{RpcProvider, UpgradeArg};
 use crate::lifecycle::EthereumNetwork;
 use crate::logs::DEBUG;
-use crate::numeric::{BlockNumber, LedgerBurnIndex, LedgerMintIndex, TransactionNonce, Wei};
+use crate::numeric::{
+    BlockNumber, Erc20Value, LedgerBurnIndex, LedgerMintIndex, TransactionNonce, Wei,
+};
 use candid::Principal;
 use ic_canister_log::log;
 use ic_cdk::api::management_canister::ecdsa::EcdsaPublicKeyResponse;
@@ -13,7 +19,7 @@ use ic_crypto_ecdsa_secp256k1::PublicKey;
 use std::cell::RefCell;
 use std::collections::{btree_map, BTreeMap, BTreeSet, HashSet};
 use strum_macros::EnumIter;
-use transactions::EthTransactions;
+use transactions::{Erc20WithdrawalRequest, EthTransactions, EthWithdrawalRequest};
 
 pub mod audit;
 pub mod event;
@@ -38,6 +44,19 @@ impl MintedEvent {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MintedErc20Event {
+    pub deposit_event: ReceivedErc20Event,
+    pub mint_block_index: LedgerMintIndex,
+    pub ckerc20_token_symbol: String,
+}
+
+impl MintedErc20Event {
+    pub fn source(&self) -> EventSource {
+        self.deposit_event.source()
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct State {
     pub ethereum_network: EthereumNetwork,
@@ -46,7 +65,8 @@ pub struct State {
     pub ethereum_contract_address: Option<Address>,
     pub ecdsa_public_key: Option<EcdsaPublicKeyResponse>,
     pub minimum_withdrawal_amount: Wei,
-    pub ethereum_block_height: BlockTag,
+    pub finality: Finality,
+    pub fee_model: FeeModel,
     pub first_scraped_block_number: BlockNumber,
     pub last_scraped_block_number: BlockNumber,
     pub last_observed_block_number: Option<BlockNumber>,
@@ -55,6 +75,26 @@ pub struct State {
     pub invalid_events: BTreeMap<EventSource, String>,
     pub eth_transactions: EthTransactions,
 
+    /// Canister ID of the ledger suite orchestrator, which is the only principal
+    /// allowed to add new ckERC20 tokens.
+    pub ledger_suite_orchestrator_id: Option<Principal>,
+    /// Address of the helper smart contract used to deposit ERC-20 tokens.
+    pub erc20_helper_contract_address: Option<Address>,
+    /// ERC-20 tokens supported by the minter, indexed by their contract address.
+    pub ckerc20_tokens: BTreeMap<Address, CkErc20Token>,
+    pub last_erc20_scraped_block_number: BlockNumber,
+    pub erc20_events_to_mint: BTreeMap<EventSource, ReceivedErc20Event>,
+    pub minted_erc20_events: BTreeMap<EventSource, MintedErc20Event>,
+    /// Current balance of ERC-20 tokens held by the minter, indexed by the token contract address.
+    /// Computed based on audit events.
+    pub erc20_balances: BTreeMap<Address, Erc20Value>,
+
+    /// JSON-RPC providers configured via the upgrade argument.
+    /// If empty, the minter uses the default providers of the Ethereum network.
+    pub rpc_providers: Vec<CustomProvider>,
+    /// Strategies overriding the default way of reducing the responses of the JSON-RPC providers.
+    pub rpc_reduction_strategies: BTreeMap<RpcMethod, ReductionStrategy>,
+
     /// Current balance of ETH held by minter.
     /// Computed based on audit events.
     pub eth_balance: EthBalance,
@@ -67,6 +107,9 @@ pub struct State {
     /// Number of HTTP outcalls since the last upgrade.
     /// Used to correlate request and response in logs.
     pub http_request_counter: u64,
+
+    /// Number of failed JSON-RPC calls since the last upgrade, indexed by provider name and method.
+    pub rpc_provider_errors: BTreeMap<(String, String), u64>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -77,6 +120,13 @@ pub enum InvalidStateError {
     InvalidEthereumContractAddress(String),
     InvalidMinimumWithdrawalAmount(String),
     InvalidLastScrapedBlockNumber(String),
+    InvalidErc20HelperContractAddress(String),
+    InvalidLastErc20ScrapedBlockNumber(String),
+    InvalidLedgerSuiteOrchestratorId(String),
+    InvalidRpcProviders(String),
+    InvalidRpcReductionStrategy(String),
+    InvalidConfirmationDepth(String),
+    InvalidFeeModel(String),
 }
 
 impl State {
@@ -105,6 +155,26 @@ impl State {
                 "minimum_withdrawal_amount must be positive".to_string(),
             ));
         }
+        if self
+            .erc20_helper_contract_address
+            .iter()
+            .any(|address| address == &Address::ZERO)
+        {
+            return Err(InvalidStateError::InvalidErc20HelperContractAddress(
+                "erc20_helper_contract_address cannot be the zero address".to_string(),
+            ));
+        }
+        if self.ethereum_network.is_layer2() && self.rpc_providers.is_empty() {
+            return Err(InvalidStateError::InvalidRpcProviders(format!(
+                "{} has no default JSON-RPC providers, rpc_providers must be set",
+                self.ethereum_network
+            )));
+        }
+        if self.ledger_suite_orchestrator_id == Some(Principal::anonymous()) {
+            return Err(InvalidStateError::InvalidLedgerSuiteOrchestratorId(
+                "ledger_suite_orchestrator_id cannot be the anonymous principal".to_string(),
+            ));
+        }
         Ok(())
     }
 
@@ -130,6 +200,58 @@ impl State {
         self.update_eth_balance_upon_deposit(event)
     }
 
+    fn record_erc20_deposit(&mut self, event: &ReceivedErc20Event) {
+        let event_source = event.source();
+        assert!(
+            self.ckerc20_tokens
+                .contains_key(&event.erc20_contract_address),
+            "BUG: accepted a deposit of an unsupported ERC-20 token {}",
+            event.erc20_contract_address
+        );
+        assert!(
+            !self.erc20_events_to_mint.contains_key(&event_source),
+            "there must be no two different events with the same source"
+        );
+        assert!(!self.minted_erc20_events.contains_key(&event_source));
+        assert!(!self.invalid_events.contains_key(&event_source));
+
+        self.erc20_events_to_mint
+            .insert(event_source, event.clone());
+
+        self.erc20_balance_add(event.erc20_contract_address, event.value);
+    }
+
+    fn record_add_ckerc20_token(&mut self, token: CkErc20Token) {
+        assert_eq!(
+            self.ethereum_network, token.erc20_ethereum_network,
+            "ERROR: Expected {}, but got {}",
+            self.ethereum_network, token.erc20_ethereum_network
+        );
+        assert!(
+            self.ckerc20_tokens
+                .values()
+                .all(|t| t.ckerc20_ledger_id != token.ckerc20_ledger_id
+                    && t.ckerc20_token_symbol != token.ckerc20_token_symbol),
+            "ERROR: ckERC20 token {token:?} is already supported"
+        );
+        match self.ckerc20_tokens.entry(token.erc20_contract_address) {
+            btree_map::Entry::Occupied(entry) => panic!(
+                "ERROR: ERC-20 token {} is already supported by {:?}",
+                entry.key(),
+                entry.get()
+            ),
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(token);
+            }
+        }
+    }
+
+    pub fn find_ck_erc20_token_by_ledger_id(&self, ledger_id: &Principal) -> Option<&CkErc20Token> {
+        self.ckerc20_tokens
+            .values()
+            .find(|token| &token.ckerc20_ledger_id == ledger_id)
+    }
+
     fn record_invalid_deposit(&mut self, source: EventSource, error: String) -> bool {
         assert!(
             !self.events_to_mint.contains_key(&source),
@@ -139,6 +261,14 @@ impl State {
             !self.minted_events.contains_key(&source),
             "attempted to mark a minted event {source:?} as invalid"
         );
+        assert!(
+            !self.erc20_events_to_mint.contains_key(&source),
+            "attempted to mark an accepted ERC-20 event as invalid"
+        );
+        assert!(
+            !self.minted_erc20_events.contains_key(&source),
+            "attempted to mark a minted ERC-20 event {source:?} as invalid"
+        );
 
         match self.invalid_events.entry(source) {
             btree_map::Entry::Occupied(_) => false,
@@ -172,14 +302,61 @@ impl State {
         );
     }
 
+    fn record_successful_erc20_mint(
+        &mut self,
+        source: EventSource,
+        mint_block_index: LedgerMintIndex,
+        ckerc20_token_symbol: &str,
+        erc20_contract_address: Address,
+    ) {
+        assert!(
+            !self.invalid_events.contains_key(&source),
+            "attempted to mint an event previously marked as invalid {source:?}"
+        );
+        let deposit_event = match self.erc20_events_to_mint.remove(&source) {
+            Some(event) => event,
+            None => panic!("attempted to mint ckERC20 for an unknown event {source:?}"),
+        };
+        assert_eq!(
+            deposit_event.erc20_contract_address, erc20_contract_address,
+            "BUG: ERC-20 contract address mismatch for event {source:?}"
+        );
+        assert_eq!(
+            self.minted_erc20_events.insert(
+                source,
+                MintedErc20Event {
+                    deposit_event,
+                    mint_block_index,
+                    ckerc20_token_symbol: ckerc20_token_symbol.to_string(),
+                }
+            ),
+            None,
+            "attempted to mint ckERC20 twice for the same event {source:?}"
+        );
+    }
+
     pub fn record_finalized_transaction(
         &mut self,
         withdrawal_id: &LedgerBurnIndex,
         receipt: &TransactionReceipt,
     ) {
+        let eth_withdrawal_request = self
+            .eth_transactions
+            .get_pending_eth_withdrawal(withdrawal_id)
+            .cloned();
+        let erc20_withdrawal_request = self
+            .eth_transactions
+            .get_pending_erc20_withdrawal(withdrawal_id)
+            .cloned();
         self.eth_transactions
             .record_finalized_transaction(*withdrawal_id, receipt.clone());
-        self.update_eth_balance_upon_withdrawal(withdrawal_id, receipt);
+        match (eth_withdrawal_request, erc20_withdrawal_request) {
+            (_, Some(request)) => self.update_balances_upon_erc20_withdrawal(&request, receipt),
+            (Some(request), None) => self.update_eth_balance_upon_withdrawal(&request, receipt),
+            (None, None) => {
+                panic!("BUG: missing withdrawal request for finalized transaction {withdrawal_id}")
+            }
+        }
     }
 
     pub fn next_request_id(&mut self) -> u64 {
@@ -194,9 +371,11 @@ impl State {
         self.eth_balance.eth_balance_add(event.value);
     }
 
+    /// The transaction fee charged to the user is the withdrawal amount minus the transaction amount,
+    /// which also covers the L1 data fee reserved on OP Stack rollups.
     fn update_eth_balance_upon_withdrawal(
         &mut self,
-        withdrawal_id: &LedgerBurnIndex,
+        request: &EthWithdrawalRequest,
         receipt: &TransactionReceipt,
     ) {
         let tx_fee = receipt.effective_transaction_fee();
@@ -205,15 +384,18 @@ impl State {
                 let tx = self
                     .eth_transactions
                     .finalized_tx
-                    .get_alt(withdrawal_id)
+                    .get_alt(&request.ledger_burn_index)
                     .expect("BUG: missing finalized transaction");
                 let debited_amount = tx
                     .transaction()
                     .amount
                     .checked_add(tx_fee)
                     .expect("BUG: debited amount always fits into U256");
-                let charged_tx_fee = tx.transaction_price().max_transaction_fee();
-                let unspent_tx_fee = charged_tx_fee.checked_sub(tx_fee).expect("BUG: charged transaction fee MUST always be at least the effective transaction fee");
+                let charged_tx_fee = request
+                    .withdrawal_amount
+                    .checked_sub(tx.transaction().amount)
+                    .expect("BUG: transaction amount MUST always be at most the withdrawal amount");
+                let unspent_tx_fee = unspent_tx_fee(charged_tx_fee, receipt);
 
                 self.eth_balance.eth_balance_sub(debited_amount);
                 self.eth_balance.total_effective_tx_fees_add(tx_fee);
@@ -226,12 +408,75 @@ impl State {
         }
     }
 
+    /// Unlike for ETH withdrawals, the transaction fees of an ERC-20 withdrawal are never
+    /// reimbursed, even if the transaction failed, since they were paid with burned ckETH.
+    fn update_balances_upon_erc20_withdrawal(
+        &mut self,
+        request: &Erc20WithdrawalRequest,
+        receipt: &TransactionReceipt,
+    ) {
+        let tx_fee = receipt.effective_transaction_fee();
+        let unspent_tx_fee = unspent_tx_fee(request.max_transaction_fee, receipt);
+        self.eth_balance.eth_balance_sub(tx_fee);
+        self.eth_balance.total_effective_tx_fees_add(tx_fee);
+        self.eth_balance.total_unspent_tx_fees_add(unspent_tx_fee);
+        if receipt.status == TransactionStatus::Success {
+            self.erc20_balance_sub(request.erc20_contract_address, request.withdrawal_amount);
+        }
+    }
+
+    fn erc20_balance_add(&mut self, erc20_contract_address: Address, value: Erc20Value) {
+        let balance = self
+            .erc20_balances
+            .entry(erc20_contract_address)
+            .or_insert(Erc20Value::ZERO);
+        *balance = balance.checked_add(value).unwrap_or_else(|| {
+            panic!(
+                "BUG: overflow when adding {} to the balance of ERC-20 token {}",
+                value, erc20_contract_address
+            )
+        });
+    }
+
+    fn erc20_balance_sub(&mut self, erc20_contract_address: Address, value: Erc20Value) {
+        let balance = self
+            .erc20_balances
+            .get(&erc20_contract_address)
+            .copied()
+            .unwrap_or(Erc20Value::ZERO);
+        let new_balance = balance.checked_sub(value).unwrap_or_else(|| {
+            panic!(
+                "BUG: underflow when subtracting {} from the balance of ERC-20 token {}",
+                value, erc20_contract_address
+            )
+        });
+        if new_balance == Erc20Value::ZERO {
+            self.erc20_balances.remove(&erc20_contract_address);
+        } else {
+            self.erc20_balances
+                .insert(erc20_contract_address, new_balance);
+        }
+    }
+
     pub const fn ethereum_network(&self) -> EthereumNetwork {
         self.ethereum_network
     }
 
-    pub const fn ethereum_block_height(&self) -> BlockTag {
-        self.ethereum_block_height
+    pub const fn finality(&self) -> Finality {
+        self.finality
+    }
+
+    pub const fn fee_model(&self) -> FeeModel {
+        self.fee_model
+    }
+
+    pub fn chain_profile(&self) -> ChainProfile {
+        ChainProfile {
+            network: self.ethereum_network,
+            finality: self.finality,
+            fee_model: self.fee_model,
+            helper_contract_address: self.ethereum_contract_address,
+        }
     }
 
     fn upgrade(&mut self, upgrade_args: UpgradeArg) -> Result<(), InvalidStateError> {
@@ -242,6 +487,13 @@ impl State {
             minimum_withdrawal_amount,
             ethereum_contract_address,
             ethereum_block_height,
+            erc20_helper_contract_address,
+            last_erc20_scraped_block_number,
+            ledger_suite_orchestrator_id,
+            rpc_providers,
+            rpc_reduction_strategies,
+            confirmation_depth,
+            fee_model,
         } = upgrade_args;
         if let Some(nonce) = next_transaction_nonce {
             let nonce = TransactionNonce::try_from(nonce)
@@ -260,8 +512,46 @@ impl State {
             })?;
             self.ethereum_contract_address = Some(ethereum_contract_address);
         }
-        if let Some(block_height) = ethereum_block_height {
-            self.ethereum_block_height = block_height.into();
+        match (ethereum_block_height, confirmation_depth) {
+            (None, None) => {}
+            (block_height, confirmation_depth) => {
+                let block_tag = block_height.map(BlockTag::from).unwrap_or_default();
+                self.finality = finality(block_tag, confirmation_depth)?;
+            }
+        }
+        if let Some(fee_model) = fee_model {
+            self.fee_model = FeeModel::try_from(fee_model)
+                .map_err(|e| InvalidStateError::InvalidFeeModel(format!("ERROR: {}", e)))?;
+        }
+        if let Some(address) = erc20_helper_contract_address {
+            let erc20_helper_contract_address = Address::from_str(&address).map_err(|e| {
+                InvalidStateError::InvalidErc20HelperContractAddress(format!("ERROR: {}", e))
+            })?;
+            self.erc20_helper_contract_address = Some(erc20_helper_contract_address);
+        }
+        if let Some(block_number) = last_erc20_scraped_block_number {
+            self.last_erc20_scraped_block_number =
+                BlockNumber::try_from(block_number).map_err(|e| {
+                    InvalidStateError::InvalidLastErc20ScrapedBlockNumber(format!("ERROR: {}", e))
+                })?;
+        }
+        if let Some(orchestrator_id) = ledger_suite_orchestrator_id {
+            self.ledger_suite_orchestrator_id = Some(orchestrator_id);
+        }
+        if let Some(providers) = rpc_providers {
+            self.rpc_providers = parse_rpc_providers(providers)?;
+        }
+        if let Some(strategies) = rpc_reduction_strategies {
+            for strategy in strategies {
+                if !strategy.method.supports(strategy.strategy) {
+                    return Err(InvalidStateError::InvalidRpcReductionStrategy(format!(
+                        "ERROR: {:?} does not support the {:?} reduction strategy",
+                        strategy.method, strategy.strategy
+                    )));
+                }
+                self.rpc_reduction_strategies
+                    .insert(strategy.method, strategy.strategy);
+            }
         }
         self.validate_config()
     }
@@ -296,18 +586,113 @@ impl State {
             self.last_scraped_block_number,
             other.last_scraped_block_number
         );
-        ensure_eq!(self.ethereum_block_height, other.ethereum_block_height);
+        ensure_eq!(self.finality, other.finality);
+        ensure_eq!(self.fee_model, other.fee_model);
         ensure_eq!(self.events_to_mint, other.events_to_mint);
         ensure_eq!(self.minted_events, other.minted_events);
         ensure_eq!(self.invalid_events, other.invalid_events);
+        ensure_eq!(
+            self.ledger_suite_orchestrator_id,
+            other.ledger_suite_orchestrator_id
+        );
+        ensure_eq!(
+            self.erc20_helper_contract_address,
+            other.erc20_helper_contract_address
+        );
+        ensure_eq!(self.ckerc20_tokens, other.ckerc20_tokens);
+        ensure_eq!(
+            self.last_erc20_scraped_block_number,
+            other.last_erc20_scraped_block_number
+        );
+        ensure_eq!(self.erc20_events_to_mint, other.erc20_events_to_mint);
+        ensure_eq!(self.minted_erc20_events, other.minted_erc20_events);
+        ensure_eq!(self.erc20_balances, other.erc20_balances);
+        ensure_eq!(self.rpc_providers, other.rpc_providers);
+        ensure_eq!(
+            self.rpc_reduction_strategies,
+            other.rpc_reduction_strategies
+        );
 
         self.eth_transactions
             .is_equivalent_to(&other.eth_transactions)
     }
 
+    pub fn record_rpc_provider_error(&mut self, provider_name: &str, method: &str) {
+        *self
+            .rpc_provider_errors
+            .entry((provider_name.to_string(), method.to_string()))
+            .or_default() += 1;
+    }
+
     pub fn eth_balance(&self) -> &EthBalance {
         &self.eth_balance
     }
+
+    pub fn erc20_balances(&self) -> &BTreeMap<Address, Erc20Value> {
+        &self.erc20_balances
+    }
+}
+
+/// Validates the JSON-RPC providers given in the init or upgrade argument.
+pub(crate) fn parse_rpc_providers(
+    providers: Vec<RpcProvider>,
+) -> Result<Vec<CustomProvider>, InvalidStateError> {
+    let mut names = BTreeSet::new();
+    let mut custom_providers = Vec::with_capacity(providers.len());
+    for provider in providers {
+        if provider.name.trim().is_empty() {
+            return Err(InvalidStateError::InvalidRpcProviders(
+                "ERROR: provider name cannot be blank".to_string(),
+            ));
+        }
+        if !names.insert(provider.name.clone()) {
+            return Err(InvalidStateError::InvalidRpcProviders(format!(
+                "ERROR: duplicate provider name '{}'",
+                provider.name
+            )));
+        }
+        if !provider.url.starts_with("https://") {
+            return Err(InvalidStateError::InvalidRpcProviders(format!(
+                "ERROR: URL of provider '{}' must use HTTPS",
+                provider.name
+            )));
+        }
+        custom_providers.push(CustomProvider {
+            name: provider.name,
+            url: provider.url,
+        });
+    }
+    Ok(custom_providers)
+}
+
+/// Determines the finality from the block tag and the confirmation depth given in the init or
+/// upgrade argument, where the confirmation depth, if any, takes precedence.
+pub(crate) fn finality(
+    block_tag: BlockTag,
+    confirmation_depth: Option<u64>,
+) -> Result<Finality, InvalidStateError> {
+    match confirmation_depth {
+        None => Ok(Finality::BlockTag(block_tag)),
+        Some(0) => Err(InvalidStateError::InvalidConfirmationDepth(
+            "ERROR: confirmation_depth must be positive".to_string(),
+        )),
+        Some(depth) => Ok(Finality::ConfirmationDepth(depth)),
+    }
+}
+
+/// Returns the part of the charged transaction fee that was not spent by the transaction.
+/// The L1 data fee of OP Stack rollups is only bounded by the amount reserved when the
+/// transaction was created, which may not cover it if the L1 fees increased in the meantime,
+/// in which case the minter pays the difference.
+fn unspent_tx_fee(charged_tx_fee: Wei, receipt: &TransactionReceipt) -> Wei {
+    let tx_fee = receipt.effective_transaction_fee();
+    match charged_tx_fee.checked_sub(tx_fee) {
+        Some(unspent_tx_fee) => unspent_tx_fee,
+        None if receipt.l1_fee.is_some() => Wei::ZERO,
+        None => panic!(
+            "BUG: charged transaction fee MUST always be at least the effective transaction fee"
+        ),
+    }
 }
 
 pub fn read_state<R>(f: impl FnOnce(&State) -> R) -> R {
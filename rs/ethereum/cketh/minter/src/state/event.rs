@@ -1,8 +1,12 @@
-use crate::eth_logs::{EventSource, ReceivedEthEvent};
+use crate::address::Address;
+use crate::erc20::CkErc20Token;
+use crate::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent};
 use crate::eth_rpc_client::responses::TransactionReceipt;
 use crate::lifecycle::{init::InitArg, upgrade::UpgradeArg};
 use crate::numeric::{BlockNumber, LedgerBurnIndex, LedgerMintIndex};
-use crate::state::transactions::{EthWithdrawalRequest, Reimbursed};
+use crate::state::transactions::{
+    Erc20Reimbursed, Erc20WithdrawalRequest, EthWithdrawalRequest, Reimbursed, ReimbursementRequest,
+};
 use crate::tx::{Eip1559TransactionRequest, SignedEip1559TransactionRequest};
 use minicbor::{Decode, Encode};
 
@@ -90,6 +94,45 @@ pub enum EventType {
     /// The minter successfully reimbursed a failed withdrawal.
     #[n(12)]
     ReimbursedEthWithdrawal(#[n(0)] Reimbursed),
+    /// The minter discovered a ckERC20 deposit in the ERC-20 helper contract logs.
+    #[n(13)]
+    AcceptedErc20Deposit(#[n(0)] ReceivedErc20Event),
+    /// The minter minted ckERC20 in response to a deposit.
+    #[n(14)]
+    MintedCkErc20 {
+        /// The unique identifier of the deposit on the Ethereum network.
+        #[n(0)]
+        event_source: EventSource,
+        /// The transaction index on the ckERC20 ledger.
+        #[cbor(n(1), with = "crate::cbor::id")]
+        mint_block_index: LedgerMintIndex,
+        /// The symbol of the minted ckERC20 token.
+        #[n(2)]
+        ckerc20_token_symbol: String,
+        /// The address of the deposited ERC-20 token.
+        #[n(3)]
+        erc20_contract_address: Address,
+    },
+    /// The minter processed the ERC-20 helper smart contract logs up to the specified height.
+    #[n(15)]
+    SyncedErc20ToBlock {
+        /// The last processed block number (inclusive).
+        #[n(0)]
+        block_number: BlockNumber,
+    },
+    /// The minter started supporting a new ckERC20 token.
+    #[n(16)]
+    AddedCkErc20Token(#[n(0)] CkErc20Token),
+    /// The minter accepted a new ERC-20 withdrawal request.
+    #[n(17)]
+    AcceptedErc20WithdrawalRequest(#[n(0)] Erc20WithdrawalRequest),
+    /// The minter rejected an ERC-20 withdrawal request because burning the ckERC20 tokens failed
+    /// and will reimburse the ckETH burned to pay for the transaction fees.
+    #[n(18)]
+    FailedErc20WithdrawalRequest(#[n(0)] ReimbursementRequest),
+    /// The minter successfully reimbursed the ckERC20 tokens of a failed ERC-20 withdrawal.
+    #[n(19)]
+    ReimbursedErc20Withdrawal(#[n(0)] Erc20Reimbursed),
 }
 
 #[derive(Encode, Decode, Debug, PartialEq, Eq)]
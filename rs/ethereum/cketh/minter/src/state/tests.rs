@@ -1,11 +1,14 @@
 use crate::address::Address;
+use crate::chain_profile::{FeeModel, Finality};
 use crate::checked_amount::CheckedAmountOf;
-use crate::endpoints::CandidBlockTag;
-use crate::eth_logs::{EventSource, ReceivedEthEvent};
+use crate::endpoints::{CandidBlockTag, CandidFeeModel};
+use crate::erc20::CkErc20Token;
+use crate::eth_logs::{EventSource, ReceivedErc20Event, ReceivedEthEvent};
 use crate::eth_rpc::{BlockTag, Hash};
 use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
+use crate::eth_rpc_client::{ReductionStrategy, RpcMethod};
 use crate::lifecycle::init::InitArg;
-use crate::lifecycle::upgrade::UpgradeArg;
+use crate::lifecycle::upgrade::{RpcProvider, RpcReductionStrategy, UpgradeArg};
 use crate::lifecycle::EthereumNetwork;
 use crate::numeric::{
     wei_from_milli_ether, BlockNumber, GasAmount, LedgerBurnIndex, LedgerMintIndex, LogIndex,
@@ -57,6 +60,9 @@ fn a_state() -> State {
         minimum_withdrawal_amount: wei_from_milli_ether(10).into(),
         next_transaction_nonce: Default::default(),
         last_scraped_block_number: Default::default(),
+        rpc_providers: None,
+        confirmation_depth: None,
+        fee_model: None,
     })
     .expect("init args should be valid")
 }
@@ -184,6 +190,9 @@ mod mint_transaction {
             minimum_withdrawal_amount: wei_from_milli_ether(10).into(),
             next_transaction_nonce: Default::default(),
             last_scraped_block_number: Default::default(),
+            rpc_providers: None,
+            confirmation_depth: None,
+            fee_model: None,
         })
         .expect("init args should be valid")
     }
@@ -208,12 +217,16 @@ fn received_eth_event() -> ReceivedEthEvent {
 
 mod upgrade {
     use crate::address::Address;
+    use crate::chain_profile::{FeeModel, Finality};
+    use crate::endpoints::CandidFeeModel;
     use crate::eth_rpc::BlockTag;
-    use crate::lifecycle::upgrade::UpgradeArg;
-    use crate::numeric::{wei_from_milli_ether, TransactionNonce, Wei};
+    use crate::eth_rpc_client::providers::CustomProvider;
+    use crate::eth_rpc_client::{ReductionStrategy, RpcMethod};
+    use crate::lifecycle::upgrade::{RpcProvider, RpcReductionStrategy, UpgradeArg};
+    use crate::numeric::{wei_from_milli_ether, BlockNumber, TransactionNonce, Wei, WeiPerGas};
     use crate::state::{InvalidStateError, State};
     use assert_matches::assert_matches;
-    use candid::Nat;
+    use candid::{Nat, Principal};
     use num_bigint::BigUint;
     use std::str::FromStr;
 
@@ -258,6 +271,122 @@ mod upgrade {
             }),
             Err(InvalidStateError::InvalidEthereumContractAddress(_))
         );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                erc20_helper_contract_address: Some(
+                    "0x0000000000000000000000000000000000000000".to_string(),
+                ),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidErc20HelperContractAddress(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                ledger_suite_orchestrator_id: Some(Principal::anonymous()),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidLedgerSuiteOrchestratorId(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                rpc_providers: Some(vec![
+                    rpc_provider("alchemy", "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}"),
+                    rpc_provider("alchemy", "https://eth-mainnet.alchemyapi.io/v2/{API_KEY}"),
+                ]),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidRpcProviders(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                rpc_providers: Some(vec![rpc_provider(" ", "https://rpc.ankr.com/eth")]),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidRpcProviders(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                rpc_providers: Some(vec![rpc_provider("ankr", "http://rpc.ankr.com/eth")]),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidRpcProviders(_))
+        );
+
+        for method in [RpcMethod::EthGetLogs, RpcMethod::EthGetTransactionReceipt] {
+            let mut state = initial_state();
+            assert_matches!(
+                state.upgrade(UpgradeArg {
+                    rpc_reduction_strategies: Some(vec![RpcReductionStrategy {
+                        method,
+                        strategy: ReductionStrategy::MinByKey,
+                    }]),
+                    ..Default::default()
+                }),
+                Err(InvalidStateError::InvalidRpcReductionStrategy(_))
+            );
+        }
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                confirmation_depth: Some(0),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidConfirmationDepth(_))
+        );
+
+        let mut state = initial_state();
+        assert_matches!(
+            state.upgrade(UpgradeArg {
+                fee_model: Some(CandidFeeModel::Eip1559FixedPriorityFee {
+                    max_priority_fee_per_gas: Nat(BigUint::from_bytes_be(
+                        &ethnum::u256::MAX.to_be_bytes(),
+                    ) + 1_u8),
+                }),
+                ..Default::default()
+            }),
+            Err(InvalidStateError::InvalidFeeModel(_))
+        );
+    }
+
+    #[test]
+    fn should_not_record_api_keys_in_upgrade_arg() {
+        let mut upgrade_arg = UpgradeArg {
+            rpc_providers: Some(vec![
+                RpcProvider {
+                    api_key: Some("secret".to_string()),
+                    ..rpc_provider("alchemy", "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}")
+                },
+                rpc_provider("publicnode", "https://ethereum.publicnode.com"),
+            ]),
+            ..Default::default()
+        };
+        assert!(format!("{:?}", upgrade_arg).contains("alchemy"));
+        assert!(!format!("{:?}", upgrade_arg).contains("secret"));
+
+        let api_keys = upgrade_arg
+            .take_rpc_api_keys()
+            .expect("BUG: upgrade changes providers");
+
+        assert_eq!(api_keys.get("alchemy"), Some("secret"));
+        assert_eq!(api_keys.get("publicnode"), None);
+        assert!(!format!("{:?}", api_keys).contains("secret"));
+        assert!(upgrade_arg
+            .rpc_providers
+            .unwrap()
+            .iter()
+            .all(|provider| provider.api_key.is_none()));
+        assert_eq!(UpgradeArg::default().take_rpc_api_keys(), None);
     }
 
     #[test]
@@ -271,6 +400,25 @@ mod upgrade {
                 "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34".to_string(),
             ),
             ethereum_block_height: Some(CandidBlockTag::Safe),
+            erc20_helper_contract_address: Some(
+                "0xE1788E4834c896F1932188645cc36c54d1b80AC1".to_string(),
+            ),
+            last_erc20_scraped_block_number: Some(Nat::from(20)),
+            ledger_suite_orchestrator_id: Some(
+                Principal::from_text("vxkom-oyaaa-aaaar-qafda-cai").unwrap(),
+            ),
+            rpc_providers: Some(vec![
+                rpc_provider("alchemy", "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}"),
+                rpc_provider("publicnode", "https://ethereum.publicnode.com"),
+            ]),
+            rpc_reduction_strategies: Some(vec![RpcReductionStrategy {
+                method: RpcMethod::EthGetBlockByNumber,
+                strategy: ReductionStrategy::MinByKey,
+            }]),
+            confirmation_depth: None,
+            fee_model: Some(CandidFeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas: Nat::from(1_000_000),
+            }),
         };
 
         state.upgrade(upgrade_arg).expect("valid upgrade args");
@@ -284,12 +432,82 @@ mod upgrade {
             state.ethereum_contract_address,
             Some(Address::from_str("0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34").unwrap())
         );
-        assert_eq!(state.ethereum_block_height, BlockTag::Safe);
+        assert_eq!(state.finality, Finality::BlockTag(BlockTag::Safe));
+        assert_eq!(
+            state.fee_model,
+            FeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas: WeiPerGas::new(1_000_000)
+            }
+        );
+        assert_eq!(
+            state.erc20_helper_contract_address,
+            Some(Address::from_str("0xE1788E4834c896F1932188645cc36c54d1b80AC1").unwrap())
+        );
+        assert_eq!(
+            state.last_erc20_scraped_block_number,
+            BlockNumber::from(20_u64)
+        );
+        assert_eq!(
+            state.ledger_suite_orchestrator_id,
+            Some(Principal::from_text("vxkom-oyaaa-aaaar-qafda-cai").unwrap())
+        );
+        assert_eq!(
+            state.rpc_providers,
+            vec![
+                CustomProvider {
+                    name: "alchemy".to_string(),
+                    url: "https://eth-mainnet.g.alchemy.com/v2/{API_KEY}".to_string(),
+                },
+                CustomProvider {
+                    name: "publicnode".to_string(),
+                    url: "https://ethereum.publicnode.com".to_string(),
+                }
+            ]
+        );
+        assert_eq!(
+            state.rpc_reduction_strategies,
+            maplit::btreemap! {RpcMethod::EthGetBlockByNumber => ReductionStrategy::MinByKey}
+        );
+
+        state
+            .upgrade(UpgradeArg {
+                rpc_providers: Some(vec![]),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+        assert_eq!(state.rpc_providers, vec![]);
+        assert_eq!(
+            state.rpc_reduction_strategies,
+            maplit::btreemap! {RpcMethod::EthGetBlockByNumber => ReductionStrategy::MinByKey}
+        );
+
+        state
+            .upgrade(UpgradeArg {
+                confirmation_depth: Some(12),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+        assert_eq!(state.finality, Finality::ConfirmationDepth(12));
+
+        state
+            .upgrade(UpgradeArg {
+                ethereum_block_height: Some(CandidBlockTag::Finalized),
+                ..Default::default()
+            })
+            .expect("valid upgrade args");
+        assert_eq!(state.finality, Finality::BlockTag(BlockTag::Finalized));
+    }
+
+    fn rpc_provider(name: &str, url: &str) -> RpcProvider {
+        RpcProvider {
+            name: name.to_string(),
+            url: url.to_string(),
+            api_key: None,
+        }
     }
 
     fn initial_state() -> State {
         use crate::lifecycle::init::InitArg;
-        use candid::Principal;
         State::try_from(InitArg {
             ethereum_network: Default::default(),
             ecdsa_key_name: "test_key_1".to_string(),
@@ -300,6 +518,9 @@ mod upgrade {
             minimum_withdrawal_amount: wei_from_milli_ether(10).into(),
             next_transaction_nonce: Default::default(),
             last_scraped_block_number: Default::default(),
+            rpc_providers: None,
+            confirmation_depth: None,
+            fee_model: None,
         })
         .expect("valid init args")
     }
@@ -377,7 +598,10 @@ prop_compose! {
             ethereum_block_height,
             minimum_withdrawal_amount,
             next_transaction_nonce,
-            last_scraped_block_number
+            last_scraped_block_number,
+            rpc_providers: None,
+            confirmation_depth: None,
+            fee_model: None,
         }
     }
 }
@@ -388,16 +612,77 @@ prop_compose! {
         ethereum_block_height in proptest::option::of(arb_block_tag()),
         minimum_withdrawal_amount in proptest::option::of(arb_nat()),
         next_transaction_nonce in proptest::option::of(arb_nat()),
+        erc20_helper_contract_address in proptest::option::of(arb_address()),
+        last_erc20_scraped_block_number in proptest::option::of(arb_nat()),
+        ledger_suite_orchestrator_id in proptest::option::of(arb_principal()),
+        rpc_providers in proptest::option::of(pvec(arb_rpc_provider(), 0..5)),
+        rpc_reduction_strategies in proptest::option::of(pvec(arb_rpc_reduction_strategy(), 0..5)),
+        confirmation_depth in proptest::option::of(any::<u64>()),
+        fee_model in proptest::option::of(arb_fee_model()),
     ) -> UpgradeArg {
         UpgradeArg {
             ethereum_contract_address: contract_address.map(|addr| addr.to_string()),
             ethereum_block_height,
             minimum_withdrawal_amount,
             next_transaction_nonce,
+            erc20_helper_contract_address: erc20_helper_contract_address.map(|addr| addr.to_string()),
+            last_erc20_scraped_block_number,
+            ledger_suite_orchestrator_id,
+            rpc_providers,
+            rpc_reduction_strategies,
+            confirmation_depth,
+            fee_model,
         }
     }
 }
 
+fn arb_fee_model() -> impl Strategy<Value = CandidFeeModel> {
+    prop_oneof![
+        Just(CandidFeeModel::Eip1559),
+        arb_nat().prop_map(
+            |max_priority_fee_per_gas| CandidFeeModel::Eip1559FixedPriorityFee {
+                max_priority_fee_per_gas
+            }
+        ),
+        (arb_nat(), arb_nat()).prop_map(|(max_priority_fee_per_gas, max_l1_data_fee)| {
+            CandidFeeModel::OpStack {
+                max_priority_fee_per_gas,
+                max_l1_data_fee,
+            }
+        }),
+    ]
+}
+
+prop_compose! {
+    fn arb_rpc_provider()(
+        name in "[a-z]+",
+        url in "https://[a-z]+\\.[a-z]+",
+    ) -> RpcProvider {
+        RpcProvider {
+            name,
+            url,
+            api_key: None,
+        }
+    }
+}
+
+fn arb_rpc_reduction_strategy() -> impl Strategy<Value = RpcReductionStrategy> {
+    (
+        prop_oneof![
+            Just(RpcMethod::EthGetLogs),
+            Just(RpcMethod::EthGetBlockByNumber),
+            Just(RpcMethod::EthGetTransactionReceipt),
+            Just(RpcMethod::EthFeeHistory),
+        ],
+        prop_oneof![
+            Just(ReductionStrategy::Equality),
+            Just(ReductionStrategy::StrictMajority),
+            Just(ReductionStrategy::MinByKey),
+        ],
+    )
+        .prop_map(|(method, strategy)| RpcReductionStrategy { method, strategy })
+}
+
 prop_compose! {
     fn arb_received_eth_event()(
         transaction_hash in arb_hash(),
@@ -418,6 +703,44 @@ prop_compose! {
     }
 }
 
+prop_compose! {
+    fn arb_received_erc20_event()(
+        transaction_hash in arb_hash(),
+        block_number in arb_checked_amount_of(),
+        log_index in arb_checked_amount_of(),
+        from_address in arb_address(),
+        value in arb_checked_amount_of(),
+        principal in arb_principal(),
+        erc20_contract_address in arb_address(),
+    ) -> ReceivedErc20Event {
+        ReceivedErc20Event {
+            transaction_hash,
+            block_number,
+            log_index,
+            from_address,
+            value,
+            principal,
+            erc20_contract_address,
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_ckerc20_token()(
+        erc20_ethereum_network in prop_oneof![Just(EthereumNetwork::Mainnet), Just(EthereumNetwork::Sepolia)],
+        erc20_contract_address in arb_address(),
+        ckerc20_token_symbol in "[a-zA-Z0-9]{1,20}",
+        ckerc20_ledger_id in arb_principal(),
+    ) -> CkErc20Token {
+        CkErc20Token {
+            erc20_ethereum_network,
+            erc20_contract_address,
+            ckerc20_token_symbol,
+            ckerc20_ledger_id,
+        }
+    }
+}
+
 prop_compose! {
     fn arb_unsigned_tx()(
         chain_id in any::<u64>(),
@@ -477,6 +800,7 @@ prop_compose! {
         gas_used in arb_checked_amount_of(),
         status in arb_transaction_status(),
         transaction_hash in arb_hash(),
+        l1_fee in proptest::option::of(arb_checked_amount_of()),
     ) -> TransactionReceipt {
         TransactionReceipt {
             block_hash,
@@ -485,6 +809,7 @@ prop_compose! {
             gas_used,
             status,
             transaction_hash,
+            l1_fee,
         }
     }
 }
@@ -529,6 +854,26 @@ fn arb_event_type() -> impl Strategy<Value = EventType> {
                 transaction_receipt,
             }
         }),
+        arb_received_erc20_event().prop_map(EventType::AcceptedErc20Deposit),
+        (
+            arb_event_source(),
+            any::<u64>(),
+            "[a-zA-Z0-9]{1,20}",
+            arb_address()
+        )
+            .prop_map(
+                |(event_source, index, ckerc20_token_symbol, erc20_contract_address)| {
+                    EventType::MintedCkErc20 {
+                        event_source,
+                        mint_block_index: index.into(),
+                        ckerc20_token_symbol,
+                        erc20_contract_address,
+                    }
+                }
+            ),
+        arb_checked_amount_of()
+            .prop_map(|block_number| EventType::SyncedErc20ToBlock { block_number }),
+        arb_ckerc20_token().prop_map(EventType::AddedCkErc20Token),
     ]
 }
 
@@ -672,6 +1017,7 @@ fn state_equivalence() {
                     "0x06afc3c693dc2ba2c19b5c287c4dddce040d766bea5fd13c8a7268b04aa94f2d"
                         .parse()
                         .unwrap(),
+                l1_fee: None,
             })
             .expect("valid receipt"),
         ),
@@ -702,6 +1048,10 @@ fn state_equivalence() {
                 withdrawal_id: LedgerBurnIndex::new(6),
             },
         },
+        erc20_withdrawal_requests: Default::default(),
+        erc20_maybe_reimburse: Default::default(),
+        erc20_reimbursement_requests: Default::default(),
+        erc20_reimbursed: Default::default(),
     };
     let state = State {
         ethereum_network: EthereumNetwork::Mainnet,
@@ -717,7 +1067,8 @@ fn state_equivalence() {
             chain_code: vec![2; 32],
         }),
         minimum_withdrawal_amount: Wei::new(1_000_000_000_000_000),
-        ethereum_block_height: BlockTag::Finalized,
+        finality: Finality::BlockTag(BlockTag::Finalized),
+        fee_model: FeeModel::Eip1559,
         first_scraped_block_number: BlockNumber::new(1_000_001),
         last_scraped_block_number: BlockNumber::new(1_000_000),
         last_observed_block_number: Some(BlockNumber::new(2_000_000)),
@@ -752,6 +1103,16 @@ fn state_equivalence() {
         active_tasks: Default::default(),
         http_request_counter: 100,
         eth_balance: Default::default(),
+        ledger_suite_orchestrator_id: None,
+        erc20_helper_contract_address: None,
+        ckerc20_tokens: Default::default(),
+        last_erc20_scraped_block_number: BlockNumber::new(1_000_000),
+        erc20_events_to_mint: Default::default(),
+        minted_erc20_events: Default::default(),
+        erc20_balances: Default::default(),
+        rpc_providers: Default::default(),
+        rpc_reduction_strategies: Default::default(),
+        rpc_provider_errors: Default::default(),
     };
 
     assert_eq!(
@@ -760,6 +1121,7 @@ fn state_equivalence() {
             ecdsa_public_key: None,
             last_observed_block_number: None,
             http_request_counter: 0,
+            rpc_provider_errors: btreemap! {("ankr".to_string(), "eth_getLogs".to_string()) => 1},
             ..state.clone()
         }),
         "changing only computed/transient fields should result in an equivalent state",
@@ -813,7 +1175,25 @@ fn state_equivalence() {
     assert_ne!(
         Ok(()),
         state.is_equivalent_to(&State {
-            ethereum_block_height: BlockTag::Latest,
+            finality: Finality::BlockTag(BlockTag::Latest),
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
+
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            finality: Finality::ConfirmationDepth(12),
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
+
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            fee_model: EthereumNetwork::Base.default_fee_model(),
             ..state.clone()
         }),
         "changing essential fields should break equivalence",
@@ -1179,6 +1559,7 @@ mod eth_balance {
                 gas_used: signed_tx.transaction().gas_limit,
                 status: self.tx_status,
                 transaction_hash: signed_tx.hash(),
+                l1_fee: self.l1_fee,
             };
             apply_state_transition(
                 state,
@@ -1191,3 +1572,363 @@ mod eth_balance {
         }
     }
 }
+
+mod erc20 {
+    use crate::erc20::CkErc20Token;
+    use crate::eth_logs::ReceivedErc20Event;
+    use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
+    use crate::lifecycle::EthereumNetwork;
+    use crate::numeric::{
+        BlockNumber, Erc20Value, GasAmount, LedgerBurnIndex, LedgerMintIndex, LogIndex,
+        TransactionNonce, Wei, WeiPerGas,
+    };
+    use crate::state::audit::{apply_state_transition, EventType};
+    use crate::state::tests::{a_state, received_eth_event};
+    use crate::state::transactions::{
+        create_erc20_transaction, Erc20ReimbursementRequest, Erc20WithdrawalRequest,
+    };
+    use crate::state::{MintedErc20Event, State};
+    use crate::tx::{Eip1559Signature, SignedEip1559TransactionRequest, TransactionPrice};
+    use maplit::btreemap;
+
+    #[test]
+    fn should_add_ckerc20_token() {
+        let mut state = a_state();
+
+        apply_state_transition(&mut state, &EventType::AddedCkErc20Token(ckusdc()));
+
+        assert_eq!(
+            state.ckerc20_tokens,
+            btreemap! { ckusdc().erc20_contract_address => ckusdc() }
+        );
+        assert_eq!(
+            state.find_ck_erc20_token_by_ledger_id(&ckusdc().ckerc20_ledger_id),
+            Some(&ckusdc())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is already supported")]
+    fn should_not_add_same_ckerc20_token_twice() {
+        let mut state = a_state();
+        apply_state_transition(&mut state, &EventType::AddedCkErc20Token(ckusdc()));
+
+        apply_state_transition(
+            &mut state,
+            &EventType::AddedCkErc20Token(CkErc20Token {
+                ckerc20_token_symbol: "ckUSDC2".to_string(),
+                ckerc20_ledger_id: "sa4so-piaaa-aaaar-qacnq-cai".parse().unwrap(),
+                ..ckusdc()
+            }),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERROR: Expected Ethereum Testnet Sepolia")]
+    fn should_not_add_ckerc20_token_from_other_network() {
+        let mut state = a_state();
+
+        apply_state_transition(
+            &mut state,
+            &EventType::AddedCkErc20Token(CkErc20Token {
+                erc20_ethereum_network: EthereumNetwork::Mainnet,
+                ..ckusdc()
+            }),
+        );
+    }
+
+    #[test]
+    fn should_record_erc20_deposit_and_mint() {
+        let mut state = a_state();
+        apply_state_transition(&mut state, &EventType::AddedCkErc20Token(ckusdc()));
+        let deposit = received_erc20_event();
+
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedErc20Deposit(deposit.clone()),
+        );
+
+        assert_eq!(
+            state.erc20_events_to_mint,
+            btreemap! { deposit.source() => deposit.clone() }
+        );
+        assert_eq!(
+            state.erc20_balances,
+            btreemap! { deposit.erc20_contract_address => deposit.value }
+        );
+        assert_eq!(state.eth_balance, a_state().eth_balance);
+
+        apply_state_transition(
+            &mut state,
+            &EventType::MintedCkErc20 {
+                event_source: deposit.source(),
+                mint_block_index: LedgerMintIndex::new(42),
+                ckerc20_token_symbol: "ckUSDC".to_string(),
+                erc20_contract_address: deposit.erc20_contract_address,
+            },
+        );
+
+        assert!(state.erc20_events_to_mint.is_empty());
+        assert_eq!(
+            state.minted_erc20_events,
+            btreemap! {
+                deposit.source() => MintedErc20Event {
+                    deposit_event: deposit.clone(),
+                    mint_block_index: LedgerMintIndex::new(42),
+                    ckerc20_token_symbol: "ckUSDC".to_string(),
+                }
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported ERC-20 token")]
+    fn should_not_accept_deposit_of_unsupported_erc20_token() {
+        let mut state = a_state();
+
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedErc20Deposit(received_erc20_event()),
+        );
+    }
+
+    #[test]
+    fn should_update_balances_after_successful_erc20_withdrawal() {
+        let mut state = state_with_erc20_deposit();
+        let eth_balance_before = state.eth_balance.clone();
+
+        let receipt = Erc20WithdrawalFlow {
+            tx_status: TransactionStatus::Success,
+            l1_fee: None,
+        }
+        .apply(&mut state);
+
+        let tx_fee = receipt.effective_transaction_fee();
+        assert_eq!(
+            state.eth_balance.eth_balance(),
+            eth_balance_before
+                .eth_balance()
+                .checked_sub(tx_fee)
+                .unwrap()
+        );
+        assert_eq!(state.eth_balance.total_effective_tx_fees(), tx_fee);
+        assert_eq!(
+            state.eth_balance.total_unspent_tx_fees(),
+            max_transaction_fee().checked_sub(tx_fee).unwrap()
+        );
+        assert_eq!(
+            state.erc20_balances,
+            btreemap! {
+                ckusdc().erc20_contract_address => received_erc20_event()
+                    .value
+                    .checked_sub(withdrawal_amount())
+                    .unwrap()
+            }
+        );
+        assert!(state
+            .eth_transactions
+            .get_erc20_reimbursement_requests()
+            .is_empty());
+    }
+
+    #[test]
+    fn should_reimburse_ckerc20_after_failed_erc20_withdrawal() {
+        let mut state = state_with_erc20_deposit();
+        let eth_balance_before = state.eth_balance.clone();
+
+        let receipt = Erc20WithdrawalFlow {
+            tx_status: TransactionStatus::Failure,
+            l1_fee: None,
+        }
+        .apply(&mut state);
+
+        let tx_fee = receipt.effective_transaction_fee();
+        assert_eq!(
+            state.eth_balance.eth_balance(),
+            eth_balance_before
+                .eth_balance()
+                .checked_sub(tx_fee)
+                .unwrap()
+        );
+        assert_eq!(
+            state.erc20_balances,
+            btreemap! { ckusdc().erc20_contract_address => received_erc20_event().value }
+        );
+        assert_eq!(
+            state.eth_transactions.get_erc20_reimbursement_requests(),
+            vec![Erc20ReimbursementRequest {
+                withdrawal_id: LedgerBurnIndex::new(10),
+                ledger_id: ckusdc().ckerc20_ledger_id,
+                burn_in_block: LedgerBurnIndex::new(7),
+                reimbursed_amount: withdrawal_amount(),
+                to: erc20_withdrawal_request().from,
+                to_subaccount: None,
+                transaction_hash: receipt.transaction_hash,
+            }]
+        );
+    }
+
+    #[test]
+    fn should_absorb_l1_data_fee_exceeding_the_charged_transaction_fee() {
+        let mut state = state_with_erc20_deposit();
+        let eth_balance_before = state.eth_balance.clone();
+
+        let receipt = Erc20WithdrawalFlow {
+            tx_status: TransactionStatus::Success,
+            l1_fee: Some(max_transaction_fee()),
+        }
+        .apply(&mut state);
+
+        let tx_fee = receipt.effective_transaction_fee();
+        assert!(tx_fee > max_transaction_fee());
+        assert_eq!(
+            state.eth_balance.eth_balance(),
+            eth_balance_before
+                .eth_balance()
+                .checked_sub(tx_fee)
+                .unwrap()
+        );
+        assert_eq!(state.eth_balance.total_effective_tx_fees(), tx_fee);
+        assert_eq!(state.eth_balance.total_unspent_tx_fees(), Wei::ZERO);
+    }
+
+    fn state_with_erc20_deposit() -> State {
+        let mut state = a_state();
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_eth_event()),
+        );
+        apply_state_transition(&mut state, &EventType::AddedCkErc20Token(ckusdc()));
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedErc20Deposit(received_erc20_event()),
+        );
+        state
+    }
+
+    struct Erc20WithdrawalFlow {
+        tx_status: TransactionStatus,
+        l1_fee: Option<Wei>,
+    }
+
+    impl Erc20WithdrawalFlow {
+        fn apply(self, state: &mut State) -> TransactionReceipt {
+            let withdrawal_request = erc20_withdrawal_request();
+            apply_state_transition(
+                state,
+                &EventType::AcceptedErc20WithdrawalRequest(withdrawal_request.clone()),
+            );
+
+            let transaction = create_erc20_transaction(
+                &withdrawal_request,
+                TransactionNonce::ZERO,
+                TransactionPrice {
+                    gas_limit: GasAmount::new(65_000),
+                    max_fee_per_gas: WeiPerGas::new(1_000_000_000),
+                    max_priority_fee_per_gas: WeiPerGas::new(1_000_000_000),
+                },
+                Wei::ZERO,
+                EthereumNetwork::Sepolia,
+            )
+            .expect("BUG: burned ckETH covers the transaction fee");
+            apply_state_transition(
+                state,
+                &EventType::CreatedTransaction {
+                    withdrawal_id: withdrawal_request.cketh_ledger_burn_index,
+                    transaction: transaction.clone(),
+                },
+            );
+
+            let signed_tx = SignedEip1559TransactionRequest::from((
+                transaction,
+                Eip1559Signature {
+                    signature_y_parity: false,
+                    r: Default::default(),
+                    s: Default::default(),
+                },
+            ));
+            apply_state_transition(
+                state,
+                &EventType::SignedTransaction {
+                    withdrawal_id: withdrawal_request.cketh_ledger_burn_index,
+                    transaction: signed_tx.clone(),
+                },
+            );
+
+            let tx_receipt = TransactionReceipt {
+                block_hash: "0xce67a85c9fb8bc50213815c32814c159fd75160acf7cb8631e8e7b7cf7f1d472"
+                    .parse()
+                    .unwrap(),
+                block_number: BlockNumber::new(4190269),
+                effective_gas_price: WeiPerGas::new(500_000_000),
+                gas_used: GasAmount::new(50_000),
+                status: self.tx_status,
+                transaction_hash: signed_tx.hash(),
+                l1_fee: self.l1_fee,
+            };
+            apply_state_transition(
+                state,
+                &EventType::FinalizedTransaction {
+                    withdrawal_id: withdrawal_request.cketh_ledger_burn_index,
+                    transaction_receipt: tx_receipt.clone(),
+                },
+            );
+            tx_receipt
+        }
+    }
+
+    fn ckusdc() -> CkErc20Token {
+        CkErc20Token {
+            erc20_ethereum_network: EthereumNetwork::Sepolia,
+            erc20_contract_address: "0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"
+                .parse()
+                .unwrap(),
+            ckerc20_token_symbol: "ckUSDC".to_string(),
+            ckerc20_ledger_id: "mxzaz-hqaaa-aaaar-qaada-cai".parse().unwrap(),
+        }
+    }
+
+    fn received_erc20_event() -> ReceivedErc20Event {
+        ReceivedErc20Event {
+            transaction_hash: "0xf353e17cbf7c6a8a3bd6e7e5a25a9e8c5c5b5f7b5e2d6f5e8f2d2b0b5e2d6f5e"
+                .parse()
+                .unwrap(),
+            block_number: BlockNumber::new(5_539_800),
+            log_index: LogIndex::from(3_u8),
+            from_address: "0xdd2851cdd40ae6536831558dd46db62fac7a844d"
+                .parse()
+                .unwrap(),
+            value: Erc20Value::from(2_000_000_u64),
+            principal: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            erc20_contract_address: ckusdc().erc20_contract_address,
+        }
+    }
+
+    fn max_transaction_fee() -> Wei {
+        Wei::new(100_000_000_000_000)
+    }
+
+    fn withdrawal_amount() -> Erc20Value {
+        Erc20Value::from(1_500_000_u64)
+    }
+
+    fn erc20_withdrawal_request() -> Erc20WithdrawalRequest {
+        Erc20WithdrawalRequest {
+            max_transaction_fee: max_transaction_fee(),
+            withdrawal_amount: withdrawal_amount(),
+            destination: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            cketh_ledger_burn_index: LedgerBurnIndex::new(10),
+            erc20_contract_address: ckusdc().erc20_contract_address,
+            ckerc20_ledger_id: ckusdc().ckerc20_ledger_id,
+            ckerc20_ledger_burn_index: LedgerBurnIndex::new(7),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+        }
+    }
+}
@@ -3,12 +3,15 @@ mod tests;
 
 use crate::address::Address;
 use crate::endpoints::{EthTransaction, RetrieveEthStatus, TxFinalizedStatus};
+use crate::erc20::encode_erc20_transfer_data;
 use crate::eth_rpc::Hash;
 use crate::eth_rpc_client::responses::TransactionReceipt;
 use crate::eth_rpc_client::responses::TransactionStatus;
 use crate::lifecycle::EthereumNetwork;
 use crate::map::MultiKeyMap;
-use crate::numeric::{LedgerBurnIndex, LedgerMintIndex, TransactionCount, TransactionNonce, Wei};
+use crate::numeric::{
+    Erc20Value, LedgerBurnIndex, LedgerMintIndex, TransactionCount, TransactionNonce, Wei,
+};
 use crate::tx::{
     Eip1559TransactionRequest, FinalizedEip1559Transaction, SignedEip1559TransactionRequest,
     TransactionPrice,
@@ -34,6 +37,37 @@ pub struct EthWithdrawalRequest {
     pub from_subaccount: Option<Subaccount>,
 }
 
+/// ERC-20 withdrawal request issued by the user.
+/// The transaction fees are paid in ckETH, which was burned when the request was accepted.
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+pub struct Erc20WithdrawalRequest {
+    /// Amount of burned ckETH that can be used to pay for the Ethereum transaction fees.
+    #[n(0)]
+    pub max_transaction_fee: Wei,
+    /// Amount of ERC-20 tokens to withdraw.
+    #[n(1)]
+    pub withdrawal_amount: Erc20Value,
+    /// Address receiving the ERC-20 tokens.
+    #[n(2)]
+    pub destination: Address,
+    /// Index of the ckETH burn transaction on the ckETH ledger, which identifies the withdrawal.
+    #[cbor(n(3), with = "crate::cbor::id")]
+    pub cketh_ledger_burn_index: LedgerBurnIndex,
+    /// Address of the ERC-20 smart contract.
+    #[n(4)]
+    pub erc20_contract_address: Address,
+    /// The ckERC20 ledger on which the tokens were burned.
+    #[cbor(n(5), with = "crate::cbor::principal")]
+    pub ckerc20_ledger_id: Principal,
+    /// Index of the ckERC20 burn transaction on the ckERC20 ledger.
+    #[cbor(n(6), with = "crate::cbor::id")]
+    pub ckerc20_ledger_burn_index: LedgerBurnIndex,
+    #[cbor(n(7), with = "crate::cbor::principal")]
+    pub from: Principal,
+    #[n(8)]
+    pub from_subaccount: Option<Subaccount>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
 pub struct ReimbursementRequest {
     #[cbor(n(0), with = "crate::cbor::id")]
@@ -56,6 +90,41 @@ pub struct Reimbursed {
     pub reimbursed_amount: Wei,
 }
 
+/// Reimbursement of the ckERC20 tokens of a failed ERC-20 withdrawal.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub struct Erc20ReimbursementRequest {
+    #[cbor(n(0), with = "crate::cbor::id")]
+    pub withdrawal_id: LedgerBurnIndex,
+    #[cbor(n(1), with = "crate::cbor::principal")]
+    pub ledger_id: Principal,
+    #[cbor(n(2), with = "crate::cbor::id")]
+    pub burn_in_block: LedgerBurnIndex,
+    #[n(3)]
+    pub reimbursed_amount: Erc20Value,
+    #[cbor(n(4), with = "crate::cbor::principal")]
+    pub to: Principal,
+    #[n(5)]
+    pub to_subaccount: Option<Subaccount>,
+    #[n(6)]
+    pub transaction_hash: Hash,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub struct Erc20Reimbursed {
+    #[cbor(n(0), with = "crate::cbor::id")]
+    pub withdrawal_id: LedgerBurnIndex,
+    #[cbor(n(1), with = "crate::cbor::id")]
+    pub burn_in_block: LedgerBurnIndex,
+    #[cbor(n(2), with = "crate::cbor::id")]
+    pub reimbursed_in_block: LedgerMintIndex,
+    #[cbor(n(3), with = "crate::cbor::principal")]
+    pub ledger_id: Principal,
+    #[n(4)]
+    pub reimbursed_amount: Erc20Value,
+    #[n(5)]
+    pub transaction_hash: Hash,
+}
+
 #[derive(Clone, Eq, PartialEq, Encode, Decode)]
 #[cbor(transparent)]
 pub struct Subaccount(#[cbor(n(0), with = "minicbor::bytes")] pub [u8; 32]);
@@ -86,6 +155,25 @@ impl fmt::Debug for EthWithdrawalRequest {
     }
 }
 
+impl fmt::Debug for Erc20WithdrawalRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("Erc20WithdrawalRequest")
+            .field("max_transaction_fee", &self.max_transaction_fee)
+            .field("withdrawal_amount", &self.withdrawal_amount)
+            .field("destination", &self.destination)
+            .field("cketh_ledger_burn_index", &self.cketh_ledger_burn_index)
+            .field("erc20_contract_address", &self.erc20_contract_address)
+            .field(
+                "ckerc20_ledger_id",
+                &DebugPrincipal(&self.ckerc20_ledger_id),
+            )
+            .field("ckerc20_ledger_burn_index", &self.ckerc20_ledger_burn_index)
+            .field("from", &DebugPrincipal(&self.from))
+            .field("from_subaccount", &self.from_subaccount)
+            .finish()
+    }
+}
+
 /// State machine holding Ethereum transactions issued by the minter.
 /// Overall the transaction lifecycle is as follows:
 /// 1. The user's withdrawal request is enqueued and processed in a FIFO order.
@@ -100,6 +188,11 @@ impl fmt::Debug for EthWithdrawalRequest {
 ///    The others sent transactions for that nonce were never mined and can be discarded.
 /// 6. If a given transaction fails the minter will reimburse the user who requested the
 ///    withdrawal with the corresponding amount minus fees.
+///
+/// ERC-20 withdrawal requests go through the same lifecycle, using the burn index on the
+/// ckETH ledger (where the transaction fees were paid) as identifier. They only differ in that
+/// the transaction fees are bounded by the burned ckETH amount instead of being deducted from
+/// the withdrawn amount, and that a failed transaction is reimbursed on the ckERC20 ledger.
 #[derive(Clone, Debug, Eq, PartialEq)]
 // TODO FI-948: limit number of withdrawal_requests and pending transactions nonces
 pub struct EthTransactions {
@@ -115,6 +208,12 @@ pub struct EthTransactions {
     pub(in crate::state) maybe_reimburse: BTreeMap<LedgerBurnIndex, EthWithdrawalRequest>,
     pub(in crate::state) reimbursement_requests: BTreeMap<LedgerBurnIndex, ReimbursementRequest>,
     pub(in crate::state) reimbursed: BTreeMap<LedgerBurnIndex, Reimbursed>,
+
+    pub(in crate::state) erc20_withdrawal_requests: VecDeque<Erc20WithdrawalRequest>,
+    pub(in crate::state) erc20_maybe_reimburse: BTreeMap<LedgerBurnIndex, Erc20WithdrawalRequest>,
+    pub(in crate::state) erc20_reimbursement_requests:
+        BTreeMap<LedgerBurnIndex, Erc20ReimbursementRequest>,
+    pub(in crate::state) erc20_reimbursed: BTreeMap<LedgerBurnIndex, Erc20Reimbursed>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -124,6 +223,11 @@ pub enum CreateTransactionError {
         withdrawal_amount: Wei,
         max_transaction_fee: Wei,
     },
+    InsufficientTransactionFee {
+        ledger_burn_index: LedgerBurnIndex,
+        allowed_max_transaction_fee: Wei,
+        actual_max_transaction_fee: Wei,
+    },
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -134,6 +238,12 @@ pub enum ResubmitTransactionError {
         transaction_amount: Wei,
         max_transaction_fee: Wei,
     },
+    InsufficientTransactionFee {
+        ledger_burn_index: LedgerBurnIndex,
+        transaction_nonce: TransactionNonce,
+        allowed_max_transaction_fee: Wei,
+        max_transaction_fee: Wei,
+    },
 }
 
 impl EthTransactions {
@@ -147,6 +257,10 @@ impl EthTransactions {
             maybe_reimburse: Default::default(),
             reimbursement_requests: Default::default(),
             reimbursed: Default::default(),
+            erc20_withdrawal_requests: VecDeque::new(),
+            erc20_maybe_reimburse: Default::default(),
+            erc20_reimbursement_requests: Default::default(),
+            erc20_reimbursed: Default::default(),
         }
     }
 
@@ -166,19 +280,54 @@ impl EthTransactions {
         self.reimbursed.values().cloned().collect()
     }
 
+    pub fn get_erc20_reimbursement_requests(&self) -> Vec<Erc20ReimbursementRequest> {
+        self.erc20_reimbursement_requests
+            .values()
+            .cloned()
+            .collect()
+    }
+
     pub fn record_withdrawal_request(&mut self, request: EthWithdrawalRequest) {
-        let burn_index = request.ledger_burn_index;
+        self.assert_new_withdrawal_id(&request.ledger_burn_index);
+        self.withdrawal_requests.push_back(request);
+    }
+
+    pub fn record_erc20_withdrawal_request(&mut self, request: Erc20WithdrawalRequest) {
+        self.assert_new_withdrawal_id(&request.cketh_ledger_burn_index);
+        self.erc20_withdrawal_requests.push_back(request);
+    }
+
+    /// Records the reimbursement of the ckETH burned to pay for the transaction fees of an
+    /// ERC-20 withdrawal request, that was rejected because burning the ckERC20 tokens failed.
+    pub fn record_failed_erc20_withdrawal_request(&mut self, request: ReimbursementRequest) {
+        let withdrawal_id = request.withdrawal_id;
+        self.assert_new_withdrawal_id(&withdrawal_id);
+        assert!(
+            !self.reimbursed.contains_key(&withdrawal_id),
+            "BUG: withdrawal {withdrawal_id} was already reimbursed"
+        );
+        assert_eq!(
+            self.reimbursement_requests.insert(withdrawal_id, request),
+            None,
+            "BUG: duplicate reimbursement request for withdrawal {withdrawal_id}"
+        );
+    }
+
+    fn assert_new_withdrawal_id(&self, burn_index: &LedgerBurnIndex) {
         if self
             .withdrawal_requests
             .iter()
-            .any(|r| r.ledger_burn_index == burn_index)
-            || self.created_tx.contains_alt(&burn_index)
-            || self.sent_tx.contains_alt(&burn_index)
-            || self.finalized_tx.contains_alt(&burn_index)
+            .any(|r| &r.ledger_burn_index == burn_index)
+            || self
+                .erc20_withdrawal_requests
+                .iter()
+                .any(|r| &r.cketh_ledger_burn_index == burn_index)
+            || self.created_tx.contains_alt(burn_index)
+            || self.sent_tx.contains_alt(burn_index)
+            || self.finalized_tx.contains_alt(burn_index)
         {
             panic!("BUG: duplicate ledger burn index {burn_index}");
         }
-        self.withdrawal_requests.push_back(request);
     }
 
     /// Move an existing withdrawal request to the back of the queue.
@@ -196,11 +345,34 @@ impl EthTransactions {
         self.record_withdrawal_request(request);
     }
 
+    /// Move an existing ERC-20 withdrawal request to the back of the queue.
+    pub fn reschedule_erc20_withdrawal_request(&mut self, request: Erc20WithdrawalRequest) {
+        assert_eq!(
+            self.erc20_withdrawal_requests
+                .iter()
+                .filter(|r| r.cketh_ledger_burn_index == request.cketh_ledger_burn_index)
+                .count(),
+            1,
+            "BUG: expected exactly one ERC-20 withdrawal request with ledger burn index {}",
+            request.cketh_ledger_burn_index
+        );
+        self.erc20_withdrawal_requests.retain(|r| r != &request);
+        self.record_erc20_withdrawal_request(request);
+    }
+
     pub fn record_created_transaction(
         &mut self,
         withdrawal_id: LedgerBurnIndex,
         transaction: Eip1559TransactionRequest,
     ) {
+        if let Some(erc20_withdrawal_request) = self
+            .erc20_withdrawal_requests
+            .iter()
+            .find(|req| req.cketh_ledger_burn_index == withdrawal_id)
+            .cloned()
+        {
+            return self.record_created_erc20_transaction(erc20_withdrawal_request, transaction);
+        }
         let withdrawal_request = self
             .withdrawal_requests
             .iter()
@@ -219,20 +391,63 @@ impl EthTransactions {
             withdrawal_request.withdrawal_amount > transaction.amount,
             "BUG: transaction amount should be the withdrawal amount deducted from transaction fees"
         );
+        self.remove_withdrawal_request(&withdrawal_request);
+        self.record_created_transaction_with_next_nonce(withdrawal_id, transaction);
+        self.maybe_reimburse
+            .insert(withdrawal_id, withdrawal_request);
+    }
+
+    fn record_created_erc20_transaction(
+        &mut self,
+        withdrawal_request: Erc20WithdrawalRequest,
+        transaction: Eip1559TransactionRequest,
+    ) {
+        let withdrawal_id = withdrawal_request.cketh_ledger_burn_index;
+        assert_eq!(
+            withdrawal_request.erc20_contract_address, transaction.destination,
+            "BUG: ERC-20 transaction must be sent to the ERC-20 contract"
+        );
+        assert_eq!(
+            transaction.amount,
+            Wei::ZERO,
+            "BUG: ERC-20 transaction must not transfer ETH"
+        );
+        assert_eq!(
+            transaction.data,
+            encode_erc20_transfer_data(
+                &withdrawal_request.destination,
+                withdrawal_request.withdrawal_amount
+            ),
+            "BUG: ERC-20 transaction data does not match the withdrawal request"
+        );
+        assert!(
+            transaction.transaction_price().max_transaction_fee()
+                <= withdrawal_request.max_transaction_fee,
+            "BUG: ERC-20 transaction fee exceeds the amount of burned ckETH"
+        );
+        self.erc20_withdrawal_requests
+            .retain(|r| r != &withdrawal_request);
+        self.record_created_transaction_with_next_nonce(withdrawal_id, transaction);
+        self.erc20_maybe_reimburse
+            .insert(withdrawal_id, withdrawal_request);
+    }
+
+    fn record_created_transaction_with_next_nonce(
+        &mut self,
+        withdrawal_id: LedgerBurnIndex,
+        transaction: Eip1559TransactionRequest,
+    ) {
         let nonce = self.next_nonce;
         assert_eq!(transaction.nonce, nonce, "BUG: transaction nonce mismatch");
         self.next_nonce = self
             .next_nonce
             .checked_increment()
             .expect("Transaction nonce overflow");
-        self.remove_withdrawal_request(&withdrawal_request);
         assert_eq!(
             self.created_tx
-                .try_insert(nonce, withdrawal_request.ledger_burn_index, transaction),
+                .try_insert(nonce, withdrawal_id, transaction),
             Ok(())
         );
-        self.maybe_reimburse
-            .insert(withdrawal_id, withdrawal_request);
     }
 
     pub fn record_signed_transaction(
@@ -267,6 +482,9 @@ impl EthTransactions {
     /// with nonces greater than the latest mined transaction nonce:
     /// * the resubmitted transaction will need to be re-signed if its transaction fee was increased
     /// * the resubmitted transaction can be resent as is if its transaction fee was not increased
+    /// The fees of a transaction handling an ERC-20 withdrawal are not deducted from the
+    /// transaction amount but are bounded by the amount of ckETH burned for that withdrawal,
+    /// which must also cover `max_l1_data_fee`.
     /// We stop on the first error since if a transaction with nonce n could not be resubmitted
     /// (e.g., the transaction amount does not cover the new fees),
     /// then the next transactions with nonces n+1, n+2, ... are blocked anyway
@@ -275,6 +493,7 @@ impl EthTransactions {
         &self,
         latest_transaction_count: TransactionCount,
         current_transaction_price: TransactionPrice,
+        max_l1_data_fee: Wei,
     ) -> Vec<Result<(LedgerBurnIndex, Eip1559TransactionRequest), ResubmitTransactionError>> {
         // If transaction count at block height H is c > 0, then transactions with nonces
         // 0, 1, ..., c - 1 were mined. If transaction count is 0, then no transactions were mined.
@@ -294,6 +513,31 @@ impl EthTransactions {
                 let new_tx_price = last_tx_price
                     .increase_by_10_percent()
                     .max(current_transaction_price.clone());
+                if let Some(erc20_request) = self.erc20_maybe_reimburse.get(burn_index) {
+                    let max_transaction_fee = new_tx_price
+                        .max_transaction_fee()
+                        .checked_add(max_l1_data_fee)
+                        .expect("BUG: max transaction fee always fits into U256");
+                    if max_transaction_fee > erc20_request.max_transaction_fee {
+                        transactions_to_resubmit.push(Err(
+                            ResubmitTransactionError::InsufficientTransactionFee {
+                                ledger_burn_index: *burn_index,
+                                transaction_nonce: *nonce,
+                                allowed_max_transaction_fee: erc20_request.max_transaction_fee,
+                                max_transaction_fee,
+                            },
+                        ));
+                        return transactions_to_resubmit;
+                    }
+                    let new_tx = Eip1559TransactionRequest {
+                        max_priority_fee_per_gas: new_tx_price.max_priority_fee_per_gas,
+                        max_fee_per_gas: new_tx_price.max_fee_per_gas,
+                        gas_limit: new_tx_price.gas_limit,
+                        ..last_tx
+                    };
+                    transactions_to_resubmit.push(Ok((*burn_index, new_tx)));
+                    continue;
+                }
                 let new_amount = match last_tx.amount.checked_sub(
                     new_tx_price
                         .max_transaction_fee()
@@ -394,6 +638,23 @@ impl EthTransactions {
             Ok(())
         );
 
+        if let Some(erc20_request) = self.erc20_maybe_reimburse.remove(&ledger_burn_index) {
+            if receipt.status == TransactionStatus::Failure {
+                self.erc20_reimbursement_requests.insert(
+                    ledger_burn_index,
+                    Erc20ReimbursementRequest {
+                        withdrawal_id: ledger_burn_index,
+                        ledger_id: erc20_request.ckerc20_ledger_id,
+                        burn_in_block: erc20_request.ckerc20_ledger_burn_index,
+                        reimbursed_amount: erc20_request.withdrawal_amount,
+                        to: erc20_request.from,
+                        to_subaccount: erc20_request.from_subaccount,
+                        transaction_hash: receipt.transaction_hash,
+                    },
+                );
+            }
+            return;
+        }
         let maybe_reimburse = self.maybe_reimburse.remove(&ledger_burn_index).expect(
             "failed to remove entry from maybe_reimburse map with block index: {ledger_burn_index}",
         );
@@ -435,11 +696,58 @@ impl EthTransactions {
         );
     }
 
+    pub fn record_finalized_erc20_reimbursement(
+        &mut self,
+        withdrawal_id: LedgerBurnIndex,
+        reimbursed_in_block: LedgerMintIndex,
+    ) {
+        let reimbursement_request = self
+            .erc20_reimbursement_requests
+            .remove(&withdrawal_id)
+            .expect("failed to remove ERC-20 reimbursement request");
+        assert_eq!(
+            self.erc20_reimbursed.insert(
+                withdrawal_id,
+                Erc20Reimbursed {
+                    withdrawal_id,
+                    burn_in_block: reimbursement_request.burn_in_block,
+                    reimbursed_in_block,
+                    ledger_id: reimbursement_request.ledger_id,
+                    reimbursed_amount: reimbursement_request.reimbursed_amount,
+                    transaction_hash: reimbursement_request.transaction_hash,
+                },
+            ),
+            None
+        );
+    }
+
+    /// Returns the ETH withdrawal request that was used to create the transaction
+    /// with the given withdrawal identifier, if the transaction is not yet finalized.
+    pub fn get_pending_eth_withdrawal(
+        &self,
+        withdrawal_id: &LedgerBurnIndex,
+    ) -> Option<&EthWithdrawalRequest> {
+        self.maybe_reimburse.get(withdrawal_id)
+    }
+
+    /// Returns the ERC-20 withdrawal request that was used to create the transaction
+    /// with the given withdrawal identifier, if the transaction is not yet finalized.
+    pub fn get_pending_erc20_withdrawal(
+        &self,
+        withdrawal_id: &LedgerBurnIndex,
+    ) -> Option<&Erc20WithdrawalRequest> {
+        self.erc20_maybe_reimburse.get(withdrawal_id)
+    }
+
     pub fn transaction_status(&self, burn_index: &LedgerBurnIndex) -> RetrieveEthStatus {
         if self
             .withdrawal_requests
             .iter()
             .any(|r| &r.ledger_burn_index == burn_index)
+            || self
+                .erc20_withdrawal_requests
+                .iter()
+                .any(|r| &r.cketh_ledger_burn_index == burn_index)
         {
             return RetrieveEthStatus::Pending;
         }
@@ -460,6 +768,13 @@ impl EthTransactions {
                     reimbursed_amount: reimbursed.reimbursed_amount.into(),
                 });
             }
+            if let Some(reimbursed) = self.erc20_reimbursed.get(burn_index) {
+                return RetrieveEthStatus::TxFinalized(TxFinalizedStatus::Reimbursed {
+                    reimbursed_in_block: reimbursed.reimbursed_in_block.get().into(),
+                    transaction_hash: tx.transaction_hash().to_string(),
+                    reimbursed_amount: reimbursed.reimbursed_amount.into(),
+                });
+            }
             if tx.transaction_status() == &TransactionStatus::Failure {
                 return RetrieveEthStatus::TxFinalized(TxFinalizedStatus::PendingReimbursement(
                     EthTransaction {
@@ -480,6 +795,24 @@ impl EthTransactions {
         &self,
         requested_batch_size: usize,
     ) -> Vec<EthWithdrawalRequest> {
+        self.withdrawal_requests_iter()
+            .take(self.actual_batch_size(requested_batch_size))
+            .cloned()
+            .collect()
+    }
+
+    pub fn erc20_withdrawal_requests_batch(
+        &self,
+        requested_batch_size: usize,
+    ) -> Vec<Erc20WithdrawalRequest> {
+        self.erc20_withdrawal_requests
+            .iter()
+            .take(self.actual_batch_size(requested_batch_size))
+            .cloned()
+            .collect()
+    }
+
+    fn actual_batch_size(&self, requested_batch_size: usize) -> usize {
         // The number of pending transaction nonces is counted and not the number of pending transactions
         // because a nonce may be associated with several distinct transactions (due to re-submission and dynamic fees).
         // However, once a nonce is chosen for a withdrawal request, it's in our interest that the corresponding transaction be finalized asap.
@@ -487,15 +820,11 @@ impl EthTransactions {
         const MAX_NUM_PENDING_TRANSACTION_NONCES: usize = 1000;
         let unique_pending_transaction_nonces: BTreeSet<_> =
             self.created_tx.keys().chain(self.sent_tx.keys()).collect();
-        let actual_batch_size = min(
+        min(
             MAX_NUM_PENDING_TRANSACTION_NONCES
                 .saturating_sub(unique_pending_transaction_nonces.len()),
             requested_batch_size,
-        );
-        self.withdrawal_requests_iter()
-            .take(actual_batch_size)
-            .cloned()
-            .collect()
+        )
     }
 
     pub fn withdrawal_requests_iter(&self) -> impl Iterator<Item = &EthWithdrawalRequest> {
@@ -503,7 +832,7 @@ impl EthTransactions {
     }
 
     pub fn withdrawal_requests_len(&self) -> usize {
-        self.withdrawal_requests.len()
+        self.withdrawal_requests.len() + self.erc20_withdrawal_requests.len()
     }
 
     pub fn transactions_to_sign_iter(
@@ -577,6 +906,7 @@ impl EthTransactions {
 
     pub fn has_pending_requests(&self) -> bool {
         !self.withdrawal_requests.is_empty()
+            || !self.erc20_withdrawal_requests.is_empty()
             || !self.created_tx.is_empty()
             || !self.sent_tx.is_empty()
     }
@@ -622,6 +952,14 @@ impl EthTransactions {
             buf
         }
 
+        fn sorted_erc20_requests(
+            requests: &VecDeque<Erc20WithdrawalRequest>,
+        ) -> Vec<Erc20WithdrawalRequest> {
+            let mut buf: Vec<_> = requests.iter().cloned().collect();
+            buf.sort_unstable_by_key(|req| req.cketh_ledger_burn_index);
+            buf
+        }
+
         // We can reorder request in `reschedule_withdrawal_request`. The audit log won't
         // reflect this change, so we must sort the queues before comparing them.
         ensure_eq!(
@@ -637,13 +975,25 @@ impl EthTransactions {
         ensure_eq!(self.reimbursement_requests, other.reimbursement_requests);
         ensure_eq!(self.reimbursed, other.reimbursed);
 
+        ensure_eq!(
+            sorted_erc20_requests(&self.erc20_withdrawal_requests),
+            sorted_erc20_requests(&other.erc20_withdrawal_requests)
+        );
+        ensure_eq!(self.erc20_maybe_reimburse, other.erc20_maybe_reimburse);
+        ensure_eq!(
+            self.erc20_reimbursement_requests,
+            other.erc20_reimbursement_requests
+        );
+        ensure_eq!(self.erc20_reimbursed, other.erc20_reimbursed);
+
         Ok(())
     }
 }
 
 /// Creates an EIP-1559 transaction for the given withdrawal request.
 /// The transaction fees are paid by the beneficiary,
-/// meaning that the fees will be deducted from the withdrawal amount.
+/// meaning that the fees, including the reserved `max_l1_data_fee`,
+/// will be deducted from the withdrawal amount.
 ///
 /// # Errors
 /// * `CreateTransactionError::InsufficientAmount` if the withdrawal amount does not cover the transaction fee.
@@ -651,9 +1001,13 @@ pub fn create_transaction(
     withdrawal_request: &EthWithdrawalRequest,
     nonce: TransactionNonce,
     transaction_price: TransactionPrice,
+    max_l1_data_fee: Wei,
     ethereum_network: EthereumNetwork,
 ) -> Result<Eip1559TransactionRequest, CreateTransactionError> {
-    let max_transaction_fee = transaction_price.max_transaction_fee();
+    let max_transaction_fee = transaction_price
+        .max_transaction_fee()
+        .checked_add(max_l1_data_fee)
+        .expect("BUG: max transaction fee always fits into U256");
     let tx_amount = match withdrawal_request
         .withdrawal_amount
         .checked_sub(max_transaction_fee)
@@ -680,6 +1034,47 @@ pub fn create_transaction(
     })
 }
 
+/// Creates an EIP-1559 transaction calling `transfer` on the ERC-20 contract
+/// for the given ERC-20 withdrawal request.
+/// The transaction fees, including the reserved `max_l1_data_fee`,
+/// are paid with the ckETH burned when the request was accepted.
+///
+/// # Errors
+/// * `CreateTransactionError::InsufficientTransactionFee` if the burned ckETH does not cover the transaction fee.
+pub fn create_erc20_transaction(
+    withdrawal_request: &Erc20WithdrawalRequest,
+    nonce: TransactionNonce,
+    transaction_price: TransactionPrice,
+    max_l1_data_fee: Wei,
+    ethereum_network: EthereumNetwork,
+) -> Result<Eip1559TransactionRequest, CreateTransactionError> {
+    let max_transaction_fee = transaction_price
+        .max_transaction_fee()
+        .checked_add(max_l1_data_fee)
+        .expect("BUG: max transaction fee always fits into U256");
+    if max_transaction_fee > withdrawal_request.max_transaction_fee {
+        return Err(CreateTransactionError::InsufficientTransactionFee {
+            ledger_burn_index: withdrawal_request.cketh_ledger_burn_index,
+            allowed_max_transaction_fee: withdrawal_request.max_transaction_fee,
+            actual_max_transaction_fee: max_transaction_fee,
+        });
+    }
+    Ok(Eip1559TransactionRequest {
+        chain_id: ethereum_network.chain_id(),
+        nonce,
+        max_priority_fee_per_gas: transaction_price.max_priority_fee_per_gas,
+        max_fee_per_gas: transaction_price.max_fee_per_gas,
+        gas_limit: transaction_price.gas_limit,
+        destination: withdrawal_request.erc20_contract_address,
+        amount: Wei::ZERO,
+        data: encode_erc20_transfer_data(
+            &withdrawal_request.destination,
+            withdrawal_request.withdrawal_amount,
+        ),
+        access_list: Default::default(),
+    })
+}
+
 /// Returns true if the two transactions are equal ignoring the transaction fee and amount.
 /// The following fields are ignored:
 /// * `max_fee_per_gas`
@@ -281,7 +281,7 @@ mod eth_transactions {
     mod record_created_transaction {
         use crate::address::Address;
         use crate::lifecycle::EthereumNetwork;
-        use crate::numeric::{LedgerBurnIndex, TransactionNonce};
+        use crate::numeric::{LedgerBurnIndex, TransactionNonce, Wei};
         use crate::state::transactions::tests::{
             create_and_record_transaction, expect_panic_with_message, transaction_price,
             withdrawal_request_with_index,
@@ -299,6 +299,7 @@ mod eth_transactions {
                 &withdrawal_request,
                 TransactionNonce::ZERO,
                 transaction_price(),
+                Wei::ZERO,
                 EthereumNetwork::Sepolia,
             )
             .unwrap();
@@ -321,6 +322,7 @@ mod eth_transactions {
                 &withdrawal_request,
                 TransactionNonce::ZERO,
                 transaction_price(),
+                Wei::ZERO,
                 EthereumNetwork::Sepolia,
             )
             .unwrap();
@@ -372,6 +374,7 @@ mod eth_transactions {
                     &withdrawal_request,
                     wrong_nonce,
                     transaction_price(),
+                    Wei::ZERO,
                     EthereumNetwork::Sepolia,
                 )
                 .unwrap();
@@ -537,8 +540,11 @@ mod eth_transactions {
         #[test]
         fn should_be_empty_when_no_sent_transactions() {
             let transactions = EthTransactions::new(TransactionNonce::ZERO);
-            let resubmitted_txs = transactions
-                .create_resubmit_transactions(TransactionCount::ZERO, transaction_price());
+            let resubmitted_txs = transactions.create_resubmit_transactions(
+                TransactionCount::ZERO,
+                transaction_price(),
+                Wei::ZERO,
+            );
 
             assert_eq!(resubmitted_txs, vec![]);
         }
@@ -566,6 +572,7 @@ mod eth_transactions {
                 let resubmitted_txs = transactions.create_resubmit_transactions(
                     TransactionCount::from(num_tx + 1),
                     higher_new_price.clone(),
+                    Wei::ZERO,
                 );
 
                 assert_eq!(resubmitted_txs, vec![]);
@@ -590,8 +597,11 @@ mod eth_transactions {
                     create_and_record_signed_transaction(&mut transactions, created_tx);
             }
 
-            let resubmitted_txs = transactions
-                .create_resubmit_transactions(TransactionCount::from(10_u8), initial_price.clone());
+            let resubmitted_txs = transactions.create_resubmit_transactions(
+                TransactionCount::from(10_u8),
+                initial_price.clone(),
+                Wei::ZERO,
+            );
 
             assert_eq!(resubmitted_txs, vec![]);
         }
@@ -665,6 +675,7 @@ mod eth_transactions {
                 let resubmitted_txs = transactions.create_resubmit_transactions(
                     TransactionCount::ZERO,
                     test.price_at_tx_resubmission.clone(),
+                    Wei::ZERO,
                 );
 
                 let expected_resubmitted_tx = Eip1559TransactionRequest {
@@ -709,8 +720,11 @@ mod eth_transactions {
                 ..initial_price
             };
 
-            let resubmitted_txs = transactions
-                .create_resubmit_transactions(TransactionCount::from(30_u8), higher_price.clone());
+            let resubmitted_txs = transactions.create_resubmit_transactions(
+                TransactionCount::from(30_u8),
+                higher_price.clone(),
+                Wei::ZERO,
+            );
             assert_eq!(resubmitted_txs.len(), 70);
             for (i, (withdrawal_id, resubmitted_tx)) in resubmitted_txs
                 .into_iter()
@@ -878,8 +892,11 @@ mod eth_transactions {
             let _signed_tx =
                 create_and_record_signed_transaction(&mut transactions, created_tx.clone());
 
-            let resubmitted_txs_1 = transactions
-                .create_resubmit_transactions(TransactionCount::ZERO, resubmit_price_1.clone());
+            let resubmitted_txs_1 = transactions.create_resubmit_transactions(
+                TransactionCount::ZERO,
+                resubmit_price_1.clone(),
+                Wei::ZERO,
+            );
             let resubmitted_tx1 = Eip1559TransactionRequest {
                 max_fee_per_gas: WeiPerGas::from(13_u8),
                 max_priority_fee_per_gas: WeiPerGas::from(24_u8),
@@ -908,8 +925,11 @@ mod eth_transactions {
                 max_fee_per_gas: initial_price.max_fee_per_gas.checked_mul(2_u8).unwrap(),
                 ..resubmit_price_1
             };
-            let resubmitted_txs_2 =
-                transactions.create_resubmit_transactions(TransactionCount::ZERO, resubmit_price_2);
+            let resubmitted_txs_2 = transactions.create_resubmit_transactions(
+                TransactionCount::ZERO,
+                resubmit_price_2,
+                Wei::ZERO,
+            );
             let resubmitted_tx2 = Eip1559TransactionRequest {
                 max_fee_per_gas: WeiPerGas::from(22_u8),
                 max_priority_fee_per_gas: WeiPerGas::from(24_u8),
@@ -1524,6 +1544,7 @@ mod create_transaction {
                 &withdrawal_request,
                 TransactionNonce::TWO,
                 transaction_price,
+                Wei::ZERO,
                 EthereumNetwork::Sepolia,
             );
 
@@ -1558,6 +1579,7 @@ mod create_transaction {
                 &withdrawal_request,
                 TransactionNonce::TWO,
                 transaction_price.clone(),
+                Wei::ZERO,
                 EthereumNetwork::Sepolia,
             );
 
@@ -1574,13 +1596,58 @@ mod create_transaction {
             }))
         }
     }
+
+    #[test]
+    fn should_deduct_l1_data_fee_reserve_from_transaction_amount() {
+        let transaction_price = transaction_price();
+        let max_l1_data_fee = Wei::new(100_000_000_000_000);
+        let max_transaction_fee = transaction_price
+            .max_transaction_fee()
+            .checked_add(max_l1_data_fee)
+            .unwrap();
+        let ledger_burn_index = LedgerBurnIndex::new(15);
+        let withdrawal_request = EthWithdrawalRequest {
+            withdrawal_amount: max_transaction_fee,
+            ..withdrawal_request_with_index(ledger_burn_index)
+        };
+        assert_eq!(
+            create_transaction(
+                &withdrawal_request,
+                TransactionNonce::TWO,
+                transaction_price.clone(),
+                max_l1_data_fee,
+                EthereumNetwork::Optimism,
+            )
+            .map(|tx| tx.amount),
+            Ok(Wei::ZERO)
+        );
+
+        let insufficient_request = EthWithdrawalRequest {
+            withdrawal_amount: max_transaction_fee.checked_sub(Wei::ONE).unwrap(),
+            ..withdrawal_request
+        };
+        assert_eq!(
+            create_transaction(
+                &insufficient_request,
+                TransactionNonce::TWO,
+                transaction_price,
+                max_l1_data_fee,
+                EthereumNetwork::Optimism,
+            ),
+            Err(CreateTransactionError::InsufficientAmount {
+                ledger_burn_index,
+                withdrawal_amount: insufficient_request.withdrawal_amount,
+                max_transaction_fee,
+            })
+        );
+    }
 }
 
 mod withdrawal_flow {
     use super::arbitrary::{
         arb_checked_amount_of, arb_non_overflowing_transaction_price, arb_withdrawal_request,
     };
-    use crate::numeric::TransactionNonce;
+    use crate::numeric::{TransactionNonce, Wei};
     use crate::state::transactions::tests::sign_transaction;
     use crate::state::transactions::{create_transaction, EthTransactions, EthereumNetwork};
     use proptest::proptest;
@@ -1597,7 +1664,7 @@ mod withdrawal_flow {
         });
 
         proptest!(|(transaction_price in arb_non_overflowing_transaction_price(), transaction_count in arb_checked_amount_of())| {
-            let resubmit_txs = wrapped_txs.borrow().create_resubmit_transactions(transaction_count, transaction_price.clone());
+            let resubmit_txs = wrapped_txs.borrow().create_resubmit_transactions(transaction_count, transaction_price.clone(), Wei::ZERO);
             for (_withdrawal_id, resubmit_tx) in resubmit_txs.into_iter().flatten() {
                 wrapped_txs.borrow_mut().record_resubmit_transaction(resubmit_tx);
             }
@@ -1609,6 +1676,7 @@ mod withdrawal_flow {
                     &request,
                     nonce,
                     transaction_price.clone(),
+                    Wei::ZERO,
                     EthereumNetwork::Sepolia,
                 ){
                     wrapped_txs.borrow_mut().record_created_transaction(request.ledger_burn_index, created_tx);
@@ -1851,6 +1919,7 @@ fn create_and_record_transaction(
         &withdrawal_request,
         transactions.next_transaction_nonce(),
         transaction_price,
+        Wei::ZERO,
         EthereumNetwork::Sepolia,
     )
     .expect("failed to create transaction");
@@ -1903,6 +1972,7 @@ fn transaction_receipt(
         gas_used: signed_tx.transaction().gas_limit,
         status,
         transaction_hash: signed_tx.hash(),
+        l1_fee: None,
     }
 }
 
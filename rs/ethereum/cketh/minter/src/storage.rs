@@ -1,15 +1,17 @@
+use crate::eth_rpc_client::providers::ApiKeys;
 use crate::state::event::{Event, EventType};
 use ic_stable_structures::{
     log::Log as StableLog,
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
     storable::Storable,
-    DefaultMemoryImpl,
+    DefaultMemoryImpl, StableCell,
 };
 use std::borrow::Cow;
 use std::cell::RefCell;
 
 const LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(0);
 const LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(1);
+const RPC_API_KEYS_MEMORY_ID: MemoryId = MemoryId::new(2);
 
 type VMem = VirtualMemory<DefaultMemoryImpl>;
 type EventLog = StableLog<Event, VMem, VMem>;
@@ -27,6 +29,19 @@ impl Storable for Event {
     }
 }
 
+impl Storable for ApiKeys {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut buf = vec![];
+        minicbor::encode(self, &mut buf).expect("API keys encoding should always succeed");
+        Cow::Owned(buf)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        minicbor::decode(bytes.as_ref())
+            .unwrap_or_else(|e| panic!("failed to decode API keys: {e}"))
+    }
+}
+
 thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(
         MemoryManager::init(DefaultMemoryImpl::default())
@@ -42,6 +57,18 @@ thread_local! {
                   ).expect("failed to initialize stable log")
               )
         );
+
+    /// API keys of the JSON-RPC providers.
+    /// They are stored outside of the event log since events are public.
+    static RPC_API_KEYS: RefCell<StableCell<ApiKeys, VMem>> = MEMORY_MANAGER
+        .with(|m|
+              RefCell::new(
+                  StableCell::init(
+                      m.borrow().get(RPC_API_KEYS_MEMORY_ID),
+                      ApiKeys::default()
+                  ).expect("failed to initialize API keys cell")
+              )
+        );
 }
 
 /// Appends the event to the event log.
@@ -67,3 +94,15 @@ where
 {
     EVENTS.with(|events| f(Box::new(events.borrow().iter())))
 }
+
+/// Returns the API keys of the JSON-RPC providers.
+pub fn rpc_api_keys() -> ApiKeys {
+    RPC_API_KEYS.with(|cell| cell.borrow().get().clone())
+}
+
+/// Replaces the API keys of the JSON-RPC providers.
+pub fn set_rpc_api_keys(api_keys: ApiKeys) {
+    RPC_API_KEYS
+        .with(|cell| cell.borrow_mut().set(api_keys))
+        .expect("storing API keys should succeed");
+}
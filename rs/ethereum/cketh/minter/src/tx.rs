@@ -2,8 +2,10 @@
 mod tests;
 
 use crate::address::Address;
+use crate::chain_profile::FeeModel;
 use crate::eth_rpc::{FeeHistory, Hash};
 use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
+use crate::lifecycle::EthereumNetwork;
 use crate::numeric::{BlockNumber, GasAmount, TransactionNonce, Wei, WeiPerGas};
 use crate::state::{lazy_call_ecdsa_public_key, read_state};
 use ethnum::u256;
@@ -433,21 +435,33 @@ impl TransactionPrice {
     }
 }
 
-pub fn estimate_transaction_price(fee_history: &FeeHistory) -> TransactionPrice {
+pub fn estimate_transaction_price(
+    fee_history: &FeeHistory,
+    fee_model: FeeModel,
+    ethereum_network: EthereumNetwork,
+) -> TransactionPrice {
     // average value between the `minSuggestedMaxPriorityFeePerGas`
     // used by Metamask, see
     // https://github.com/MetaMask/core/blob/f5a4f52e17f407c6411e4ef9bd6685aab184b91d/packages/gas-fee-controller/src/fetchGasEstimatesViaEthFeeHistory/calculateGasFeeEstimatesForPriorityLevels.ts#L14
     const MIN_MAX_PRIORITY_FEE_PER_GAS: WeiPerGas = WeiPerGas::new(1_500_000_000); //1.5 gwei
-    const TRANSACTION_GAS_LIMIT: GasAmount = GasAmount::new(21_000);
     let base_fee_of_next_finalized_block = *fee_history
         .base_fee_per_gas
         .last()
         .expect("base_fee_per_gas should not be empty to be able to evaluate transaction price");
-    let max_priority_fee_per_gas = {
-        let mut rewards: Vec<&WeiPerGas> = fee_history.reward.iter().flatten().collect();
-        let historic_max_priority_fee_per_gas =
-            **median(&mut rewards).expect("should be non-empty with rewards of the last 5 blocks");
-        historic_max_priority_fee_per_gas.max(MIN_MAX_PRIORITY_FEE_PER_GAS)
+    let max_priority_fee_per_gas = match fee_model {
+        FeeModel::Eip1559 => {
+            let mut rewards: Vec<&WeiPerGas> = fee_history.reward.iter().flatten().collect();
+            let historic_max_priority_fee_per_gas = **median(&mut rewards)
+                .expect("should be non-empty with rewards of the last 5 blocks");
+            historic_max_priority_fee_per_gas.max(MIN_MAX_PRIORITY_FEE_PER_GAS)
+        }
+        FeeModel::Eip1559FixedPriorityFee {
+            max_priority_fee_per_gas,
+        }
+        | FeeModel::OpStack {
+            max_priority_fee_per_gas,
+            ..
+        } => max_priority_fee_per_gas,
     };
     let max_fee_per_gas = base_fee_of_next_finalized_block
         .checked_mul(2_u8)
@@ -455,12 +469,23 @@ pub fn estimate_transaction_price(fee_history: &FeeHistory) -> TransactionPrice
         .checked_add(max_priority_fee_per_gas)
         .expect("ERROR: overflow during transaction price estimation");
     TransactionPrice {
-        gas_limit: TRANSACTION_GAS_LIMIT,
+        gas_limit: ethereum_network.eth_transfer_gas_limit(),
         max_fee_per_gas,
         max_priority_fee_per_gas,
     }
 }
 
+pub fn estimate_erc20_transaction_price(
+    fee_history: &FeeHistory,
+    fee_model: FeeModel,
+    ethereum_network: EthereumNetwork,
+) -> TransactionPrice {
+    TransactionPrice {
+        gas_limit: ethereum_network.erc20_transfer_gas_limit(),
+        ..estimate_transaction_price(fee_history, fee_model, ethereum_network)
+    }
+}
+
 fn median<T: Ord>(values: &mut [T]) -> Option<&T> {
     if values.is_empty() {
         return None;
@@ -1,3 +1,4 @@
+use crate::chain_profile::Finality;
 use crate::eth_rpc::JsonRpcResult;
 use crate::eth_rpc::{
     BlockSpec, BlockTag, FeeHistory, FeeHistoryParams, Quantity, SendRawTransactionResult,
@@ -11,7 +12,8 @@ use crate::logs::{DEBUG, INFO};
 use crate::numeric::{LedgerBurnIndex, LedgerMintIndex, TransactionCount};
 use crate::state::audit::{process_event, EventType};
 use crate::state::transactions::{
-    create_transaction, CreateTransactionError, Reimbursed, ReimbursementRequest,
+    create_erc20_transaction, create_transaction, CreateTransactionError, Erc20Reimbursed,
+    Erc20ReimbursementRequest, Reimbursed, ReimbursementRequest,
 };
 use crate::state::{mutate_state, read_state, State, TaskType};
 use crate::tx::{estimate_transaction_price, TransactionPrice};
@@ -39,7 +41,9 @@ pub async fn process_reimbursement() {
 
     let reimbursement_requests: Vec<ReimbursementRequest> =
         read_state(|s| s.eth_transactions.get_reimbursement_requests());
-    if reimbursement_requests.is_empty() {
+    let erc20_reimbursement_requests: Vec<Erc20ReimbursementRequest> =
+        read_state(|s| s.eth_transactions.get_erc20_reimbursement_requests());
+    if reimbursement_requests.is_empty() && erc20_reimbursement_requests.is_empty() {
         return;
     }
 
@@ -94,6 +98,62 @@ pub async fn process_reimbursement() {
             )
         });
     }
+    for reimbursement_request in erc20_reimbursement_requests {
+        let ledger_canister_id = reimbursement_request.ledger_id;
+        let client = ICRC1Client {
+            runtime: CdkRuntime,
+            ledger_canister_id,
+        };
+        let args = TransferArg {
+            from_subaccount: None,
+            to: Account {
+                owner: reimbursement_request.to,
+                subaccount: reimbursement_request
+                    .to_subaccount
+                    .as_ref()
+                    .map(|subaccount| subaccount.0),
+            },
+            fee: None,
+            created_at_time: None,
+            memo: None,
+            amount: Nat::from(reimbursement_request.reimbursed_amount),
+        };
+        let block_index = match client.transfer(args).await {
+            Ok(Ok(block_index)) => block_index
+                .0
+                .to_u64()
+                .expect("block index should fit into u64"),
+            Ok(Err(err)) => {
+                log!(
+                    INFO,
+                    "[process_reimbursement] Failed to mint ckERC20 on ledger {ledger_canister_id}: {err}"
+                );
+                error_count += 1;
+                continue;
+            }
+            Err(err) => {
+                log!(
+                    INFO,
+                    "[process_reimbursement] Failed to send a message to the ledger ({ledger_canister_id}): {err:?}"
+                );
+                error_count += 1;
+                continue;
+            }
+        };
+        mutate_state(|s| {
+            process_event(
+                s,
+                EventType::ReimbursedErc20Withdrawal(Erc20Reimbursed {
+                    withdrawal_id: reimbursement_request.withdrawal_id,
+                    burn_in_block: reimbursement_request.burn_in_block,
+                    reimbursed_in_block: LedgerMintIndex::new(block_index),
+                    ledger_id: reimbursement_request.ledger_id,
+                    reimbursed_amount: reimbursement_request.reimbursed_amount,
+                    transaction_hash: reimbursement_request.transaction_hash,
+                }),
+            )
+        });
+    }
     if error_count > 0 {
         log!(
             INFO,
@@ -128,7 +188,11 @@ pub async fn process_retrieve_eth_requests() {
             return;
         }
     };
-    let transaction_price = estimate_transaction_price(&fee_history);
+    let transaction_price = estimate_transaction_price(
+        &fee_history,
+        read_state(State::fee_model),
+        read_state(State::ethereum_network),
+    );
     let max_transaction_fee = transaction_price.max_transaction_fee();
     log!(
         INFO,
@@ -180,8 +244,11 @@ async fn resubmit_transactions_batch(
         }
     };
     let transactions_to_resubmit = read_state(|s| {
-        s.eth_transactions
-            .create_resubmit_transactions(latest_transaction_count, transaction_price.clone())
+        s.eth_transactions.create_resubmit_transactions(
+            latest_transaction_count,
+            transaction_price.clone(),
+            s.fee_model.max_l1_data_fee(),
+        )
     });
     for result in transactions_to_resubmit {
         match result {
@@ -208,6 +275,7 @@ async fn resubmit_transactions_batch(
 }
 
 fn create_transactions_batch(transaction_price: TransactionPrice) {
+    let max_l1_data_fee = read_state(|s| s.fee_model.max_l1_data_fee());
     for request in read_state(|s| {
         s.eth_transactions
             .withdrawal_requests_batch(WITHDRAWAL_REQUESTS_BATCH_SIZE)
@@ -215,7 +283,13 @@ fn create_transactions_batch(transaction_price: TransactionPrice) {
         log!(DEBUG, "[create_transactions_batch]: processing {request:?}",);
         let ethereum_network = read_state(State::ethereum_network);
         let nonce = read_state(|s| s.eth_transactions.next_transaction_nonce());
-        match create_transaction(&request, nonce, transaction_price.clone(), ethereum_network) {
+        match create_transaction(
+            &request,
+            nonce,
+            transaction_price.clone(),
+            max_l1_data_fee,
+            ethereum_network,
+        ) {
             Ok(transaction) => {
                 log!(
                     DEBUG,
@@ -245,6 +319,65 @@ fn create_transactions_batch(transaction_price: TransactionPrice) {
                 );
                 mutate_state(|s| s.eth_transactions.reschedule_withdrawal_request(request));
             }
+            Err(e @ CreateTransactionError::InsufficientTransactionFee { .. }) => {
+                panic!("BUG: unexpected error {e:?} for ETH withdrawal request {request:?}")
+            }
+        };
+    }
+
+    let erc20_transaction_price = TransactionPrice {
+        gas_limit: read_state(State::ethereum_network).erc20_transfer_gas_limit(),
+        ..transaction_price
+    };
+    for request in read_state(|s| {
+        s.eth_transactions
+            .erc20_withdrawal_requests_batch(WITHDRAWAL_REQUESTS_BATCH_SIZE)
+    }) {
+        log!(DEBUG, "[create_transactions_batch]: processing {request:?}",);
+        let ethereum_network = read_state(State::ethereum_network);
+        let nonce = read_state(|s| s.eth_transactions.next_transaction_nonce());
+        match create_erc20_transaction(
+            &request,
+            nonce,
+            erc20_transaction_price.clone(),
+            max_l1_data_fee,
+            ethereum_network,
+        ) {
+            Ok(transaction) => {
+                log!(
+                    DEBUG,
+                    "[create_transactions_batch]: created transaction {transaction:?}",
+                );
+
+                mutate_state(|s| {
+                    process_event(
+                        s,
+                        EventType::CreatedTransaction {
+                            withdrawal_id: request.cketh_ledger_burn_index,
+                            transaction,
+                        },
+                    );
+                });
+            }
+            Err(CreateTransactionError::InsufficientTransactionFee {
+                ledger_burn_index,
+                allowed_max_transaction_fee,
+                actual_max_transaction_fee,
+            }) => {
+                log!(
+                    INFO,
+                    "[create_transactions_batch]: ERC-20 withdrawal request with burn index {ledger_burn_index} has insufficient
+                transaction fee {allowed_max_transaction_fee:?} to cover the current transaction fee: {actual_max_transaction_fee:?}.
+                Request moved back to end of queue."
+                );
+                mutate_state(|s| {
+                    s.eth_transactions
+                        .reschedule_erc20_withdrawal_request(request)
+                });
+            }
+            Err(e @ CreateTransactionError::InsufficientAmount { .. }) => {
+                panic!("BUG: unexpected error {e:?} for ERC-20 withdrawal request {request:?}")
+            }
         };
     }
 }
@@ -404,10 +537,17 @@ async fn finalize_transactions_batch() {
 
 async fn finalized_transaction_count() -> Result<TransactionCount, MultiCallError<TransactionCount>>
 {
+    // Chains relying on a confirmation depth may not support the `finalized` block tag.
+    let block = read_state(|s| match (s.finality(), s.last_observed_block_number) {
+        (Finality::ConfirmationDepth(_), Some(last_observed_block_number)) => {
+            BlockSpec::Number(last_observed_block_number)
+        }
+        _ => BlockSpec::Tag(BlockTag::Finalized),
+    });
     read_state(EthRpcClient::from_state)
         .eth_get_transaction_count(GetTransactionCountParams {
             address: crate::state::minter_address().await,
-            block: BlockSpec::Tag(BlockTag::Finalized),
+            block,
         })
         .await
         .reduce_with_equality()
@@ -367,6 +367,9 @@ fn install_minter(env: &StateMachine, ledger_id: CanisterId, minter_id: Canister
         ethereum_contract_address: Some("0x907b6EFc1a398fD88A8161b3cA02eEc8Eaf72ca1".to_string()),
         minimum_withdrawal_amount: 1.into(),
         last_scraped_block_number: 3_956_206.into(),
+        rpc_providers: None,
+        confirmation_depth: None,
+        fee_model: None,
     };
     let minter_arg = MinterArg::InitArg(args);
     env.install_existing_canister(minter_id, minter_wasm(), Encode!(&minter_arg).unwrap())
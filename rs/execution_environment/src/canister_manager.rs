@@ -21,6 +21,7 @@ use ic_ic00_types::{
 };
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, HypervisorError, IngressHistoryWriter, SubnetAvailableMemory,
+    SubnetCapacityLimits,
 };
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
@@ -104,6 +105,7 @@ pub(crate) enum StopCanisterResult {
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub(crate) struct CanisterMgrConfig {
     pub(crate) subnet_memory_capacity: NumBytes,
+    pub(crate) subnet_memory_reservation: NumBytes,
     pub(crate) default_provisional_cycles_balance: Cycles,
     pub(crate) default_freeze_threshold: NumSeconds,
     pub(crate) compute_capacity: u64,
@@ -120,6 +122,7 @@ impl CanisterMgrConfig {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         subnet_memory_capacity: NumBytes,
+        subnet_memory_reservation: NumBytes,
         default_provisional_cycles_balance: Cycles,
         default_freeze_threshold: NumSeconds,
         own_subnet_id: SubnetId,
@@ -134,6 +137,7 @@ impl CanisterMgrConfig {
     ) -> Self {
         Self {
             subnet_memory_capacity,
+            subnet_memory_reservation,
             default_provisional_cycles_balance,
             default_freeze_threshold,
             own_subnet_id,
@@ -380,7 +384,10 @@ impl CanisterManager {
             | Ok(Ic00Method::BitcoinGetUtxos)
             | Ok(Ic00Method::BitcoinSendTransaction)
             | Ok(Ic00Method::BitcoinSendTransactionInternal)
-            | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles) => Err(UserError::new(
+            | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles)
+            // Subnet and node information is meant for canisters only.
+            | Ok(Ic00Method::SubnetInfo)
+            | Ok(Ic00Method::NodeMetricsHistory) => Err(UserError::new(
                 ErrorCode::CanisterRejectedMessage,
                 format!("Only canisters can call ic00 method {}", method_name),
             )),
@@ -465,17 +472,18 @@ impl CanisterManager {
         subnet_memory_saturation: &ResourceSaturation,
         canister_cycles_balance: Cycles,
         subnet_size: usize,
+        capacity_limits: &SubnetCapacityLimits,
     ) -> Result<ValidatedCanisterSettings, CanisterManagerError> {
         validate_canister_settings(
             settings,
             NumBytes::new(0),
             NumBytes::new(0),
             MemoryAllocation::BestEffort,
-            subnet_available_memory,
+            &self.admission_available_memory(subnet_available_memory, capacity_limits),
             subnet_memory_saturation,
             ComputeAllocation::zero(),
             subnet_compute_allocation_usage,
-            self.config.compute_capacity,
+            self.compute_capacity(capacity_limits),
             self.config.max_controllers,
             self.config.default_freeze_threshold,
             canister_cycles_balance,
@@ -552,6 +560,7 @@ impl CanisterManager {
         round_limits: &mut RoundLimits,
         subnet_memory_saturation: ResourceSaturation,
         subnet_size: usize,
+        capacity_limits: &SubnetCapacityLimits,
     ) -> Result<(), CanisterManagerError> {
         let sender = origin.origin();
 
@@ -562,11 +571,12 @@ impl CanisterManager {
             canister.memory_usage(),
             canister.message_memory_usage(),
             canister.memory_allocation(),
-            &round_limits.subnet_available_memory,
+            &self
+                .admission_available_memory(&round_limits.subnet_available_memory, capacity_limits),
             &subnet_memory_saturation,
             canister.compute_allocation(),
             round_limits.compute_allocation_used,
-            self.config.compute_capacity,
+            self.compute_capacity(capacity_limits),
             self.config.max_controllers,
             canister.system_state.freeze_threshold,
             canister.system_state.balance(),
@@ -634,7 +644,7 @@ impl CanisterManager {
         sender_subnet_id: SubnetId,
         cycles: Cycles,
         mut settings: CanisterSettings,
-        max_number_of_canisters: u64,
+        capacity_limits: &SubnetCapacityLimits,
         state: &mut ReplicatedState,
         subnet_size: usize,
         round_limits: &mut RoundLimits,
@@ -680,6 +690,7 @@ impl CanisterManager {
             &subnet_memory_saturation,
             cycles - fee,
             subnet_size,
+            capacity_limits,
         ) {
             Err(err) => (Err(err), cycles),
             Ok(validate_settings) => {
@@ -688,7 +699,7 @@ impl CanisterManager {
                     cycles,
                     fee,
                     validate_settings,
-                    max_number_of_canisters,
+                    capacity_limits,
                     state,
                     round_limits,
                     None,
@@ -1209,7 +1220,7 @@ impl CanisterManager {
         specified_id: Option<PrincipalId>,
         state: &mut ReplicatedState,
         provisional_whitelist: &ProvisionalWhitelist,
-        max_number_of_canisters: u64,
+        capacity_limits: &SubnetCapacityLimits,
         round_limits: &mut RoundLimits,
         subnet_memory_saturation: ResourceSaturation,
         subnet_size: usize,
@@ -1240,6 +1251,7 @@ impl CanisterManager {
             &subnet_memory_saturation,
             cycles,
             subnet_size,
+            capacity_limits,
         ) {
             Err(err) => Err(err),
             Ok(validated_settings) => self.create_canister_helper(
@@ -1247,7 +1259,7 @@ impl CanisterManager {
                 cycles,
                 Cycles::new(0),
                 validated_settings,
-                max_number_of_canisters,
+                capacity_limits,
                 state,
                 round_limits,
                 specified_id,
@@ -1256,6 +1268,56 @@ impl CanisterManager {
         }
     }
 
+    /// Returns the execution memory used or reserved by the canisters on the
+    /// subnet, derived from the memory that is still available.
+    fn subnet_memory_taken(&self, subnet_available_memory: &SubnetAvailableMemory) -> NumBytes {
+        let available = subnet_available_memory
+            .get_execution_memory()
+            .saturating_mul(subnet_available_memory.get_scaling_factor())
+            .max(0) as u64;
+        NumBytes::new(
+            self.config
+                .subnet_memory_capacity
+                .get()
+                .saturating_sub(self.config.subnet_memory_reservation.get())
+                .saturating_sub(available),
+        )
+    }
+
+    /// Returns the memory available for new canisters and memory allocations,
+    /// i.e. the subnet available memory capped by the total memory limit of
+    /// the subnet record.
+    fn admission_available_memory(
+        &self,
+        subnet_available_memory: &SubnetAvailableMemory,
+        capacity_limits: &SubnetCapacityLimits,
+    ) -> SubnetAvailableMemory {
+        if capacity_limits.max_total_memory_bytes == 0 {
+            return *subnet_available_memory;
+        }
+        let available_below_limit = capacity_limits
+            .max_total_memory_bytes
+            .saturating_sub(self.subnet_memory_taken(subnet_available_memory).get())
+            .min(i64::MAX as u64) as i64;
+        SubnetAvailableMemory::new(
+            subnet_available_memory
+                .get_execution_memory()
+                .min(available_below_limit),
+            subnet_available_memory.get_message_memory(),
+            subnet_available_memory.get_wasm_custom_sections_memory(),
+        )
+    }
+
+    /// Returns the compute capacity (in percent) that canisters may allocate,
+    /// i.e. the configured capacity capped by the compute allocation limit of
+    /// the subnet record.
+    fn compute_capacity(&self, capacity_limits: &SubnetCapacityLimits) -> u64 {
+        match capacity_limits.max_reserved_compute_allocation {
+            0 => self.config.compute_capacity,
+            limit => self.config.compute_capacity.min(limit),
+        }
+    }
+
     /// Validates specified ID is available for use.
     ///
     /// It must be used in in the context of provisional create canister flow when a specified ID is provided.
@@ -1296,7 +1358,7 @@ impl CanisterManager {
         cycles: Cycles,
         creation_fee: Cycles,
         settings: ValidatedCanisterSettings,
-        max_number_of_canisters: u64,
+        capacity_limits: &SubnetCapacityLimits,
         state: &mut ReplicatedState,
         round_limits: &mut RoundLimits,
         specified_id: Option<PrincipalId>,
@@ -1305,13 +1367,23 @@ impl CanisterManager {
         let sender = origin.origin();
 
         // A value of 0 is equivalent to setting no limit.
-        // See documentation of `SubnetRecord` for the semantics of `max_number_of_canisters`.
+        // See documentation of `SubnetRecord` for the semantics of the limits.
+        let max_number_of_canisters = capacity_limits.max_number_of_canisters;
         if max_number_of_canisters > 0 && state.num_canisters() as u64 >= max_number_of_canisters {
             return Err(CanisterManagerError::MaxNumberOfCanistersReached {
                 subnet_id: self.config.own_subnet_id,
                 max_number_of_canisters,
             });
         }
+        let max_total_memory = NumBytes::new(capacity_limits.max_total_memory_bytes);
+        if max_total_memory.get() > 0
+            && self.subnet_memory_taken(&round_limits.subnet_available_memory) >= max_total_memory
+        {
+            return Err(CanisterManagerError::MaxTotalMemoryReached {
+                subnet_id: self.config.own_subnet_id,
+                max_total_memory,
+            });
+        }
 
         let new_canister_id = match specified_id {
             Some(spec_id) => self.validate_specified_id(state, spec_id)?,
@@ -1685,6 +1757,10 @@ pub(crate) enum CanisterManagerError {
         subnet_id: SubnetId,
         max_number_of_canisters: u64,
     },
+    MaxTotalMemoryReached {
+        subnet_id: SubnetId,
+        max_total_memory: NumBytes,
+    },
     CanisterNotHostedBySubnet {
         message: String,
     },
@@ -1882,6 +1958,12 @@ impl From<CanisterManagerError> for UserError {
                     format!("Subnet {} has reached the allowed canister limit of {} canisters. Retry creating the canister.", subnet_id, max_number_of_canisters),
                 )
             }
+            MaxTotalMemoryReached { subnet_id, max_total_memory } => {
+                Self::new(
+                    ErrorCode::SubnetOversubscribed,
+                    format!("Subnet {} has reached the allowed memory limit of {} for canisters. Retry creating the canister on another subnet.", subnet_id, max_total_memory.display()),
+                )
+            }
             CanisterNotHostedBySubnet {message} => {
                 Self::new(
                     ErrorCode::CanisterNotHostedBySubnet,
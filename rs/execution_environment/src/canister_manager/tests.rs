@@ -27,7 +27,9 @@ use ic_ic00_types::{
     InstallCodeArgsV2, Method, Payload, SkipPreUpgrade, StoredChunksArgs, StoredChunksReply,
     UpdateSettingsArgs, UploadChunkArgs, UploadChunkReply,
 };
-use ic_interfaces::execution_environment::{ExecutionMode, HypervisorError, SubnetAvailableMemory};
+use ic_interfaces::execution_environment::{
+    ExecutionMode, HypervisorError, SubnetAvailableMemory, SubnetCapacityLimits,
+};
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
@@ -83,7 +85,11 @@ const DEFAULT_PROVISIONAL_BALANCE: Cycles = Cycles::new(100_000_000_000_000);
 const MEMORY_CAPACITY: NumBytes = NumBytes::new(8 * 1024 * 1024 * 1024); // 8GiB
 const MAX_CONTROLLERS: usize = 10;
 const WASM_PAGE_SIZE_IN_BYTES: u64 = 64 * 1024; // 64KiB
-const MAX_NUMBER_OF_CANISTERS: u64 = 0;
+const NO_CAPACITY_LIMITS: SubnetCapacityLimits = SubnetCapacityLimits {
+    max_number_of_canisters: 0,
+    max_total_memory_bytes: 0,
+    max_reserved_compute_allocation: 0,
+};
 // The simplest valid WASM binary: "(module)"
 const MINIMAL_WASM: [u8; 8] = [
     0, 97, 115, 109, // \0ASM - magic
@@ -270,6 +276,7 @@ fn canister_manager_config(
 ) -> CanisterMgrConfig {
     CanisterMgrConfig::new(
         MEMORY_CAPACITY,
+        NumBytes::new(0),
         DEFAULT_PROVISIONAL_BALANCE,
         NumSeconds::from(100_000),
         subnet_id,
@@ -400,7 +407,7 @@ fn install_canister_makes_subnet_oversubscribed() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -418,7 +425,7 @@ fn install_canister_makes_subnet_oversubscribed() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -436,7 +443,7 @@ fn install_canister_makes_subnet_oversubscribed() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -548,7 +555,7 @@ fn upgrade_canister_with_no_wasm_fails() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -603,7 +610,7 @@ fn can_update_compute_allocation_during_upgrade() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -678,7 +685,7 @@ fn upgrading_canister_makes_subnet_oversubscribed() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -696,7 +703,7 @@ fn upgrading_canister_makes_subnet_oversubscribed() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -714,7 +721,7 @@ fn upgrading_canister_makes_subnet_oversubscribed() {
                     freezing_threshold: Some(1.into()),
                     ..CanisterSettings::default()
                 },
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -907,7 +914,7 @@ fn can_update_memory_allocation_during_upgrade() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1043,7 +1050,7 @@ fn can_create_canister() {
                     sender_subnet_id,
                     *INITIAL_CYCLES,
                     CanisterSettings::default(),
-                    MAX_NUMBER_OF_CANISTERS,
+                    &NO_CAPACITY_LIMITS,
                     &mut state,
                     SMALL_APP_SUBNET_MAX_SIZE,
                     &mut round_limits,
@@ -1061,7 +1068,7 @@ fn can_create_canister() {
                     sender_subnet_id,
                     *INITIAL_CYCLES,
                     CanisterSettings::default(),
-                    MAX_NUMBER_OF_CANISTERS,
+                    &NO_CAPACITY_LIMITS,
                     &mut state,
                     SMALL_APP_SUBNET_MAX_SIZE,
                     &mut round_limits,
@@ -1094,7 +1101,7 @@ fn create_canister_fails_if_not_enough_cycles_are_sent_with_the_request() {
                 sender_subnet_id,
                 Cycles::new(100),
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1133,7 +1140,7 @@ fn can_create_canister_with_extra_cycles() {
                     sender_subnet_id,
                     Cycles::from(cycles),
                     CanisterSettings::default(),
-                    MAX_NUMBER_OF_CANISTERS,
+                    &NO_CAPACITY_LIMITS,
                     &mut state,
                     SMALL_APP_SUBNET_MAX_SIZE,
                     &mut round_limits,
@@ -1164,7 +1171,7 @@ fn cannot_install_non_empty_canister() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1230,7 +1237,7 @@ fn install_code_with_wrong_controller_fails() {
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1297,7 +1304,7 @@ fn create_canister_sets_correct_allocations() {
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 settings,
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1327,7 +1334,7 @@ fn create_canister_updates_consumed_cycles_metric_correctly() {
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1381,7 +1388,7 @@ fn provisional_create_canister_has_no_creation_fee() {
                 None,
                 &mut state,
                 &ProvisionalWhitelist::All,
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
@@ -1425,7 +1432,7 @@ fn reinstall_on_empty_canister_succeeds() {
                 subnet_test_id(1),
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1534,7 +1541,7 @@ fn install_puts_canister_back_after_invalid_wasm() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1594,7 +1601,7 @@ fn reinstall_clears_stable_memory() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1689,7 +1696,7 @@ fn stop_a_running_canister() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1826,7 +1833,7 @@ fn stop_a_canister_with_incorrect_controller() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1903,7 +1910,7 @@ fn start_a_canister_with_incorrect_controller() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -1943,7 +1950,7 @@ fn starting_an_already_running_canister_keeps_it_running() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2053,7 +2060,7 @@ fn get_canister_status_with_incorrect_controller() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2093,7 +2100,7 @@ fn get_canister_status_of_running_canister() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2128,7 +2135,7 @@ fn get_canister_status_of_self() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2208,6 +2215,7 @@ fn set_controller_with_incorrect_controller() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             ),
             Err(CanisterManagerError::CanisterInvalidController {
                 canister_id,
@@ -2250,6 +2258,7 @@ fn set_controller_with_correct_controller() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             )
             .is_ok());
 
@@ -2450,7 +2459,7 @@ fn install_canister_with_query_allocation() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2523,7 +2532,7 @@ fn create_canister_with_cycles_sender_in_whitelist() {
             None,
             &mut state,
             &ProvisionalWhitelist::Set(btreeset! { canister_test_id(1).get() }),
-            MAX_NUMBER_OF_CANISTERS,
+            &NO_CAPACITY_LIMITS,
             &mut round_limits,
             ResourceSaturation::default(),
             SMALL_APP_SUBNET_MAX_SIZE,
@@ -2561,7 +2570,7 @@ fn create_canister_with_specified_id(
         Some(specified_id),
         &mut state,
         &ProvisionalWhitelist::Set(btreeset! { canister_test_id(1).get() }),
-        MAX_NUMBER_OF_CANISTERS,
+        &NO_CAPACITY_LIMITS,
         &mut round_limits,
         ResourceSaturation::default(),
         SMALL_APP_SUBNET_MAX_SIZE,
@@ -2693,7 +2702,7 @@ fn installing_a_canister_with_not_enough_memory_allocation_fails() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2797,7 +2806,7 @@ fn upgrading_canister_with_not_enough_memory_allocation_fails() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -2934,7 +2943,7 @@ fn installing_a_canister_with_not_enough_cycles_fails() {
                 cycles_account_manager.canister_creation_fee(SMALL_APP_SUBNET_MAX_SIZE)
                     + Cycles::new(100),
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -3047,7 +3056,7 @@ fn failed_upgrade_hooks_consume_instructions() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -3190,7 +3199,7 @@ fn failed_install_hooks_consume_instructions() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -3276,7 +3285,7 @@ fn install_code_respects_instruction_limit() {
             subnet_id,
             *INITIAL_CYCLES,
             CanisterSettings::default(),
-            MAX_NUMBER_OF_CANISTERS,
+            &NO_CAPACITY_LIMITS,
             &mut state,
             SMALL_APP_SUBNET_MAX_SIZE,
             &mut round_limits,
@@ -3627,7 +3636,7 @@ fn lower_memory_allocation_than_usage_fails() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -3669,6 +3678,7 @@ fn lower_memory_allocation_than_usage_fails() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             ),
             Err(CanisterManagerError::NotEnoughMemoryAllocationGiven { .. })
         );
@@ -3695,7 +3705,7 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -3745,6 +3755,7 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             )
             .unwrap();
 
@@ -3797,7 +3808,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -3876,6 +3887,7 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             )
             .unwrap();
 
@@ -3987,7 +3999,7 @@ fn test_install_when_setting_memory_allocation_to_zero() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -4013,6 +4025,7 @@ fn test_install_when_setting_memory_allocation_to_zero() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             )
             .unwrap();
 
@@ -4057,7 +4070,7 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
                 subnet_id,
                 *INITIAL_CYCLES,
                 settings,
-                MAX_NUMBER_OF_CANISTERS,
+                &NO_CAPACITY_LIMITS,
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -4100,6 +4113,7 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
                 &mut round_limits,
                 ResourceSaturation::default(),
                 SMALL_APP_SUBNET_MAX_SIZE,
+                &NO_CAPACITY_LIMITS,
             )
             .unwrap();
 
@@ -4140,7 +4154,10 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                3, /* max_number_of_canisters */
+                &SubnetCapacityLimits {
+                    max_number_of_canisters: 3,
+                    ..NO_CAPACITY_LIMITS
+                },
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -4155,7 +4172,10 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                3, /* max_number_of_canisters */
+                &SubnetCapacityLimits {
+                    max_number_of_canisters: 3,
+                    ..NO_CAPACITY_LIMITS
+                },
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -4170,7 +4190,10 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                3, /* max_number_of_canisters */
+                &SubnetCapacityLimits {
+                    max_number_of_canisters: 3,
+                    ..NO_CAPACITY_LIMITS
+                },
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -4188,7 +4211,10 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
             sender_subnet_id,
             *INITIAL_CYCLES,
             CanisterSettings::default(),
-            3, /* max_number_of_canisters */
+            &SubnetCapacityLimits {
+                max_number_of_canisters: 3,
+                ..NO_CAPACITY_LIMITS
+            },
             &mut state,
             SMALL_APP_SUBNET_MAX_SIZE,
             &mut round_limits,
@@ -4208,7 +4234,10 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
                 sender_subnet_id,
                 *INITIAL_CYCLES,
                 CanisterSettings::default(),
-                10, /* max_number_of_canisters */
+                &SubnetCapacityLimits {
+                    max_number_of_canisters: 10,
+                    ..NO_CAPACITY_LIMITS
+                },
                 &mut state,
                 SMALL_APP_SUBNET_MAX_SIZE,
                 &mut round_limits,
@@ -4221,6 +4250,167 @@ fn max_number_of_canisters_is_respected_when_creating_canisters() {
     })
 }
 
+#[test]
+fn max_total_memory_is_respected_when_creating_canisters() {
+    with_setup(|canister_manager, mut state, _| {
+        const GIB: u64 = 1 << 30;
+        // 1GiB of the subnet memory is already taken by other canisters.
+        let mut round_limits = RoundLimits {
+            instructions: as_round_instructions(EXECUTION_PARAMETERS.instruction_limits.message()),
+            subnet_available_memory: SubnetAvailableMemory::new(
+                (MEMORY_CAPACITY.get() - GIB) as i64,
+                SUBNET_MEMORY_CAPACITY,
+                SUBNET_MEMORY_CAPACITY,
+            ),
+            compute_allocation_used: state.total_compute_allocation(),
+        };
+        let sender = canister_test_id(1).get();
+        let sender_subnet_id = subnet_test_id(1);
+        let capacity_limits = SubnetCapacityLimits {
+            max_total_memory_bytes: 2 * GIB,
+            ..NO_CAPACITY_LIMITS
+        };
+
+        // Reserving 512MiB stays below the limit of 2GiB, should succeed.
+        canister_manager
+            .create_canister(
+                canister_change_origin_from_principal(&sender),
+                sender_subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettingsBuilder::new()
+                    .with_memory_allocation(
+                        MemoryAllocation::try_from(NumBytes::new(GIB / 2)).unwrap(),
+                    )
+                    .build(),
+                &capacity_limits,
+                &mut state,
+                SMALL_APP_SUBNET_MAX_SIZE,
+                &mut round_limits,
+                ResourceSaturation::default(),
+                &no_op_counter(),
+            )
+            .0
+            .unwrap();
+
+        // Reserving another 1GiB exceeds the limit, should fail.
+        let (res, _) = canister_manager.create_canister(
+            canister_change_origin_from_principal(&sender),
+            sender_subnet_id,
+            *INITIAL_CYCLES,
+            CanisterSettingsBuilder::new()
+                .with_memory_allocation(MemoryAllocation::try_from(NumBytes::new(GIB)).unwrap())
+                .build(),
+            &capacity_limits,
+            &mut state,
+            SMALL_APP_SUBNET_MAX_SIZE,
+            &mut round_limits,
+            ResourceSaturation::default(),
+            &no_op_counter(),
+        );
+        assert_eq!(
+            res,
+            Err(CanisterManagerError::SubnetMemoryCapacityOverSubscribed {
+                requested: NumBytes::new(GIB),
+                available: NumBytes::new(GIB / 2),
+            })
+        );
+
+        // With 1.5GiB taken, no canister can be created under a limit of 1.5GiB.
+        let (res, _) = canister_manager.create_canister(
+            canister_change_origin_from_principal(&sender),
+            sender_subnet_id,
+            *INITIAL_CYCLES,
+            CanisterSettings::default(),
+            &SubnetCapacityLimits {
+                max_total_memory_bytes: 3 * GIB / 2,
+                ..NO_CAPACITY_LIMITS
+            },
+            &mut state,
+            SMALL_APP_SUBNET_MAX_SIZE,
+            &mut round_limits,
+            ResourceSaturation::default(),
+            &no_op_counter(),
+        );
+        assert_matches!(res, Err(CanisterManagerError::MaxTotalMemoryReached { .. }));
+        assert_eq!(state.num_canisters(), 1);
+
+        // Without a limit the canister is created.
+        canister_manager
+            .create_canister(
+                canister_change_origin_from_principal(&sender),
+                sender_subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettings::default(),
+                &NO_CAPACITY_LIMITS,
+                &mut state,
+                SMALL_APP_SUBNET_MAX_SIZE,
+                &mut round_limits,
+                ResourceSaturation::default(),
+                &no_op_counter(),
+            )
+            .0
+            .unwrap();
+        assert_eq!(state.num_canisters(), 2);
+    })
+}
+
+#[test]
+fn max_reserved_compute_allocation_is_respected_when_creating_canisters() {
+    with_setup(|canister_manager, mut state, _| {
+        let mut round_limits = RoundLimits {
+            instructions: as_round_instructions(EXECUTION_PARAMETERS.instruction_limits.message()),
+            subnet_available_memory: (*MAX_SUBNET_AVAILABLE_MEMORY),
+            compute_allocation_used: state.total_compute_allocation(),
+        };
+        let sender = canister_test_id(1).get();
+        let sender_subnet_id = subnet_test_id(1);
+        let capacity_limits = SubnetCapacityLimits {
+            max_reserved_compute_allocation: 40,
+            ..NO_CAPACITY_LIMITS
+        };
+
+        // A compute allocation of 50% exceeds the limit of 40%, should fail.
+        let (res, _) = canister_manager.create_canister(
+            canister_change_origin_from_principal(&sender),
+            sender_subnet_id,
+            *INITIAL_CYCLES,
+            CanisterSettingsBuilder::new()
+                .with_compute_allocation(ComputeAllocation::try_from(50).unwrap())
+                .build(),
+            &capacity_limits,
+            &mut state,
+            SMALL_APP_SUBNET_MAX_SIZE,
+            &mut round_limits,
+            ResourceSaturation::default(),
+            &no_op_counter(),
+        );
+        assert_matches!(
+            res,
+            Err(CanisterManagerError::SubnetComputeCapacityOverSubscribed { .. })
+        );
+
+        // A compute allocation of 30% stays below the limit, should succeed.
+        canister_manager
+            .create_canister(
+                canister_change_origin_from_principal(&sender),
+                sender_subnet_id,
+                *INITIAL_CYCLES,
+                CanisterSettingsBuilder::new()
+                    .with_compute_allocation(ComputeAllocation::try_from(30).unwrap())
+                    .build(),
+                &capacity_limits,
+                &mut state,
+                SMALL_APP_SUBNET_MAX_SIZE,
+                &mut round_limits,
+                ResourceSaturation::default(),
+                &no_op_counter(),
+            )
+            .0
+            .unwrap();
+        assert_eq!(state.num_canisters(), 1);
+    })
+}
+
 /// This canister exports a query that returns its canister version.
 const CANISTER_VERSION: &str = r#"
     (module
@@ -34,8 +34,9 @@ use ic_ic00_types::{
     CanisterInfoResponse, CanisterSettingsArgs, CanisterStatusType, ClearChunkStoreArgs,
     ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
     ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallChunkedCodeArgs, InstallCodeArgsV2,
-    Method as Ic00Method, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
-    ProvisionalTopUpCanisterArgs, SetupInitialDKGArgs, SignWithECDSAArgs, StoredChunksArgs,
+    Method as Ic00Method, NodeMetrics, NodeMetricsHistoryArgs, NodeMetricsHistoryResponse,
+    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
+    SetupInitialDKGArgs, SignWithECDSAArgs, StoredChunksArgs, SubnetInfoArgs, SubnetInfoResponse,
     UninstallCodeArgs, UpdateSettingsArgs, UploadChunkArgs, IC_00,
 };
 use ic_interfaces::execution_environment::{
@@ -329,6 +330,7 @@ impl ExecutionEnvironment {
         );
         let canister_manager_config: CanisterMgrConfig = CanisterMgrConfig::new(
             config.subnet_memory_capacity,
+            config.subnet_memory_reservation,
             config.default_provisional_cycles_balance,
             config.default_freeze_threshold,
             own_subnet_id,
@@ -643,7 +645,7 @@ impl ExecutionEnvironment {
                                 canister_id,
                                 &mut state,
                                 round_limits,
-                                registry_settings.subnet_size,
+                                registry_settings,
                             ),
                         };
                         // The induction cost of `UpdateSettings` is charged
@@ -946,7 +948,7 @@ impl ExecutionEnvironment {
                                     args.specified_id,
                                     &mut state,
                                     &registry_settings.provisional_whitelist,
-                                    registry_settings.max_number_of_canisters,
+                                    &registry_settings.subnet_capacity_limits,
                                     round_limits,
                                     self.subnet_memory_saturation(
                                         &round_limits.subnet_available_memory,
@@ -1060,6 +1062,22 @@ impl ExecutionEnvironment {
                 Some((res, msg.take_cycles()))
             }
 
+            Ok(Ic00Method::SubnetInfo) => {
+                let res = match SubnetInfoArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => self.subnet_info(args, &state, registry_settings),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::NodeMetricsHistory) => {
+                let res = match NodeMetricsHistoryArgs::decode(payload) {
+                    Err(err) => Err(err),
+                    Ok(args) => self.node_metrics_history(args, &state),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
             Ok(Ic00Method::DeleteChunks) | Ok(Ic00Method::InstallChunkedCode) => Some((
                 Err(UserError::new(
                     ErrorCode::CanisterRejectedMessage,
@@ -1357,7 +1375,7 @@ impl ExecutionEnvironment {
                     sender_subnet_id,
                     cycles,
                     settings,
-                    registry_settings.max_number_of_canisters,
+                    &registry_settings.subnet_capacity_limits,
                     state,
                     registry_settings.subnet_size,
                     round_limits,
@@ -1382,7 +1400,7 @@ impl ExecutionEnvironment {
         canister_id: CanisterId,
         state: &mut ReplicatedState,
         round_limits: &mut RoundLimits,
-        subnet_size: usize,
+        registry_settings: &RegistryExecutionSettings,
     ) -> Result<Vec<u8>, UserError> {
         let canister = get_canister_mut(canister_id, state)?;
         self.canister_manager
@@ -1393,7 +1411,8 @@ impl ExecutionEnvironment {
                 canister,
                 round_limits,
                 self.subnet_memory_saturation(&round_limits.subnet_available_memory),
-                subnet_size,
+                registry_settings.subnet_size,
+                &registry_settings.subnet_capacity_limits,
             )
             .map(|()| EmptyBlob.encode())
             .map_err(|err| err.into())
@@ -1596,6 +1615,79 @@ impl ExecutionEnvironment {
             .map_err(|err| err.into())
     }
 
+    /// Reports the usage of the subnet together with the capacity limits of
+    /// its subnet record.
+    fn subnet_info(
+        &self,
+        args: SubnetInfoArgs,
+        state: &ReplicatedState,
+        registry_settings: &RegistryExecutionSettings,
+    ) -> Result<Vec<u8>, UserError> {
+        self.validate_own_subnet_id(args.subnet_id)?;
+        let limits = &registry_settings.subnet_capacity_limits;
+        let ecdsa_keys = state
+            .metadata
+            .network_topology
+            .subnets
+            .get(&self.own_subnet_id)
+            .map(|subnet_topology| subnet_topology.ecdsa_keys_held.iter().cloned().collect())
+            .unwrap_or_default();
+        Ok(SubnetInfoResponse {
+            replica_version: registry_settings.replica_version.to_string(),
+            ecdsa_keys,
+            number_of_canisters: state.num_canisters() as u64,
+            max_number_of_canisters: limits.max_number_of_canisters,
+            memory_taken: state.memory_taken().execution().get(),
+            max_total_memory_bytes: limits.max_total_memory_bytes,
+            compute_allocation_used: state.total_compute_allocation(),
+            max_reserved_compute_allocation: limits.max_reserved_compute_allocation,
+        }
+        .encode())
+    }
+
+    /// Returns the daily snapshots of the block making statistics of the
+    /// subnet's nodes taken at or after `args.start_at_timestamp_nanos`.
+    fn node_metrics_history(
+        &self,
+        args: NodeMetricsHistoryArgs,
+        state: &ReplicatedState,
+    ) -> Result<Vec<u8>, UserError> {
+        self.validate_own_subnet_id(args.subnet_id)?;
+        let history: Vec<NodeMetricsHistoryResponse> = state
+            .metadata
+            .blockmaker_metrics_time_series
+            .snapshots_since(Time::from_nanos_since_unix_epoch(
+                args.start_at_timestamp_nanos,
+            ))
+            .map(|(time, snapshot)| NodeMetricsHistoryResponse {
+                timestamp_nanos: time.as_nanos_since_unix_epoch(),
+                node_metrics: snapshot
+                    .iter()
+                    .map(|(node_id, stats)| NodeMetrics {
+                        node_id: *node_id,
+                        num_blocks_proposed_total: stats.blocks_proposed_total,
+                        num_block_failures_total: stats.blocks_not_proposed_total,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(Encode!(&history).unwrap())
+    }
+
+    /// Subnet and node information is only served by the subnet itself.
+    fn validate_own_subnet_id(&self, subnet_id: SubnetId) -> Result<(), UserError> {
+        if subnet_id != self.own_subnet_id {
+            return Err(UserError::new(
+                ErrorCode::SubnetNotFound,
+                format!(
+                    "Subnet {} cannot report on subnet {}",
+                    self.own_subnet_id, subnet_id
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     // Executes an inter-canister response.
     //
     // Returns a tuple with the result, along with a flag indicating whether or
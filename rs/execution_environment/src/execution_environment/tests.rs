@@ -3022,3 +3022,104 @@ fn output_requests_on_application_subnets_update_subnet_available_memory_reserve
     );
     assert_correct_request(system_state, canister_id);
 }
+
+fn get_xnet_reply_data(test: &ExecutionTest) -> Vec<u8> {
+    match test.xnet_messages()[0].clone() {
+        RequestOrResponse::Response(response) => match &response.response_payload {
+            Payload::Data(data) => data.clone(),
+            Payload::Reject(reject) => panic!("Unexpected reject: {:?}", reject),
+        },
+        RequestOrResponse::Request(request) => panic!("Expected a response, got {:?}", request),
+    }
+}
+
+#[test]
+fn subnet_info_reports_replica_version_and_capacity() {
+    let own_subnet = subnet_test_id(1);
+    let other_subnet = subnet_test_id(2);
+    let other_canister = canister_test_id(10);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(other_subnet, other_canister)
+        .with_max_number_of_canisters(10)
+        .build();
+    test.create_canister(Cycles::new(1_000_000_000_000));
+
+    test.inject_call_to_ic00(
+        Method::SubnetInfo,
+        ic00::SubnetInfoArgs {
+            subnet_id: own_subnet,
+        }
+        .encode(),
+        Cycles::new(0),
+    );
+    test.execute_all();
+    let info = ic00::SubnetInfoResponse::decode(&get_xnet_reply_data(&test)).unwrap();
+    assert_eq!(
+        info.replica_version,
+        ic_types::ReplicaVersion::default().to_string()
+    );
+    assert_eq!(info.number_of_canisters, 1);
+    assert_eq!(info.max_number_of_canisters, 10);
+    assert!(info.can_create_canister());
+}
+
+#[test]
+fn node_metrics_history_returns_snapshots_since_start() {
+    use ic_types::{batch::BlockmakerMetrics, time::Time};
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+    let own_subnet = subnet_test_id(1);
+    let other_subnet = subnet_test_id(2);
+    let other_canister = canister_test_id(10);
+    let mut test = ExecutionTestBuilder::new()
+        .with_own_subnet_id(own_subnet)
+        .with_caller(other_subnet, other_canister)
+        .build();
+    for (day, blockmaker, failed_blockmakers) in [(1, 1, vec![]), (2, 2, vec![node_test_id(1)])] {
+        test.state_mut()
+            .metadata
+            .blockmaker_metrics_time_series
+            .observe(
+                Time::from_nanos_since_unix_epoch(day * DAY),
+                &BlockmakerMetrics {
+                    blockmaker: node_test_id(blockmaker),
+                    failed_blockmakers,
+                },
+            );
+    }
+
+    test.inject_call_to_ic00(
+        Method::NodeMetricsHistory,
+        ic00::NodeMetricsHistoryArgs {
+            subnet_id: own_subnet,
+            start_at_timestamp_nanos: DAY + 1,
+        }
+        .encode(),
+        Cycles::new(0),
+    );
+    test.execute_all();
+    let history = Decode!(
+        &get_xnet_reply_data(&test),
+        Vec<ic00::NodeMetricsHistoryResponse>
+    )
+    .unwrap();
+    assert_eq!(
+        history,
+        vec![ic00::NodeMetricsHistoryResponse {
+            timestamp_nanos: 2 * DAY,
+            node_metrics: vec![
+                ic00::NodeMetrics {
+                    node_id: node_test_id(1),
+                    num_blocks_proposed_total: 1,
+                    num_block_failures_total: 1,
+                },
+                ic00::NodeMetrics {
+                    node_id: node_test_id(2),
+                    num_blocks_proposed_total: 1,
+                    num_block_failures_total: 0,
+                },
+            ],
+        }]
+    );
+}
@@ -4,8 +4,8 @@ use ic_config::flag_status::FlagStatus;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_embedders::wasm_executor::{WasmExecutionResult, WasmExecutor};
 use ic_embedders::wasm_utils::decoding::decoded_wasm_size;
+use ic_embedders::{compilation_cache_version, CompilationCache, CompilationResult};
 use ic_embedders::{wasm_executor::WasmExecutorImpl, WasmExecutionInput, WasmtimeEmbedder};
-use ic_embedders::{CompilationCache, CompilationResult};
 use ic_interfaces::execution_environment::{HypervisorResult, WasmExecutionOutput};
 use ic_logger::ReplicaLogger;
 use ic_metrics::buckets::decimal_buckets_with_zero;
@@ -236,6 +236,19 @@ impl Hypervisor {
             embedder_config.metering_type = ic_config::embedders::MeteringType::Old;
         }
 
+        let compilation_cache = match config.compilation_cache_dir {
+            Some(dir) => CompilationCache::new_with_disk_cache(
+                config.max_compilation_cache_size,
+                dir,
+                config.max_compilation_cache_disk_size,
+                compilation_cache_version(&embedder_config)
+                    .expect("Failed to compute the compilation cache version"),
+                metrics_registry,
+                log.clone(),
+            ),
+            None => CompilationCache::new(config.max_compilation_cache_size),
+        };
+
         let wasm_executor: Arc<dyn WasmExecutor> = match config.canister_sandboxing_flag {
             FlagStatus::Enabled => {
                 let executor = SandboxedExecutionController::new(
@@ -265,7 +278,7 @@ impl Hypervisor {
             own_subnet_type,
             log,
             cycles_account_manager,
-            compilation_cache: Arc::new(CompilationCache::new(config.max_compilation_cache_size)),
+            compilation_cache: Arc::new(compilation_cache),
             deterministic_time_slicing: config.deterministic_time_slicing,
             cost_to_compile_wasm_instruction: config
                 .embedders_config
@@ -2224,7 +2224,9 @@ fn get_instructions_limits_for_subnet_message(
             | UploadChunk
             | StoredChunks
             | DeleteChunks
-            | ClearChunkStore => default_limits,
+            | ClearChunkStore
+            | SubnetInfo
+            | NodeMetricsHistory => default_limits,
             InstallCode | InstallChunkedCode => InstructionLimits::new(
                 dts,
                 config.max_instructions_per_install_code,
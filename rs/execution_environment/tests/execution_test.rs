@@ -1,17 +1,22 @@
-use candid::Encode;
+use candid::{Decode, Encode};
 use ic_config::{
-    execution_environment::Config as HypervisorConfig,
+    execution_environment::{BitcoinConfig, Config as HypervisorConfig},
     subnet_config::{CyclesAccountManagerConfig, SubnetConfig},
 };
 use ic_ic00_types::{
-    CanisterIdRecord, CanisterSettingsArgs, CanisterSettingsArgsBuilder, CanisterStatusResultV2,
-    CreateCanisterArgs, EmptyBlob, Method, Payload, UpdateSettingsArgs, IC_00,
+    BitcoinGetSuccessorsArgs, BitcoinGetSuccessorsRequestInitial, BitcoinGetSuccessorsResponse,
+    BitcoinGetSuccessorsResponseComplete, BitcoinNetwork, BoundedHttpHeaders,
+    CanisterHttpRequestArgs, CanisterHttpResponsePayload, CanisterIdRecord, CanisterSettingsArgs,
+    CanisterSettingsArgsBuilder, CanisterStatusResultV2, CreateCanisterArgs, EmptyBlob, HttpMethod,
+    Method, Payload, UpdateSettingsArgs, IC_00,
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::{
-    ErrorCode, StateMachine, StateMachineBuilder, StateMachineConfig, UserError,
+    BitcoinAdapterRequestWrapper, BitcoinAdapterResponseWrapper, CanisterHttpRequestContext,
+    ErrorCode, ExternalServiceHandler, StateMachine, StateMachineBuilder, StateMachineConfig,
+    UserError,
 };
-use ic_types::{ingress::WasmResult, CanisterId, Cycles, NumBytes};
+use ic_types::{ingress::WasmResult, messages::RejectContext, CanisterId, Cycles, NumBytes};
 use ic_universal_canister::{call_args, wasm, UNIVERSAL_CANISTER_WASM};
 use std::{convert::TryInto, sync::Arc, time::Duration};
 
@@ -1421,3 +1426,130 @@ fn reserved_balance_limit_is_initialized_after_replica_upgrade() {
         Some(CyclesAccountManagerConfig::application_subnet().default_reserved_balance_limit),
     )
 }
+
+/// Echoes the URL of every HTTP outcall in the response body.
+struct EchoUrlHandler;
+
+impl ExternalServiceHandler for EchoUrlHandler {
+    fn handle_http_request(
+        &self,
+        context: &CanisterHttpRequestContext,
+    ) -> Option<Result<CanisterHttpResponsePayload, RejectContext>> {
+        Some(Ok(CanisterHttpResponsePayload {
+            status: 200,
+            headers: vec![],
+            body: context.url.clone().into_bytes(),
+        }))
+    }
+}
+
+#[test]
+fn http_outcalls_are_answered_by_external_service_handler() {
+    let env = StateMachineBuilder::new()
+        .with_external_service_handler(Arc::new(EchoUrlHandler))
+        .build();
+    let canister_id = create_universal_canister_with_cycles(&env, None, INITIAL_CYCLES_BALANCE);
+
+    let url = "https://example.com/".to_string();
+    let http_request = wasm()
+        .call_with_cycles(
+            IC_00,
+            Method::HttpRequest,
+            call_args().other_side(
+                Encode!(&CanisterHttpRequestArgs {
+                    url: url.clone(),
+                    max_response_bytes: None,
+                    headers: BoundedHttpHeaders::new(vec![]),
+                    body: None,
+                    method: HttpMethod::GET,
+                    transform: None,
+                })
+                .unwrap(),
+            ),
+            Cycles::new(B),
+        )
+        .build();
+
+    let result = env.execute_ingress(canister_id, "update", http_request);
+    let response = match result {
+        Ok(WasmResult::Reply(bytes)) => Decode!(&bytes, CanisterHttpResponsePayload).unwrap(),
+        result => panic!("Unexpected result {:?}", result),
+    };
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, url.into_bytes());
+    assert!(env.canister_http_request_contexts().is_empty());
+}
+
+/// Answers every `get_successors` request with a single "block" that
+/// consists of the anchor of the request.
+struct EchoAnchorHandler;
+
+impl ExternalServiceHandler for EchoAnchorHandler {
+    fn handle_bitcoin_request(
+        &self,
+        request: &BitcoinAdapterRequestWrapper,
+    ) -> Option<BitcoinAdapterResponseWrapper> {
+        match request {
+            BitcoinAdapterRequestWrapper::GetSuccessorsRequest(request) => {
+                Some(BitcoinAdapterResponseWrapper::GetSuccessorsResponse(
+                    BitcoinGetSuccessorsResponseComplete {
+                        blocks: vec![request.anchor.clone()],
+                        next: vec![],
+                    },
+                ))
+            }
+            BitcoinAdapterRequestWrapper::SendTransactionRequest(_) => None,
+        }
+    }
+}
+
+#[test]
+fn bitcoin_requests_are_answered_by_external_service_handler() {
+    // The first canister created on the subnet gets privileged access to the
+    // Bitcoin adapter.
+    let bitcoin_canister_id = CanisterId::from_u64(0);
+    let env = StateMachineBuilder::new()
+        .with_config(Some(StateMachineConfig::new(
+            SubnetConfig::new(SubnetType::Application),
+            HypervisorConfig {
+                bitcoin: BitcoinConfig {
+                    privileged_access: vec![bitcoin_canister_id],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )))
+        .with_external_service_handler(Arc::new(EchoAnchorHandler))
+        .build();
+    let canister_id = create_universal_canister_with_cycles(&env, None, INITIAL_CYCLES_BALANCE);
+    assert_eq!(canister_id, bitcoin_canister_id);
+
+    let anchor = vec![1; 32];
+    let get_successors = wasm()
+        .call_simple(
+            IC_00,
+            Method::BitcoinGetSuccessors,
+            call_args().other_side(
+                BitcoinGetSuccessorsArgs::Initial(BitcoinGetSuccessorsRequestInitial {
+                    network: BitcoinNetwork::Regtest,
+                    anchor: anchor.clone(),
+                    processed_block_hashes: vec![],
+                })
+                .encode(),
+            ),
+        )
+        .build();
+
+    let result = env.execute_ingress(canister_id, "update", get_successors);
+    let response = match result {
+        Ok(WasmResult::Reply(bytes)) => BitcoinGetSuccessorsResponse::decode(&bytes).unwrap(),
+        result => panic!("Unexpected result {:?}", result),
+    };
+    assert_eq!(
+        response,
+        BitcoinGetSuccessorsResponse::Complete(BitcoinGetSuccessorsResponseComplete {
+            blocks: vec![anchor],
+            next: vec![],
+        })
+    );
+}
@@ -33,7 +33,7 @@ use ic_test_utilities::{
     cycles_account_manager::CyclesAccountManagerBuilder,
     history::MockIngressHistory,
     state::{CanisterStateBuilder, ReplicatedStateBuilder},
-    types::ids::{node_test_id, subnet_test_id},
+    types::ids::{node_test_id, subnet_test_id, user_test_id},
     types::messages::SignedIngressBuilder,
     FastForwardTimeSource,
 };
@@ -154,6 +154,8 @@ fn prepare(
             .nonce(i as u64)
             .expiry_time(now + expiry)
             .canister_id(*canisters.next().unwrap())
+            // Distinct senders, so that the per-sender limit does not apply.
+            .sender(user_test_id(i as u64))
             .build();
         let message_id = IngressMessageId::from(&ingress);
         let peer_id = (i % 10) as u64;
@@ -25,11 +25,78 @@ use ic_types::{
     consensus::Payload,
     ingress::{IngressSets, IngressStatus},
     messages::{extract_effective_canister_id, MessageId, SignedIngress},
-    CanisterId, CountBytes, Cycles, Height, NumBytes, Time,
+    CanisterId, CountBytes, Cycles, Height, NumBytes, Time, UserId,
 };
 use ic_validator::RequestValidationError;
 use std::{collections::BTreeMap, sync::Arc};
 
+/// Maximum number of messages from a single sender in a block.
+///
+/// Messages from the anonymous principal are exempt: all anonymous callers
+/// share the same sender, so a single anonymous caller flooding the subnet
+/// would otherwise crowd out all others. They are still bounded by
+/// [`MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK`].
+///
+/// Like the per-canister limit below, this limit is part of payload
+/// validation, so all replicas of a subnet must agree on it: a replica
+/// enforcing a lower limit than the block maker would reject the block. This
+/// holds during rollouts because all replicas of a subnet switch to a new
+/// replica version at the same CUP height. The limits must therefore only be
+/// changed with a replica version upgrade, never through per-node
+/// configuration.
+const MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK: usize = 50;
+
+/// Maximum number of messages to a single canister in a block.
+const MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK: usize = 250;
+
+/// Counts the messages in a block per sender and per canister, to enforce
+/// [`MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK`] and
+/// [`MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK`].
+///
+/// Within these limits, messages are selected in the order of the ingress
+/// pool. They are not prioritized by fee, as the induction fee of a message
+/// is charged to the receiving canister and does not depend on its sender.
+#[derive(Default)]
+struct MessagesPerSenderAndCanister {
+    per_sender: BTreeMap<UserId, usize>,
+    per_canister: BTreeMap<CanisterId, usize>,
+}
+
+impl MessagesPerSenderAndCanister {
+    /// Counts `ingress`, unless its sender or canister already has the
+    /// maximum number of messages in the block.
+    fn try_add(&mut self, ingress: &SignedIngress) -> Result<(), IngressPermanentError> {
+        let sender = ingress.sender();
+        let canister_id = ingress.canister_id();
+        let sender_count = self.per_sender.get(&sender).copied().unwrap_or_default();
+        if sender_count >= MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK && !sender.get().is_anonymous()
+        {
+            return Err(
+                IngressPermanentError::IngressPayloadTooManyMessagesFromSender(
+                    sender,
+                    MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK,
+                ),
+            );
+        }
+        let canister_count = self
+            .per_canister
+            .get(&canister_id)
+            .copied()
+            .unwrap_or_default();
+        if canister_count >= MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK {
+            return Err(
+                IngressPermanentError::IngressPayloadTooManyMessagesToCanister(
+                    canister_id,
+                    MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK,
+                ),
+            );
+        }
+        self.per_sender.insert(sender, sender_count + 1);
+        self.per_canister.insert(canister_id, canister_count + 1);
+        Ok(())
+    }
+}
+
 impl IngressSelector for IngressManager {
     fn get_ingress_payload(
         &self,
@@ -82,6 +149,11 @@ impl IngressSelector for IngressManager {
         let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
         let mut num_messages = 0;
 
+        // To prevent a single sender or canister from flooding the block, valid
+        // messages exceeding their sender's or canister's share are left in the
+        // pool for a later block.
+        let mut messages_per_sender_and_canister = MessagesPerSenderAndCanister::default();
+
         let mut messages_in_payload = self.ingress_pool.select_validated(
             expiry_range,
             Box::new(move |ingress_obj| {
@@ -97,6 +169,14 @@ impl IngressSelector for IngressManager {
                 );
                 match result {
                     Ok(()) => {
+                        if messages_per_sender_and_canister
+                            .try_add(&ingress_obj.signed_ingress)
+                            .is_err()
+                        {
+                            self.metrics.ingress_selector_deferred_messages_total.inc();
+                            return SelectResult::Skip;
+                        }
+
                         num_messages += 1;
                         // Calculate the size and abort once we have hit the limit
                         accumulated_size += ingress_obj.signed_ingress.count_bytes();
@@ -207,10 +287,12 @@ impl IngressSelector for IngressManager {
 
         // Tracks the sum of cycles needed per canister.
         let mut cycles_needed: BTreeMap<CanisterId, Cycles> = BTreeMap::new();
+        let mut messages_per_sender_and_canister = MessagesPerSenderAndCanister::default();
         for i in 0..payload.message_count() {
             let (ingress_id, ingress) = payload
                 .get(i)
                 .map_err(IngressPermanentError::IngressPayloadError)?;
+            messages_per_sender_and_canister.try_add(&ingress)?;
 
             self.validate_ingress(
                 ingress_id.clone(),
@@ -509,6 +591,7 @@ mod tests {
     use super::*;
     use crate::tests::{access_ingress_pool, setup, setup_registry, setup_with_params};
     use assert_matches::assert_matches;
+    use ic_canister_client_sender::{Ed25519KeyPair, Sender};
     use ic_ic00_types::{CanisterIdRecord, Payload, IC_00};
     use ic_interfaces::{
         artifact_pool::{MutablePool, UnvalidatedArtifact, ValidatedPoolReader},
@@ -945,6 +1028,238 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    // Messages of a sender exceeding its share of the block are left for a
+    // later block, while messages of other senders are included.
+    async fn test_get_payload_caps_messages_of_flooding_sender() {
+        setup_with_params(
+            None,
+            None,
+            None,
+            Some(
+                ReplicatedStateBuilder::default()
+                    .with_canister(
+                        CanisterStateBuilder::default()
+                            .with_canister_id(canister_test_id(0))
+                            .build(),
+                    )
+                    .build(),
+            ),
+            |ingress_manager, ingress_pool| {
+                let validation_context = ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                };
+
+                // The flooding sender's messages arrive first.
+                let flooding_sender =
+                    Sender::from_keypair(&Ed25519KeyPair::generate(&mut rand::thread_rng()));
+                let mut messages: Vec<_> = (0..=MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK)
+                    .map(|i| {
+                        SignedIngressBuilder::new()
+                            .canister_id(canister_test_id(0))
+                            .nonce(i as u64)
+                            .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                            .sign_for_sender(&flooding_sender)
+                            .build()
+                    })
+                    .collect();
+                let other_sender_msg = SignedIngressBuilder::new()
+                    .canister_id(canister_test_id(0))
+                    .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                    .sign_for_randomly_generated_sender()
+                    .build();
+                messages.push(other_sender_msg.clone());
+
+                access_ingress_pool(&ingress_pool, |ingress_pool| {
+                    for (i, message) in messages.iter().enumerate() {
+                        let message_id = IngressMessageId::from(message);
+                        ingress_pool.insert(UnvalidatedArtifact {
+                            message: message.clone(),
+                            peer_id: node_test_id(0),
+                            timestamp: mock_time() + Duration::from_millis(i as u64),
+                        });
+                        ingress_pool.apply_changes(
+                            &SysTimeSource::new(),
+                            vec![ChangeAction::MoveToValidated((
+                                message_id,
+                                node_test_id(0),
+                                message.count_bytes(),
+                                (),
+                                crypto_hash(message.binary()).get(),
+                            ))],
+                        );
+                    }
+                });
+
+                let payload = ingress_manager.get_ingress_payload(
+                    &HashSet::new(),
+                    &validation_context,
+                    NumBytes::new(1024 * 1024),
+                );
+                assert_eq!(
+                    payload.message_count(),
+                    MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK + 1
+                );
+
+                let payload_ids: Vec<_> = (0..payload.message_count())
+                    .map(|i| payload.get(i).unwrap().0)
+                    .collect();
+                assert_eq!(
+                    payload_ids.last().unwrap(),
+                    &IngressMessageId::from(&other_sender_msg)
+                );
+                assert!(!payload_ids.contains(&IngressMessageId::from(
+                    &messages[MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK]
+                )));
+
+                // The validation of the payload agrees with its selection.
+                assert!(ingress_manager
+                    .validate_ingress_payload(&payload, &HashSet::new(), &validation_context)
+                    .is_ok());
+            },
+        )
+    }
+
+    #[tokio::test]
+    // Messages of anonymous senders are not capped per sender, as all anonymous
+    // callers share the same sender.
+    async fn test_get_payload_does_not_cap_anonymous_messages() {
+        setup_with_params(
+            None,
+            None,
+            None,
+            Some(
+                ReplicatedStateBuilder::default()
+                    .with_canister(
+                        CanisterStateBuilder::default()
+                            .with_canister_id(canister_test_id(0))
+                            .build(),
+                    )
+                    .build(),
+            ),
+            |ingress_manager, ingress_pool| {
+                let validation_context = ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                };
+
+                let messages: Vec<_> = (0..=MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK)
+                    .map(|i| {
+                        SignedIngressBuilder::new()
+                            .canister_id(canister_test_id(0))
+                            .nonce(i as u64)
+                            .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                            .build()
+                    })
+                    .collect();
+                assert!(messages[0].sender().get().is_anonymous());
+
+                access_ingress_pool(&ingress_pool, |ingress_pool| {
+                    for (i, message) in messages.iter().enumerate() {
+                        let message_id = IngressMessageId::from(message);
+                        ingress_pool.insert(UnvalidatedArtifact {
+                            message: message.clone(),
+                            peer_id: node_test_id(0),
+                            timestamp: mock_time() + Duration::from_millis(i as u64),
+                        });
+                        ingress_pool.apply_changes(
+                            &SysTimeSource::new(),
+                            vec![ChangeAction::MoveToValidated((
+                                message_id,
+                                node_test_id(0),
+                                message.count_bytes(),
+                                (),
+                                crypto_hash(message.binary()).get(),
+                            ))],
+                        );
+                    }
+                });
+
+                let payload = ingress_manager.get_ingress_payload(
+                    &HashSet::new(),
+                    &validation_context,
+                    NumBytes::new(1024 * 1024),
+                );
+                assert_eq!(
+                    payload.message_count(),
+                    MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK + 1
+                );
+                assert!(ingress_manager
+                    .validate_ingress_payload(&payload, &HashSet::new(), &validation_context)
+                    .is_ok());
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_validate_ingress_payload_max_messages_per_sender() {
+        setup(|ingress_manager, _| {
+            let payload: Vec<_> = (0..=MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(i as u64))
+                        .sender(user_test_id(1))
+                        .nonce(i as u64)
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+            let sender = payload[0].sender();
+
+            let ingress_validation = ingress_manager.validate_ingress_payload(
+                &IngressPayload::from(payload),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+            );
+
+            assert_matches!(
+                ingress_validation,
+                Err(ValidationError::Permanent(
+                    IngressPermanentError::IngressPayloadTooManyMessagesFromSender(user_id, MAX_INGRESS_MESSAGES_PER_SENDER_PER_BLOCK),
+                )) if user_id == sender
+            );
+        })
+    }
+
+    #[tokio::test]
+    async fn test_validate_ingress_payload_max_messages_per_canister() {
+        setup(|ingress_manager, _| {
+            let payload: Vec<_> = (0..=MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK)
+                .map(|i| {
+                    SignedIngressBuilder::new()
+                        .canister_id(canister_test_id(0))
+                        .sender(user_test_id(i as u64))
+                        .expiry_time(mock_time() + MAX_INGRESS_TTL)
+                        .build()
+                })
+                .collect();
+
+            let ingress_validation = ingress_manager.validate_ingress_payload(
+                &IngressPayload::from(payload),
+                &HashSet::new(),
+                &ValidationContext {
+                    time: mock_time(),
+                    registry_version: RegistryVersion::from(1),
+                    certified_height: Height::from(0),
+                },
+            );
+
+            assert_matches!(
+                ingress_validation,
+                Err(ValidationError::Permanent(
+                    IngressPermanentError::IngressPayloadTooManyMessagesToCanister(canister_id, MAX_INGRESS_MESSAGES_PER_CANISTER_PER_BLOCK),
+                )) if canister_id == canister_test_id(0)
+            );
+        })
+    }
+
     #[tokio::test]
     // Select two small messages in the artifact pool
     async fn test_get_payload_small_size_accumulation() {
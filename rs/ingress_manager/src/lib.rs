@@ -35,7 +35,7 @@ use ic_types::{
 use ic_validator::{
     CanisterIdSet, HttpRequestVerifier, HttpRequestVerifierImpl, RequestValidationError,
 };
-use prometheus::{Histogram, IntGauge};
+use prometheus::{Histogram, IntCounter, IntGauge};
 use std::{
     collections::{BTreeMap, HashSet},
     ops::RangeInclusive,
@@ -80,6 +80,7 @@ struct IngressManagerMetrics {
     ingress_selector_get_payload_time: Histogram,
     ingress_selector_validate_payload_time: Histogram,
     ingress_payload_cache_size: IntGauge,
+    ingress_selector_deferred_messages_total: IntCounter,
 }
 
 impl IngressManagerMetrics {
@@ -104,6 +105,10 @@ impl IngressManagerMetrics {
                 "ingress_payload_cache_size",
                 "The number of HashSets in payload builder's ingress payload cache.",
             ),
+            ingress_selector_deferred_messages_total: metrics_registry.int_counter(
+                "ingress_selector_deferred_messages_total",
+                "Ingress messages left for a later block because their sender or canister exceeded its share of the block.",
+            ),
         }
     }
 }
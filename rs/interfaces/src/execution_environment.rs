@@ -16,7 +16,7 @@ use ic_types::{
         AnonymousQuery, AnonymousQueryResponse, CertificateDelegation, HttpQueryResponse,
         MessageId, SignedIngressContent, UserQuery,
     },
-    Cycles, ExecutionRound, Height, NumInstructions, NumPages, Randomness, Time,
+    Cycles, ExecutionRound, Height, NumInstructions, NumPages, Randomness, ReplicaVersion, Time,
 };
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
@@ -950,13 +950,30 @@ pub enum ExecutionRoundType {
     OrdinaryRound,
 }
 
+/// Limits on the canisters and resource reservations that a subnet admits,
+/// as recorded in its subnet record. A limit of `0` means that there is no
+/// limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubnetCapacityLimits {
+    /// The maximum number of canisters on the subnet.
+    pub max_number_of_canisters: u64,
+    /// The maximum memory in bytes that canisters on the subnet may use or
+    /// reserve in total.
+    pub max_total_memory_bytes: u64,
+    /// The maximum sum of the compute allocations (in percent) of the
+    /// canisters on the subnet.
+    pub max_reserved_compute_allocation: u64,
+}
+
 /// Configuration of execution that comes from the registry.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RegistryExecutionSettings {
-    pub max_number_of_canisters: u64,
+    pub subnet_capacity_limits: SubnetCapacityLimits,
     pub provisional_whitelist: ProvisionalWhitelist,
     pub max_ecdsa_queue_size: u32,
     pub subnet_size: usize,
+    /// The replica version of the subnet record.
+    pub replica_version: ReplicaVersion,
 }
 
 pub trait Scheduler: Send {
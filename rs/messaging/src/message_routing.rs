@@ -8,7 +8,9 @@ use ic_cycles_account_manager::CyclesAccountManager;
 use ic_ic00_types::EcdsaKeyId;
 use ic_interfaces::crypto::ErrorReproducibility;
 use ic_interfaces::{
-    execution_environment::{IngressHistoryWriter, RegistryExecutionSettings, Scheduler},
+    execution_environment::{
+        IngressHistoryWriter, RegistryExecutionSettings, Scheduler, SubnetCapacityLimits,
+    },
     messaging::{MessageRouting, MessageRoutingError},
 };
 use ic_interfaces_certified_stream_store::CertifiedStreamStore;
@@ -35,7 +37,8 @@ use ic_types::{
     malicious_flags::MaliciousFlags,
     registry::RegistryClientError,
     xnet::{StreamHeader, StreamIndex},
-    Height, NodeId, NumBytes, PrincipalIdBlobParseError, RegistryVersion, SubnetId, Time,
+    Height, NodeId, NumBytes, PrincipalIdBlobParseError, RegistryVersion, ReplicaVersion, SubnetId,
+    Time,
 };
 use ic_utils::thread::JoinOnDrop;
 #[cfg(test)]
@@ -636,12 +639,24 @@ impl BatchProcessorImpl {
         let node_public_keys = self.try_to_populate_node_public_keys(nodes, registry_version)?;
 
         let subnet_features = subnet_record.features.unwrap_or_default().into();
-        let max_number_of_canisters = subnet_record.max_number_of_canisters;
+        let subnet_capacity_limits = SubnetCapacityLimits {
+            max_number_of_canisters: subnet_record.max_number_of_canisters,
+            max_total_memory_bytes: subnet_record.max_total_memory_bytes,
+            max_reserved_compute_allocation: subnet_record.max_reserved_compute_allocation,
+        };
         let max_ecdsa_queue_size = subnet_record
             .ecdsa_config
             .map(|c| c.max_queue_size)
             .unwrap_or(0);
 
+        let replica_version = ReplicaVersion::try_from(subnet_record.replica_version_id.as_str())
+            .map_err(|err| {
+            ReadRegistryError::Persistent(format!(
+                "'replica version of subnet {}', err: {}",
+                own_subnet_id, err
+            ))
+        })?;
+
         let subnet_size = if subnet_record.membership.is_empty() {
             self.metrics.critical_error_missing_subnet_size.inc();
             warn!(
@@ -662,10 +677,11 @@ impl BatchProcessorImpl {
             network_topology,
             subnet_features,
             RegistryExecutionSettings {
-                max_number_of_canisters,
+                subnet_capacity_limits,
                 provisional_whitelist,
                 max_ecdsa_queue_size,
                 subnet_size,
+                replica_version,
             },
             node_public_keys,
         ))
@@ -33,7 +33,7 @@ use ic_types::{
     crypto::threshold_sig::ni_dkg::{NiDkgTag, NiDkgTranscript},
     crypto::AlgorithmId,
     time::Time,
-    NodeId, PrincipalId, Randomness,
+    NodeId, PrincipalId, Randomness, ReplicaVersion,
 };
 use maplit::{btreemap, btreeset};
 use std::{fmt::Debug, str::FromStr, sync::Arc, time::Duration};
@@ -123,6 +123,8 @@ struct SubnetRecord<'a> {
     features: SubnetFeatures,
     ecdsa_config: EcdsaConfig,
     max_number_of_canisters: u64,
+    max_total_memory_bytes: u64,
+    max_reserved_compute_allocation: u64,
 }
 
 impl<'a> From<SubnetRecord<'a>> for SubnetRecordProto {
@@ -133,6 +135,8 @@ impl<'a> From<SubnetRecord<'a>> for SubnetRecordProto {
             .with_features(record.features.into())
             .with_ecdsa_config(record.ecdsa_config)
             .with_max_number_of_canisters(record.max_number_of_canisters)
+            .with_max_total_memory_bytes(record.max_total_memory_bytes)
+            .with_max_reserved_compute_allocation(record.max_reserved_compute_allocation)
             .build()
     }
 }
@@ -489,10 +493,11 @@ fn make_batch_processor(
     let metrics = Arc::new(MessageRoutingMetrics::new(&MetricsRegistry::default()));
     let state_manager = Arc::new(FakeStateManager::default());
     let registry_settings = Arc::new(Mutex::new(RegistryExecutionSettings {
-        max_number_of_canisters: 0,
+        subnet_capacity_limits: SubnetCapacityLimits::default(),
         provisional_whitelist: ProvisionalWhitelist::All,
         max_ecdsa_queue_size: 0,
         subnet_size: 0,
+        replica_version: ReplicaVersion::default(),
     }));
     let batch_processor = BatchProcessorImpl {
         state_manager: state_manager.clone(),
@@ -566,6 +571,8 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
                 ..Default::default()
             },
             max_number_of_canisters: 387,
+            max_total_memory_bytes: 4 << 30,
+            max_reserved_compute_allocation: 250,
         };
 
         let own_transcript = dummy_transcript_for_tests_with_params(
@@ -705,8 +712,12 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
 
         // Check registry execution settings.
         assert_eq!(
-            own_subnet_record.max_number_of_canisters,
-            registry_execution_settings.max_number_of_canisters,
+            SubnetCapacityLimits {
+                max_number_of_canisters: own_subnet_record.max_number_of_canisters,
+                max_total_memory_bytes: own_subnet_record.max_total_memory_bytes,
+                max_reserved_compute_allocation: own_subnet_record.max_reserved_compute_allocation,
+            },
+            registry_execution_settings.subnet_capacity_limits,
         );
         assert_eq!(
             provisional_whitelist,
@@ -720,6 +731,10 @@ fn try_read_registry_succeeds_with_fully_specified_registry_records() {
             own_subnet_record.membership.len(),
             registry_execution_settings.subnet_size,
         );
+        assert_eq!(
+            ReplicaVersion::default(),
+            registry_execution_settings.replica_version,
+        );
 
         // Check node public keys.
         assert_eq!(node_public_keys.len(), 2);
@@ -1282,6 +1297,8 @@ fn process_batch_updates_subnet_metrics() {
                 ..Default::default()
             },
             max_number_of_canisters: 387,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
         };
 
         let own_transcript = dummy_transcript_for_tests_with_params(
@@ -99,6 +99,10 @@ impl StateMachine for StateMachineImpl {
         state.metadata.network_topology = network_topology;
         state.metadata.own_subnet_features = subnet_features;
         state.metadata.node_public_keys = node_public_keys;
+        state
+            .metadata
+            .blockmaker_metrics_time_series
+            .observe(state.metadata.batch_time, &batch.blockmaker_metrics);
         if let Err(message) = state.metadata.init_allocation_ranges_if_empty() {
             self.metrics
                 .observe_no_canister_allocation_range(&self.log, message);
@@ -1,4 +1,4 @@
-use candid::CandidType;
+use candid::{CandidType, Nat};
 use ic_ic00_types::CanisterSettingsArgs;
 use ic_nns_common::types::UpdateIcpXdrConversionRatePayload;
 use ic_types::{CanisterId, Cycles, PrincipalId, SubnetId};
@@ -6,6 +6,7 @@ use ic_xrc_types::ExchangeRate;
 use icp_ledger::{
     AccountIdentifier, BlockIndex, Memo, SendArgs, Subaccount, Tokens, DEFAULT_TRANSFER_FEE,
 };
+use icrc_ledger_types::icrc1::account::Account;
 use serde::{Deserialize, Serialize};
 
 pub const DEFAULT_CYCLES_PER_XDR: u128 = 1_000_000_000_000u128; // 1T cycles = 1 XDR
@@ -14,6 +15,7 @@ pub const PERMYRIAD_DECIMAL_PLACES: u32 = 4;
 
 pub const CREATE_CANISTER_REFUND_FEE: Tokens = Tokens::from_e8s(DEFAULT_TRANSFER_FEE.get_e8s() * 4);
 pub const TOP_UP_CANISTER_REFUND_FEE: Tokens = Tokens::from_e8s(DEFAULT_TRANSFER_FEE.get_e8s() * 2);
+pub const MINT_CYCLES_REFUND_FEE: Tokens = Tokens::from_e8s(DEFAULT_TRANSFER_FEE.get_e8s() * 2);
 
 /// Cycles penalty charged for sending bad requests that incur a lot of work.
 pub const BAD_REQUEST_CYCLES_PENALTY: u128 = 100_000_000; // TODO(SDK-1248) revisit fair pricing. Currently costs significantly more than an update call
@@ -43,6 +45,7 @@ pub struct CyclesCanisterInitPayload {
     pub minting_account_id: Option<AccountIdentifier>,
     pub last_purged_notification: Option<BlockIndex>,
     pub exchange_rate_canister: Option<ExchangeRateCanister>,
+    pub cycles_ledger_canister_id: Option<CanisterId>,
 }
 
 /// Argument taken by top up notification endpoint
@@ -63,6 +66,44 @@ pub struct NotifyCreateCanister {
     pub settings: Option<CanisterSettingsArgs>,
 }
 
+/// Argument taken by the mint cycles notification endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct NotifyMintCyclesArg {
+    pub block_index: BlockIndex,
+    /// The subaccount of the caller on the cycles ledger that receives the
+    /// minted cycles.
+    pub to_subaccount: Option<icrc_ledger_types::icrc1::account::Subaccount>,
+    /// The memo of the deposit on the cycles ledger.
+    pub deposit_memo: Option<Vec<u8>>,
+}
+
+/// Result of a successful mint cycles notification
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct NotifyMintCyclesSuccess {
+    /// The index of the deposit block on the cycles ledger.
+    pub block_index: Nat,
+    /// The amount of cycles that were minted and deposited.
+    pub minted: Nat,
+    /// The balance of the account on the cycles ledger after the deposit.
+    pub balance: Nat,
+}
+
+pub type NotifyMintCyclesResult = Result<NotifyMintCyclesSuccess, NotifyError>;
+
+/// Argument of the `deposit` method of the cycles ledger
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct CyclesLedgerDepositArgs {
+    pub to: Account,
+    pub memo: Option<icrc_ledger_types::icrc1::transfer::Memo>,
+}
+
+/// Result of the `deposit` method of the cycles ledger
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct CyclesLedgerDepositResult {
+    pub block_index: Nat,
+    pub balance: Nat,
+}
+
 /// Error for notify endpoints
 #[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
 pub enum NotifyError {
@@ -104,15 +145,41 @@ pub enum CreateCanisterError {
 /// Options to select subnets when creating a canister
 #[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
 pub enum SubnetSelection {
-    /// Choose a random subnet that satisfies the specified properties
+    /// Choose the subnet with the fewest canisters among those that satisfy
+    /// the specified properties
     Filter(SubnetFilter),
     /// Choose a specific subnet
     Subnet { subnet: SubnetId },
 }
 
-#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+/// Properties a subnet must satisfy to be eligible for a new canister. Fields
+/// that are not set do not restrict the selection.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq, Default)]
 pub struct SubnetFilter {
+    /// Only consider subnets of this (CMC-level) subnet type. If not set, the
+    /// subnets the caller is authorized to use are considered.
     pub subnet_type: Option<String>,
+    /// Only consider subnets whose number of canisters, as last reported by
+    /// the subnet, is at least this far below the `max_number_of_canisters` in
+    /// their registry record. Subnets without a limit always qualify.
+    pub min_free_canister_slots: Option<u64>,
+    /// Only consider subnets all of whose nodes are located in data centers
+    /// whose region (as recorded in the registry, e.g. "Europe,BE,Brussels")
+    /// starts with one of these prefixes.
+    pub data_center_regions: Option<Vec<String>>,
+    /// Only consider subnets all of whose nodes are located in one of these
+    /// data centers.
+    pub data_center_ids: Option<Vec<String>>,
+}
+
+impl SubnetFilter {
+    /// Returns true if evaluating this filter requires information about the
+    /// subnets from the registry.
+    pub fn requires_registry_data(&self) -> bool {
+        self.min_free_canister_slots.is_some()
+            || self.data_center_regions.is_some()
+            || self.data_center_ids.is_some()
+    }
 }
 pub enum NotifyErrorCode {
     /// An internal error in the cycles minting canister (e.g., inconsistent state).
@@ -124,6 +191,10 @@ pub enum NotifyErrorCode {
     RefundFailed = 3,
     /// The subnet selection parameters are set in an invalid way.
     BadSubnetSelection = 4,
+    /// The deposit memo of a mint cycles notification is too long.
+    DepositMemoTooLong = 5,
+    /// No cycles ledger is configured, so cycles cannot be minted to an account.
+    CyclesLedgerNotConfigured = 6,
 }
 
 impl NotifyError {
@@ -167,6 +238,10 @@ impl std::fmt::Display for NotifyError {
 
 pub const MEMO_CREATE_CANISTER: Memo = Memo(0x41455243); // == 'CREA'
 pub const MEMO_TOP_UP_CANISTER: Memo = Memo(0x50555054); // == 'TPUP'
+pub const MEMO_MINT_CYCLES: Memo = Memo(0x544e494d); // == 'MINT'
+
+/// The maximum length of the memo of a deposit on the cycles ledger.
+pub const MAX_DEPOSIT_MEMO_LEN: usize = 32;
 
 pub fn create_canister_txn(
     amount: Tokens,
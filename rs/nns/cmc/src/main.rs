@@ -1,4 +1,4 @@
-use candid::{candid_method, CandidType, Encode};
+use candid::{candid_method, CandidType, Encode, Nat};
 use cycles_minting_canister::*;
 use dfn_candid::{candid_one, CandidOne};
 use dfn_core::{
@@ -16,7 +16,7 @@ use ic_crypto_tree_hash::{
 };
 use ic_ic00_types::{
     BoundedVec, CanisterIdRecord, CanisterSettingsArgs, CanisterSettingsArgsBuilder,
-    CreateCanisterArgs, Method, IC_00,
+    CreateCanisterArgs, Method, SubnetInfoArgs, SubnetInfoResponse, IC_00,
 };
 use ic_ledger_core::block::BlockType;
 use ic_ledger_core::tokens::CheckedSub;
@@ -30,6 +30,7 @@ use icp_ledger::{
     AccountIdentifier, Block, BlockIndex, BlockRes, CyclesResponse, Memo, Operation, SendArgs,
     Subaccount, Tokens, TransactionNotification, DEFAULT_TRANSFER_FEE,
 };
+use icrc_ledger_types::icrc1::account::Account;
 use on_wire::{FromWire, IntoWire, NewType};
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use serde::{Deserialize, Serialize};
@@ -40,10 +41,12 @@ use std::{
     thread::LocalKey,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
+use subnet_metadata::SubnetMetadata;
 
 mod environment;
 mod exchange_rate_canister;
 mod limiter;
+mod subnet_metadata;
 
 /// The past 30 days are used for the average ICP/XDR rate.
 const NUM_DAYS_FOR_ICP_XDR_AVERAGE: usize = 30;
@@ -65,6 +68,49 @@ const CREATE_CANISTER_MIN_CYCLES: u64 = 100_000_000_000;
 
 thread_local! {
     static STATE: RefCell<Option<State>> = RefCell::new(None);
+
+    /// Tracks the refresh of the subnet metadata. It is not part of the
+    /// state, so a refresh interrupted by an upgrade does not block later
+    /// ones and a refresh starts right after an upgrade.
+    static SUBNET_METADATA_REFRESH: RefCell<SubnetMetadataRefresh> =
+        RefCell::new(SubnetMetadataRefresh::default());
+}
+
+#[derive(Default)]
+struct SubnetMetadataRefresh {
+    in_progress: bool,
+    last_started_timestamp_seconds: u64,
+}
+
+/// Marks the refresh of the subnet metadata as in progress for as long as it
+/// exists. If the continuation of one of the calls of the refresh traps, the
+/// cleanup callback of the call drops the refresh future and with it the
+/// guard, so that later refreshes are not blocked.
+struct SubnetMetadataRefreshGuard;
+
+impl SubnetMetadataRefreshGuard {
+    /// Starts a refresh if none is in progress and the last one started at
+    /// least `SUBNET_LOAD_REFRESH_INTERVAL_SECONDS` ago.
+    fn try_start(now: u64) -> Option<Self> {
+        SUBNET_METADATA_REFRESH.with(|refresh| {
+            let mut refresh = refresh.borrow_mut();
+            let due = !refresh.in_progress
+                && now.saturating_sub(refresh.last_started_timestamp_seconds)
+                    >= subnet_metadata::SUBNET_LOAD_REFRESH_INTERVAL_SECONDS;
+            if !due {
+                return None;
+            }
+            refresh.in_progress = true;
+            refresh.last_started_timestamp_seconds = now;
+            Some(Self)
+        })
+    }
+}
+
+impl Drop for SubnetMetadataRefreshGuard {
+    fn drop(&mut self) {
+        SUBNET_METADATA_REFRESH.with(|refresh| refresh.borrow_mut().in_progress = false);
+    }
 }
 
 fn with_state<R>(f: impl FnOnce(&State) -> R) -> R {
@@ -122,6 +168,8 @@ pub enum NotificationStatus {
     NotifiedTopUp(Result<Cycles, NotifyError>),
     /// The cached result of a completed canister creation.
     NotifiedCreateCanister(Result<CanisterId, NotifyError>),
+    /// The cached result of a completed cycles mint into a cycles ledger account.
+    NotifiedMint(NotifyMintCyclesResult),
 }
 
 #[derive(Serialize, Deserialize, Clone, CandidType, Eq, PartialEq, Debug)]
@@ -137,6 +185,9 @@ pub struct State {
     /// Account used to burn funds.
     pub minting_account_id: Option<AccountIdentifier>,
 
+    /// The cycles ledger that `notify_mint_cycles` deposits minted cycles to.
+    pub cycles_ledger_canister_id: Option<CanisterId>,
+
     pub authorized_subnets: BTreeMap<PrincipalId, Vec<SubnetId>>,
 
     pub default_subnets: Vec<SubnetId>,
@@ -190,6 +241,12 @@ pub struct State {
 
     /// This is used to ensure that only one exchange rate update is being performed at a time from heartbeat.
     pub update_exchange_rate_canister_state: Option<UpdateExchangeRateState>,
+
+    /// Information about subnets, used to evaluate subnet filters. Entries
+    /// are refreshed from the heartbeat: the registry part once it is older
+    /// than `SUBNET_METADATA_MAX_AGE_SECONDS`, the number of canisters every
+    /// `SUBNET_LOAD_REFRESH_INTERVAL_SECONDS`.
+    pub subnet_metadata: Option<BTreeMap<SubnetId, SubnetMetadata>>,
 }
 
 impl State {
@@ -239,6 +296,7 @@ impl Default for State {
             governance_canister_id: CanisterId::ic_00(),
             exchange_rate_canister_id: None,
             minting_account_id: None,
+            cycles_ledger_canister_id: None,
             authorized_subnets: BTreeMap::new(),
             default_subnets: vec![],
             icp_xdr_conversion_rate: Some(IcpXdrConversionRate {
@@ -259,6 +317,7 @@ impl Default for State {
             maturity_modulation_permyriad: Some(0),
             subnet_types_to_subnets: Some(BTreeMap::new()),
             update_exchange_rate_canister_state: Some(UpdateExchangeRateState::default()),
+            subnet_metadata: Some(BTreeMap::new()),
         }
     }
 }
@@ -307,6 +366,7 @@ fn init(maybe_args: Option<CyclesCanisterInitPayload>) {
         if let Some(xrc_flag) = args.exchange_rate_canister {
             state.exchange_rate_canister_id = xrc_flag.extract_exchange_rate_canister_id();
         }
+        state.cycles_ledger_canister_id = args.cycles_ledger_canister_id;
     });
 }
 
@@ -1017,6 +1077,11 @@ fn create_canister_() {
     over_async(candid_one, create_canister)
 }
 
+#[export_name = "canister_update notify_mint_cycles"]
+fn notify_mint_cycles_() {
+    over_async(candid_one, notify_mint_cycles)
+}
+
 fn is_transient_error<T>(result: &Result<T, NotifyError>) -> bool {
     if let Err(e) = result {
         return e.is_retriable();
@@ -1062,6 +1127,9 @@ async fn notify_top_up(
                         "The same payment is already processed as create canister request".into(),
                     )))
                 }
+                NotificationStatus::NotifiedMint(_) => Some(Err(NotifyError::InvalidTransaction(
+                    "The same payment is already processed as mint cycles request".into(),
+                ))),
             },
             Entry::Vacant(entry) => {
                 entry.insert(NotificationStatus::Processing);
@@ -1137,6 +1205,9 @@ async fn notify_create_canister(
                 NotificationStatus::NotifiedTopUp(_) => Some(Err(NotifyError::InvalidTransaction(
                     "The same payment is already processed as a top up request.".into(),
                 ))),
+                NotificationStatus::NotifiedMint(_) => Some(Err(NotifyError::InvalidTransaction(
+                    "The same payment is already processed as a mint cycles request.".into(),
+                ))),
             },
             Entry::Vacant(entry) => {
                 entry.insert(NotificationStatus::Processing);
@@ -1262,6 +1333,7 @@ fn memo_to_intent_str(memo: Memo) -> String {
     match memo {
         MEMO_CREATE_CANISTER => "CreateCanister".into(),
         MEMO_TOP_UP_CANISTER => "TopUp".into(),
+        MEMO_MINT_CYCLES => "MintCycles".into(),
         a => format!("unrecognized: {a:?}"),
     }
 }
@@ -1305,6 +1377,109 @@ async fn fetch_transaction(
     Ok((amount, from))
 }
 
+/// Notify about a payment for minting cycles into an account on the cycles
+/// ledger. The payment must have been sent to the subaccount of the cycles
+/// minting canister that corresponds to the caller, and the cycles are
+/// deposited to an account of the caller.
+///
+/// # Arguments
+///
+/// * `block_index` -  The index of the block containing the payment.
+/// * `to_subaccount` - The subaccount of the caller that receives the cycles.
+/// * `deposit_memo` - The memo of the deposit on the cycles ledger.
+#[candid_method(update, rename = "notify_mint_cycles")]
+async fn notify_mint_cycles(
+    NotifyMintCyclesArg {
+        block_index,
+        to_subaccount,
+        deposit_memo,
+    }: NotifyMintCyclesArg,
+) -> NotifyMintCyclesResult {
+    let deposit_memo_len = deposit_memo.as_ref().map(|memo| memo.len()).unwrap_or(0);
+    if deposit_memo_len > MAX_DEPOSIT_MEMO_LEN {
+        return Err(NotifyError::Other {
+            error_code: NotifyErrorCode::DepositMemoTooLong as u64,
+            error_message: format!(
+                "Memo length {} exceeds the maximum length of {}",
+                deposit_memo_len, MAX_DEPOSIT_MEMO_LEN
+            ),
+        });
+    }
+
+    let cycles_ledger_canister_id =
+        with_state(|state| state.cycles_ledger_canister_id).ok_or(NotifyError::Other {
+            error_code: NotifyErrorCode::CyclesLedgerNotConfigured as u64,
+            error_message: "No cycles ledger is configured.".to_string(),
+        })?;
+
+    let caller = caller();
+    let cmc_id = dfn_core::api::id();
+    let sub = Subaccount::from(&caller);
+    let expected_to = AccountIdentifier::new(cmc_id.get(), Some(sub));
+
+    let (amount, from) = fetch_transaction(block_index, expected_to, MEMO_MINT_CYCLES).await?;
+
+    let maybe_early_result = with_state_mut(|state| {
+        state.purge_old_notifications(MAX_NOTIFY_HISTORY);
+
+        if block_index <= state.last_purged_notification.unwrap() {
+            return Some(Err(NotifyError::TransactionTooOld(
+                state.last_purged_notification.unwrap() + 1,
+            )));
+        }
+
+        match state.blocks_notified.as_mut().unwrap().entry(block_index) {
+            Entry::Occupied(entry) => match entry.get() {
+                NotificationStatus::Processing => Some(Err(NotifyError::Processing)),
+                NotificationStatus::NotifiedMint(resp) => Some(resp.clone()),
+                NotificationStatus::NotifiedTopUp(_) => Some(Err(NotifyError::InvalidTransaction(
+                    "The same payment is already processed as a top up request.".into(),
+                ))),
+                NotificationStatus::NotifiedCreateCanister(_) => {
+                    Some(Err(NotifyError::InvalidTransaction(
+                        "The same payment is already processed as a create canister request."
+                            .into(),
+                    )))
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(NotificationStatus::Processing);
+                None
+            }
+        }
+    });
+
+    match maybe_early_result {
+        Some(result) => result,
+        None => {
+            let to_account = Account {
+                owner: caller.0,
+                subaccount: to_subaccount,
+            };
+            let result = process_mint_cycles(
+                cycles_ledger_canister_id,
+                to_account,
+                deposit_memo,
+                from,
+                amount,
+            )
+            .await;
+
+            with_state_mut(|state| {
+                state.blocks_notified.as_mut().unwrap().insert(
+                    block_index,
+                    NotificationStatus::NotifiedMint(result.clone()),
+                );
+                if is_transient_error(&result) {
+                    state.blocks_notified.as_mut().unwrap().remove(&block_index);
+                }
+            });
+
+            result
+        }
+    }
+}
+
 /// Processes a legacy notification from the Ledger canister.
 async fn transaction_notification(tn: TransactionNotification) -> Result<CyclesResponse, String> {
     let caller = caller();
@@ -1342,6 +1517,9 @@ async fn transaction_notification(tn: TransactionNotification) -> Result<CyclesR
                 NotificationStatus::NotifiedCreateCanister(resp) => {
                     Err(format!("Already notified: {:?}", resp))
                 }
+                NotificationStatus::NotifiedMint(resp) => {
+                    Err(format!("Already notified: {:?}", resp))
+                }
             },
             Entry::Vacant(entry) => {
                 entry.insert(NotificationStatus::Processing);
@@ -1519,6 +1697,43 @@ async fn process_top_up(
     }
 }
 
+async fn process_mint_cycles(
+    cycles_ledger_canister_id: CanisterId,
+    to_account: Account,
+    deposit_memo: Option<Vec<u8>>,
+    from: AccountIdentifier,
+    amount: Tokens,
+) -> NotifyMintCyclesResult {
+    let cycles = tokens_to_cycles(amount)?;
+
+    let sub = Subaccount::from(&PrincipalId(to_account.owner));
+
+    print(format!(
+        "Minting {} cycles to account {} on cycles ledger {}.",
+        cycles, to_account, cycles_ledger_canister_id
+    ));
+
+    match deposit_to_cycles_ledger(cycles_ledger_canister_id, to_account, deposit_memo, cycles)
+        .await
+    {
+        Ok(deposit_result) => {
+            burn_and_log(sub, amount).await;
+            Ok(NotifyMintCyclesSuccess {
+                block_index: deposit_result.block_index,
+                minted: Nat::from(cycles.get()),
+                balance: deposit_result.balance,
+            })
+        }
+        Err(err) => {
+            let refund_block = refund_icp(sub, from, amount, MINT_CYCLES_REFUND_FEE).await?;
+            Err(NotifyError::Refunded {
+                reason: err,
+                block_index: refund_block,
+            })
+        }
+    }
+}
+
 /// Attempt to burn the funds.
 /// Burning doesn't return errors - we don't want to reject the transaction
 /// notification because then it could be retried.
@@ -1649,6 +1864,42 @@ async fn deposit_cycles(
     Ok(())
 }
 
+/// Mint `cycles` and deposit them to `to_account` on the cycles ledger.
+async fn deposit_to_cycles_ledger(
+    cycles_ledger_canister_id: CanisterId,
+    to_account: Account,
+    deposit_memo: Option<Vec<u8>>,
+    cycles: Cycles,
+) -> Result<CyclesLedgerDepositResult, String> {
+    ensure_balance(cycles)?;
+
+    let res: Result<CyclesLedgerDepositResult, (Option<i32>, String)> =
+        dfn_core::api::call_with_funds_and_cleanup(
+            cycles_ledger_canister_id,
+            "deposit",
+            dfn_candid::candid_one,
+            CyclesLedgerDepositArgs {
+                to: to_account,
+                memo: deposit_memo.map(Into::into),
+            },
+            dfn_core::api::Funds::new(
+                cycles
+                    .get()
+                    .try_into()
+                    .map_err(|_| "Cycles u64 overflow".to_owned())?,
+            ),
+        )
+        .await;
+
+    res.map_err(|(code, msg)| {
+        format!(
+            "Depositing cycles to the cycles ledger failed with code {}: {:?}",
+            code.unwrap_or_default(),
+            msg
+        )
+    })
+}
+
 async fn do_create_canister(
     controller_id: PrincipalId,
     cycles: Cycles,
@@ -1666,17 +1917,18 @@ async fn do_create_canister(
     // of subnets. Otherwise, fall back to the list of subnets for the
     // provided controller id.
 
+    let mut subnet_filter = None;
     let mut subnets: Vec<SubnetId> = match subnet_selection {
         Some(option) => match option {
-            SubnetSelection::Filter(subnet_filter) => {
-                with_state(|state| match subnet_filter.subnet_type {
+            SubnetSelection::Filter(filter) => {
+                let subnets = with_state(|state| match &filter.subnet_type {
                     Some(subnet_type) => {
                         let subnet_types_to_subnets = state
                             .subnet_types_to_subnets
                             .as_ref()
                             .expect("subnet types to subnets mapping is `None`");
                         subnet_types_to_subnets
-                            .get(&subnet_type)
+                            .get(subnet_type)
                             .map(|set| set.iter().cloned().collect())
                             .ok_or(format!(
                                 "Provided subnet type {} does not exist",
@@ -1684,7 +1936,9 @@ async fn do_create_canister(
                             ))
                     }
                     None => Ok(get_subnets_for(&controller_id)),
-                })
+                });
+                subnet_filter = Some(filter);
+                subnets
             }
             SubnetSelection::Subnet { subnet } => with_state(|state| {
                 if state.default_subnets.contains(&subnet)
@@ -1716,6 +1970,26 @@ async fn do_create_canister(
     // that we load balance canister creations among them.
     subnets.shuffle(&mut rng);
 
+    // If a filter was given, only keep the subnets that satisfy it, with the
+    // least-loaded ones first. The metadata is refreshed from the heartbeat,
+    // so subnets whose metadata has not been fetched yet only qualify if the
+    // filter does not need any.
+    if let Some(filter) = subnet_filter {
+        subnets = with_state(|state| {
+            subnet_metadata::select_subnets(
+                subnets,
+                &filter,
+                state.subnet_metadata.as_ref().unwrap(),
+            )
+        });
+        if subnets.is_empty() {
+            return Err(format!(
+                "No subnet in which {} may create canisters satisfies {:?}.",
+                controller_id, filter
+            ));
+        }
+    }
+
     let mut last_err = None;
 
     if mint_cycles && !subnets.is_empty() {
@@ -1738,6 +2012,25 @@ async fn do_create_canister(
         });
 
     for subnet_id in subnets {
+        // Skip subnets that report having reached one of the capacity limits
+        // of their registry record. If the subnet cannot be queried, try to
+        // create the canister anyway.
+        let info: Result<SubnetInfoResponse, _> = call_with_cleanup(
+            subnet_id.into(),
+            &Method::SubnetInfo.to_string(),
+            candid_one,
+            SubnetInfoArgs { subnet_id },
+        )
+        .await;
+        if let Ok(info) = info {
+            if !info.can_create_canister() {
+                let err = format!("Subnet {} is full: {:?}", subnet_id, info);
+                print(format!("[cycles] skipping full subnet: {}", err));
+                last_err = Some(err);
+                continue;
+            }
+        }
+
         let result: Result<CanisterIdRecord, _> = dfn_core::api::call_with_funds_and_cleanup(
             subnet_id.into(),
             &Method::CreateCanister.to_string(),
@@ -1770,6 +2063,14 @@ async fn do_create_canister(
             canister_id, subnet_id
         ));
 
+        // Account for the new canister until the next refresh of the number
+        // of canisters on the subnet.
+        with_state_mut(|state| {
+            if let Some(metadata) = state.subnet_metadata.as_mut().unwrap().get_mut(&subnet_id) {
+                metadata.number_of_canisters += 1;
+            }
+        });
+
         return Ok(canister_id);
     }
 
@@ -1813,6 +2114,88 @@ fn total_supply_() {
     })
 }
 
+/// Refreshes the metadata of all subnets on which the CMC may create
+/// canisters: the registry part if it is missing or stale, and the number of
+/// canisters as reported by the `subnet_info` method of each subnet. Subnets
+/// whose registry data cannot be fetched are skipped until the next refresh.
+async fn refresh_subnet_metadata(now: u64) {
+    let subnets: BTreeSet<SubnetId> = with_state(|state| {
+        state
+            .default_subnets
+            .iter()
+            .chain(state.authorized_subnets.values().flatten())
+            .chain(
+                state
+                    .subnet_types_to_subnets
+                    .as_ref()
+                    .unwrap()
+                    .values()
+                    .flatten(),
+            )
+            .cloned()
+            .collect()
+    });
+
+    let mut data_centers_of_operators = BTreeMap::new();
+    let mut regions_of_data_centers = BTreeMap::new();
+    for subnet_id in subnets {
+        let cached = with_state(|state| {
+            state
+                .subnet_metadata
+                .as_ref()
+                .unwrap()
+                .get(&subnet_id)
+                .filter(|subnet_metadata| !subnet_metadata.is_stale(now))
+                .cloned()
+        });
+        let mut metadata = match cached {
+            Some(metadata) => metadata,
+            None => match subnet_metadata::fetch_subnet_metadata(
+                subnet_id,
+                now,
+                &mut data_centers_of_operators,
+                &mut regions_of_data_centers,
+            )
+            .await
+            {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    print(format!(
+                        "[cycles] failed to refresh subnet metadata: {}",
+                        err
+                    ));
+                    continue;
+                }
+            },
+        };
+
+        let info: Result<SubnetInfoResponse, _> = call_with_cleanup(
+            subnet_id.into(),
+            &Method::SubnetInfo.to_string(),
+            candid_one,
+            SubnetInfoArgs { subnet_id },
+        )
+        .await;
+        match info {
+            Ok(info) => metadata.number_of_canisters = info.number_of_canisters,
+            Err((code, msg)) => print(format!(
+                "[cycles] failed to get the subnet info of {} with code {}: {}",
+                subnet_id,
+                code.unwrap_or_default(),
+                msg
+            )),
+        }
+
+        with_state_mut(|state| {
+            state
+                .subnet_metadata
+                .as_mut()
+                .unwrap()
+                .insert(subnet_id, metadata)
+        });
+    }
+}
+
 /// Return the list of subnets in which this controller is allowed to create
 /// canisters
 fn get_subnets_for(controller_id: &PrincipalId) -> Vec<SubnetId> {
@@ -1871,11 +2254,17 @@ fn post_upgrade(maybe_args: Option<CyclesCanisterInitPayload>) {
     if new_state.subnet_types_to_subnets.is_none() {
         new_state.subnet_types_to_subnets = Some(BTreeMap::new());
     }
+    if new_state.subnet_metadata.is_none() {
+        new_state.subnet_metadata = Some(BTreeMap::new());
+    }
 
     if let Some(args) = maybe_args {
         if let Some(xrc_flag) = args.exchange_rate_canister {
             new_state.exchange_rate_canister_id = xrc_flag.extract_exchange_rate_canister_id();
         }
+        if let Some(cycles_ledger_canister_id) = args.cycles_ledger_canister_id {
+            new_state.cycles_ledger_canister_id = Some(cycles_ledger_canister_id);
+        }
     }
 
     STATE.with(|state| state.replace(Some(new_state)));
@@ -1887,6 +2276,14 @@ fn canister_heartbeat() {
         let future = update_exchange_rate();
         dfn_core::api::futures::spawn(future);
     }
+
+    let now = CanisterEnvironment.now_timestamp_seconds();
+    if let Some(guard) = SubnetMetadataRefreshGuard::try_start(now) {
+        dfn_core::api::futures::spawn(async move {
+            let _guard = guard;
+            refresh_subnet_metadata(now).await;
+        });
+    }
 }
 
 async fn update_exchange_rate() {
@@ -1999,6 +2396,7 @@ fn get_subnet_selection(
     } else if let Some(subnet_type) = subnet_type {
         Ok(Some(SubnetSelection::Filter(SubnetFilter {
             subnet_type: Some(subnet_type),
+            ..Default::default()
         })))
     } else {
         Ok(subnet_selection)
@@ -2019,6 +2417,7 @@ mod tests {
             exchange_rate_canister: None,
             minting_account_id: None,
             last_purged_notification: Some(0),
+            cycles_ledger_canister_id: None,
         }))
     }
 
@@ -2051,6 +2450,14 @@ mod tests {
                 PrincipalId::new_user_test_id(4),
             ))),
         );
+        blocks_notified.insert(
+            61,
+            NotificationStatus::NotifiedMint(Ok(NotifyMintCyclesSuccess {
+                block_index: Nat::from(7_u64),
+                minted: Nat::from(1_000_000_u64),
+                balance: Nat::from(3_000_000_u64),
+            })),
+        );
         state.blocks_notified = Some(blocks_notified);
 
         let bytes = state.encode();
@@ -2554,6 +2961,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_subnet_metadata_refresh_guard() {
+        let interval = subnet_metadata::SUBNET_LOAD_REFRESH_INTERVAL_SECONDS;
+        let now = 10 * interval;
+
+        let guard = SubnetMetadataRefreshGuard::try_start(now);
+        assert!(guard.is_some());
+        // Only one refresh runs at a time, even once the next one is due.
+        assert!(SubnetMetadataRefreshGuard::try_start(now + interval).is_none());
+
+        // Dropping the guard, e.g. when a continuation of the refresh traps,
+        // ends the refresh.
+        drop(guard);
+        assert!(SubnetMetadataRefreshGuard::try_start(now + interval - 1).is_none());
+        assert!(SubnetMetadataRefreshGuard::try_start(now + interval).is_some());
+    }
+
     #[test]
     fn test_candid_interface_compatibility() {
         use candid::utils::{service_compatible, CandidSource};
@@ -0,0 +1,340 @@
+use candid::CandidType;
+use cycles_minting_canister::SubnetFilter;
+use ic_nns_constants::REGISTRY_CANISTER_ID;
+use ic_protobuf::registry::{
+    dc::v1::DataCenterRecord, node::v1::NodeRecord, node_operator::v1::NodeOperatorRecord,
+    subnet::v1::SubnetRecord,
+};
+use ic_registry_keys::{
+    make_data_center_record_key, make_node_operator_record_key, make_node_record_key,
+    make_subnet_record_key,
+};
+use ic_registry_transport::{deserialize_get_value_response, serialize_get_value_request};
+use ic_types::{NodeId, PrincipalId, SubnetId};
+use on_wire::bytes;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The registry part of the metadata is refetched once it is older than this.
+pub const SUBNET_METADATA_MAX_AGE_SECONDS: u64 = 24 * 60 * 60;
+
+/// How often the number of canisters on each subnet is refreshed.
+pub const SUBNET_LOAD_REFRESH_INTERVAL_SECONDS: u64 = 10 * 60;
+
+/// Information about a subnet, derived from the registry and from the subnet
+/// itself, that is needed to evaluate a `SubnetFilter`.
+#[derive(Serialize, Deserialize, Clone, CandidType, Eq, PartialEq, Debug, Default)]
+pub struct SubnetMetadata {
+    /// The `max_number_of_canisters` of the subnet record. Zero means that
+    /// the number of canisters is not limited.
+    pub max_number_of_canisters: u64,
+    /// The number of canisters on the subnet, as last reported by its
+    /// `subnet_info` method and increased by the canisters the CMC created on
+    /// it since.
+    pub number_of_canisters: u64,
+    /// The ids of the data centers hosting the nodes of the subnet.
+    pub data_center_ids: BTreeSet<String>,
+    /// The regions of the data centers hosting the nodes of the subnet.
+    pub data_center_regions: BTreeSet<String>,
+    /// The registry version the metadata was read at.
+    pub registry_version: u64,
+    /// When the registry part of the metadata was fetched, in seconds since
+    /// the UNIX epoch.
+    pub fetched_at_timestamp_seconds: u64,
+}
+
+impl SubnetMetadata {
+    pub fn is_stale(&self, now_timestamp_seconds: u64) -> bool {
+        now_timestamp_seconds.saturating_sub(self.fetched_at_timestamp_seconds)
+            >= SUBNET_METADATA_MAX_AGE_SECONDS
+    }
+
+    /// Returns how many more canisters the subnet can host, or `None` if the
+    /// subnet has no limit.
+    pub fn free_canister_slots(&self) -> Option<u64> {
+        if self.max_number_of_canisters == 0 {
+            None
+        } else {
+            Some(
+                self.max_number_of_canisters
+                    .saturating_sub(self.number_of_canisters),
+            )
+        }
+    }
+
+    /// Returns true if the subnet satisfies the registry-dependent parts of
+    /// `filter`. The subnet type is not checked here.
+    pub fn matches(&self, filter: &SubnetFilter) -> bool {
+        if let Some(min_free_canister_slots) = filter.min_free_canister_slots {
+            if let Some(free) = self.free_canister_slots() {
+                if free < min_free_canister_slots {
+                    return false;
+                }
+            }
+        }
+        if let Some(regions) = &filter.data_center_regions {
+            if self.data_center_regions.is_empty()
+                || !self.data_center_regions.iter().all(|region| {
+                    regions
+                        .iter()
+                        .any(|prefix| region.starts_with(prefix.as_str()))
+                })
+            {
+                return false;
+            }
+        }
+        if let Some(dc_ids) = &filter.data_center_ids {
+            if self.data_center_ids.is_empty()
+                || !self
+                    .data_center_ids
+                    .iter()
+                    .all(|dc_id| dc_ids.iter().any(|id| id.eq_ignore_ascii_case(dc_id)))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Keeps the subnets that satisfy `filter` and orders them from least to most
+/// loaded, where the load of a subnet is its number of canisters. Subnets
+/// whose number of canisters is not known yet come last. The relative order
+/// of equally loaded subnets is preserved, so a previous shuffle still spreads
+/// creations among them.
+///
+/// Subnets without metadata are only kept if the filter does not need any.
+pub fn select_subnets(
+    subnets: Vec<SubnetId>,
+    filter: &SubnetFilter,
+    metadata: &BTreeMap<SubnetId, SubnetMetadata>,
+) -> Vec<SubnetId> {
+    let mut subnets: Vec<SubnetId> = subnets
+        .into_iter()
+        .filter(|subnet| match metadata.get(subnet) {
+            Some(subnet_metadata) => subnet_metadata.matches(filter),
+            None => !filter.requires_registry_data(),
+        })
+        .collect();
+    subnets.sort_by_key(|subnet| {
+        metadata.get(subnet).map_or(u64::MAX, |subnet_metadata| {
+            subnet_metadata.number_of_canisters
+        })
+    });
+    subnets
+}
+
+async fn get_registry_value<T: Message + Default>(key: String) -> Result<(T, u64), String> {
+    let request = serialize_get_value_request(key.clone().into_bytes(), None)
+        .map_err(|err| format!("Failed to encode registry request for {}: {}", key, err))?;
+    let response: Vec<u8> =
+        dfn_core::api::call_with_cleanup(REGISTRY_CANISTER_ID, "get_value", bytes, request)
+            .await
+            .map_err(|(code, msg)| {
+                format!(
+                    "Getting {} from the registry failed with code {}: {}",
+                    key,
+                    code.unwrap_or_default(),
+                    msg
+                )
+            })?;
+    let (value, version) = deserialize_get_value_response(response)
+        .map_err(|err| format!("Getting {} from the registry failed: {}", key, err))?;
+    let value = T::decode(value.as_slice())
+        .map_err(|err| format!("Failed to decode registry value {}: {}", key, err))?;
+    Ok((value, version))
+}
+
+/// Reads the registry part of the metadata of `subnet_id`. The number of
+/// canisters is left at zero; it is set from the subnet's `subnet_info`.
+///
+/// `data_centers_of_operators` caches the data center of each node operator
+/// and `regions_of_data_centers` the region of each data center, so that
+/// fetching the metadata of several subnets does not look them up repeatedly.
+pub async fn fetch_subnet_metadata(
+    subnet_id: SubnetId,
+    now_timestamp_seconds: u64,
+    data_centers_of_operators: &mut BTreeMap<PrincipalId, String>,
+    regions_of_data_centers: &mut BTreeMap<String, String>,
+) -> Result<SubnetMetadata, String> {
+    let (subnet_record, registry_version) =
+        get_registry_value::<SubnetRecord>(make_subnet_record_key(subnet_id)).await?;
+
+    let mut metadata = SubnetMetadata {
+        max_number_of_canisters: subnet_record.max_number_of_canisters,
+        registry_version,
+        fetched_at_timestamp_seconds: now_timestamp_seconds,
+        ..Default::default()
+    };
+
+    for node_id in subnet_record.membership {
+        let node_id = NodeId::from(
+            PrincipalId::try_from(node_id.as_slice())
+                .map_err(|err| format!("Invalid node id in subnet {}: {}", subnet_id, err))?,
+        );
+        let (node_record, _) =
+            get_registry_value::<NodeRecord>(make_node_record_key(node_id)).await?;
+        let node_operator_id = PrincipalId::try_from(node_record.node_operator_id.as_slice())
+            .map_err(|err| format!("Invalid node operator id of node {}: {}", node_id, err))?;
+
+        let dc_id = match data_centers_of_operators.get(&node_operator_id) {
+            Some(dc_id) => dc_id.clone(),
+            None => {
+                let (node_operator_record, _) = get_registry_value::<NodeOperatorRecord>(
+                    make_node_operator_record_key(node_operator_id),
+                )
+                .await?;
+                let dc_id = node_operator_record.dc_id;
+                data_centers_of_operators.insert(node_operator_id, dc_id.clone());
+                dc_id
+            }
+        };
+
+        let region = match regions_of_data_centers.get(&dc_id) {
+            Some(region) => region.clone(),
+            None => {
+                let (dc_record, _) =
+                    get_registry_value::<DataCenterRecord>(make_data_center_record_key(&dc_id))
+                        .await?;
+                regions_of_data_centers.insert(dc_id.clone(), dc_record.region.clone());
+                dc_record.region
+            }
+        };
+
+        metadata.data_center_ids.insert(dc_id);
+        metadata.data_center_regions.insert(region);
+    }
+
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types_test_utils::ids::subnet_test_id;
+
+    fn metadata(
+        max_number_of_canisters: u64,
+        number_of_canisters: u64,
+        dcs: &[(&str, &str)],
+    ) -> SubnetMetadata {
+        SubnetMetadata {
+            max_number_of_canisters,
+            number_of_canisters,
+            data_center_ids: dcs.iter().map(|(id, _)| id.to_string()).collect(),
+            data_center_regions: dcs.iter().map(|(_, region)| region.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        let filter = SubnetFilter::default();
+        assert!(metadata(0, 0, &[]).matches(&filter));
+        assert!(metadata(10, 10, &[("zh1", "Europe,CH,Zurich")]).matches(&filter));
+    }
+
+    #[test]
+    fn test_min_free_canister_slots() {
+        let filter = SubnetFilter {
+            min_free_canister_slots: Some(5),
+            ..Default::default()
+        };
+        assert!(metadata(10, 5, &[]).matches(&filter));
+        assert!(!metadata(10, 6, &[]).matches(&filter));
+        // Subnets without a limit always qualify.
+        assert!(metadata(0, 1_000_000, &[]).matches(&filter));
+    }
+
+    #[test]
+    fn test_data_center_regions() {
+        let filter = SubnetFilter {
+            data_center_regions: Some(vec!["Europe".to_string()]),
+            ..Default::default()
+        };
+        assert!(metadata(
+            0,
+            0,
+            &[("zh1", "Europe,CH,Zurich"), ("br1", "Europe,BE,Brussels")]
+        )
+        .matches(&filter));
+        assert!(!metadata(
+            0,
+            0,
+            &[
+                ("zh1", "Europe,CH,Zurich"),
+                ("sf1", "North America,US,California")
+            ]
+        )
+        .matches(&filter));
+        assert!(!metadata(0, 0, &[]).matches(&filter));
+    }
+
+    #[test]
+    fn test_data_center_ids() {
+        let filter = SubnetFilter {
+            data_center_ids: Some(vec!["ZH1".to_string(), "br1".to_string()]),
+            ..Default::default()
+        };
+        assert!(metadata(0, 0, &[("zh1", "Europe,CH,Zurich")]).matches(&filter));
+        assert!(!metadata(
+            0,
+            0,
+            &[("zh1", "Europe,CH,Zurich"), ("ge1", "Europe,CH,Geneva")]
+        )
+        .matches(&filter));
+    }
+
+    #[test]
+    fn test_select_subnets_prefers_least_loaded() {
+        let (s1, s2, s3, s4) = (
+            subnet_test_id(1),
+            subnet_test_id(2),
+            subnet_test_id(3),
+            subnet_test_id(4),
+        );
+        let subnet_metadata: BTreeMap<_, _> = [
+            (s1, metadata(100, 20, &[("zh1", "Europe,CH,Zurich")])),
+            (s2, metadata(100, 10, &[("zh1", "Europe,CH,Zurich")])),
+            (
+                s3,
+                metadata(100, 0, &[("sf1", "North America,US,California")]),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            select_subnets(
+                vec![s1, s2, s3, s4],
+                &SubnetFilter::default(),
+                &subnet_metadata,
+            ),
+            // The load of s4 is not known, so it comes last.
+            vec![s3, s2, s1, s4]
+        );
+        assert_eq!(
+            select_subnets(
+                vec![s1, s2, s3, s4],
+                &SubnetFilter {
+                    data_center_regions: Some(vec!["Europe".to_string()]),
+                    ..Default::default()
+                },
+                &subnet_metadata,
+            ),
+            vec![s2, s1]
+        );
+        assert_eq!(
+            select_subnets(
+                vec![s1, s2, s3, s4],
+                &SubnetFilter {
+                    min_free_canister_slots: Some(85),
+                    ..Default::default()
+                },
+                &subnet_metadata,
+            ),
+            vec![s3, s2]
+        );
+    }
+}
@@ -1,10 +1,11 @@
-use candid::{Decode, Encode};
+use candid::{Decode, Encode, Nat};
 use canister_test::Canister;
 use cycles_minting_canister::{
-    ChangeSubnetTypeAssignmentArgs, CreateCanister, CreateCanisterError,
-    IcpXdrConversionRateCertifiedResponse, NotifyCreateCanister, NotifyError, SubnetListWithType,
+    ChangeSubnetTypeAssignmentArgs, CreateCanister, CreateCanisterError, CyclesCanisterInitPayload,
+    CyclesLedgerDepositResult, IcpXdrConversionRateCertifiedResponse, NotifyCreateCanister,
+    NotifyError, NotifyMintCyclesArg, NotifyMintCyclesResult, SubnetListWithType,
     SubnetTypesToSubnetsResponse, UpdateSubnetTypeArgs, BAD_REQUEST_CYCLES_PENALTY,
-    MEMO_CREATE_CANISTER, MEMO_TOP_UP_CANISTER,
+    MEMO_CREATE_CANISTER, MEMO_MINT_CYCLES, MEMO_TOP_UP_CANISTER, MINT_CYCLES_REFUND_FEE,
 };
 use dfn_candid::candid_one;
 use dfn_protobuf::protobuf;
@@ -20,27 +21,28 @@ use ic_nervous_system_common_test_keys::{
 };
 use ic_nns_common::types::{NeuronId, ProposalId, UpdateIcpXdrConversionRatePayload};
 use ic_nns_constants::{
-    CYCLES_MINTING_CANISTER_ID, GOVERNANCE_CANISTER_ID, LEDGER_CANISTER_INDEX_IN_NNS_SUBNET,
+    CYCLES_MINTING_CANISTER_ID, GOVERNANCE_CANISTER_ID, LEDGER_CANISTER_ID,
+    LEDGER_CANISTER_INDEX_IN_NNS_SUBNET,
 };
 use ic_nns_governance::pb::v1::{NnsFunction, ProposalStatus};
 use ic_nns_test_utils::{
-    common::NnsInitPayloadsBuilder,
+    common::{build_cmc_wasm, NnsInitPayloadsBuilder},
     governance::{submit_external_update_proposal, wait_for_final_state},
     ids::TEST_NEURON_1_ID,
     itest_helpers::{local_test_on_nns_subnet, NnsCanisters},
     neuron_helpers::get_neuron_1,
     state_test_helpers::{
-        cmc_set_default_authorized_subnetworks, set_up_universal_canister, setup_nns_canisters,
-        update_with_sender,
+        cmc_set_default_authorized_subnetworks, ledger_account_balance, set_up_universal_canister,
+        setup_nns_canisters, update_with_sender,
     },
 };
 use ic_state_machine_tests::{StateMachine, WasmResult};
 use ic_test_utilities::universal_canister::{call_args, wasm};
 use ic_types_test_utils::ids::subnet_test_id;
 use icp_ledger::{
-    tokens_from_proto, AccountBalanceArgs, AccountIdentifier, BlockIndex, CyclesResponse, Memo,
-    NotifyCanisterArgs, SendArgs, Subaccount, Tokens, TransferArgs, TransferError,
-    DEFAULT_TRANSFER_FEE,
+    tokens_from_proto, AccountBalanceArgs, AccountIdentifier, BinaryAccountBalanceArgs, BlockIndex,
+    CyclesResponse, Memo, NotifyCanisterArgs, SendArgs, Subaccount, Tokens, TransferArgs,
+    TransferError, DEFAULT_TRANSFER_FEE,
 };
 
 /// Test that the CMC's `icp_xdr_conversion_rate` can be updated via Governance
@@ -878,3 +880,187 @@ fn test_change_subnet_type_assignment() {
         Ok(())
     });
 }
+
+const MOCK_DEPOSIT_BLOCK_INDEX: u64 = 17;
+const MOCK_DEPOSIT_BALANCE: u64 = 123_456_789;
+
+/// Returns a minimal cycles ledger whose `deposit` method accepts all attached
+/// cycles and replies with `MOCK_DEPOSIT_BLOCK_INDEX` and `MOCK_DEPOSIT_BALANCE`.
+fn mock_cycles_ledger_wat() -> String {
+    let reply = Encode!(&CyclesLedgerDepositResult {
+        block_index: Nat::from(MOCK_DEPOSIT_BLOCK_INDEX),
+        balance: Nat::from(MOCK_DEPOSIT_BALANCE),
+    })
+    .unwrap();
+    let data: String = reply.iter().map(|b| format!("\\{:02x}", b)).collect();
+    format!(
+        r#"(module
+              (import "ic0" "msg_cycles_accept128"
+                (func $msg_cycles_accept128 (param i64 i64 i32)))
+              (import "ic0" "msg_reply_data_append"
+                (func $msg_reply_data_append (param i32 i32)))
+              (import "ic0" "msg_reply" (func $msg_reply))
+              (memory 1)
+              (data (i32.const 16) "{data}")
+              (func $deposit
+                (call $msg_cycles_accept128 (i64.const -1) (i64.const -1) (i32.const 0))
+                (call $msg_reply_data_append (i32.const 16) (i32.const {len}))
+                (call $msg_reply))
+              (export "canister_update deposit" (func $deposit)))"#,
+        data = data,
+        len = reply.len(),
+    )
+}
+
+/// Sets up the NNS with 100 ICP on `TEST_USER1_PRINCIPAL`s Ledger account and
+/// points the CMC to a cycles ledger running the given WAT.
+fn setup_cmc_with_cycles_ledger(cycles_ledger_wat: &str) -> (StateMachine, CanisterId) {
+    let account = AccountIdentifier::new(*TEST_USER1_PRINCIPAL, None);
+    let state_machine = StateMachine::new();
+    let nns_init_payloads = NnsInitPayloadsBuilder::new()
+        .with_ledger_account(account, Tokens::new(100, 0).unwrap())
+        .build();
+    setup_nns_canisters(&state_machine, nns_init_payloads);
+
+    let cycles_ledger = state_machine.install_canister_wat(cycles_ledger_wat, vec![], None);
+    state_machine
+        .upgrade_canister(
+            CYCLES_MINTING_CANISTER_ID,
+            build_cmc_wasm().bytes(),
+            Encode!(&Some(CyclesCanisterInitPayload {
+                ledger_canister_id: None,
+                governance_canister_id: None,
+                minting_account_id: None,
+                last_purged_notification: None,
+                exchange_rate_canister: None,
+                cycles_ledger_canister_id: Some(cycles_ledger),
+            }))
+            .unwrap(),
+        )
+        .expect("failed to upgrade the CMC");
+
+    (state_machine, cycles_ledger)
+}
+
+/// Sends 10 ICP from `TEST_USER1_PRINCIPAL`s Ledger account to its subaccount
+/// of the CMC with the mint cycles memo.
+fn send_mint_cycles_transfer(state_machine: &StateMachine) -> BlockIndex {
+    let transfer_args = TransferArgs {
+        memo: MEMO_MINT_CYCLES,
+        amount: Tokens::new(10, 0).unwrap(),
+        fee: DEFAULT_TRANSFER_FEE,
+        from_subaccount: None,
+        to: AccountIdentifier::new(
+            CYCLES_MINTING_CANISTER_ID.get(),
+            Some(Subaccount::from(&TEST_USER1_PRINCIPAL.clone())),
+        )
+        .to_address(),
+        created_at_time: None,
+    };
+    send_transfer(state_machine, &transfer_args).expect("transfer failed")
+}
+
+fn notify_mint_cycles(
+    state_machine: &StateMachine,
+    block_index: BlockIndex,
+) -> NotifyMintCyclesResult {
+    let notify_args = NotifyMintCyclesArg {
+        block_index,
+        to_subaccount: None,
+        deposit_memo: None,
+    };
+
+    if let WasmResult::Reply(res) = state_machine
+        .execute_ingress_as(
+            *TEST_USER1_PRINCIPAL,
+            CYCLES_MINTING_CANISTER_ID,
+            "notify_mint_cycles",
+            Encode!(&notify_args).unwrap(),
+        )
+        .unwrap()
+    {
+        Decode!(&res, NotifyMintCyclesResult).unwrap()
+    } else {
+        panic!("notify rejected")
+    }
+}
+
+fn user1_balance(state_machine: &mut StateMachine) -> Tokens {
+    ledger_account_balance(
+        state_machine,
+        LEDGER_CANISTER_ID,
+        &BinaryAccountBalanceArgs {
+            account: AccountIdentifier::new(*TEST_USER1_PRINCIPAL, None).to_address(),
+        },
+    )
+}
+
+/// Test that notify_mint_cycles deposits the minted cycles to the cycles
+/// ledger and that a repeated notification returns the same result without
+/// minting again.
+#[test]
+fn test_cmc_notify_mint_cycles() {
+    let (mut state_machine, cycles_ledger) =
+        setup_cmc_with_cycles_ledger(&mock_cycles_ledger_wat());
+    let initial_cycles = state_machine.cycle_balance(cycles_ledger);
+
+    let block_index = send_mint_cycles_transfer(&state_machine);
+    let success =
+        notify_mint_cycles(&state_machine, block_index).expect("notify_mint_cycles failed");
+
+    assert_eq!(success.block_index, Nat::from(MOCK_DEPOSIT_BLOCK_INDEX));
+    assert_eq!(success.balance, Nat::from(MOCK_DEPOSIT_BALANCE));
+    assert!(success.minted > Nat::from(0_u64));
+    let deposited = state_machine.cycle_balance(cycles_ledger) - initial_cycles;
+    assert_eq!(success.minted, Nat::from(deposited));
+
+    // The payment was burned rather than refunded.
+    let expected_balance = Tokens::new(100, 0)
+        .unwrap()
+        .checked_sub(&Tokens::new(10, 0).unwrap())
+        .and_then(|balance| balance.checked_sub(&DEFAULT_TRANSFER_FEE))
+        .unwrap();
+    assert_eq!(user1_balance(&mut state_machine), expected_balance);
+
+    // A duplicate notification returns the cached result and mints nothing.
+    assert_eq!(notify_mint_cycles(&state_machine, block_index), Ok(success));
+    assert_eq!(
+        state_machine.cycle_balance(cycles_ledger) - initial_cycles,
+        deposited
+    );
+    assert_eq!(user1_balance(&mut state_machine), expected_balance);
+}
+
+/// Test that notify_mint_cycles refunds the payment minus the
+/// `MINT_CYCLES_REFUND_FEE` if the deposit to the cycles ledger fails.
+#[test]
+fn test_cmc_notify_mint_cycles_refunds_when_deposit_fails() {
+    // A cycles ledger without a `deposit` method rejects every deposit.
+    let (mut state_machine, cycles_ledger) = setup_cmc_with_cycles_ledger("(module)");
+    let initial_cycles = state_machine.cycle_balance(cycles_ledger);
+
+    let block_index = send_mint_cycles_transfer(&state_machine);
+    let result = notify_mint_cycles(&state_machine, block_index);
+
+    match &result {
+        Err(NotifyError::Refunded {
+            block_index: Some(_),
+            ..
+        }) => (),
+        other => panic!("expected a refund, got {:?}", other),
+    }
+    assert_eq!(state_machine.cycle_balance(cycles_ledger), initial_cycles);
+
+    // The user paid the transfer fee twice (payment and refund) and the refund fee.
+    let expected_balance = Tokens::new(100, 0)
+        .unwrap()
+        .checked_sub(&DEFAULT_TRANSFER_FEE)
+        .and_then(|balance| balance.checked_sub(&DEFAULT_TRANSFER_FEE))
+        .and_then(|balance| balance.checked_sub(&MINT_CYCLES_REFUND_FEE))
+        .unwrap();
+    assert_eq!(user1_balance(&mut state_machine), expected_balance);
+
+    // A duplicate notification returns the same refund instead of refunding again.
+    assert_eq!(notify_mint_cycles(&state_machine, block_index), result);
+    assert_eq!(user1_balance(&mut state_machine), expected_balance);
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use ic_quic_transport::Transport;
+use ic_types::{artifact::ArtifactKind, NodeId};
+
+/// Possible errors when assembling an artifact received from a peer.
+#[derive(Debug)]
+pub enum AssembleError {
+    /// The received bytes could not be deserialized.
+    Deserialization(String),
+    /// Parts that were stripped from the artifact are neither available
+    /// locally nor could they be fetched from the peer.
+    MissingParts(String),
+    /// The assembled artifact does not match the artifact the peer stripped.
+    Mismatch(String),
+}
+
+/// Converts artifacts between the form in which they are kept in the pool and
+/// a stripped form in which they are sent to peers that request them.
+///
+/// This allows a client to leave out parts of an artifact that peers most
+/// likely already have, e.g. the ingress messages of a block proposal, and to
+/// reconstruct the artifact on the receiving side.
+///
+/// Stripped artifacts are served by a separate `rpc_stripped` endpoint. The
+/// `rpc` endpoint keeps serving the full artifacts in the format that nodes
+/// without an assembler expect, and a node falls back to it if a peer does not
+/// serve the stripped endpoint or the stripped artifact cannot be assembled.
+#[async_trait]
+pub trait ArtifactAssembler<Artifact: ArtifactKind>: Send + Sync {
+    /// Serializes the stripped form of `message` to be sent to a peer.
+    fn disassemble_message(&self, message: Artifact::Message) -> Bytes;
+
+    /// Reconstructs a message that `peer` serialized with
+    /// [`ArtifactAssembler::disassemble_message`]. Parts that are not
+    /// available locally are fetched from `peer` through `transport`.
+    async fn assemble_message(
+        &self,
+        bytes: Bytes,
+        peer: NodeId,
+        transport: &dyn Transport,
+    ) -> Result<Artifact::Message, AssembleError>;
+}
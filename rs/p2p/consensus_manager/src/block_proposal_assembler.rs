@@ -0,0 +1,570 @@
+//! Sends block proposals to peers without the ingress messages they contain.
+//!
+//! Most ingress messages included in a block proposal were gossiped to all
+//! nodes of the subnet before the block maker included them, so a peer can
+//! usually reassemble a proposal from its own ingress pool. Only the ids of
+//! the ingress messages are sent along with the stripped proposal, and
+//! messages missing from the local ingress pool are fetched from the peer that
+//! sent the proposal. If a proposal cannot be reassembled, the full proposal
+//! is requested instead, see [`ArtifactAssembler`].
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::{
+    assembler::{ArtifactAssembler, AssembleError},
+    receiver::build_rpc_handler_request,
+};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use bytes::Bytes;
+use futures::future::try_join_all;
+use ic_interfaces::ingress_pool::IngressPool;
+use ic_metrics::MetricsRegistry;
+use ic_quic_transport::Transport;
+use ic_types::{
+    artifact::{ArtifactKind, IngressMessageId},
+    artifact_kind::{ConsensusArtifact, IngressArtifact},
+    batch::IngressPayload,
+    consensus::{hashed::Hashed, Block, BlockPayload, BlockProposal, ConsensusMessage, Payload},
+    crypto::{crypto_hash, Signed},
+    messages::SignedIngress,
+    NodeId,
+};
+use prometheus::IntCounterVec;
+use serde::{Deserialize, Serialize};
+
+/// Time after which fetching the ingress messages of a stripped block proposal
+/// from a peer is given up.
+const INGRESS_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SOURCE_LABEL: &str = "source";
+const SOURCE_POOL: &str = "pool";
+const SOURCE_PEER: &str = "peer";
+
+/// The representation in which consensus artifacts are sent to peers that
+/// request stripped artifacts.
+#[derive(Deserialize, Serialize)]
+#[allow(clippy::large_enum_variant)]
+enum MaybeStrippedConsensusMessage {
+    StrippedBlockProposal(StrippedBlockProposal),
+    Unstripped(ConsensusMessage),
+}
+
+/// A block proposal whose ingress payload was replaced by the ids of the
+/// ingress messages it contains.
+#[derive(Deserialize, Serialize)]
+struct StrippedBlockProposal {
+    /// The block proposal with an empty ingress payload. The block and payload
+    /// hashes are those of the original block proposal.
+    block_proposal_without_ingress: BlockProposal,
+    /// The ids of the removed ingress messages, in payload order.
+    ingress_message_ids: Vec<IngressMessageId>,
+}
+
+/// An [`ArtifactAssembler`] for consensus artifacts that strips the ingress
+/// messages from block proposals and reassembles them from the local ingress
+/// pool.
+pub struct BlockProposalAssembler {
+    ingress_pool: Arc<RwLock<dyn IngressPool + Send + Sync>>,
+    ingress_messages_total: IntCounterVec,
+}
+
+impl BlockProposalAssembler {
+    pub fn new(
+        ingress_pool: Arc<RwLock<dyn IngressPool + Send + Sync>>,
+        metrics_registry: &MetricsRegistry,
+    ) -> Self {
+        Self {
+            ingress_pool,
+            ingress_messages_total: metrics_registry.int_counter_vec(
+                "ic_consensus_manager_block_proposal_ingress_messages_total",
+                "Ingress messages of received stripped block proposals, by where they were found.",
+                &[SOURCE_LABEL],
+            ),
+        }
+    }
+
+    /// Looks up the given ingress messages in the local ingress pool. The pool
+    /// is read on a blocking thread because the ingress manager may hold the
+    /// write lock for a long time.
+    async fn get_from_pool(
+        &self,
+        ids: Vec<IngressMessageId>,
+    ) -> Result<Vec<Option<SignedIngress>>, AssembleError> {
+        let ingress_pool = self.ingress_pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let pool = ingress_pool.read().unwrap();
+            ids.iter()
+                .map(|id| {
+                    pool.validated()
+                        .get(id)
+                        .map(|artifact| artifact.msg.signed_ingress.clone())
+                        .or_else(|| {
+                            pool.unvalidated()
+                                .get(id)
+                                .map(|artifact| artifact.message.signed_ingress.clone())
+                        })
+                })
+                .collect()
+        })
+        .await
+        .map_err(|err| {
+            AssembleError::MissingParts(format!("Failed to read the ingress pool: {}", err))
+        })
+    }
+
+    async fn assemble_block_proposal(
+        &self,
+        stripped: StrippedBlockProposal,
+        peer: NodeId,
+        transport: &dyn Transport,
+    ) -> Result<BlockProposal, AssembleError> {
+        let StrippedBlockProposal {
+            block_proposal_without_ingress,
+            ingress_message_ids,
+        } = stripped;
+
+        let mut ingress_messages = self.get_from_pool(ingress_message_ids.clone()).await?;
+        let missing: Vec<usize> = ingress_messages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, message)| message.is_none().then_some(i))
+            .collect();
+        self.ingress_messages_total
+            .with_label_values(&[SOURCE_POOL])
+            .inc_by((ingress_message_ids.len() - missing.len()) as u64);
+
+        if !missing.is_empty() {
+            let fetched = tokio::time::timeout(
+                INGRESS_FETCH_TIMEOUT,
+                try_join_all(
+                    missing
+                        .iter()
+                        .map(|i| fetch_ingress_message(&ingress_message_ids[*i], peer, transport)),
+                ),
+            )
+            .await
+            .map_err(|_| {
+                AssembleError::MissingParts(format!(
+                    "Timed out fetching {} ingress messages from {}",
+                    missing.len(),
+                    peer
+                ))
+            })??;
+            self.ingress_messages_total
+                .with_label_values(&[SOURCE_PEER])
+                .inc_by(fetched.len() as u64);
+            for (i, message) in missing.into_iter().zip(fetched) {
+                ingress_messages[i] = Some(message);
+            }
+        }
+
+        let Signed { content, signature } = block_proposal_without_ingress;
+        let (block_hash, block) = content.decompose();
+        let mut block_payload = block.payload.as_ref().clone();
+        match &mut block_payload {
+            BlockPayload::Data(data) => {
+                data.batch.ingress =
+                    IngressPayload::from(ingress_messages.into_iter().flatten().collect::<Vec<_>>())
+            }
+            BlockPayload::Summary(_) => {
+                return Err(AssembleError::Mismatch(
+                    "Stripped block proposal has a summary payload".to_string(),
+                ))
+            }
+        }
+
+        // The payload hash is covered by the block hash the proposal is signed
+        // for, so the reassembled payload must match it.
+        let payload = Payload::new(crypto_hash, block_payload);
+        if payload.get_hash() != block.payload.get_hash() {
+            return Err(AssembleError::Mismatch(format!(
+                "Payload of block proposal {:?} does not match its hash after reassembly",
+                block_hash
+            )));
+        }
+
+        Ok(Signed {
+            content: Hashed::recompose(block_hash, Block { payload, ..block }),
+            signature,
+        })
+    }
+}
+
+#[async_trait]
+impl ArtifactAssembler<ConsensusArtifact> for BlockProposalAssembler {
+    fn disassemble_message(&self, message: ConsensusMessage) -> Bytes {
+        let message = match message {
+            ConsensusMessage::BlockProposal(proposal) => match strip_block_proposal(&proposal) {
+                Some(stripped) => MaybeStrippedConsensusMessage::StrippedBlockProposal(stripped),
+                None => MaybeStrippedConsensusMessage::Unstripped(ConsensusMessage::BlockProposal(
+                    proposal,
+                )),
+            },
+            message => MaybeStrippedConsensusMessage::Unstripped(message),
+        };
+        Bytes::from(bincode::serialize(&message).expect("Serializing consensus message"))
+    }
+
+    async fn assemble_message(
+        &self,
+        bytes: Bytes,
+        peer: NodeId,
+        transport: &dyn Transport,
+    ) -> Result<ConsensusMessage, AssembleError> {
+        let message: MaybeStrippedConsensusMessage = bincode::deserialize(&bytes)
+            .map_err(|err| AssembleError::Deserialization(err.to_string()))?;
+        match message {
+            MaybeStrippedConsensusMessage::StrippedBlockProposal(stripped) => self
+                .assemble_block_proposal(stripped, peer, transport)
+                .await
+                .map(ConsensusMessage::BlockProposal),
+            MaybeStrippedConsensusMessage::Unstripped(message) => Ok(message),
+        }
+    }
+}
+
+/// Returns a copy of the given block proposal without its ingress messages, or
+/// `None` if it contains no ingress messages.
+fn strip_block_proposal(proposal: &BlockProposal) -> Option<StrippedBlockProposal> {
+    let block = proposal.content.as_ref();
+    if block.payload.is_summary() {
+        return None;
+    }
+    let data = block.payload.as_ref().as_data();
+    if data.batch.ingress.is_empty() {
+        return None;
+    }
+
+    let ingress_message_ids = data.batch.ingress.message_ids();
+    let mut data_without_ingress = data.clone();
+    data_without_ingress.batch.ingress = IngressPayload::default();
+    let block_without_ingress = Block {
+        payload: Payload::new_with(
+            block.payload.get_hash().clone(),
+            block.payload.payload_type(),
+            Box::new(move || BlockPayload::Data(data_without_ingress)),
+        ),
+        ..block.clone()
+    };
+
+    Some(StrippedBlockProposal {
+        block_proposal_without_ingress: Signed {
+            content: Hashed::recompose(proposal.content.get_hash().clone(), block_without_ingress),
+            signature: proposal.signature.clone(),
+        },
+        ingress_message_ids,
+    })
+}
+
+/// Fetches an ingress message from the validated ingress pool of `peer`.
+async fn fetch_ingress_message(
+    id: &IngressMessageId,
+    peer: NodeId,
+    transport: &dyn Transport,
+) -> Result<SignedIngress, AssembleError> {
+    let request = build_rpc_handler_request(IngressArtifact::TAG.into(), id, false);
+    let response = transport.rpc(&peer, request).await.map_err(|err| {
+        AssembleError::MissingParts(format!(
+            "Failed to fetch ingress message {} from {}: {:?}",
+            id, peer, err
+        ))
+    })?;
+    if response.status() != StatusCode::OK {
+        return Err(AssembleError::MissingParts(format!(
+            "Peer {} does not have ingress message {}",
+            peer, id
+        )));
+    }
+
+    let message: SignedIngress = bincode::deserialize(response.body())
+        .map_err(|err| AssembleError::Deserialization(err.to_string()))?;
+    if IngressMessageId::from(&message) != *id {
+        return Err(AssembleError::Mismatch(format!(
+            "Peer {} sent a different ingress message than {}",
+            peer, id
+        )));
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        metrics::ConsensusManagerMetrics,
+        peer_limits::PeerBandwidthLimiter,
+        receiver::{ConsensusManagerReceiver, DownloadResult},
+        AdvertUpdate,
+    };
+    use axum::http::Response;
+    use ic_artifact_pool::{consensus_pool::ConsensusPoolImpl, ingress_pool::IngressPoolImpl};
+    use ic_interfaces::{artifact_pool::MutablePool, artifact_pool::UnvalidatedArtifact};
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_p2p_test_utils::mocks::MockTransport;
+    use ic_quic_transport::{ConnId, SendError};
+    use ic_test_utilities::{
+        artifact_pool_config::with_test_pool_config,
+        consensus::fake::FakeContentSigner,
+        types::{ids::node_test_id, messages::SignedIngressBuilder},
+    };
+    use ic_types::{
+        artifact::{ConsensusMessageId, Priority, PriorityFn},
+        batch::{BatchPayload, ValidationContext},
+        consensus::{dkg, ConsensusMessageAttribute, Rank},
+        crypto::{CryptoHash, CryptoHashOf},
+        time::UNIX_EPOCH,
+        Height, RegistryVersion,
+    };
+    use std::collections::HashSet;
+    use tokio::sync::watch;
+
+    fn ingress_messages(count: u64) -> Vec<SignedIngress> {
+        (0..count)
+            .map(|nonce| SignedIngressBuilder::new().nonce(nonce).build())
+            .collect()
+    }
+
+    fn block_proposal(ingress_messages: Vec<SignedIngress>) -> BlockProposal {
+        let block = Block::new(
+            CryptoHashOf::from(CryptoHash(vec![])),
+            Payload::new(
+                crypto_hash,
+                (
+                    BatchPayload {
+                        ingress: IngressPayload::from(ingress_messages),
+                        ..BatchPayload::default()
+                    },
+                    dkg::Dealings::new_empty(Height::from(0)),
+                    None,
+                )
+                    .into(),
+            ),
+            Height::from(1),
+            Rank(0),
+            ValidationContext {
+                certified_height: Height::from(0),
+                registry_version: RegistryVersion::from(1),
+                time: UNIX_EPOCH,
+            },
+        );
+        BlockProposal::fake(block, node_test_id(1))
+    }
+
+    fn assembler_with_pool(
+        pool_config: ic_config::artifact_pool::ArtifactPoolConfig,
+        messages_in_pool: Vec<SignedIngress>,
+    ) -> BlockProposalAssembler {
+        let mut ingress_pool = IngressPoolImpl::new(
+            node_test_id(0),
+            pool_config,
+            MetricsRegistry::new(),
+            no_op_logger(),
+        );
+        for message in messages_in_pool {
+            ingress_pool.insert(UnvalidatedArtifact {
+                message,
+                peer_id: node_test_id(1),
+                timestamp: UNIX_EPOCH,
+            });
+        }
+        BlockProposalAssembler::new(Arc::new(RwLock::new(ingress_pool)), &MetricsRegistry::new())
+    }
+
+    fn assert_same_proposal(assembled: ConsensusMessage, proposal: &BlockProposal) {
+        match assembled {
+            ConsensusMessage::BlockProposal(assembled) => {
+                assert_eq!(assembled.content.get_hash(), proposal.content.get_hash());
+                assert_eq!(
+                    assembled.content.as_ref().payload.as_ref(),
+                    proposal.content.as_ref().payload.as_ref()
+                );
+            }
+            other => panic!("Expected a block proposal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn block_proposal_is_assembled_from_local_ingress_pool() {
+        with_test_pool_config(|pool_config| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let messages = ingress_messages(3);
+            let proposal = block_proposal(messages.clone());
+            let assembler = assembler_with_pool(pool_config, messages);
+
+            let bytes =
+                assembler.disassemble_message(ConsensusMessage::BlockProposal(proposal.clone()));
+            assert!(bytes.len() < full_proposal_bytes(&proposal).len());
+
+            // All ingress messages are in the local pool, so the peer is not contacted.
+            let transport = MockTransport::new();
+            let assembled = rt
+                .block_on(assembler.assemble_message(bytes, node_test_id(1), &transport))
+                .unwrap();
+            assert_same_proposal(assembled, &proposal);
+        });
+    }
+
+    #[test]
+    fn missing_ingress_messages_are_fetched_from_peer() {
+        with_test_pool_config(|pool_config| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let messages = ingress_messages(3);
+            let proposal = block_proposal(messages.clone());
+            let assembler = assembler_with_pool(pool_config, messages[..1].to_vec());
+
+            let mut transport = MockTransport::new();
+            transport
+                .expect_rpc()
+                .times(2)
+                .returning(move |_, request| {
+                    let id: IngressMessageId = bincode::deserialize(request.body()).unwrap();
+                    let message = messages
+                        .iter()
+                        .find(|m| IngressMessageId::from(*m) == id)
+                        .unwrap();
+                    Ok(axum::http::Response::new(Bytes::from(
+                        bincode::serialize(message).unwrap(),
+                    )))
+                });
+
+            let bytes =
+                assembler.disassemble_message(ConsensusMessage::BlockProposal(proposal.clone()));
+            let assembled = rt
+                .block_on(assembler.assemble_message(bytes, node_test_id(1), &transport))
+                .unwrap();
+            assert_same_proposal(assembled, &proposal);
+        });
+    }
+
+    #[test]
+    fn assembly_fails_if_peer_cannot_provide_ingress_messages() {
+        with_test_pool_config(|pool_config| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let proposal = block_proposal(ingress_messages(2));
+            let assembler = assembler_with_pool(pool_config, vec![]);
+
+            let mut transport = MockTransport::new();
+            transport.expect_rpc().returning(|_, _| {
+                Err(SendError::ConnectionNotFound {
+                    reason: "test".to_string(),
+                })
+            });
+
+            let bytes =
+                assembler.disassemble_message(ConsensusMessage::BlockProposal(proposal.clone()));
+            let result =
+                rt.block_on(assembler.assemble_message(bytes, node_test_id(1), &transport));
+            assert!(matches!(result, Err(AssembleError::MissingParts(_))));
+        });
+    }
+
+    /// Downloads `proposal` from a single peer with the consensus manager
+    /// receiver, using `transport` to talk to the peer.
+    fn download_proposal(
+        rt: &tokio::runtime::Runtime,
+        assembler: BlockProposalAssembler,
+        proposal: &BlockProposal,
+        transport: MockTransport,
+    ) -> ConsensusMessage {
+        let advert = ConsensusArtifact::message_to_advert(&ConsensusMessage::BlockProposal(
+            proposal.clone(),
+        ));
+        let (_peers_tx, mut peers_rx) = watch::channel(HashSet::from([node_test_id(1)]));
+        let (_priority_fn_tx, priority_fn_rx) = watch::channel(Box::new(
+            |_: &ConsensusMessageId, _: &ConsensusMessageAttribute| Priority::FetchNow,
+        ) as PriorityFn<_, _>);
+        let result = rt.block_on(ConsensusManagerReceiver::<
+            ConsensusArtifact,
+            ConsensusPoolImpl,
+            (AdvertUpdate<ConsensusArtifact>, NodeId, ConnId),
+        >::download_artifact(
+            &advert.id,
+            &advert.attribute,
+            None,
+            &mut peers_rx,
+            priority_fn_rx,
+            Some(Arc::new(assembler)),
+            Arc::new(PeerBandwidthLimiter::new(None)),
+            Arc::new(transport),
+            ConsensusManagerMetrics::new::<ConsensusArtifact>(&MetricsRegistry::new()),
+        ));
+        match result {
+            DownloadResult::Completed(message, peer) => {
+                assert_eq!(peer, node_test_id(1));
+                message
+            }
+            _ => panic!("Expected the download of the block proposal to complete"),
+        }
+    }
+
+    fn full_proposal_bytes(proposal: &BlockProposal) -> Bytes {
+        Bytes::from(bincode::serialize(&ConsensusMessage::BlockProposal(proposal.clone())).unwrap())
+    }
+
+    fn response(status: StatusCode, body: Bytes) -> Response<Bytes> {
+        Response::builder().status(status).body(body).unwrap()
+    }
+
+    #[test]
+    fn receiver_falls_back_to_full_proposal_if_ingress_is_missing() {
+        with_test_pool_config(|pool_config| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let proposal = block_proposal(ingress_messages(2));
+            // Neither this node nor the peer have the ingress messages.
+            let assembler = assembler_with_pool(pool_config, vec![]);
+            let stripped_bytes =
+                assembler.disassemble_message(ConsensusMessage::BlockProposal(proposal.clone()));
+            let full_bytes = full_proposal_bytes(&proposal);
+
+            let mut transport = MockTransport::new();
+            transport
+                .expect_rpc()
+                .withf(|_, request| request.uri().path() == "/consensus/rpc_stripped")
+                .times(1)
+                .returning(move |_, _| Ok(response(StatusCode::OK, stripped_bytes.clone())));
+            transport
+                .expect_rpc()
+                .withf(|_, request| request.uri().path() == "/ingress/rpc")
+                .times(1..=2)
+                .returning(|_, _| Ok(response(StatusCode::NO_CONTENT, Bytes::new())));
+            transport
+                .expect_rpc()
+                .withf(|_, request| request.uri().path() == "/consensus/rpc")
+                .times(1)
+                .returning(move |_, _| Ok(response(StatusCode::OK, full_bytes.clone())));
+
+            let downloaded = download_proposal(&rt, assembler, &proposal, transport);
+            assert_same_proposal(downloaded, &proposal);
+        });
+    }
+
+    #[test]
+    fn receiver_falls_back_to_full_proposal_if_peer_does_not_serve_stripped_proposals() {
+        with_test_pool_config(|pool_config| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let messages = ingress_messages(2);
+            let proposal = block_proposal(messages.clone());
+            let assembler = assembler_with_pool(pool_config, messages);
+            let full_bytes = full_proposal_bytes(&proposal);
+
+            // The peer predates the stripped endpoint.
+            let mut transport = MockTransport::new();
+            transport
+                .expect_rpc()
+                .withf(|_, request| request.uri().path() == "/consensus/rpc_stripped")
+                .times(1)
+                .returning(|_, _| Ok(response(StatusCode::NOT_FOUND, Bytes::new())));
+            transport
+                .expect_rpc()
+                .withf(|_, request| request.uri().path() == "/consensus/rpc")
+                .times(1)
+                .returning(move |_, _| Ok(response(StatusCode::OK, full_bytes.clone())));
+
+            let downloaded = download_proposal(&rt, assembler, &proposal, transport);
+            assert_same_proposal(downloaded, &proposal);
+        });
+    }
+}
@@ -4,6 +4,7 @@ use std::{
 };
 
 use crate::metrics::ConsensusManagerMetrics;
+pub use assembler::{ArtifactAssembler, AssembleError};
 use axum::Router;
 use crossbeam_channel::Sender as CrossbeamSender;
 use ic_interfaces::{
@@ -26,10 +27,16 @@ use tokio::{
     sync::{mpsc::Receiver, watch},
 };
 
+mod assembler;
+mod block_proposal_assembler;
 mod metrics;
+mod peer_limits;
 mod receiver;
 mod sender;
 
+pub use block_proposal_assembler::BlockProposalAssembler;
+pub use peer_limits::PeerLimits;
+
 type StartConsensusManagerFn<'a> =
     Box<dyn FnOnce(Arc<dyn Transport>, watch::Receiver<SubnetTopology>) + 'a>;
 
@@ -70,7 +77,90 @@ impl<'r> ConsensusManagerBuilder<'r> {
         <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
         <Artifact as ArtifactKind>::Attribute: Serialize + for<'a> Deserialize<'a> + Send + Sync,
     {
-        let (router, adverts_from_peers_rx) = build_axum_router(self.log.clone(), raw_pool.clone());
+        self.add_client_impl(
+            adverts_to_send,
+            raw_pool,
+            priority_fn_producer,
+            sender,
+            None,
+            PeerLimits::default(),
+        )
+    }
+
+    /// Like [`ConsensusManagerBuilder::add_client`], but artifacts are
+    /// requested from peers in the stripped form produced by `assembler`
+    /// first, see [`ArtifactAssembler`].
+    pub fn add_client_with_assembler<Artifact, Pool>(
+        &mut self,
+        adverts_to_send: Receiver<ArtifactProcessorEvent<Artifact>>,
+        raw_pool: Arc<RwLock<Pool>>,
+        priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
+        sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+        assembler: Arc<dyn ArtifactAssembler<Artifact>>,
+    ) where
+        Pool: 'static + Send + Sync + ValidatedPoolReader<Artifact>,
+        Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
+        <Artifact as ArtifactKind>::Id:
+            Serialize + for<'a> Deserialize<'a> + Clone + Eq + Hash + Send + Sync,
+        <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
+        <Artifact as ArtifactKind>::Attribute: Serialize + for<'a> Deserialize<'a> + Send + Sync,
+    {
+        self.add_client_impl(
+            adverts_to_send,
+            raw_pool,
+            priority_fn_producer,
+            sender,
+            Some(assembler),
+            PeerLimits::default(),
+        )
+    }
+
+    /// Like [`ConsensusManagerBuilder::add_client`], but bounds the slots each
+    /// peer can occupy and the rate at which artifacts are downloaded from each
+    /// peer according to `limits`.
+    pub fn add_client_with_limits<Artifact, Pool>(
+        &mut self,
+        adverts_to_send: Receiver<ArtifactProcessorEvent<Artifact>>,
+        raw_pool: Arc<RwLock<Pool>>,
+        priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
+        sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+        limits: PeerLimits,
+    ) where
+        Pool: 'static + Send + Sync + ValidatedPoolReader<Artifact>,
+        Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
+        <Artifact as ArtifactKind>::Id:
+            Serialize + for<'a> Deserialize<'a> + Clone + Eq + Hash + Send + Sync,
+        <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
+        <Artifact as ArtifactKind>::Attribute: Serialize + for<'a> Deserialize<'a> + Send + Sync,
+    {
+        self.add_client_impl(
+            adverts_to_send,
+            raw_pool,
+            priority_fn_producer,
+            sender,
+            None,
+            limits,
+        )
+    }
+
+    fn add_client_impl<Artifact, Pool>(
+        &mut self,
+        adverts_to_send: Receiver<ArtifactProcessorEvent<Artifact>>,
+        raw_pool: Arc<RwLock<Pool>>,
+        priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
+        sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+        assembler: Option<Arc<dyn ArtifactAssembler<Artifact>>>,
+        limits: PeerLimits,
+    ) where
+        Pool: 'static + Send + Sync + ValidatedPoolReader<Artifact>,
+        Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
+        <Artifact as ArtifactKind>::Id:
+            Serialize + for<'a> Deserialize<'a> + Clone + Eq + Hash + Send + Sync,
+        <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
+        <Artifact as ArtifactKind>::Attribute: Serialize + for<'a> Deserialize<'a> + Send + Sync,
+    {
+        let (router, adverts_from_peers_rx) =
+            build_axum_router(self.log.clone(), raw_pool.clone(), assembler.clone());
 
         let log = self.log.clone();
         let rt_handle = self.rt_handle.clone();
@@ -86,6 +176,8 @@ impl<'r> ConsensusManagerBuilder<'r> {
                 raw_pool,
                 priority_fn_producer,
                 sender,
+                assembler,
+                limits,
                 transport,
                 topology_watcher,
             )
@@ -122,6 +214,8 @@ fn start_consensus_manager<Artifact, Pool>(
     raw_pool: Arc<RwLock<Pool>>,
     priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
     sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+    assembler: Option<Arc<dyn ArtifactAssembler<Artifact>>>,
+    limits: PeerLimits,
     transport: Arc<dyn Transport>,
     topology_watcher: watch::Receiver<SubnetTopology>,
 ) where
@@ -151,6 +245,8 @@ fn start_consensus_manager<Artifact, Pool>(
         raw_pool,
         priority_fn_producer,
         sender,
+        assembler,
+        limits,
         transport,
         topology_watcher,
     );
@@ -19,6 +19,8 @@ pub(crate) struct ConsensusManagerMetrics {
     pub download_task_artifact_download_duration: Histogram,
     pub download_task_restart_after_join_total: IntCounter,
     pub download_task_artifact_download_errors_total: IntCounter,
+    pub download_task_artifact_assembly_errors_total: IntCounter,
+    pub download_task_bandwidth_limited_total: IntCounter,
 
     // Slot table
     pub slot_table_updates_total: IntCounter,
@@ -28,6 +30,7 @@ pub(crate) struct ConsensusManagerMetrics {
     pub slot_table_new_entry_total: IntCounterVec,
     pub slot_table_seen_id_total: IntCounter,
     pub slot_table_removals_total: IntCounter,
+    pub slot_table_limit_exceeded_total: IntCounter,
 
     // Topology update
     pub topology_updates_total: IntCounter,
@@ -120,6 +123,22 @@ impl ConsensusManagerMetrics {
                 ))
                 .unwrap(),
             ),
+            download_task_artifact_assembly_errors_total: metrics_registry.register(
+                IntCounter::with_opts(opts!(
+                    "ic_consensus_manager_download_task_artifact_assembly_errors_total",
+                    "Downloaded stripped artifacts that could not be assembled, after which the full artifact is requested.",
+                    const_labels.clone(),
+                ))
+                .unwrap(),
+            ),
+            download_task_bandwidth_limited_total: metrics_registry.register(
+                IntCounter::with_opts(opts!(
+                    "ic_consensus_manager_download_task_bandwidth_limited_total",
+                    "Download attempts delayed because all advertising peers exceeded their bandwidth limit.",
+                    const_labels.clone(),
+                ))
+                .unwrap(),
+            ),
 
             slot_table_updates_total: metrics_registry.register(
                 IntCounter::with_opts(opts!(
@@ -180,6 +199,14 @@ impl ConsensusManagerMetrics {
                 ))
                 .unwrap(),
             ),
+            slot_table_limit_exceeded_total: metrics_registry.register(
+                IntCounter::with_opts(opts!(
+                    "ic_consensus_manager_slot_table_limit_exceeded_total",
+                    "Slot updates ignored because the slot number exceeds the per-peer slot limit.",
+                    const_labels.clone(),
+                ))
+                .unwrap(),
+            ),
 
             topology_updates_total: metrics_registry.register(
                 IntCounter::with_opts(opts!(
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ic_types::NodeId;
+
+/// Limits that a client of the consensus manager imposes on each of its peers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerLimits {
+    /// Adverts that a peer places in a slot with a number at or above this
+    /// limit are ignored. This bounds the number of artifacts a single peer
+    /// can make this node track.
+    pub slot_limit: u64,
+    /// Maximum sustained rate, in bytes per second, at which artifacts are
+    /// downloaded from a single peer. `None` means no limit.
+    pub max_download_bytes_per_sec: Option<u64>,
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        Self {
+            slot_limit: u64::MAX,
+            max_download_bytes_per_sec: None,
+        }
+    }
+}
+
+struct TokenBucket {
+    /// Bytes that may still be downloaded. Becomes negative if a download is
+    /// larger than the remaining budget, delaying the next download.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Tracks the bytes downloaded from each peer and enforces
+/// `PeerLimits::max_download_bytes_per_sec` with a token bucket per peer
+/// that allows bursts of up to one second worth of bytes.
+pub(crate) struct PeerBandwidthLimiter {
+    bytes_per_sec: Option<u64>,
+    buckets: Mutex<HashMap<NodeId, TokenBucket>>,
+}
+
+impl PeerBandwidthLimiter {
+    pub(crate) fn new(bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            bytes_per_sec,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns true if an artifact may currently be downloaded from `peer`.
+    pub(crate) fn has_budget(&self, peer: &NodeId, now: Instant) -> bool {
+        let Some(bytes_per_sec) = self.bytes_per_sec else {
+            return true;
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(peer) {
+            Some(bucket) => {
+                Self::refill(bucket, bytes_per_sec, now);
+                bucket.tokens > 0.0
+            }
+            None => true,
+        }
+    }
+
+    /// Charges `bytes` downloaded from `peer` against its budget.
+    pub(crate) fn consume(&self, peer: NodeId, bytes: usize, now: Instant) {
+        let Some(bytes_per_sec) = self.bytes_per_sec else {
+            return;
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(peer).or_insert_with(|| TokenBucket {
+            tokens: bytes_per_sec as f64,
+            last_refill: now,
+        });
+        Self::refill(bucket, bytes_per_sec, now);
+        bucket.tokens -= bytes as f64;
+    }
+
+    /// Forgets the budget of peers that left the topology.
+    pub(crate) fn retain(&self, mut keep: impl FnMut(&NodeId) -> bool) {
+        self.buckets.lock().unwrap().retain(|peer, _| keep(peer));
+    }
+
+    fn refill(bucket: &mut TokenBucket, bytes_per_sec: u64, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * bytes_per_sec as f64)
+            .min(bytes_per_sec as f64);
+        bucket.last_refill = now;
+    }
+}
+
+/// How long a download waits before checking again whether one of the peers
+/// advertising the artifact is back within its bandwidth limit.
+pub(crate) const BANDWIDTH_LIMIT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types_test_utils::ids::node_test_id;
+
+    #[test]
+    fn unlimited_limiter_always_has_budget() {
+        let limiter = PeerBandwidthLimiter::new(None);
+        let now = Instant::now();
+        limiter.consume(node_test_id(1), 1 << 30, now);
+        assert!(limiter.has_budget(&node_test_id(1), now));
+    }
+
+    #[test]
+    fn peer_regains_budget_over_time() {
+        let limiter = PeerBandwidthLimiter::new(Some(1000));
+        let now = Instant::now();
+        assert!(limiter.has_budget(&node_test_id(1), now));
+
+        // A download larger than the burst exhausts the budget of that peer only.
+        limiter.consume(node_test_id(1), 3000, now);
+        assert!(!limiter.has_budget(&node_test_id(1), now));
+        assert!(limiter.has_budget(&node_test_id(2), now));
+
+        // 2000 bytes are owed, so the peer regains budget after two seconds.
+        assert!(!limiter.has_budget(&node_test_id(1), now + Duration::from_secs(2)));
+        assert!(limiter.has_budget(&node_test_id(1), now + Duration::from_millis(2100)));
+
+        limiter.retain(|peer| *peer != node_test_id(1));
+        assert!(limiter.has_budget(&node_test_id(1), now));
+    }
+}
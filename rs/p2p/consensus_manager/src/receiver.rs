@@ -2,7 +2,7 @@ use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
     hash::Hash,
     sync::{Arc, RwLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -10,7 +10,8 @@ use crate::{
         ConsensusManagerMetrics, DOWNLOAD_TASK_RESULT_ALL_PEERS_DELETED,
         DOWNLOAD_TASK_RESULT_COMPLETED, DOWNLOAD_TASK_RESULT_DROP,
     },
-    AdvertUpdate, CommitId, Data, SlotNumber,
+    peer_limits::{PeerBandwidthLimiter, PeerLimits, BANDWIDTH_LIMIT_RETRY_INTERVAL},
+    AdvertUpdate, ArtifactAssembler, AssembleError, CommitId, Data, SlotNumber,
 };
 use axum::{
     extract::State,
@@ -42,12 +43,15 @@ use tokio::{
 };
 
 type ValidatedPoolReaderRef<T> = Arc<RwLock<dyn ValidatedPoolReader<T> + Send + Sync>>;
+type ArtifactAssemblerRef<T> = Arc<dyn ArtifactAssembler<T>>;
+type RpcHandlerState<T> = (ValidatedPoolReaderRef<T>, Option<ArtifactAssemblerRef<T>>);
 type ReceivedAdvertSender<A> = Sender<(AdvertUpdate<A>, NodeId, ConnId)>;
 
 #[allow(unused)]
 pub fn build_axum_router<Artifact: ArtifactKind>(
     log: ReplicaLogger,
     pool: ValidatedPoolReaderRef<Artifact>,
+    assembler: Option<ArtifactAssemblerRef<Artifact>>,
 ) -> (Router, Receiver<(AdvertUpdate<Artifact>, NodeId, ConnId)>)
 where
     Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
@@ -60,7 +64,11 @@ where
     let endpoint: &'static str = Artifact::TAG.into();
     let router = Router::new()
         .route(&format!("/{}/rpc", endpoint), any(rpc_handler))
-        .with_state(pool)
+        .route(
+            &format!("/{}/rpc_stripped", endpoint),
+            any(rpc_stripped_handler),
+        )
+        .with_state((pool, assembler))
         .route(&format!("/{}/update", endpoint), any(update_handler))
         .with_state(update_tx);
 
@@ -68,7 +76,7 @@ where
 }
 
 async fn rpc_handler<Artifact: ArtifactKind>(
-    State(pool): State<ValidatedPoolReaderRef<Artifact>>,
+    State((pool, _)): State<RpcHandlerState<Artifact>>,
     payload: Bytes,
 ) -> Result<Bytes, StatusCode>
 where
@@ -92,6 +100,35 @@ where
     Ok(bytes)
 }
 
+/// Serves artifacts in the stripped form produced by the [`ArtifactAssembler`]
+/// of the client. Responds with [`StatusCode::NOT_FOUND`] like a node that
+/// predates this endpoint if the client has no assembler.
+async fn rpc_stripped_handler<Artifact: ArtifactKind>(
+    State((pool, assembler)): State<RpcHandlerState<Artifact>>,
+    payload: Bytes,
+) -> Result<Bytes, StatusCode>
+where
+    Artifact: ArtifactKind + Serialize + for<'a> Deserialize<'a> + Send + 'static,
+    <Artifact as ArtifactKind>::Id:
+        Serialize + for<'a> Deserialize<'a> + Clone + Eq + Hash + Send + Sync,
+    <Artifact as ArtifactKind>::Message: Serialize + for<'a> Deserialize<'a> + Send,
+    <Artifact as ArtifactKind>::Attribute: Serialize + for<'a> Deserialize<'a> + Send + Sync,
+{
+    let assembler = assembler.ok_or(StatusCode::NOT_FOUND)?;
+    let id: Artifact::Id = bincode::deserialize(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let jh = tokio::task::spawn_blocking(move || {
+        let msg = pool.read().unwrap().get_validated_by_identifier(&id)?;
+        Some(assembler.disassemble_message(msg))
+    });
+    let bytes = jh
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NO_CONTENT)?;
+
+    Ok(bytes)
+}
+
 async fn update_handler<Artifact: ArtifactKind>(
     State(sender): State<ReceivedAdvertSender<Artifact>>,
     Extension(peer): Extension<NodeId>,
@@ -129,8 +166,11 @@ pub(crate) struct ConsensusManagerReceiver<Artifact: ArtifactKind, Pool, Receive
     priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
     current_priority_fn: watch::Sender<PriorityFn<Artifact::Id, Artifact::Attribute>>,
     sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+    assembler: Option<ArtifactAssemblerRef<Artifact>>,
 
     slot_table: HashMap<NodeId, HashMap<SlotNumber, SlotEntry<Artifact::Id>>>,
+    slot_limit: u64,
+    bandwidth_limiter: Arc<PeerBandwidthLimiter>,
     active_downloads: HashMap<Artifact::Id, watch::Sender<HashSet<NodeId>>>,
 
     #[allow(clippy::type_complexity)]
@@ -164,6 +204,8 @@ where
         raw_pool: Arc<RwLock<Pool>>,
         priority_fn_producer: Arc<dyn PriorityFnAndFilterProducer<Artifact, Pool>>,
         sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+        assembler: Option<ArtifactAssemblerRef<Artifact>>,
+        limits: PeerLimits,
         transport: Arc<dyn Transport>,
         topology_watcher: watch::Receiver<SubnetTopology>,
     ) {
@@ -180,9 +222,14 @@ where
             priority_fn_producer,
             current_priority_fn,
             sender,
+            assembler,
             transport,
             active_downloads: HashMap::new(),
             slot_table: HashMap::new(),
+            slot_limit: limits.slot_limit,
+            bandwidth_limiter: Arc::new(PeerBandwidthLimiter::new(
+                limits.max_download_bytes_per_sec,
+            )),
             artifact_processor_tasks: JoinSet::new(),
             topology_watcher,
         };
@@ -222,6 +269,8 @@ where
                                 peer_rx,
                                 self.current_priority_fn.subscribe(),
                                 self.sender.clone(),
+                                self.assembler.clone(),
+                                self.bandwidth_limiter.clone(),
                                 self.transport.clone(),
                                 self.metrics.clone()
                             ),
@@ -250,6 +299,11 @@ where
             commit_id,
             data,
         } = advert_update;
+        // Bound the number of artifacts a single peer can make this node track.
+        if slot_number.get() >= self.slot_limit {
+            self.metrics.slot_table_limit_exceeded_total.inc();
+            return;
+        }
         let (advert, artifact) = match data {
             Data::Artifact(artifact) => {
                 self.metrics.slot_table_updates_with_artifact_total.inc();
@@ -311,6 +365,8 @@ where
                             rx,
                             self.current_priority_fn.subscribe(),
                             self.sender.clone(),
+                            self.assembler.clone(),
+                            self.bandwidth_limiter.clone(),
                             self.transport.clone(),
                             self.metrics.clone(),
                         ),
@@ -342,13 +398,15 @@ where
     /// The download fails iff:
     /// - The priority function evaluates the advert to [`Priority::Drop`] -> [`DownloadResult::PriorityIsDrop`]
     /// - The set of peers advertising the artifact, `peer_rx`, becomes empty -> [`DownloadResult::AllPeersDeletedTheArtifact`]
-    async fn download_artifact(
+    pub(crate) async fn download_artifact(
         id: &Artifact::Id,
         attr: &Artifact::Attribute,
         // Only first peer for specific artifact ID is considered for push
         mut artifact: Option<(Artifact::Message, NodeId)>,
         mut peer_rx: &mut watch::Receiver<HashSet<NodeId>>,
         mut priority_fn_watcher: watch::Receiver<PriorityFn<Artifact::Id, Artifact::Attribute>>,
+        assembler: Option<ArtifactAssemblerRef<Artifact>>,
+        bandwidth_limiter: Arc<PeerBandwidthLimiter>,
         transport: Arc<dyn Transport>,
         metrics: ConsensusManagerMetrics,
     ) -> DownloadResult<Artifact::Message> {
@@ -389,11 +447,31 @@ where
                     .download_task_artifact_download_duration
                     .start_timer();
                 let mut rng = SmallRng::from_entropy();
-                while let Some(peer) = {
-                    let peer = peer_rx.borrow().iter().choose(&mut rng).copied();
-                    peer
-                } {
-                    let request = build_rpc_handler_request(Artifact::TAG.into(), &id);
+                // Request a stripped artifact first if the client has an
+                // assembler, and fall back to the full artifact if the peer
+                // does not serve stripped artifacts or the stripped artifact
+                // cannot be assembled.
+                let mut stripped = assembler.is_some();
+                loop {
+                    // Only download from peers that are within their bandwidth limit.
+                    let peer = {
+                        let peers = peer_rx.borrow();
+                        if peers.is_empty() {
+                            break;
+                        }
+                        let now = Instant::now();
+                        peers
+                            .iter()
+                            .filter(|peer| bandwidth_limiter.has_budget(peer, now))
+                            .choose(&mut rng)
+                            .copied()
+                    };
+                    let Some(peer) = peer else {
+                        metrics.download_task_bandwidth_limited_total.inc();
+                        time::sleep(BANDWIDTH_LIMIT_RETRY_INTERVAL).await;
+                        continue;
+                    };
+                    let request = build_rpc_handler_request(Artifact::TAG.into(), &id, stripped);
 
                     let peer_deleted_the_artifact = async {
                         peer_rx.changed().await;
@@ -420,13 +498,33 @@ where
                         }
 
                         Ok(response) = transport.rpc(&peer, request) => {
-                            if let StatusCode::OK = response.status() {
-                                if let Ok(message) =
-                                    bincode::deserialize::<Artifact::Message>(response.body())
-                                {
-                                    result = DownloadResult::Completed(message, peer);
-                                    break;
+                            bandwidth_limiter.consume(peer, response.body().len(), Instant::now());
+                            match response.status() {
+                                StatusCode::OK => {
+                                    let message = match &assembler {
+                                        Some(assembler) if stripped => {
+                                            assembler
+                                                .assemble_message(response.into_body(), peer, transport.as_ref())
+                                                .await
+                                        }
+                                        _ => bincode::deserialize::<Artifact::Message>(response.body())
+                                            .map_err(|err| AssembleError::Deserialization(err.to_string())),
+                                    };
+                                    match message {
+                                        Ok(message) => {
+                                            result = DownloadResult::Completed(message, peer);
+                                            break;
+                                        }
+                                        Err(_) if stripped => {
+                                            metrics.download_task_artifact_assembly_errors_total.inc();
+                                            stripped = false;
+                                        }
+                                        Err(_) => {}
+                                    }
                                 }
+                                // The peer predates the stripped endpoint.
+                                StatusCode::NOT_FOUND if stripped => stripped = false,
+                                _ => {}
                             }
                             metrics.download_task_artifact_download_errors_total.inc();
                         }
@@ -455,6 +553,8 @@ where
         mut peer_rx: watch::Receiver<HashSet<NodeId>>,
         mut priority_fn_watcher: watch::Receiver<PriorityFn<Artifact::Id, Artifact::Attribute>>,
         sender: CrossbeamSender<UnvalidatedArtifactEvent<Artifact>>,
+        assembler: Option<ArtifactAssemblerRef<Artifact>>,
+        bandwidth_limiter: Arc<PeerBandwidthLimiter>,
         transport: Arc<dyn Transport>,
         metrics: ConsensusManagerMetrics,
     ) -> (
@@ -469,6 +569,8 @@ where
             artifact,
             &mut peer_rx,
             priority_fn_watcher,
+            assembler,
+            bandwidth_limiter,
             transport,
             metrics.clone(),
         )
@@ -535,6 +637,8 @@ where
                 true
             }
         });
+        self.bandwidth_limiter
+            .retain(|node_id| !nodes_leaving_topology.contains(node_id));
 
         for peers_sender in self.active_downloads.values() {
             peers_sender.send_if_modified(|set| {
@@ -547,14 +651,19 @@ where
     }
 }
 
-fn build_rpc_handler_request<T: Serialize>(uri_prefix: &str, id: &T) -> Request<Bytes> {
+pub(crate) fn build_rpc_handler_request<T: Serialize>(
+    uri_prefix: &str,
+    id: &T,
+    stripped: bool,
+) -> Request<Bytes> {
+    let endpoint = if stripped { "rpc_stripped" } else { "rpc" };
     Request::builder()
-        .uri(format!("/{}/rpc", uri_prefix))
+        .uri(format!("/{}/{}", uri_prefix, endpoint))
         .body(Bytes::from(bincode::serialize(id).unwrap()))
         .unwrap()
 }
 
-enum DownloadResult<T> {
+pub(crate) enum DownloadResult<T> {
     Completed(T, NodeId),
     AllPeersDeletedTheArtifact,
     PriorityIsDrop,
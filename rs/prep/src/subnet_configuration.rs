@@ -313,6 +313,8 @@ impl SubnetConfig {
             max_instructions_per_install_code: self.max_instructions_per_install_code,
             features: Some(self.features),
             max_number_of_canisters: self.max_number_of_canisters,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
             ssh_readonly_access: self.ssh_readonly_access,
             ssh_backup_access: self.ssh_backup_access,
             ecdsa_config: self.ecdsa_config,
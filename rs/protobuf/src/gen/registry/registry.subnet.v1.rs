@@ -85,6 +85,18 @@ pub struct SubnetRecord {
     /// appropriate proposal which sets `is_halted` to `false` is approved.
     #[prost(bool, tag = "28")]
     pub halt_at_cup_height: bool,
+    /// The maximum total memory (in bytes) that canisters on the subnet may use or reserve. Creating
+    /// canisters and increasing memory allocations is rejected once the limit would be exceeded.
+    ///
+    /// A value of 0 is equivalent to setting no limit.
+    #[prost(uint64, tag = "29")]
+    pub max_total_memory_bytes: u64,
+    /// The maximum sum of the compute allocations (in percent) that canisters on the subnet may
+    /// reserve.
+    ///
+    /// A value of 0 is equivalent to setting no limit.
+    #[prost(uint64, tag = "30")]
+    pub max_reserved_compute_allocation: u64,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
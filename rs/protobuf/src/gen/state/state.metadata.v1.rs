@@ -363,6 +363,26 @@ pub struct NodePublicKeyEntry {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NodeBlockmakerStats {
+    #[prost(message, optional, tag = "1")]
+    pub node_id: ::core::option::Option<super::super::super::types::v1::NodeId>,
+    #[prost(uint64, tag = "2")]
+    pub blocks_proposed_total: u64,
+    #[prost(uint64, tag = "3")]
+    pub blocks_not_proposed_total: u64,
+}
+/// Running totals of the block making statistics of the subnet's nodes, as of
+/// `timestamp_nanos`.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BlockmakerMetricsSnapshot {
+    #[prost(uint64, tag = "1")]
+    pub timestamp_nanos: u64,
+    #[prost(message, repeated, tag = "2")]
+    pub node_stats: ::prost::alloc::vec::Vec<NodeBlockmakerStats>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SystemMetadata {
     #[prost(message, optional, tag = "2")]
     pub prev_state_hash: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
@@ -409,6 +429,8 @@ pub struct SystemMetadata {
         ::prost::alloc::vec::Vec<BitcoinGetSuccessorsFollowUpResponses>,
     #[prost(message, repeated, tag = "19")]
     pub node_public_keys: ::prost::alloc::vec::Vec<NodePublicKeyEntry>,
+    #[prost(message, repeated, tag = "20")]
+    pub blockmaker_metrics_time_series: ::prost::alloc::vec::Vec<BlockmakerMetricsSnapshot>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -315,14 +315,37 @@ impl Recovery {
         try_readonly: bool,
         keep_downloaded_state: bool,
         additional_excludes: Vec<&str>,
+    ) -> impl Step {
+        self.get_download_state_step_into(
+            node_ip,
+            try_readonly,
+            keep_downloaded_state,
+            additional_excludes,
+            &self.data_dir,
+            &self.work_dir,
+        )
+    }
+
+    /// Return a [DownloadIcStateStep] downloading the ic_state of the given
+    /// node to `target`, using `working_dir` as the working directory of the
+    /// step. Useful when the states of several subnets are downloaded side by
+    /// side.
+    pub fn get_download_state_step_into(
+        &self,
+        node_ip: IpAddr,
+        try_readonly: bool,
+        keep_downloaded_state: bool,
+        additional_excludes: Vec<&str>,
+        target: &Path,
+        working_dir: &Path,
     ) -> impl Step {
         DownloadIcStateStep {
             logger: self.logger.clone(),
             try_readonly,
             node_ip,
-            target: self.data_dir.display().to_string(),
+            target: target.display().to_string(),
             keep_downloaded_state,
-            working_dir: self.work_dir.display().to_string(),
+            working_dir: working_dir.display().to_string(),
             require_confirmation: self.ssh_confirmation,
             key_file: self.key_file.clone(),
             additional_excludes: additional_excludes
@@ -1,6 +1,12 @@
-use ic_agent::{export::Principal, hash_tree::Label, lookup_value, Agent, Certificate};
-use ic_base_types::SubnetId;
+use ic_agent::{
+    export::Principal,
+    hash_tree::Label,
+    identity::{BasicIdentity, Secp256k1Identity},
+    lookup_value, Agent, Certificate, Identity,
+};
+use ic_base_types::{CanisterId, SubnetId};
 use ic_crypto_utils_threshold_sig_der::{parse_threshold_sig_key, public_key_to_der};
+use ic_ic00_types::{CanisterIdRecord, Payload};
 use ic_recovery::{
     error::{RecoveryError, RecoveryResult},
     file_sync_helper::{read_bytes, write_bytes},
@@ -150,6 +156,51 @@ impl AgentHelper {
     }
 }
 
+/// Calls `method` of the management canister on the canister `canister_id`,
+/// e.g. `stop_canister` or `start_canister`, as the identity stored in the PEM
+/// file at `identity_path`. Both secp256k1 and Ed25519 identities are accepted.
+///
+/// The call is sent to `url`, which has to route it to the subnet hosting the
+/// canister, e.g. an API boundary node. When `fetch_root_key` is set, the root
+/// key is fetched from `url` instead of using the mainnet root key.
+pub(crate) fn call_management_canister(
+    url: &Url,
+    identity_path: &Path,
+    canister_id: CanisterId,
+    method: &str,
+    fetch_root_key: bool,
+) -> RecoveryResult<()> {
+    let identity: Box<dyn Identity> = match Secp256k1Identity::from_pem_file(identity_path) {
+        Ok(identity) => Box::new(identity),
+        Err(_) => Box::new(
+            BasicIdentity::from_pem_file(identity_path)
+                .map_err(|err| agent_error("Failed to read the identity", err))?,
+        ),
+    };
+    let agent = Agent::builder()
+        .with_url(url.to_string())
+        .with_boxed_identity(identity)
+        .build()
+        .map_err(|err| agent_error("Failed to build an Agent", err))?;
+
+    block_on(async {
+        if fetch_root_key {
+            agent
+                .fetch_root_key()
+                .await
+                .map_err(|err| agent_error("Failed to fetch the root key", err))?;
+        }
+        agent
+            .update(&Principal::management_canister(), method)
+            .with_effective_canister_id(canister_id.get().0)
+            .with_arg(CanisterIdRecord::from(canister_id).encode())
+            .call_and_wait()
+            .await
+            .map_err(|err| agent_error(format!("Failed to call {}", method), err))
+    })
+    .map(|_| ())
+}
+
 fn agent_error(message: impl Display, error: impl Display) -> RecoveryError {
     RecoveryError::AgentError(format!("{}: {}", message, error))
 }
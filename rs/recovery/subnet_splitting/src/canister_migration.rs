@@ -0,0 +1,641 @@
+use crate::{
+    admin_helper::{
+        get_halt_subnet_at_cup_height_command, get_propose_to_complete_canister_migration_command,
+        get_propose_to_prepare_canister_migration_command,
+        get_propose_to_reroute_canister_ranges_command,
+    },
+    layout::Layout,
+    steps::{
+        CanisterAction, ManageCanisterStep, MoveCanisterStateStep, ReadRegistryStep, WaitForCUPStep,
+    },
+    target_subnet::TargetSubnet,
+    utils::get_state_hash,
+};
+
+use clap::Parser;
+use ic_base_types::{CanisterId, SubnetId};
+use ic_recovery::{
+    cli::{consent_given, read_optional},
+    error::{RecoveryError, RecoveryResult},
+    recovery_iterator::RecoveryIterator,
+    recovery_state::{HasRecoveryState, RecoveryState},
+    registry_helper::RegistryPollingStrategy,
+    steps::{AdminStep, Step, UploadAndRestartStep},
+    NeuronArgs, Recovery, RecoveryArgs, IC_REGISTRY_LOCAL_STORE,
+};
+use ic_registry_routing_table::{CanisterIdRange, RoutingTable};
+use serde::{Deserialize, Serialize};
+use slog::Logger;
+use strum::{EnumMessage, IntoEnumIterator};
+use strum_macros::{EnumIter, EnumString};
+
+use std::{collections::HashMap, iter::Peekable, net::IpAddr, path::PathBuf};
+
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    EnumIter,
+    EnumString,
+    Serialize,
+    Deserialize,
+    EnumMessage,
+    clap::ValueEnum,
+)]
+pub enum StepType {
+    StopCanister,
+    PrepareCanisterMigration,
+    CheckRegistryForCanisterMigrationsEntry,
+    HaltSourceSubnetAtCupHeight,
+    HaltDestinationSubnetAtCupHeight,
+    RerouteCanisterRanges,
+    CheckRegistryForRoutingTableEntry,
+    DownloadStateFromSourceSubnet,
+    DownloadStateFromDestinationSubnet,
+    MoveCanisterState,
+    ProposeCupForSourceSubnet,
+    UploadStateToSourceSubnet,
+    ProposeCupForDestinationSubnet,
+    UploadStateToDestinationSubnet,
+    WaitForCUPOnSourceSubnet,
+    WaitForCUPOnDestinationSubnet,
+    UnhaltSourceSubnet,
+    UnhaltDestinationSubnet,
+    StartCanister,
+    CompleteCanisterMigration,
+    CheckRegistryForCanisterMigrationsEntryAgain,
+    Cleanup,
+}
+
+#[derive(Debug, Clone, PartialEq, Parser, Serialize, Deserialize)]
+#[clap(version = "1.0")]
+pub struct CanisterMigrationArgs {
+    /// Id of the subnet currently hosting the canister.
+    #[clap(long, parse(try_from_str=ic_recovery::util::subnet_id_from_str))]
+    pub source_subnet_id: SubnetId,
+
+    /// Id of the subnet the canister will be moved to.
+    #[clap(long, parse(try_from_str=ic_recovery::util::subnet_id_from_str))]
+    pub destination_subnet_id: SubnetId,
+
+    /// Id of the canister to be moved. The canister has to be stopped.
+    #[clap(long)]
+    pub canister_id: CanisterId,
+
+    /// Path to the PEM file of an identity controlling the canister. If set,
+    /// the canister is stopped before and started after the migration by
+    /// calls through the NNS URL, which has to be an API boundary node.
+    /// Otherwise a controller has to stop and start the canister.
+    #[clap(long, parse(from_os_str))]
+    pub controller_key_file: Option<PathBuf>,
+
+    /// Public ssh key to be deployed to both subnets for read only access.
+    #[clap(long)]
+    pub pub_key: Option<String>,
+
+    /// If the downloaded states should be backed up locally.
+    #[clap(long)]
+    pub keep_downloaded_state: Option<bool>,
+
+    /// IP address of the node from the source subnet to download the state from.
+    #[clap(long)]
+    pub download_node_source: Option<IpAddr>,
+
+    /// IP address of the node from the destination subnet to download the state from.
+    #[clap(long)]
+    pub download_node_destination: Option<IpAddr>,
+
+    /// IP address of the node to upload the new source subnet state to.
+    #[clap(long)]
+    pub upload_node_source: Option<IpAddr>,
+
+    /// IP address of the node to upload the new destination subnet state to.
+    #[clap(long)]
+    pub upload_node_destination: Option<IpAddr>,
+
+    /// If present the tool will start execution for the provided step, skipping the initial ones.
+    #[clap(long = "resume")]
+    #[clap(value_enum)]
+    pub next_step: Option<StepType>,
+
+    /// Roll back a migration that failed before a new state was uploaded to
+    /// either subnet: the canister is routed back to the source subnet, both
+    /// subnets are unhalted from their unmodified states, the canister is
+    /// started, and the canister migrations entry is removed.
+    #[clap(long)]
+    #[serde(default)]
+    pub rollback: bool,
+}
+
+/// Moves a single canister from one subnet to another.
+///
+/// The canister is stopped, both subnets are halted at their next CUP height,
+/// the canister is rerouted to the destination subnet, and its state is moved
+/// from the latest checkpoint of the source subnet into the latest checkpoint
+/// of the destination subnet. Both subnets are then restarted from recovery
+/// CUPs and the canister is started again.
+///
+/// Until a new state is uploaded to either subnet, a failed migration can be
+/// rolled back with `--rollback`, which leaves the canister on the source
+/// subnet. Afterwards, the remaining steps have to be completed with
+/// `--resume` instead.
+pub struct CanisterMigration {
+    step_iterator: Peekable<StepTypeIter>,
+    params: CanisterMigrationArgs,
+    recovery_args: RecoveryArgs,
+    neuron_args: Option<NeuronArgs>,
+    recovery: Recovery,
+    layout: Layout,
+    logger: Logger,
+}
+
+impl CanisterMigration {
+    pub fn new(
+        logger: Logger,
+        recovery_args: RecoveryArgs,
+        neuron_args: Option<NeuronArgs>,
+        canister_migration_args: CanisterMigrationArgs,
+    ) -> Self {
+        let recovery = Recovery::new(
+            logger.clone(),
+            recovery_args.clone(),
+            neuron_args.clone(),
+            recovery_args.nns_url.clone(),
+            RegistryPollingStrategy::WithEveryRead,
+        )
+        .expect("Failed to initialize recovery");
+
+        Self::check_preconditions(&recovery, &canister_migration_args)
+            .expect("Canister migration should satisfy all the preconditions");
+
+        Self {
+            step_iterator: StepType::iter().peekable(),
+            params: canister_migration_args,
+            recovery_args,
+            neuron_args,
+            layout: Layout::new(&recovery),
+            recovery,
+            logger,
+        }
+    }
+
+    /// Checks that the source and destination subnets are different, that both
+    /// exist, and that the canister is routed to the source subnet.
+    ///
+    /// When resuming after the canister has been rerouted, or when rolling back,
+    /// it is also accepted that the canister is already routed to the
+    /// destination subnet.
+    fn check_preconditions(
+        recovery: &Recovery,
+        args: &CanisterMigrationArgs,
+    ) -> RecoveryResult<()> {
+        let validation_error =
+            |error_message: String| Err(RecoveryError::ValidationFailed(error_message));
+
+        if args.source_subnet_id == args.destination_subnet_id {
+            return validation_error(
+                "Source and destination subnets should be different".to_string(),
+            );
+        }
+
+        for subnet_id in [args.source_subnet_id, args.destination_subnet_id] {
+            if recovery
+                .registry_helper
+                .get_subnet_record(subnet_id)?
+                .1
+                .is_none()
+            {
+                return validation_error(format!("Subnet {} does not exist", subnet_id));
+            }
+        }
+
+        let (_, Some(routing_table)) = recovery.registry_helper.get_routing_table()? else {
+            return validation_error("Routing table should not be empty".to_string());
+        };
+
+        let already_rerouted = args.rollback
+            || args
+                .next_step
+                .is_some_and(|step| step as usize > StepType::RerouteCanisterRanges as usize);
+        match routing_table.route(args.canister_id.get()) {
+            Some(subnet_id) if subnet_id == args.source_subnet_id => Ok(()),
+            Some(subnet_id) if subnet_id == args.destination_subnet_id && already_rerouted => {
+                Ok(())
+            }
+            other => validation_error(format!(
+                "Canister {} should be routed to subnet {}, but is routed to {:?}",
+                args.canister_id, args.source_subnet_id, other
+            )),
+        }
+    }
+
+    pub fn get_recovery_api(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    fn canister_id_ranges(&self) -> Vec<CanisterIdRange> {
+        vec![CanisterIdRange {
+            start: self.params.canister_id,
+            end: self.params.canister_id,
+        }]
+    }
+
+    fn manage_canister(&self, action: CanisterAction) -> RecoveryResult<impl Step> {
+        let controller_key_file = self
+            .params
+            .controller_key_file
+            .clone()
+            .ok_or(RecoveryError::StepSkipped)?;
+
+        Ok(ManageCanisterStep {
+            action,
+            canister_id: self.params.canister_id,
+            url: self.recovery_args.nns_url.clone(),
+            controller_key_file,
+            fetch_root_key: self.recovery_args.test_mode,
+            logger: self.recovery.logger.clone(),
+        })
+    }
+
+    /// Reroutes the canister to the destination subnet or, when rolling back,
+    /// back to the source subnet. The rollback is skipped if the canister
+    /// was never rerouted.
+    fn reroute_canister_ranges(&self) -> RecoveryResult<impl Step> {
+        let (from, to) = if self.params.rollback {
+            (
+                self.params.destination_subnet_id,
+                self.params.source_subnet_id,
+            )
+        } else {
+            (
+                self.params.source_subnet_id,
+                self.params.destination_subnet_id,
+            )
+        };
+
+        if self.params.rollback {
+            let (_, routing_table) = self.recovery.registry_helper.get_routing_table()?;
+            let routed_to =
+                routing_table.and_then(|table| table.route(self.params.canister_id.get()));
+            if routed_to == Some(to) {
+                return Err(RecoveryError::StepSkipped);
+            }
+        }
+
+        Ok(AdminStep {
+            logger: self.recovery.logger.clone(),
+            ic_admin_cmd: get_propose_to_reroute_canister_ranges_command(
+                &self.recovery.admin_helper,
+                &self.canister_id_ranges(),
+                from,
+                to,
+            ),
+        })
+    }
+
+    fn halt_at_cup_height(&self, target_subnet: TargetSubnet) -> impl Step {
+        AdminStep {
+            logger: self.recovery.logger.clone(),
+            ic_admin_cmd: get_halt_subnet_at_cup_height_command(
+                &self.recovery.admin_helper,
+                self.subnet_id(target_subnet),
+                &self.params.pub_key,
+            ),
+        }
+    }
+
+    fn download_state(&self, target_subnet: TargetSubnet) -> RecoveryResult<impl Step> {
+        let node_ip = match target_subnet {
+            TargetSubnet::Source => self.params.download_node_source,
+            TargetSubnet::Destination => self.params.download_node_destination,
+        }
+        .ok_or(RecoveryError::StepSkipped)?;
+
+        Ok(self.recovery.get_download_state_step_into(
+            node_ip,
+            self.params.pub_key.is_some(),
+            self.params.keep_downloaded_state == Some(true),
+            /*additional_excludes=*/
+            vec!["orchestrator", "ic_consensus_pool", IC_REGISTRY_LOCAL_STORE],
+            &self.layout.original_data_dir(target_subnet),
+            &self.layout.work_dir(target_subnet),
+        ))
+    }
+
+    fn unhalt(&self, target_subnet: TargetSubnet) -> impl Step {
+        self.recovery.halt_subnet(
+            self.subnet_id(target_subnet),
+            /*is_halted=*/ false,
+            /*keys=*/ &[],
+        )
+    }
+
+    fn propose_cup(&self, target_subnet: TargetSubnet) -> RecoveryResult<impl Step> {
+        let checkpoints_dir = self.layout.checkpoints_dir(target_subnet);
+
+        let (max_name, max_height) =
+            Recovery::get_latest_checkpoint_name_and_height(&checkpoints_dir)?;
+
+        let max_checkpoint_dir = checkpoints_dir.join(max_name);
+        let state_hash = get_state_hash(max_checkpoint_dir)?;
+
+        self.recovery.update_recovery_cup(
+            self.subnet_id(target_subnet),
+            Recovery::get_recovery_height(max_height),
+            state_hash,
+            /*replacement_nodes=*/ &[],
+            /*registry_params=*/ None,
+            /*ecdsa_subnet_id=*/ None,
+        )
+    }
+
+    fn upload_and_restart_step(&self, target_subnet: TargetSubnet) -> RecoveryResult<impl Step> {
+        match self.upload_node(target_subnet) {
+            Some(node_ip) => Ok(UploadAndRestartStep {
+                logger: self.recovery.logger.clone(),
+                node_ip,
+                work_dir: self.layout.work_dir(target_subnet),
+                data_src: self.layout.ic_state_dir(target_subnet),
+                require_confirmation: !self.recovery_args.skip_prompts,
+                key_file: self.recovery.key_file.clone(),
+                check_ic_replay_height: false,
+            }),
+            None => Err(RecoveryError::StepSkipped),
+        }
+    }
+
+    fn wait_for_cup_step(&self, target_subnet: TargetSubnet) -> RecoveryResult<impl Step> {
+        match self.upload_node(target_subnet) {
+            Some(node_ip) => Ok(WaitForCUPStep {
+                logger: self.recovery.logger.clone(),
+                layout: self.layout.clone(),
+                node_ip,
+                target_subnet,
+            }),
+            None => Err(RecoveryError::StepSkipped),
+        }
+    }
+
+    fn upload_node(&self, target_subnet: TargetSubnet) -> Option<IpAddr> {
+        match target_subnet {
+            TargetSubnet::Source => self.params.upload_node_source,
+            TargetSubnet::Destination => self.params.upload_node_destination,
+        }
+    }
+
+    fn subnet_id(&self, target_subnet: TargetSubnet) -> SubnetId {
+        match target_subnet {
+            TargetSubnet::Source => self.params.source_subnet_id,
+            TargetSubnet::Destination => self.params.destination_subnet_id,
+        }
+    }
+}
+
+impl RecoveryIterator<StepType, StepTypeIter> for CanisterMigration {
+    fn get_step_iterator(&mut self) -> &mut Peekable<StepTypeIter> {
+        &mut self.step_iterator
+    }
+
+    fn store_next_step(&mut self, step_type: Option<StepType>) {
+        self.params.next_step = step_type;
+    }
+
+    fn get_logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    fn interactive(&self) -> bool {
+        !self.recovery_args.skip_prompts
+    }
+
+    /// When rolling back, only the steps restoring the registry, unhalting
+    /// the subnets and starting the canister are executed.
+    fn get_skipped_steps(&self) -> Vec<StepType> {
+        if !self.params.rollback {
+            return vec![];
+        }
+
+        vec![
+            StepType::StopCanister,
+            StepType::PrepareCanisterMigration,
+            StepType::CheckRegistryForCanisterMigrationsEntry,
+            StepType::HaltSourceSubnetAtCupHeight,
+            StepType::HaltDestinationSubnetAtCupHeight,
+            StepType::DownloadStateFromSourceSubnet,
+            StepType::DownloadStateFromDestinationSubnet,
+            StepType::MoveCanisterState,
+            StepType::ProposeCupForSourceSubnet,
+            StepType::UploadStateToSourceSubnet,
+            StepType::ProposeCupForDestinationSubnet,
+            StepType::UploadStateToDestinationSubnet,
+            StepType::WaitForCUPOnSourceSubnet,
+            StepType::WaitForCUPOnDestinationSubnet,
+        ]
+    }
+
+    fn read_step_params(&mut self, step_type: StepType) {
+        match step_type {
+            StepType::StopCanister | StepType::StartCanister => {
+                if self.params.controller_key_file.is_none() {
+                    self.params.controller_key_file = read_optional(
+                        &self.logger,
+                        "Enter the path to the PEM file of a controller of the canister \
+                         (leave empty to stop and start the canister manually): ",
+                    )
+                }
+            }
+
+            StepType::HaltSourceSubnetAtCupHeight => {
+                if self.params.pub_key.is_none() {
+                    self.params.pub_key = read_optional(
+                        &self.logger,
+                        "Enter public key to add readonly SSH access to both subnets: ",
+                    )
+                }
+            }
+
+            StepType::DownloadStateFromSourceSubnet => {
+                if self.params.download_node_source.is_none() {
+                    self.params.download_node_source =
+                        read_optional(&self.logger, "Enter download IP on the Source Subnet:");
+                }
+
+                if self.params.keep_downloaded_state.is_none() {
+                    self.params.keep_downloaded_state = Some(consent_given(
+                        &self.logger,
+                        "Preserve original downloaded states locally?",
+                    ));
+                }
+            }
+
+            StepType::DownloadStateFromDestinationSubnet => {
+                if self.params.download_node_destination.is_none() {
+                    self.params.download_node_destination =
+                        read_optional(&self.logger, "Enter download IP on the Destination Subnet:");
+                }
+            }
+
+            StepType::UploadStateToSourceSubnet => {
+                if self.params.upload_node_source.is_none() {
+                    self.params.upload_node_source = read_optional(
+                        &self.logger,
+                        "Enter IP of node in the Source Subnet with admin access: ",
+                    );
+                }
+            }
+
+            StepType::UploadStateToDestinationSubnet => {
+                if self.params.upload_node_destination.is_none() {
+                    self.params.upload_node_destination = read_optional(
+                        &self.logger,
+                        "Enter IP of node in the Destination Subnet with admin access: ",
+                    );
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    fn get_step_impl(&self, step_type: StepType) -> RecoveryResult<Box<dyn Step>> {
+        let step: Box<dyn Step> = match step_type {
+            StepType::StopCanister => self.manage_canister(CanisterAction::Stop)?.into(),
+
+            StepType::PrepareCanisterMigration => AdminStep {
+                logger: self.recovery.logger.clone(),
+                ic_admin_cmd: get_propose_to_prepare_canister_migration_command(
+                    &self.recovery.admin_helper,
+                    &self.canister_id_ranges(),
+                    self.params.source_subnet_id,
+                    self.params.destination_subnet_id,
+                ),
+            }
+            .into(),
+
+            StepType::CheckRegistryForCanisterMigrationsEntry
+            | StepType::CheckRegistryForCanisterMigrationsEntryAgain => {
+                let registry_helper = self.recovery.registry_helper.clone();
+
+                ReadRegistryStep {
+                    logger: self.recovery.logger.clone(),
+                    label: "Canister Migrations".to_string(),
+                    querier: move || registry_helper.get_canister_migrations(),
+                    interactive: !self.recovery_args.skip_prompts,
+                }
+                .into()
+            }
+
+            StepType::HaltSourceSubnetAtCupHeight => {
+                self.halt_at_cup_height(TargetSubnet::Source).into()
+            }
+            StepType::HaltDestinationSubnetAtCupHeight => {
+                self.halt_at_cup_height(TargetSubnet::Destination).into()
+            }
+
+            StepType::RerouteCanisterRanges => self.reroute_canister_ranges()?.into(),
+
+            StepType::CheckRegistryForRoutingTableEntry => {
+                let registry_helper = self.recovery.registry_helper.clone();
+                let source_subnet = self.params.source_subnet_id;
+                let destination_subnet = self.params.destination_subnet_id;
+
+                let get_ranges = move |routing_table: RoutingTable| {
+                    HashMap::from([
+                        (source_subnet, routing_table.ranges(source_subnet)),
+                        (destination_subnet, routing_table.ranges(destination_subnet)),
+                    ])
+                };
+
+                ReadRegistryStep {
+                    logger: self.recovery.logger.clone(),
+                    label: "Routing Table".to_string(),
+                    querier: move || {
+                        registry_helper.get_routing_table().map(
+                            |(registry_version, routing_table)| {
+                                (registry_version, routing_table.map(get_ranges))
+                            },
+                        )
+                    },
+                    interactive: !self.recovery_args.skip_prompts,
+                }
+                .into()
+            }
+
+            StepType::DownloadStateFromSourceSubnet => {
+                self.download_state(TargetSubnet::Source)?.into()
+            }
+            StepType::DownloadStateFromDestinationSubnet => {
+                self.download_state(TargetSubnet::Destination)?.into()
+            }
+
+            StepType::MoveCanisterState => MoveCanisterStateStep {
+                canister_id: self.params.canister_id,
+                source_subnet_id: self.params.source_subnet_id,
+                destination_subnet_id: self.params.destination_subnet_id,
+                layout: self.layout.clone(),
+                logger: self.recovery.logger.clone(),
+            }
+            .into(),
+
+            StepType::ProposeCupForSourceSubnet => self.propose_cup(TargetSubnet::Source)?.into(),
+            StepType::UploadStateToSourceSubnet => {
+                self.upload_and_restart_step(TargetSubnet::Source)?.into()
+            }
+            StepType::ProposeCupForDestinationSubnet => {
+                self.propose_cup(TargetSubnet::Destination)?.into()
+            }
+            StepType::UploadStateToDestinationSubnet => self
+                .upload_and_restart_step(TargetSubnet::Destination)?
+                .into(),
+            StepType::WaitForCUPOnSourceSubnet => {
+                self.wait_for_cup_step(TargetSubnet::Source)?.into()
+            }
+            StepType::WaitForCUPOnDestinationSubnet => {
+                self.wait_for_cup_step(TargetSubnet::Destination)?.into()
+            }
+            StepType::UnhaltSourceSubnet => self.unhalt(TargetSubnet::Source).into(),
+            StepType::UnhaltDestinationSubnet => self.unhalt(TargetSubnet::Destination).into(),
+            StepType::StartCanister => self.manage_canister(CanisterAction::Start)?.into(),
+
+            StepType::CompleteCanisterMigration => AdminStep {
+                logger: self.recovery.logger.clone(),
+                ic_admin_cmd: get_propose_to_complete_canister_migration_command(
+                    &self.recovery.admin_helper,
+                    &self.canister_id_ranges(),
+                    self.params.source_subnet_id,
+                    self.params.destination_subnet_id,
+                ),
+            }
+            .into(),
+
+            StepType::Cleanup => self.recovery.get_cleanup_step().into(),
+        };
+
+        Ok(step)
+    }
+}
+
+impl Iterator for CanisterMigration {
+    type Item = (StepType, Box<dyn Step>);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_step()
+    }
+}
+
+impl HasRecoveryState for CanisterMigration {
+    type StepType = StepType;
+    type SubcommandArgsType = CanisterMigrationArgs;
+
+    fn get_next_step(&self) -> Option<Self::StepType> {
+        self.params.next_step
+    }
+
+    fn get_state(&self) -> RecoveryResult<RecoveryState<Self::SubcommandArgsType>> {
+        Ok(RecoveryState {
+            recovery_args: self.recovery_args.clone(),
+            neuron_args: self.neuron_args.clone(),
+            subcommand_args: self.params.clone(),
+        })
+    }
+}
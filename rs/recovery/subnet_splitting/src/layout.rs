@@ -16,6 +16,7 @@ use std::path::{Path, PathBuf};
 /// |  |-- original_source_manifest.data
 /// |  |-- nns.pem
 /// |  |-- pruned_state_tree.cbor
+/// |  |-- (destination_)original_data/
 /// |  |-- (destination_)work_dir/
 /// |  |   |-- data/
 /// |  |   |   |-- cups/cup.types.v1.CatchUpPackage.pb
@@ -30,6 +31,7 @@ pub(crate) struct Layout {
     original_state_manifest: PathBuf,
     expected_manifests: PathBuf,
     source_working_dir: PathBuf,
+    source_original_data_dir: PathBuf,
 }
 
 impl Layout {
@@ -41,6 +43,7 @@ impl Layout {
             source_working_dir: recovery.work_dir.clone(),
             original_state_manifest: recovery.recovery_dir.join("original_source_manifest.data"),
             expected_manifests: recovery.recovery_dir.join("expected_manifests.data"),
+            source_original_data_dir: recovery.data_dir.clone(),
         }
     }
 
@@ -93,6 +96,15 @@ impl Layout {
         }
     }
 
+    /// Where the originally downloaded state of the given subnet is preserved,
+    /// if requested.
+    pub(crate) fn original_data_dir(&self, target_subnet: TargetSubnet) -> PathBuf {
+        match target_subnet {
+            TargetSubnet::Source => self.source_original_data_dir.clone(),
+            TargetSubnet::Destination => self.root.join("destination_original_data"),
+        }
+    }
+
     pub(crate) fn latest_checkpoint_dir(
         &self,
         target_subnet: TargetSubnet,
@@ -1,5 +1,5 @@
 use crate::{
-    agent_helper::AgentHelper,
+    agent_helper::{call_management_canister, AgentHelper},
     layout::Layout,
     state_tool_helper::StateToolHelper,
     target_subnet::TargetSubnet,
@@ -7,7 +7,7 @@ use crate::{
     validation::validate_artifacts,
 };
 
-use ic_base_types::SubnetId;
+use ic_base_types::{CanisterId, SubnetId};
 use ic_metrics::MetricsRegistry;
 use ic_recovery::{
     cli::consent_given,
@@ -20,12 +20,12 @@ use ic_recovery::{
 };
 use ic_registry_routing_table::CanisterIdRange;
 use ic_registry_subnet_type::SubnetType;
-use ic_state_manager::split::resolve_ranges_and_split;
+use ic_state_manager::{canister_migration::move_canister, split::resolve_ranges_and_split};
 use ic_types::Height;
 use slog::{error, info, Logger};
 use url::Url;
 
-use std::net::IpAddr;
+use std::{net::IpAddr, path::PathBuf};
 
 pub(crate) struct CopyWorkDirStep {
     pub(crate) layout: Layout,
@@ -173,6 +173,106 @@ impl Step for SplitStateStep {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum CanisterAction {
+    Stop,
+    Start,
+}
+
+impl CanisterAction {
+    fn method(&self) -> &'static str {
+        match self {
+            CanisterAction::Stop => "stop_canister",
+            CanisterAction::Start => "start_canister",
+        }
+    }
+}
+
+pub(crate) struct ManageCanisterStep {
+    pub(crate) action: CanisterAction,
+    pub(crate) canister_id: CanisterId,
+    pub(crate) url: Url,
+    pub(crate) controller_key_file: PathBuf,
+    pub(crate) fetch_root_key: bool,
+    pub(crate) logger: Logger,
+}
+
+impl Step for ManageCanisterStep {
+    fn descr(&self) -> String {
+        format!(
+            "Calling {} on canister {} through {} as the controller in {}.",
+            self.action.method(),
+            self.canister_id,
+            self.url,
+            self.controller_key_file.display(),
+        )
+    }
+
+    fn exec(&self) -> RecoveryResult<()> {
+        info!(
+            self.logger,
+            "Calling {} on canister {}",
+            self.action.method(),
+            self.canister_id
+        );
+        call_management_canister(
+            &self.url,
+            &self.controller_key_file,
+            self.canister_id,
+            self.action.method(),
+            self.fetch_root_key,
+        )
+    }
+}
+
+pub(crate) struct MoveCanisterStateStep {
+    pub(crate) canister_id: CanisterId,
+    pub(crate) source_subnet_id: SubnetId,
+    pub(crate) destination_subnet_id: SubnetId,
+    pub(crate) layout: Layout,
+    pub(crate) logger: Logger,
+}
+
+impl Step for MoveCanisterStateStep {
+    fn descr(&self) -> String {
+        format!(
+            "Moving the state of canister {} from the state of subnet {} in {} to the state of \
+             subnet {} in {}, verifying the canister's files, and removing all but the highest \
+             checkpoints.",
+            self.canister_id,
+            self.source_subnet_id,
+            self.layout.work_dir(TargetSubnet::Source).display(),
+            self.destination_subnet_id,
+            self.layout.work_dir(TargetSubnet::Destination).display(),
+        )
+    }
+
+    fn exec(&self) -> RecoveryResult<()> {
+        info!(self.logger, "Moving the canister state");
+        move_canister(
+            self.layout.ic_state_dir(TargetSubnet::Source),
+            self.layout.ic_state_dir(TargetSubnet::Destination),
+            self.canister_id,
+            &MetricsRegistry::new(),
+            self.logger.clone().into(),
+        )
+        .map_err(RecoveryError::OutputError)?;
+
+        for target_subnet in [TargetSubnet::Source, TargetSubnet::Destination] {
+            info!(
+                self.logger,
+                "Removing past checkpoints of the {:?} subnet", target_subnet
+            );
+            Recovery::remove_all_but_highest_checkpoints(
+                &self.layout.checkpoints_dir(target_subnet),
+                &self.logger,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
 pub(crate) struct ComputeExpectedManifestsStep {
     pub(crate) state_tool_helper: StateToolHelper,
     pub(crate) source_subnet_id: SubnetId,
@@ -1681,6 +1681,17 @@ struct ProposeToUpdateSubnetCmd {
     /// of this field.
     #[clap(long)]
     pub max_number_of_canisters: Option<u64>,
+
+    /// If set, the subnet will not admit new canisters or memory allocations
+    /// once canisters use or reserve this many bytes in total. Zero means no
+    /// limit.
+    #[clap(long)]
+    pub max_total_memory_bytes: Option<u64>,
+
+    /// If set, the subnet will not admit compute allocations beyond this total
+    /// (in percent). Zero means no limit.
+    #[clap(long)]
+    pub max_reserved_compute_allocation: Option<u64>,
 }
 
 fn parse_ecdsa_keys_option(maybe_value: &Option<Vec<String>>) -> Vec<EcdsaKeyId> {
@@ -1829,6 +1840,8 @@ impl ProposalPayload<UpdateSubnetPayload> for ProposeToUpdateSubnetCmd {
             ssh_readonly_access: self.ssh_readonly_access.clone(),
             ssh_backup_access: self.ssh_backup_access.clone(),
             max_number_of_canisters: self.max_number_of_canisters,
+            max_total_memory_bytes: self.max_total_memory_bytes,
+            max_reserved_compute_allocation: self.max_reserved_compute_allocation,
         }
     }
 }
@@ -63,6 +63,8 @@ pub(crate) struct SubnetRecord {
     pub max_instructions_per_install_code: u64,
     pub features: SubnetFeatures,
     pub max_number_of_canisters: u64,
+    pub max_total_memory_bytes: u64,
+    pub max_reserved_compute_allocation: u64,
     pub ssh_readonly_access: Vec<String>,
     pub ssh_backup_access: Vec<String>,
     pub ecdsa_config: Option<EcdsaConfig>,
@@ -115,6 +117,8 @@ impl From<&SubnetRecordProto> for SubnetRecord {
             max_instructions_per_install_code: value.max_instructions_per_install_code,
             features: value.features.clone().unwrap_or_default().into(),
             max_number_of_canisters: value.max_number_of_canisters,
+            max_total_memory_bytes: value.max_total_memory_bytes,
+            max_reserved_compute_allocation: value.max_reserved_compute_allocation,
             ssh_readonly_access: value.ssh_readonly_access.clone(),
             ssh_backup_access: value.ssh_backup_access.clone(),
             ecdsa_config: value
@@ -16,14 +16,16 @@ use ic_protobuf::registry::{
 };
 use ic_registry_transport::{
     deserialize_atomic_mutate_request, deserialize_get_changes_since_request,
-    deserialize_get_value_request,
+    deserialize_get_chunk_request, deserialize_get_value_request,
     pb::v1::{
-        registry_error::Code, CertifiedResponse, RegistryAtomicMutateResponse, RegistryDelta,
-        RegistryError, RegistryGetChangesSinceRequest, RegistryGetChangesSinceResponse,
-        RegistryGetLatestVersionResponse, RegistryGetValueResponse,
+        high_capacity_registry_get_value_response, high_capacity_registry_value,
+        registry_error::Code, CertifiedResponse, GetChunkResponse, HighCapacityRegistryDelta,
+        HighCapacityRegistryGetChangesSinceResponse, HighCapacityRegistryGetValueResponse,
+        HighCapacityRegistryValue, RegistryAtomicMutateResponse, RegistryError,
+        RegistryGetChangesSinceRequest, RegistryGetLatestVersionResponse,
     },
-    serialize_atomic_mutate_response, serialize_get_changes_since_response,
-    serialize_get_value_response,
+    serialize_atomic_mutate_response, serialize_get_chunk_response,
+    serialize_high_capacity_get_changes_since_response, serialize_high_capacity_get_value_response,
 };
 use ic_types::PrincipalId;
 use prost::Message;
@@ -211,31 +213,29 @@ fn current_authz() {
 #[export_name = "canister_query get_changes_since"]
 fn get_changes_since() {
     let response_pb = match deserialize_get_changes_since_request(arg_data()) {
-        Ok(version) => {
+        Ok(request) => {
             let registry = registry();
+            let (max_versions, error) = versions_to_return(&request);
 
-            let max_versions = registry
-                .count_fitting_deltas(version, MAX_REGISTRY_DELTAS_SIZE)
-                .min(MAX_VERSIONS_PER_QUERY);
-
-            RegistryGetChangesSinceResponse {
-                error: None,
+            HighCapacityRegistryGetChangesSinceResponse {
+                error,
                 version: registry.latest_version(),
-                deltas: registry.get_changes_since(version, Some(max_versions)),
+                deltas: registry
+                    .get_high_capacity_changes_since(request.version, Some(max_versions)),
             }
         }
-        Err(error) => RegistryGetChangesSinceResponse {
+        Err(error) => HighCapacityRegistryGetChangesSinceResponse {
             error: Some(RegistryError {
                 code: Code::MalformedMessage as i32,
                 reason: error.to_string(),
                 key: Vec::<u8>::default(),
             }),
             version: 0,
-            deltas: Vec::<RegistryDelta>::default(),
+            deltas: Vec::<HighCapacityRegistryDelta>::default(),
         },
     };
-    let bytes =
-        serialize_get_changes_since_response(response_pb).expect("Error serializing response");
+    let bytes = serialize_high_capacity_get_changes_since_response(response_pb)
+        .expect("Error serializing response");
 
     reply(&bytes);
 }
@@ -249,9 +249,9 @@ fn get_certified_changes_since() {
             let latest_version = registry().latest_version();
             let from_version = EncodedVersion::from(req.version.saturating_add(1));
 
-            let max_versions = registry()
-                .count_fitting_deltas(req.version, MAX_REGISTRY_DELTAS_SIZE)
-                .min(MAX_VERSIONS_PER_QUERY);
+            // Certified responses cannot carry an error, so clients that are
+            // not chunk aware just stop seeing new versions.
+            let (max_versions, _) = versions_to_return(&req);
 
             let to_version = EncodedVersion::from(req.version.saturating_add(max_versions as u64));
             let delta_tree = registry()
@@ -275,17 +275,44 @@ fn get_certified_changes_since() {
 #[export_name = "canister_query get_value"]
 fn get_value() {
     let response_pb = match deserialize_get_value_request(arg_data()) {
-        Ok((key, version_opt)) => {
+        Ok(request) => {
+            let key = request.key;
             let registry = registry();
-            let version = version_opt.unwrap_or_else(|| registry.latest_version());
-            let result = registry.get(&key, version);
+            let version = request.version.unwrap_or_else(|| registry.latest_version());
+            let result = registry.get_high_capacity(&key, version);
             match result {
-                Some(value) => RegistryGetValueResponse {
+                Some(HighCapacityRegistryValue {
+                    version,
+                    content: Some(high_capacity_registry_value::Content::LargeValueChunkKeys(_)),
+                    ..
+                }) if !request.chunk_aware => HighCapacityRegistryGetValueResponse {
+                    error: Some(RegistryError {
+                        code: Code::MalformedMessage as i32,
+                        key: key.clone(),
+                        reason: format!(
+                            "The value at version {} is stored as chunks, which are only \
+                             returned if the request is chunk aware",
+                            version
+                        ),
+                    }),
+                    version,
+                    content: None,
+                },
+                Some(value) => HighCapacityRegistryGetValueResponse {
                     error: None,
                     version: value.version,
-                    value: value.value.clone(),
+                    content: value.content.map(|content| match content {
+                        high_capacity_registry_value::Content::Value(value) => {
+                            high_capacity_registry_get_value_response::Content::Value(value)
+                        }
+                        high_capacity_registry_value::Content::LargeValueChunkKeys(chunk_keys) => {
+                            high_capacity_registry_get_value_response::Content::LargeValueChunkKeys(
+                                chunk_keys,
+                            )
+                        }
+                    }),
                 },
-                None => RegistryGetValueResponse {
+                None => HighCapacityRegistryGetValueResponse {
                     error: Some(RegistryError {
                         code: Code::KeyNotPresent as i32,
                         key: key.clone(),
@@ -298,21 +325,89 @@ fn get_value() {
                     // or use it as a precondition, we can only ask that nothing has changed
                     // since the moment we did this read.
                     version,
-                    value: Vec::<u8>::default(),
+                    content: None,
                 },
             }
         }
-        Err(error) => RegistryGetValueResponse {
+        Err(error) => HighCapacityRegistryGetValueResponse {
             error: Some(RegistryError {
                 code: Code::MalformedMessage as i32,
                 key: Vec::<u8>::default(),
                 reason: error.to_string(),
             }),
             version: 0,
-            value: Vec::<u8>::default(),
+            content: None,
+        },
+    };
+    let bytes = serialize_high_capacity_get_value_response(response_pb)
+        .expect("Error serializing response");
+    reply(&bytes);
+}
+
+/// Returns the number of versions after `request.version` to include in a
+/// response to a get_changes_since() request.
+///
+/// Clients that are not chunk aware only get the versions before the first one
+/// that stores a value as chunks, so that they never see an empty value in
+/// place of a chunked one. In that case, the error to report is returned too.
+fn versions_to_return(request: &RegistryGetChangesSinceRequest) -> (usize, Option<RegistryError>) {
+    let registry = registry();
+    let max_versions = registry
+        .count_fitting_deltas(request.version, MAX_REGISTRY_DELTAS_SIZE)
+        .min(MAX_VERSIONS_PER_QUERY);
+    if request.chunk_aware {
+        return (max_versions, None);
+    }
+    match registry.first_chunked_version_after(request.version) {
+        Some(chunked_version)
+            if chunked_version <= request.version.saturating_add(max_versions as u64) =>
+        {
+            (
+                (chunked_version - request.version - 1) as usize,
+                Some(RegistryError {
+                    code: Code::MalformedMessage as i32,
+                    key: Vec::<u8>::default(),
+                    reason: format!(
+                        "Version {} stores values as chunks, which are only returned if the \
+                         request is chunk aware",
+                        chunked_version
+                    ),
+                }),
+            )
+        }
+        _ => (max_versions, None),
+    }
+}
+
+/// Returns a chunk of a large value, given the SHA-256 of its content (as
+/// listed in `LargeValueChunkKeys`).
+#[export_name = "canister_query get_chunk"]
+fn get_chunk() {
+    let response_pb = match deserialize_get_chunk_request(arg_data()) {
+        Ok(content_sha256) => match registry().get_chunk(&content_sha256) {
+            Some(content) => GetChunkResponse {
+                error: None,
+                content: content.to_vec(),
+            },
+            None => GetChunkResponse {
+                error: Some(RegistryError {
+                    code: Code::KeyNotPresent as i32,
+                    key: content_sha256,
+                    reason: "No chunk with the given SHA-256".to_string(),
+                }),
+                content: Vec::<u8>::default(),
+            },
+        },
+        Err(error) => GetChunkResponse {
+            error: Some(RegistryError {
+                code: Code::MalformedMessage as i32,
+                key: Vec::<u8>::default(),
+                reason: error.to_string(),
+            }),
+            content: Vec::<u8>::default(),
         },
     };
-    let bytes = serialize_get_value_response(response_pb).expect("Error serializing response");
+    let bytes = serialize_get_chunk_response(response_pb).expect("Error serializing response");
     reply(&bytes);
 }
 
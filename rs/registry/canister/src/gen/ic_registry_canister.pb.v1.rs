@@ -5,8 +5,10 @@ pub struct ChangelogEntry {
     #[prost(uint64, tag = "1")]
     pub version: u64,
     /// Serialized value of
-    /// ic_registry_transport.pb.v1.RegistryAtomicMutateRequest, with all
-    /// preconditions removed (as they had been checked already).
+    /// ic_registry_transport.pb.v1.HighCapacityRegistryAtomicMutateRequest,
+    /// with all preconditions removed (as they had been checked already).
+    /// Unless it references chunks, this is the same as the serialized
+    /// ic_registry_transport.pb.v1.RegistryAtomicMutateRequest.
     ///
     /// We use bytes instead of actual value to make sure that the hash
     /// of a changelog entry never changes. If we stored the protobuf
@@ -33,6 +35,10 @@ pub struct RegistryStableStorage {
     /// Only present if version == VERSION_1.
     #[prost(message, repeated, tag = "3")]
     pub changelog: ::prost::alloc::vec::Vec<ChangelogEntry>,
+    /// The chunks of large values referenced by the changelog. Only present
+    /// if version == VERSION_1.
+    #[prost(message, repeated, tag = "4")]
+    pub chunks: ::prost::alloc::vec::Vec<LargeValueChunk>,
 }
 /// Nested message and enum types in `RegistryStableStorage`.
 pub mod registry_stable_storage {
@@ -97,6 +103,16 @@ pub mod registry_stable_storage {
         }
     }
 }
+/// The content of a chunk of a large value, see
+/// ic_registry_transport.pb.v1.LargeValueChunkKeys.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LargeValueChunk {
+    #[prost(bytes = "vec", tag = "1")]
+    pub content_sha256: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub content: ::prost::alloc::vec::Vec<u8>,
+}
 /// A container for the what gets written to stable storage,
 /// from the registry canister.
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -340,6 +340,8 @@ impl From<CreateSubnetPayload> for SubnetRecord {
 
             is_halted: val.is_halted,
             halt_at_cup_height: false,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
 
             max_instructions_per_message: val.max_instructions_per_message,
             max_instructions_per_round: val.max_instructions_per_round,
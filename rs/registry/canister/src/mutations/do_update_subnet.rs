@@ -220,6 +220,8 @@ pub struct UpdateSubnetPayload {
     pub ecdsa_key_signing_disable: Option<Vec<EcdsaKeyId>>,
 
     pub max_number_of_canisters: Option<u64>,
+    pub max_total_memory_bytes: Option<u64>,
+    pub max_reserved_compute_allocation: Option<u64>,
 
     pub ssh_readonly_access: Option<Vec<String>>,
     pub ssh_backup_access: Option<Vec<String>>,
@@ -308,6 +310,8 @@ fn merge_subnet_record(
         ecdsa_key_signing_enable: _,
         ecdsa_key_signing_disable: _,
         max_number_of_canisters,
+        max_total_memory_bytes,
+        max_reserved_compute_allocation,
         ssh_readonly_access,
         ssh_backup_access,
     } = payload;
@@ -355,6 +359,8 @@ fn merge_subnet_record(
     maybe_set_option!(subnet_record, ecdsa_config);
 
     maybe_set!(subnet_record, max_number_of_canisters);
+    maybe_set!(subnet_record, max_total_memory_bytes);
+    maybe_set!(subnet_record, max_reserved_compute_allocation);
 
     maybe_set!(subnet_record, ssh_readonly_access);
     maybe_set!(subnet_record, ssh_backup_access);
@@ -435,6 +441,8 @@ mod tests {
             ecdsa_key_signing_enable: Some(vec![make_ecdsa_key("key_id_2")]),
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: Some(10),
+            max_total_memory_bytes: None,
+            max_reserved_compute_allocation: None,
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
         }
@@ -471,6 +479,8 @@ mod tests {
             ecdsa_key_signing_enable: None,
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: None,
+            max_total_memory_bytes: None,
+            max_reserved_compute_allocation: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
         }
@@ -507,6 +517,8 @@ mod tests {
             max_instructions_per_install_code: 200_000_000_000,
             features: None,
             max_number_of_canisters: 0,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
@@ -557,6 +569,8 @@ mod tests {
             ecdsa_key_signing_enable: Some(vec![make_ecdsa_key("key_id_2")]),
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: Some(10),
+            max_total_memory_bytes: Some(100 * 1024 * 1024 * 1024),
+            max_reserved_compute_allocation: Some(300),
             ssh_readonly_access: Some(vec!["pub_key_0".to_string()]),
             ssh_backup_access: Some(vec!["pub_key_1".to_string()]),
         };
@@ -609,6 +623,8 @@ mod tests {
                     .into()
                 ),
                 max_number_of_canisters: 10,
+                max_total_memory_bytes: 100 * 1024 * 1024 * 1024,
+                max_reserved_compute_allocation: 300,
                 ssh_readonly_access: vec!["pub_key_0".to_string()],
                 ssh_backup_access: vec!["pub_key_1".to_string()],
             }
@@ -646,6 +662,8 @@ mod tests {
             max_instructions_per_install_code: 200_000_000_000,
             features: None,
             max_number_of_canisters: 0,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
@@ -686,6 +704,8 @@ mod tests {
             ecdsa_key_signing_enable: None,
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: Some(50),
+            max_total_memory_bytes: None,
+            max_reserved_compute_allocation: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
         };
@@ -721,6 +741,8 @@ mod tests {
                 max_instructions_per_install_code: 200_000_000_000,
                 features: None,
                 max_number_of_canisters: 50,
+                max_total_memory_bytes: 0,
+                max_reserved_compute_allocation: 0,
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
@@ -774,6 +796,8 @@ mod tests {
             max_instructions_per_install_code: 200_000_000_000,
             features: None,
             max_number_of_canisters: 0,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
@@ -814,6 +838,8 @@ mod tests {
             ecdsa_key_signing_enable: None,
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: None,
+            max_total_memory_bytes: None,
+            max_reserved_compute_allocation: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
         };
@@ -843,6 +869,8 @@ mod tests {
             max_instructions_per_install_code: 200_000_000_000,
             features: None,
             max_number_of_canisters: 0,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
@@ -883,6 +911,8 @@ mod tests {
             ecdsa_key_signing_enable: None,
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: None,
+            max_total_memory_bytes: None,
+            max_reserved_compute_allocation: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
         };
@@ -918,6 +948,8 @@ mod tests {
                 max_instructions_per_install_code: 200_000_000_000,
                 features: None,
                 max_number_of_canisters: 0,
+                max_total_memory_bytes: 0,
+                max_reserved_compute_allocation: 0,
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
@@ -956,6 +988,8 @@ mod tests {
             max_instructions_per_install_code: 200_000_000_000,
             features: None,
             max_number_of_canisters: 10,
+            max_total_memory_bytes: 0,
+            max_reserved_compute_allocation: 0,
             ssh_readonly_access: vec![],
             ssh_backup_access: vec![],
             ecdsa_config: None,
@@ -996,6 +1030,8 @@ mod tests {
             ecdsa_key_signing_enable: None,
             ecdsa_key_signing_disable: None,
             max_number_of_canisters: None,
+            max_total_memory_bytes: None,
+            max_reserved_compute_allocation: None,
             ssh_readonly_access: None,
             ssh_backup_access: None,
         };
@@ -1031,6 +1067,8 @@ mod tests {
                 max_instructions_per_install_code: 200_000_000_000,
                 features: None,
                 max_number_of_canisters: 10,
+                max_total_memory_bytes: 0,
+                max_reserved_compute_allocation: 0,
                 ssh_readonly_access: vec![],
                 ssh_backup_access: vec![],
                 ecdsa_config: None,
@@ -1,14 +1,18 @@
 use crate::{
     common::LOG_PREFIX,
     pb::v1::{
-        registry_stable_storage::Version as ReprVersion, ChangelogEntry, RegistryStableStorage,
+        registry_stable_storage::Version as ReprVersion, ChangelogEntry, LargeValueChunk,
+        RegistryStableStorage,
     },
 };
 use ic_certified_map::RbTree;
+use ic_crypto_sha2::Sha256;
 use ic_registry_transport::{
     pb::v1::{
-        registry_mutation::Type, RegistryAtomicMutateRequest, RegistryDelta, RegistryMutation,
-        RegistryValue,
+        high_capacity_registry_mutation, high_capacity_registry_value, registry_mutation::Type,
+        HighCapacityRegistryAtomicMutateRequest, HighCapacityRegistryDelta,
+        HighCapacityRegistryMutation, HighCapacityRegistryValue, LargeValueChunkKeys,
+        RegistryAtomicMutateRequest, RegistryDelta, RegistryMutation, RegistryValue,
     },
     Error,
 };
@@ -16,7 +20,7 @@ use ic_types::messages::MAX_INTER_CANISTER_PAYLOAD_IN_BYTES_U64;
 use prost::Message;
 use std::{
     cmp::max,
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     fmt,
 };
 
@@ -30,6 +34,15 @@ use dfn_core::println;
 pub const MAX_REGISTRY_DELTAS_SIZE: usize =
     2 * MAX_INTER_CANISTER_PAYLOAD_IN_BYTES_U64 as usize / 3;
 
+/// When a set of mutations would not fit into a single delta, its values
+/// of at least this size are stored as chunks, and only the keys of the chunks
+/// are recorded in the changelog.
+pub const MIN_CHUNKED_VALUE_SIZE: usize = 10 * 1024;
+
+/// The maximum size of a chunk of a large value. Chunks are returned one at a
+/// time, so they are subject to the same limit as registry deltas.
+pub const MAX_CHUNK_SIZE: usize = MAX_REGISTRY_DELTAS_SIZE;
+
 /// The type for the registry map.
 ///
 /// The Deque part is mostly future proofing for when we have garbage collection
@@ -94,6 +107,16 @@ pub struct Registry {
     /// RegistryAtomicMutateRequest.  We keep the serialized version around to
     /// make sure that hash trees stay the same even if protobuf schema evolves.
     pub(crate) changelog: RbTree<EncodedVersion, Vec<u8>>,
+
+    /// The chunks of large values, keyed by the SHA-256 of their content.
+    ///
+    /// The changelog only references large values by the keys of their
+    /// chunks, so this is needed to reconstruct `store` after an upgrade.
+    pub(crate) chunks: BTreeMap<Vec<u8>, Vec<u8>>,
+
+    /// The keys of the chunks of the values in `store` that are stored as
+    /// chunks, indexed by registry key and version.
+    pub(crate) large_values: BTreeMap<(Vec<u8>, Version), LargeValueChunkKeys>,
 }
 
 impl Registry {
@@ -109,6 +132,35 @@ impl Registry {
         version: u64,
         max_versions: Option<usize>,
     ) -> Vec<RegistryDelta> {
+        self.map_changes_since(version, max_versions, |_, value| value.clone())
+            .into_iter()
+            .map(|(key, values)| RegistryDelta { key, values })
+            .collect()
+    }
+
+    /// Same as `get_changes_since`, but values that are stored as chunks are
+    /// replaced by the keys of their chunks.
+    pub fn get_high_capacity_changes_since(
+        &self,
+        version: u64,
+        max_versions: Option<usize>,
+    ) -> Vec<HighCapacityRegistryDelta> {
+        self.map_changes_since(version, max_versions, |key, value| {
+            self.to_high_capacity(key, value)
+        })
+        .into_iter()
+        .map(|(key, values)| HighCapacityRegistryDelta { key, values })
+        .collect()
+    }
+
+    /// Applies `f` to every value versioned `(version, version + max_versions]`
+    /// and groups the results by key, dropping keys without such values.
+    fn map_changes_since<T>(
+        &self,
+        version: u64,
+        max_versions: Option<usize>,
+        f: impl Fn(&[u8], &RegistryValue) -> T,
+    ) -> Vec<(Vec<u8>, Vec<T>)> {
         let max_version = match max_versions {
             Some(max_versions) => version.saturating_add(max_versions as u64),
             None => std::u64::MAX,
@@ -117,18 +169,20 @@ impl Registry {
         self.store
             .iter()
             // For every key create a delta with values versioned `(version, max_version]`.
-            .map(|(key, values)| RegistryDelta {
-                key: key.clone(),
-                values: values
-                    .iter()
-                    .rev()
-                    .skip_while(|value| value.version > max_version)
-                    .take_while(|value| value.version > version)
-                    .cloned()
-                    .collect(),
+            .map(|(key, values)| {
+                (
+                    key.clone(),
+                    values
+                        .iter()
+                        .rev()
+                        .skip_while(|value| value.version > max_version)
+                        .take_while(|value| value.version > version)
+                        .map(|value| f(key, value))
+                        .collect::<Vec<_>>(),
+                )
             })
             // Drop empty deltas.
-            .filter(|delta| !delta.values.is_empty())
+            .filter(|(_, values)| !values.is_empty())
             .collect()
     }
 
@@ -149,6 +203,47 @@ impl Registry {
         Some(value)
     }
 
+    /// Same as `get`, but a value that is stored as chunks is replaced by the
+    /// keys of its chunks.
+    pub fn get_high_capacity(
+        &self,
+        key: &[u8],
+        version: Version,
+    ) -> Option<HighCapacityRegistryValue> {
+        self.get(key, version)
+            .map(|value| self.to_high_capacity(key, value))
+    }
+
+    /// Returns the content of the chunk with the given SHA-256, if any.
+    pub fn get_chunk(&self, content_sha256: &[u8]) -> Option<&[u8]> {
+        self.chunks.get(content_sha256).map(Vec::as_slice)
+    }
+
+    /// Returns the first version after `version` that stores a value as
+    /// chunks, if any.
+    pub fn first_chunked_version_after(&self, version: Version) -> Option<Version> {
+        self.large_values
+            .keys()
+            .map(|(_, chunked_version)| *chunked_version)
+            .filter(|chunked_version| *chunked_version > version)
+            .min()
+    }
+
+    /// Converts `value`, stored under `key`, to the representation returned
+    /// to clients.
+    fn to_high_capacity(&self, key: &[u8], value: &RegistryValue) -> HighCapacityRegistryValue {
+        match self.large_values.get(&(key.to_vec(), value.version)) {
+            Some(chunk_keys) => HighCapacityRegistryValue {
+                version: value.version,
+                deletion_marker: value.deletion_marker,
+                content: Some(high_capacity_registry_value::Content::LargeValueChunkKeys(
+                    chunk_keys.clone(),
+                )),
+            },
+            None => value.clone().into(),
+        }
+    }
+
     /// Computes the number of deltas with version greater than `since_version`
     /// that fit into the specified byte limit.
     ///
@@ -189,7 +284,7 @@ impl Registry {
 
     fn apply_mutations_as_version(
         &mut self,
-        mut mutations: Vec<RegistryMutation>,
+        mut mutations: Vec<HighCapacityRegistryMutation>,
         version: Version,
     ) {
         // We sort entries by key to eliminate the difference between changelog
@@ -209,27 +304,128 @@ impl Registry {
             //    INSERT entry is removed, the newly connected clients won't
             //    fail because of an UPDATE in the first survived entry with the
             //    same key.
-            m.mutation_type = match Type::from_i32(m.mutation_type).unwrap() {
-                Type::Insert | Type::Update | Type::Upsert => Type::Upsert,
-                Type::Delete => Type::Delete,
-            } as i32;
+            m.mutation_type = normalize_mutation_type(m.mutation_type);
         }
 
-        let req = RegistryAtomicMutateRequest {
+        let req = HighCapacityRegistryAtomicMutateRequest {
             mutations,
             preconditions: vec![],
         };
         self.changelog_insert(version, &req);
 
         for mutation in req.mutations {
+            let value = match mutation.content {
+                None => vec![],
+                Some(high_capacity_registry_mutation::Content::Value(value)) => value,
+                Some(high_capacity_registry_mutation::Content::LargeValueChunkKeys(chunk_keys)) => {
+                    let value = self.dechunkify(&chunk_keys);
+                    self.large_values
+                        .insert((mutation.key.clone(), version), chunk_keys);
+                    value
+                }
+            };
             (*self.store.entry(mutation.key).or_default()).push_back(RegistryValue {
                 version,
-                value: mutation.value,
+                value,
                 deletion_marker: mutation.mutation_type == Type::Delete as i32,
             });
         }
     }
 
+    /// Converts `mutations` to their changelog representation. If they would
+    /// not fit into a single delta, values of at least
+    /// [`MIN_CHUNKED_VALUE_SIZE`] are split into chunks, which are stored in
+    /// `chunks`, and the mutations only reference them.
+    ///
+    /// Mutations that fit are left unchanged, so that their changelog entry
+    /// is the same as before chunking was introduced.
+    fn chunkify_if_too_large(
+        &mut self,
+        mut mutations: Vec<RegistryMutation>,
+    ) -> Vec<HighCapacityRegistryMutation> {
+        // Measure the size of the changelog entry, i.e. after normalization.
+        for m in mutations.iter_mut() {
+            m.mutation_type = normalize_mutation_type(m.mutation_type);
+        }
+        let req = RegistryAtomicMutateRequest {
+            mutations,
+            preconditions: vec![],
+        };
+        let delta_size = std::mem::size_of::<EncodedVersion>() + req.encoded_len();
+        let too_large = delta_size > MAX_REGISTRY_DELTAS_SIZE;
+
+        req.mutations
+            .into_iter()
+            .map(|mutation| {
+                if !too_large || mutation.value.len() < MIN_CHUNKED_VALUE_SIZE {
+                    return mutation.into();
+                }
+                let chunk_keys = self.store_chunks(&mutation.value);
+                HighCapacityRegistryMutation {
+                    mutation_type: mutation.mutation_type,
+                    key: mutation.key,
+                    content: Some(
+                        high_capacity_registry_mutation::Content::LargeValueChunkKeys(chunk_keys),
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// Splits `value` into chunks of at most [`MAX_CHUNK_SIZE`] bytes, stores
+    /// them under the SHA-256 of their content and returns their keys.
+    fn store_chunks(&mut self, value: &[u8]) -> LargeValueChunkKeys {
+        let chunk_content_sha256s = value
+            .chunks(MAX_CHUNK_SIZE)
+            .map(|chunk| {
+                let content_sha256 = Sha256::hash(chunk).to_vec();
+                self.chunks
+                    .entry(content_sha256.clone())
+                    .or_insert_with(|| chunk.to_vec());
+                content_sha256
+            })
+            .collect();
+        LargeValueChunkKeys {
+            chunk_content_sha256s,
+        }
+    }
+
+    /// Removes the chunks that are not referenced by any value, and returns
+    /// how many were removed.
+    ///
+    /// Chunks are only referenced by the changelog, which is never pruned, so
+    /// this only removes chunks that were stored without the changelog entry
+    /// that references them, e.g. by an earlier version of this canister.
+    fn garbage_collect_chunks(&mut self) -> usize {
+        let referenced: BTreeSet<Vec<u8>> = self
+            .large_values
+            .values()
+            .flat_map(|chunk_keys| chunk_keys.chunk_content_sha256s.iter().cloned())
+            .collect();
+        let num_chunks = self.chunks.len();
+        self.chunks
+            .retain(|content_sha256, _| referenced.contains(content_sha256));
+        num_chunks - self.chunks.len()
+    }
+
+    /// Reassembles a value from its chunks.
+    ///
+    /// Panics if a chunk is missing, as the changelog must only reference
+    /// chunks that are stored.
+    fn dechunkify(&self, chunk_keys: &LargeValueChunkKeys) -> Vec<u8> {
+        let mut value = vec![];
+        for content_sha256 in &chunk_keys.chunk_content_sha256s {
+            let chunk = self.chunks.get(content_sha256).unwrap_or_else(|| {
+                panic!(
+                    "{}Chunk {:?} of a large value is missing.",
+                    LOG_PREFIX, content_sha256
+                )
+            });
+            value.extend_from_slice(chunk);
+        }
+        value
+    }
+
     /// Applies the given mutations, without any check corresponding
     /// to the mutation_type.
     ///
@@ -243,6 +439,7 @@ impl Registry {
             return;
         }
         self.increment_version();
+        let mutations = self.chunkify_if_too_large(mutations);
         self.apply_mutations_as_version(mutations, self.version);
     }
 
@@ -319,6 +516,14 @@ impl Registry {
                         encoded_mutation: bytes.clone(),
                     })
                     .collect(),
+                chunks: self
+                    .chunks
+                    .iter()
+                    .map(|(content_sha256, content)| LargeValueChunk {
+                        content_sha256: content_sha256.clone(),
+                        content: content.clone(),
+                    })
+                    .collect(),
             },
             ReprVersion::Unspecified => RegistryStableStorage {
                 version: repr_version as i32,
@@ -331,6 +536,7 @@ impl Registry {
                     })
                     .collect(),
                 changelog: vec![],
+                chunks: vec![],
             },
         }
     }
@@ -345,7 +551,10 @@ impl Registry {
 
     /// Inserts a changelog entry at the given version, while enforcing the
     /// [`MAX_REGISTRY_DELTAS_SIZE`] limit.
-    fn changelog_insert(&mut self, version: u64, req: &RegistryAtomicMutateRequest) {
+    ///
+    /// `req` is either a `RegistryAtomicMutateRequest` or a
+    /// `HighCapacityRegistryAtomicMutateRequest`.
+    fn changelog_insert(&mut self, version: u64, req: &impl Message) {
         let version = EncodedVersion::from(version);
         let bytes = pb_encode(req);
 
@@ -374,6 +583,7 @@ impl Registry {
     pub fn from_serializable_form(&mut self, stable_repr: RegistryStableStorage) {
         assert!(self.store.is_empty());
         assert!(self.changelog.is_empty());
+        assert!(self.chunks.is_empty());
         assert_eq!(self.version, 0);
 
         let repr_version = ReprVersion::from_i32(stable_repr.version).unwrap_or_else(|| {
@@ -385,6 +595,18 @@ impl Registry {
 
         match repr_version {
             ReprVersion::Version1 => {
+                // Chunks have to be restored first, as they are needed to
+                // reconstruct large values when replaying the changelog.
+                for chunk in stable_repr.chunks {
+                    assert_eq!(
+                        Sha256::hash(&chunk.content).to_vec(),
+                        chunk.content_sha256,
+                        "Content of chunk {:?} does not match its hash",
+                        chunk.content_sha256
+                    );
+                    self.chunks.insert(chunk.content_sha256, chunk.content);
+                }
+
                 let mut current_version = 0;
                 for entry in stable_repr.changelog {
                     // Code to fix ICSUP-2589.
@@ -399,16 +621,19 @@ impl Registry {
                             mutation_type: Type::Upsert as i32,
                             key: "_".into(),
                             value: "".into(),
-                        }];
+                        }
+                        .into()];
                         self.apply_mutations_as_version(mutations, i);
                         self.version = i;
                     }
                     // End code to fix ICSUP-2589
 
-                    let req = RegistryAtomicMutateRequest::decode(&entry.encoded_mutation[..])
-                        .unwrap_or_else(|err| {
-                            panic!("Failed to decode mutation@{}: {}", entry.version, err)
-                        });
+                    let req = HighCapacityRegistryAtomicMutateRequest::decode(
+                        &entry.encoded_mutation[..],
+                    )
+                    .unwrap_or_else(|err| {
+                        panic!("Failed to decode mutation@{}: {}", entry.version, err)
+                    });
                     self.apply_mutations_as_version(req.mutations, entry.version);
                     self.version = entry.version;
                     current_version = self.version;
@@ -440,25 +665,38 @@ impl Registry {
                                 value: v.value.clone(),
                             })
                     }
-
-                    self.store.insert(delta.key, VecDeque::from(delta.values));
                 }
                 // We iterated over keys in ascending order, so the mutations
                 // must also be sorted by key, resulting in canonical encoding.
+                // Large values are chunked exactly as they were when the
+                // mutations were first applied, as chunks are
+                // content-addressed.
                 for (v, mutations) in mutations_by_version.into_iter() {
-                    self.changelog_insert(
-                        v,
-                        &RegistryAtomicMutateRequest {
-                            mutations,
-                            preconditions: vec![],
-                        },
-                    );
+                    let mutations = self.chunkify_if_too_large(mutations);
+                    self.apply_mutations_as_version(mutations, v);
                 }
             }
         }
+
+        let num_removed_chunks = self.garbage_collect_chunks();
+        if num_removed_chunks > 0 {
+            println!(
+                "{}Removed {} chunks that are not referenced by any value.",
+                LOG_PREFIX, num_removed_chunks
+            );
+        }
     }
 }
 
+/// Normalizes all the INSERT/UPDATE/UPSERT operations to be just UPSERTs (see
+/// `apply_mutations_as_version`).
+fn normalize_mutation_type(mutation_type: i32) -> i32 {
+    (match Type::from_i32(mutation_type).unwrap() {
+        Type::Insert | Type::Update | Type::Upsert => Type::Upsert,
+        Type::Delete => Type::Delete,
+    }) as i32
+}
+
 fn pb_encode(msg: &impl prost::Message) -> Vec<u8> {
     let mut buf = vec![];
     msg.encode(&mut buf).unwrap();
@@ -1052,14 +1290,126 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "[Registry] Transaction rejected because delta would be too large")]
-    fn test_apply_mutations_delta_too_large() {
+    fn test_apply_mutations_large_value_is_chunked() {
         let mut registry = Registry::new();
         let version = 1;
         let key = b"key";
+        let small_key = b"small_key";
 
-        let too_large_value = vec![0; max_mutation_value_size(version, key) + 1];
-        let mutations = vec![upsert(key, too_large_value)];
+        let large_value: Vec<u8> = (0..2 * MAX_CHUNK_SIZE + 1).map(|i| i as u8).collect();
+        let small_value = vec![1, 2, 3];
+        let mutations = vec![upsert(key, &large_value), upsert(small_key, &small_value)];
+        apply_mutations_skip_invariant_checks(&mut registry, mutations);
+
+        assert_eq!(registry.latest_version(), version);
+        assert_eq!(registry.chunks.len(), 3);
+        assert_eq!(
+            registry.get(key, version),
+            Some(&RegistryValue {
+                value: large_value.clone(),
+                version,
+                deletion_marker: false
+            })
+        );
+
+        // Clients only see the keys of the chunks, which resolve to the value.
+        let chunk_keys = match registry.get_high_capacity(key, version).unwrap().content {
+            Some(high_capacity_registry_value::Content::LargeValueChunkKeys(chunk_keys)) => {
+                chunk_keys
+            }
+            content => panic!("Expected the value to be chunked, got {:?}", content),
+        };
+        let resolved: Vec<u8> = chunk_keys
+            .chunk_content_sha256s
+            .iter()
+            .flat_map(|content_sha256| registry.get_chunk(content_sha256).unwrap().to_vec())
+            .collect();
+        assert_eq!(resolved, large_value);
+
+        // Small values are not chunked.
+        assert_eq!(
+            registry.get_high_capacity(small_key, version),
+            Some(HighCapacityRegistryValue {
+                version,
+                deletion_marker: false,
+                content: Some(high_capacity_registry_value::Content::Value(small_value)),
+            })
+        );
+        let deltas = registry.get_high_capacity_changes_since(0, None);
+        assert_eq!(deltas.len(), 2);
+
+        serialize_then_deserialize(registry);
+    }
+
+    #[test]
+    fn test_first_chunked_version_after() {
+        let mut registry = Registry::new();
+        let large_value = vec![0; MAX_REGISTRY_DELTAS_SIZE];
+
+        apply_mutations_skip_invariant_checks(&mut registry, vec![upsert(b"small", b"1")]);
+        apply_mutations_skip_invariant_checks(&mut registry, vec![upsert(b"large", &large_value)]);
+        apply_mutations_skip_invariant_checks(&mut registry, vec![upsert(b"small", b"2")]);
+
+        assert_eq!(registry.first_chunked_version_after(0), Some(2));
+        assert_eq!(registry.first_chunked_version_after(1), Some(2));
+        assert_eq!(registry.first_chunked_version_after(2), None);
+    }
+
+    #[test]
+    fn test_from_serializable_form_removes_unreferenced_chunks() {
+        let mut registry = Registry::new();
+        let large_value = vec![0; MAX_REGISTRY_DELTAS_SIZE];
+        apply_mutations_skip_invariant_checks(&mut registry, vec![upsert(b"large", &large_value)]);
+
+        let mut stable_repr = registry.serializable_form_at(ReprVersion::Version1);
+        let orphan = b"orphan".to_vec();
+        stable_repr.chunks.push(LargeValueChunk {
+            content_sha256: Sha256::hash(&orphan).to_vec(),
+            content: orphan.clone(),
+        });
+
+        let mut deserialized = Registry::new();
+        deserialized.from_serializable_form(stable_repr);
+
+        assert_eq!(deserialized.get_chunk(&Sha256::hash(&orphan)), None);
+        assert_eq!(deserialized, registry);
+    }
+
+    #[test]
+    fn test_apply_mutations_small_values_are_not_chunked() {
+        let mut registry = Registry::new();
+        let version = 1;
+        let key = b"key";
+
+        let max_value = vec![0; max_mutation_value_size(version, key)];
+        let mutations = vec![upsert(key, &max_value)];
+        apply_mutations_skip_invariant_checks(&mut registry, mutations.clone());
+
+        assert!(registry.chunks.is_empty());
+        assert!(registry.large_values.is_empty());
+        // The changelog entry is the same as it was before chunking.
+        assert_eq!(
+            registry
+                .changelog()
+                .get(EncodedVersion::from(version).as_ref())
+                .unwrap(),
+            &pb_encode(&RegistryAtomicMutateRequest {
+                mutations,
+                preconditions: vec![],
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "[Registry] Transaction rejected because delta would be too large")]
+    fn test_apply_mutations_delta_too_large() {
+        let mut registry = Registry::new();
+
+        // Many values, none of which is large enough to be chunked.
+        let value = vec![0; MIN_CHUNKED_VALUE_SIZE - 1];
+        let mutations = (0..MAX_REGISTRY_DELTAS_SIZE / MIN_CHUNKED_VALUE_SIZE + 1)
+            .map(|i| upsert(format!("key{}", i), &value))
+            .collect();
 
         apply_mutations_skip_invariant_checks(&mut registry, mutations);
     }
@@ -1115,9 +1465,31 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "[Registry] Transaction rejected because delta would be too large")]
     fn test_from_serializable_form_version_unspecified_delta_too_large() {
-        test_from_serializable_form_impl(1, ReprVersion::Unspecified)
+        let mut registry = Registry::new();
+        let version = 1;
+        let key = b"key";
+        let value = vec![0; max_mutation_value_size(version, key) + 1];
+        registry
+            .store
+            .entry(key.to_vec())
+            .or_default()
+            .push_back(RegistryValue {
+                version,
+                value: value.clone(),
+                deletion_marker: false,
+            });
+        registry.version = version;
+
+        let stable_repr = registry.serializable_form_at(ReprVersion::Unspecified);
+        let mut deserialized = Registry::new();
+        deserialized.from_serializable_form(stable_repr);
+
+        // The value no longer fits into a delta, so it is stored as chunks.
+        assert_eq!(deserialized.get(key, version).unwrap().value, value);
+        assert!(deserialized
+            .large_values
+            .contains_key(&(key.to_vec(), version)));
     }
 
     #[test]
@@ -52,6 +52,7 @@ fn get_value_request(key: impl AsRef<[u8]>, version: Option<u64>) -> RegistryGet
     RegistryGetValueRequest {
         version,
         key: key.as_ref().to_vec(),
+        chunk_aware: false,
     }
 }
 
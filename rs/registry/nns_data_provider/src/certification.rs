@@ -2,7 +2,9 @@ use ic_certification::{verify_certified_data, CertificateValidationError};
 use ic_crypto_tree_hash::{LabeledTree, MixedHashTree};
 use ic_interfaces_registry::RegistryTransportRecord;
 use ic_registry_transport::pb::v1::{
-    registry_mutation::Type, CertifiedResponse, RegistryAtomicMutateRequest,
+    high_capacity_registry_mutation, high_capacity_registry_value, registry_mutation::Type,
+    CertifiedResponse, HighCapacityRegistryAtomicMutateRequest, HighCapacityRegistryDelta,
+    HighCapacityRegistryValue,
 };
 use ic_types::{
     crypto::threshold_sig::ThresholdSigPublicKey, CanisterId, RegistryVersion, SubnetId, Time,
@@ -50,7 +52,7 @@ pub enum CertificationError {
 struct CertifiedPayload {
     current_version: Leb128EncodedU64,
     #[serde(default)]
-    delta: BTreeMap<u64, Protobuf<RegistryAtomicMutateRequest>>,
+    delta: BTreeMap<u64, Protobuf<HighCapacityRegistryAtomicMutateRequest>>,
 }
 
 fn embed_certificate_error(err: CertificateValidationError) -> CertificationError {
@@ -119,10 +121,26 @@ fn validate_version_range(
 }
 
 /// Decodes registry deltas from their hash tree representation.
+///
+/// Fails if any of the deltas contains a value that is stored as chunks, see
+/// `decode_high_capacity_hash_tree`.
 pub fn decode_hash_tree(
     since_version: u64,
     hash_tree: MixedHashTree,
 ) -> Result<(Vec<RegistryTransportRecord>, RegistryVersion), CertificationError> {
+    let (deltas, current_version) = decode_high_capacity_hash_tree(since_version, hash_tree)?;
+    Ok((inline_records(deltas)?, current_version))
+}
+
+/// Decodes registry deltas from their hash tree representation, leaving
+/// values that are stored as chunks unresolved.
+///
+/// Each returned delta contains a single value, and the deltas are sorted by
+/// version.
+pub fn decode_high_capacity_hash_tree(
+    since_version: u64,
+    hash_tree: MixedHashTree,
+) -> Result<(Vec<HighCapacityRegistryDelta>, RegistryVersion), CertificationError> {
     // Extract structured deltas from their tree representation.
     let labeled_tree = LabeledTree::<Vec<u8>>::try_from(hash_tree).map_err(|err| {
         CertificationError::MalformedHashTree(format!(
@@ -150,15 +168,26 @@ pub fn decode_hash_tree(
         .into_iter()
         .flat_map(|(v, mutate_req)| {
             mutate_req.0.mutations.into_iter().map(move |m| {
-                let value = if m.mutation_type == Type::Delete as i32 {
+                let deletion_marker = m.mutation_type == Type::Delete as i32;
+                let content = if deletion_marker {
                     None
                 } else {
-                    Some(m.value)
+                    m.content.map(|content| match content {
+                        high_capacity_registry_mutation::Content::Value(value) => {
+                            high_capacity_registry_value::Content::Value(value)
+                        }
+                        high_capacity_registry_mutation::Content::LargeValueChunkKeys(
+                            chunk_keys,
+                        ) => high_capacity_registry_value::Content::LargeValueChunkKeys(chunk_keys),
+                    })
                 };
-                RegistryTransportRecord {
-                    key: String::from_utf8_lossy(&m.key[..]).to_string(),
-                    value,
-                    version: RegistryVersion::from(v),
+                HighCapacityRegistryDelta {
+                    key: m.key,
+                    values: vec![HighCapacityRegistryValue {
+                        version: v,
+                        deletion_marker,
+                        content,
+                    }],
                 }
             })
         })
@@ -167,18 +196,65 @@ pub fn decode_hash_tree(
     Ok((changes, RegistryVersion::from(current_version)))
 }
 
+/// Converts deltas as returned by `decode_high_capacity_hash_tree` into
+/// transport records, failing on values that are stored as chunks.
+fn inline_records(
+    deltas: Vec<HighCapacityRegistryDelta>,
+) -> Result<Vec<RegistryTransportRecord>, CertificationError> {
+    let mut records = vec![];
+    for delta in deltas {
+        let key = String::from_utf8_lossy(&delta.key[..]).to_string();
+        for value in delta.values {
+            let record_value = match value.content {
+                _ if value.deletion_marker => None,
+                None => Some(vec![]),
+                Some(high_capacity_registry_value::Content::Value(value)) => Some(value),
+                Some(high_capacity_registry_value::Content::LargeValueChunkKeys(_)) => {
+                    return Err(CertificationError::InvalidDeltas(format!(
+                        "value of {} at version {} is stored as chunks",
+                        key, value.version
+                    )));
+                }
+            };
+            records.push(RegistryTransportRecord {
+                key: key.clone(),
+                value: record_value,
+                version: RegistryVersion::from(value.version),
+            });
+        }
+    }
+    Ok(records)
+}
+
 /// Parses a response of the "get_certified_changes_since" registry method,
 /// validates data integrity and authenticity and returns
 ///   * The list of changes to apply.
 ///   * The latest version available (might be greater than the version of the
 ///     last received delta if there were too many deltas to send in one go).
 ///   * The time when the received data was last certified by the subnet.
+///
+/// Fails if any of the deltas contains a value that is stored as chunks, see
+/// `decode_certified_high_capacity_deltas`.
 pub fn decode_certified_deltas(
     since_version: u64,
     canister_id: &CanisterId,
     nns_pk: &ThresholdSigPublicKey,
     payload: &[u8],
 ) -> Result<(Vec<RegistryTransportRecord>, RegistryVersion, Time), CertificationError> {
+    let (deltas, current_version, time) =
+        decode_certified_high_capacity_deltas(since_version, canister_id, nns_pk, payload)?;
+    Ok((inline_records(deltas)?, current_version, time))
+}
+
+/// Same as `decode_certified_deltas`, but values that are stored as chunks
+/// are returned as the keys of their chunks. The chunks are
+/// content-addressed, so they can be fetched and verified separately.
+pub fn decode_certified_high_capacity_deltas(
+    since_version: u64,
+    canister_id: &CanisterId,
+    nns_pk: &ThresholdSigPublicKey,
+    payload: &[u8],
+) -> Result<(Vec<HighCapacityRegistryDelta>, RegistryVersion, Time), CertificationError> {
     let certified_response = CertifiedResponse::decode(payload).map_err(|err| {
         CertificationError::DeserError(format!(
             "failed to decode certified response from {}: {:?}",
@@ -209,7 +285,8 @@ pub fn decode_certified_deltas(
     )
     .map_err(embed_certificate_error)?;
 
-    let (changes, current_version) = decode_hash_tree(since_version, mixed_hash_tree)?;
+    let (changes, current_version) =
+        decode_high_capacity_hash_tree(since_version, mixed_hash_tree)?;
 
     Ok((changes, current_version, time))
 }
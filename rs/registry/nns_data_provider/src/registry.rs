@@ -5,14 +5,20 @@ use std::time::Duration;
 use url::Url;
 
 use ic_canister_client::{Agent, Sender};
+use ic_crypto_sha2::Sha256;
 use ic_interfaces_registry::RegistryTransportRecord;
 use ic_registry_transport::{
-    deserialize_atomic_mutate_response, deserialize_get_changes_since_response,
-    deserialize_get_value_response, serialize_atomic_mutate_request,
-    serialize_get_changes_since_request, serialize_get_value_request,
+    deserialize_atomic_mutate_response, deserialize_get_chunk_response,
+    deserialize_high_capacity_get_changes_since_response,
+    deserialize_high_capacity_get_value_response, serialize_atomic_mutate_request,
+    serialize_get_changes_since_request, serialize_get_chunk_request, serialize_get_value_request,
 };
 use ic_registry_transport::{
-    pb::v1::{Precondition, RegistryDelta, RegistryMutation},
+    pb::v1::{
+        high_capacity_registry_get_value_response, high_capacity_registry_value,
+        HighCapacityRegistryDelta, LargeValueChunkKeys, Precondition, RegistryDelta,
+        RegistryMutation, RegistryValue,
+    },
     Error,
 };
 use ic_types::{crypto::threshold_sig::ThresholdSigPublicKey, CanisterId, RegistryVersion, Time};
@@ -61,6 +67,7 @@ impl RegistryCanister {
     }
 
     /// Queries the registry for all changes that occurred since 'version'.
+    /// Values that are stored as chunks are fetched and reassembled.
     ///
     /// On each request a random NNS-hosting replica is chosen to send the
     /// request to.
@@ -75,7 +82,11 @@ impl RegistryCanister {
             .await
         {
             Ok(result) => match result {
-                Some(response) => deserialize_get_changes_since_response(response),
+                Some(response) => {
+                    let (deltas, latest_version) =
+                        deserialize_high_capacity_get_changes_since_response(response)?;
+                    Ok((self.dechunkify_deltas(deltas).await?, latest_version))
+                }
                 None => Err(ic_registry_transport::Error::UnknownError(
                     "No response was received from registry_get_changes_since.".to_string(),
                 )),
@@ -131,13 +142,23 @@ impl RegistryCanister {
                 ))
             })?;
 
-        crate::certification::decode_certified_deltas(
-            version,
-            &self.canister_id,
-            nns_public_key,
-            &response[..],
-        )
-        .map_err(|err| Error::UnknownError(format!("{:?}", err)))
+        let (deltas, latest_version, time) =
+            crate::certification::decode_certified_high_capacity_deltas(
+                version,
+                &self.canister_id,
+                nns_public_key,
+                &response[..],
+            )
+            .map_err(|err| Error::UnknownError(format!("{:?}", err)))?;
+
+        // The keys of the chunks are certified and the chunks are verified
+        // against them, so the reassembled values can be trusted as well.
+        let deltas = self.dechunkify_deltas(deltas).await?;
+        Ok((
+            registry_deltas_to_registry_transport_records(deltas)?,
+            latest_version,
+            time,
+        ))
     }
 
     pub async fn get_latest_version(&self) -> Result<u64, Error> {
@@ -180,7 +201,22 @@ impl RegistryCanister {
             .await
         {
             Ok(result) => match result {
-                Some(response) => deserialize_get_value_response(response),
+                Some(response) => {
+                    let (content, version) =
+                        deserialize_high_capacity_get_value_response(response)?;
+                    let value = match content {
+                        None => vec![],
+                        Some(high_capacity_registry_get_value_response::Content::Value(value)) => {
+                            value
+                        }
+                        Some(
+                            high_capacity_registry_get_value_response::Content::LargeValueChunkKeys(
+                                chunk_keys,
+                            ),
+                        ) => self.dechunkify(&chunk_keys).await?,
+                    };
+                    Ok((value, version))
+                }
                 None => Err(ic_registry_transport::Error::UnknownError(
                     "No response was received from registry_get_value.".to_string(),
                 )),
@@ -225,6 +261,77 @@ impl RegistryCanister {
             ))]),
         }
     }
+
+    /// Fetches the chunk with the given SHA-256 and verifies its content.
+    async fn get_chunk(&self, content_sha256: &[u8]) -> Result<Vec<u8>, Error> {
+        let payload = serialize_get_chunk_request(content_sha256.to_vec()).unwrap();
+        let content = match self
+            .choose_random_agent()
+            .execute_query(&self.canister_id, "get_chunk", payload)
+            .await
+        {
+            Ok(Some(response)) => deserialize_get_chunk_response(response)?,
+            Ok(None) => {
+                return Err(ic_registry_transport::Error::UnknownError(
+                    "No response was received from registry_get_chunk.".to_string(),
+                ))
+            }
+            Err(error_string) => {
+                return Err(ic_registry_transport::Error::UnknownError(format!(
+                    "Error on registry_get_chunk: {}",
+                    error_string
+                )))
+            }
+        };
+
+        if Sha256::hash(&content) != content_sha256 {
+            return Err(ic_registry_transport::Error::MalformedMessage(format!(
+                "Content of chunk {:?} does not match its hash",
+                content_sha256
+            )));
+        }
+        Ok(content)
+    }
+
+    /// Reassembles a value that is stored as chunks.
+    async fn dechunkify(&self, chunk_keys: &LargeValueChunkKeys) -> Result<Vec<u8>, Error> {
+        let mut value = vec![];
+        for content_sha256 in &chunk_keys.chunk_content_sha256s {
+            value.extend(self.get_chunk(content_sha256).await?);
+        }
+        Ok(value)
+    }
+
+    /// Converts `deltas` to `RegistryDelta`s, reassembling the values that are
+    /// stored as chunks.
+    async fn dechunkify_deltas(
+        &self,
+        deltas: Vec<HighCapacityRegistryDelta>,
+    ) -> Result<Vec<RegistryDelta>, Error> {
+        let mut result = Vec::with_capacity(deltas.len());
+        for delta in deltas {
+            let mut values = Vec::with_capacity(delta.values.len());
+            for value in delta.values {
+                let content = match value.content {
+                    None => vec![],
+                    Some(high_capacity_registry_value::Content::Value(value)) => value,
+                    Some(high_capacity_registry_value::Content::LargeValueChunkKeys(
+                        chunk_keys,
+                    )) => self.dechunkify(&chunk_keys).await?,
+                };
+                values.push(RegistryValue {
+                    value: content,
+                    version: value.version,
+                    deletion_marker: value.deletion_marker,
+                });
+            }
+            result.push(RegistryDelta {
+                key: delta.key,
+                values,
+            });
+        }
+        Ok(result)
+    }
 }
 
 /// Convert `Vec<RegistryDelta>` to `Vec<RegistryTransportRecord>`.
@@ -96,6 +96,13 @@ pub struct RegistryDelta {
 pub struct RegistryGetChangesSinceRequest {
     #[prost(uint64, tag = "1")]
     pub version: u64,
+    /// Set by clients that recognize values stored as chunks (see
+    /// LargeValueChunkKeys). Other clients only get the versions before the
+    /// first one that stores a value as chunks, with `error` set in the
+    /// uncertified response, so that they never mistake such a value for an
+    /// empty one.
+    #[prost(bool, tag = "2")]
+    pub chunk_aware: bool,
 }
 /// Message corresponding to the response from the registry
 /// canister to a get_latest_version() request.
@@ -130,6 +137,11 @@ pub struct RegistryGetValueRequest {
     /// Required.
     #[prost(bytes = "vec", tag = "2")]
     pub key: ::prost::alloc::vec::Vec<u8>,
+    /// Set by clients that recognize values stored as chunks (see
+    /// LargeValueChunkKeys). Other clients get an error instead of a value
+    /// that is stored as chunks.
+    #[prost(bool, tag = "3")]
+    pub chunk_aware: bool,
 }
 /// Message corresponding to the response from the canister
 /// to a get_value() request.
@@ -267,6 +279,138 @@ pub struct RegistryAtomicMutateResponse {
     #[prost(uint64, tag = "2")]
     pub version: u64,
 }
+/// Values that are too large to be included in a single response are split
+/// into chunks that are stored separately, under the SHA-256 of their content.
+/// The original value is the concatenation of the chunks, in order.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LargeValueChunkKeys {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub chunk_content_sha256s: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HighCapacityRegistryValue {
+    #[prost(uint64, tag = "2")]
+    pub version: u64,
+    #[prost(bool, tag = "3")]
+    pub deletion_marker: bool,
+    #[prost(oneof = "high_capacity_registry_value::Content", tags = "1, 4")]
+    pub content: ::core::option::Option<high_capacity_registry_value::Content>,
+}
+/// Nested message and enum types in `HighCapacityRegistryValue`.
+pub mod high_capacity_registry_value {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Content {
+        /// Same as RegistryValue.value.
+        #[prost(bytes, tag = "1")]
+        Value(::prost::alloc::vec::Vec<u8>),
+        /// Set instead of value if the value is stored as chunks.
+        #[prost(message, tag = "4")]
+        LargeValueChunkKeys(super::LargeValueChunkKeys),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HighCapacityRegistryDelta {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, repeated, tag = "2")]
+    pub values: ::prost::alloc::vec::Vec<HighCapacityRegistryValue>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HighCapacityRegistryGetChangesSinceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub error: ::core::option::Option<RegistryError>,
+    #[prost(uint64, tag = "2")]
+    pub version: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub deltas: ::prost::alloc::vec::Vec<HighCapacityRegistryDelta>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HighCapacityRegistryGetValueResponse {
+    #[prost(message, optional, tag = "1")]
+    pub error: ::core::option::Option<RegistryError>,
+    #[prost(uint64, tag = "2")]
+    pub version: u64,
+    #[prost(
+        oneof = "high_capacity_registry_get_value_response::Content",
+        tags = "3, 4"
+    )]
+    pub content: ::core::option::Option<high_capacity_registry_get_value_response::Content>,
+}
+/// Nested message and enum types in `HighCapacityRegistryGetValueResponse`.
+pub mod high_capacity_registry_get_value_response {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Content {
+        /// Same as RegistryGetValueResponse.value.
+        #[prost(bytes, tag = "3")]
+        Value(::prost::alloc::vec::Vec<u8>),
+        /// Set instead of value if the value is stored as chunks.
+        #[prost(message, tag = "4")]
+        LargeValueChunkKeys(super::LargeValueChunkKeys),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HighCapacityRegistryMutation {
+    #[prost(enumeration = "registry_mutation::Type", tag = "1")]
+    pub mutation_type: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(oneof = "high_capacity_registry_mutation::Content", tags = "3, 5")]
+    pub content: ::core::option::Option<high_capacity_registry_mutation::Content>,
+}
+/// Nested message and enum types in `HighCapacityRegistryMutation`.
+pub mod high_capacity_registry_mutation {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Content {
+        /// Same as RegistryMutation.value.
+        #[prost(bytes, tag = "3")]
+        Value(::prost::alloc::vec::Vec<u8>),
+        /// Set instead of value if the value is stored as chunks.
+        #[prost(message, tag = "5")]
+        LargeValueChunkKeys(super::LargeValueChunkKeys),
+    }
+}
+/// The changelog of the registry canister, which is what gets certified,
+/// consists of these. Chunked values are only referenced by the SHA-256 of
+/// their chunks, so the certification covers their content as well.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HighCapacityRegistryAtomicMutateRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub mutations: ::prost::alloc::vec::Vec<HighCapacityRegistryMutation>,
+    #[prost(message, repeated, tag = "5")]
+    pub preconditions: ::prost::alloc::vec::Vec<Precondition>,
+}
+/// Message to retrieve a chunk of a large value from the registry canister.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetChunkRequest {
+    /// The SHA-256 of the content of the chunk, as found in
+    /// LargeValueChunkKeys.
+    #[prost(bytes = "vec", tag = "1")]
+    pub content_sha256: ::prost::alloc::vec::Vec<u8>,
+}
+/// Message corresponding to the response from the registry canister to a
+/// get_chunk() request.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetChunkResponse {
+    /// Set if the chunk is not known to the registry canister.
+    #[prost(message, optional, tag = "1")]
+    pub error: ::core::option::Option<RegistryError>,
+    /// The content of the chunk. Callers are expected to verify that its
+    /// SHA-256 matches the requested one.
+    #[prost(bytes = "vec", tag = "2")]
+    pub content: ::prost::alloc::vec::Vec<u8>,
+}
 /// Message encoding a response to any *_certified method call.
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
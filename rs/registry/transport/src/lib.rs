@@ -4,8 +4,8 @@ pub mod pb;
 use std::{fmt, str};
 
 use crate::pb::v1::{
-    registry_error::Code, registry_mutation::Type, Precondition, RegistryDelta, RegistryError,
-    RegistryMutation,
+    registry_error::Code, registry_mutation::Type, HighCapacityRegistryDelta, Precondition,
+    RegistryDelta, RegistryError, RegistryMutation, RegistryValue,
 };
 use prost::Message;
 use serde::{Deserialize, Serialize};
@@ -113,12 +113,17 @@ impl From<Error> for RegistryError {
 
 /// Serializes the arguments for a request to the get_value() function in the
 /// registry canister, into protobuf.
+///
+/// The request is marked as `chunk_aware`: a value that is stored as chunks is
+/// returned as the keys of its chunks, see
+/// `deserialize_high_capacity_get_value_response`.
 pub fn serialize_get_value_request(
     key: Vec<u8>,
     version_opt: Option<u64>,
 ) -> Result<Vec<u8>, Error> {
     let mut request: pb::v1::RegistryGetValueRequest = pb::v1::RegistryGetValueRequest {
         key,
+        chunk_aware: true,
         ..Default::default()
     };
     if let Some(version) = version_opt {
@@ -134,9 +139,11 @@ pub fn serialize_get_value_request(
 
 /// Deserializes the arguments for a request to the get_value() function in the
 /// registry canister, from protobuf.
-pub fn deserialize_get_value_request(request: Vec<u8>) -> Result<(Vec<u8>, Option<u64>), Error> {
+pub fn deserialize_get_value_request(
+    request: Vec<u8>,
+) -> Result<pb::v1::RegistryGetValueRequest, Error> {
     match pb::v1::RegistryGetValueRequest::decode(&request[..]) {
-        Ok(request) => Ok((request.key, request.version)),
+        Ok(request) => Ok(request),
         Err(error) => Err(Error::MalformedMessage(error.to_string())),
     }
 }
@@ -158,15 +165,19 @@ pub fn serialize_get_value_response(
 
 /// Deserializes the response obtained from the registry canister for a
 /// get_value() call, from protobuf.
+///
+/// Fails if the value is stored as chunks, see
+/// `deserialize_high_capacity_get_value_response`.
 pub fn deserialize_get_value_response(response: Vec<u8>) -> Result<(Vec<u8>, u64), Error> {
-    match pb::v1::RegistryGetValueResponse::decode(&response[..]) {
-        Ok(response) => {
-            if let Some(error) = response.error {
-                return Err(Error::from(error));
-            }
-            Ok((response.value, response.version))
-        }
-        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    use pb::v1::high_capacity_registry_get_value_response::Content;
+
+    match deserialize_high_capacity_get_value_response(response)? {
+        (None, version) => Ok((vec![], version)),
+        (Some(Content::Value(value)), version) => Ok((value, version)),
+        (Some(Content::LargeValueChunkKeys(_)), version) => Err(Error::MalformedMessage(format!(
+            "The value at version {} is stored as chunks",
+            version
+        ))),
     }
 }
 
@@ -194,11 +205,13 @@ pub fn deserialize_get_latest_version_response(response: Vec<u8>) -> Result<u64,
         .map_err(|e| Error::MalformedMessage(e.to_string()))
 }
 
-/// Deserializes the response obtained from the registry canister for a
-/// get_changes_since() call, from protobuf.
-pub fn deserialize_get_changes_since_request(request: Vec<u8>) -> Result<u64, Error> {
+/// Deserializes the arguments for a request to the get_changes_since()
+/// function in the registry canister, from protobuf.
+pub fn deserialize_get_changes_since_request(
+    request: Vec<u8>,
+) -> Result<pb::v1::RegistryGetChangesSinceRequest, Error> {
     match pb::v1::RegistryGetChangesSinceRequest::decode(&request[..]) {
-        Ok(request) => Ok(request.version),
+        Ok(request) => Ok(request),
         Err(error) => Err(Error::MalformedMessage(error.to_string())),
     }
 }
@@ -221,12 +234,19 @@ pub fn serialize_get_changes_since_response(
 
 /// Serializes a request for a get_changes_since() request to the registry
 /// canister.
+///
+/// The request is marked as `chunk_aware`: values that are stored as chunks are
+/// returned as the keys of their chunks, see
+/// `deserialize_high_capacity_get_changes_since_response`.
 //
 // This uses the PB structs directly as this function is meant to
 // be used in the registry canister only and thus there is no problem with
 // leaking the PB structs to the rest of the code base.
 pub fn serialize_get_changes_since_request(version: u64) -> Result<Vec<u8>, Error> {
-    let request = pb::v1::RegistryGetChangesSinceRequest { version };
+    let request = pb::v1::RegistryGetChangesSinceRequest {
+        version,
+        chunk_aware: true,
+    };
     let mut buf = Vec::new();
     match request.encode(&mut buf) {
         Ok(_) => Ok(buf),
@@ -236,11 +256,149 @@ pub fn serialize_get_changes_since_request(version: u64) -> Result<Vec<u8>, Erro
 
 /// Deserializes the response obtained from the registry canister for a
 /// get_changes_since() call, from protobuf.
+///
+/// Fails if any value is stored as chunks, see
+/// `deserialize_high_capacity_get_changes_since_response`.
 pub fn deserialize_get_changes_since_response(
     response: Vec<u8>,
 ) -> Result<(Vec<RegistryDelta>, u64), Error> {
-    match pb::v1::RegistryGetChangesSinceResponse::decode(&response[..]) {
-        Ok(response) => Ok((response.deltas, response.version)),
+    use pb::v1::high_capacity_registry_value::Content;
+
+    let (deltas, version) = deserialize_high_capacity_get_changes_since_response(response)?;
+    let deltas = deltas
+        .into_iter()
+        .map(|HighCapacityRegistryDelta { key, values }| {
+            let values = values
+                .into_iter()
+                .map(|value| {
+                    let bytes = match value.content {
+                        None => vec![],
+                        Some(Content::Value(bytes)) => bytes,
+                        Some(Content::LargeValueChunkKeys(_)) => {
+                            return Err(Error::MalformedMessage(format!(
+                                "The value of key {} at version {} is stored as chunks",
+                                String::from_utf8_lossy(&key),
+                                value.version
+                            )))
+                        }
+                    };
+                    Ok(RegistryValue {
+                        value: bytes,
+                        version: value.version,
+                        deletion_marker: value.deletion_marker,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(RegistryDelta { key, values })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok((deltas, version))
+}
+
+/// Serializes a response for a get_value() request to the registry canister,
+/// possibly referencing a value that is stored as chunks.
+pub fn serialize_high_capacity_get_value_response(
+    response: pb::v1::HighCapacityRegistryGetValueResponse,
+) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    match response.encode(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Deserializes the response obtained from the registry canister for a
+/// get_value() call, from protobuf, without resolving chunked values.
+///
+/// Returns `None` as content if the value is empty.
+pub fn deserialize_high_capacity_get_value_response(
+    response: Vec<u8>,
+) -> Result<
+    (
+        Option<pb::v1::high_capacity_registry_get_value_response::Content>,
+        u64,
+    ),
+    Error,
+> {
+    match pb::v1::HighCapacityRegistryGetValueResponse::decode(&response[..]) {
+        Ok(response) => {
+            if let Some(error) = response.error {
+                return Err(Error::from(error));
+            }
+            Ok((response.content, response.version))
+        }
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Serializes a response for a get_changes_since() request to the registry
+/// canister, possibly referencing values that are stored as chunks.
+pub fn serialize_high_capacity_get_changes_since_response(
+    response: pb::v1::HighCapacityRegistryGetChangesSinceResponse,
+) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    match response.encode(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Deserializes the response obtained from the registry canister for a
+/// get_changes_since() call, from protobuf, without resolving chunked values.
+pub fn deserialize_high_capacity_get_changes_since_response(
+    response: Vec<u8>,
+) -> Result<(Vec<HighCapacityRegistryDelta>, u64), Error> {
+    match pb::v1::HighCapacityRegistryGetChangesSinceResponse::decode(&response[..]) {
+        Ok(response) => {
+            if let Some(error) = response.error {
+                return Err(Error::from(error));
+            }
+            Ok((response.deltas, response.version))
+        }
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Serializes a request for a get_chunk() call to the registry canister.
+pub fn serialize_get_chunk_request(content_sha256: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let request = pb::v1::GetChunkRequest { content_sha256 };
+    let mut buf = Vec::new();
+    match request.encode(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Deserializes the arguments for a request to the get_chunk() function in
+/// the registry canister, from protobuf.
+pub fn deserialize_get_chunk_request(request: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match pb::v1::GetChunkRequest::decode(&request[..]) {
+        Ok(request) => Ok(request.content_sha256),
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Serializes a response for a get_chunk() request to the registry canister.
+pub fn serialize_get_chunk_response(response: pb::v1::GetChunkResponse) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    match response.encode(&mut buf) {
+        Ok(_) => Ok(buf),
+        Err(error) => Err(Error::MalformedMessage(error.to_string())),
+    }
+}
+
+/// Deserializes the response obtained from the registry canister for a
+/// get_chunk() call, from protobuf.
+///
+/// Note that this does not verify the content of the chunk against its hash.
+pub fn deserialize_get_chunk_response(response: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match pb::v1::GetChunkResponse::decode(&response[..]) {
+        Ok(response) => {
+            if let Some(error) = response.error {
+                return Err(Error::from(error));
+            }
+            Ok(response.content)
+        }
         Err(error) => Err(Error::MalformedMessage(error.to_string())),
     }
 }
@@ -393,6 +551,72 @@ mod tests {
         assert_eq!(ret_version, version);
     }
 
+    #[test]
+    fn test_high_capacity_get_value_response_is_compatible() {
+        use crate::pb::v1::high_capacity_registry_get_value_response::Content;
+
+        let value = vec![1, 2, 3, 4];
+        let bytes = serialize_get_value_response(pb::v1::RegistryGetValueResponse {
+            version: 10,
+            value: value.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            deserialize_high_capacity_get_value_response(bytes).unwrap(),
+            (Some(Content::Value(value)), 10)
+        );
+
+        let chunk_keys = pb::v1::LargeValueChunkKeys {
+            chunk_content_sha256s: vec![vec![42; 32], vec![43; 32]],
+        };
+        let bytes = serialize_high_capacity_get_value_response(
+            pb::v1::HighCapacityRegistryGetValueResponse {
+                version: 11,
+                content: Some(Content::LargeValueChunkKeys(chunk_keys.clone())),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            deserialize_high_capacity_get_value_response(bytes.clone()).unwrap(),
+            (Some(Content::LargeValueChunkKeys(chunk_keys)), 11)
+        );
+        // Clients unaware of chunks see an empty value.
+        assert_eq!(deserialize_get_value_response(bytes).unwrap(), (vec![], 11));
+    }
+
+    #[test]
+    fn test_high_capacity_mutation_has_same_encoding() {
+        let request = RegistryAtomicMutateRequest {
+            mutations: vec![upsert("a", "1"), delete("b"), upsert("c", "")],
+            preconditions: vec![precondition("a", 3)],
+        };
+        let high_capacity_request =
+            pb::v1::HighCapacityRegistryAtomicMutateRequest::from(request.clone());
+
+        assert_eq!(
+            request.encode_to_vec(),
+            high_capacity_request.encode_to_vec()
+        );
+    }
+
+    #[test]
+    fn test_serde_get_chunk() {
+        let bytes = serialize_get_chunk_request(vec![7; 32]).unwrap();
+        assert_eq!(deserialize_get_chunk_request(bytes).unwrap(), vec![7; 32]);
+
+        let bytes = serialize_get_chunk_response(pb::v1::GetChunkResponse {
+            error: None,
+            content: vec![1, 2, 3],
+        })
+        .unwrap();
+        assert_eq!(
+            deserialize_get_chunk_response(bytes).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_serde_get_value_response_with_error() {
@@ -56,6 +56,57 @@ impl fmt::Display for v1::RegistryMutation {
     }
 }
 
+impl From<v1::RegistryMutation> for v1::HighCapacityRegistryMutation {
+    /// Wraps the value of `mutation` as inline content. An empty value is
+    /// left unset, so that both messages have the same encoding.
+    fn from(mutation: v1::RegistryMutation) -> Self {
+        let v1::RegistryMutation {
+            mutation_type,
+            key,
+            value,
+        } = mutation;
+        Self {
+            mutation_type,
+            key,
+            content: if value.is_empty() {
+                None
+            } else {
+                Some(v1::high_capacity_registry_mutation::Content::Value(value))
+            },
+        }
+    }
+}
+
+impl From<v1::RegistryAtomicMutateRequest> for v1::HighCapacityRegistryAtomicMutateRequest {
+    fn from(request: v1::RegistryAtomicMutateRequest) -> Self {
+        Self {
+            mutations: request.mutations.into_iter().map(Into::into).collect(),
+            preconditions: request.preconditions,
+        }
+    }
+}
+
+impl From<v1::RegistryValue> for v1::HighCapacityRegistryValue {
+    /// Wraps the value of `value` as inline content. An empty value is left
+    /// unset.
+    fn from(value: v1::RegistryValue) -> Self {
+        let v1::RegistryValue {
+            value,
+            version,
+            deletion_marker,
+        } = value;
+        Self {
+            version,
+            deletion_marker,
+            content: if value.is_empty() {
+                None
+            } else {
+                Some(v1::high_capacity_registry_value::Content::Value(value))
+            },
+        }
+    }
+}
+
 impl fmt::Display for v1::RegistryAtomicMutateRequest {
     /// Produces a string that shows the keys being mutated and the type of
     /// mutations, but not the values.
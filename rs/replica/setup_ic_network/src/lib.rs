@@ -79,6 +79,25 @@ use tokio::sync::mpsc::Sender as TokioSender;
 
 const ENABLE_NEW_STATE_SYNC: bool = false;
 const ENABLE_NEW_P2P_CONSENSUS: bool = false;
+/// Disseminates ingress with the consensus manager while all other artifacts
+/// are still disseminated by the legacy gossip protocol. Nodes with and without
+/// the flag cannot exchange ingress, so it must be enabled on all nodes of a
+/// subnet at once.
+const ENABLE_NEW_P2P_INGRESS: bool = false;
+/// Disseminates consensus artifacts with the consensus manager, which sends
+/// block proposals without the ingress messages they contain, while all other
+/// artifacts except ingress are still disseminated by the legacy gossip
+/// protocol. Like [`ENABLE_NEW_P2P_INGRESS`], it must be enabled on all nodes
+/// of a subnet at once.
+const ENABLE_NEW_P2P_BLOCK_PROPOSALS: bool = false;
+
+/// Limits on what a single peer can make this node download via ingress
+/// gossip when ingress is disseminated by the consensus manager. They bound the
+/// damage a peer flooding the subnet with ingress can do.
+const INGRESS_PEER_LIMITS: ic_consensus_manager::PeerLimits = ic_consensus_manager::PeerLimits {
+    slot_limit: 50_000,
+    max_download_bytes_per_sec: Some(20 * 1024 * 1024),
+};
 
 /// The P2P state sync client.
 pub enum P2PStateSyncClient {
@@ -89,7 +108,14 @@ pub enum P2PStateSyncClient {
 }
 
 enum P2PSenders {
-    Old(Sender<GossipAdvert>),
+    /// If `consensus` or `ingress` is set, the corresponding artifacts are
+    /// disseminated by the consensus manager while all other artifacts are
+    /// disseminated by the legacy gossip protocol.
+    Old {
+        advert_tx: Sender<GossipAdvert>,
+        consensus: Option<TokioSender<ArtifactProcessorEvent<ConsensusArtifact>>>,
+        ingress: Option<TokioSender<ArtifactProcessorEvent<IngressArtifact>>>,
+    },
     New {
         consensus: TokioSender<ArtifactProcessorEvent<ConsensusArtifact>>,
         certification: TokioSender<ArtifactProcessorEvent<CertificationArtifact>>,
@@ -244,18 +270,23 @@ pub fn setup_consensus_and_p2p(
             ingress_pool,
         } = artifact_pools;
 
-        new_p2p_consensus.add_client(
+        new_p2p_consensus.add_client_with_assembler(
             consensus_rx,
             consensus_pool,
             p2p_clients.consensus.priority_fn_producer,
             p2p_clients.consensus.client_handle.sender,
+            Arc::new(ic_consensus_manager::BlockProposalAssembler::new(
+                ingress_pool.clone(),
+                metrics_registry,
+            )),
         );
 
-        new_p2p_consensus.add_client(
+        new_p2p_consensus.add_client_with_limits(
             ingress_rx,
             ingress_pool.clone(),
             p2p_clients.ingress.priority_fn_producer,
             p2p_clients.ingress.client_handle.sender.clone(),
+            INGRESS_PEER_LIMITS,
         );
 
         new_p2p_consensus.add_client(
@@ -297,24 +328,72 @@ pub fn setup_consensus_and_p2p(
             ingress_pool,
         )
     } else {
-        let (p2p_clients, join_handles, artifact_pools) =
-            start_consensus(P2PSenders::Old(advert_tx.clone()));
+        let (ingress_tx, ingress_rx) = if ENABLE_NEW_P2P_INGRESS {
+            let (ingress_tx, ingress_rx) = tokio::sync::mpsc::channel(MAX_ADVERT_BUFFER);
+            (Some(ingress_tx), Some(ingress_rx))
+        } else {
+            (None, None)
+        };
+        let (consensus_tx, consensus_rx) = if ENABLE_NEW_P2P_BLOCK_PROPOSALS {
+            let (consensus_tx, consensus_rx) = tokio::sync::mpsc::channel(MAX_ADVERT_BUFFER);
+            (Some(consensus_tx), Some(consensus_rx))
+        } else {
+            (None, None)
+        };
+        let (p2p_clients, join_handles, artifact_pools) = start_consensus(P2PSenders::Old {
+            advert_tx: advert_tx.clone(),
+            consensus: consensus_tx,
+            ingress: ingress_tx,
+        });
 
         let ingress_sender = p2p_clients.ingress.client_handle.sender.clone();
 
+        if let Some(ingress_rx) = ingress_rx {
+            new_p2p_consensus.add_client_with_limits(
+                ingress_rx,
+                artifact_pools.ingress_pool.clone(),
+                p2p_clients.ingress.priority_fn_producer,
+                p2p_clients.ingress.client_handle.sender,
+                INGRESS_PEER_LIMITS,
+            );
+        } else {
+            backends.insert(
+                IngressArtifact::TAG,
+                Box::new(p2p_clients.ingress.client_handle),
+            );
+        }
+
+        if let Some(consensus_rx) = consensus_rx {
+            new_p2p_consensus.add_client_with_assembler(
+                consensus_rx,
+                consensus_pool,
+                p2p_clients.consensus.priority_fn_producer,
+                p2p_clients.consensus.client_handle.sender,
+                Arc::new(ic_consensus_manager::BlockProposalAssembler::new(
+                    artifact_pools.ingress_pool.clone(),
+                    metrics_registry,
+                )),
+            );
+        } else {
+            backends.insert(
+                ConsensusArtifact::TAG,
+                Box::new(p2p_clients.consensus.client_handle),
+            );
+        }
+
+        if ENABLE_NEW_P2P_INGRESS || ENABLE_NEW_P2P_BLOCK_PROPOSALS {
+            p2p_router = Some(
+                new_p2p_consensus
+                    .router()
+                    .merge(p2p_router.unwrap_or_default()),
+            );
+        }
+
         backends.insert(
             CertificationArtifact::TAG,
             Box::new(p2p_clients.certification.client_handle),
         );
-        backends.insert(
-            ConsensusArtifact::TAG,
-            Box::new(p2p_clients.consensus.client_handle),
-        );
         backends.insert(DkgArtifact::TAG, Box::new(p2p_clients.dkg.client_handle));
-        backends.insert(
-            IngressArtifact::TAG,
-            Box::new(p2p_clients.ingress.client_handle),
-        );
         backends.insert(
             EcdsaArtifact::TAG,
             Box::new(p2p_clients.ecdsa.client_handle),
@@ -563,7 +642,11 @@ fn start_consensus(
 
         // Create the consensus client.
         let send_advert: Box<dyn Fn(_) + Send> = match &advert_tx {
-            P2PSenders::New { consensus, .. } => {
+            P2PSenders::New { consensus, .. }
+            | P2PSenders::Old {
+                consensus: Some(consensus),
+                ..
+            } => {
                 let advert_tx = consensus.clone();
                 Box::new(move |req| {
                     advert_tx
@@ -572,7 +655,11 @@ fn start_consensus(
                 })
             }
 
-            P2PSenders::Old(advert_tx) => {
+            P2PSenders::Old {
+                advert_tx,
+                consensus: None,
+                ..
+            } => {
                 let advert_tx = advert_tx.clone();
 
                 Box::new(move |req| {
@@ -604,7 +691,11 @@ fn start_consensus(
 
         // Create the consensus client.
         let send_advert: Box<dyn Fn(_) + Send> = match &advert_tx {
-            P2PSenders::New { ingress, .. } => {
+            P2PSenders::New { ingress, .. }
+            | P2PSenders::Old {
+                ingress: Some(ingress),
+                ..
+            } => {
                 let advert_tx = ingress.clone();
                 Box::new(move |req| {
                     advert_tx
@@ -613,7 +704,11 @@ fn start_consensus(
                 })
             }
 
-            P2PSenders::Old(advert_tx) => {
+            P2PSenders::Old {
+                advert_tx,
+                ingress: None,
+                ..
+            } => {
                 let advert_tx = advert_tx.clone();
 
                 Box::new(move |req| {
@@ -652,7 +747,7 @@ fn start_consensus(
                 })
             }
 
-            P2PSenders::Old(advert_tx) => {
+            P2PSenders::Old { advert_tx, .. } => {
                 let advert_tx = advert_tx.clone();
 
                 Box::new(move |req| {
@@ -700,7 +795,7 @@ fn start_consensus(
                         .expect("Channel should not be closed");
                 })
             }
-            P2PSenders::Old(advert_tx) => {
+            P2PSenders::Old { advert_tx, .. } => {
                 let advert_tx = advert_tx.clone();
 
                 Box::new(move |req| {
@@ -758,7 +853,7 @@ fn start_consensus(
                         .expect("Channel should not be closed");
                 })
             }
-            P2PSenders::Old(advert_tx) => {
+            P2PSenders::Old { advert_tx, .. } => {
                 let advert_tx = advert_tx.clone();
                 Box::new(move |req| {
                     if let ArtifactProcessorEvent::Advert(advert) = req {
@@ -808,7 +903,7 @@ fn start_consensus(
                         .expect("Channel should not be closed");
                 })
             }
-            P2PSenders::Old(advert_tx) => {
+            P2PSenders::Old { advert_tx, .. } => {
                 let advert_tx = advert_tx.clone();
                 Box::new(move |req| {
                     if let ArtifactProcessorEvent::Advert(advert) = req {
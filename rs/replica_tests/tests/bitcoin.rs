@@ -3,6 +3,7 @@ use ic_base_types::CanisterId;
 use ic_btc_interface::NetworkInRequest as BitcoinNetwork;
 use ic_btc_service::{
     btc_service_server::{BtcService, BtcServiceServer},
+    BtcServiceGetBlockFiltersRequest, BtcServiceGetBlockFiltersResponse,
     BtcServiceGetSuccessorsRequest, BtcServiceGetSuccessorsResponse,
     BtcServiceSendTransactionRequest, BtcServiceSendTransactionResponse,
 };
@@ -107,6 +108,15 @@ impl BtcService for MockBitcoinAdapter {
             .clone()
             .map(tonic::Response::new)
     }
+
+    async fn get_block_filters(
+        &self,
+        _request: tonic::Request<BtcServiceGetBlockFiltersRequest>,
+    ) -> Result<tonic::Response<BtcServiceGetBlockFiltersResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "Block filters are not served by the mock adapter",
+        ))
+    }
 }
 
 fn spawn_mock_bitcoin_adapter(
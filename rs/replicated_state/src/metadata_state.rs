@@ -29,6 +29,7 @@ use ic_registry_routing_table::{
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
+    batch::BlockmakerMetrics,
     crypto::CryptoHash,
     ingress::{IngressState, IngressStatus},
     messages::{
@@ -158,6 +159,10 @@ pub struct SystemMetadata {
     /// response limit. To work around this limitation, large responses are paginated
     /// and are stored here temporarily until they're fetched by the calling canister.
     pub bitcoin_get_successors_follow_up_responses: BTreeMap<CanisterId, Vec<BlockBlob>>,
+
+    /// Daily snapshots of the block making statistics of the subnet's nodes,
+    /// aggregated from the `BlockmakerMetrics` of every batch.
+    pub blockmaker_metrics_time_series: BlockmakerMetricsTimeSeries,
 }
 
 /// Full description of the IC network toplogy.
@@ -519,6 +524,120 @@ impl TryFrom<pb_metadata::SubnetMetrics> for SubnetMetrics {
     }
 }
 
+/// Maximum number of daily snapshots retained by a
+/// `BlockmakerMetricsTimeSeries`.
+pub const BLOCKMAKER_METRICS_TIME_SERIES_NUM_SNAPSHOTS: usize = 60;
+
+const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Block making statistics of a single node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockmakerStats {
+    /// The number of finalized blocks made by the node.
+    pub blocks_proposed_total: u64,
+    /// The number of finalized blocks made by a lower ranked block maker while
+    /// the node was ranked higher, i.e. rounds in which the node failed to get
+    /// its own block finalized.
+    pub blocks_not_proposed_total: u64,
+}
+
+/// Running totals of the block making statistics of the subnet's nodes, one
+/// snapshot per day (UTC), keyed by the batch time of the last update.
+///
+/// The latest snapshot is updated with every batch and a new snapshot is
+/// started with the first batch of every day. At most
+/// `BLOCKMAKER_METRICS_TIME_SERIES_NUM_SNAPSHOTS` snapshots are retained.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockmakerMetricsTimeSeries(BTreeMap<Time, BTreeMap<NodeId, BlockmakerStats>>);
+
+impl BlockmakerMetricsTimeSeries {
+    /// Adds the block maker metrics of a batch delivered at `batch_time`.
+    pub fn observe(&mut self, batch_time: Time, metrics: &BlockmakerMetrics) {
+        let day = |time: Time| time.as_nanos_since_unix_epoch() / NANOS_PER_DAY;
+
+        let (time, mut snapshot) = match self.0.keys().next_back().copied() {
+            None => (batch_time, BTreeMap::new()),
+            // First batch of a new day: start a new snapshot from the latest totals.
+            Some(last_time) if batch_time > last_time && day(batch_time) != day(last_time) => {
+                (batch_time, self.0[&last_time].clone())
+            }
+            // Otherwise update the latest snapshot, never moving it back in time.
+            Some(last_time) => (
+                batch_time.max(last_time),
+                self.0.remove(&last_time).unwrap_or_default(),
+            ),
+        };
+
+        snapshot
+            .entry(metrics.blockmaker)
+            .or_default()
+            .blocks_proposed_total += 1;
+        for node_id in &metrics.failed_blockmakers {
+            snapshot
+                .entry(*node_id)
+                .or_default()
+                .blocks_not_proposed_total += 1;
+        }
+        self.0.insert(time, snapshot);
+
+        while self.0.len() > BLOCKMAKER_METRICS_TIME_SERIES_NUM_SNAPSHOTS {
+            self.0.pop_first();
+        }
+    }
+
+    /// Returns the snapshots last updated at or after `start`, oldest first.
+    pub fn snapshots_since(
+        &self,
+        start: Time,
+    ) -> impl Iterator<Item = (&Time, &BTreeMap<NodeId, BlockmakerStats>)> {
+        self.0.range(start..)
+    }
+}
+
+impl From<&BlockmakerMetricsTimeSeries> for Vec<pb_metadata::BlockmakerMetricsSnapshot> {
+    fn from(item: &BlockmakerMetricsTimeSeries) -> Self {
+        item.0
+            .iter()
+            .map(|(time, snapshot)| pb_metadata::BlockmakerMetricsSnapshot {
+                timestamp_nanos: time.as_nanos_since_unix_epoch(),
+                node_stats: snapshot
+                    .iter()
+                    .map(|(node_id, stats)| pb_metadata::NodeBlockmakerStats {
+                        node_id: Some(node_id_into_protobuf(*node_id)),
+                        blocks_proposed_total: stats.blocks_proposed_total,
+                        blocks_not_proposed_total: stats.blocks_not_proposed_total,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<Vec<pb_metadata::BlockmakerMetricsSnapshot>> for BlockmakerMetricsTimeSeries {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: Vec<pb_metadata::BlockmakerMetricsSnapshot>) -> Result<Self, Self::Error> {
+        let mut time_series = BTreeMap::new();
+        for entry in item {
+            let mut snapshot = BTreeMap::new();
+            for stats in entry.node_stats {
+                snapshot.insert(
+                    node_id_try_from_option(stats.node_id)?,
+                    BlockmakerStats {
+                        blocks_proposed_total: stats.blocks_proposed_total,
+                        blocks_not_proposed_total: stats.blocks_not_proposed_total,
+                    },
+                );
+            }
+            time_series.insert(
+                Time::from_nanos_since_unix_epoch(entry.timestamp_nanos),
+                snapshot,
+            );
+        }
+        Ok(Self(time_series))
+    }
+}
+
 impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
     fn from(item: &SystemMetadata) -> Self {
         // We do not store the subnet type when we serialize SystemMetadata. We rely on
@@ -566,6 +685,7 @@ impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
                     public_key: public_key.clone(),
                 })
                 .collect(),
+            blockmaker_metrics_time_series: (&item.blockmaker_metrics_time_series).into(),
         }
     }
 }
@@ -673,6 +793,7 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             },
             expected_compiled_wasms: BTreeSet::new(),
             bitcoin_get_successors_follow_up_responses,
+            blockmaker_metrics_time_series: item.blockmaker_metrics_time_series.try_into()?,
         })
     }
 }
@@ -708,6 +829,7 @@ impl SystemMetadata {
             subnet_metrics: Default::default(),
             expected_compiled_wasms: BTreeSet::new(),
             bitcoin_get_successors_follow_up_responses: BTreeMap::default(),
+            blockmaker_metrics_time_series: BlockmakerMetricsTimeSeries::default(),
         }
     }
 
@@ -975,6 +1097,7 @@ impl SystemMetadata {
             subnet_metrics: _,
             ref expected_compiled_wasms,
             bitcoin_get_successors_follow_up_responses: _,
+            blockmaker_metrics_time_series: _,
         } = self;
 
         let split_from_subnet = split_from.expect("Not a state resulting from a subnet split");
@@ -1966,6 +2089,7 @@ pub(crate) mod testing {
             subnet_metrics: Default::default(),
             expected_compiled_wasms: Default::default(),
             bitcoin_get_successors_follow_up_responses: Default::default(),
+            blockmaker_metrics_time_series: Default::default(),
         };
     }
 }
@@ -441,6 +441,13 @@ fn roundtrip_encoding() {
     system_metadata.node_public_keys = btreemap! {
         node_test_id(1) => pk_der,
     };
+    system_metadata.blockmaker_metrics_time_series.observe(
+        Time::from_nanos_since_unix_epoch(1),
+        &BlockmakerMetrics {
+            blockmaker: node_test_id(1),
+            failed_blockmakers: vec![node_test_id(2)],
+        },
+    );
 
     // Decoding a `SystemMetadata` with no `canister_allocation_ranges` succeeds.
     let mut proto = pb::SystemMetadata::from(&system_metadata);
@@ -1407,3 +1414,62 @@ fn consumed_cycles_total_calculates_the_right_amount() {
         NominalCycles::from(250)
     );
 }
+
+#[test]
+fn blockmaker_metrics_time_series_keeps_daily_snapshots() {
+    const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+    let at = |nanos: u64| Time::from_nanos_since_unix_epoch(nanos);
+    let metrics = |blockmaker: u64, failed_blockmakers: &[u64]| BlockmakerMetrics {
+        blockmaker: node_test_id(blockmaker),
+        failed_blockmakers: failed_blockmakers
+            .iter()
+            .map(|id| node_test_id(*id))
+            .collect(),
+    };
+    let stats = |blocks_proposed_total, blocks_not_proposed_total| BlockmakerStats {
+        blocks_proposed_total,
+        blocks_not_proposed_total,
+    };
+
+    let mut time_series = BlockmakerMetricsTimeSeries::default();
+    time_series.observe(at(DAY + 10), &metrics(1, &[]));
+    time_series.observe(at(DAY + 20), &metrics(2, &[1]));
+
+    // Batches of the same day update a single snapshot, keyed by the latest
+    // batch time.
+    assert_eq!(
+        time_series.snapshots_since(UNIX_EPOCH).collect::<Vec<_>>(),
+        vec![(
+            &at(DAY + 20),
+            &btreemap! {
+                node_test_id(1) => stats(1, 1),
+                node_test_id(2) => stats(1, 0),
+            }
+        )]
+    );
+
+    // The first batch of the next day starts a new snapshot from the totals.
+    time_series.observe(at(2 * DAY), &metrics(1, &[]));
+    assert_eq!(
+        time_series
+            .snapshots_since(at(DAY + 21))
+            .collect::<Vec<_>>(),
+        vec![(
+            &at(2 * DAY),
+            &btreemap! {
+                node_test_id(1) => stats(2, 1),
+                node_test_id(2) => stats(1, 0),
+            }
+        )]
+    );
+    assert_eq!(time_series.snapshots_since(UNIX_EPOCH).count(), 2);
+
+    // Only the most recent snapshots are retained.
+    for day in 3..(BLOCKMAKER_METRICS_TIME_SERIES_NUM_SNAPSHOTS as u64 + 10) {
+        time_series.observe(at(day * DAY), &metrics(1, &[]));
+    }
+    assert_eq!(
+        time_series.snapshots_since(UNIX_EPOCH).count(),
+        BLOCKMAKER_METRICS_TIME_SERIES_NUM_SNAPSHOTS
+    );
+}
@@ -6,7 +6,10 @@ use ic_ledger_canister_core::ledger::{
     self as core_ledger, LedgerContext, LedgerData, TransactionInfo,
 };
 use ic_ledger_core::{
-    approvals::AllowanceTable, balances::Balances, block::EncodedBlock, timestamp::TimeStamp,
+    approvals::{AllowanceTable, HeapAllowancesData},
+    balances::Balances,
+    block::EncodedBlock,
+    timestamp::TimeStamp,
 };
 use ic_ledger_core::{block::BlockIndex, tokens::Tokens};
 use ic_ledger_hash_of::HashOf;
@@ -64,7 +67,7 @@ fn unknown_token() -> String {
 pub struct Ledger {
     pub balances: LedgerBalances,
     #[serde(default)]
-    pub approvals: AllowanceTable<ApprovalKey, AccountIdentifier, Tokens>,
+    pub approvals: AllowanceTable<HeapAllowancesData<ApprovalKey, AccountIdentifier, Tokens>>,
     pub blockchain: Blockchain<dfn_runtime::DfnRuntime, IcpLedgerArchiveWasm>,
     // A cap on the maximum number of accounts.
     pub maximum_number_of_accounts: usize,
@@ -109,7 +112,7 @@ pub struct Ledger {
 
 impl LedgerContext for Ledger {
     type AccountId = AccountIdentifier;
-    type Approvals = AllowanceTable<ApprovalKey, Self::AccountId, Tokens>;
+    type Approvals = AllowanceTable<HeapAllowancesData<ApprovalKey, Self::AccountId, Tokens>>;
     type BalancesStore = BTreeMap<AccountIdentifier, Tokens>;
     type Tokens = Tokens;
 
@@ -137,6 +140,7 @@ impl LedgerContext for Ledger {
 impl LedgerData for Ledger {
     type Runtime = dfn_runtime::DfnRuntime;
     type ArchiveWasm = IcpLedgerArchiveWasm;
+    type BlockData = Vec<EncodedBlock>;
     type Transaction = Transaction;
     type Block = Block;
 
@@ -168,11 +172,13 @@ impl LedgerData for Ledger {
         &self.token_symbol
     }
 
-    fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm> {
+    fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm, Self::BlockData> {
         &self.blockchain
     }
 
-    fn blockchain_mut(&mut self) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm> {
+    fn blockchain_mut(
+        &mut self,
+    ) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm, Self::BlockData> {
         &mut self.blockchain
     }
 
@@ -37,6 +37,12 @@ use icp_ledger::{
 use icrc_ledger_types::icrc1::transfer::TransferArg;
 use icrc_ledger_types::icrc2::allowance::{Allowance, AllowanceArgs};
 use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
+use icrc_ledger_types::icrc21::{
+    errors::{ErrorInfo, Icrc21Error},
+    lib::build_icrc21_consent_info_for_icrc1_and_icrc2_endpoints,
+    requests::ConsentMessageRequest,
+    responses::ConsentInfo,
+};
 use icrc_ledger_types::{
     icrc::generic_metadata_value::MetadataValue as Value, icrc3::archive::QueryArchiveFn,
 };
@@ -565,7 +571,7 @@ fn block(block_index: BlockIndex) -> Option<Result<EncodedBlock, CanisterId>> {
             "[ledger] Checking the ledger for block [{}]",
             block_index
         ));
-        state.blockchain.get(block_index).cloned().map(Ok)
+        state.blockchain.get(block_index).map(Ok)
     }
 }
 
@@ -592,6 +598,10 @@ fn icrc1_supported_standards() -> Vec<StandardRecord> {
             url: "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-2".to_string(),
         });
     }
+    standards.push(StandardRecord {
+        name: "ICRC-21".to_string(),
+        url: "https://github.com/dfinity/wg-identity-authentication/blob/main/topics/ICRC-21/icrc_21_consent_msg.md".to_string(),
+    });
     standards
 }
 
@@ -1161,9 +1171,9 @@ fn query_blocks(GetBlocksArgs { start, length }: GetBlocksArgs) -> QueryBlocksRe
     let blocks: Vec<CandidBlock> = ledger
         .blockchain
         .block_slice(local_blocks.clone())
-        .iter()
+        .into_iter()
         .map(|enc_block| -> CandidBlock {
-            Block::decode(enc_block.clone())
+            Block::decode(enc_block)
                 .expect("bug: failed to decode encoded block")
                 .into()
         })
@@ -1325,7 +1335,7 @@ fn query_encoded_blocks(
 
     let local_blocks = range_utils::take(&locations.local_blocks, MAX_BLOCKS_PER_REQUEST);
 
-    let blocks = ledger.blockchain.block_slice(local_blocks.clone()).to_vec();
+    let blocks = ledger.blockchain.block_slice(local_blocks.clone());
 
     let archived_blocks = locations
         .archived_blocks
@@ -1483,6 +1493,31 @@ fn icrc2_allowance_candid() {
     over(candid_one, icrc2_allowance)
 }
 
+#[candid_method(update, rename = "icrc21_canister_call_consent_message")]
+fn icrc21_canister_call_consent_message(
+    consent_msg_request: ConsentMessageRequest,
+) -> Result<ConsentInfo, Icrc21Error> {
+    let caller_principal = caller().0;
+    let ledger = LEDGER.read().unwrap();
+    if !ledger.feature_flags.icrc2 && consent_msg_request.method.starts_with("icrc2_") {
+        return Err(Icrc21Error::UnsupportedCanisterCall(ErrorInfo {
+            description: "ICRC-2 features are not enabled on the ledger.".to_string(),
+        }));
+    }
+    build_icrc21_consent_info_for_icrc1_and_icrc2_endpoints(
+        consent_msg_request,
+        caller_principal,
+        Nat::from(ledger.transfer_fee.get_e8s()),
+        ledger.token_symbol.clone(),
+        icrc1_decimals(),
+    )
+}
+
+#[export_name = "canister_update icrc21_canister_call_consent_message"]
+fn icrc21_canister_call_consent_message_candid() {
+    over(candid_one, icrc21_canister_call_consent_message)
+}
+
 candid::export_service!();
 
 #[export_name = "canister_query __get_candid_interface_tmp_hack"]
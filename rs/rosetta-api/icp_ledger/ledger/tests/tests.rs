@@ -502,6 +502,11 @@ fn test_approve_smoke() {
     ic_icrc1_ledger_sm_tests::test_approve_smoke(ledger_wasm(), encode_init_args);
 }
 
+#[test]
+fn test_icrc21_consent_message() {
+    ic_icrc1_ledger_sm_tests::test_icrc21_consent_message(ledger_wasm(), encode_init_args);
+}
+
 #[test]
 fn test_approve_expiration() {
     ic_icrc1_ledger_sm_tests::test_approve_expiration(ledger_wasm(), encode_init_args);
@@ -578,6 +583,7 @@ fn test_feature_flags() {
         &approve_args,
         &allowance_args,
         &transfer_from_args,
+        &["ICRC-1", "ICRC-21"],
     );
 
     env.upgrade_canister(
@@ -599,6 +605,7 @@ fn test_feature_flags() {
         &approve_args,
         &allowance_args,
         &transfer_from_args,
+        &["ICRC-1", "ICRC-21"],
     );
 
     env.upgrade_canister(
@@ -618,7 +625,7 @@ fn test_feature_flags() {
         standards.push(standard.name);
     }
     standards.sort();
-    assert_eq!(standards, vec!["ICRC-1", "ICRC-2"]);
+    assert_eq!(standards, vec!["ICRC-1", "ICRC-2", "ICRC-21"]);
 
     let block_index =
         send_approval(&env, canister_id, from.0, &approve_args).expect("approval failed");
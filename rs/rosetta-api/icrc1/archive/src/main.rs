@@ -10,6 +10,9 @@ use ic_stable_structures::{
 };
 use icrc_ledger_types::icrc3::blocks::BlockRange;
 use icrc_ledger_types::icrc3::blocks::GenericBlock as IcrcBlock;
+use icrc_ledger_types::icrc3::blocks::{
+    BlockWithId, GetBlocksArgs, GetBlocksResult, ICRC3GenericBlock,
+};
 
 use icrc_ledger_types::icrc3::transactions::Transaction;
 use icrc_ledger_types::icrc3::transactions::{GetTransactionsRequest, TransactionRange};
@@ -271,6 +274,46 @@ fn get_blocks(req: GetTransactionsRequest) -> BlockRange {
     BlockRange { blocks }
 }
 
+/// Get the blocks in the given ranges in the ICRC-3 format.
+/// Ranges, or parts thereof, that are not stored in this archive are ignored.
+#[query]
+#[candid_method(query)]
+fn icrc3_get_blocks(args: GetBlocksArgs) -> GetBlocksResult {
+    let (offset, max_blocks) =
+        with_archive_opts(|opts| (opts.block_index_offset, opts.max_transactions_per_response));
+    let num_blocks = with_blocks(|blocks| blocks.len());
+
+    let mut blocks = vec![];
+    for arg in args {
+        let start = match u64::try_from(&arg.start.0) {
+            Ok(start) => start,
+            // There is no block beyond u64::MAX.
+            Err(_) => continue,
+        };
+        let length = u64::try_from(&arg.length.0).unwrap_or(u64::MAX);
+        let end = start.saturating_add(length).min(offset + num_blocks);
+        let start = start.max(offset);
+        let length = end
+            .saturating_sub(start)
+            .min(max_blocks.saturating_sub(blocks.len() as u64));
+        if length == 0 {
+            continue;
+        }
+        for (id, block) in (start..).zip(decode_block_range(start, length, decode_icrc1_block)) {
+            blocks.push(BlockWithId {
+                id: id.into(),
+                block: ICRC3GenericBlock::from(block),
+            });
+        }
+    }
+
+    GetBlocksResult {
+        log_length: (offset + num_blocks).into(),
+        blocks,
+        archived_blocks: vec![],
+    }
+}
+
 #[query]
 fn __get_candid_interface_tmp_hack() -> &'static str {
     include_str!(env!("ARCHIVE_DID_PATH"))
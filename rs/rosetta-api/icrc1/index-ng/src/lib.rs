@@ -7,6 +7,9 @@ use icrc_ledger_types::icrc3::transactions::Transaction;
 /// The maximum number of blocks to return in a single [get_blocks] request.
 pub const DEFAULT_MAX_BLOCKS_PER_RESPONSE: u64 = 2000;
 
+/// The maximum number of accounts in a single [get_accounts_transactions] request.
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
 #[derive(CandidType, Debug, Deserialize)]
 pub enum IndexArg {
     Init(InitArg),
@@ -66,6 +69,80 @@ pub struct GetAccountTransactionsError {
 pub type GetAccountTransactionsResult =
     Result<GetAccountTransactionsResponse, GetAccountTransactionsError>;
 
+#[derive(CandidType, Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TransactionKind {
+    Mint,
+    Burn,
+    Transfer,
+    Approve,
+}
+
+#[derive(CandidType, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct TransactionFilter {
+    // Only return transactions of the given kinds.
+    // If None then transactions of all kinds are returned.
+    pub kinds: Option<Vec<TransactionKind>>,
+    // Only return transactions with a timestamp greater than
+    // or equal to start_timestamp, in nanoseconds since the epoch.
+    pub start_timestamp: Option<u64>,
+    // Only return transactions with a timestamp strictly lower
+    // than end_timestamp, in nanoseconds since the epoch.
+    pub end_timestamp: Option<u64>,
+    // Only return transfers between the account and the counterparty,
+    // and approvals of the account for the counterparty.
+    pub counterparty: Option<Account>,
+}
+
+#[derive(CandidType, Debug, Deserialize, PartialEq, Eq)]
+pub struct GetAccountsTransactionsArgs {
+    // The accounts whose transactions should be fetched,
+    // at most MAX_ACCOUNTS_PER_REQUEST.
+    pub accounts: Vec<Account>,
+    // The txid of the last transaction seen by the client.
+    // If None then the results will start from the most recent
+    // txid. If set then the results will start from the next
+    // most recent txid after start (start won't be included).
+    pub start: Option<BlockIndex>,
+    // Maximum number of transactions to fetch.
+    pub max_results: Nat,
+    pub filter: Option<TransactionFilter>,
+}
+
+#[derive(CandidType, Debug, Deserialize, PartialEq, Eq)]
+pub struct GetAccountsTransactionsResponse {
+    // The balances of the requested accounts, in the order of the request.
+    pub balances: Vec<(Account, Nat)>,
+    // The transactions of all the requested accounts from the most recent
+    // to the oldest. A transaction between two requested accounts is
+    // returned only once.
+    pub transactions: Vec<TransactionWithId>,
+}
+
+pub type GetAccountsTransactionsResult =
+    Result<GetAccountsTransactionsResponse, GetAccountTransactionsError>;
+
+#[derive(CandidType, Debug, Deserialize, PartialEq, Eq)]
+pub struct GetAccountBalanceHistoryArgs {
+    pub account: Account,
+    // The txid of the last balance change seen by the client.
+    // If None then the results will start from the most recent
+    // balance change. If set then the results will start from
+    // the next most recent change after start (start won't be included).
+    pub start: Option<BlockIndex>,
+    // Maximum number of balance changes to fetch.
+    pub max_results: Nat,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct BalanceHistoryEntry {
+    // The txid of the transaction that changed the balance.
+    pub block_index: BlockIndex,
+    // The timestamp of the transaction, in nanoseconds since the epoch.
+    pub timestamp: u64,
+    // The balance of the account after the transaction.
+    pub balance: Nat,
+}
+
 #[derive(CandidType, Debug, Deserialize, PartialEq, Eq)]
 pub struct ListSubaccountsArgs {
     pub owner: Principal,
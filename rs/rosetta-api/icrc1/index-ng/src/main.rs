@@ -9,9 +9,12 @@ use ic_crypto_sha2::Sha256;
 use ic_icrc1::blocks::{encoded_block_to_generic_block, generic_block_to_encoded_block};
 use ic_icrc1::{Block, Operation};
 use ic_icrc1_index_ng::{
-    FeeCollectorRanges, GetAccountTransactionsArgs, GetAccountTransactionsResponse,
-    GetAccountTransactionsResult, IndexArg, ListSubaccountsArgs, Log, LogEntry, Status,
-    TransactionWithId, DEFAULT_MAX_BLOCKS_PER_RESPONSE,
+    BalanceHistoryEntry, FeeCollectorRanges, GetAccountBalanceHistoryArgs,
+    GetAccountTransactionsArgs, GetAccountTransactionsError, GetAccountTransactionsResponse,
+    GetAccountTransactionsResult, GetAccountsTransactionsArgs, GetAccountsTransactionsResponse,
+    GetAccountsTransactionsResult, IndexArg, ListSubaccountsArgs, Log, LogEntry, Status,
+    TransactionFilter, TransactionKind, TransactionWithId, DEFAULT_MAX_BLOCKS_PER_RESPONSE,
+    MAX_ACCOUNTS_PER_REQUEST,
 };
 use ic_ledger_core::block::{BlockIndex as BlockIndex64, BlockType, EncodedBlock};
 use ic_ledger_core::tokens::{CheckedAdd, CheckedSub, Zero};
@@ -22,6 +25,7 @@ use ic_stable_structures::{
     StableLog, Storable,
 };
 use icrc_ledger_types::icrc1::account::{Account, Subaccount};
+use icrc_ledger_types::icrc1::transfer::BlockIndex;
 use icrc_ledger_types::icrc3::archive::{ArchivedRange, QueryBlockArchiveFn};
 use icrc_ledger_types::icrc3::blocks::{
     BlockRange, GenericBlock, GetBlocksRequest, GetBlocksResponse,
@@ -50,6 +54,15 @@ const BLOCK_LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(1);
 const BLOCK_LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(2);
 const ACCOUNT_BLOCK_IDS_MEMORY_ID: MemoryId = MemoryId::new(3);
 const ACCOUNT_DATA_MEMORY_ID: MemoryId = MemoryId::new(4);
+const ACCOUNT_BLOCK_KIND_IDS_MEMORY_ID: MemoryId = MemoryId::new(5);
+const ACCOUNT_COUNTERPARTY_BLOCK_IDS_MEMORY_ID: MemoryId = MemoryId::new(6);
+const BLOCK_TIMESTAMPS_MEMORY_ID: MemoryId = MemoryId::new(7);
+const ACCOUNT_BALANCE_HISTORY_MEMORY_ID: MemoryId = MemoryId::new(8);
+
+/// The version of the indexes derived from the block log. The indexes
+/// added after the version of an upgraded index are backfilled from the
+/// block log, see [IndexesBackfill].
+const INDEXES_VERSION: u32 = 1;
 
 const DEFAULT_MAX_WAIT_TIME: Duration = Duration::from_secs(2);
 const DEFAULT_RETRY_WAIT_TIME: Duration = Duration::from_secs(1);
@@ -68,6 +81,25 @@ type BlockLog = StableLog<Vec<u8>, VM, VM>;
 type AccountBlockIdsMapKey = ([u8; Sha256::DIGEST_LEN], Reverse<u64>);
 type AccountBlockIdsMap = StableBTreeMap<AccountBlockIdsMapKey, (), VM>;
 
+type AccountBlockKindIdsMapKey = (([u8; Sha256::DIGEST_LEN], BlockKind), Reverse<u64>);
+type AccountBlockKindIdsMap = StableBTreeMap<AccountBlockKindIdsMapKey, (), VM>;
+
+// The first element of this tuple is the pair of the hashes of
+// the account and of its counterparty in the transaction.
+type AccountCounterpartyBlockIdsMapKey = (
+    ([u8; Sha256::DIGEST_LEN], [u8; Sha256::DIGEST_LEN]),
+    Reverse<u64>,
+);
+type AccountCounterpartyBlockIdsMap =
+    StableBTreeMap<AccountCounterpartyBlockIdsMapKey, BlockKind, VM>;
+
+// The block timestamps and indices. The timestamps of the blocks
+// of a ledger never decrease with the block index.
+type BlockTimestampsMap = StableBTreeMap<(u64, BlockIndex64), (), VM>;
+
+// The balance of the account after the block and the timestamp of the block.
+type AccountBalanceHistoryMap = StableBTreeMap<AccountBlockIdsMapKey, (Tokens, u64), VM>;
+
 // The second element of this tuple is the account represented
 // as principal of type Blob<29> and the effective subaccount
 type AccountDataMapKey = (AccountDataType, (Blob<29>, [u8; 32]));
@@ -100,6 +132,26 @@ thread_local! {
         RefCell::new(AccountDataMap::init(memory_manager.get(ACCOUNT_DATA_MEMORY_ID)))
     });
 
+    /// Map that contains the block ids of an account by kind of block.
+    static ACCOUNT_BLOCK_KIND_IDS: RefCell<AccountBlockKindIdsMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(AccountBlockKindIdsMap::init(memory_manager.get(ACCOUNT_BLOCK_KIND_IDS_MEMORY_ID)))
+    });
+
+    /// Map that contains the block ids of an account by counterparty.
+    static ACCOUNT_COUNTERPARTY_BLOCK_IDS: RefCell<AccountCounterpartyBlockIdsMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(AccountCounterpartyBlockIdsMap::init(memory_manager.get(ACCOUNT_COUNTERPARTY_BLOCK_IDS_MEMORY_ID)))
+    });
+
+    /// Map that contains the blocks ordered by timestamp.
+    static BLOCK_TIMESTAMPS: RefCell<BlockTimestampsMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(BlockTimestampsMap::init(memory_manager.get(BLOCK_TIMESTAMPS_MEMORY_ID)))
+    });
+
+    /// Map that contains the balance of an account after each block changing it.
+    static ACCOUNT_BALANCE_HISTORY: RefCell<AccountBalanceHistoryMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(AccountBalanceHistoryMap::init(memory_manager.get(ACCOUNT_BALANCE_HISTORY_MEMORY_ID)))
+    });
+
     /// Profiling data to understand cycles usage
     static PROFILING_DATA: RefCell<SpanStats> = RefCell::new(SpanStats::default());
 }
@@ -123,6 +175,27 @@ struct State {
 
     /// This fee is used if no fee nor effetive_fee is found in Approve blocks.
     pub last_fee: Option<Tokens>,
+
+    /// The version of the indexes built from the block log, see [INDEXES_VERSION].
+    #[serde(default)]
+    indexes_version: u32,
+
+    /// The progress of the backfill of the indexes added by the last upgrade.
+    #[serde(default)]
+    indexes_backfill: Option<IndexesBackfill>,
+}
+
+/// The progress of the backfill of the transaction filter and balance history
+/// indexes for the blocks indexed before the upgrade that added them.
+/// The account block ids and the balances of these blocks are already indexed
+/// and are not touched by the backfill.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct IndexesBackfill {
+    /// The blocks whose indexes remain to be backfilled.
+    blocks: Range<BlockIndex64>,
+
+    /// The fee of the last Transfer block before `blocks.start`.
+    last_fee: Option<Tokens>,
 }
 
 // NOTE: the default configuration is dysfunctional, but it's convenient to have
@@ -136,6 +209,8 @@ impl Default for State {
             last_wait_time: Duration::from_secs(0),
             fee_collectors: Default::default(),
             last_fee: None,
+            indexes_version: INDEXES_VERSION,
+            indexes_backfill: None,
         }
     }
 }
@@ -193,6 +268,76 @@ impl BoundedStorable for AccountDataType {
     const IS_FIXED_SIZE: bool = true;
 }
 
+/// The kind of the operation of a block, used as part of the keys of the indexes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum BlockKind {
+    Mint = 0,
+    Burn = 1,
+    Transfer = 2,
+    Approve = 3,
+}
+
+impl BlockKind {
+    fn of(block: &Block<Tokens>) -> Self {
+        match block.transaction.operation {
+            Operation::Mint { .. } => Self::Mint,
+            Operation::Burn { .. } => Self::Burn,
+            Operation::Transfer { .. } => Self::Transfer,
+            Operation::Approve { .. } => Self::Approve,
+        }
+    }
+}
+
+impl From<TransactionKind> for BlockKind {
+    fn from(kind: TransactionKind) -> Self {
+        match kind {
+            TransactionKind::Mint => Self::Mint,
+            TransactionKind::Burn => Self::Burn,
+            TransactionKind::Transfer => Self::Transfer,
+            TransactionKind::Approve => Self::Approve,
+        }
+    }
+}
+
+impl Storable for BlockKind {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(vec![*self as u8])
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        if bytes.len() != 1 {
+            panic!(
+                "Expected a single byte for BlockKind but found {}",
+                bytes.len()
+            );
+        }
+        match bytes[0] {
+            0 => Self::Mint,
+            1 => Self::Burn,
+            2 => Self::Transfer,
+            3 => Self::Approve,
+            kind => panic!("Unknown BlockKind {}", kind),
+        }
+    }
+}
+
+#[test]
+fn test_block_kind_storable() {
+    for kind in [
+        BlockKind::Mint,
+        BlockKind::Burn,
+        BlockKind::Transfer,
+        BlockKind::Approve,
+    ] {
+        assert_eq!(kind, BlockKind::from_bytes(kind.to_bytes()));
+    }
+}
+
+impl BoundedStorable for BlockKind {
+    const MAX_SIZE: u32 = 1;
+    const IS_FIXED_SIZE: bool = true;
+}
+
 /// A helper function to access the scalar state.
 fn with_state<R>(f: impl FnOnce(&State) -> R) -> R {
     STATE.with(|cell| f(cell.borrow().get()))
@@ -230,6 +375,28 @@ fn with_account_data<R>(f: impl FnOnce(&mut AccountDataMap) -> R) -> R {
     ACCOUNT_DATA.with(|cell| f(&mut cell.borrow_mut()))
 }
 
+/// A helper function to access the account block ids by kind.
+fn with_account_block_kind_ids<R>(f: impl FnOnce(&mut AccountBlockKindIdsMap) -> R) -> R {
+    ACCOUNT_BLOCK_KIND_IDS.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the account block ids by counterparty.
+fn with_account_counterparty_block_ids<R>(
+    f: impl FnOnce(&mut AccountCounterpartyBlockIdsMap) -> R,
+) -> R {
+    ACCOUNT_COUNTERPARTY_BLOCK_IDS.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the block timestamps.
+fn with_block_timestamps<R>(f: impl FnOnce(&mut BlockTimestampsMap) -> R) -> R {
+    BLOCK_TIMESTAMPS.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the account balance history.
+fn with_account_balance_history<R>(f: impl FnOnce(&mut AccountBalanceHistoryMap) -> R) -> R {
+    ACCOUNT_BALANCE_HISTORY.with(|cell| f(&mut cell.borrow_mut()))
+}
+
 /// A helper function that returns a decoded block stored in the
 /// block log at the given index or None if there is no block at that index.
 /// This function can trap if the index at the given block cannot be decoded
@@ -297,6 +464,22 @@ fn post_upgrade(index_arg: Option<IndexArg>) {
         _ => (),
     };
 
+    if with_state(|state| state.indexes_version) < INDEXES_VERSION {
+        let num_blocks = with_blocks(|blocks| blocks.len());
+        log!(
+            P0,
+            "[post_upgrade] the indexes are outdated, backfilling the new indexes of {} blocks",
+            num_blocks
+        );
+        mutate_state(|state| {
+            state.indexes_version = INDEXES_VERSION;
+            state.indexes_backfill = Some(IndexesBackfill {
+                blocks: 0..num_blocks,
+                last_fee: None,
+            });
+        });
+    }
+
     // set the first build_index to be called after init
     set_build_index_timer(Duration::from_secs(0));
 }
@@ -388,6 +571,16 @@ pub async fn build_index() -> Option<()> {
     let failure_guard = guard((), |_| {
         set_build_index_timer(DEFAULT_RETRY_WAIT_TIME);
     });
+    let max_blocks_per_response = with_state(|state| state.max_blocks_per_response);
+    if backfill_indexes(max_blocks_per_response) {
+        // The new blocks are fully indexed, therefore the backfill
+        // does not need to complete before fetching them.
+        log!(
+            P1,
+            "Backfilled the indexes of up to {} blocks",
+            max_blocks_per_response
+        );
+    }
     let next_txid = with_blocks(|blocks| blocks.len());
     let res = get_blocks_from_ledger(next_txid).await?;
     let mut tx_indexed_count: usize = 0;
@@ -409,7 +602,11 @@ pub async fn build_index() -> Option<()> {
     }
     tx_indexed_count += res.blocks.len();
     append_blocks(res.blocks);
-    let wait_time = compute_wait_time(tx_indexed_count);
+    let wait_time = if is_backfilling_indexes() {
+        Duration::ZERO
+    } else {
+        compute_wait_time(tx_indexed_count)
+    };
     log!(
         P1,
         "Indexed: {} waiting : {:?}",
@@ -455,18 +652,7 @@ fn append_block(block_index: BlockIndex64, block: GenericBlock) {
 
         let decoded_block = decode_encoded_block_or_trap(block_index, block);
 
-        // add the block idx to the indices
-        with_account_block_ids(|account_block_ids| {
-            for account in get_accounts(&decoded_block) {
-                account_block_ids.insert(account_block_ids_key(account, block_index), ());
-            }
-        });
-
-        // add the block to the fee_collector if one is set
-        index_fee_collector(block_index, &decoded_block);
-
-        // change the balance of the involved accounts
-        process_balance_changes(block_index, &decoded_block);
+        index_block(block_index, &decoded_block);
     });
 }
 
@@ -480,6 +666,132 @@ fn append_blocks(new_blocks: Vec<GenericBlock>) {
     }
 }
 
+/// Backfills the transaction filter and balance history indexes of at most
+/// `max_blocks` blocks that were indexed before the upgrade that added them.
+/// Returns true if some blocks were backfilled.
+fn backfill_indexes(max_blocks: u64) -> bool {
+    let backfill = with_state(|state| state.indexes_backfill.clone());
+    let Some(IndexesBackfill {
+        blocks,
+        mut last_fee,
+    }) = backfill
+    else {
+        return false;
+    };
+    let end = blocks.end.min(blocks.start.saturating_add(max_blocks));
+    for block_index in blocks.start..end {
+        let block = get_decoded_block(block_index).unwrap_or_else(|| {
+            trap(&format!(
+                "Block {} not found in the block log while backfilling the indexes",
+                block_index
+            ))
+        });
+        index_block_filters(block_index, &block);
+        last_fee = transfer_fee(block_index, &block).or(last_fee);
+        backfill_balance_history(block_index, &block, last_fee);
+    }
+    mutate_state(|state| {
+        state.indexes_backfill = (end < blocks.end).then_some(IndexesBackfill {
+            blocks: end..blocks.end,
+            last_fee,
+        })
+    });
+    true
+}
+
+/// Returns true while the indexes added by the last upgrade are incomplete.
+fn is_backfilling_indexes() -> bool {
+    with_state(|state| state.indexes_backfill.is_some())
+}
+
+/// Records the balances after a block indexed before the upgrade that added the
+/// balance history. The current balances already include the later blocks, so
+/// the balances are computed from the previous entries of the history instead.
+fn backfill_balance_history(
+    block_index: BlockIndex64,
+    block: &Block<Tokens>,
+    last_fee: Option<Tokens>,
+) {
+    with_account_balance_history(|account_balance_history| {
+        for (account, change) in balance_changes(block_index, block, last_fee) {
+            let key = account_block_ids_key(account, block_index);
+            let balance = match account_balance_history.get(&key) {
+                // The account appears more than once in the block.
+                Some((balance, _)) => balance,
+                None => account_balance_history
+                    .range((key.0, Reverse(block_index))..)
+                    .take_while(|(k, _)| k.0 == key.0)
+                    .next()
+                    .map_or_else(Tokens::zero, |(_, (balance, _))| balance),
+            };
+            account_balance_history.insert(
+                key,
+                (change.apply(block_index, account, balance), block.timestamp),
+            );
+        }
+    });
+}
+
+fn index_block(block_index: BlockIndex64, block: &Block<Tokens>) {
+    // add the block idx to the indices
+    with_account_block_ids(|account_block_ids| {
+        for account in get_accounts(block) {
+            account_block_ids.insert(account_block_ids_key(account, block_index), ());
+        }
+    });
+
+    // add the block to the indices used to filter transactions
+    index_block_filters(block_index, block);
+
+    // add the block to the fee_collector if one is set
+    index_fee_collector(block_index, block);
+
+    // change the balance of the involved accounts
+    process_balance_changes(block_index, block);
+
+    // record the new balance of the involved accounts
+    record_balance_history(block_index, block);
+}
+
+fn index_block_filters(block_index: BlockIndex64, block: &Block<Tokens>) {
+    let kind = BlockKind::of(block);
+    with_account_block_kind_ids(|account_block_kind_ids| {
+        for account in get_accounts(block) {
+            account_block_kind_ids
+                .insert(((account_sha256(account), kind), Reverse(block_index)), ());
+        }
+    });
+    with_account_counterparty_block_ids(|account_counterparty_block_ids| {
+        for (account, counterparty) in get_counterparties(block) {
+            account_counterparty_block_ids.insert(
+                (
+                    (account_sha256(account), account_sha256(counterparty)),
+                    Reverse(block_index),
+                ),
+                kind,
+            );
+        }
+    });
+    with_block_timestamps(|block_timestamps| {
+        block_timestamps.insert((block.timestamp, block_index), ())
+    });
+}
+
+fn record_balance_history(block_index: BlockIndex64, block: &Block<Tokens>) {
+    let mut accounts = get_accounts(block);
+    if let Operation::Transfer { .. } = block.transaction.operation {
+        accounts.extend(get_fee_collector(block_index, block));
+    }
+    with_account_balance_history(|account_balance_history| {
+        for account in accounts {
+            account_balance_history.insert(
+                account_block_ids_key(account, block_index),
+                (get_balance(account), block.timestamp),
+            );
+        }
+    });
+}
+
 fn index_fee_collector(block_index: BlockIndex64, block: &Block<Tokens>) {
     if let Some(fee_collector) = get_fee_collector(block_index, block) {
         mutate_state(|s| {
@@ -507,92 +819,120 @@ fn push_block(block_ranges: &mut Vec<Range<BlockIndex64>>, block_index: BlockInd
     }
 }
 
-fn process_balance_changes(block_index: BlockIndex64, block: &Block<Tokens>) {
-    measure_span(
-        &PROFILING_DATA,
-        "append_blocks.process_balance_changes",
-        move || match block.transaction.operation {
-            Operation::Burn { from, amount, .. } => debit(block_index, from, amount),
-            Operation::Mint { to, amount } => credit(block_index, to, amount),
-            Operation::Transfer {
-                from,
-                to,
-                amount,
-                fee,
-                ..
-            } => {
-                let fee = block.effective_fee.or(fee).unwrap_or_else(|| {
-                    ic_cdk::trap(&format!(
-                        "Block {} is of type Transfer but has no fee or effective fee!",
-                        block_index
-                    ))
-                });
-                mutate_state(|s| s.last_fee = Some(fee));
-                debit(
-                    block_index,
+/// A change of the balance of an account caused by a block.
+#[derive(Clone, Copy, Debug)]
+enum BalanceChange {
+    Credit(Tokens),
+    Debit(Tokens),
+}
+
+impl BalanceChange {
+    fn apply(self, block_index: BlockIndex64, account: Account, balance: Tokens) -> Tokens {
+        match self {
+            Self::Credit(amount) => balance.checked_add(&amount).unwrap_or_else(|| {
+                ic_cdk::trap(&format!("Block {} caused an overflow for account {} when calculating balance {} + amount {}",
+                    block_index, account, balance, amount))
+            }),
+            Self::Debit(amount) => balance.checked_sub(&amount).unwrap_or_else(|| {
+                ic_cdk::trap(&format!("Block {} caused an underflow for account {} when calculating balance {} - amount {}",
+                    block_index, account, balance, amount));
+            }),
+        }
+    }
+}
+
+/// Returns the fee of the block if it is a Transfer block.
+fn transfer_fee(block_index: BlockIndex64, block: &Block<Tokens>) -> Option<Tokens> {
+    match block.transaction.operation {
+        Operation::Transfer { fee, .. } => Some(block.effective_fee.or(fee).unwrap_or_else(|| {
+            ic_cdk::trap(&format!(
+                "Block {} is of type Transfer but has no fee or effective fee!",
+                block_index
+            ))
+        })),
+        _ => None,
+    }
+}
+
+/// Returns the changes of the balances caused by the block in the order in
+/// which they apply. `last_fee` is the fee of the last Transfer block before
+/// the block.
+fn balance_changes(
+    block_index: BlockIndex64,
+    block: &Block<Tokens>,
+    last_fee: Option<Tokens>,
+) -> Vec<(Account, BalanceChange)> {
+    match block.transaction.operation {
+        Operation::Burn { from, amount, .. } => vec![(from, BalanceChange::Debit(amount))],
+        Operation::Mint { to, amount } => vec![(to, BalanceChange::Credit(amount))],
+        Operation::Transfer {
+            from, to, amount, ..
+        } => {
+            let fee = transfer_fee(block_index, block).expect("bug: a Transfer block has a fee");
+            let mut changes = vec![
+                (
                     from,
-                    amount.checked_add(&fee).unwrap_or_else(|| {
+                    BalanceChange::Debit(amount.checked_add(&fee).unwrap_or_else(|| {
                         ic_cdk::trap(&format!(
                             "token amount overflow while indexing block {block_index}"
                         ))
-                    }),
-                );
-                credit(block_index, to, amount);
-                if let Some(fee_collector) = get_fee_collector(block_index, block) {
-                    credit(block_index, fee_collector, fee);
-                }
+                    })),
+                ),
+                (to, BalanceChange::Credit(amount)),
+            ];
+            if let Some(fee_collector) = get_fee_collector(block_index, block) {
+                changes.push((fee_collector, BalanceChange::Credit(fee)));
             }
-            Operation::Approve {
-                from, fee, spender, ..
-            } => {
-                let fee = match fee.or(block.effective_fee) {
-                    Some(fee) => fee,
-                    // NB. There was a bug in the ledger which would create
-                    // approve blocks with the fee fields unset. The bug was
-                    // quickly fixed, but there are a few blocks on the mainnet
-                    // that don't have their fee fields populated.
-                    None => match with_state(|state| state.last_fee) {
-                        Some(last_fee) => {
-                            log!(
-                                P1,
-                                "fee and effective_fee aren't set in block {block_index}, using last transfer fee {last_fee}"
-                            );
-                            last_fee
-                        }
-                        None => ic_cdk::trap(&format!("bug: index is stuck because block with index {block_index} doesn't contain a fee and no fee has been recorded before")),
+            changes
+        }
+        Operation::Approve { from, fee, .. } => {
+            let fee = match fee.or(block.effective_fee) {
+                Some(fee) => fee,
+                // NB. There was a bug in the ledger which would create
+                // approve blocks with the fee fields unset. The bug was
+                // quickly fixed, but there are a few blocks on the mainnet
+                // that don't have their fee fields populated.
+                None => match last_fee {
+                    Some(last_fee) => {
+                        log!(
+                            P1,
+                            "fee and effective_fee aren't set in block {block_index}, using last transfer fee {last_fee}"
+                        );
+                        last_fee
                     }
-                };
+                    None => ic_cdk::trap(&format!("bug: index is stuck because block with index {block_index} doesn't contain a fee and no fee has been recorded before")),
+                }
+            };
+            vec![(from, BalanceChange::Debit(fee))]
+        }
+    }
+}
 
+fn process_balance_changes(block_index: BlockIndex64, block: &Block<Tokens>) {
+    measure_span(
+        &PROFILING_DATA,
+        "append_blocks.process_balance_changes",
+        move || {
+            if let Some(fee) = transfer_fee(block_index, block) {
+                mutate_state(|s| s.last_fee = Some(fee));
+            }
+            if let Operation::Approve { spender, .. } = block.transaction.operation {
                 // It is possible that the spender account has not existed prior to this approve transaction.
                 // Until a transfer_from transaction occurs such account would not show up in a `list_subaccounts` query as the spender is not involved in any credit or debit calls at this point.
                 // To ensure that the account still shows up in the `list_subaccount` query we can simply call `change_balance` without actually changing the balance.
                 // If the account is new, this will add it to the AccountDataMap with balance 0 and thus show up in a `list_subaccount` query.
                 change_balance(spender, |balance| balance);
-
-                debit(block_index, from, fee);
+            }
+            let last_fee = with_state(|state| state.last_fee);
+            for (account, change) in balance_changes(block_index, block, last_fee) {
+                change_balance(account, |balance| {
+                    change.apply(block_index, account, balance)
+                });
             }
         },
     );
 }
 
-fn debit(block_index: BlockIndex64, account: Account, amount: Tokens) {
-    change_balance(account, |balance| {
-        balance.checked_sub(&amount).unwrap_or_else(|| {
-            ic_cdk::trap(&format!("Block {} caused an underflow for account {} when calculating balance {} - amount {}",
-                block_index, account, balance, amount));
-        })
-    })
-}
-
-fn credit(block_index: BlockIndex64, account: Account, amount: Tokens) {
-    change_balance(account, |balance| {
-        balance.checked_add(&amount).unwrap_or_else(|| {
-            ic_cdk::trap(&format!("Block {} caused an overflow for account {} when calculating balance {} + amount {}",
-                block_index, account, balance, amount))
-        })
-    });
-}
-
 fn generic_block_to_encoded_block_or_trap(
     block_index: BlockIndex64,
     block: GenericBlock,
@@ -623,6 +963,15 @@ fn get_accounts(block: &Block<Tokens>) -> Vec<Account> {
     }
 }
 
+/// Returns the pairs of accounts and counterparties of the block.
+fn get_counterparties(block: &Block<Tokens>) -> Vec<(Account, Account)> {
+    match block.transaction.operation {
+        Operation::Burn { .. } | Operation::Mint { .. } => vec![],
+        Operation::Transfer { from, to, .. } => vec![(from, to), (to, from)],
+        Operation::Approve { from, spender, .. } => vec![(from, spender)],
+    }
+}
+
 fn get_fee_collector(block_index: BlockIndex64, block: &Block<Tokens>) -> Option<Account> {
     if block.fee_collector.is_some() {
         block.fee_collector
@@ -686,32 +1035,107 @@ fn ledger_id() -> Principal {
     with_state(|state| state.ledger_id)
 }
 
-#[query]
-#[candid_method(query)]
-fn get_account_transactions(arg: GetAccountTransactionsArgs) -> GetAccountTransactionsResult {
-    let length = arg
-        .max_results
+/// Converts the max_results argument of a request into the
+/// number of results to return.
+fn max_results_to_length(max_results: &Nat) -> usize {
+    max_results
         .0
         .to_u64()
         .expect("The length must be a u64!")
         .min(with_state(|opts| opts.max_blocks_per_response))
-        .min(usize::MAX as u64) as usize;
+        .min(usize::MAX as u64) as usize
+}
+
+/// Converts the start argument of a request into the (excluded)
+/// block index from which to return results.
+fn start_to_block_index(start: Option<BlockIndex>) -> BlockIndex64 {
     // TODO: deal with the user setting start to u64::MAX
-    let start = arg
-        .start
-        .map_or(u64::MAX, |n| n.0.to_u64().expect("start must be a u64!"));
-    let key = account_block_ids_key(arg.account, start);
-    let mut transactions = vec![];
-    let indices = with_account_block_ids(|account_block_ids| {
-        account_block_ids
-            .range(key..)
-            // old txs of the requested account and skip the start index
-            .take_while(|(k, _)| k.0 == key.0)
-            .filter(|(k, _)| k.1 .0 < start)
-            .take(length)
-            .map(|(k, _)| k.1 .0)
-            .collect::<Vec<BlockIndex64>>()
+    start.map_or(u64::MAX, |n| n.0.to_u64().expect("start must be a u64!"))
+}
+
+/// Returns the range of the indices of the blocks with a timestamp
+/// within the time range of the filter.
+fn filter_block_range(filter: &TransactionFilter) -> Range<BlockIndex64> {
+    // The timestamps of the blocks never decrease, therefore the blocks
+    // in the time range are between the first block with a timestamp
+    // greater than or equal to the start and the first block with a
+    // timestamp greater than or equal to the end.
+    let first_block_at = |timestamp: u64| {
+        with_block_timestamps(|block_timestamps| {
+            block_timestamps
+                .range((timestamp, 0)..)
+                .next()
+                .map_or(u64::MAX, |((_, block_index), _)| block_index)
+        })
+    };
+    let start = filter.start_timestamp.map_or(0, first_block_at);
+    let end = filter.end_timestamp.map_or(u64::MAX, first_block_at);
+    start..end
+}
+
+/// Returns at most `length` indices of the blocks of the account within the
+/// range and matching the kinds and counterparty of the filter, from the
+/// most recent to the oldest.
+fn get_account_block_ids(
+    account: Account,
+    range: Range<BlockIndex64>,
+    filter: &TransactionFilter,
+    length: usize,
+) -> Vec<BlockIndex64> {
+    if range.is_empty() || length == 0 {
+        return vec![];
+    }
+    let account_hash = account_sha256(account);
+    let first = Reverse(range.end - 1);
+    let kinds = filter.kinds.as_ref().map(|kinds| {
+        let mut kinds: Vec<BlockKind> = kinds.iter().map(|kind| BlockKind::from(*kind)).collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
     });
+    if let Some(counterparty) = filter.counterparty {
+        let prefix = (account_hash, account_sha256(counterparty));
+        with_account_counterparty_block_ids(|account_counterparty_block_ids| {
+            account_counterparty_block_ids
+                .range((prefix, first)..)
+                .take_while(|(k, _)| k.0 == prefix && k.1 .0 >= range.start)
+                .filter(|(_, kind)| kinds.as_ref().map_or(true, |kinds| kinds.contains(kind)))
+                .take(length)
+                .map(|(k, _)| k.1 .0)
+                .collect()
+        })
+    } else if let Some(kinds) = kinds {
+        let mut indices: Vec<BlockIndex64> = kinds
+            .into_iter()
+            .flat_map(|kind| {
+                let prefix = (account_hash, kind);
+                with_account_block_kind_ids(|account_block_kind_ids| {
+                    account_block_kind_ids
+                        .range((prefix, first)..)
+                        .take_while(|(k, _)| k.0 == prefix && k.1 .0 >= range.start)
+                        .take(length)
+                        .map(|(k, _)| k.1 .0)
+                        .collect::<Vec<BlockIndex64>>()
+                })
+            })
+            .collect();
+        indices.sort_unstable_by(|a, b| b.cmp(a));
+        indices.truncate(length);
+        indices
+    } else {
+        with_account_block_ids(|account_block_ids| {
+            account_block_ids
+                .range((account_hash, first)..)
+                .take_while(|(k, _)| k.0 == account_hash && k.1 .0 >= range.start)
+                .take(length)
+                .map(|(k, _)| k.1 .0)
+                .collect()
+        })
+    }
+}
+
+fn get_transactions_with_ids(indices: Vec<BlockIndex64>) -> Vec<TransactionWithId> {
+    let mut transactions = vec![];
     for id in indices {
         let block = with_blocks(|blocks| {
             blocks.get(id).unwrap_or_else(|| {
@@ -728,6 +1152,18 @@ fn get_account_transactions(arg: GetAccountTransactionsArgs) -> GetAccountTransa
         };
         transactions.push(transaction_with_idx);
     }
+    transactions
+}
+
+#[query]
+#[candid_method(query)]
+fn get_account_transactions(arg: GetAccountTransactionsArgs) -> GetAccountTransactionsResult {
+    let length = max_results_to_length(&arg.max_results);
+    let start = start_to_block_index(arg.start);
+    // old txs of the requested account and skip the start index
+    let indices =
+        get_account_block_ids(arg.account, 0..start, &TransactionFilter::default(), length);
+    let transactions = get_transactions_with_ids(indices);
     let oldest_tx_id = get_oldest_tx_id(arg.account).map(|tx_id| tx_id.into());
     let balance = get_balance(arg.account).into();
     Ok(GetAccountTransactionsResponse {
@@ -737,6 +1173,74 @@ fn get_account_transactions(arg: GetAccountTransactionsArgs) -> GetAccountTransa
     })
 }
 
+#[query]
+#[candid_method(query)]
+fn get_accounts_transactions(arg: GetAccountsTransactionsArgs) -> GetAccountsTransactionsResult {
+    if arg.accounts.len() > MAX_ACCOUNTS_PER_REQUEST {
+        return Err(GetAccountTransactionsError {
+            message: format!(
+                "The number of accounts {} is greater than the maximum number of accounts per request {}",
+                arg.accounts.len(),
+                MAX_ACCOUNTS_PER_REQUEST
+            ),
+        });
+    }
+    let filter = arg.filter.unwrap_or_default();
+    if filter != TransactionFilter::default() && is_backfilling_indexes() {
+        return Err(GetAccountTransactionsError {
+            message: "The transaction filters are not available until the index finishes backfilling them after the upgrade".to_string(),
+        });
+    }
+    let length = max_results_to_length(&arg.max_results);
+    let start = start_to_block_index(arg.start);
+    let range = filter_block_range(&filter);
+    let range = range.start..range.end.min(start);
+    let mut indices: Vec<BlockIndex64> = arg
+        .accounts
+        .iter()
+        .flat_map(|account| get_account_block_ids(*account, range.clone(), &filter, length))
+        .collect();
+    // a transaction between two of the accounts appears in the indices of both
+    indices.sort_unstable_by(|a, b| b.cmp(a));
+    indices.dedup();
+    indices.truncate(length);
+    let balances = arg
+        .accounts
+        .iter()
+        .map(|account| (*account, get_balance(*account).into()))
+        .collect();
+    Ok(GetAccountsTransactionsResponse {
+        balances,
+        transactions: get_transactions_with_ids(indices),
+    })
+}
+
+#[query]
+#[candid_method(query)]
+fn get_account_balance_history(arg: GetAccountBalanceHistoryArgs) -> Vec<BalanceHistoryEntry> {
+    if is_backfilling_indexes() {
+        trap("The balance history is not available until the index finishes backfilling it after the upgrade");
+    }
+    let length = max_results_to_length(&arg.max_results);
+    let start = start_to_block_index(arg.start);
+    if start == 0 {
+        return vec![];
+    }
+    let account_hash = account_sha256(arg.account);
+    with_account_balance_history(|account_balance_history| {
+        account_balance_history
+            .range((account_hash, Reverse(start - 1))..)
+            .take_while(|(k, _)| k.0 == account_hash)
+            .take(length)
+            .map(|(k, (balance, timestamp))| BalanceHistoryEntry {
+                block_index: k.1 .0.into(),
+                timestamp,
+                balance: balance.into(),
+            })
+            .collect()
+    })
+}
+
 fn encoded_block_bytes_to_flat_transaction(
     block_index: BlockIndex64,
     block: Vec<u8>,
@@ -947,3 +1451,101 @@ fn compute_wait_time_test() {
     assert_eq!(wait_time(25), compute_wait_time(blocks(75)));
     assert_eq!(wait_time(0), compute_wait_time(blocks(100)));
 }
+
+#[test]
+fn test_backfill_indexes() {
+    let account = |n: u8| Account {
+        owner: Principal::from_slice(&[n]),
+        subaccount: None,
+    };
+    let tokens = |n: u64| Tokens::try_from(Nat::from(n)).unwrap();
+    let blocks = vec![
+        Operation::Mint {
+            to: account(1),
+            amount: tokens(1_000_000),
+        },
+        Operation::Transfer {
+            from: account(1),
+            to: account(2),
+            spender: None,
+            amount: tokens(100_000),
+            fee: Some(tokens(100)),
+        },
+        Operation::Approve {
+            from: account(2),
+            spender: account(1),
+            amount: tokens(10_000),
+            expected_allowance: None,
+            expires_at: None,
+            fee: Some(tokens(100)),
+        },
+        Operation::Transfer {
+            from: account(2),
+            to: account(2),
+            spender: None,
+            amount: tokens(1_000),
+            fee: Some(tokens(100)),
+        },
+        Operation::Burn {
+            from: account(1),
+            spender: None,
+            amount: tokens(5_000),
+        },
+    ];
+    for (block_index, operation) in blocks.into_iter().enumerate() {
+        let block = Block {
+            parent_hash: None,
+            transaction: ic_icrc1::Transaction {
+                operation,
+                created_at_time: None,
+                memo: None,
+            },
+            effective_fee: None,
+            timestamp: block_index as u64 * 1_000,
+            fee_collector: Some(account(3)),
+            fee_collector_block_index: None,
+        };
+        with_blocks(|blocks| blocks.append(&block.clone().encode().into_vec()))
+            .expect("failed to append a block");
+        index_block(block_index as u64, &block);
+    }
+
+    let snapshot = || {
+        (
+            with_account_block_kind_ids(|m| m.iter().collect::<Vec<_>>()),
+            with_account_counterparty_block_ids(|m| m.iter().collect::<Vec<_>>()),
+            with_block_timestamps(|m| m.iter().collect::<Vec<_>>()),
+            with_account_balance_history(|m| m.iter().collect::<Vec<_>>()),
+        )
+    };
+    let expected = snapshot();
+
+    // Drop the indexes that an index without them did not build.
+    with_memory_manager(|memory_manager| {
+        with_account_block_kind_ids(|m| {
+            *m = AccountBlockKindIdsMap::new(memory_manager.get(ACCOUNT_BLOCK_KIND_IDS_MEMORY_ID))
+        });
+        with_account_counterparty_block_ids(|m| {
+            *m = AccountCounterpartyBlockIdsMap::new(
+                memory_manager.get(ACCOUNT_COUNTERPARTY_BLOCK_IDS_MEMORY_ID),
+            )
+        });
+        with_block_timestamps(|m| {
+            *m = BlockTimestampsMap::new(memory_manager.get(BLOCK_TIMESTAMPS_MEMORY_ID))
+        });
+        with_account_balance_history(|m| {
+            *m =
+                AccountBalanceHistoryMap::new(memory_manager.get(ACCOUNT_BALANCE_HISTORY_MEMORY_ID))
+        });
+    });
+    mutate_state(|state| {
+        state.indexes_backfill = Some(IndexesBackfill {
+            blocks: 0..5,
+            last_fee: None,
+        })
+    });
+
+    while backfill_indexes(2) {}
+    assert!(!is_backfilling_indexes());
+    assert_eq!(snapshot(), expected);
+}
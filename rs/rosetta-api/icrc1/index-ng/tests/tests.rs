@@ -4,10 +4,12 @@ use ic_canisters_http_types::{HttpRequest, HttpResponse};
 use ic_icrc1::blocks::generic_block_to_encoded_block;
 use ic_icrc1::Block;
 use ic_icrc1_index_ng::{
-    FeeCollectorRanges, GetAccountTransactionsArgs, GetAccountTransactionsResponse,
-    GetAccountTransactionsResult, GetBlocksResponse, IndexArg, InitArg as IndexInitArg,
-    ListSubaccountsArgs, Log, Status, TransactionWithId, UpgradeArg as IndexUpgradeArg,
-    DEFAULT_MAX_BLOCKS_PER_RESPONSE,
+    BalanceHistoryEntry, FeeCollectorRanges, GetAccountBalanceHistoryArgs,
+    GetAccountTransactionsArgs, GetAccountTransactionsResponse, GetAccountTransactionsResult,
+    GetAccountsTransactionsArgs, GetAccountsTransactionsResult, GetBlocksResponse, IndexArg,
+    InitArg as IndexInitArg, ListSubaccountsArgs, Log, Status, TransactionFilter, TransactionKind,
+    TransactionWithId, UpgradeArg as IndexUpgradeArg, DEFAULT_MAX_BLOCKS_PER_RESPONSE,
+    MAX_ACCOUNTS_PER_REQUEST,
 };
 use ic_icrc1_ledger::{
     ChangeFeeCollector, FeatureFlags, InitArgsBuilder as LedgerInitArgsBuilder, LedgerArgument,
@@ -479,6 +481,64 @@ fn get_account_transactions(
         .expect("Failed to perform GetAccountTransactionsArgs")
 }
 
+fn get_accounts_transactions(
+    env: &StateMachine,
+    index_id: CanisterId,
+    accounts: Vec<Account>,
+    start: Option<u64>,
+    max_results: u64,
+    filter: Option<TransactionFilter>,
+) -> GetAccountsTransactionsResult {
+    let req = GetAccountsTransactionsArgs {
+        accounts,
+        start: start.map(|n| n.into()),
+        max_results: max_results.into(),
+        filter,
+    };
+    let req = Encode!(&req).expect("Failed to encode GetAccountsTransactionsArgs");
+    let res = env
+        .execute_ingress(index_id, "get_accounts_transactions", req)
+        .expect("Failed to get_accounts_transactions")
+        .bytes();
+    Decode!(&res, GetAccountsTransactionsResult)
+        .expect("Failed to decode GetAccountsTransactionsResult")
+}
+
+// Returns the ids of the transactions of the accounts that match the filter.
+fn get_accounts_transactions_ids(
+    env: &StateMachine,
+    index_id: CanisterId,
+    accounts: Vec<Account>,
+    filter: TransactionFilter,
+) -> Vec<u64> {
+    get_accounts_transactions(env, index_id, accounts, None, u64::MAX, Some(filter))
+        .expect("Failed to perform GetAccountsTransactionsArgs")
+        .transactions
+        .into_iter()
+        .map(|tx| tx.id.0.to_u64().unwrap())
+        .collect()
+}
+
+fn get_account_balance_history(
+    env: &StateMachine,
+    index_id: CanisterId,
+    account: Account,
+    start: Option<u64>,
+    max_results: u64,
+) -> Vec<BalanceHistoryEntry> {
+    let req = GetAccountBalanceHistoryArgs {
+        account,
+        start: start.map(|n| n.into()),
+        max_results: max_results.into(),
+    };
+    let req = Encode!(&req).expect("Failed to encode GetAccountBalanceHistoryArgs");
+    let res = env
+        .execute_ingress(index_id, "get_account_balance_history", req)
+        .expect("Failed to get_account_balance_history")
+        .bytes();
+    Decode!(&res, Vec<BalanceHistoryEntry>).expect("Failed to decode Vec<BalanceHistoryEntry>")
+}
+
 fn list_subaccounts(
     env: &StateMachine,
     index: CanisterId,
@@ -1395,3 +1455,220 @@ fn test_principal_subaccounts() {
     // The subaccount 1 should show up in a `list_subaccount` query although it has only been involved in an Approve transaction
     assert!(subaccounts.contains(&account(2, 1).subaccount.unwrap()));
 }
+
+#[test]
+fn test_get_accounts_transactions_filters() {
+    let env = &StateMachine::new();
+    let ledger_id = install_ledger(
+        env,
+        vec![(account(1, 0), 1_000_000_000_000)], // txid: 0
+        default_archive_options(),
+        None,
+    );
+    let index_id = install_index_ng(env, ledger_id);
+
+    transfer(env, ledger_id, account(1, 0), account(2, 0), 1_000_000); // txid: 1
+    transfer(env, ledger_id, account(1, 0), account(3, 0), 2_000_000); // txid: 2
+    approve(env, ledger_id, account(1, 0), account(4, 0), 3_000_000); // txid: 3
+
+    env.advance_time(Duration::from_secs(3600));
+    let later = env
+        .time()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+
+    transfer(env, ledger_id, account(2, 0), account(1, 0), 100_000); // txid: 4
+    transfer(env, ledger_id, account(2, 0), MINTER, 100_000); // txid: 5
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+
+    let ids = |accounts: Vec<Account>, filter: TransactionFilter| {
+        get_accounts_transactions_ids(env, index_id, accounts, filter)
+    };
+
+    // No filter.
+    assert_eq!(
+        ids(vec![account(1, 0)], TransactionFilter::default()),
+        vec![4, 3, 2, 1, 0]
+    );
+
+    // Filter by kind.
+    let kinds = |kinds: Vec<TransactionKind>| TransactionFilter {
+        kinds: Some(kinds),
+        ..TransactionFilter::default()
+    };
+    assert_eq!(
+        ids(vec![account(1, 0)], kinds(vec![TransactionKind::Transfer])),
+        vec![4, 2, 1]
+    );
+    assert_eq!(
+        ids(
+            vec![account(1, 0)],
+            kinds(vec![TransactionKind::Approve, TransactionKind::Mint])
+        ),
+        vec![3, 0]
+    );
+    assert_eq!(
+        ids(vec![account(2, 0)], kinds(vec![TransactionKind::Burn])),
+        vec![5]
+    );
+
+    // Filter by counterparty.
+    let counterparty = |counterparty: Account| TransactionFilter {
+        counterparty: Some(counterparty),
+        ..TransactionFilter::default()
+    };
+    assert_eq!(
+        ids(vec![account(1, 0)], counterparty(account(2, 0))),
+        vec![4, 1]
+    );
+    assert_eq!(
+        ids(vec![account(1, 0)], counterparty(account(4, 0))),
+        vec![3]
+    );
+    assert_eq!(
+        ids(
+            vec![account(1, 0)],
+            TransactionFilter {
+                kinds: Some(vec![TransactionKind::Transfer]),
+                counterparty: Some(account(4, 0)),
+                ..TransactionFilter::default()
+            }
+        ),
+        Vec::<u64>::new()
+    );
+
+    // Filter by time.
+    assert_eq!(
+        ids(
+            vec![account(1, 0)],
+            TransactionFilter {
+                start_timestamp: Some(later),
+                ..TransactionFilter::default()
+            }
+        ),
+        vec![4]
+    );
+    assert_eq!(
+        ids(
+            vec![account(1, 0)],
+            TransactionFilter {
+                end_timestamp: Some(later),
+                ..TransactionFilter::default()
+            }
+        ),
+        vec![3, 2, 1, 0]
+    );
+
+    // Multiple accounts, the transactions between them are returned once.
+    assert_eq!(
+        ids(
+            vec![account(1, 0), account(2, 0)],
+            TransactionFilter::default()
+        ),
+        vec![5, 4, 3, 2, 1, 0]
+    );
+    let res = get_accounts_transactions(
+        env,
+        index_id,
+        vec![account(1, 0), account(2, 0)],
+        Some(4),
+        2,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        res.transactions
+            .iter()
+            .map(|tx| tx.id.0.to_u64().unwrap())
+            .collect::<Vec<_>>(),
+        vec![3, 2]
+    );
+    assert_eq!(
+        res.balances,
+        vec![
+            (
+                account(1, 0),
+                Nat::from(icrc1_balance_of(env, ledger_id, account(1, 0)))
+            ),
+            (
+                account(2, 0),
+                Nat::from(icrc1_balance_of(env, ledger_id, account(2, 0)))
+            ),
+        ]
+    );
+
+    // Too many accounts.
+    let accounts = (0..=MAX_ACCOUNTS_PER_REQUEST as u128)
+        .map(|subaccount| account(1, subaccount))
+        .collect();
+    assert!(get_accounts_transactions(env, index_id, accounts, None, 10, None).is_err());
+}
+
+#[test]
+fn test_get_account_balance_history() {
+    let env = &StateMachine::new();
+    let ledger_id = install_ledger(
+        env,
+        vec![(account(1, 0), 10_000_000)], // txid: 0
+        default_archive_options(),
+        None,
+    );
+    let index_id = install_index_ng(env, ledger_id);
+
+    transfer(env, ledger_id, account(1, 0), account(2, 0), 1_000_000); // txid: 1
+    approve(env, ledger_id, account(1, 0), account(2, 0), 1_000_000); // txid: 2
+    transfer(env, ledger_id, account(2, 0), account(1, 0), 100_000); // txid: 3
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+
+    let history = |account: Account, start: Option<u64>, max_results: u64| {
+        get_account_balance_history(env, index_id, account, start, max_results)
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.block_index.0.to_u64().unwrap(),
+                    entry.balance.0.to_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        history(account(1, 0), None, 10),
+        vec![
+            (3, 10_000_000 - 1_000_000 - 2 * FEE + 100_000),
+            (2, 10_000_000 - 1_000_000 - 2 * FEE),
+            (1, 10_000_000 - 1_000_000 - FEE),
+            (0, 10_000_000),
+        ]
+    );
+    assert_eq!(
+        history(account(2, 0), None, 10),
+        vec![(3, 1_000_000 - 100_000 - FEE), (1, 1_000_000)]
+    );
+
+    // Pagination.
+    assert_eq!(
+        history(account(1, 0), Some(3), 2),
+        vec![
+            (2, 10_000_000 - 1_000_000 - 2 * FEE),
+            (1, 10_000_000 - 1_000_000 - FEE),
+        ]
+    );
+    assert_eq!(
+        history(account(1, 0), Some(0), 10),
+        Vec::<(u64, u64)>::new()
+    );
+
+    // The history survives upgrades.
+    env.upgrade_canister(
+        index_id,
+        index_ng_wasm(),
+        Encode!(&None::<IndexArg>).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        history(account(2, 0), None, 1),
+        vec![(3, 1_000_000 - 100_000 - FEE)]
+    );
+}
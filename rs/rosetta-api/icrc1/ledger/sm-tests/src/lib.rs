@@ -1,5 +1,6 @@
 use candid::{CandidType, Decode, Encode, Int, Nat, Principal};
 use ic_base_types::PrincipalId;
+use ic_crypto_tree_hash::{LookupStatus, MixedHashTree};
 use ic_error_types::UserError;
 use ic_icrc1::blocks::encoded_block_to_generic_block;
 use ic_icrc1::{endpoints::StandardRecord, hash::Hash, Block, Operation, Transaction};
@@ -9,17 +10,24 @@ use ic_ledger_core::block::{BlockIndex, BlockType};
 use ic_ledger_hash_of::HashOf;
 use ic_state_machine_tests::{CanisterId, ErrorCode, StateMachine, WasmResult};
 use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue as Value;
-use icrc_ledger_types::icrc::generic_value::Value as GenericValue;
+use icrc_ledger_types::icrc::generic_value::{ICRC3Value, Value as GenericValue};
 use icrc_ledger_types::icrc1::account::{Account, Subaccount};
 use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
 use icrc_ledger_types::icrc2::allowance::{Allowance, AllowanceArgs};
 use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
 use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
+use icrc_ledger_types::icrc21::{
+    errors::Icrc21Error,
+    requests::{ConsentMessageMetadata, ConsentMessageRequest, ConsentMessageSpec},
+    responses::{ConsentInfo, ConsentMessage},
+};
 use icrc_ledger_types::icrc3;
-use icrc_ledger_types::icrc3::archive::ArchiveInfo;
+use icrc_ledger_types::icrc3::archive::{ArchiveInfo, GetArchivesArgs, GetArchivesResult};
 use icrc_ledger_types::icrc3::blocks::BlockRange;
 use icrc_ledger_types::icrc3::blocks::GenericBlock as IcrcBlock;
-use icrc_ledger_types::icrc3::blocks::GetBlocksResponse;
+use icrc_ledger_types::icrc3::blocks::{
+    GetBlocksRequest, GetBlocksResponse, GetBlocksResult, ICRC3DataCertificate, SupportedBlockType,
+};
 use icrc_ledger_types::icrc3::transactions::GetTransactionsRequest;
 use icrc_ledger_types::icrc3::transactions::GetTransactionsResponse;
 use icrc_ledger_types::icrc3::transactions::Transaction as Tx;
@@ -28,6 +36,7 @@ use icrc_ledger_types::icrc3::transactions::Transfer;
 use num_traits::ToPrimitive;
 use proptest::prelude::*;
 use proptest::test_runner::{Config as TestRunnerConfig, TestCaseResult, TestRunner};
+use serde_bytes::ByteBuf;
 use std::{
     cmp,
     collections::{BTreeMap, HashMap},
@@ -311,6 +320,69 @@ fn get_archive_blocks(
     get_transactions_as(env, archive, start, length, "get_blocks".to_string())
 }
 
+fn icrc3_get_archives(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: Option<Principal>,
+) -> GetArchivesResult {
+    Decode!(
+        &env.query(
+            ledger,
+            "icrc3_get_archives",
+            Encode!(&GetArchivesArgs { from }).unwrap()
+        )
+        .expect("failed to query icrc3_get_archives")
+        .bytes(),
+        GetArchivesResult
+    )
+    .expect("failed to decode icrc3_get_archives response")
+}
+
+fn icrc3_get_blocks(
+    env: &StateMachine,
+    canister: Principal,
+    ranges: Vec<(u64, u64)>,
+) -> GetBlocksResult {
+    let canister_id = CanisterId::unchecked_from_principal(canister.into());
+    let args: Vec<GetBlocksRequest> = ranges
+        .into_iter()
+        .map(|(start, length)| GetBlocksRequest {
+            start: Nat::from(start),
+            length: Nat::from(length),
+        })
+        .collect();
+    Decode!(
+        &env.query(canister_id, "icrc3_get_blocks", Encode!(&args).unwrap())
+            .expect("failed to query icrc3_get_blocks")
+            .bytes(),
+        GetBlocksResult
+    )
+    .expect("failed to decode icrc3_get_blocks response")
+}
+
+fn icrc3_get_tip_certificate(
+    env: &StateMachine,
+    ledger: CanisterId,
+) -> Option<ICRC3DataCertificate> {
+    Decode!(
+        &env.query(ledger, "icrc3_get_tip_certificate", Encode!().unwrap())
+            .expect("failed to query icrc3_get_tip_certificate")
+            .bytes(),
+        Option<ICRC3DataCertificate>
+    )
+    .expect("failed to decode icrc3_get_tip_certificate response")
+}
+
+fn icrc3_supported_block_types(env: &StateMachine, ledger: CanisterId) -> Vec<SupportedBlockType> {
+    Decode!(
+        &env.query(ledger, "icrc3_supported_block_types", Encode!().unwrap())
+            .expect("failed to query icrc3_supported_block_types")
+            .bytes(),
+        Vec<SupportedBlockType>
+    )
+    .expect("failed to decode icrc3_supported_block_types response")
+}
+
 fn get_phash(block: &IcrcBlock) -> Result<Option<Hash>, String> {
     match block {
         IcrcBlock::Map(map) => {
@@ -702,7 +774,7 @@ where
         standards.push(standard.name);
     }
     standards.sort();
-    assert_eq!(standards, vec!["ICRC-1", "ICRC-2"]);
+    assert_eq!(standards, vec!["ICRC-1", "ICRC-2", "ICRC-21"]);
 }
 pub fn test_metadata<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
 where
@@ -770,7 +842,7 @@ where
         standards.push(standard.name);
     }
     standards.sort();
-    assert_eq!(standards, vec!["ICRC-1", "ICRC-2"]);
+    assert_eq!(standards, vec!["ICRC-1", "ICRC-2", "ICRC-21", "ICRC-3"]);
 }
 
 pub fn test_total_supply<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
@@ -1361,6 +1433,122 @@ where
     assert_eq!(0, missing_blocks_reply.archived_blocks.len());
 }
 
+pub fn test_icrc3_get_blocks<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let p1 = PrincipalId::new_user_test_id(1);
+    let p2 = PrincipalId::new_user_test_id(2);
+
+    let (env, canister_id) = setup(
+        ledger_wasm,
+        encode_init_args,
+        vec![(Account::from(p1.0), 10_000_000)],
+    );
+
+    for i in 0..ARCHIVE_TRIGGER_THRESHOLD {
+        transfer(&env, canister_id, p1.0, p2.0, 10_000 + i * 10_000).expect("transfer failed");
+    }
+
+    env.run_until_completion(/*max_ticks=*/ 10);
+
+    let archives = icrc3_get_archives(&env, canister_id, None);
+    assert_eq!(archives.len(), 1);
+    assert_eq!(archives[0].start, Nat::from(0));
+    assert_eq!(archives[0].end, Nat::from(NUM_BLOCKS_TO_ARCHIVE - 1));
+    let archive_id = archives[0].canister_id;
+    assert_eq!(
+        icrc3_get_archives(&env, canister_id, Some(archive_id)),
+        vec![]
+    );
+
+    let chain_length = ARCHIVE_TRIGGER_THRESHOLD + 1;
+    let resp = icrc3_get_blocks(&env, canister_id.get().0, vec![(0, 1_000_000)]);
+    assert_eq!(resp.log_length, Nat::from(chain_length));
+    assert_eq!(
+        resp.blocks.len(),
+        (chain_length - NUM_BLOCKS_TO_ARCHIVE) as usize
+    );
+    assert_eq!(resp.archived_blocks.len(), 1);
+    let archived_blocks = &resp.archived_blocks[0];
+    assert_eq!(archived_blocks.callback.canister_id, archive_id);
+    assert_eq!(archived_blocks.callback.method, "icrc3_get_blocks");
+    assert_eq!(
+        archived_blocks.args,
+        vec![GetBlocksRequest {
+            start: Nat::from(0),
+            length: Nat::from(NUM_BLOCKS_TO_ARCHIVE),
+        }]
+    );
+
+    let archive_resp = icrc3_get_blocks(&env, archive_id, vec![(0, NUM_BLOCKS_TO_ARCHIVE)]);
+    assert_eq!(archive_resp.blocks.len(), NUM_BLOCKS_TO_ARCHIVE as usize);
+    assert_eq!(archive_resp.archived_blocks, vec![]);
+
+    // Check that the ICRC-3 blocks are the legacy blocks and that the hash chain is correct.
+    let legacy_blocks: Vec<IcrcBlock> =
+        get_archive_blocks(&env, archive_id, 0, NUM_BLOCKS_TO_ARCHIVE as usize)
+            .blocks
+            .into_iter()
+            .chain(get_blocks(&env, canister_id.get().0, 0, 1_000_000).blocks)
+            .collect();
+    let blocks: Vec<_> = archive_resp
+        .blocks
+        .into_iter()
+        .chain(resp.blocks.into_iter())
+        .collect();
+    assert_eq!(blocks.len(), legacy_blocks.len());
+
+    let mut prev_hash = None;
+    for (i, (block, legacy_block)) in blocks.iter().zip(legacy_blocks.iter()).enumerate() {
+        assert_eq!(block.id, Nat::from(i));
+        assert_eq!(block.block, ICRC3Value::from(legacy_block.clone()));
+        assert_eq!(
+            prev_hash,
+            get_phash(legacy_block).expect("cannot get the hash of the previous block")
+        );
+        prev_hash = Some(block.block.hash());
+    }
+
+    // Check that the tip certificate certifies the hash of the last block.
+    let certificate = icrc3_get_tip_certificate(&env, canister_id)
+        .expect("the tip certificate should be available in a query");
+    let hash_tree: MixedHashTree = ciborium::de::from_reader(certificate.hash_tree.as_slice())
+        .expect("failed to decode the hash tree");
+    assert_eq!(
+        hash_tree.lookup(&[b"last_block_hash"]),
+        LookupStatus::Found(&MixedHashTree::Leaf(prev_hash.unwrap().to_vec()))
+    );
+    let mut last_block_index = vec![];
+    leb128::write::unsigned(&mut last_block_index, chain_length - 1).unwrap();
+    assert_eq!(
+        hash_tree.lookup(&[b"last_block_index"]),
+        LookupStatus::Found(&MixedHashTree::Leaf(last_block_index))
+    );
+    let mut hash_tree_version = vec![];
+    leb128::write::unsigned(&mut hash_tree_version, ic_icrc1_ledger::HASH_TREE_VERSION).unwrap();
+    assert_eq!(
+        hash_tree.lookup(&[b"hash_tree_version"]),
+        LookupStatus::Found(&MixedHashTree::Leaf(hash_tree_version))
+    );
+
+    // Check that requesting non-existing blocks does not crash the ledger.
+    let missing_blocks_reply = icrc3_get_blocks(&env, canister_id.get().0, vec![(100, 5)]);
+    assert_eq!(0, missing_blocks_reply.blocks.len());
+    assert_eq!(0, missing_blocks_reply.archived_blocks.len());
+
+    let block_types: Vec<_> = icrc3_supported_block_types(&env, canister_id)
+        .into_iter()
+        .map(|block_type| block_type.block_type)
+        .collect();
+    for block_type in ["1burn", "1mint", "1xfer"] {
+        assert!(block_types.contains(&block_type.to_string()));
+    }
+    assert!(supported_standards(&env, canister_id)
+        .iter()
+        .any(|standard| standard.name == "ICRC-3"));
+}
+
 // Generate random blocks and check that their CBOR encoding complies with the CDDL spec.
 pub fn block_encoding_agrees_with_the_schema() {
     use std::path::PathBuf;
@@ -2398,6 +2586,103 @@ where
         .ends_with("the minting account cannot delegate mints"));
 }
 
+fn icrc21_consent_message(
+    env: &StateMachine,
+    ledger: CanisterId,
+    caller: Principal,
+    method: &str,
+    arg: Vec<u8>,
+) -> Result<ConsentInfo, Icrc21Error> {
+    let request = ConsentMessageRequest {
+        method: method.to_string(),
+        arg: ByteBuf::from(arg),
+        user_preferences: ConsentMessageSpec {
+            metadata: ConsentMessageMetadata {
+                language: "en".to_string(),
+                utc_offset_minutes: None,
+            },
+            device_spec: None,
+        },
+    };
+    Decode!(
+        &env.execute_ingress_as(
+            PrincipalId(caller),
+            ledger,
+            "icrc21_canister_call_consent_message",
+            Encode!(&request).unwrap()
+        )
+        .expect("failed to get the consent message")
+        .bytes(),
+        Result<ConsentInfo, Icrc21Error>
+    )
+    .expect("failed to decode icrc21_canister_call_consent_message response")
+}
+
+pub fn test_icrc21_consent_message<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let from = PrincipalId::new_user_test_id(1);
+    let receiver = PrincipalId::new_user_test_id(2);
+
+    let (env, canister_id) = setup(ledger_wasm, encode_init_args, vec![]);
+
+    let transfer_arg = TransferArg {
+        from_subaccount: None,
+        to: Account::from(receiver.0),
+        fee: None,
+        created_at_time: None,
+        memo: None,
+        amount: Nat::from(150_000_000u64),
+    };
+    let consent_info = icrc21_consent_message(
+        &env,
+        canister_id,
+        from.0,
+        "icrc1_transfer",
+        Encode!(&transfer_arg).unwrap(),
+    )
+    .expect("failed to build the transfer consent message");
+    assert_eq!(consent_info.metadata.language, "en");
+    match consent_info.consent_message {
+        ConsentMessage::GenericDisplayMessage(message) => {
+            assert!(message.contains(&format!("`1.5 {}`", TOKEN_SYMBOL)));
+            assert!(message.contains(&format!("`0.0001 {}`", TOKEN_SYMBOL)));
+            assert!(message.contains(&from.0.to_string()));
+            assert!(message.contains(&receiver.0.to_string()));
+        }
+        other => panic!("unexpected consent message: {:?}", other),
+    }
+
+    let approve_args = default_approve_args(receiver.0, 150_000);
+    let consent_info = icrc21_consent_message(
+        &env,
+        canister_id,
+        from.0,
+        "icrc2_approve",
+        Encode!(&approve_args).unwrap(),
+    )
+    .expect("failed to build the approve consent message");
+    match consent_info.consent_message {
+        ConsentMessage::GenericDisplayMessage(message) => {
+            assert!(message.contains(&format!("`0.0015 {}`", TOKEN_SYMBOL)));
+            assert!(message.contains(&receiver.0.to_string()));
+        }
+        other => panic!("unexpected consent message: {:?}", other),
+    }
+
+    assert!(matches!(
+        icrc21_consent_message(
+            &env,
+            canister_id,
+            from.0,
+            "icrc1_balance_of",
+            Encode!(&Account::from(from.0)).unwrap(),
+        ),
+        Err(Icrc21Error::UnsupportedCanisterCall(_))
+    ));
+}
+
 pub fn expect_icrc2_disabled(
     env: &StateMachine,
     from: PrincipalId,
@@ -2405,6 +2690,7 @@ pub fn expect_icrc2_disabled(
     approve_args: &ApproveArgs,
     allowance_args: &AllowanceArgs,
     transfer_from_args: &TransferFromArgs,
+    expected_standards: &[&str],
 ) {
     let err = env
         .execute_ingress_as(
@@ -2451,9 +2737,12 @@ pub fn expect_icrc2_disabled(
         "Expected ICRC-2 disabled error, got: {}",
         err.description()
     );
-    let standards = supported_standards(env, canister_id);
-    assert_eq!(standards.len(), 1);
-    assert_eq!(standards[0].name, "ICRC-1");
+    let mut standards: Vec<_> = supported_standards(env, canister_id)
+        .into_iter()
+        .map(|standard| standard.name)
+        .collect();
+    standards.sort();
+    assert_eq!(standards, expected_standards);
 }
 
 pub fn test_feature_flags<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
@@ -2489,6 +2778,7 @@ where
         &approve_args,
         &allowance_args,
         &transfer_from_args,
+        &["ICRC-1", "ICRC-21", "ICRC-3"],
     );
 
     let upgrade_args = LedgerArgument::Upgrade(Some(UpgradeArgs {
@@ -2510,6 +2800,7 @@ where
         &approve_args,
         &allowance_args,
         &transfer_from_args,
+        &["ICRC-1", "ICRC-21", "ICRC-3"],
     );
 
     let upgrade_args = LedgerArgument::Upgrade(Some(UpgradeArgs {
@@ -2525,7 +2816,7 @@ where
         standards.push(standard.name);
     }
     standards.sort();
-    assert_eq!(standards, vec!["ICRC-1", "ICRC-2"]);
+    assert_eq!(standards, vec!["ICRC-1", "ICRC-2", "ICRC-21", "ICRC-3"]);
 
     let block_index =
         send_approval(&env, canister_id, from.0, &approve_args).expect("approval failed");
@@ -1,16 +1,18 @@
 pub mod cdk_runtime;
+pub mod storage;
 
 #[cfg(test)]
 mod tests;
 
 use crate::cdk_runtime::CdkRuntime;
+use crate::storage::{StableAllowancesData, StableBalances, StableBlockData};
 use candid::{
     types::number::{Int, Nat},
     CandidType, Principal,
 };
 use ic_crypto_tree_hash::{Label, MixedHashTree};
 use ic_icrc1::blocks::encoded_block_to_generic_block;
-use ic_icrc1::{Block, LedgerBalances, Transaction};
+use ic_icrc1::{Block, Transaction};
 pub use ic_ledger_canister_core::archive::ArchiveOptions;
 use ic_ledger_canister_core::{
     archive::ArchiveCanisterWasm,
@@ -28,11 +30,18 @@ use ic_ledger_core::{
 use ic_ledger_hash_of::HashOf;
 use icrc_ledger_types::icrc1::account::Account;
 use icrc_ledger_types::icrc3::transactions::Transaction as Tx;
-use icrc_ledger_types::icrc3::{blocks::GetBlocksResponse, transactions::GetTransactionsResponse};
+use icrc_ledger_types::icrc3::{
+    blocks::{
+        ArchivedBlocks, BlockWithId, GetBlocksArgs, GetBlocksRequest, GetBlocksResponse,
+        GetBlocksResult, ICRC3GenericBlock,
+    },
+    transactions::GetTransactionsResponse,
+};
 use icrc_ledger_types::{
     icrc::generic_metadata_value::MetadataValue as Value,
-    icrc3::archive::{ArchivedRange, QueryBlockArchiveFn, QueryTxArchiveFn},
+    icrc3::archive::{ArchivedRange, ICRC3GetBlocksFn, QueryBlockArchiveFn, QueryTxArchiveFn},
 };
+use num_traits::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use serde_bytes::ByteBuf;
 use std::borrow::Cow;
@@ -47,9 +56,22 @@ const MAX_TRANSACTIONS_PER_REQUEST: usize = 2_000;
 const ACCOUNTS_OVERFLOW_TRIM_QUANTITY: usize = 100_000;
 const MAX_TRANSACTIONS_IN_WINDOW: usize = 3_000_000;
 const MAX_TRANSACTIONS_TO_PURGE: usize = 100_000;
+/// The maximum amount of bytes a 64-bit number can occupy when encoded in
+/// LEB128.
+const MAX_U64_ENCODING_BYTES: usize = 10;
+/// The version of the layout of the hash tree certifying the tip of the block
+/// log, certified under the label `hash_tree_version`. Trees without that
+/// label (version 0) certify the last block index in big-endian and the last
+/// block hash only under `tip_hash`. Version 1 certifies the last block index
+/// in LEB128 and the last block hash under `last_block_hash`, as required by
+/// ICRC-3.
+pub const HASH_TREE_VERSION: u64 = 1;
 
 const DEFAULT_MAX_MEMO_LENGTH: u16 = 32;
 
+pub type LedgerBalances<Tokens> = Balances<StableBalances<Tokens>>;
+pub type LedgerAllowances<Tokens> = AllowanceTable<StableAllowancesData<Tokens>>;
+
 #[derive(Debug, Clone)]
 pub struct Icrc1ArchiveWasm;
 
@@ -275,8 +297,8 @@ pub enum LedgerArgument {
 pub struct Ledger<Tokens: TokensType> {
     balances: LedgerBalances<Tokens>,
     #[serde(default)]
-    approvals: AllowanceTable<ApprovalKey, Account, Tokens>,
-    blockchain: Blockchain<CdkRuntime, Icrc1ArchiveWasm>,
+    approvals: LedgerAllowances<Tokens>,
+    blockchain: Blockchain<CdkRuntime, Icrc1ArchiveWasm, StableBlockData>,
 
     minting_account: Account,
     fee_collector: Option<FeeCollector<Account>>,
@@ -425,8 +447,8 @@ impl From<ApprovalKey> for (Account, Account) {
 
 impl<Tokens: TokensType> LedgerContext for Ledger<Tokens> {
     type AccountId = Account;
-    type Approvals = AllowanceTable<ApprovalKey, Account, Tokens>;
-    type BalancesStore = BTreeMap<Self::AccountId, Tokens>;
+    type Approvals = LedgerAllowances<Tokens>;
+    type BalancesStore = StableBalances<Tokens>;
     type Tokens = Tokens;
 
     fn balances(&self) -> &Balances<Self::BalancesStore> {
@@ -453,6 +475,7 @@ impl<Tokens: TokensType> LedgerContext for Ledger<Tokens> {
 impl<Tokens: TokensType> LedgerData for Ledger<Tokens> {
     type Runtime = CdkRuntime;
     type ArchiveWasm = Icrc1ArchiveWasm;
+    type BlockData = StableBlockData;
     type Transaction = Transaction<Tokens>;
     type Block = Block<Tokens>;
 
@@ -484,11 +507,13 @@ impl<Tokens: TokensType> LedgerData for Ledger<Tokens> {
         &self.token_symbol
     }
 
-    fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm> {
+    fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm, Self::BlockData> {
         &self.blockchain
     }
 
-    fn blockchain_mut(&mut self) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm> {
+    fn blockchain_mut(
+        &mut self,
+    ) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm, Self::BlockData> {
         &mut self.blockchain
     }
 
@@ -609,19 +634,42 @@ impl<Tokens: TokensType> Ledger<Tokens> {
         self.construct_hash_tree().digest().0
     }
 
+    /// Constructs the hash tree certifying the tip of the block log.
+    ///
+    /// The tree contains the labels `last_block_index` (LEB128-encoded) and `last_block_hash`
+    /// required by ICRC-3. The label `tip_hash` (an alias of `last_block_hash`) is kept for the
+    /// clients of `get_data_certificate`, and the label `hash_tree_version` (LEB128-encoded
+    /// [HASH_TREE_VERSION]) lets them tell this layout from the one with a big-endian
+    /// `last_block_index`.
     pub fn construct_hash_tree(&self) -> MixedHashTree {
         match self.blockchain().last_hash {
             Some(hash) => {
                 let last_block_index = self.blockchain().chain_length().checked_sub(1).unwrap();
+                let mut last_block_index_encoded = Vec::with_capacity(MAX_U64_ENCODING_BYTES);
+                leb128::write::unsigned(&mut last_block_index_encoded, last_block_index).unwrap();
+                let mut hash_tree_version_encoded = Vec::with_capacity(MAX_U64_ENCODING_BYTES);
+                leb128::write::unsigned(&mut hash_tree_version_encoded, HASH_TREE_VERSION).unwrap();
                 MixedHashTree::Fork(Box::new((
                     MixedHashTree::Labeled(
-                        Label::from("last_block_index"),
-                        Box::new(MixedHashTree::Leaf(last_block_index.to_be_bytes().to_vec())),
-                    ),
-                    MixedHashTree::Labeled(
-                        Label::from("tip_hash"),
-                        Box::new(MixedHashTree::Leaf(hash.as_slice().to_vec())),
+                        Label::from("hash_tree_version"),
+                        Box::new(MixedHashTree::Leaf(hash_tree_version_encoded)),
                     ),
+                    MixedHashTree::Fork(Box::new((
+                        MixedHashTree::Labeled(
+                            Label::from("last_block_hash"),
+                            Box::new(MixedHashTree::Leaf(hash.as_slice().to_vec())),
+                        ),
+                        MixedHashTree::Fork(Box::new((
+                            MixedHashTree::Labeled(
+                                Label::from("last_block_index"),
+                                Box::new(MixedHashTree::Leaf(last_block_index_encoded)),
+                            ),
+                            MixedHashTree::Labeled(
+                                Label::from("tip_hash"),
+                                Box::new(MixedHashTree::Leaf(hash.as_slice().to_vec())),
+                            ),
+                        ))),
+                    ))),
                 )))
             }
             None => MixedHashTree::Empty,
@@ -698,4 +746,59 @@ impl<Tokens: TokensType> Ledger<Tokens> {
             archived_blocks,
         }
     }
+
+    /// Returns the blocks in the specified ranges in the ICRC-3 format.
+    ///
+    /// The ledger returns at most [MAX_TRANSACTIONS_PER_REQUEST] local blocks.
+    /// Archived blocks are grouped by archive, with a callback to fetch them.
+    pub fn icrc3_get_blocks(&self, args: GetBlocksArgs) -> GetBlocksResult {
+        let mut blocks = vec![];
+        let mut archived_ranges: BTreeMap<Principal, GetBlocksArgs> = BTreeMap::new();
+
+        for arg in args {
+            let start = match arg.start.0.to_u64() {
+                Some(start) => start,
+                // There is no block beyond u64::MAX.
+                None => continue,
+            };
+            let length = arg.length.0.to_usize().unwrap_or(usize::MAX);
+            let locations = block_locations(self, start, length);
+
+            let local_blocks_range = range_utils::take(
+                &locations.local_blocks,
+                MAX_TRANSACTIONS_PER_REQUEST.saturating_sub(blocks.len()),
+            );
+            for (id, encoded_block) in local_blocks_range
+                .clone()
+                .zip(self.blockchain.block_slice(local_blocks_range))
+            {
+                blocks.push(BlockWithId {
+                    id: Nat::from(id),
+                    block: ICRC3GenericBlock::from(encoded_block_to_generic_block(&encoded_block)),
+                });
+            }
+
+            for (canister_id, slice) in locations.archived_blocks {
+                archived_ranges
+                    .entry(canister_id.get().0)
+                    .or_default()
+                    .push(GetBlocksRequest {
+                        start: Nat::from(slice.start),
+                        length: Nat::from(range_utils::range_len(&slice)),
+                    });
+            }
+        }
+
+        GetBlocksResult {
+            log_length: Nat::from(self.blockchain.chain_length()),
+            blocks,
+            archived_blocks: archived_ranges
+                .into_iter()
+                .map(|(canister_id, args)| ArchivedBlocks {
+                    args,
+                    callback: ICRC3GetBlocksFn::new(canister_id, "icrc3_get_blocks"),
+                })
+                .collect(),
+        }
+    }
 }
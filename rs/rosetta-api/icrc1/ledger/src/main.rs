@@ -1,28 +1,38 @@
 use candid::candid_method;
 use candid::types::number::Nat;
-use ic_canister_log::{declare_log_buffer, export};
+use ic_canister_log::{declare_log_buffer, export, log};
 use ic_canisters_http_types::{HttpRequest, HttpResponse, HttpResponseBuilder};
-use ic_cdk::api::stable::{StableReader, StableWriter};
 use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query, update};
 use ic_icrc1::{
     endpoints::{convert_transfer_error, StandardRecord},
     Operation, Transaction,
 };
-use ic_icrc1_ledger::{Ledger, LedgerArgument};
+use ic_icrc1_ledger::{storage, Ledger, LedgerArgument};
+use ic_ledger_canister_core::blockchain::BlockData;
 use ic_ledger_canister_core::ledger::{
     apply_transaction, archive_blocks, LedgerAccess, LedgerContext, LedgerData,
     TransferError as CoreTransferError,
 };
+use ic_ledger_core::balances::InspectableBalancesStore;
 use ic_ledger_core::tokens::Zero;
 use ic_ledger_core::{approvals::Approvals, timestamp::TimeStamp};
 use icrc_ledger_types::icrc1::transfer::Memo;
 use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
+use icrc_ledger_types::icrc21::{
+    errors::{ErrorInfo, Icrc21Error},
+    lib::build_icrc21_consent_info_for_icrc1_and_icrc2_endpoints,
+    requests::ConsentMessageRequest,
+    responses::ConsentInfo,
+};
 use icrc_ledger_types::icrc3::blocks::DataCertificate;
 use icrc_ledger_types::{
     icrc::generic_metadata_value::MetadataValue as Value,
     icrc3::{
-        archive::ArchiveInfo,
-        blocks::{GetBlocksRequest, GetBlocksResponse},
+        archive::{ArchiveInfo, GetArchivesArgs, GetArchivesResult, ICRC3ArchiveInfo},
+        blocks::{
+            GetBlocksArgs, GetBlocksRequest, GetBlocksResponse, GetBlocksResult,
+            ICRC3DataCertificate, SupportedBlockType,
+        },
         transactions::{GetTransactionsRequest, GetTransactionsResponse},
     },
 };
@@ -37,9 +47,14 @@ use icrc_ledger_types::{
 use num_traits::{bounds::Bounded, ToPrimitive};
 use serde_bytes::ByteBuf;
 use std::cell::RefCell;
+use std::time::Duration;
 
 const MAX_MESSAGE_SIZE: u64 = 1024 * 1024;
 
+/// The number of instructions a migration step may use, well below the
+/// instruction limit of a single message.
+const MAX_INSTRUCTIONS_PER_MIGRATION_STEP: u64 = 5_000_000_000;
+
 #[cfg(not(feature = "u256-tokens"))]
 type Tokens = ic_icrc1_tokens_u64::U64;
 
@@ -83,7 +98,8 @@ fn init(args: LedgerArgument) {
             let now = TimeStamp::from_nanos_since_unix_epoch(ic_cdk::api::time());
             LEDGER.with(|cell| {
                 *cell.borrow_mut() = Some(Ledger::<Tokens>::from_init_args(init_args, now))
-            })
+            });
+            ic_cdk::api::set_certified_data(&Access::with_ledger(Ledger::root_hash));
         }
         LedgerArgument::Upgrade(_) => {
             panic!("Cannot initialize the canister with an Upgrade argument. Please provide an Init argument.");
@@ -91,19 +107,29 @@ fn init(args: LedgerArgument) {
     }
 }
 
+// Balances, allowances and blocks live in stable structures, so only the
+// remaining ledger state is serialized on upgrade.
 #[pre_upgrade]
 fn pre_upgrade() {
-    Access::with_ledger(|ledger| ciborium::ser::into_writer(ledger, StableWriter::default()))
+    let mut bytes = vec![];
+    Access::with_ledger(|ledger| ciborium::ser::into_writer(ledger, &mut bytes))
         .expect("failed to encode ledger state");
+    storage::write_upgrade_state(&bytes);
 }
 
 #[post_upgrade]
 fn post_upgrade(args: Option<LedgerArgument>) {
+    let bytes = if storage::is_memory_manager_initialized() {
+        storage::read_upgrade_state()
+    } else {
+        // The previous version serialized the whole ledger at the beginning of
+        // stable memory. Decoding that state moves the blocks into stable
+        // structures and queues balances and allowances for the migration.
+        storage::read_legacy_stable_memory()
+    };
     LEDGER.with(|cell| {
-        *cell.borrow_mut() = Some(
-            ciborium::de::from_reader(StableReader::default())
-                .expect("failed to decode ledger state"),
-        );
+        *cell.borrow_mut() =
+            Some(ciborium::de::from_reader(&bytes[..]).expect("failed to decode ledger state"));
     });
 
     if let Some(args) = args {
@@ -116,6 +142,33 @@ fn post_upgrade(args: Option<LedgerArgument>) {
             }
         }
     }
+
+    // The layout of the certified hash tree might have changed with the new version.
+    ic_cdk::api::set_certified_data(&Access::with_ledger(Ledger::root_hash));
+
+    if storage::is_migration_in_progress() {
+        log!(
+            LOG,
+            "[post_upgrade]: migrating the ledger state to stable structures"
+        );
+        ic_cdk_timers::set_timer(Duration::ZERO, migrate_next_batch);
+    }
+}
+
+/// Moves the next batch of balances and allowances to stable structures and
+/// schedules itself again until the migration is complete.
+fn migrate_next_batch() {
+    let done = storage::migrate_next_batch(|| {
+        ic_cdk::api::instruction_counter() > MAX_INSTRUCTIONS_PER_MIGRATION_STEP
+    });
+    if done {
+        log!(
+            LOG,
+            "[migrate_next_batch]: migration to stable structures complete"
+        );
+    } else {
+        ic_cdk_timers::set_timer(Duration::ZERO, migrate_next_batch);
+    }
 }
 
 fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
@@ -153,7 +206,7 @@ fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> std::i
         w.encode_gauge(
             "ledger_transactions",
             ledger.blockchain().blocks.len() as f64,
-            "Total number of transactions stored in the main memory.",
+            "Total number of transactions stored in the ledger and not archived yet.",
         )?;
         w.encode_gauge(
             "ledger_archived_transactions",
@@ -479,6 +532,14 @@ fn supported_standards() -> Vec<StandardRecord> {
             url: "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-2".to_string(),
         });
     }
+    standards.push(StandardRecord {
+        name: "ICRC-3".to_string(),
+        url: "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-3".to_string(),
+    });
+    standards.push(StandardRecord {
+        name: "ICRC-21".to_string(),
+        url: "https://github.com/dfinity/wg-identity-authentication/blob/main/topics/ICRC-21/icrc_21_consent_msg.md".to_string(),
+    });
     standards
 }
 
@@ -512,6 +573,75 @@ fn get_data_certificate() -> DataCertificate {
     }
 }
 
+#[query]
+#[candid_method(query)]
+fn icrc3_get_archives(args: GetArchivesArgs) -> GetArchivesResult {
+    let archives = archives().into_iter().map(|archive| ICRC3ArchiveInfo {
+        canister_id: archive.canister_id,
+        start: archive.block_range_start,
+        end: archive.block_range_end,
+    });
+    match args.from {
+        Some(from) => archives
+            .skip_while(|archive| archive.canister_id != from)
+            .skip(1)
+            .collect(),
+        None => archives.collect(),
+    }
+}
+
+#[query]
+#[candid_method(query)]
+fn icrc3_get_blocks(args: GetBlocksArgs) -> GetBlocksResult {
+    Access::with_ledger(|ledger| ledger.icrc3_get_blocks(args))
+}
+
+#[query]
+#[candid_method(query)]
+fn icrc3_get_tip_certificate() -> Option<ICRC3DataCertificate> {
+    let certificate = ByteBuf::from(ic_cdk::api::data_certificate()?);
+    let hash_tree = Access::with_ledger(|ledger| ledger.construct_hash_tree());
+    let mut tree_buf = vec![];
+    ciborium::ser::into_writer(&hash_tree, &mut tree_buf).unwrap();
+    Some(ICRC3DataCertificate {
+        certificate,
+        hash_tree: ByteBuf::from(tree_buf),
+    })
+}
+
+#[query]
+#[candid_method(query)]
+fn icrc3_supported_block_types() -> Vec<SupportedBlockType> {
+    let icrc1_url = "https://github.com/dfinity/ICRC-1/blob/main/standards/ICRC-1/README.md";
+    let icrc2_url = "https://github.com/dfinity/ICRC-1/blob/main/standards/ICRC-2/README.md";
+    let mut block_types = vec![
+        SupportedBlockType {
+            block_type: "1burn".to_string(),
+            url: icrc1_url.to_string(),
+        },
+        SupportedBlockType {
+            block_type: "1mint".to_string(),
+            url: icrc1_url.to_string(),
+        },
+        SupportedBlockType {
+            block_type: "1xfer".to_string(),
+            url: icrc1_url.to_string(),
+        },
+    ];
+    let icrc2 = Access::with_ledger(|ledger| ledger.feature_flags().icrc2);
+    if icrc2 {
+        block_types.push(SupportedBlockType {
+            block_type: "2approve".to_string(),
+            url: icrc2_url.to_string(),
+        });
+        block_types.push(SupportedBlockType {
+            block_type: "2xfer".to_string(),
+            url: icrc2_url.to_string(),
+        });
+    }
+    block_types
+}
+
 #[update]
 #[candid_method(update)]
 async fn icrc2_approve(arg: ApproveArgs) -> Result<Nat, ApproveError> {
@@ -611,6 +741,34 @@ fn icrc2_allowance(arg: AllowanceArgs) -> Allowance {
     })
 }
 
+#[update]
+#[candid_method(update)]
+fn icrc21_canister_call_consent_message(
+    consent_msg_request: ConsentMessageRequest,
+) -> Result<ConsentInfo, Icrc21Error> {
+    let caller_principal = ic_cdk::api::caller();
+    let (ledger_fee, token_symbol, decimals, icrc2) = Access::with_ledger(|ledger| {
+        (
+            Nat::from(ledger.transfer_fee()),
+            ledger.token_symbol().to_string(),
+            ledger.decimals(),
+            ledger.feature_flags().icrc2,
+        )
+    });
+    if !icrc2 && consent_msg_request.method.starts_with("icrc2_") {
+        return Err(Icrc21Error::UnsupportedCanisterCall(ErrorInfo {
+            description: "ICRC-2 features are not enabled on the ledger.".to_string(),
+        }));
+    }
+    build_icrc21_consent_info_for_icrc1_and_icrc2_endpoints(
+        consent_msg_request,
+        caller_principal,
+        ledger_fee,
+        token_symbol,
+        decimals,
+    )
+}
+
 candid::export_service!();
 
 #[query]
@@ -0,0 +1,755 @@
+//! The part of the ledger state that lives in stable memory.
+//!
+//! Balances, allowances and the blocks that have not been archived yet are
+//! kept in stable structures, so upgrades do not need to serialize them. The
+//! rest of the ledger state is small and is written to the upgrades memory in
+//! `pre_upgrade`.
+//!
+//! The handles defined in this module serialize as empty collections. When the
+//! ledger state is decoded from a version that kept these collections on the
+//! heap, the blocks are moved into the stable structures right away, since the
+//! number of blocks that are not archived is bounded. Balances and allowances
+//! are unbounded, so they are kept on the heap as pending entries and moved in
+//! batches by [migrate_next_batch], which the ledger calls from a timer until
+//! the migration is complete. In the meantime, the handles look up the pending
+//! entries as well, and the pending entries are serialized on upgrade.
+
+use crate::ApprovalKey;
+use candid::Nat;
+use ic_ledger_canister_core::blockchain::BlockData;
+use ic_ledger_core::approvals::{Allowance, AllowancesData, HeapAllowancesData};
+use ic_ledger_core::balances::{BalancesStore, InspectableBalancesStore};
+use ic_ledger_core::block::EncodedBlock;
+use ic_ledger_core::timestamp::TimeStamp;
+use ic_ledger_core::tokens::TokensType;
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::storable::Blob;
+use ic_stable_structures::{
+    BoundedStorable, DefaultMemoryImpl, Memory, StableBTreeMap, StableCell, Storable,
+};
+use icrc_ledger_types::icrc1::account::Account;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::marker::PhantomData;
+use std::ops::{Bound, Range};
+
+const UPGRADES_MEMORY_ID: MemoryId = MemoryId::new(0);
+const BALANCES_MEMORY_ID: MemoryId = MemoryId::new(1);
+const ALLOWANCES_MEMORY_ID: MemoryId = MemoryId::new(2);
+const ALLOWANCES_EXPIRATIONS_MEMORY_ID: MemoryId = MemoryId::new(3);
+const ALLOWANCES_ARRIVALS_MEMORY_ID: MemoryId = MemoryId::new(4);
+const BLOCKS_MEMORY_ID: MemoryId = MemoryId::new(5);
+const BLOCKS_RANGE_MEMORY_ID: MemoryId = MemoryId::new(6);
+
+const WASM_PAGE_SIZE: u64 = 65_536;
+
+/// The magic bytes the memory manager writes at the beginning of stable memory.
+const MEMORY_MANAGER_MAGIC: &[u8; 3] = b"MGR";
+
+/// The maximum length of an unsigned LEB128 encoding of a 256-bit number.
+const MAX_TOKENS_LEN: usize = 37;
+
+/// Blocks are split into chunks of this size to fit into a bounded map.
+const BLOCK_CHUNK_SIZE: usize = 1024;
+
+/// The number of balances [StableBalances::iter] loads at once.
+const BALANCES_ITER_BATCH_SIZE: usize = 1_000;
+
+type VM = VirtualMemory<DefaultMemoryImpl>;
+
+/// The principal of the owner padded to 29 bytes and the effective subaccount.
+type AccountKey = (Blob<29>, [u8; 32]);
+type AllowanceKey = (AccountKey, AccountKey);
+type StoredTokens = Blob<MAX_TOKENS_LEN>;
+
+type BalancesMap = StableBTreeMap<AccountKey, StoredTokens, VM>;
+type AllowancesMap = StableBTreeMap<AllowanceKey, StoredAllowance, VM>;
+type AllowancesQueue = StableBTreeMap<(u64, AllowanceKey), (), VM>;
+// The key is the sequence number of the block and the index of the chunk.
+type BlocksMap = StableBTreeMap<(u64, u32), Blob<BLOCK_CHUNK_SIZE>, VM>;
+// The range of sequence numbers of the stored blocks.
+type BlocksRangeCell = StableCell<(u64, u64), VM>;
+
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+
+    static BALANCES: RefCell<BalancesMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(BalancesMap::init(memory_manager.get(BALANCES_MEMORY_ID)))
+    });
+
+    static ALLOWANCES: RefCell<AllowancesMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(AllowancesMap::init(memory_manager.get(ALLOWANCES_MEMORY_ID)))
+    });
+
+    static ALLOWANCES_EXPIRATIONS: RefCell<AllowancesQueue> = with_memory_manager(|memory_manager| {
+        RefCell::new(AllowancesQueue::init(memory_manager.get(ALLOWANCES_EXPIRATIONS_MEMORY_ID)))
+    });
+
+    static ALLOWANCES_ARRIVALS: RefCell<AllowancesQueue> = with_memory_manager(|memory_manager| {
+        RefCell::new(AllowancesQueue::init(memory_manager.get(ALLOWANCES_ARRIVALS_MEMORY_ID)))
+    });
+
+    static BLOCKS: RefCell<BlocksMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(BlocksMap::init(memory_manager.get(BLOCKS_MEMORY_ID)))
+    });
+
+    static BLOCKS_RANGE: RefCell<BlocksRangeCell> = with_memory_manager(|memory_manager| {
+        RefCell::new(BlocksRangeCell::init(memory_manager.get(BLOCKS_RANGE_MEMORY_ID), (0, 0))
+            .expect("failed to initialize the blocks range cell"))
+    });
+}
+
+// Balances and allowances decoded from a legacy ledger state that have not been
+// moved to the stable structures yet. An entry is either pending or stored in
+// stable memory, never both.
+thread_local! {
+    static PENDING_BALANCES: RefCell<BTreeMap<AccountKey, StoredTokens>> = RefCell::default();
+
+    static PENDING_ALLOWANCES: RefCell<BTreeMap<AllowanceKey, StoredAllowance>> = RefCell::default();
+
+    static PENDING_ALLOWANCES_EXPIRATIONS: RefCell<BTreeSet<(u64, AllowanceKey)>> = RefCell::default();
+
+    static PENDING_ALLOWANCES_ARRIVALS: RefCell<BTreeSet<(u64, AllowanceKey)>> = RefCell::default();
+}
+
+fn with_memory_manager<R>(f: impl FnOnce(&MemoryManager<DefaultMemoryImpl>) -> R) -> R {
+    MEMORY_MANAGER.with(|cell| f(&cell.borrow()))
+}
+
+/// Returns true if stable memory is laid out by the memory manager, false if
+/// it is empty or was written by a ledger version that serialized its whole
+/// state at the beginning of stable memory.
+pub fn is_memory_manager_initialized() -> bool {
+    let memory = DefaultMemoryImpl::default();
+    if memory.size() == 0 {
+        return false;
+    }
+    let mut magic = [0u8; 3];
+    memory.read(0, &mut magic);
+    &magic == MEMORY_MANAGER_MAGIC
+}
+
+/// Returns the content of the whole stable memory.
+///
+/// The legacy ledger state must be read with this function before any
+/// stable structure is accessed, because initializing the memory manager
+/// overwrites the beginning of stable memory.
+pub fn read_legacy_stable_memory() -> Vec<u8> {
+    let memory = DefaultMemoryImpl::default();
+    let mut bytes = vec![0; (memory.size() * WASM_PAGE_SIZE) as usize];
+    memory.read(0, &mut bytes);
+    bytes
+}
+
+/// Writes the encoded ledger state to the upgrades memory, prefixed by its length.
+pub fn write_upgrade_state(bytes: &[u8]) {
+    let memory = with_memory_manager(|memory_manager| memory_manager.get(UPGRADES_MEMORY_ID));
+    let required_size = 8 + bytes.len() as u64;
+    let required_pages = (required_size + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+    if memory.size() < required_pages && memory.grow(required_pages - memory.size()) < 0 {
+        panic!(
+            "failed to grow the upgrades memory to {} pages",
+            required_pages
+        );
+    }
+    memory.write(0, &(bytes.len() as u64).to_le_bytes());
+    memory.write(8, bytes);
+}
+
+/// Reads the encoded ledger state written by [write_upgrade_state].
+pub fn read_upgrade_state() -> Vec<u8> {
+    let memory = with_memory_manager(|memory_manager| memory_manager.get(UPGRADES_MEMORY_ID));
+    let mut len_bytes = [0u8; 8];
+    memory.read(0, &mut len_bytes);
+    let mut bytes = vec![0; u64::from_le_bytes(len_bytes) as usize];
+    memory.read(8, &mut bytes);
+    bytes
+}
+
+/// Returns true if some balances or allowances decoded from a legacy ledger
+/// state have not been moved to the stable structures yet.
+pub fn is_migration_in_progress() -> bool {
+    PENDING_BALANCES.with(|pending| !pending.borrow().is_empty())
+        || PENDING_ALLOWANCES.with(|pending| !pending.borrow().is_empty())
+        || PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| !pending.borrow().is_empty())
+        || PENDING_ALLOWANCES_ARRIVALS.with(|pending| !pending.borrow().is_empty())
+}
+
+/// Moves pending balances and allowances to the stable structures, one entry
+/// at a time, until `is_out_of_budget` returns true or there is nothing left
+/// to move. Returns true if the migration is complete.
+pub fn migrate_next_batch(is_out_of_budget: impl Fn() -> bool) -> bool {
+    while migrate_next_entry() {
+        if is_out_of_budget() {
+            return !is_migration_in_progress();
+        }
+    }
+    true
+}
+
+/// Moves one pending entry to the stable structures.
+/// Returns false if there was no pending entry.
+fn migrate_next_entry() -> bool {
+    if let Some((key, balance)) = PENDING_BALANCES.with(|pending| pending.borrow_mut().pop_first())
+    {
+        BALANCES.with(|balances| balances.borrow_mut().insert(key, balance));
+        return true;
+    }
+    if let Some((key, allowance)) =
+        PENDING_ALLOWANCES.with(|pending| pending.borrow_mut().pop_first())
+    {
+        ALLOWANCES.with(|allowances| allowances.borrow_mut().insert(key, allowance));
+        return true;
+    }
+    if let Some(entry) =
+        PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| pending.borrow_mut().pop_first())
+    {
+        ALLOWANCES_EXPIRATIONS.with(|expirations| expirations.borrow_mut().insert(entry, ()));
+        return true;
+    }
+    if let Some(entry) =
+        PENDING_ALLOWANCES_ARRIVALS.with(|pending| pending.borrow_mut().pop_first())
+    {
+        ALLOWANCES_ARRIVALS.with(|arrivals| arrivals.borrow_mut().insert(entry, ()));
+        return true;
+    }
+    false
+}
+
+fn account_key(account: &Account) -> AccountKey {
+    (
+        Blob::try_from(account.owner.as_slice()).expect("principals have at most 29 bytes"),
+        *account.effective_subaccount(),
+    )
+}
+
+fn account_from_key((owner, subaccount): AccountKey) -> Account {
+    Account {
+        owner: candid::Principal::from_slice(owner.as_slice()),
+        subaccount: if subaccount == [0; 32] {
+            None
+        } else {
+            Some(subaccount)
+        },
+    }
+}
+
+fn allowance_key(key: &ApprovalKey) -> AllowanceKey {
+    let (account, spender) = key.clone().into();
+    (account_key(&account), account_key(&spender))
+}
+
+fn approval_key_from((account, spender): AllowanceKey) -> ApprovalKey {
+    ApprovalKey::from((&account_from_key(account), &account_from_key(spender)))
+}
+
+fn encode_tokens<Tokens: TokensType>(tokens: Tokens) -> StoredTokens {
+    let mut buf = vec![];
+    Nat::encode(&tokens.into(), &mut buf).expect("bug: failed to encode tokens");
+    Blob::try_from(&buf[..]).expect("bug: tokens do not fit into 256 bits")
+}
+
+fn decode_tokens<Tokens: TokensType>(stored: StoredTokens) -> Tokens {
+    let nat = Nat::decode(&mut stored.as_slice()).unwrap_or_else(|e| {
+        panic!(
+            "bug: invalid tokens encoding {:?}: {}",
+            stored.as_slice(),
+            e
+        )
+    });
+    Tokens::try_from(nat).unwrap_or_else(|e| panic!("bug: invalid stored tokens: {}", e))
+}
+
+/// Account balances stored in stable memory.
+#[derive(Debug)]
+pub struct StableBalances<Tokens> {
+    _marker: PhantomData<Tokens>,
+}
+
+impl<Tokens> Default for StableBalances<Tokens> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Tokens: TokensType> Serialize for StableBalances<Tokens> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PENDING_BALANCES
+            .with(|pending| {
+                pending
+                    .borrow()
+                    .iter()
+                    .map(|(key, balance)| {
+                        (account_from_key(*key), decode_tokens::<Tokens>(*balance))
+                    })
+                    .collect::<BTreeMap<Account, Tokens>>()
+            })
+            .serialize(serializer)
+    }
+}
+
+impl<'de, Tokens: TokensType> Deserialize<'de> for StableBalances<Tokens> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let balances = BTreeMap::<Account, Tokens>::deserialize(deserializer)?;
+        PENDING_BALANCES.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            for (account, balance) in balances {
+                if !balance.is_zero() {
+                    pending.insert(account_key(&account), encode_tokens(balance));
+                }
+            }
+        });
+        Ok(Self::default())
+    }
+}
+
+impl<Tokens: TokensType> BalancesStore for StableBalances<Tokens> {
+    type AccountId = Account;
+    type Tokens = Tokens;
+
+    fn get_balance(&self, k: &Account) -> Option<Tokens> {
+        let key = account_key(k);
+        BALANCES
+            .with(|balances| balances.borrow().get(&key))
+            .or_else(|| PENDING_BALANCES.with(|pending| pending.borrow().get(&key).copied()))
+            .map(decode_tokens)
+    }
+
+    fn update<F, E>(&mut self, k: Account, mut f: F) -> Result<Tokens, E>
+    where
+        F: FnMut(Option<&Tokens>) -> Result<Tokens, E>,
+    {
+        let prev = self.get_balance(&k);
+        let new_v = f(prev.as_ref())?;
+        let key = account_key(&k);
+        BALANCES.with(|balances| {
+            if new_v.is_zero() {
+                balances.borrow_mut().remove(&key);
+            } else {
+                balances.borrow_mut().insert(key, encode_tokens(new_v));
+            }
+        });
+        PENDING_BALANCES.with(|pending| pending.borrow_mut().remove(&key));
+        Ok(new_v)
+    }
+}
+
+impl<Tokens: TokensType> InspectableBalancesStore for StableBalances<Tokens> {
+    fn iter(&self) -> Box<dyn Iterator<Item = (Account, Tokens)> + '_> {
+        let pending: Vec<_> = PENDING_BALANCES.with(|pending| {
+            pending
+                .borrow()
+                .iter()
+                .map(|(key, balance)| (account_from_key(*key), decode_tokens(*balance)))
+                .collect()
+        });
+        Box::new(
+            StableBalancesIter {
+                start: Bound::Unbounded,
+                batch: VecDeque::new(),
+                _marker: PhantomData,
+            }
+            .chain(pending),
+        )
+    }
+
+    fn len(&self) -> usize {
+        BALANCES.with(|balances| balances.borrow().len() as usize)
+            + PENDING_BALANCES.with(|pending| pending.borrow().len())
+    }
+}
+
+/// Iterates over the balances in batches, so that the map is not borrowed
+/// for the lifetime of the iterator.
+struct StableBalancesIter<Tokens> {
+    start: Bound<AccountKey>,
+    batch: VecDeque<(AccountKey, StoredTokens)>,
+    _marker: PhantomData<Tokens>,
+}
+
+impl<Tokens: TokensType> Iterator for StableBalancesIter<Tokens> {
+    type Item = (Account, Tokens);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.batch.is_empty() {
+            self.batch = BALANCES.with(|balances| {
+                balances
+                    .borrow()
+                    .range((self.start, Bound::Unbounded))
+                    .take(BALANCES_ITER_BATCH_SIZE)
+                    .collect()
+            });
+            if let Some((key, _)) = self.batch.back() {
+                self.start = Bound::Excluded(*key);
+            }
+        }
+        self.batch
+            .pop_front()
+            .map(|(key, balance)| (account_from_key(key), decode_tokens(balance)))
+    }
+}
+
+/// An allowance in the format stored in stable memory.
+#[derive(Clone)]
+struct StoredAllowance {
+    amount: StoredTokens,
+    expires_at: Option<u64>,
+    arrived_at: u64,
+}
+
+impl Storable for StoredAllowance {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut buf = Vec::with_capacity(Self::MAX_SIZE as usize);
+        buf.extend_from_slice(&self.arrived_at.to_be_bytes());
+        match self.expires_at {
+            Some(expires_at) => {
+                buf.push(1);
+                buf.extend_from_slice(&expires_at.to_be_bytes());
+            }
+            None => {
+                buf.push(0);
+                buf.extend_from_slice(&[0; 8]);
+            }
+        }
+        buf.extend_from_slice(self.amount.as_slice());
+        Cow::Owned(buf)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        assert!(
+            bytes.len() > 17,
+            "bug: invalid allowance encoding {:?}",
+            bytes
+        );
+        let u64_at =
+            |offset: usize| u64::from_be_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        Self {
+            arrived_at: u64_at(0),
+            expires_at: match bytes[8] {
+                0 => None,
+                _ => Some(u64_at(9)),
+            },
+            amount: Blob::try_from(&bytes[17..]).expect("bug: invalid allowance amount"),
+        }
+    }
+}
+
+impl BoundedStorable for StoredAllowance {
+    const MAX_SIZE: u32 = 17 + MAX_TOKENS_LEN as u32;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+impl StoredAllowance {
+    fn encode<Tokens: TokensType>(allowance: Allowance<Tokens>) -> Self {
+        Self {
+            amount: encode_tokens(allowance.amount),
+            expires_at: allowance
+                .expires_at
+                .map(|ts| ts.as_nanos_since_unix_epoch()),
+            arrived_at: allowance.arrived_at.as_nanos_since_unix_epoch(),
+        }
+    }
+
+    fn decode<Tokens: TokensType>(self) -> Allowance<Tokens> {
+        Allowance {
+            amount: decode_tokens(self.amount),
+            expires_at: self.expires_at.map(TimeStamp::from_nanos_since_unix_epoch),
+            arrived_at: TimeStamp::from_nanos_since_unix_epoch(self.arrived_at),
+        }
+    }
+}
+
+/// Allowances stored in stable memory.
+#[derive(Debug)]
+pub struct StableAllowancesData<Tokens> {
+    _marker: PhantomData<Tokens>,
+}
+
+impl<Tokens> Default for StableAllowancesData<Tokens> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Tokens: TokensType> Serialize for StableAllowancesData<Tokens> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut data = HeapAllowancesData::<ApprovalKey, Account, Tokens>::default();
+        PENDING_ALLOWANCES.with(|pending| {
+            for (key, allowance) in pending.borrow().iter() {
+                data.set_allowance(approval_key_from(*key), allowance.clone().decode());
+            }
+        });
+        PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| {
+            for (timestamp, key) in pending.borrow().iter() {
+                data.insert_expiry(
+                    TimeStamp::from_nanos_since_unix_epoch(*timestamp),
+                    approval_key_from(*key),
+                );
+            }
+        });
+        PENDING_ALLOWANCES_ARRIVALS.with(|pending| {
+            for (timestamp, key) in pending.borrow().iter() {
+                data.insert_arrival(
+                    TimeStamp::from_nanos_since_unix_epoch(*timestamp),
+                    approval_key_from(*key),
+                );
+            }
+        });
+        data.serialize(serializer)
+    }
+}
+
+impl<'de, Tokens: TokensType> Deserialize<'de> for StableAllowancesData<Tokens> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (allowances, expiration_queue, arrival_queue) =
+            HeapAllowancesData::<ApprovalKey, Account, Tokens>::deserialize(deserializer)?
+                .into_parts();
+        PENDING_ALLOWANCES.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            for (key, allowance) in allowances {
+                pending.insert(allowance_key(&key), StoredAllowance::encode(allowance));
+            }
+        });
+        PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            for (timestamp, key) in expiration_queue {
+                pending.insert((timestamp.as_nanos_since_unix_epoch(), allowance_key(&key)));
+            }
+        });
+        PENDING_ALLOWANCES_ARRIVALS.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            for (timestamp, key) in arrival_queue {
+                pending.insert((timestamp.as_nanos_since_unix_epoch(), allowance_key(&key)));
+            }
+        });
+        Ok(Self::default())
+    }
+}
+
+impl<Tokens: TokensType> AllowancesData for StableAllowancesData<Tokens> {
+    type AccountId = Account;
+    type Tokens = Tokens;
+    type Key = ApprovalKey;
+
+    fn get_allowance(&self, key: &ApprovalKey) -> Option<Allowance<Tokens>> {
+        let key = allowance_key(key);
+        ALLOWANCES
+            .with(|allowances| allowances.borrow().get(&key))
+            .or_else(|| PENDING_ALLOWANCES.with(|pending| pending.borrow().get(&key).cloned()))
+            .map(StoredAllowance::decode)
+    }
+
+    fn set_allowance(&mut self, key: ApprovalKey, allowance: Allowance<Tokens>) {
+        let key = allowance_key(&key);
+        ALLOWANCES.with(|allowances| {
+            allowances
+                .borrow_mut()
+                .insert(key, StoredAllowance::encode(allowance))
+        });
+        PENDING_ALLOWANCES.with(|pending| pending.borrow_mut().remove(&key));
+    }
+
+    fn remove_allowance(&mut self, key: &ApprovalKey) {
+        let key = allowance_key(key);
+        ALLOWANCES.with(|allowances| allowances.borrow_mut().remove(&key));
+        PENDING_ALLOWANCES.with(|pending| pending.borrow_mut().remove(&key));
+    }
+
+    fn insert_expiry(&mut self, timestamp: TimeStamp, key: ApprovalKey) {
+        let entry = (timestamp.as_nanos_since_unix_epoch(), allowance_key(&key));
+        ALLOWANCES_EXPIRATIONS.with(|expirations| expirations.borrow_mut().insert(entry, ()));
+    }
+
+    fn remove_expiry(&mut self, timestamp: TimeStamp, key: ApprovalKey) {
+        let entry = (timestamp.as_nanos_since_unix_epoch(), allowance_key(&key));
+        ALLOWANCES_EXPIRATIONS.with(|expirations| expirations.borrow_mut().remove(&entry));
+        PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| pending.borrow_mut().remove(&entry));
+    }
+
+    fn insert_arrival(&mut self, timestamp: TimeStamp, key: ApprovalKey) {
+        let entry = (timestamp.as_nanos_since_unix_epoch(), allowance_key(&key));
+        ALLOWANCES_ARRIVALS.with(|arrivals| arrivals.borrow_mut().insert(entry, ()));
+    }
+
+    fn remove_arrival(&mut self, timestamp: TimeStamp, key: ApprovalKey) {
+        let entry = (timestamp.as_nanos_since_unix_epoch(), allowance_key(&key));
+        ALLOWANCES_ARRIVALS.with(|arrivals| arrivals.borrow_mut().remove(&entry));
+        PENDING_ALLOWANCES_ARRIVALS.with(|pending| pending.borrow_mut().remove(&entry));
+    }
+
+    fn first_expiry(&self) -> Option<(TimeStamp, ApprovalKey)> {
+        let stored = ALLOWANCES_EXPIRATIONS
+            .with(|expirations| expirations.borrow().iter().next())
+            .map(|(entry, ())| entry);
+        let pending =
+            PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| pending.borrow().first().copied());
+        stored
+            .into_iter()
+            .chain(pending)
+            .min()
+            .map(|(timestamp, key)| {
+                (
+                    TimeStamp::from_nanos_since_unix_epoch(timestamp),
+                    approval_key_from(key),
+                )
+            })
+    }
+
+    fn pop_first_expiry(&mut self) -> Option<(TimeStamp, ApprovalKey)> {
+        let (timestamp, key) = self.first_expiry()?;
+        self.remove_expiry(timestamp, key.clone());
+        Some((timestamp, key))
+    }
+
+    fn oldest_arrivals(&self, n: usize) -> Vec<ApprovalKey> {
+        let mut arrivals: Vec<(u64, AllowanceKey)> = ALLOWANCES_ARRIVALS.with(|arrivals| {
+            arrivals
+                .borrow()
+                .iter()
+                .take(n)
+                .map(|(entry, ())| entry)
+                .collect()
+        });
+        PENDING_ALLOWANCES_ARRIVALS
+            .with(|pending| arrivals.extend(pending.borrow().iter().take(n).copied()));
+        arrivals.sort();
+        arrivals
+            .into_iter()
+            .take(n)
+            .map(|(_arrived_at, key)| approval_key_from(key))
+            .collect()
+    }
+
+    fn len_allowances(&self) -> usize {
+        ALLOWANCES.with(|allowances| allowances.borrow().len() as usize)
+            + PENDING_ALLOWANCES.with(|pending| pending.borrow().len())
+    }
+
+    fn len_expirations(&self) -> usize {
+        ALLOWANCES_EXPIRATIONS.with(|expirations| expirations.borrow().len() as usize)
+            + PENDING_ALLOWANCES_EXPIRATIONS.with(|pending| pending.borrow().len())
+    }
+
+    fn len_arrivals(&self) -> usize {
+        ALLOWANCES_ARRIVALS.with(|arrivals| arrivals.borrow().len() as usize)
+            + PENDING_ALLOWANCES_ARRIVALS.with(|pending| pending.borrow().len())
+    }
+}
+
+/// Blocks that have not been archived yet, stored in stable memory.
+///
+/// Each block gets a sequence number when it is added; the offsets used by
+/// [BlockData] are relative to the sequence number of the oldest stored block.
+#[derive(Debug, Default)]
+pub struct StableBlockData;
+
+impl Serialize for StableBlockData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Vec::<EncodedBlock>::new().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StableBlockData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut data = Self;
+        for block in Vec::<EncodedBlock>::deserialize(deserializer)? {
+            data.add_block(block);
+        }
+        Ok(data)
+    }
+}
+
+impl StableBlockData {
+    fn range() -> (u64, u64) {
+        BLOCKS_RANGE.with(|cell| *cell.borrow().get())
+    }
+
+    fn set_range(range: (u64, u64)) {
+        BLOCKS_RANGE
+            .with(|cell| cell.borrow_mut().set(range))
+            .expect("failed to update the blocks range");
+    }
+
+    fn read_block(seq: u64) -> EncodedBlock {
+        let mut bytes = vec![];
+        BLOCKS.with(|blocks| {
+            for (_, chunk) in blocks.borrow().range((seq, 0)..=(seq, u32::MAX)) {
+                bytes.extend_from_slice(chunk.as_slice());
+            }
+        });
+        EncodedBlock::from_vec(bytes)
+    }
+}
+
+impl BlockData for StableBlockData {
+    fn add_block(&mut self, block: EncodedBlock) {
+        let (first, end) = Self::range();
+        BLOCKS.with(|blocks| {
+            let mut blocks = blocks.borrow_mut();
+            for (i, chunk) in block.as_slice().chunks(BLOCK_CHUNK_SIZE).enumerate() {
+                blocks.insert(
+                    (end, i as u32),
+                    Blob::try_from(chunk).expect("bug: block chunk is too large"),
+                );
+            }
+        });
+        Self::set_range((first, end + 1));
+    }
+
+    fn get_blocks(&self, range: Range<u64>) -> Vec<EncodedBlock> {
+        let (first, end) = Self::range();
+        assert!(
+            range.start <= range.end && range.end <= end - first,
+            "requested block range {:?} is out of the stored range 0..{}",
+            range,
+            end - first
+        );
+        range
+            .map(|offset| Self::read_block(first + offset))
+            .collect()
+    }
+
+    fn get_block(&self, offset: u64) -> Option<EncodedBlock> {
+        let (first, end) = Self::range();
+        let seq = first.checked_add(offset)?;
+        (seq < end).then(|| Self::read_block(seq))
+    }
+
+    fn remove_blocks(&mut self, num_blocks: u64) {
+        let (first, end) = Self::range();
+        assert!(
+            num_blocks <= end - first,
+            "Asked to remove more blocks than present. Present: {}, to remove: {}",
+            end - first,
+            num_blocks
+        );
+        BLOCKS.with(|blocks| {
+            let mut blocks = blocks.borrow_mut();
+            let keys: Vec<(u64, u32)> = blocks
+                .range((first, 0)..(first + num_blocks, 0))
+                .map(|(key, _)| key)
+                .collect();
+            for key in keys {
+                blocks.remove(&key);
+            }
+        });
+        Self::set_range((first + num_blocks, end));
+    }
+
+    fn len(&self) -> u64 {
+        let (first, end) = Self::range();
+        end - first
+    }
+
+    fn last_block(&self) -> Option<EncodedBlock> {
+        let (first, end) = Self::range();
+        (end > first).then(|| Self::read_block(end - 1))
+    }
+}
@@ -1,13 +1,19 @@
-use crate::{InitArgs, Ledger};
+use crate::storage::{self, StableAllowancesData, StableBalances, StableBlockData};
+use crate::{ApprovalKey, InitArgs, Ledger};
 use ic_base_types::PrincipalId;
 use ic_icrc1::{Operation, Transaction};
 use ic_ledger_canister_core::archive::ArchiveOptions;
+use ic_ledger_canister_core::blockchain::BlockData;
 use ic_ledger_canister_core::ledger::{LedgerContext, LedgerTransaction, TxApplyError};
-use ic_ledger_core::approvals::{Allowance, Approvals};
+use ic_ledger_core::approvals::{Allowance, AllowancesData, Approvals, HeapAllowancesData};
+use ic_ledger_core::balances::{BalancesStore, InspectableBalancesStore};
+use ic_ledger_core::block::EncodedBlock;
 use ic_ledger_core::timestamp::TimeStamp;
 use ic_ledger_core::Tokens;
 use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue as Value;
 use icrc_ledger_types::icrc1::account::Account;
+use std::cell::Cell;
+use std::collections::BTreeMap;
 
 use ic_icrc1_ledger_sm_tests::{
     ARCHIVE_TRIGGER_THRESHOLD, BLOB_META_KEY, BLOB_META_VALUE, DECIMAL_PLACES, FEE, INT_META_KEY,
@@ -606,3 +612,149 @@ fn test_approval_burn_from() {
     assert_eq!(ctx.balances().account_balance(&spender), Tokens::ZERO);
     assert_eq!(ctx.balances().total_supply().get_e8s(), 90_000);
 }
+
+#[test]
+fn test_stable_block_data() {
+    let block = |n: u8, len: usize| EncodedBlock::from_vec(vec![n; len]);
+    let mut data = StableBlockData;
+
+    data.add_block(block(0, 10));
+    data.add_block(block(1, 1024));
+    data.add_block(block(2, 3000));
+    assert_eq!(data.len(), 3);
+    assert_eq!(data.get_block(1), Some(block(1, 1024)));
+    assert_eq!(data.get_block(3), None);
+    assert_eq!(data.last_block(), Some(block(2, 3000)));
+    assert_eq!(
+        data.get_blocks(0..3),
+        vec![block(0, 10), block(1, 1024), block(2, 3000)]
+    );
+
+    data.remove_blocks(2);
+    assert_eq!(data.len(), 1);
+    assert_eq!(data.get_block(0), Some(block(2, 3000)));
+
+    data.add_block(block(3, 1));
+    assert_eq!(data.get_blocks(0..2), vec![block(2, 3000), block(3, 1)]);
+}
+
+#[test]
+fn test_stable_balances_iter() {
+    let mut store = StableBalances::<Tokens>::default();
+    for n in 1..=2_500 {
+        store
+            .update(test_account_id(n), |_| -> Result<Tokens, ()> {
+                Ok(tokens(n))
+            })
+            .unwrap();
+    }
+    // Zero balances are not stored.
+    store
+        .update(test_account_id(1), |_| -> Result<Tokens, ()> {
+            Ok(Tokens::ZERO)
+        })
+        .unwrap();
+
+    assert_eq!(store.len(), 2_499);
+    let mut balances: Vec<(Account, Tokens)> = store.iter().collect();
+    balances.sort();
+    let mut expected: Vec<(Account, Tokens)> = (2..=2_500)
+        .map(|n| (test_account_id(n), tokens(n)))
+        .collect();
+    expected.sort();
+    assert_eq!(balances, expected);
+}
+
+#[test]
+fn test_legacy_state_migrates_in_batches() {
+    let legacy_balances: BTreeMap<Account, Tokens> =
+        (1..=100).map(|n| (test_account_id(n), tokens(n))).collect();
+    let approval_key = |n: u64| ApprovalKey::from((&test_account_id(n), &test_account_id(n + 1)));
+    let allowance = |n: u64| Allowance {
+        amount: tokens(n),
+        expires_at: Some(ts(1_000 + n)),
+        arrived_at: ts(n),
+    };
+    let mut legacy_allowances = HeapAllowancesData::<ApprovalKey, Account, Tokens>::default();
+    for n in 1..=10 {
+        legacy_allowances.set_allowance(approval_key(n), allowance(n));
+        legacy_allowances.insert_expiry(ts(1_000 + n), approval_key(n));
+        legacy_allowances.insert_arrival(ts(n), approval_key(n));
+    }
+
+    let mut buf = vec![];
+    ciborium::ser::into_writer(&legacy_balances, &mut buf).unwrap();
+    let mut balances: StableBalances<Tokens> = ciborium::de::from_reader(&buf[..]).unwrap();
+    let mut buf = vec![];
+    ciborium::ser::into_writer(&legacy_allowances, &mut buf).unwrap();
+    let mut allowances: StableAllowancesData<Tokens> = ciborium::de::from_reader(&buf[..]).unwrap();
+    assert!(storage::is_migration_in_progress());
+
+    // The pending entries are visible while the migration is in progress.
+    assert_eq!(balances.len(), 100);
+    assert_eq!(balances.get_balance(&test_account_id(7)), Some(tokens(7)));
+    assert_eq!(
+        allowances.get_allowance(&approval_key(3)),
+        Some(allowance(3))
+    );
+    assert_eq!(
+        allowances.first_expiry(),
+        Some((ts(1_001), approval_key(1)))
+    );
+    assert_eq!(
+        allowances.oldest_arrivals(2),
+        vec![approval_key(1), approval_key(2)]
+    );
+
+    // Updates during the migration take precedence over the pending entries.
+    balances
+        .update(test_account_id(7), |_| -> Result<Tokens, ()> {
+            Ok(tokens(70))
+        })
+        .unwrap();
+    allowances.remove_allowance(&approval_key(10));
+    allowances.remove_expiry(ts(1_010), approval_key(10));
+    allowances.remove_arrival(ts(10), approval_key(10));
+
+    // The pending state survives an upgrade in the middle of the migration.
+    let mut buf = vec![];
+    ciborium::ser::into_writer(&balances, &mut buf).unwrap();
+    let restored: BTreeMap<Account, Tokens> = ciborium::de::from_reader(&buf[..]).unwrap();
+    assert_eq!(restored.len(), 99);
+    assert_eq!(restored.get(&test_account_id(7)), None);
+
+    let mut batches = 0;
+    loop {
+        batches += 1;
+        let steps = Cell::new(0);
+        let done = storage::migrate_next_batch(|| {
+            steps.set(steps.get() + 1);
+            steps.get() >= 10
+        });
+        if done {
+            break;
+        }
+    }
+    assert!(batches > 1);
+    assert!(!storage::is_migration_in_progress());
+
+    let mut migrated: Vec<(Account, Tokens)> = balances.iter().collect();
+    migrated.sort();
+    let mut expected: Vec<(Account, Tokens)> = (1..=100)
+        .map(|n| (test_account_id(n), tokens(if n == 7 { 70 } else { n })))
+        .collect();
+    expected.sort();
+    assert_eq!(migrated, expected);
+    assert_eq!(allowances.len_allowances(), 9);
+    assert_eq!(allowances.len_expirations(), 9);
+    assert_eq!(allowances.len_arrivals(), 9);
+    assert_eq!(
+        allowances.get_allowance(&approval_key(9)),
+        Some(allowance(9))
+    );
+    assert_eq!(allowances.get_allowance(&approval_key(10)), None);
+    assert_eq!(
+        allowances.pop_first_expiry(),
+        Some((ts(1_001), approval_key(1)))
+    );
+}
@@ -1,10 +1,11 @@
 use candid::{CandidType, Decode, Encode, Nat};
 use ic_base_types::{CanisterId, PrincipalId};
-use ic_icrc1_ledger::{ChangeFeeCollector, InitArgs, LedgerArgument};
+use ic_icrc1_ledger::{ChangeFeeCollector, FeatureFlags, InitArgs, LedgerArgument, UpgradeArgs};
 use ic_icrc1_ledger_sm_tests::{
-    ARCHIVE_TRIGGER_THRESHOLD, BLOB_META_KEY, BLOB_META_VALUE, DECIMAL_PLACES, FEE, INT_META_KEY,
-    INT_META_VALUE, MINTER, NAT_META_KEY, NAT_META_VALUE, NUM_BLOCKS_TO_ARCHIVE, TEXT_META_KEY,
-    TEXT_META_VALUE, TOKEN_NAME, TOKEN_SYMBOL,
+    default_approve_args, get_allowance, send_approval, ARCHIVE_TRIGGER_THRESHOLD, BLOB_META_KEY,
+    BLOB_META_VALUE, DECIMAL_PLACES, FEE, INT_META_KEY, INT_META_VALUE, MINTER, NAT_META_KEY,
+    NAT_META_VALUE, NUM_BLOCKS_TO_ARCHIVE, TEXT_META_KEY, TEXT_META_VALUE, TOKEN_NAME,
+    TOKEN_SYMBOL,
 };
 use ic_ledger_canister_core::archive::ArchiveOptions;
 use ic_ledger_core::block::BlockIndex;
@@ -12,6 +13,7 @@ use ic_state_machine_tests::StateMachine;
 use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue as Value;
 use icrc_ledger_types::icrc1::account::Account;
 use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+use icrc_ledger_types::icrc3::blocks::{GetBlocksRequest, GetBlocksResponse};
 use num_traits::ToPrimitive;
 use std::path::PathBuf;
 
@@ -137,6 +139,11 @@ fn test_get_blocks() {
     ic_icrc1_ledger_sm_tests::test_get_blocks(ledger_wasm(), encode_init_args);
 }
 
+#[test]
+fn test_icrc3_get_blocks() {
+    ic_icrc1_ledger_sm_tests::test_icrc3_get_blocks(ledger_wasm(), encode_init_args);
+}
+
 // Generate random blocks and check that their CBOR encoding complies with the CDDL spec.
 #[test]
 fn block_encoding_agrees_with_the_schema() {
@@ -232,6 +239,11 @@ fn test_feature_flags() {
     ic_icrc1_ledger_sm_tests::test_feature_flags(ledger_wasm(), encode_init_args);
 }
 
+#[test]
+fn test_icrc21_consent_message() {
+    ic_icrc1_ledger_sm_tests::test_icrc21_consent_message(ledger_wasm(), encode_init_args);
+}
+
 #[test]
 fn test_transfer_from_smoke() {
     ic_icrc1_ledger_sm_tests::test_transfer_from_smoke(ledger_wasm(), encode_init_args);
@@ -407,3 +419,111 @@ fn test_upgrade_from_first_version() {
     transfer(&env, ledger_id, MINTER, account(2), 3_000_000);
     transfer(&env, ledger_id, account(1), account(3), 1_000_000);
 }
+
+fn get_all_blocks(
+    env: &StateMachine,
+    ledger_id: CanisterId,
+) -> Vec<icrc_ledger_types::icrc::generic_value::Value> {
+    let args = Encode!(&GetBlocksRequest {
+        start: Nat::from(0),
+        length: Nat::from(1_000_u64),
+    })
+    .unwrap();
+    let res = env
+        .query(ledger_id, "get_blocks", args)
+        .expect("Unable to perform get_blocks")
+        .bytes();
+    Decode!(&res, GetBlocksResponse).unwrap().blocks
+}
+
+#[cfg_attr(feature = "u256-tokens", ignore)]
+#[test]
+fn test_upgrade_moves_state_to_stable_structures() {
+    let env = StateMachine::new();
+
+    let ledger_wasm_first_version =
+        std::fs::read(std::env::var("IC_ICRC1_LEDGER_FIRST_VERSION_WASM_PATH").unwrap()).unwrap();
+    let init_args = Encode!(&LegacyInitArgs {
+        minting_account: MINTER,
+        fee_collector_account: None,
+        initial_balances: vec![(account(1), 10_000_000)],
+        transfer_fee: FEE,
+        token_name: TOKEN_NAME.to_string(),
+        token_symbol: TOKEN_SYMBOL.to_string(),
+        metadata: vec![],
+        archive_options: ArchiveOptions {
+            trigger_threshold: ARCHIVE_TRIGGER_THRESHOLD as usize,
+            num_blocks_to_archive: NUM_BLOCKS_TO_ARCHIVE as usize,
+            node_max_memory_size_bytes: None,
+            max_message_size_bytes: None,
+            controller_id: PrincipalId::new_user_test_id(100),
+            cycles_for_archive_creation: None,
+            max_transactions_per_response: None,
+        },
+    })
+    .unwrap();
+    let ledger_id = env
+        .install_canister(ledger_wasm_first_version, init_args, None)
+        .unwrap();
+    for n in 2..10 {
+        transfer(&env, ledger_id, account(1), account(n), 100_000 * n);
+    }
+    let balances: Vec<u64> = (1..10)
+        .map(|n| balance_of(&env, ledger_id, account(n)))
+        .collect();
+    let blocks = get_all_blocks(&env, ledger_id);
+
+    // The first upgrade queues the heap state for the migration, the second
+    // one reads it back from stable structures.
+    for _ in 0..2 {
+        let upgrade_args = Encode!(&LedgerArgument::Upgrade(None)).unwrap();
+        env.upgrade_canister(ledger_id, ledger_wasm(), upgrade_args)
+            .expect("Unable to upgrade the ledger canister");
+
+        let balances_after_upgrade: Vec<u64> = (1..10)
+            .map(|n| balance_of(&env, ledger_id, account(n)))
+            .collect();
+        assert_eq!(balances, balances_after_upgrade);
+        assert_eq!(blocks, get_all_blocks(&env, ledger_id));
+        // Let the timer move the pending balances to stable structures.
+        env.tick();
+    }
+
+    transfer(&env, ledger_id, account(2), account(1), 100_000);
+    assert_eq!(
+        balances[0] + 100_000,
+        balance_of(&env, ledger_id, account(1))
+    );
+
+    // The legacy versions predate ICRC-2, so the allowances are created after
+    // the migration and must survive further upgrades.
+    let upgrade_args = Encode!(&LedgerArgument::Upgrade(Some(UpgradeArgs {
+        feature_flags: Some(FeatureFlags { icrc2: true }),
+        ..UpgradeArgs::default()
+    })))
+    .unwrap();
+    env.upgrade_canister(ledger_id, ledger_wasm(), upgrade_args)
+        .expect("Unable to upgrade the ledger canister");
+    for n in 2..5 {
+        send_approval(
+            &env,
+            ledger_id,
+            account(n).owner,
+            &default_approve_args(account(n + 1), 1_000 * n),
+        )
+        .expect("approval failed");
+    }
+    let allowances: Vec<Nat> = (2..5)
+        .map(|n| get_allowance(&env, ledger_id, account(n), account(n + 1)).allowance)
+        .collect();
+
+    let upgrade_args = Encode!(&LedgerArgument::Upgrade(None)).unwrap();
+    env.upgrade_canister(ledger_id, ledger_wasm(), upgrade_args)
+        .expect("Unable to upgrade the ledger canister");
+    env.tick();
+
+    let allowances_after_upgrade: Vec<Nat> = (2..5)
+        .map(|n| get_allowance(&env, ledger_id, account(n), account(n + 1)).allowance)
+        .collect();
+    assert_eq!(allowances, allowances_after_upgrade);
+}
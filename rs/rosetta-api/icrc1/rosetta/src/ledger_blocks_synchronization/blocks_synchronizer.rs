@@ -408,9 +408,42 @@ async fn fetch_blockchain_tip_data(agent: Arc<Icrc1Agent>) -> anyhow::Result<(u6
     let hash_tree: MixedHashTree = serde_cbor::from_slice(&data_certificate.hash_tree)
         .map_err(|err| anyhow::Error::msg(err.to_string()))?;
 
+    // Ledgers implementing ICRC-3 certify the version of the layout of the hash tree under
+    // `hash_tree_version`. From version 1 on, the last block hash is certified under
+    // `last_block_hash` and the last block index is LEB128-encoded. Older ledgers certify the
+    // hash under `tip_hash` and the index in big-endian.
+    let hash_tree_version = match hash_tree.lookup(&[b"hash_tree_version"]) {
+        Found(MixedHashTree::Leaf(l)) => {
+            leb128::read::unsigned(&mut l.as_slice()).map_err(|err| {
+                anyhow::Error::msg(format!(
+                    "Hash tree version is not a valid LEB128 encoding: {}",
+                    err
+                ))
+            })?
+        }
+        Found(_) => {
+            return Err(anyhow::Error::msg(
+                "Hash tree version was found, but MixedHashTree is no a Leaf",
+            ))
+        }
+        _ => 0,
+    };
+    let (hash_label, is_icrc3_tree): (&[u8], bool) = if hash_tree_version >= 1 {
+        (b"last_block_hash", true)
+    } else {
+        (b"tip_hash", false)
+    };
+
     // Extract the last block index from the hash tree.
     let last_block_index = match hash_tree.lookup(&[b"last_block_index"]) {
         Found(x) => match x {
+            MixedHashTree::Leaf(l) if is_icrc3_tree => leb128::read::unsigned(&mut l.as_slice())
+                .map_err(|err| {
+                    anyhow::Error::msg(format!(
+                        "Last block index is not a valid LEB128 encoding: {}",
+                        err
+                    ))
+                }),
             MixedHashTree::Leaf(l) => {
                 let mut bytes: [u8; 8] = [0u8; 8];
                 for (i, e) in l.iter().enumerate() {
@@ -428,7 +461,7 @@ async fn fetch_blockchain_tip_data(agent: Arc<Icrc1Agent>) -> anyhow::Result<(u6
     }?;
 
     // Extract the last block hash from the hash tree.
-    let last_block_hash = match hash_tree.lookup(&[b"tip_hash"]) {
+    let last_block_hash = match hash_tree.lookup(&[hash_label]) {
         Found(x) => match x {
             MixedHashTree::Leaf(l) => {
                 let mut bytes: Hash = [0u8; 32];
@@ -7,8 +7,12 @@ use ic_ledger_canister_blocks_synchronizer_test_utils::{
 };
 use ic_ledger_canister_core::ledger::{LedgerContext, LedgerTransaction};
 use ic_ledger_core::{
-    approvals::AllowanceTable, balances::BalancesStore, block::BlockType, timestamp::TimeStamp,
-    tokens::CheckedAdd, Tokens,
+    approvals::{AllowanceTable, HeapAllowancesData},
+    balances::BalancesStore,
+    block::BlockType,
+    timestamp::TimeStamp,
+    tokens::CheckedAdd,
+    Tokens,
 };
 use icp_ledger::{apply_operation, AccountIdentifier, ApprovalKey, Block, Operation};
 use rusqlite::params;
@@ -18,7 +22,7 @@ pub(crate) fn sqlite_on_disk_store(path: &Path) -> Blocks {
     Blocks::new_persistent(path).unwrap()
 }
 
-type Approvals = AllowanceTable<ApprovalKey, AccountIdentifier, Tokens>;
+type Approvals = AllowanceTable<HeapAllowancesData<ApprovalKey, AccountIdentifier, Tokens>>;
 
 #[derive(Default)]
 struct TestContext {
@@ -165,13 +169,13 @@ async fn store_account_balances_test() {
         if let Some(acc_str) = from_account {
             let id = AccountIdentifier::from_hex(acc_str.as_str()).unwrap();
             let amount_from = store.get_account_balance(&id, &hb.index).unwrap();
-            let amount_local = *context.balance_book.store.get_balance(&id).unwrap();
+            let amount_local = context.balance_book.store.get_balance(&id).unwrap();
             assert_eq!(amount_from, amount_local);
         }
         if let Some(acc_str) = to_account {
             let id = AccountIdentifier::from_hex(acc_str.as_str()).unwrap();
             let amount_to = store.get_account_balance(&id, &hb.index).unwrap();
-            let amount_local = *context.balance_book.store.get_balance(&id).unwrap();
+            let amount_local = context.balance_book.store.get_balance(&id).unwrap();
             assert_eq!(amount_to, amount_local);
         }
     }
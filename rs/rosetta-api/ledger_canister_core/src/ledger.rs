@@ -1,4 +1,9 @@
-use crate::{archive::ArchiveCanisterWasm, blockchain::Blockchain, range_utils, runtime::Runtime};
+use crate::{
+    archive::ArchiveCanisterWasm,
+    blockchain::{BlockData, Blockchain},
+    range_utils,
+    runtime::Runtime,
+};
 use ic_base_types::CanisterId;
 use ic_canister_log::{log, Sink};
 use ic_ledger_core::approvals::{
@@ -136,6 +141,7 @@ pub trait LedgerAccess {
 pub trait LedgerData: LedgerContext {
     type ArchiveWasm: ArchiveCanisterWasm;
     type Runtime: Runtime;
+    type BlockData: BlockData;
     type Block: BlockType<
         Transaction = Self::Transaction,
         AccountId = Self::AccountId,
@@ -174,8 +180,10 @@ pub trait LedgerData: LedgerContext {
 
     // Ledger data structures
 
-    fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm>;
-    fn blockchain_mut(&mut self) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm>;
+    fn blockchain(&self) -> &Blockchain<Self::Runtime, Self::ArchiveWasm, Self::BlockData>;
+    fn blockchain_mut(
+        &mut self,
+    ) -> &mut Blockchain<Self::Runtime, Self::ArchiveWasm, Self::BlockData>;
 
     fn transactions_by_hash(&self) -> &BTreeMap<HashOf<Self::Transaction>, BlockIndex>;
     fn transactions_by_hash_mut(&mut self) -> &mut BTreeMap<HashOf<Self::Transaction>, BlockIndex>;
@@ -490,15 +498,15 @@ where
 
     // Accumulate up to `trim_quantity` accounts
     for (account, balance) in iter.by_ref().take(num_accounts) {
-        to_trim.push((*balance, account.clone()));
+        to_trim.push((balance, account));
     }
 
     for (account, balance) in iter {
         // If any account's balance is lower than the maximum in our set,
         // include that account, and remove the current maximum
         if let Some((greatest_balance, _)) = to_trim.peek() {
-            if balance < greatest_balance {
-                to_trim.push((*balance, account.clone()));
+            if balance < *greatest_balance {
+                to_trim.push((balance, account));
                 to_trim.pop();
             }
         }
@@ -1,7 +1,6 @@
 use crate::timestamp::TimeStamp;
 use crate::tokens::{TokensType, Zero};
 use serde::{Deserialize, Serialize};
-use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 
@@ -82,8 +81,49 @@ impl<Tokens: Zero> Default for Allowance<Tokens> {
     }
 }
 
+/// The storage backing an [AllowanceTable].
+///
+/// Implementations keep three collections in sync under the control of the
+/// table: the allowances indexed by key, the expiration queue and the arrival
+/// queue.
+pub trait AllowancesData {
+    type AccountId;
+    type Tokens;
+    type Key;
+
+    fn get_allowance(&self, key: &Self::Key) -> Option<Allowance<Self::Tokens>>;
+
+    fn set_allowance(&mut self, key: Self::Key, allowance: Allowance<Self::Tokens>);
+
+    fn remove_allowance(&mut self, key: &Self::Key);
+
+    fn insert_expiry(&mut self, timestamp: TimeStamp, key: Self::Key);
+
+    fn remove_expiry(&mut self, timestamp: TimeStamp, key: Self::Key);
+
+    fn insert_arrival(&mut self, timestamp: TimeStamp, key: Self::Key);
+
+    fn remove_arrival(&mut self, timestamp: TimeStamp, key: Self::Key);
+
+    /// Returns the entry of the expiration queue with the smallest timestamp.
+    fn first_expiry(&self) -> Option<(TimeStamp, Self::Key)>;
+
+    /// Removes and returns the entry of the expiration queue with the smallest timestamp.
+    fn pop_first_expiry(&mut self) -> Option<(TimeStamp, Self::Key)>;
+
+    /// Returns the keys of at most `n` allowances that arrived first.
+    fn oldest_arrivals(&self, n: usize) -> Vec<Self::Key>;
+
+    fn len_allowances(&self) -> usize;
+
+    fn len_expirations(&self) -> usize;
+
+    fn len_arrivals(&self) -> usize;
+}
+
+/// Allowances kept in heap memory.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct AllowanceTable<K, AccountId, Tokens>
+pub struct HeapAllowancesData<K, AccountId, Tokens>
 where
     K: Ord,
 {
@@ -96,37 +136,134 @@ where
     _marker: PhantomData<fn(&AccountId, &AccountId) -> K>,
 }
 
-impl<K: Ord, AccountId, Tokens> Default for AllowanceTable<K, AccountId, Tokens> {
+impl<K: Ord, AccountId, Tokens> Default for HeapAllowancesData<K, AccountId, Tokens> {
     fn default() -> Self {
-        Self::new()
+        Self {
+            allowances: BTreeMap::new(),
+            expiration_queue: BTreeSet::new(),
+            arrival_queue: BTreeSet::new(),
+            _marker: PhantomData,
+        }
     }
 }
 
-impl<K, AccountId, Tokens> AllowanceTable<K, AccountId, Tokens>
+impl<K, AccountId, Tokens> HeapAllowancesData<K, AccountId, Tokens>
 where
     K: Ord,
 {
+    /// Consumes the data and returns the allowances, the expiration queue and
+    /// the arrival queue.
+    #[allow(clippy::type_complexity)]
+    pub fn into_parts(
+        self,
+    ) -> (
+        BTreeMap<K, Allowance<Tokens>>,
+        BTreeSet<(TimeStamp, K)>,
+        BTreeSet<(TimeStamp, K)>,
+    ) {
+        (self.allowances, self.expiration_queue, self.arrival_queue)
+    }
+}
+
+impl<K, AccountId, Tokens> AllowancesData for HeapAllowancesData<K, AccountId, Tokens>
+where
+    K: Ord + Clone,
+    Tokens: Clone,
+{
+    type AccountId = AccountId;
+    type Tokens = Tokens;
+    type Key = K;
+
+    fn get_allowance(&self, key: &K) -> Option<Allowance<Tokens>> {
+        self.allowances.get(key).cloned()
+    }
+
+    fn set_allowance(&mut self, key: K, allowance: Allowance<Tokens>) {
+        self.allowances.insert(key, allowance);
+    }
+
+    fn remove_allowance(&mut self, key: &K) {
+        self.allowances.remove(key);
+    }
+
+    fn insert_expiry(&mut self, timestamp: TimeStamp, key: K) {
+        self.expiration_queue.insert((timestamp, key));
+    }
+
+    fn remove_expiry(&mut self, timestamp: TimeStamp, key: K) {
+        self.expiration_queue.remove(&(timestamp, key));
+    }
+
+    fn insert_arrival(&mut self, timestamp: TimeStamp, key: K) {
+        self.arrival_queue.insert((timestamp, key));
+    }
+
+    fn remove_arrival(&mut self, timestamp: TimeStamp, key: K) {
+        self.arrival_queue.remove(&(timestamp, key));
+    }
+
+    fn first_expiry(&self) -> Option<(TimeStamp, K)> {
+        self.expiration_queue.first().cloned()
+    }
+
+    fn pop_first_expiry(&mut self) -> Option<(TimeStamp, K)> {
+        self.expiration_queue.pop_first()
+    }
+
+    fn oldest_arrivals(&self, n: usize) -> Vec<K> {
+        self.arrival_queue
+            .iter()
+            .take(n)
+            .map(|(_arrived_at, key)| key.clone())
+            .collect()
+    }
+
+    fn len_allowances(&self) -> usize {
+        self.allowances.len()
+    }
+
+    fn len_expirations(&self) -> usize {
+        self.expiration_queue.len()
+    }
+
+    fn len_arrivals(&self) -> usize {
+        self.arrival_queue.len()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct AllowanceTable<AD> {
+    allowances_data: AD,
+}
+
+impl<AD: Default> Default for AllowanceTable<AD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<AD: Default> AllowanceTable<AD> {
     pub fn new() -> Self {
         Self {
-            allowances: BTreeMap::new(),
-            expiration_queue: BTreeSet::new(),
-            arrival_queue: BTreeSet::new(),
-            _marker: PhantomData,
+            allowances_data: AD::default(),
         }
     }
+}
 
+impl<AD: AllowancesData> AllowanceTable<AD> {
     fn check_postconditions(&self) {
         debug_assert!(
-            self.expiration_queue.len() <= self.allowances.len(),
+            self.allowances_data.len_expirations() <= self.allowances_data.len_allowances(),
             "expiration queue length ({}) larger than allowances length ({})",
-            self.expiration_queue.len(),
-            self.allowances.len()
+            self.allowances_data.len_expirations(),
+            self.allowances_data.len_allowances()
         );
         debug_assert!(
-            self.arrival_queue.len() == self.allowances.len(),
+            self.allowances_data.len_arrivals() == self.allowances_data.len_allowances(),
             "arrival_queue length ({}) should be equal to allowances length ({})",
-            self.arrival_queue.len(),
-            self.allowances.len()
+            self.allowances_data.len_arrivals(),
+            self.allowances_data.len_allowances()
         );
     }
 
@@ -137,26 +274,27 @@ where
     }
 }
 
-impl<K, AccountId, Tokens> Approvals for AllowanceTable<K, AccountId, Tokens>
+impl<AD> Approvals for AllowanceTable<AD>
 where
-    K: Ord + for<'a> From<(&'a AccountId, &'a AccountId)> + Clone,
-    K: Into<(AccountId, AccountId)>,
-    AccountId: std::cmp::PartialEq,
-    Tokens: TokensType,
+    AD: AllowancesData,
+    AD::Key: for<'a> From<(&'a AD::AccountId, &'a AD::AccountId)> + Clone,
+    AD::Key: Into<(AD::AccountId, AD::AccountId)>,
+    AD::AccountId: std::cmp::PartialEq,
+    AD::Tokens: TokensType,
 {
-    type AccountId = AccountId;
-    type Tokens = Tokens;
+    type AccountId = AD::AccountId;
+    type Tokens = AD::Tokens;
 
     fn allowance(
         &self,
-        account: &AccountId,
-        spender: &AccountId,
+        account: &AD::AccountId,
+        spender: &AD::AccountId,
         now: TimeStamp,
-    ) -> Allowance<Tokens> {
-        let key = K::from((account, spender));
-        match self.allowances.get(&key) {
+    ) -> Allowance<AD::Tokens> {
+        let key = AD::Key::from((account, spender));
+        match self.allowances_data.get_allowance(&key) {
             Some(allowance) if allowance.expires_at.unwrap_or_else(remote_future) > now => {
-                allowance.clone()
+                allowance
             }
             _ => Allowance::default(),
         }
@@ -164,13 +302,13 @@ where
 
     fn approve(
         &mut self,
-        account: &AccountId,
-        spender: &AccountId,
-        amount: Tokens,
+        account: &AD::AccountId,
+        spender: &AD::AccountId,
+        amount: AD::Tokens,
         expires_at: Option<TimeStamp>,
         now: TimeStamp,
-        expected_allowance: Option<Tokens>,
-    ) -> Result<Tokens, ApproveError<Tokens>> {
+        expected_allowance: Option<AD::Tokens>,
+    ) -> Result<AD::Tokens, ApproveError<AD::Tokens>> {
         self.with_postconditions_check(|table| {
             if account == spender {
                 return Err(ApproveError::SelfApproval);
@@ -180,33 +318,36 @@ where
                 return Err(ApproveError::ExpiredApproval { now });
             }
 
-            let key = K::from((account, spender));
+            let key = AD::Key::from((account, spender));
+            let data = &mut table.allowances_data;
 
-            match table.allowances.entry(key.clone()) {
-                Entry::Vacant(e) => {
-                    if amount == Tokens::zero() {
+            match data.get_allowance(&key) {
+                None => {
+                    if amount == AD::Tokens::zero() {
                         return Ok(amount);
                     }
                     if let Some(expected_allowance) = expected_allowance {
                         if !expected_allowance.is_zero() {
                             return Err(ApproveError::AllowanceChanged {
-                                current_allowance: Tokens::zero(),
+                                current_allowance: AD::Tokens::zero(),
                             });
                         }
                     }
                     if let Some(expires_at) = expires_at {
-                        table.expiration_queue.insert((expires_at, key.clone()));
+                        data.insert_expiry(expires_at, key.clone());
                     }
-                    table.arrival_queue.insert((now, key));
-                    e.insert(Allowance {
-                        amount,
-                        expires_at,
-                        arrived_at: now,
-                    });
+                    data.insert_arrival(now, key.clone());
+                    data.set_allowance(
+                        key,
+                        Allowance {
+                            amount,
+                            expires_at,
+                            arrived_at: now,
+                        },
+                    );
                     Ok(amount)
                 }
-                Entry::Occupied(mut e) => {
-                    let allowance = e.get_mut();
+                Some(allowance) => {
                     if let Some(expected_allowance) = expected_allowance {
                         if expected_allowance != allowance.amount {
                             return Err(ApproveError::AllowanceChanged {
@@ -214,32 +355,33 @@ where
                             });
                         }
                     }
-                    table
-                        .arrival_queue
-                        .remove(&(allowance.arrived_at, key.clone()));
-                    if amount == Tokens::zero() {
-                        if let Some(expires_at) = e.get().expires_at {
-                            table.expiration_queue.remove(&(expires_at, key.clone()));
+                    data.remove_arrival(allowance.arrived_at, key.clone());
+                    if amount == AD::Tokens::zero() {
+                        if let Some(expires_at) = allowance.expires_at {
+                            data.remove_expiry(expires_at, key.clone());
                         }
-                        e.remove();
+                        data.remove_allowance(&key);
                         return Ok(amount);
                     }
-                    table.arrival_queue.insert((now, key.clone()));
-                    allowance.amount = amount;
-                    allowance.arrived_at = now;
-                    let old_expiration = std::mem::replace(&mut allowance.expires_at, expires_at);
-
-                    if expires_at != old_expiration {
-                        if let Some(old_expiration) = old_expiration {
-                            table
-                                .expiration_queue
-                                .remove(&(old_expiration, key.clone()));
+                    data.insert_arrival(now, key.clone());
+
+                    if expires_at != allowance.expires_at {
+                        if let Some(old_expiration) = allowance.expires_at {
+                            data.remove_expiry(old_expiration, key.clone());
                         }
                         if let Some(expires_at) = expires_at {
-                            table.expiration_queue.insert((expires_at, key));
+                            data.insert_expiry(expires_at, key.clone());
                         }
                     }
-                    Ok(e.get().amount)
+                    data.set_allowance(
+                        key,
+                        Allowance {
+                            amount,
+                            expires_at,
+                            arrived_at: now,
+                        },
+                    );
+                    Ok(amount)
                 }
             }
         })
@@ -247,21 +389,21 @@ where
 
     fn use_allowance(
         &mut self,
-        account: &AccountId,
-        spender: &AccountId,
-        amount: Tokens,
+        account: &AD::AccountId,
+        spender: &AD::AccountId,
+        amount: AD::Tokens,
         now: TimeStamp,
-    ) -> Result<Tokens, InsufficientAllowance<Tokens>> {
+    ) -> Result<AD::Tokens, InsufficientAllowance<AD::Tokens>> {
         self.with_postconditions_check(|table| {
-            let key = K::from((account, spender));
+            let key = AD::Key::from((account, spender));
+            let data = &mut table.allowances_data;
 
-            match table.allowances.entry(key.clone()) {
-                Entry::Vacant(_) => Err(InsufficientAllowance(Tokens::zero())),
-                Entry::Occupied(mut e) => {
-                    if e.get().expires_at.unwrap_or_else(remote_future) <= now {
-                        Err(InsufficientAllowance(Tokens::zero()))
+            match data.get_allowance(&key) {
+                None => Err(InsufficientAllowance(AD::Tokens::zero())),
+                Some(mut allowance) => {
+                    if allowance.expires_at.unwrap_or_else(remote_future) <= now {
+                        Err(InsufficientAllowance(AD::Tokens::zero()))
                     } else {
-                        let allowance = e.get_mut();
                         if allowance.amount < amount {
                             return Err(InsufficientAllowance(allowance.amount));
                         }
@@ -271,11 +413,13 @@ where
                             .expect("Underflow when using allowance");
                         let rest = allowance.amount;
                         if rest.is_zero() {
-                            if let Some(expires_at) = e.get().expires_at {
-                                table.expiration_queue.remove(&(expires_at, key.clone()));
+                            if let Some(expires_at) = allowance.expires_at {
+                                data.remove_expiry(expires_at, key.clone());
                             }
-                            table.arrival_queue.remove(&(e.get().arrived_at, key));
-                            e.remove();
+                            data.remove_arrival(allowance.arrived_at, key.clone());
+                            data.remove_allowance(&key);
+                        } else {
+                            data.set_allowance(key, allowance);
                         }
                         Ok(rest)
                     }
@@ -285,28 +429,27 @@ where
     }
 
     fn select_approvals_to_trim(&self, n: usize) -> Vec<(Self::AccountId, Self::AccountId)> {
-        let mut result = vec![];
-        for (_expiration, key) in &self.arrival_queue {
-            if result.len() >= n {
-                break;
-            }
-            result.push(key.clone().into());
-        }
-        result
+        self.allowances_data
+            .oldest_arrivals(n)
+            .into_iter()
+            .map(Into::into)
+            .collect()
     }
 }
 
-impl<K, AccountId, Tokens> PrunableApprovals for AllowanceTable<K, AccountId, Tokens>
+impl<AD> PrunableApprovals for AllowanceTable<AD>
 where
-    K: Ord + Clone,
+    AD: AllowancesData,
+    AD::Key: Clone,
 {
     fn prune(&mut self, now: TimeStamp, limit: usize) -> usize {
         self.with_postconditions_check(|table| {
+            let data = &mut table.allowances_data;
             let mut pruned = 0;
             for _ in 0..limit {
-                match table.expiration_queue.first() {
+                match data.first_expiry() {
                     Some((ts, _key)) => {
-                        if *ts > now {
+                        if ts > now {
                             return pruned;
                         }
                     }
@@ -314,13 +457,11 @@ where
                         return pruned;
                     }
                 }
-                if let Some((_, key)) = table.expiration_queue.pop_first() {
-                    if let Some(allowance) = table.allowances.get(&key) {
+                if let Some((_, key)) = data.pop_first_expiry() {
+                    if let Some(allowance) = data.get_allowance(&key) {
                         if allowance.expires_at.unwrap_or_else(remote_future) <= now {
-                            table
-                                .arrival_queue
-                                .remove(&(allowance.arrived_at, key.clone()));
-                            table.allowances.remove(&key);
+                            data.remove_arrival(allowance.arrived_at, key.clone());
+                            data.remove_allowance(&key);
                             pruned += 1;
                         }
                     }
@@ -331,7 +472,7 @@ where
     }
 
     fn len(&self) -> usize {
-        self.allowances.len()
+        self.allowances_data.len_allowances()
     }
 }
 
@@ -1,4 +1,7 @@
 use core::sync::atomic::Ordering;
+pub use ic_btc_types_internal::{
+    BitcoinAdapterRequestWrapper, BitcoinAdapterResponse, BitcoinAdapterResponseWrapper,
+};
 use ic_config::flag_status::FlagStatus;
 use ic_config::{execution_environment::Config as HypervisorConfig, subnet_config::SubnetConfig};
 use ic_consensus::consensus::payload_builder::PayloadBuilderImpl;
@@ -90,7 +93,7 @@ use ic_types::crypto::{
     CombinedThresholdSigOf, KeyPurpose, Signable, Signed,
 };
 use ic_types::malicious_flags::MaliciousFlags;
-use ic_types::messages::{CallbackId, Certificate, Response};
+use ic_types::messages::{CallbackId, Certificate, RejectContext, Response};
 use ic_types::signature::ThresholdSignature;
 use ic_types::time::GENESIS;
 use ic_types::xnet::CertifiedStreamSlice;
@@ -495,6 +498,34 @@ impl XNetSlicePool for PocketXNetSlicePoolImpl {
     fn garbage_collect_slice(&self, _subnet_id: SubnetId, _stream_position: ExpectedIndices) {}
 }
 
+/// Simulates the services outside of the replicated state machine that
+/// subnets talk to via adapters, i.e. the targets of canister HTTP outcalls
+/// and the Bitcoin network.
+///
+/// A registered handler is consulted for every pending request on each
+/// [`StateMachine::tick`]. Returning `None` leaves the request pending, so
+/// that it is offered to the handler again on the next tick.
+pub trait ExternalServiceHandler: Send + Sync {
+    /// Returns the response to a canister HTTP outcall. The response is
+    /// delivered as is, i.e. the transform function of the request (if any)
+    /// is not applied.
+    fn handle_http_request(
+        &self,
+        _context: &CanisterHttpRequestContext,
+    ) -> Option<Result<CanisterHttpResponsePayload, RejectContext>> {
+        None
+    }
+
+    /// Returns the response of the Bitcoin adapter to a `get_successors` or
+    /// `send_transaction` request.
+    fn handle_bitcoin_request(
+        &self,
+        _request: &BitcoinAdapterRequestWrapper,
+    ) -> Option<BitcoinAdapterResponseWrapper> {
+        None
+    }
+}
+
 /// Represents a replicated state machine detached from the network layer that
 /// can be used to test this part of the stack in isolation.
 pub struct StateMachine {
@@ -521,6 +552,7 @@ pub struct StateMachine {
     time: std::sync::atomic::AtomicU64,
     ecdsa_subnet_public_keys: BTreeMap<EcdsaKeyId, MasterEcdsaPublicKey>,
     replica_logger: ReplicaLogger,
+    external_service_handler: Option<Arc<dyn ExternalServiceHandler>>,
 }
 
 impl Default for StateMachine {
@@ -559,6 +591,7 @@ pub struct StateMachineBuilder {
     features: SubnetFeatures,
     runtime: Option<Arc<Runtime>>,
     registry_data_provider: Arc<ProtoRegistryDataProvider>,
+    external_service_handler: Option<Arc<dyn ExternalServiceHandler>>,
 }
 
 impl StateMachineBuilder {
@@ -587,6 +620,7 @@ impl StateMachineBuilder {
             },
             runtime: None,
             registry_data_provider: Arc::new(ProtoRegistryDataProvider::new()),
+            external_service_handler: None,
         }
     }
 
@@ -719,6 +753,28 @@ impl StateMachineBuilder {
         }
     }
 
+    /// Registers a handler that answers canister HTTP outcalls and Bitcoin
+    /// adapter requests on every tick.
+    pub fn with_external_service_handler(
+        self,
+        external_service_handler: Arc<dyn ExternalServiceHandler>,
+    ) -> Self {
+        Self {
+            external_service_handler: Some(external_service_handler),
+            ..self
+        }
+    }
+
+    fn with_optional_external_service_handler(
+        self,
+        external_service_handler: Option<Arc<dyn ExternalServiceHandler>>,
+    ) -> Self {
+        Self {
+            external_service_handler,
+            ..self
+        }
+    }
+
     pub fn build(self) -> StateMachine {
         let mut routing_table = self.routing_table;
         if routing_table.is_empty() {
@@ -755,6 +811,7 @@ impl StateMachineBuilder {
             }),
             registry_version,
             self.registry_data_provider,
+            self.external_service_handler,
         )
     }
 
@@ -946,6 +1003,7 @@ impl StateMachine {
         runtime: Arc<Runtime>,
         registry_version: RegistryVersion,
         registry_data_provider: Arc<ProtoRegistryDataProvider>,
+        external_service_handler: Option<Arc<dyn ExternalServiceHandler>>,
     ) -> Self {
         let replica_logger = replica_logger();
 
@@ -1132,20 +1190,31 @@ impl StateMachine {
             time: std::sync::atomic::AtomicU64::new(time.as_nanos_since_unix_epoch()),
             ecdsa_subnet_public_keys,
             replica_logger,
+            external_service_handler,
         }
     }
 
-    fn into_components(self) -> (TempDir, u64, Time, bool) {
+    #[allow(clippy::type_complexity)]
+    fn into_components(
+        self,
+    ) -> (
+        TempDir,
+        u64,
+        Time,
+        bool,
+        Option<Arc<dyn ExternalServiceHandler>>,
+    ) {
         (
             self.state_dir,
             self.nonce.into_inner(),
             Time::from_nanos_since_unix_epoch(self.time.into_inner()),
             self.checkpoints_enabled.into_inner(),
+            self.external_service_handler,
         )
     }
 
     pub fn into_state_dir(self) -> TempDir {
-        let (path, _, _, _) = self.into_components();
+        let (path, _, _, _, _) = self.into_components();
         path
     }
 
@@ -1153,13 +1222,15 @@ impl StateMachine {
     pub fn restart_node(self) -> Self {
         // We must drop self before setup_form_dir so that we don't have two StateManagers pointing
         // to the same root.
-        let (state_dir, nonce, time, checkpoints_enabled) = self.into_components();
+        let (state_dir, nonce, time, checkpoints_enabled, external_service_handler) =
+            self.into_components();
 
         StateMachineBuilder::new()
             .with_state_dir(state_dir)
             .with_nonce(nonce)
             .with_time(time)
             .with_checkpoints_enabled(checkpoints_enabled)
+            .with_optional_external_service_handler(external_service_handler)
             .build()
     }
 
@@ -1168,7 +1239,8 @@ impl StateMachine {
     pub fn restart_node_with_config(self, config: StateMachineConfig) -> Self {
         // We must drop self before setup_form_dir so that we don't have two StateManagers pointing
         // to the same root.
-        let (state_dir, nonce, time, checkpoints_enabled) = self.into_components();
+        let (state_dir, nonce, time, checkpoints_enabled, external_service_handler) =
+            self.into_components();
 
         StateMachineBuilder::new()
             .with_state_dir(state_dir)
@@ -1176,6 +1248,7 @@ impl StateMachine {
             .with_time(time)
             .with_config(Some(config))
             .with_checkpoints_enabled(checkpoints_enabled)
+            .with_optional_external_service_handler(external_service_handler)
             .build()
     }
 
@@ -1321,6 +1394,10 @@ impl StateMachine {
 
     /// Triggers a single round of execution without any new inputs.  The state
     /// machine will invoke heartbeats and make progress on pending async calls.
+    ///
+    /// If an [`ExternalServiceHandler`] is registered, the responses it
+    /// provides to pending HTTP outcalls and Bitcoin adapter requests are
+    /// delivered in this round.
     pub fn tick(&self) {
         let mut payload = PayloadBuilder::default();
         let state = self.state_manager.get_latest_state().take();
@@ -1356,9 +1433,67 @@ impl StateMachine {
                 response_payload: MsgPayload::Data(reply.encode()),
             });
         }
+        if let Some(handler) = &self.external_service_handler {
+            payload = self.handle_external_service_requests(&state, handler.as_ref(), payload);
+        }
         self.execute_payload(payload);
     }
 
+    /// Adds the responses of `handler` to the pending HTTP outcalls and
+    /// Bitcoin adapter requests in `state` to `payload`.
+    fn handle_external_service_requests(
+        &self,
+        state: &ReplicatedState,
+        handler: &dyn ExternalServiceHandler,
+        mut payload: PayloadBuilder,
+    ) -> PayloadBuilder {
+        let subnet_call_context_manager = &state.metadata.subnet_call_context_manager;
+
+        for (id, context) in subnet_call_context_manager
+            .canister_http_request_contexts
+            .iter()
+        {
+            payload = match handler.handle_http_request(context) {
+                Some(Ok(response)) => payload.http_response(*id, &response),
+                Some(Err(reject)) => payload.http_response_failure(*id, reject),
+                None => payload,
+            };
+        }
+
+        let bitcoin_requests = subnet_call_context_manager
+            .bitcoin_send_transaction_internal_contexts
+            .iter()
+            .map(|(id, context)| {
+                (
+                    id,
+                    BitcoinAdapterRequestWrapper::SendTransactionRequest(context.payload.clone()),
+                )
+            })
+            .chain(
+                subnet_call_context_manager
+                    .bitcoin_get_successors_contexts
+                    .iter()
+                    .map(|(id, context)| {
+                        (
+                            id,
+                            BitcoinAdapterRequestWrapper::GetSuccessorsRequest(
+                                context.payload.clone(),
+                            ),
+                        )
+                    }),
+            );
+        for (id, request) in bitcoin_requests {
+            if let Some(response) = handler.handle_bitcoin_request(&request) {
+                payload = payload.bitcoin_adapter_response(BitcoinAdapterResponse {
+                    response,
+                    callback_id: id.get(),
+                });
+            }
+        }
+
+        payload
+    }
+
     /// Makes the state machine tick until there are no more messages in the system.
     /// This method is useful if you need to wait for asynchronous canister communication to
     /// complete.
@@ -1403,7 +1538,7 @@ impl StateMachine {
             messages: BatchMessages {
                 signed_ingress_msgs: payload.ingress_messages,
                 certified_stream_slices: payload.xnet_payload.stream_slices,
-                bitcoin_adapter_responses: vec![],
+                bitcoin_adapter_responses: payload.bitcoin_adapter_responses,
                 query_stats: payload.query_stats,
             },
             randomness: Randomness::from(seed),
@@ -2462,6 +2597,7 @@ pub struct PayloadBuilder {
     ingress_messages: Vec<SignedIngress>,
     xnet_payload: XNetPayload,
     consensus_responses: Vec<Response>,
+    bitcoin_adapter_responses: Vec<BitcoinAdapterResponse>,
     query_stats: Option<QueryStatsPayload>,
 }
 
@@ -2473,6 +2609,7 @@ impl Default for PayloadBuilder {
             ingress_messages: Default::default(),
             xnet_payload: Default::default(),
             consensus_responses: Default::default(),
+            bitcoin_adapter_responses: Default::default(),
             query_stats: Default::default(),
         }
         .with_max_expiry_time_from_now(GENESIS.into())
@@ -2568,6 +2705,22 @@ impl PayloadBuilder {
         self
     }
 
+    pub fn http_response_failure(mut self, id: CallbackId, reject: RejectContext) -> Self {
+        self.consensus_responses.push(Response {
+            originator: CanisterId::ic_00(),
+            respondent: CanisterId::ic_00(),
+            originator_reply_callback: id,
+            refund: Cycles::zero(),
+            response_payload: MsgPayload::Reject(reject),
+        });
+        self
+    }
+
+    pub fn bitcoin_adapter_response(mut self, response: BitcoinAdapterResponse) -> Self {
+        self.bitcoin_adapter_responses.push(response);
+        self
+    }
+
     pub fn ingress_ids(&self) -> Vec<MessageId> {
         self.ingress_messages.iter().map(|i| i.id()).collect()
     }
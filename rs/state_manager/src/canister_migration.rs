@@ -0,0 +1,217 @@
+//! Moves the state of a single canister from one subnet's replicated state to
+//! another's, as part of a canister migration.
+use crate::{
+    manifest::manifest_from_path,
+    split::{read_checkpoint, write_checkpoint},
+    StateManagerMetrics, NUMBER_OF_CHECKPOINT_THREADS,
+};
+
+use ic_base_types::CanisterId;
+use ic_config::state_manager::Config;
+use ic_logger::{info, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_replicated_state::{
+    page_map::PageAllocatorFileDescriptor, page_map::TestPageAllocatorFileDescriptorImpl,
+    CanisterStatus,
+};
+use ic_state_layout::{canister_id_from_path, StateLayout};
+use ic_types::state_sync::Manifest;
+use scoped_threadpool::Pool;
+use std::{collections::BTreeMap, path::Path, path::PathBuf, sync::Arc};
+
+#[cfg(test)]
+mod tests;
+
+/// Loads the latest checkpoints under `source_root` and `destination_root`;
+/// moves the state of `canister_id` from the former to the latter; and writes
+/// back both states as new checkpoints, under the same roots.
+///
+/// The canister must be stopped and must not have any messages in its queues.
+/// After writing the new checkpoints, the manifest entries of the canister's
+/// files in the new destination checkpoint are checked against those in the
+/// original source checkpoint, so that any corruption introduced while moving
+/// the state is detected before the checkpoints are used.
+///
+/// If writing or verifying the new checkpoints fails, any new checkpoint is
+/// removed again, leaving the original checkpoints as the latest ones under
+/// both roots.
+pub fn move_canister(
+    source_root: PathBuf,
+    destination_root: PathBuf,
+    canister_id: CanisterId,
+    metrics_registry: &MetricsRegistry,
+    log: ReplicaLogger,
+) -> Result<(), String> {
+    let source_config = Config::new(source_root);
+    let destination_config = Config::new(destination_root);
+    let source_layout = StateLayout::try_new(
+        log.clone(),
+        source_config.state_root.clone(),
+        metrics_registry,
+    )
+    .map_err(|e| e.to_string())?;
+    let destination_layout = StateLayout::try_new(
+        log.clone(),
+        destination_config.state_root.clone(),
+        metrics_registry,
+    )
+    .map_err(|e| e.to_string())?;
+
+    // A thread pool to use for reading and writing checkpoints.
+    let mut thread_pool = Pool::new(NUMBER_OF_CHECKPOINT_THREADS);
+
+    // Create the file descriptor factory that is used to create files for PageMaps.
+    let fd_factory: Arc<dyn PageAllocatorFileDescriptor> =
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new());
+
+    let metrics = StateManagerMetrics::new(metrics_registry);
+    let (source_cp, mut source_state) = read_checkpoint(
+        &source_layout,
+        &mut thread_pool,
+        fd_factory.clone(),
+        &metrics,
+    )?;
+    let (destination_cp, mut destination_state) = read_checkpoint(
+        &destination_layout,
+        &mut thread_pool,
+        fd_factory.clone(),
+        &metrics,
+    )?;
+
+    if destination_state.canister_state(&canister_id).is_some() {
+        return Err(format!(
+            "Canister {} already exists on the destination subnet {}",
+            canister_id, destination_state.metadata.own_subnet_id
+        ));
+    }
+    let canister = source_state
+        .take_canister_state(&canister_id)
+        .ok_or(format!(
+            "Canister {} does not exist on the source subnet {}",
+            canister_id, source_state.metadata.own_subnet_id
+        ))?;
+    if !matches!(canister.system_state.status, CanisterStatus::Stopped) {
+        return Err(format!(
+            "Canister {} must be stopped before it can be migrated, but its status is {:?}",
+            canister_id,
+            canister.status()
+        ));
+    }
+    if canister.has_input() || canister.has_output() {
+        return Err(format!(
+            "Canister {} must not have any enqueued messages before it can be migrated",
+            canister_id
+        ));
+    }
+
+    info!(
+        log,
+        "Moving canister {} from subnet {} to subnet {}",
+        canister_id,
+        source_state.metadata.own_subnet_id,
+        destination_state.metadata.own_subnet_id
+    );
+    destination_state.put_canister_state(canister);
+
+    let result: Result<(), String> = (|| {
+        write_checkpoint(
+            &source_state,
+            source_layout.clone(),
+            &source_cp,
+            &mut thread_pool,
+            fd_factory.clone(),
+            &source_config,
+            &metrics,
+            log.clone(),
+        )?;
+        write_checkpoint(
+            &destination_state,
+            destination_layout.clone(),
+            &destination_cp,
+            &mut thread_pool,
+            fd_factory,
+            &destination_config,
+            &metrics,
+            log.clone(),
+        )?;
+
+        // Verify that the canister's files were moved unmodified.
+        let new_destination_cp = destination_layout
+            .checkpoint(destination_cp.height().increment())
+            .map_err(|e| e.to_string())?;
+        let expected = canister_file_hashes(source_cp.raw_path(), canister_id)?;
+        let actual = canister_file_hashes(new_destination_cp.raw_path(), canister_id)?;
+        if expected != actual {
+            return Err(format!(
+                "The files of canister {} in the destination checkpoint at {} do not match the \
+                 ones in the source checkpoint at {}",
+                canister_id,
+                new_destination_cp.raw_path().display(),
+                source_cp.raw_path().display()
+            ));
+        }
+        info!(
+            log,
+            "Verified {} files of canister {} in the new destination checkpoint",
+            actual.len(),
+            canister_id
+        );
+        Ok(())
+    })();
+
+    // Roll back: remove any checkpoint written above, so that the original
+    // checkpoints are again the latest ones on both sides.
+    if let Err(err) = result {
+        let mut rollback_errors = vec![];
+        for (layout, cp) in [
+            (&source_layout, &source_cp),
+            (&destination_layout, &destination_cp),
+        ] {
+            let new_height = cp.height().increment();
+            match layout.checkpoint_heights() {
+                Ok(heights) if !heights.contains(&new_height) => (),
+                Ok(_) => {
+                    if let Err(e) = layout.force_remove_checkpoint(new_height) {
+                        rollback_errors.push(e.to_string());
+                    }
+                }
+                Err(e) => rollback_errors.push(e.to_string()),
+            }
+        }
+        if rollback_errors.is_empty() {
+            return Err(format!("{}. All new checkpoints were removed.", err));
+        }
+        return Err(format!(
+            "{}. Failed to remove the new checkpoints: {}",
+            err,
+            rollback_errors.join("; ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the manifest file hashes of the files belonging to `canister_id`
+/// in the checkpoint at `checkpoint_path`, keyed by relative path.
+pub fn canister_file_hashes(
+    checkpoint_path: &Path,
+    canister_id: CanisterId,
+) -> Result<BTreeMap<PathBuf, [u8; 32]>, String> {
+    let manifest = manifest_from_path(checkpoint_path).map_err(|e| {
+        format!(
+            "Failed to compute manifest of checkpoint at {}: {}",
+            checkpoint_path.display(),
+            e
+        )
+    })?;
+    Ok(canister_files(&manifest, canister_id))
+}
+
+fn canister_files(manifest: &Manifest, canister_id: CanisterId) -> BTreeMap<PathBuf, [u8; 32]> {
+    manifest
+        .file_table
+        .iter()
+        .filter(|file_info| canister_id_from_path(&file_info.relative_path) == Some(canister_id))
+        .map(|file_info| (file_info.relative_path.clone(), file_info.hash))
+        .collect()
+}
@@ -0,0 +1,209 @@
+use super::*;
+use crate::{checkpoint::make_checkpoint, tip::spawn_tip_thread};
+use assert_matches::assert_matches;
+use ic_base_types::NumSeconds;
+use ic_config::state_manager::lsmt_storage_default;
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{CanisterState, ReplicatedState, SchedulerState, SystemState};
+use ic_test_utilities::{
+    state::new_canister_state,
+    types::ids::{SUBNET_1, SUBNET_2},
+};
+use ic_test_utilities_logger::with_test_replica_logger;
+use ic_test_utilities_tmpdir::tmpdir;
+use ic_types::{malicious_flags::MaliciousFlags, Cycles, Height, SubnetId};
+use tempfile::TempDir;
+
+/// Fictitious controller of all other canisters.
+const CANISTER_0: CanisterId = CanisterId::from_u64(0);
+/// Stopped canister on the source subnet.
+const CANISTER_1: CanisterId = CanisterId::from_u64(1);
+/// Running canister on the source subnet.
+const CANISTER_2: CanisterId = CanisterId::from_u64(2);
+/// Running canister on the destination subnet.
+const CANISTER_3: CanisterId = CanisterId::from_u64(3);
+
+const SOURCE_SUBNET: SubnetId = SUBNET_1;
+const DESTINATION_SUBNET: SubnetId = SUBNET_2;
+
+const HEIGHT: Height = Height::new(42);
+const INITIAL_CYCLES: Cycles = Cycles::new(1 << 36);
+
+#[test]
+fn move_stopped_canister() {
+    with_test_replica_logger(|log| {
+        let (source, destination) = new_state_layouts(log.clone());
+
+        move_canister(
+            source.path().to_path_buf(),
+            destination.path().to_path_buf(),
+            CANISTER_1,
+            &MetricsRegistry::new(),
+            log.clone(),
+        )
+        .unwrap();
+
+        let source_layout = layout(&source, &log);
+        let destination_layout = layout(&destination, &log);
+        assert_eq!(
+            vec![HEIGHT, HEIGHT.increment()],
+            source_layout.checkpoint_heights().unwrap()
+        );
+        assert_eq!(
+            vec![HEIGHT, HEIGHT.increment()],
+            destination_layout.checkpoint_heights().unwrap()
+        );
+        assert_eq!(
+            vec![CANISTER_2],
+            source_layout
+                .checkpoint(HEIGHT.increment())
+                .unwrap()
+                .canister_ids()
+                .unwrap()
+        );
+        assert_eq!(
+            vec![CANISTER_1, CANISTER_3],
+            destination_layout
+                .checkpoint(HEIGHT.increment())
+                .unwrap()
+                .canister_ids()
+                .unwrap()
+        );
+
+        // The canister's files are identical to the ones on the source subnet.
+        let before = canister_file_hashes(
+            source_layout.checkpoint(HEIGHT).unwrap().raw_path(),
+            CANISTER_1,
+        )
+        .unwrap();
+        let after = canister_file_hashes(
+            destination_layout
+                .checkpoint(HEIGHT.increment())
+                .unwrap()
+                .raw_path(),
+            CANISTER_1,
+        )
+        .unwrap();
+        assert!(!before.is_empty());
+        assert_eq!(before, after);
+    })
+}
+
+#[test]
+fn move_running_canister_fails() {
+    with_test_replica_logger(|log| {
+        let (source, destination) = new_state_layouts(log.clone());
+
+        let res = move_canister(
+            source.path().to_path_buf(),
+            destination.path().to_path_buf(),
+            CANISTER_2,
+            &MetricsRegistry::new(),
+            log.clone(),
+        );
+
+        assert_matches!(res, Err(err) if err.contains("must be stopped"));
+        // No new checkpoints were written.
+        assert_eq!(
+            vec![HEIGHT],
+            layout(&source, &log).checkpoint_heights().unwrap()
+        );
+        assert_eq!(
+            vec![HEIGHT],
+            layout(&destination, &log).checkpoint_heights().unwrap()
+        );
+    })
+}
+
+#[test]
+fn move_canister_already_on_destination_fails() {
+    with_test_replica_logger(|log| {
+        let (source, destination) = new_state_layouts(log.clone());
+
+        let res = move_canister(
+            source.path().to_path_buf(),
+            destination.path().to_path_buf(),
+            CANISTER_3,
+            &MetricsRegistry::new(),
+            log,
+        );
+
+        assert_matches!(res, Err(err) if err.contains("already exists"));
+    })
+}
+
+fn layout(tmp: &TempDir, log: &ReplicaLogger) -> StateLayout {
+    StateLayout::try_new(
+        log.clone(),
+        tmp.path().to_path_buf(),
+        &MetricsRegistry::new(),
+    )
+    .unwrap()
+}
+
+/// Creates state layouts for the source subnet, hosting the stopped
+/// `CANISTER_1` and the running `CANISTER_2`; and for the destination subnet,
+/// hosting `CANISTER_3`.
+fn new_state_layouts(log: ReplicaLogger) -> (TempDir, TempDir) {
+    let mut source_state = ReplicatedState::new(SOURCE_SUBNET, SubnetType::Application);
+    source_state.put_canister_state(CanisterState::new(
+        SystemState::new_stopped_for_testing(
+            CANISTER_1,
+            CANISTER_0.get(),
+            INITIAL_CYCLES,
+            NumSeconds::from(100_000),
+        ),
+        None,
+        SchedulerState::default(),
+    ));
+    source_state.put_canister_state(new_canister_state(
+        CANISTER_2,
+        CANISTER_0.get(),
+        INITIAL_CYCLES,
+        NumSeconds::from(100_000),
+    ));
+
+    let mut destination_state = ReplicatedState::new(DESTINATION_SUBNET, SubnetType::Application);
+    destination_state.put_canister_state(new_canister_state(
+        CANISTER_3,
+        CANISTER_0.get(),
+        INITIAL_CYCLES,
+        NumSeconds::from(100_000),
+    ));
+
+    (
+        new_state_layout(source_state, "source", log.clone()),
+        new_state_layout(destination_state, "destination", log),
+    )
+}
+
+/// Writes `state` as the checkpoint at `HEIGHT` into a new state layout under
+/// a temporary directory.
+fn new_state_layout(state: ReplicatedState, name: &str, log: ReplicaLogger) -> TempDir {
+    let tmp = tmpdir(name);
+    let metrics_registry = MetricsRegistry::new();
+    let layout =
+        StateLayout::try_new(log.clone(), tmp.path().to_path_buf(), &metrics_registry).unwrap();
+    let tip_handler = layout.capture_tip_handler();
+    let state_manager_metrics = StateManagerMetrics::new(&metrics_registry);
+    let (_tip_thread, tip_channel) = spawn_tip_thread(
+        log,
+        tip_handler,
+        layout.clone(),
+        lsmt_storage_default(),
+        state_manager_metrics.clone(),
+        MaliciousFlags::default(),
+    );
+
+    make_checkpoint(
+        &state,
+        HEIGHT,
+        &tip_channel,
+        &state_manager_metrics.checkpoint_metrics,
+        &mut Pool::new(NUMBER_OF_CHECKPOINT_THREADS),
+        Arc::new(TestPageAllocatorFileDescriptorImpl::new()),
+    )
+    .unwrap_or_else(|err| panic!("Expected make_checkpoint to succeed, got {:?}", err));
+
+    tmp
+}
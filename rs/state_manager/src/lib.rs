@@ -1,5 +1,6 @@
 // Needs to be `pub` so that the benchmarking code in `state_benches`
 // can access it.
+pub mod canister_migration;
 pub mod checkpoint;
 pub mod labeled_tree_visitor;
 pub mod manifest;
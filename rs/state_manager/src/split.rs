@@ -133,7 +133,7 @@ fn resolve(
 }
 
 /// Reads the `ReplicatedState` from the latest checkpoint under `state_layout`.
-fn read_checkpoint(
+pub(crate) fn read_checkpoint(
     state_layout: &StateLayout,
     thread_pool: &mut Pool,
     fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
@@ -169,7 +169,7 @@ fn read_checkpoint(
 
 /// Writes the given `ReplicatedState` into a new checkpoint under
 /// `state_layout`, based off of `old_cp`.
-fn write_checkpoint(
+pub(crate) fn write_checkpoint(
     state: &ReplicatedState,
     state_layout: StateLayout,
     old_cp: &CheckpointLayout<ReadOnly>,
@@ -8,8 +8,9 @@ use ic_ic00_types::{
     BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetUtxosArgs,
     BitcoinSendTransactionArgs, CanisterIdRecord, CanisterInfoRequest, ClearChunkStoreArgs,
     ComputeInitialEcdsaDealingsArgs, ECDSAPublicKeyArgs, EcdsaKeyId, InstallChunkedCodeArgs,
-    InstallCodeArgsV2, Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs,
-    SignWithECDSAArgs, StoredChunksArgs, UninstallCodeArgs, UpdateSettingsArgs, UploadChunkArgs,
+    InstallCodeArgsV2, Method as Ic00Method, NodeMetricsHistoryArgs, Payload,
+    ProvisionalTopUpCanisterArgs, SignWithECDSAArgs, StoredChunksArgs, SubnetInfoArgs,
+    UninstallCodeArgs, UpdateSettingsArgs, UploadChunkArgs,
 };
 use ic_replicated_state::NetworkTopology;
 
@@ -236,6 +237,14 @@ pub(super) fn resolve_destination(
                     ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::StoredChunks)
                 })
         }
+        Ok(Ic00Method::SubnetInfo) => {
+            let subnet_id = SubnetInfoArgs::decode(payload)?.subnet_id;
+            route_to_known_subnet(network_topology, subnet_id)
+        }
+        Ok(Ic00Method::NodeMetricsHistory) => {
+            let subnet_id = NodeMetricsHistoryArgs::decode(payload)?.subnet_id;
+            route_to_known_subnet(network_topology, subnet_id)
+        }
         Ok(Ic00Method::DeleteChunks) => Err(ResolveDestinationError::UserError(UserError::new(
             ic_error_types::ErrorCode::CanisterRejectedMessage,
             "Chunked upload API is not yet implemented",
@@ -246,6 +255,21 @@ pub(super) fn resolve_destination(
     }
 }
 
+/// Routes a request about `subnet_id` to that subnet, provided it exists.
+fn route_to_known_subnet(
+    network_topology: &NetworkTopology,
+    subnet_id: SubnetId,
+) -> Result<PrincipalId, ResolveDestinationError> {
+    if network_topology.subnets.contains_key(&subnet_id) {
+        Ok(subnet_id.get())
+    } else {
+        Err(ResolveDestinationError::UserError(UserError::new(
+            ic_error_types::ErrorCode::SubnetNotFound,
+            format!("Subnet {} not found", subnet_id),
+        )))
+    }
+}
+
 enum EcdsaSubnetKind {
     OnlyHoldsKey,
     HoldsAndSignWithKey,
@@ -245,7 +245,9 @@ impl SystemStateChanges {
             | Ok(Ic00Method::UploadChunk)
             | Ok(Ic00Method::StoredChunks)
             | Ok(Ic00Method::DeleteChunks)
-            | Ok(Ic00Method::ClearChunkStore) => Ok(None),
+            | Ok(Ic00Method::ClearChunkStore)
+            | Ok(Ic00Method::SubnetInfo)
+            | Ok(Ic00Method::NodeMetricsHistory) => Ok(None),
             Err(_) => Err(UserError::new(
                 ErrorCode::CanisterMethodNotFound,
                 format!("Management canister has no method '{}'", msg.method_name),
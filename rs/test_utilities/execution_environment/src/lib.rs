@@ -27,7 +27,7 @@ use ic_ic00_types::{
 };
 use ic_interfaces::execution_environment::{
     ExecutionMode, IngressHistoryWriter, QueryHandler, RegistryExecutionSettings,
-    SubnetAvailableMemory,
+    SubnetAvailableMemory, SubnetCapacityLimits,
 };
 use ic_interfaces_state_manager::Labeled;
 use ic_logger::{replica_logger::no_op_logger, ReplicaLogger};
@@ -55,7 +55,8 @@ use ic_types::{
         AnonymousQuery, CallbackId, CanisterCall, CanisterMessage, CanisterTask, MessageId,
         RequestOrResponse, Response, UserQuery, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES,
     },
-    CanisterId, Cycles, Height, NumInstructions, NumPages, QueryStatsEpoch, Time, UserId,
+    CanisterId, Cycles, Height, NumInstructions, NumPages, QueryStatsEpoch, ReplicaVersion, Time,
+    UserId,
 };
 use ic_types_test_utils::ids::{node_test_id, subnet_test_id, user_test_id};
 use ic_universal_canister::UNIVERSAL_CANISTER_WASM;
@@ -128,10 +129,14 @@ pub fn generate_network_topology(
 
 pub fn test_registry_settings() -> RegistryExecutionSettings {
     RegistryExecutionSettings {
-        max_number_of_canisters: 0x2000,
+        subnet_capacity_limits: SubnetCapacityLimits {
+            max_number_of_canisters: 0x2000,
+            ..SubnetCapacityLimits::default()
+        },
         provisional_whitelist: ProvisionalWhitelist::Set(BTreeSet::new()),
         max_ecdsa_queue_size: 20,
         subnet_size: SMALL_APP_SUBNET_MAX_SIZE,
+        replica_version: ReplicaVersion::default(),
     }
 }
 
@@ -1748,9 +1753,17 @@ impl ExecutionTestBuilder {
     }
 
     pub fn with_max_number_of_canisters(self, max_number_of_canisters: u64) -> Self {
+        let subnet_capacity_limits = SubnetCapacityLimits {
+            max_number_of_canisters,
+            ..self.registry_settings.subnet_capacity_limits
+        };
+        self.with_subnet_capacity_limits(subnet_capacity_limits)
+    }
+
+    pub fn with_subnet_capacity_limits(self, subnet_capacity_limits: SubnetCapacityLimits) -> Self {
         Self {
             registry_settings: RegistryExecutionSettings {
-                max_number_of_canisters,
+                subnet_capacity_limits,
                 ..self.registry_settings
             },
             ..self
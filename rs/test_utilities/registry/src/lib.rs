@@ -189,6 +189,8 @@ pub fn test_subnet_record() -> SubnetRecord {
         max_instructions_per_install_code: 200_000_000_000,
         features: Some(SubnetFeatures::default()),
         max_number_of_canisters: 0,
+        max_total_memory_bytes: 0,
+        max_reserved_compute_allocation: 0,
         ssh_readonly_access: vec![],
         ssh_backup_access: vec![],
         ecdsa_config: None,
@@ -295,6 +297,19 @@ impl SubnetRecordBuilder {
         self
     }
 
+    pub fn with_max_total_memory_bytes(mut self, max_total_memory_bytes: u64) -> Self {
+        self.record.max_total_memory_bytes = max_total_memory_bytes;
+        self
+    }
+
+    pub fn with_max_reserved_compute_allocation(
+        mut self,
+        max_reserved_compute_allocation: u64,
+    ) -> Self {
+        self.record.max_reserved_compute_allocation = max_reserved_compute_allocation;
+        self
+    }
+
     pub fn build(self) -> SubnetRecord {
         self.record
     }
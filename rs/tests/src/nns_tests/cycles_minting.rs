@@ -724,6 +724,7 @@ pub fn test(env: TestEnv) {
             exchange_rate_canister: None,
             minting_account_id: None,
             last_purged_notification: None,
+            cycles_ledger_canister_id: None,
         }))
         .unwrap();
 
@@ -775,6 +776,7 @@ pub fn test(env: TestEnv) {
             exchange_rate_canister: None,
             minting_account_id: None,
             last_purged_notification: None,
+            cycles_ledger_canister_id: None,
         }))
         .unwrap();
 
@@ -994,6 +996,7 @@ pub fn create_canister_on_specific_subnet_type(env: TestEnv) {
                 None,
                 Some(SubnetSelection::Filter(SubnetFilter {
                     subnet_type: Some(type1),
+                    ..Default::default()
                 })),
             )
             .await
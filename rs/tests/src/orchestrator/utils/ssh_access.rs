@@ -151,6 +151,8 @@ pub(crate) fn get_updatesubnetpayload_with_keys(
         ecdsa_key_signing_enable: None,
         ecdsa_key_signing_disable: None,
         max_number_of_canisters: None,
+        max_total_memory_bytes: None,
+        max_reserved_compute_allocation: None,
         ssh_readonly_access: readonly_keys,
         ssh_backup_access: backup_keys,
     }
@@ -108,6 +108,8 @@ pub(crate) fn empty_subnet_update() -> UpdateSubnetPayload {
         ecdsa_key_signing_enable: None,
         ecdsa_key_signing_disable: None,
         max_number_of_canisters: None,
+        max_total_memory_bytes: None,
+        max_reserved_compute_allocation: None,
         ssh_readonly_access: None,
         ssh_backup_access: None,
     }
@@ -85,6 +85,10 @@ pub enum Method {
     StoredChunks,
     DeleteChunks,
     ClearChunkStore,
+
+    // Information about a subnet and its nodes.
+    SubnetInfo,
+    NodeMetricsHistory,
 }
 
 fn candid_error_to_user_error(err: candid::Error) -> UserError {
@@ -2317,3 +2321,97 @@ impl StoredChunksArgs {
 pub struct StoredChunksReply(pub Vec<serde_bytes::ByteBuf>);
 
 impl Payload<'_> for StoredChunksReply {}
+
+/// Argument of the subnet_info API.
+/// `(record {
+///     subnet_id: principal;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct SubnetInfoArgs {
+    pub subnet_id: SubnetId,
+}
+
+impl Payload<'_> for SubnetInfoArgs {}
+
+/// Struct to be returned by the subnet_info API. Limits of `0` mean that
+/// there is no limit.
+/// `(record {
+///     replica_version: text;
+///     ecdsa_keys: vec ecdsa_key_id;
+///     number_of_canisters: nat64;
+///     max_number_of_canisters: nat64;
+///     memory_taken: nat64;
+///     max_total_memory_bytes: nat64;
+///     compute_allocation_used: nat64;
+///     max_reserved_compute_allocation: nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct SubnetInfoResponse {
+    /// The replica version the subnet runs, according to its registry record.
+    pub replica_version: String,
+    /// The ECDSA keys held by the subnet.
+    pub ecdsa_keys: Vec<EcdsaKeyId>,
+    pub number_of_canisters: u64,
+    pub max_number_of_canisters: u64,
+    /// The memory in bytes used or reserved by the canisters on the subnet.
+    pub memory_taken: u64,
+    pub max_total_memory_bytes: u64,
+    /// The sum of the compute allocations (in percent) of the canisters on
+    /// the subnet.
+    pub compute_allocation_used: u64,
+    pub max_reserved_compute_allocation: u64,
+}
+
+impl Payload<'_> for SubnetInfoResponse {}
+
+impl SubnetInfoResponse {
+    /// Returns `true` if the subnet admits the creation of another canister
+    /// without a memory or compute allocation.
+    pub fn can_create_canister(&self) -> bool {
+        let below = |usage: u64, limit: u64| limit == 0 || usage < limit;
+        below(self.number_of_canisters, self.max_number_of_canisters)
+            && below(self.memory_taken, self.max_total_memory_bytes)
+    }
+}
+
+/// Argument of the node_metrics_history API.
+/// `(record {
+///     subnet_id: principal;
+///     start_at_timestamp_nanos: nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct NodeMetricsHistoryArgs {
+    pub subnet_id: SubnetId,
+    /// Only snapshots taken at or after this time are returned.
+    pub start_at_timestamp_nanos: u64,
+}
+
+impl Payload<'_> for NodeMetricsHistoryArgs {}
+
+/// Block making statistics of a node, as running totals.
+/// `(record {
+///     node_id: principal;
+///     num_blocks_proposed_total: nat64;
+///     num_block_failures_total: nat64;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct NodeMetrics {
+    pub node_id: NodeId,
+    /// The number of finalized blocks made by the node.
+    pub num_blocks_proposed_total: u64,
+    /// The number of rounds in which the node was ranked ahead of the maker
+    /// of the finalized block, i.e. failed to get its own block finalized.
+    pub num_block_failures_total: u64,
+}
+
+/// A daily snapshot of the node metrics of a subnet. The node_metrics_history
+/// API replies with a `vec` of these, oldest first.
+/// `(record {
+///     timestamp_nanos: nat64;
+///     node_metrics: vec node_metrics;
+/// })`
+#[derive(Clone, CandidType, Deserialize, Debug, Eq, PartialEq)]
+pub struct NodeMetricsHistoryResponse {
+    pub timestamp_nanos: u64,
+    pub node_metrics: Vec<NodeMetrics>,
+}
@@ -1,46 +1,29 @@
-use crate::webauthn::validate_webauthn_sig;
 use ic_constants::{MAX_INGRESS_TTL, PERMITTED_DRIFT_AT_VALIDATOR};
 use ic_crypto_interfaces_sig_verification::IngressSigVerifier;
-use ic_crypto_standalone_sig_verifier::{user_public_key_from_bytes, KeyBytesContentType};
+use ic_crypto_standalone_sig_verifier::{
+    verify_envelope_with, DelegationTargets, EnvelopeVerificationError,
+    MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION,
+};
 use ic_crypto_tree_hash::Path;
 use ic_types::crypto::threshold_sig::RootOfTrustProvider;
-use ic_types::crypto::{CanisterSig, CanisterSigOf};
 use ic_types::messages::{ReadState, SignedIngressContent, UserQuery};
 use ic_types::{
-    crypto::{AlgorithmId, BasicSig, BasicSigOf, CryptoError, UserPublicKey},
     messages::{
-        Authentication, Delegation, HasCanisterId, HttpRequest, HttpRequestContent, MessageId,
-        SignedDelegation, UserSignature, WebAuthnSignature,
+        Authentication, HasCanisterId, HttpRequest, HttpRequestContent, MessageId, UserSignature,
     },
-    CanisterId, PrincipalId, Time, UserId,
+    CanisterId, Time, UserId,
 };
-use std::collections::HashSet;
 use std::sync::Arc;
-use std::{collections::BTreeSet, convert::TryFrom, fmt};
+use std::{collections::BTreeSet, fmt};
 use thiserror::Error;
 use AuthenticationError::*;
 use RequestValidationError::*;
 
+pub use ic_crypto_standalone_sig_verifier::AuthenticationError;
+
 #[cfg(test)]
 mod tests;
 
-/// Maximum number of delegations allowed in an `HttpRequest`.
-/// Requests having more delegations will be declared invalid without further verifying whether
-/// the delegation chain is correctly signed.
-/// **Note**: this limit is currently more generous than the one in the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication),
-/// which specifies a maximum of 4 delegations, in order to prevent potentially breaking already deployed applications
-/// since this limit was before (wrongly) not enforced.
-/// This limit will be tightened up once the number of delegations can be observed (via metrics or logs),
-/// see CRP-1961.
-const MAXIMUM_NUMBER_OF_DELEGATIONS: usize = 20;
-
-/// Maximum number of targets (collection of `CanisterId`s) that can be specified in a
-/// single delegation. Requests having a single delegation with more targets will be declared
-/// invalid without any further verification.
-/// **Note**: this limit part of the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#authentication)
-/// and so changing this value might be breaking or result in a deviation from the specification.
-const MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION: usize = 1_000;
-
 /// Maximum number of paths that can be specified in a read state request. Requests having more paths
 /// will be declared invalid without any further verification.
 /// **Note**: this limit part of the [IC specification](https://internetcomputer.org/docs/current/references/ic-interface-spec#http-read-state)
@@ -62,8 +45,8 @@ pub trait HttpRequestVerifier<C, R>: Send + Sync {
     /// The given `request` is valid iff
     /// * The request hasn't expired relative to `current_time`.
     /// * The delegations (if any) are valid:
-    ///     * There are at most `MAXIMUM_NUMBER_OF_DELEGATIONS` delegations.
-    ///     * There are at most `MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION` targets for each delegation.
+    ///     * There are at most [`MAXIMUM_NUMBER_OF_DELEGATIONS`](ic_crypto_standalone_sig_verifier::MAXIMUM_NUMBER_OF_DELEGATIONS) delegations.
+    ///     * There are at most [`MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION`] targets for each delegation.
     ///     * The delegations haven't expired relative to `current_time`.
     ///     * The delegations form a chain of certificates that are correctly signed and do not contain any cycle.
     /// * The request's signature (if any) is correct.
@@ -266,36 +249,17 @@ impl fmt::Display for RequestValidationError {
     }
 }
 
-/// Error in verifying the signature or authentication part of a request.
-#[derive(Debug, PartialEq)]
-pub enum AuthenticationError {
-    InvalidBasicSignature(CryptoError),
-    InvalidCanisterSignature(String),
-    InvalidPublicKey(CryptoError),
-    WebAuthnError(String),
-    DelegationTargetError(String),
-    DelegationTooLongError { length: usize, maximum: usize },
-    DelegationContainsCyclesError { public_key: Vec<u8> },
-}
-
-impl fmt::Display for AuthenticationError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            InvalidBasicSignature(err) => write!(f, "Invalid basic signature: {}", err),
-            InvalidCanisterSignature(err) => write!(f, "Invalid canister signature: {}", err),
-            InvalidPublicKey(err) => write!(f, "Invalid public key: {}", err),
-            WebAuthnError(msg) => write!(f, "{}", msg),
-            DelegationTargetError(msg) => write!(f, "{}", msg),
-            DelegationTooLongError { length, maximum } => write!(
-                f,
-                "Chain of delegations is too long: got {} delegations, but at most {} are allowed",
-                length, maximum
-            ),
-            DelegationContainsCyclesError { public_key } => write!(
-                f,
-                "Chain of delegations contains at least one cycle: first repeating public key encountered {}",
-                hex::encode(public_key)
-            ),
+impl From<EnvelopeVerificationError> for RequestValidationError {
+    fn from(err: EnvelopeVerificationError) -> Self {
+        match err {
+            EnvelopeVerificationError::InvalidDelegationExpiry(msg) => InvalidDelegationExpiry(msg),
+            EnvelopeVerificationError::UserIdDoesNotMatchPublicKey(user_id, pubkey) => {
+                UserIdDoesNotMatchPublicKey(user_id, pubkey)
+            }
+            EnvelopeVerificationError::InvalidSignature(err) => InvalidSignature(err),
+            EnvelopeVerificationError::InvalidDelegation(err) => InvalidDelegation(err),
+            EnvelopeVerificationError::MissingSignature(user_id) => MissingSignature(user_id),
+            EnvelopeVerificationError::AnonymousSignatureNotAllowed => AnonymousSignatureNotAllowed,
         }
     }
 }
@@ -399,6 +363,7 @@ impl CanisterIdSet {
         }
     }
 
+    #[cfg(test)]
     fn intersect(self, other: Self) -> Self {
         CanisterIdSet {
             //the result of set intersection cannot contain
@@ -472,254 +437,7 @@ fn validate_ingress_expiry<C: HttpRequestContent>(
     Ok(())
 }
 
-fn validate_sender_delegation_length(
-    sender_delegation: &Option<Vec<SignedDelegation>>,
-) -> Result<(), RequestValidationError> {
-    match sender_delegation
-        .as_ref()
-        .map(|delegations| delegations.len())
-    {
-        Some(number_of_delegations) if number_of_delegations > MAXIMUM_NUMBER_OF_DELEGATIONS => {
-            Err(InvalidDelegation(DelegationTooLongError {
-                length: number_of_delegations,
-                maximum: MAXIMUM_NUMBER_OF_DELEGATIONS,
-            }))
-        }
-        _ => Ok(()),
-    }
-}
-// Check if any of the sender delegation has expired with respect to the
-// `current_time`, and return an error if so.
-fn validate_sender_delegation_expiry(
-    sender_delegation: &Option<Vec<SignedDelegation>>,
-    current_time: Time,
-) -> Result<(), RequestValidationError> {
-    if let Some(delegations) = &sender_delegation {
-        for delegation in delegations.iter() {
-            let expiry = delegation.delegation().expiration();
-            if delegation.delegation().expiration() < current_time {
-                return Err(InvalidDelegationExpiry(format!(
-                    "Specified sender delegation has expired:\n\
-                     Provided expiry:    {}\n\
-                     Local replica time: {}",
-                    expiry, current_time,
-                )));
-            }
-        }
-    }
-    Ok(())
-}
-
-// Verifies that the user id matches the public key.  Returns an error if not.
-fn validate_user_id(sender_pubkey: &[u8], id: &UserId) -> Result<(), RequestValidationError> {
-    if id.get_ref() == &PrincipalId::new_self_authenticating(sender_pubkey) {
-        Ok(())
-    } else {
-        Err(UserIdDoesNotMatchPublicKey(*id, sender_pubkey.to_vec()))
-    }
-}
-
-// Verifies that the message is properly signed.
-fn validate_signature<R: RootOfTrustProvider>(
-    validator: &dyn IngressSigVerifier,
-    message_id: &MessageId,
-    signature: &UserSignature,
-    current_time: Time,
-    root_of_trust_provider: &R,
-) -> Result<CanisterIdSet, RequestValidationError>
-where
-    R::Error: std::error::Error,
-{
-    validate_sender_delegation_length(&signature.sender_delegation)?;
-    validate_sender_delegation_expiry(&signature.sender_delegation, current_time)?;
-    let empty_vec = Vec::new();
-    let signed_delegations = signature.sender_delegation.as_ref().unwrap_or(&empty_vec);
-
-    let (pubkey, targets) = validate_delegations(
-        validator,
-        signed_delegations.as_slice(),
-        signature.signer_pubkey.clone(),
-        root_of_trust_provider,
-    )?;
-
-    let (pk, pk_type) = public_key_from_bytes(&pubkey).map_err(InvalidSignature)?;
-
-    match pk_type {
-        KeyBytesContentType::EcdsaP256PublicKeyDerWrappedCose
-        | KeyBytesContentType::RsaSha256PublicKeyDerWrappedCose => {
-            let webauthn_sig = WebAuthnSignature::try_from(signature.signature.as_slice())
-                .map_err(WebAuthnError)
-                .map_err(InvalidSignature)?;
-            validate_webauthn_sig(validator, &webauthn_sig, message_id, &pk)
-                .map_err(WebAuthnError)
-                .map_err(InvalidSignature)?;
-            Ok(targets)
-        }
-        KeyBytesContentType::Ed25519PublicKeyDer
-        | KeyBytesContentType::EcdsaP256PublicKeyDer
-        | KeyBytesContentType::EcdsaSecp256k1PublicKeyDer => {
-            let basic_sig = BasicSigOf::from(BasicSig(signature.signature.clone()));
-            validate_signature_plain(validator, message_id, &basic_sig, &pk)
-                .map_err(InvalidSignature)?;
-            Ok(targets)
-        }
-        KeyBytesContentType::IcCanisterSignatureAlgPublicKeyDer => {
-            let canister_sig = CanisterSigOf::from(CanisterSig(signature.signature.clone()));
-            let root_of_trust = root_of_trust_provider
-                .root_of_trust()
-                .map_err(|e| InvalidCanisterSignature(e.to_string()))
-                .map_err(InvalidSignature)?;
-            validator
-                .verify_canister_sig(&canister_sig, message_id, &pk, &root_of_trust)
-                .map_err(|e| InvalidCanisterSignature(e.to_string()))
-                .map_err(InvalidSignature)?;
-            Ok(targets)
-        }
-        KeyBytesContentType::RsaSha256PublicKeyDer => {
-            Err(RequestValidationError::InvalidSignature(
-                AuthenticationError::InvalidBasicSignature(CryptoError::AlgorithmNotSupported {
-                    algorithm: AlgorithmId::RsaSha256,
-                    reason: "RSA signatures are not allowed except in webauthn context".to_owned(),
-                }),
-            ))
-        }
-    }
-}
-
-fn validate_signature_plain(
-    validator: &dyn IngressSigVerifier,
-    message_id: &MessageId,
-    signature: &BasicSigOf<MessageId>,
-    pubkey: &UserPublicKey,
-) -> Result<(), AuthenticationError> {
-    validator
-        .verify_basic_sig_by_public_key(signature, message_id, pubkey)
-        .map_err(InvalidBasicSignature)
-}
-
-// Validate a chain of delegations.
-// See https://sdk.dfinity.org/docs/interface-spec/index.html#_envelope_authentication
-//
-// If the delegations are valid, returns the public key used to sign the
-// request as well as the set of canister IDs that the public key is valid for.
-fn validate_delegations<R: RootOfTrustProvider>(
-    validator: &dyn IngressSigVerifier,
-    signed_delegations: &[SignedDelegation],
-    mut pubkey: Vec<u8>,
-    root_of_trust_provider: &R,
-) -> Result<(Vec<u8>, CanisterIdSet), RequestValidationError>
-where
-    R::Error: std::error::Error,
-{
-    ensure_delegations_does_not_contain_cycles(&pubkey, signed_delegations)?;
-    ensure_delegations_does_not_contain_too_many_targets(signed_delegations)?;
-    // Initially, assume that the delegations target all possible canister IDs.
-    let mut targets = CanisterIdSet::all();
-
-    for sd in signed_delegations {
-        let delegation = sd.delegation();
-        let signature = sd.signature();
-
-        let new_targets = validate_delegation(
-            validator,
-            signature,
-            delegation,
-            &pubkey,
-            root_of_trust_provider,
-        )
-        .map_err(InvalidDelegation)?;
-        // Restrict the canister targets to the ones specified in the delegation.
-        targets = targets.intersect(new_targets);
-        pubkey = delegation.pubkey().to_vec();
-    }
-
-    Ok((pubkey, targets))
-}
-
-fn ensure_delegations_does_not_contain_cycles(
-    sender_public_key: &[u8],
-    signed_delegations: &[SignedDelegation],
-) -> Result<(), RequestValidationError> {
-    let mut observed_public_keys = HashSet::with_capacity(signed_delegations.len() + 1);
-    observed_public_keys.insert(sender_public_key);
-    for delegation in signed_delegations {
-        let current_public_key = delegation.delegation().pubkey();
-        if !observed_public_keys.insert(current_public_key) {
-            return Err(InvalidDelegation(DelegationContainsCyclesError {
-                public_key: current_public_key.clone(),
-            }));
-        }
-    }
-    Ok(())
-}
-
-fn ensure_delegations_does_not_contain_too_many_targets(
-    signed_delegations: &[SignedDelegation],
-) -> Result<(), RequestValidationError> {
-    for delegation in signed_delegations {
-        match delegation.delegation().number_of_targets() {
-            Some(number_of_targets)
-                if number_of_targets > MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION =>
-            {
-                Err(InvalidDelegation(DelegationTargetError(format!(
-                    "expected at most {} targets per delegation, but got {}",
-                    MAXIMUM_NUMBER_OF_TARGETS_PER_DELEGATION, number_of_targets
-                ))))
-            }
-            _ => Ok(()),
-        }?
-    }
-    Ok(())
-}
-
-fn validate_delegation<R: RootOfTrustProvider>(
-    validator: &dyn IngressSigVerifier,
-    signature: &[u8],
-    delegation: &Delegation,
-    pubkey: &[u8],
-    root_of_trust_provider: &R,
-) -> Result<CanisterIdSet, AuthenticationError>
-where
-    R::Error: std::error::Error,
-{
-    let (pk, pk_type) = public_key_from_bytes(pubkey)?;
-
-    match pk_type {
-        KeyBytesContentType::EcdsaP256PublicKeyDerWrappedCose
-        | KeyBytesContentType::RsaSha256PublicKeyDerWrappedCose => {
-            let webauthn_sig = WebAuthnSignature::try_from(signature).map_err(WebAuthnError)?;
-            validate_webauthn_sig(validator, &webauthn_sig, delegation, &pk)
-                .map_err(WebAuthnError)?;
-        }
-        KeyBytesContentType::Ed25519PublicKeyDer
-        | KeyBytesContentType::EcdsaP256PublicKeyDer
-        | KeyBytesContentType::EcdsaSecp256k1PublicKeyDer
-        | KeyBytesContentType::RsaSha256PublicKeyDer => {
-            let basic_sig = BasicSigOf::from(BasicSig(signature.to_vec()));
-            validator
-                .verify_basic_sig_by_public_key(&basic_sig, delegation, &pk)
-                .map_err(InvalidBasicSignature)?;
-        }
-        KeyBytesContentType::IcCanisterSignatureAlgPublicKeyDer => {
-            let canister_sig = CanisterSigOf::from(CanisterSig(signature.to_vec()));
-            let root_of_trust = root_of_trust_provider
-                .root_of_trust()
-                .map_err(|e| InvalidCanisterSignature(e.to_string()))?;
-            validator
-                .verify_canister_sig(&canister_sig, delegation, &pk, &root_of_trust)
-                .map_err(|e| InvalidCanisterSignature(e.to_string()))?;
-        }
-    }
-
-    // Validation succeeded. Return the targets of this delegation.
-    Ok(match delegation.targets().map_err(DelegationTargetError)? {
-        None => CanisterIdSet::all(),
-        Some(targets) => CanisterIdSet::try_from_iter(targets)
-            .map_err(|e| DelegationTargetError(format!("{e}")))?,
-    })
-}
-
-// Verifies correct user and signature.
+// Verifies correct user and signature, see `verify_envelope_with`.
 fn validate_user_id_and_signature<R: RootOfTrustProvider>(
     ingress_signature_verifier: &dyn IngressSigVerifier,
     sender: &UserId,
@@ -731,34 +449,17 @@ fn validate_user_id_and_signature<R: RootOfTrustProvider>(
 where
     R::Error: std::error::Error,
 {
-    match signature {
-        None => {
-            if sender.get().is_anonymous() {
-                return Ok(CanisterIdSet::all());
-            }
-            Err(MissingSignature(*sender))
-        }
-        Some(signature) => {
-            if sender.get().is_anonymous() {
-                Err(AnonymousSignatureNotAllowed)
-            } else {
-                let sender_pubkey = &signature.signer_pubkey;
-                validate_user_id(sender_pubkey, sender).and_then(|()| {
-                    validate_signature(
-                        ingress_signature_verifier,
-                        message_id,
-                        signature,
-                        current_time,
-                        root_of_trust_provider,
-                    )
-                })
-            }
-        }
+    let targets = verify_envelope_with(
+        ingress_signature_verifier,
+        sender,
+        message_id,
+        signature,
+        current_time,
+        root_of_trust_provider,
+    )?;
+    match targets {
+        DelegationTargets::All => Ok(CanisterIdSet::all()),
+        DelegationTargets::Some(targets) => CanisterIdSet::try_from_iter(targets)
+            .map_err(|e| InvalidDelegation(DelegationTargetError(format!("{e}")))),
     }
 }
-
-fn public_key_from_bytes(
-    pubkey: &[u8],
-) -> Result<(UserPublicKey, KeyBytesContentType), AuthenticationError> {
-    user_public_key_from_bytes(pubkey).map_err(InvalidPublicKey)
-}
@@ -7,9 +7,34 @@ use ic_test_utilities::types::ids::{canister_test_id, message_test_id, node_test
 use ic_types::{
     messages::{Delegation, SignedDelegation, UserSignature},
     time::UNIX_EPOCH,
+    PrincipalId,
 };
 use std::time::Duration;
 
+// Validates `signature` on behalf of the sender it authenticates.
+fn validate_signature<R: RootOfTrustProvider>(
+    validator: &dyn IngressSigVerifier,
+    message_id: &MessageId,
+    signature: &UserSignature,
+    current_time: Time,
+    root_of_trust_provider: &R,
+) -> Result<CanisterIdSet, RequestValidationError>
+where
+    R::Error: std::error::Error,
+{
+    let sender = UserId::from(PrincipalId::new_self_authenticating(
+        &signature.signer_pubkey,
+    ));
+    validate_user_id_and_signature(
+        validator,
+        &sender,
+        message_id,
+        Some(signature),
+        current_time,
+        root_of_trust_provider,
+    )
+}
+
 #[test]
 fn plain_authentication_correct_signature_passes() {
     let sig_verifier = temp_crypto_component_with_fake_registry(node_test_id(0));
@@ -1,6 +1,5 @@
 //! A crate for validating an HttpRequest.
 mod ingress_validation;
-mod webauthn;
 
 pub use ingress_validation::{
     AuthenticationError, CanisterIdSet, CanisterIdSetInstantiationError, HttpRequestVerifier,
@@ -1,10 +1,11 @@
 use crate::{
-    collector,
+    collector::{self, RequestInfo},
     content_length::ContentLength,
     message::Message,
     metrics::{FUTURE_STARTED, REQUEST_STARTING},
     plan::{EngineCall, Plan},
-    stats::Fact,
+    scenario::{CallGenerator, Scenario},
+    stats::{Fact, LabeledFact},
     RequestType,
 };
 use backoff::backoff::Backoff;
@@ -20,6 +21,7 @@ use ic_types::{
 use byte_unit::Byte;
 use futures::StreamExt;
 use itertools::Either;
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
@@ -34,7 +36,8 @@ use tokio::{
 use url::{Host, Url};
 
 use crate::metrics::{
-    LATENCY_HISTOGRAM, QUERY_REPLY, UPDATE_SENT, UPDATE_SENT_REPLY, UPDATE_WAIT_REPLY,
+    LATENCY_HISTOGRAM, QUERY_REPLY, SCENARIO_CALL_LATENCY, UPDATE_SENT, UPDATE_SENT_REPLY,
+    UPDATE_WAIT_REPLY,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -195,7 +198,7 @@ impl Engine {
             request_type,
             canister_method_name,
         );
-        let (collector, rec_handle) = collector::start::<Fact>(plan.clone(), periodic_output);
+        let (collector, rec_handle) = collector::start::<Fact>(plan.requests, periodic_output);
 
         let (tx, rx) = channel(requests);
         let time_origin = Instant::now();
@@ -239,6 +242,102 @@ impl Engine {
         rec_handle.join().unwrap()
     }
 
+    /// Execute the calls of the given scenario, at the rates given by its
+    /// phases, and return the facts labeled with the name of the call they
+    /// belong to.
+    /// - `nonce` - Nonce to use for update calls
+    pub async fn execute_scenario(
+        &self,
+        scenario: &Scenario,
+        nonce: String,
+        periodic_output: bool,
+    ) -> Vec<LabeledFact> {
+        let mut rng = StdRng::from_entropy();
+        let arrivals = scenario.arrival_times(&mut rng);
+        let requests = arrivals.len();
+        if requests == 0 {
+            debug!("Not executing any requests");
+            return vec![];
+        }
+        debug!("⏱️  Executing {} requests of scenario", requests);
+
+        let mut call_generator = CallGenerator::new(scenario);
+        let (collector, rec_handle) = collector::start::<LabeledFact>(requests, periodic_output);
+
+        let (tx, rx) = channel(requests);
+        let time_origin = Instant::now();
+
+        let rx_handle = tokio::task::spawn(Engine::evaluate_scenario_requests(
+            rx,
+            collector,
+            call_generator.call_names(),
+        ));
+
+        let mut tx_handles = vec![];
+        for (n, arrival) in arrivals.into_iter().enumerate() {
+            let (index, canister_id, call) = call_generator
+                .next(&mut rng)
+                .unwrap_or_else(|err| panic!("{}", err));
+            sleep_until(tokio::time::Instant::from_std(
+                time_origin + START_OFFSET + arrival,
+            ))
+            .await;
+            let tx = tx.clone();
+            let nonce = nonce.clone();
+            let agent = self.agents[n % self.agents.len()].clone();
+            FUTURE_STARTED.inc();
+            tx_handles.push(tokio::task::spawn(async move {
+                REQUEST_STARTING.inc();
+                // Each request produces exactly one result, which is forwarded
+                // together with the index of the call it belongs to.
+                let (call_tx, mut call_rx) = channel(1);
+                match call {
+                    EngineCall::Read { method, arg } => {
+                        Engine::execute_query(
+                            &agent,
+                            call_tx,
+                            time_origin,
+                            &canister_id,
+                            method,
+                            arg,
+                            n,
+                        )
+                        .await;
+                    }
+                    EngineCall::Write { method, arg } => {
+                        Engine::execute_update(
+                            &agent,
+                            call_tx,
+                            time_origin,
+                            &canister_id,
+                            &nonce,
+                            method,
+                            arg,
+                            n,
+                        )
+                        .await;
+                    }
+                }
+                while let Some(result) = call_rx.recv().await {
+                    tx.send((index, result)).await.unwrap_or_else(|_| {
+                        panic!("Sending a fact failed.");
+                    });
+                }
+            }));
+        }
+        for tx_handle in tx_handles {
+            tx_handle.await.unwrap_or_else(|_| {
+                panic!("Await the tx failed.");
+            });
+        }
+        std::mem::drop(tx);
+        rx_handle.await.unwrap_or_else(|_| {
+            panic!("Await the rx failed.");
+        });
+
+        rec_handle.join().unwrap()
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn execute_request(
         agent: Agent,
@@ -250,12 +349,22 @@ impl Engine {
     ) -> bool {
         match plan.generate_call(n, random_query_payload) {
             EngineCall::Read { method, arg } => {
-                Engine::execute_query(&agent, tx, time_origin, plan, method, arg, n)
+                Engine::execute_query(&agent, tx, time_origin, &plan.canister_id, method, arg, n)
                     .await
                     .is_some()
             }
             EngineCall::Write { method, arg } => {
-                Engine::execute_update(&agent, tx, time_origin, plan, method, arg, n).await
+                Engine::execute_update(
+                    &agent,
+                    tx,
+                    time_origin,
+                    &plan.canister_id,
+                    &plan.nonce,
+                    method,
+                    arg,
+                    n,
+                )
+                .await
             }
         }
     }
@@ -265,13 +374,13 @@ impl Engine {
         agent: &Agent,
         tx: Sender<CallResult>,
         _time_origin: Instant,
-        plan: &Plan,
+        canister_id: &CanisterId,
         method: String,
         arg: Vec<u8>,
         n: usize,
     ) -> Option<u32> {
         let time_query_start = Instant::now();
-        let response = agent.execute_query(&plan.canister_id, &method, arg).await;
+        let response = agent.execute_query(canister_id, &method, arg).await;
         let time_query_end = Instant::now();
         debug!("Sent query ({}). Response was: {:?}", n, response);
 
@@ -336,16 +445,16 @@ impl Engine {
         agent: &Agent,
         tx: Sender<CallResult>,
         time_origin: Instant,
-        plan: &Plan,
+        canister_id: &CanisterId,
+        nonce: &str,
         method: String,
         arg: Vec<u8>,
         n: usize,
     ) -> bool {
-        let nonce = plan.nonce.clone();
         let deadline = Instant::now() + agent.ingress_timeout;
         let (content, request_id) = prepare_update(
             &agent.sender,
-            &plan.canister_id,
+            canister_id,
             method,
             arg,
             format!("inc {} {}", nonce, n).into_bytes(),
@@ -356,7 +465,7 @@ impl Engine {
 
         debug!("Sending signed update. request id: {}.", request_id);
 
-        let path = update_path(plan.canister_id);
+        let path = update_path(*canister_id);
         let time_start = std::time::Instant::now();
         debug!(
             "Sending update() call ({}) after {}ms since origin",
@@ -466,7 +575,7 @@ impl Engine {
                     let wait = Engine::wait_ingress_for_counter_canister(
                         agent,
                         request_id.clone(),
-                        &plan.canister_id,
+                        canister_id,
                         deadline,
                     )
                     .await;
@@ -656,6 +765,49 @@ impl Engine {
         collector.send(Message::Eof).unwrap();
     }
 
+    async fn evaluate_scenario_requests(
+        mut rx: Receiver<(usize, CallResult)>,
+        collector: std::sync::mpsc::Sender<Message<LabeledFact>>,
+        call_names: Vec<String>,
+    ) {
+        let mut failures = HashMap::new();
+
+        while let Some((index, result)) = rx.recv().await {
+            let call = &call_names[index];
+            let status = if result.fact.is_succ() {
+                "success"
+            } else {
+                "failure"
+            };
+            SCENARIO_CALL_LATENCY
+                .with_label_values(&[call, status])
+                .observe(result.fact.latency().as_secs_f64());
+
+            if let Some(err_msg) = result.err_msg {
+                eprintln!("{}", err_msg);
+            }
+
+            let stat = failures.entry(result.call_failure).or_insert(0);
+            *stat += 1;
+
+            collector
+                .send(Message::Body(LabeledFact {
+                    label: call.clone(),
+                    fact: result.fact,
+                }))
+                .expect("Failed to collect facts for scenario calls");
+        }
+
+        collector
+            .send(Message::Log(format!(
+                "submit failures: {} - wait failures: {}",
+                failures.get(&CallFailure::OnSubmit).unwrap_or(&0),
+                failures.get(&CallFailure::OnWait).unwrap_or(&0),
+            )))
+            .unwrap();
+        collector.send(Message::Eof).unwrap();
+    }
+
     /// Given the raw bytes of the "arg" counter canister response (NOT the
     /// top-level response), returns the corresponding counter value.
     fn interpret_counter_canister_response(bytes: &[u8]) -> u32 {
@@ -27,6 +27,7 @@ mod engine;
 mod message;
 mod metrics;
 mod plan;
+mod scenario;
 mod stats;
 
 use ic_canister_client::{HttpClient, HttpClientConfig, Sender as AgentSender};
@@ -34,6 +35,8 @@ use ic_canister_client_sender::ed25519_public_key_to_der;
 use ic_config::metrics::{Config as MetricsConfig, Exporter};
 use ic_test_identity::{get_pair, TEST_IDENTITY_KEYPAIR, TEST_IDENTITY_KEYPAIR_HARD_CODED};
 use ic_types::{messages::Blob, CanisterId, PrincipalId, UserId};
+use scenario::Scenario;
+use serde::Serialize;
 use stats::Summary;
 
 #[cfg(build = "debug")]
@@ -59,7 +62,7 @@ fn get_logger() -> slog::Logger {
     )
 }
 
-fn write_output_json(filename: &str, summaries: &[Summary]) -> io::Result<()> {
+fn write_output_json<T: Serialize + ?Sized>(filename: &str, summaries: &T) -> io::Result<()> {
     use std::fs::File;
 
     let path = PathBuf::from(filename);
@@ -120,7 +123,7 @@ async fn main() {
         .arg(
             Arg::new("rps")
                 .short('r')
-                .required(true)
+                .required_unless_present("scenario")
                 .takes_value(true)
                 .help("Requests per second to generate. Accepts fractional values, e.g. 1.5 rps."),
         )
@@ -268,6 +271,14 @@ async fn main() {
                 .takes_value(true)
                 .help("The number of seconds to wait before timing out ingress messages."),
         )
+        .arg(
+            Arg::new("scenario")
+                .long("scenario")
+                .value_name("FILE")
+                .takes_value(true)
+                .conflicts_with_all(&["rps", "method", "updates", "canister-id", "canister"])
+                .help("Path to a JSON file describing a mix of weighted calls and the phases of the rates at which they are issued, see src/scenario.rs. The summary file then contains the statistics per call."),
+        )
         .arg(
             Arg::new("random-query-payload")
                 .long("random-query-payload")
@@ -317,7 +328,10 @@ async fn main() {
         .unwrap()
         .parse::<usize>()
         .unwrap();
-    let rps = matches.value_of("rps").unwrap().parse::<f64>().unwrap();
+    let rps = matches
+        .value_of("rps")
+        .map(|rps| rps.parse::<f64>().unwrap())
+        .unwrap_or_default();
     let rpms = (rps * 1000f64).floor() as usize;
 
     let principal_id = matches
@@ -473,6 +487,50 @@ async fn main() {
                 eng.wait_for_all_agents_to_be_healthy().await;
             }
 
+            if let Some(scenario_file) = matches.value_of("scenario") {
+                let scenario = Scenario::from_file(Path::new(scenario_file))
+                    .unwrap_or_else(|err| panic!("Failed to load scenario: {}", err));
+                println!(
+                    "Running scenario {} with {} calls in {} phases",
+                    scenario_file,
+                    scenario.calls.len(),
+                    scenario.phases.len()
+                );
+
+                let facts = eng
+                    .execute_scenario(&scenario, nonce.clone(), periodic_output)
+                    .await;
+                std::mem::drop(eng);
+
+                let chart_size = ChartSize::from_str(
+                    matches
+                        .value_of("chart-size")
+                        .expect("chart-size option not specified"),
+                    true,
+                )
+                .expect("Failed to parse chart-size option.");
+                let summaries = Summary::by_label(facts);
+                for (call, summary) in &summaries {
+                    println!("Call {}", call);
+                    println!("{}", summary.clone().with_chart_size(chart_size));
+                }
+
+                if let Some(metrics) = metrics_runtime.take() {
+                    std::mem::drop(metrics);
+                }
+
+                if let Some(filename) = matches.value_of("summary-file") {
+                    if let Err(e) = write_output_json(filename, &summaries) {
+                        println!(
+                            "Error while writing the summaries to file {}: {}",
+                            filename, e
+                        );
+                        exit_code_success = false;
+                    }
+                }
+                return;
+            }
+
             // use id of install canister if no id specified
             let canister_id = if let Some(s) = matches.value_of("canister-id") {
                 let canister_id =
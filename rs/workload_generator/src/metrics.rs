@@ -45,4 +45,10 @@ lazy_static! {
         &["type", "status"]
     )
     .unwrap();
+    pub static ref SCENARIO_CALL_LATENCY: HistogramVec = register_histogram_vec!(
+        "scenario_call_latency_seconds",
+        "The latency of the calls of a scenario as measured from the workload generator in seconds.",
+        &["call", "status"]
+    )
+    .unwrap();
 }
@@ -0,0 +1,599 @@
+//! Declarative workload scenarios.
+//!
+//! A scenario is read from a JSON file and describes a mix of weighted calls,
+//! possibly against different canisters, together with a sequence of phases
+//! that determine the rate at which requests are issued. Requests are issued
+//! open-loop, i.e. independently of whether earlier requests completed.
+//!
+//! ```json
+//! {
+//!   "calls": [
+//!     {
+//!       "name": "balance",
+//!       "type": "query",
+//!       "canister_id": "ryjl3-tyaaa-aaaaa-aaaba-cai",
+//!       "method": "account_balance_dfx",
+//!       "weight": 9,
+//!       "arg": "(record { account = \"${account}\" })",
+//!       "generators": { "account": { "choice": ["aa", "bb"] } }
+//!     },
+//!     {
+//!       "name": "write",
+//!       "type": "update",
+//!       "canister_id": "rrkah-fqaaa-aaaaa-aaaaq-cai",
+//!       "method": "write",
+//!       "weight": 1,
+//!       "arg": "(${n} : nat64)",
+//!       "generators": { "n": { "sequential": { "start": 0 } } }
+//!     }
+//!   ],
+//!   "phases": [
+//!     { "duration_secs": 60, "from_rps": 0, "rps": 100 },
+//!     { "duration_secs": 600, "rps": 100, "arrival": "poisson" }
+//!   ]
+//! }
+//! ```
+use candid::IDLArgs;
+use ic_types::{CanisterId, PrincipalId};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr, time::Duration};
+
+use crate::plan::EngineCall;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CallType {
+    Query,
+    Update,
+}
+
+/// A value that is substituted for a `${name}` placeholder in a Candid
+/// argument template.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Generator {
+    /// A uniformly random integer in `[min, max]`.
+    Random { min: u64, max: u64 },
+    /// An integer that starts at `start` and increases by `step` with every
+    /// call of the same type.
+    Sequential {
+        #[serde(default)]
+        start: u64,
+        #[serde(default = "default_step")]
+        step: u64,
+    },
+    /// One of the given values, picked uniformly at random. The value is
+    /// substituted verbatim, so text values must be quoted in the template.
+    Choice(Vec<String>),
+}
+
+fn default_step() -> u64 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Call {
+    /// The name under which statistics for this call are reported.
+    pub name: String,
+    #[serde(rename = "type")]
+    pub call_type: CallType,
+    pub canister_id: String,
+    pub method: String,
+    pub weight: u32,
+    /// A Candid argument template in textual form, e.g. `("${user}", 42)`.
+    #[serde(default = "default_arg")]
+    pub arg: String,
+    #[serde(default)]
+    pub generators: BTreeMap<String, Generator>,
+}
+
+fn default_arg() -> String {
+    String::from("()")
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Arrival {
+    /// Requests are evenly spaced.
+    #[default]
+    Constant,
+    /// Requests arrive according to a Poisson process.
+    Poisson,
+}
+
+/// A period of time during which requests are issued at a given rate. If
+/// `from_rps` is given, the rate increases (or decreases) linearly from
+/// `from_rps` to `rps` over the duration of the phase.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Phase {
+    pub duration_secs: u64,
+    pub rps: f64,
+    #[serde(default)]
+    pub from_rps: Option<f64>,
+    #[serde(default)]
+    pub arrival: Arrival,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    pub calls: Vec<Call>,
+    pub phases: Vec<Phase>,
+}
+
+impl Scenario {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let scenario: Scenario = serde_json::from_str(&contents)
+            .map_err(|err| format!("Failed to parse {}: {}", path.display(), err))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.calls.is_empty() {
+            return Err("A scenario needs at least one call".to_string());
+        }
+        if self.calls.iter().all(|call| call.weight == 0) {
+            return Err("At least one call needs a positive weight".to_string());
+        }
+        for call in &self.calls {
+            parse_canister_id(&call.canister_id)
+                .map_err(|err| format!("Call {}: {}", call.name, err))?;
+            for (name, generator) in &call.generators {
+                match generator {
+                    Generator::Random { min, max } if min > max => {
+                        return Err(format!(
+                            "Call {}: generator {} has min {} > max {}",
+                            call.name, name, min, max
+                        ))
+                    }
+                    Generator::Choice(values) if values.is_empty() => {
+                        return Err(format!(
+                            "Call {}: generator {} has no values to choose from",
+                            call.name, name
+                        ))
+                    }
+                    _ => (),
+                }
+            }
+        }
+        for phase in &self.phases {
+            let rates = [Some(phase.rps), phase.from_rps];
+            if rates
+                .iter()
+                .flatten()
+                .any(|rps| !rps.is_finite() || *rps < 0.0)
+            {
+                return Err(format!(
+                    "Phase rates must be finite and not negative: {:?}",
+                    phase
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the offsets from the start of the run at which requests are
+    /// to be issued.
+    pub fn arrival_times<R: Rng>(&self, rng: &mut R) -> Vec<Duration> {
+        let mut arrivals = vec![];
+        let mut phase_start = 0.0;
+        for phase in &self.phases {
+            if phase.duration_secs == 0 {
+                continue;
+            }
+            let duration = phase.duration_secs as f64;
+            let from_rps = phase.from_rps.unwrap_or(phase.rps);
+            // The expected number of requests until time `t` within the phase is
+            // `from_rps * t + slope * t^2`. Arrival times are obtained by
+            // inverting this for an increasing sequence of request counts.
+            let slope = (phase.rps - from_rps) / (2.0 * duration);
+            let offset_of = |count: f64| {
+                if slope.abs() < f64::EPSILON {
+                    count / from_rps
+                } else {
+                    (-from_rps + (from_rps * from_rps + 4.0 * slope * count).sqrt()) / (2.0 * slope)
+                }
+            };
+            let mut count = 0.0;
+            loop {
+                count += match phase.arrival {
+                    Arrival::Constant => 1.0,
+                    Arrival::Poisson => -(1.0 - rng.gen::<f64>()).ln(),
+                };
+                let offset = offset_of(count);
+                // The offset is not a number once a decreasing rate reaches zero.
+                if offset.is_nan() || offset >= duration {
+                    break;
+                }
+                arrivals.push(Duration::from_secs_f64(phase_start + offset));
+            }
+            phase_start += duration;
+        }
+        arrivals
+    }
+}
+
+fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
+    PrincipalId::from_str(canister_id)
+        .map_err(|err| format!("Invalid canister id {}: {}", canister_id, err))
+        .and_then(|principal_id| {
+            CanisterId::try_from(principal_id)
+                .map_err(|err| format!("Invalid canister id {}: {}", canister_id, err))
+        })
+}
+
+/// Picks calls of a [`Scenario`] according to their weights and renders their
+/// arguments.
+pub struct CallGenerator {
+    calls: Vec<Call>,
+    canister_ids: Vec<CanisterId>,
+    weights: WeightedIndex<u32>,
+    // The next value of each sequential generator, by call and placeholder.
+    sequences: Vec<BTreeMap<String, u64>>,
+}
+
+impl CallGenerator {
+    pub fn new(scenario: &Scenario) -> Self {
+        let calls = scenario.calls.clone();
+        let canister_ids = calls
+            .iter()
+            .map(|call| parse_canister_id(&call.canister_id).expect("Validated scenario"))
+            .collect();
+        let weights =
+            WeightedIndex::new(calls.iter().map(|call| call.weight)).expect("Validated scenario");
+        let sequences = calls
+            .iter()
+            .map(|call| {
+                call.generators
+                    .iter()
+                    .filter_map(|(name, generator)| match generator {
+                        Generator::Sequential { start, .. } => Some((name.clone(), *start)),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            calls,
+            canister_ids,
+            weights,
+            sequences,
+        }
+    }
+
+    pub fn call_names(&self) -> Vec<String> {
+        self.calls.iter().map(|call| call.name.clone()).collect()
+    }
+
+    /// Picks the next call and returns its index, the canister to call and
+    /// the call itself.
+    pub fn next<R: Rng>(&mut self, rng: &mut R) -> Result<(usize, CanisterId, EngineCall), String> {
+        let index = self.weights.sample(rng);
+        let call = &self.calls[index];
+        let mut arg = call.arg.clone();
+        for (name, generator) in &call.generators {
+            let value = match generator {
+                Generator::Random { min, max } => rng.gen_range(*min..=*max).to_string(),
+                Generator::Sequential { step, .. } => {
+                    let next = self.sequences[index]
+                        .get_mut(name)
+                        .expect("Sequence initialized for every sequential generator");
+                    let value = *next;
+                    *next = next.wrapping_add(*step);
+                    value.to_string()
+                }
+                Generator::Choice(values) => values[rng.gen_range(0..values.len())].clone(),
+            };
+            arg = arg.replace(&format!("${{{}}}", name), &value);
+        }
+        let arg = IDLArgs::from_str(&arg)
+            .and_then(|args| args.to_bytes())
+            .map_err(|err| {
+                format!(
+                    "Call {}: failed to encode Candid argument {}: {}",
+                    call.name, arg, err
+                )
+            })?;
+        let method = call.method.clone();
+        let engine_call = match call.call_type {
+            CallType::Query => EngineCall::Read { method, arg },
+            CallType::Update => EngineCall::Write { method, arg },
+        };
+        Ok((index, self.canister_ids[index], engine_call))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const CANISTER_ID: &str = "rrkah-fqaaa-aaaaa-aaaaq-cai";
+
+    fn scenario(json: serde_json::Value) -> Result<Scenario, String> {
+        let scenario: Scenario = serde_json::from_value(json).map_err(|err| err.to_string())?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    fn single_call(arg: &str, generators: serde_json::Value) -> Scenario {
+        scenario(serde_json::json!({
+            "calls": [{
+                "name": "write",
+                "type": "update",
+                "canister_id": CANISTER_ID,
+                "method": "write",
+                "weight": 1,
+                "arg": arg,
+                "generators": generators,
+            }],
+            "phases": [],
+        }))
+        .unwrap()
+    }
+
+    fn with_phases(phases: serde_json::Value) -> Scenario {
+        let mut scenario = single_call("()", serde_json::json!({}));
+        scenario.phases = serde_json::from_value(phases).unwrap();
+        scenario.validate().unwrap();
+        scenario
+    }
+
+    fn arrival_secs(scenario: &Scenario) -> Vec<f64> {
+        scenario
+            .arrival_times(&mut StdRng::seed_from_u64(0))
+            .iter()
+            .map(Duration::as_secs_f64)
+            .collect()
+    }
+
+    fn encoded_arg(call: EngineCall) -> Vec<u8> {
+        match call {
+            EngineCall::Read { arg, .. } | EngineCall::Write { arg, .. } => arg,
+        }
+    }
+
+    fn candid(text: &str) -> Vec<u8> {
+        IDLArgs::from_str(text).unwrap().to_bytes().unwrap()
+    }
+
+    #[test]
+    fn constant_rate_is_evenly_spaced_within_the_phase() {
+        let arrivals = arrival_secs(&with_phases(serde_json::json!([
+            { "duration_secs": 2, "rps": 10 }
+        ])));
+
+        // The request that would be due exactly at the end of the phase
+        // belongs to the next one.
+        assert_eq!(arrivals.len(), 19);
+        for (i, arrival) in arrivals.iter().enumerate() {
+            assert!((arrival - (i + 1) as f64 / 10.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn increasing_ramp_issues_the_average_rate() {
+        let arrivals = arrival_secs(&with_phases(serde_json::json!([
+            { "duration_secs": 60, "from_rps": 0, "rps": 100 }
+        ])));
+
+        // On average 50 requests per second over 60 seconds.
+        assert!((2999..=3000).contains(&arrivals.len()));
+        assert!(arrivals.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(arrivals.iter().all(|arrival| *arrival < 60.0));
+        // The rate increases, so the gaps between requests shrink.
+        let first_gap = arrivals[1] - arrivals[0];
+        let last_gap = arrivals[arrivals.len() - 1] - arrivals[arrivals.len() - 2];
+        assert!(first_gap > last_gap);
+    }
+
+    #[test]
+    fn decreasing_ramp_stops_when_the_rate_reaches_zero() {
+        let arrivals = arrival_secs(&with_phases(serde_json::json!([
+            { "duration_secs": 2, "from_rps": 10, "rps": 0 }
+        ])));
+
+        // On average 5 requests per second over 2 seconds.
+        assert!((9..=10).contains(&arrivals.len()));
+        assert!(arrivals.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(arrivals.iter().all(|arrival| *arrival <= 2.0));
+    }
+
+    #[test]
+    fn zero_rate_and_empty_phases_issue_no_requests() {
+        let arrivals = arrival_secs(&with_phases(serde_json::json!([
+            { "duration_secs": 10, "rps": 0 },
+            { "duration_secs": 10, "from_rps": 0, "rps": 0 },
+            { "duration_secs": 0, "rps": 100 }
+        ])));
+
+        assert!(arrivals.is_empty());
+    }
+
+    #[test]
+    fn phases_follow_each_other() {
+        let arrivals = arrival_secs(&with_phases(serde_json::json!([
+            { "duration_secs": 1, "rps": 2 },
+            { "duration_secs": 5, "rps": 0 },
+            { "duration_secs": 1, "rps": 2 }
+        ])));
+
+        assert_eq!(arrivals.len(), 2);
+        assert!((arrivals[0] - 0.5).abs() < 1e-9);
+        assert!((arrivals[1] - 6.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn poisson_arrivals_stay_within_phases() {
+        let arrivals = arrival_secs(&with_phases(serde_json::json!([
+            { "duration_secs": 10, "rps": 100, "arrival": "poisson" },
+            { "duration_secs": 10, "rps": 0, "arrival": "poisson" }
+        ])));
+
+        assert!(arrivals.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(arrivals.iter().all(|arrival| *arrival < 10.0));
+        assert!((800..=1200).contains(&arrivals.len()));
+    }
+
+    #[test]
+    fn placeholders_are_substituted() {
+        let scenario = single_call(
+            "(${n} : nat64, \"${name}\", ${fixed} : nat64)",
+            serde_json::json!({
+                "n": { "sequential": { "start": 5, "step": 2 } },
+                "name": { "choice": ["alice"] },
+                "fixed": { "random": { "min": 3, "max": 3 } },
+            }),
+        );
+        let mut generator = CallGenerator::new(&scenario);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for n in [5, 7, 9] {
+            let (index, canister_id, call) = generator.next(&mut rng).unwrap();
+            assert_eq!(index, 0);
+            assert_eq!(canister_id, parse_canister_id(CANISTER_ID).unwrap());
+            assert!(matches!(&call, EngineCall::Write { method, .. } if method == "write"));
+            assert_eq!(
+                encoded_arg(call),
+                candid(&format!("({} : nat64, \"alice\", 3 : nat64)", n))
+            );
+        }
+    }
+
+    #[test]
+    fn sequential_generators_start_at_zero_by_default() {
+        let scenario = single_call(
+            "(${n} : nat64)",
+            serde_json::json!({ "n": { "sequential": {} } }),
+        );
+        let mut generator = CallGenerator::new(&scenario);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for n in 0..3 {
+            let (_, _, call) = generator.next(&mut rng).unwrap();
+            assert_eq!(encoded_arg(call), candid(&format!("({} : nat64)", n)));
+        }
+    }
+
+    #[test]
+    fn invalid_rendered_arguments_are_reported() {
+        let scenario = single_call("(${missing} : nat64)", serde_json::json!({}));
+        let mut generator = CallGenerator::new(&scenario);
+
+        let err = generator.next(&mut StdRng::seed_from_u64(0)).err().unwrap();
+
+        assert!(err.contains("failed to encode Candid argument"), "{}", err);
+    }
+
+    #[test]
+    fn calls_with_zero_weight_are_never_picked() {
+        let scenario = scenario(serde_json::json!({
+            "calls": [
+                { "name": "never", "type": "query", "canister_id": CANISTER_ID, "method": "a", "weight": 0 },
+                { "name": "always", "type": "query", "canister_id": CANISTER_ID, "method": "b", "weight": 1 }
+            ],
+            "phases": [],
+        }))
+        .unwrap();
+        let mut generator = CallGenerator::new(&scenario);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let (index, _, call) = generator.next(&mut rng).unwrap();
+            assert_eq!(index, 1);
+            assert!(matches!(call, EngineCall::Read { method, .. } if method == "b"));
+        }
+    }
+
+    #[test]
+    fn invalid_scenarios_are_rejected() {
+        let call = |weight: u32, canister_id: &str, generators: serde_json::Value| {
+            serde_json::json!({
+                "name": "call",
+                "type": "query",
+                "canister_id": canister_id,
+                "method": "m",
+                "weight": weight,
+                "generators": generators,
+            })
+        };
+        let cases = [
+            (
+                serde_json::json!({ "calls": [], "phases": [] }),
+                "at least one call",
+            ),
+            (
+                serde_json::json!({ "calls": [call(0, CANISTER_ID, serde_json::json!({}))], "phases": [] }),
+                "positive weight",
+            ),
+            (
+                serde_json::json!({ "calls": [call(1, "not-a-canister", serde_json::json!({}))], "phases": [] }),
+                "Invalid canister id",
+            ),
+            (
+                serde_json::json!({
+                    "calls": [call(1, CANISTER_ID, serde_json::json!({ "x": { "random": { "min": 2, "max": 1 } } }))],
+                    "phases": [],
+                }),
+                "min 2 > max 1",
+            ),
+            (
+                serde_json::json!({
+                    "calls": [call(1, CANISTER_ID, serde_json::json!({ "x": { "choice": [] } }))],
+                    "phases": [],
+                }),
+                "no values to choose from",
+            ),
+            (
+                serde_json::json!({
+                    "calls": [call(1, CANISTER_ID, serde_json::json!({}))],
+                    "phases": [{ "duration_secs": 1, "rps": -1 }],
+                }),
+                "must be finite and not negative",
+            ),
+            (
+                serde_json::json!({
+                    "calls": [call(1, CANISTER_ID, serde_json::json!({}))],
+                    "phases": [{ "duration_secs": 1, "from_rps": -1, "rps": 1 }],
+                }),
+                "must be finite and not negative",
+            ),
+            (
+                serde_json::json!({
+                    "calls": [call(1, CANISTER_ID, serde_json::json!({}))],
+                    "phases": [{ "duration_secs": 1, "rps": 1, "arrival": "bursty" }],
+                }),
+                "unknown variant",
+            ),
+        ];
+
+        for (json, expected_error) in cases {
+            let err = scenario(json.clone()).err().unwrap_or_else(|| {
+                panic!("Scenario {} should be rejected", json);
+            });
+            assert!(
+                err.contains(expected_error),
+                "Expected an error containing {:?}, got {:?}",
+                expected_error,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn rates_that_are_not_finite_are_rejected() {
+        let mut scenario = single_call("()", serde_json::json!({}));
+        for rps in [f64::NAN, f64::INFINITY] {
+            scenario.phases = vec![Phase {
+                duration_secs: 1,
+                rps,
+                from_rps: None,
+                arrival: Arrival::Constant,
+            }];
+            assert!(scenario.validate().is_err());
+        }
+    }
+}
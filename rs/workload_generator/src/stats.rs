@@ -1,6 +1,11 @@
 use crate::{chart::Chart, collector::RequestInfo, content_length::ContentLength, ChartSize};
 use std::time::Instant;
-use std::{cmp, collections::HashMap, fmt, time::Duration};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    time::Duration,
+};
 
 use serde::Serialize;
 
@@ -66,6 +71,10 @@ impl Fact {
             success,
         }
     }
+
+    pub fn latency(&self) -> Duration {
+        self.time_request_end - self.time_request_start
+    }
 }
 impl RequestInfo for Fact {
     fn is_succ(&self) -> bool {
@@ -73,6 +82,20 @@ impl RequestInfo for Fact {
     }
 }
 
+/// A fact together with a label, e.g. the name of the call of a scenario
+/// that the request was issued for.
+#[derive(Debug)]
+pub struct LabeledFact {
+    pub label: String,
+    pub fact: Fact,
+}
+
+impl RequestInfo for LabeledFact {
+    fn is_succ(&self) -> bool {
+        self.fact.is_succ()
+    }
+}
+
 struct DurationStats {
     sorted: Vec<Duration>,
 }
@@ -215,6 +238,18 @@ impl Summary {
         }
     }
 
+    /// Calculates the statistics separately for the facts of each label.
+    pub fn by_label(facts: Vec<LabeledFact>) -> BTreeMap<String, Summary> {
+        let mut facts_by_label: BTreeMap<String, Vec<Fact>> = BTreeMap::new();
+        for LabeledFact { label, fact } in facts {
+            facts_by_label.entry(label).or_default().push(fact);
+        }
+        facts_by_label
+            .into_iter()
+            .map(|(label, facts)| (label, Summary::from_facts(&facts)))
+            .collect()
+    }
+
     #[allow(dead_code)]
     pub fn content_length(self) -> ContentLength {
         self.content_length